@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use bvm::packaging::classpath::{ClassPath, ClassPathEntry};
+use bvm::vm::value::JValue;
+use bvm::vm::Vm;
+
+/// A VM running the classes of `res/embedding`, which need no JDK.
+fn embedding_vm() -> Vm {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+    let mut class_path = ClassPath::default();
+    class_path.push(ClassPathEntry::open(root).unwrap());
+    Vm::builder().class_path(class_path).build().unwrap()
+}
+
+/// Microkernels, reported in executed instructions per second: arithmetic
+/// loops, calls which do little else, array stores and string allocation.
+fn kernels(c: &mut Criterion) {
+    let kernels = vec![
+        ("collatz", "Arithmetic", "collatz", 10_000),
+        ("fib", "Arithmetic", "fib", 20),
+        ("sieve", "Kernels", "sieve", 100_000),
+        ("concat", "Kernels", "concat", 1_000),
+    ];
+
+    let mut vm = embedding_vm();
+    let mut group = c.benchmark_group("interpreter");
+    for (label, class, name, argument) in kernels {
+        let arguments = [JValue::Int(argument)];
+        let executed = vm.executed_instructions();
+        vm.invoke_static(class, name, "(I)I", &arguments).unwrap();
+        group.throughput(Throughput::Elements(vm.executed_instructions() - executed));
+        group.bench_function(label, |b| {
+            b.iter(|| vm.invoke_static(class, name, "(I)I", &arguments))
+        });
+    }
+    group.finish();
+}
+
+/// `invokevirtual` and `invokeinterface` call sites seeing one, three and
+/// six receiver classes.
+fn dispatch(c: &mut Criterion) {
+    let mut vm = embedding_vm();
+    let mut group = c.benchmark_group("dispatch");
+    for name in ["monomorphic", "polymorphic", "megamorphic"] {
+        group.bench_function(name, |b| {
+            b.iter(|| vm.invoke_static("Dispatch", name, "(I)I", &[JValue::Int(10_000)]))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernels, dispatch);
+criterion_main!(benches);
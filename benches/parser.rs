@@ -0,0 +1,90 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use bvm::class::Class;
+use bvm::packaging::classpath::ClassPathEntry;
+
+/// Copies of `res/embedding` packed into the benchmarked jar.
+const JAR_COPIES: usize = 100;
+
+fn embedding_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding")
+}
+
+/// The class files of `res/embedding`, sorted by name.
+fn embedding_classes() -> Vec<(String, Vec<u8>)> {
+    let mut classes: Vec<_> = fs::read_dir(embedding_root())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "class")
+        })
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect();
+    classes.sort();
+    classes
+}
+
+/// Writes a jar holding [JAR_COPIES] copies of the classes, each in its own
+/// package, returning the number of classes.
+fn write_jar(path: &Path, classes: &[(String, Vec<u8>)]) -> u64 {
+    let mut jar = ZipWriter::new(File::create(path).unwrap());
+    for copy in 0..JAR_COPIES {
+        for (name, bytes) in classes {
+            jar.start_file(format!("copy{}/{}", copy, name), FileOptions::default())
+                .unwrap();
+            jar.write_all(bytes).unwrap();
+        }
+    }
+    jar.finish().unwrap();
+    (JAR_COPIES * classes.len()) as u64
+}
+
+/// Parsing single classes, from the smallest to the largest fixture.
+fn parse(c: &mut Criterion) {
+    let classes = embedding_classes();
+    let smallest = classes.iter().min_by_key(|(_, bytes)| bytes.len()).unwrap();
+    let largest = classes.iter().max_by_key(|(_, bytes)| bytes.len()).unwrap();
+
+    let mut group = c.benchmark_group("parse");
+    for (label, (_, bytes)) in [("smallest", smallest), ("largest", largest)] {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(label, |b| b.iter(|| Class::parse_bytes(bytes).unwrap()));
+    }
+    group.finish();
+}
+
+/// Opening a jar, listing its classes, then reading and parsing all of them.
+fn index_jar(c: &mut Criterion) {
+    let path = env::temp_dir().join(format!("bvm-bench-{}.jar", std::process::id()));
+    let count = write_jar(&path, &embedding_classes());
+
+    let mut group = c.benchmark_group("jar");
+    group.throughput(Throughput::Elements(count));
+    group.sample_size(20);
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            let entry = ClassPathEntry::open(&path).unwrap();
+            for name in entry.class_names().unwrap() {
+                let bytes = entry.read_class(&name).unwrap().unwrap();
+                Class::parse_bytes(&bytes).unwrap();
+            }
+        })
+    });
+    group.finish();
+
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, parse, index_jar);
+criterion_main!(benches);
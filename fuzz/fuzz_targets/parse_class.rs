@@ -0,0 +1,40 @@
+//! Parses arbitrary bytes as a class file, then decodes what the VM would
+//! decode when linking it: the descriptors and the bytecode of the members.
+//!
+//! Run with `cargo fuzz run parse_class`, seeding the corpus from the class
+//! files of `res/embedding`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use bvm::class::attributes::Attribute;
+use bvm::class::descriptor::{FieldType, MethodDescriptor};
+use bvm::class::instruction;
+use bvm::class::Class;
+
+fuzz_target!(|bytes: &[u8]| {
+    let class = match Class::parse_bytes(bytes) {
+        Ok(class) => class,
+        Err(_) => return,
+    };
+    let pool = &class.constant_pool;
+
+    let _ = class.name();
+    let _ = class.super_class_name();
+    for field in &class.fields {
+        if let Ok(descriptor) = pool.get_utf8(field.descriptor_index) {
+            let _ = FieldType::parse(descriptor);
+        }
+    }
+    for method in &class.methods {
+        if let Ok(descriptor) = pool.get_utf8(method.descriptor_index) {
+            let _ = MethodDescriptor::parse(descriptor);
+        }
+        for attribute in &method.attributes {
+            if let Attribute::Code(code) = attribute {
+                instruction::decode(&code.code);
+            }
+        }
+    }
+});
@@ -1,5 +0,0 @@
-public class Main {
-    public static void main(String[] args) {
-    	System.out.println(Main.class.getClassLoader());
-    }
-}
@@ -0,0 +1,29 @@
+public class Access {
+    private int secret = 42;
+
+    static class Inner {
+        private int hidden = 7;
+
+        int peek(Access access) {
+            return access.secret;
+        }
+    }
+
+    static class Target {
+        int value = 1;
+        static int shared = 2;
+    }
+
+    public static int nestmates() {
+        Inner inner = new Inner();
+        return inner.peek(new Access()) + inner.hidden;
+    }
+
+    public static int value() {
+        return new Target().value;
+    }
+
+    public static int shared() {
+        return Target.shared;
+    }
+}
@@ -0,0 +1,21 @@
+public class Arithmetic {
+    public static int collatz(int limit) {
+        int longest = 0;
+        for (int start = 1; start < limit; start++) {
+            long n = start;
+            int steps = 0;
+            while (n != 1) {
+                n = (n & 1) == 0 ? n >> 1 : 3 * n + 1;
+                steps++;
+            }
+            if (steps > longest) {
+                longest = steps;
+            }
+        }
+        return longest;
+    }
+
+    public static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+}
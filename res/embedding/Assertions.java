@@ -0,0 +1,38 @@
+public class Assertions {
+    public static String check(int value) {
+        try {
+            assert value > 0 : "not positive: " + value;
+            return "passed";
+        } catch (AssertionError e) {
+            return e.getMessage();
+        }
+    }
+
+    public static String nested() {
+        return Nested.check();
+    }
+
+    public static String primitive() {
+        try {
+            assert false : 42L;
+            return "passed";
+        } catch (AssertionError e) {
+            return e.getMessage();
+        }
+    }
+
+    public static boolean desired() {
+        return Assertions.class.desiredAssertionStatus();
+    }
+
+    static class Nested {
+        static String check() {
+            try {
+                assert false;
+                return "passed";
+            } catch (AssertionError e) {
+                return "failed";
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+import java.util.concurrent.atomic.AtomicBoolean;
+import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicLong;
+import java.util.concurrent.atomic.AtomicReference;
+
+public class Atomics {
+    public static int counter() {
+        AtomicInteger counter = new AtomicInteger(40);
+        counter.incrementAndGet();
+        counter.getAndIncrement();
+        counter.addAndGet(-3);
+        boolean swapped = counter.compareAndSet(39, 100);
+        boolean missed = counter.compareAndSet(39, 0);
+        Number number = counter;
+        return swapped && !missed ? number.byteValue() : -1;
+    }
+
+    public static long wrapping() {
+        AtomicLong value = new AtomicLong(Long.MAX_VALUE);
+        return value.incrementAndGet();
+    }
+
+    public static String reference(String initial, String next) {
+        AtomicReference<String> reference = new AtomicReference<>(initial);
+        // Compared by identity
+        reference.compareAndSet(new String(initial), "copy");
+        reference.compareAndSet(initial, next);
+        return reference.getAndSet(null) + "," + reference;
+    }
+
+    public static boolean flag() {
+        AtomicBoolean flag = new AtomicBoolean();
+        return flag.compareAndSet(false, true) && flag.getAndSet(false) && !flag.get();
+    }
+}
@@ -0,0 +1,39 @@
+public class Calculator {
+    private final int base;
+
+    public Calculator(int base) {
+        this.base = base;
+    }
+
+    public int offset(int value) {
+        return base + value;
+    }
+
+    public static int add(int a, int b) {
+        return a + b;
+    }
+
+    public static long fibonacci(int n) {
+        return n < 2 ? n : fibonacci(n - 1) + fibonacci(n - 2);
+    }
+
+    public static double half(double value) {
+        return value / 2;
+    }
+
+    public static int divide(int a, int b) {
+        return a / b;
+    }
+
+    public static int safeDivide(int a, int b) {
+        try {
+            return a / b;
+        } catch (ArithmeticException e) {
+            return 0;
+        }
+    }
+
+    public static String greet(String name) {
+        return "Hello, ".concat(name);
+    }
+}
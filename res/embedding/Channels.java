@@ -0,0 +1,96 @@
+import java.io.IOException;
+import java.nio.ByteBuffer;
+import java.nio.MappedByteBuffer;
+import java.nio.ReadOnlyBufferException;
+import java.nio.channels.ClosedChannelException;
+import java.nio.channels.FileChannel;
+import java.nio.file.Path;
+import java.nio.file.Paths;
+import java.nio.file.StandardOpenOption;
+
+public class Channels {
+    private static String readAll(FileChannel channel) throws IOException {
+        ByteBuffer buffer = ByteBuffer.allocate(3);
+        String text = "";
+        while (channel.read(buffer) != -1) {
+            buffer.flip();
+            while (buffer.hasRemaining()) {
+                text += (char) buffer.get();
+            }
+            buffer.clear();
+        }
+        return text;
+    }
+
+    // Writes the text to the file and reads it back, with the positions and
+    // the size on the way
+    public static String roundTrip(String file, String text) throws IOException {
+        Path path = Paths.get(file);
+        FileChannel channel = FileChannel.open(path, StandardOpenOption.CREATE,
+            StandardOpenOption.WRITE, StandardOpenOption.TRUNCATE_EXISTING);
+        int written = channel.write(ByteBuffer.wrap(text.getBytes()));
+        long position = channel.position();
+        channel.close();
+
+        channel = FileChannel.open(path);
+        String read = readAll(channel);
+        String result = read + "|" + written + "|" + position + "|" + channel.size()
+            + "|" + channel.position();
+        channel.position(2);
+        ByteBuffer rest = ByteBuffer.allocate(16);
+        channel.read(rest);
+        channel.close();
+        return result + "|" + new String(rest.array(), 0, rest.position());
+    }
+
+    // Upper-cases the file through a mapping, appending an int after its end
+    public static String mapped(String file) throws IOException {
+        Path path = Paths.get(file);
+        FileChannel channel = FileChannel.open(path, StandardOpenOption.READ,
+            StandardOpenOption.WRITE);
+        long size = channel.size();
+        MappedByteBuffer buffer = channel.map(FileChannel.MapMode.READ_WRITE, 0, size);
+        for (int i = 0; i < buffer.limit(); i++) {
+            byte b = buffer.get(i);
+            if (b >= 'a' && b <= 'z') {
+                buffer.put(i, (byte) (b - 32));
+            }
+        }
+        buffer.force();
+        channel.map(FileChannel.MapMode.READ_WRITE, size, 4).putInt(0x2d2d2d21);
+        channel.close();
+
+        channel = FileChannel.open(path);
+        MappedByteBuffer view = channel.map(FileChannel.MapMode.READ_ONLY, 0, channel.size());
+        byte[] bytes = new byte[view.remaining()];
+        view.get(bytes);
+        String result = new String(bytes) + "|" + view.isReadOnly() + "|" + view.isDirect();
+        try {
+            view.put(0, (byte) 0);
+        } catch (ReadOnlyBufferException e) {
+            result += "|read-only";
+        }
+        channel.close();
+        return result;
+    }
+
+    public static String closed(String file) throws IOException {
+        FileChannel channel = FileChannel.open(Paths.get(file));
+        channel.close();
+        try {
+            channel.read(ByteBuffer.allocate(1));
+            return null;
+        } catch (ClosedChannelException e) {
+            return "closed|" + channel.isOpen();
+        }
+    }
+
+    public static String failure(String file) {
+        try {
+            FileChannel.open(Paths.get(file), StandardOpenOption.READ).close();
+            return null;
+        } catch (IOException | SecurityException e) {
+            return e.getClass().getName() + ": " + e.getMessage();
+        }
+    }
+}
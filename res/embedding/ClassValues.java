@@ -0,0 +1,38 @@
+import java.lang.ref.WeakReference;
+
+public class ClassValues {
+    private static int computed;
+    private static final ClassValue<String> NAMES = new ClassValue<String>() {
+        @Override
+        protected String computeValue(Class<?> type) {
+            computed++;
+            return type.getName() + "#" + computed;
+        }
+    };
+    private static WeakReference<Object> dropped;
+
+    // The values computed for classes, once until they are removed
+    public static String values() {
+        String first = NAMES.get(String.class);
+        String again = NAMES.get(String.class);
+        String other = NAMES.get(ClassValues.class);
+        NAMES.remove(String.class);
+        String recomputed = NAMES.get(String.class);
+        return first + "|" + (first == again) + "|" + other + "|" + recomputed + "|" + computed;
+    }
+
+    // Computes a value of a class value nothing refers to afterwards
+    public static void drop() {
+        ClassValue<Object> values = new ClassValue<Object>() {
+            @Override
+            protected Object computeValue(Class<?> type) {
+                return new Object();
+            }
+        };
+        dropped = new WeakReference<>(values.get(Object.class));
+    }
+
+    public static boolean droppedCollected() {
+        return dropped.get() == null;
+    }
+}
@@ -0,0 +1,47 @@
+public class Cloning {
+    static class Point implements Cloneable {
+        int x;
+        int[] shared;
+
+        Point copy() throws CloneNotSupportedException {
+            return (Point) clone();
+        }
+    }
+
+    static class Plain {
+        Object copy() throws CloneNotSupportedException {
+            return clone();
+        }
+    }
+
+    public static int point() throws CloneNotSupportedException {
+        Point point = new Point();
+        point.x = 7;
+        point.shared = new int[] {1};
+        int hash = System.identityHashCode(point);
+        Point copy = point.copy();
+        copy.shared[0] = 2;
+        boolean distinct = copy != point && copy.getClass() == Point.class;
+        boolean hashed = System.identityHashCode(copy) != hash;
+        return distinct && hashed ? copy.x * 10 + point.shared[0] : -1;
+    }
+
+    public static String plain() {
+        try {
+            new Plain().copy();
+            return "cloned";
+        } catch (CloneNotSupportedException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static int array() {
+        int[] values = {1, 2, 3};
+        int[] copy = values.clone();
+        copy[0] = 10;
+        String[][] nested = {{"a"}, {"b"}};
+        String[][] shallow = nested.clone();
+        boolean same = shallow != nested && shallow[1] == nested[1];
+        return same ? values[0] + copy[0] + copy.length : -1;
+    }
+}
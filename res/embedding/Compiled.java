@@ -0,0 +1,43 @@
+public class Compiled {
+    public static long checksum(int count) {
+        long sum = 0;
+        for (int i = 0; i < count; i++) {
+            int mixed = (i * 31) ^ (i >>> 3);
+            sum += (long) mixed * (i % 7) - (byte) i + (char) -i + (short) (i << 9);
+            sum ^= sum << 13;
+        }
+        return sum;
+    }
+
+    public static double harmonic(int count) {
+        double sum = 0;
+        float thirds = 0;
+        for (int i = 1; i <= count; i++) {
+            sum += 1.0 / i;
+            thirds += (float) i / 3;
+        }
+        return sum + (long) thirds % 1000 + (int) (sum / 0.0) + (int) (0.0 / 0.0);
+    }
+
+    public static int compare(double a, double b) {
+        if (a < b) {
+            return -1;
+        }
+        if (a > b) {
+            return 1;
+        }
+        return a == b ? 0 : 2;
+    }
+
+    public static int divide(int a, int b) {
+        return a / b + a % b;
+    }
+
+    public static int sumAndCall(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += i;
+        }
+        return sum + Arithmetic.fib(1);
+    }
+}
@@ -0,0 +1,18 @@
+// Compiled with --release 11, for javac to concatenate with invokedynamic
+public class Concatenation {
+    public static String describe(String name, int count, char unit, double ratio, Object tag) {
+        return name + ": " + count + unit + " at " + ratio + " \u0001 " + tag + '!';
+    }
+
+    public static String constants(long value, boolean flag) {
+        return value + "" + flag;
+    }
+
+    public static String repeat(int times) {
+        String result = "";
+        for (int i = 0; i < times; i++) {
+            result += i + ",";
+        }
+        return result;
+    }
+}
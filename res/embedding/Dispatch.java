@@ -0,0 +1,98 @@
+public class Dispatch {
+    interface Shape {
+        int area();
+    }
+
+    static class Square implements Shape {
+        private final int side;
+
+        Square(int side) {
+            this.side = side;
+        }
+
+        public int area() {
+            return side * side;
+        }
+    }
+
+    static class Rectangle implements Shape {
+        private final int width;
+        private final int height;
+
+        Rectangle(int width, int height) {
+            this.width = width;
+            this.height = height;
+        }
+
+        public int area() {
+            return width * height;
+        }
+    }
+
+    static class Triangle implements Shape {
+        private final int base;
+        private final int height;
+
+        Triangle(int base, int height) {
+            this.base = base;
+            this.height = height;
+        }
+
+        public int area() {
+            return base * height / 2;
+        }
+    }
+
+    static class Circle implements Shape {
+        private final int radius;
+
+        Circle(int radius) {
+            this.radius = radius;
+        }
+
+        public int area() {
+            return 3 * radius * radius;
+        }
+    }
+
+    static class Empty implements Shape {
+        public int area() {
+            return 0;
+        }
+    }
+
+    static class Unit implements Shape {
+        public int area() {
+            return 1;
+        }
+    }
+
+    public static int monomorphic(int count) {
+        Square square = new Square(3);
+        int total = 0;
+        for (int i = 0; i < count; i++) {
+            total += square.area();
+        }
+        return total;
+    }
+
+    public static int polymorphic(int count) {
+        Shape[] shapes = {new Square(2), new Rectangle(2, 3), new Triangle(4, 2)};
+        return total(shapes, count);
+    }
+
+    public static int megamorphic(int count) {
+        Shape[] shapes = {
+            new Square(2), new Rectangle(2, 3), new Triangle(4, 2), new Circle(1), new Empty(), new Unit()
+        };
+        return total(shapes, count);
+    }
+
+    private static int total(Shape[] shapes, int count) {
+        int total = 0;
+        for (int i = 0; i < count; i++) {
+            total += shapes[i % shapes.length].area();
+        }
+        return total;
+    }
+}
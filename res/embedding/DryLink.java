@@ -0,0 +1,24 @@
+// Compiled against a version of DryLinkTarget with a removed() method and a
+// gone field, and with the class file of DryLink$Missing deleted, for
+// `Vm::dry_link` to find the references which no longer resolve.
+public class DryLink {
+    static class Missing {
+    }
+
+    public static void main(String[] args) {
+        System.exit(3);
+    }
+
+    static int present() {
+        DryLinkTarget[][] targets = new DryLinkTarget[1][1];
+        return DryLinkTarget.present() + targets.length;
+    }
+
+    static int removed() {
+        return DryLinkTarget.removed() + DryLinkTarget.gone;
+    }
+
+    static Object missing() {
+        return new Missing();
+    }
+}
@@ -0,0 +1,5 @@
+public class DryLinkTarget {
+    public static int present() {
+        return 1;
+    }
+}
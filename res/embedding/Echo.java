@@ -0,0 +1,11 @@
+public class Echo {
+    public static void main(String[] args) {
+        for (String arg : args) {
+            System.out.println(arg);
+        }
+    }
+
+    public static String last(String[] args) {
+        return args[args.length - 1];
+    }
+}
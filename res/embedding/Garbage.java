@@ -0,0 +1,16 @@
+public class Garbage {
+    private static int[] kept;
+
+    public static int churn(int count) {
+        int[] last = null;
+        for (int i = 0; i < count; i++) {
+            last = new int[256];
+        }
+        kept = new int[16];
+        return last.length;
+    }
+
+    public static void collect() {
+        System.gc();
+    }
+}
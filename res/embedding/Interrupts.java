@@ -0,0 +1,59 @@
+import java.util.concurrent.locks.LockSupport;
+
+public class Interrupts {
+    private static final Object lock = new Object();
+
+    public static long sleep(long millis) throws InterruptedException {
+        long start = System.currentTimeMillis();
+        Thread.sleep(millis);
+        return System.currentTimeMillis() - start;
+    }
+
+    public static long yields() {
+        long start = System.nanoTime();
+        Thread.yield();
+        return System.nanoTime() - start;
+    }
+
+    public static String interrupted() {
+        Thread current = Thread.currentThread();
+        current.interrupt();
+        String result = current.getName() + " " + current.isInterrupted();
+        try {
+            Thread.sleep(1000);
+            return "not interrupted";
+        } catch (InterruptedException e) {
+            return result + " " + e.getMessage() + " " + Thread.interrupted();
+        }
+    }
+
+    public static long waitOwned() throws InterruptedException {
+        long start = System.currentTimeMillis();
+        synchronized (lock) {
+            lock.wait(5);
+            lock.notifyAll();
+        }
+        return System.currentTimeMillis() - start;
+    }
+
+    public static String waitUnowned() throws InterruptedException {
+        try {
+            lock.wait();
+            return "returned";
+        } catch (IllegalMonitorStateException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static long park() {
+        Thread current = Thread.currentThread();
+        long start = System.currentTimeMillis();
+        LockSupport.unpark(current);
+        LockSupport.parkNanos(1_000_000_000L);
+        LockSupport.parkNanos(3_000_000L);
+        current.interrupt();
+        LockSupport.parkNanos(1_000_000_000L);
+        long elapsed = System.currentTimeMillis() - start;
+        return Thread.interrupted() ? elapsed : -1;
+    }
+}
@@ -0,0 +1,35 @@
+public class Intrinsics {
+    public static int shift() {
+        int[] values = {1, 2, 3, 4, 5};
+        System.arraycopy(values, 0, values, 1, 4);
+        return values[0] * 10000 + values[1] * 1000 + values[2] * 100 + values[3] * 10 + values[4];
+    }
+
+    public static String copyOutOfBounds() {
+        try {
+            System.arraycopy(new int[10], 5, new int[10], 0, 6);
+            return "copied";
+        } catch (ArrayIndexOutOfBoundsException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static String copyMismatch() {
+        try {
+            System.arraycopy(new int[1], 0, new Object[1], 0, 1);
+            return "copied";
+        } catch (ArrayStoreException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static String className() {
+        return new String[0].getClass().getName().concat(" ").concat(Intrinsics.class.getName());
+    }
+
+    public static boolean stableHash() {
+        Object object = new Object();
+        return object.hashCode() == System.identityHashCode(object) && object.hashCode() != 0;
+    }
+
+}
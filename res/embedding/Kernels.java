@@ -0,0 +1,23 @@
+public class Kernels {
+    public static int sieve(int limit) {
+        boolean[] composite = new boolean[limit + 1];
+        int primes = 0;
+        for (int n = 2; n <= limit; n++) {
+            if (!composite[n]) {
+                primes++;
+                for (int multiple = n * 2; multiple <= limit; multiple += n) {
+                    composite[multiple] = true;
+                }
+            }
+        }
+        return primes;
+    }
+
+    public static int concat(int count) {
+        String joined = "";
+        for (int i = 0; i < count; i++) {
+            joined = joined.concat(String.valueOf(i % 10));
+        }
+        return joined.length();
+    }
+}
@@ -0,0 +1,22 @@
+public class Limits {
+    public static void spin() {
+        while (true) {
+        }
+    }
+
+    public static int hoard() {
+        int[][] arrays = new int[1000][];
+        for (int i = 0; i < arrays.length; i++) {
+            arrays[i] = new int[1024];
+        }
+        return arrays.length;
+    }
+
+    public static int sum(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+}
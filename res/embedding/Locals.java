@@ -0,0 +1,68 @@
+import java.lang.ref.WeakReference;
+import java.util.function.Supplier;
+
+public class Locals {
+    private static int initialized;
+    private static final ThreadLocal<String> NAMED = new ThreadLocal<String>() {
+        @Override
+        protected String initialValue() {
+            initialized++;
+            return Thread.currentThread().getName();
+        }
+    };
+    private static final ThreadLocal<String> COUNTER = ThreadLocal.withInitial(new Supplier<String>() {
+        @Override
+        public String get() {
+            return "";
+        }
+    });
+    private static final InheritableThreadLocal<String> INHERITED = new InheritableThreadLocal<String>() {
+        @Override
+        protected String childValue(String parent) {
+            return parent + "+child";
+        }
+    };
+    private static final ThreadLocal<Object> HELD = new ThreadLocal<>();
+    private static WeakReference<Object> held;
+    // Keeps the terminated thread reachable
+    private static Thread worker;
+
+    // Counts in the length of the string, without boxing
+    private static void increment() {
+        COUNTER.set(COUNTER.get() + "x");
+    }
+
+    private static int count() {
+        return COUNTER.get().length();
+    }
+
+    // The values seen by the main thread and by a thread of its own
+    public static String values() throws InterruptedException {
+        INHERITED.set("parent");
+        increment();
+        increment();
+        final String[] seen = new String[1];
+        Thread thread = new Thread(new Runnable() {
+            @Override
+            public void run() {
+                increment();
+                Object value = new Object();
+                HELD.set(value);
+                held = new WeakReference<>(value);
+                seen[0] = NAMED.get() + "|" + count() + "|" + INHERITED.get();
+            }
+        }, "worker");
+        worker = thread;
+        thread.start();
+        thread.join();
+
+        String main = NAMED.get() + "|" + count() + "|" + INHERITED.get() + "|" + HELD.get();
+        COUNTER.remove();
+        NAMED.set("changed");
+        return seen[0] + "/" + main + "/" + count() + "|" + NAMED.get() + "|" + initialized;
+    }
+
+    public static boolean heldCollected() {
+        return held.get() == null;
+    }
+}
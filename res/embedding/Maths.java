@@ -0,0 +1,119 @@
+public class Maths {
+    public static double sqrt(double a) {
+        return Math.sqrt(a);
+    }
+
+    public static double sin(double a) {
+        return Math.sin(a);
+    }
+
+    public static double cos(double a) {
+        return StrictMath.cos(a);
+    }
+
+    public static double tan(double a) {
+        return StrictMath.tan(a);
+    }
+
+    public static double log(double a) {
+        return StrictMath.log(a);
+    }
+
+    public static double cbrt(double a) {
+        return StrictMath.cbrt(a);
+    }
+
+    public static double floor(double a) {
+        return Math.floor(a);
+    }
+
+    public static double ceil(double a) {
+        return StrictMath.ceil(a);
+    }
+
+    public static double rint(double a) {
+        return Math.rint(a);
+    }
+
+    public static long round(double a) {
+        return Math.round(a);
+    }
+
+    public static int round(float a) {
+        return Math.round(a);
+    }
+
+    public static double pow(double a, double b) {
+        return Math.pow(a, b);
+    }
+
+    public static double strictPow(double a, double b) {
+        return StrictMath.pow(a, b);
+    }
+
+    public static double max(double a, double b) {
+        return Math.max(a, b);
+    }
+
+    public static double min(double a, double b) {
+        return StrictMath.min(a, b);
+    }
+
+    public static double signum(double a) {
+        return Math.signum(a);
+    }
+
+    public static double remainder(double a, double b) {
+        return Math.IEEEremainder(a, b);
+    }
+
+    public static double ulp(double a) {
+        return Math.ulp(a);
+    }
+
+    public static double scalb(double a, int scale) {
+        return Math.scalb(a, scale);
+    }
+
+    public static int floorDiv(int a, int b) {
+        return Math.floorDiv(a, b);
+    }
+
+    public static int floorMod(int a, int b) {
+        return Math.floorMod(a, b);
+    }
+
+    public static long floorDiv(long a, long b) {
+        return StrictMath.floorDiv(a, b);
+    }
+
+    public static long floorMod(long a, long b) {
+        return Math.floorMod(a, b);
+    }
+
+    public static int abs(int a) {
+        return Math.abs(a);
+    }
+
+    public static int addExact(int a, int b) {
+        return Math.addExact(a, b);
+    }
+
+    public static long multiplyExact(long a, long b) {
+        return Math.multiplyExact(a, b);
+    }
+
+    public static int toIntExact(long a) {
+        return Math.toIntExact(a);
+    }
+
+    public static int negateExact(int a) {
+        return Math.negateExact(a);
+    }
+
+    public static boolean random() {
+        double first = Math.random();
+        double second = Math.random();
+        return first >= 0.0 && first < 1.0 && second >= 0.0 && second < 1.0 && first != second;
+    }
+}
@@ -0,0 +1,13 @@
+public class Monitors {
+    private static native String dump();
+
+    public static synchronized String locked(Object lock) {
+        synchronized (lock) {
+            return new Monitors().inner();
+        }
+    }
+
+    private synchronized String inner() {
+        return dump();
+    }
+}
@@ -0,0 +1,121 @@
+public class Numerics {
+    public static int f2i(float value) {
+        return (int) value;
+    }
+
+    public static long f2l(float value) {
+        return (long) value;
+    }
+
+    public static double f2d(float value) {
+        return value;
+    }
+
+    public static int d2i(double value) {
+        return (int) value;
+    }
+
+    public static long d2l(double value) {
+        return (long) value;
+    }
+
+    public static float d2f(double value) {
+        return (float) value;
+    }
+
+    public static float l2f(long value) {
+        return value;
+    }
+
+    public static double l2d(long value) {
+        return value;
+    }
+
+    public static float i2f(int value) {
+        return value;
+    }
+
+    public static int i2b(int value) {
+        return (byte) value;
+    }
+
+    public static int i2c(int value) {
+        return (char) value;
+    }
+
+    public static int i2s(int value) {
+        return (short) value;
+    }
+
+    // fcmpg
+    public static boolean less(float a, float b) {
+        return a < b;
+    }
+
+    // fcmpl
+    public static boolean greater(float a, float b) {
+        return a > b;
+    }
+
+    // dcmpg
+    public static boolean less(double a, double b) {
+        return a < b;
+    }
+
+    // dcmpl
+    public static boolean greater(double a, double b) {
+        return a > b;
+    }
+
+    public static boolean equal(double a, double b) {
+        return a == b;
+    }
+
+    public static float add(float a, float b) {
+        return a + b;
+    }
+
+    public static float multiply(float a, float b) {
+        return a * b;
+    }
+
+    public static float divide(float a, float b) {
+        return a / b;
+    }
+
+    public static float remainder(float a, float b) {
+        return a % b;
+    }
+
+    public static double add(double a, double b) {
+        return a + b;
+    }
+
+    public static double divide(double a, double b) {
+        return a / b;
+    }
+
+    public static double remainder(double a, double b) {
+        return a % b;
+    }
+
+    public static double negate(double a) {
+        return -a;
+    }
+
+    public static int divide(int a, int b) {
+        return a / b;
+    }
+
+    public static int remainder(int a, int b) {
+        return a % b;
+    }
+
+    public static long divide(long a, long b) {
+        return a / b;
+    }
+
+    public static long remainder(long a, long b) {
+        return a % b;
+    }
+}
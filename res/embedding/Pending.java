@@ -0,0 +1,18 @@
+public class Pending {
+    static final StringBuilder log = new StringBuilder();
+
+    static class Logger implements Runnable {
+        public void run() {
+            log.append("thread ");
+        }
+    }
+
+    public static String load() throws InterruptedException {
+        Thread thread = new Thread(new Logger());
+        thread.start();
+        log.append("loading ");
+        log.append(Remote.name());
+        thread.join();
+        return log.toString();
+    }
+}
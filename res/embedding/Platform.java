@@ -0,0 +1,30 @@
+public class Platform {
+    public static long elapsed() {
+        long start = System.nanoTime();
+        return System.nanoTime() - start;
+    }
+
+    public static long millis() {
+        return System.currentTimeMillis();
+    }
+
+    public static int processors() {
+        return Runtime.getRuntime().availableProcessors();
+    }
+
+    public static long freeMemory() {
+        return Runtime.getRuntime().freeMemory();
+    }
+
+    public static long totalMemory() {
+        return Runtime.getRuntime().totalMemory();
+    }
+
+    public static long maxMemory() {
+        return Runtime.getRuntime().maxMemory();
+    }
+
+    public static String env(String name) {
+        return System.getenv(name);
+    }
+}
@@ -0,0 +1,46 @@
+import java.io.IOException;
+import java.io.InputStream;
+import java.io.OutputStream;
+
+public class Processes {
+    private static String readAll(InputStream input) throws IOException {
+        String text = "";
+        byte[] buffer = new byte[4];
+        int read;
+        while ((read = input.read(buffer)) != -1) {
+            text += new String(buffer, 0, read);
+        }
+        input.close();
+        return text;
+    }
+
+    // The output of the shell script and its exit value
+    public static String shell(String script, boolean redirectErrorStream)
+            throws IOException, InterruptedException {
+        Process process = new ProcessBuilder("sh", "-c", script)
+            .redirectErrorStream(redirectErrorStream)
+            .start();
+        String output = readAll(process.getInputStream());
+        String error = readAll(process.getErrorStream());
+        return output + "|" + error + "|" + process.waitFor();
+    }
+
+    public static String cat(String text) throws IOException, InterruptedException {
+        Process process = Runtime.getRuntime().exec("cat");
+        OutputStream input = process.getOutputStream();
+        input.write(text.getBytes());
+        input.close();
+        String output = readAll(process.getInputStream());
+        process.waitFor();
+        return output + "|" + process.exitValue() + "|" + process.isAlive();
+    }
+
+    public static String failure(String program) throws IOException {
+        try {
+            Runtime.getRuntime().exec(new String[] { program });
+            return null;
+        } catch (IOException | SecurityException e) {
+            return e.getMessage();
+        }
+    }
+}
@@ -0,0 +1,46 @@
+import java.lang.ref.PhantomReference;
+import java.lang.ref.ReferenceQueue;
+import java.lang.ref.SoftReference;
+import java.lang.ref.WeakReference;
+
+public class References {
+    private static final ReferenceQueue<Object> QUEUE = new ReferenceQueue<>();
+    private static Object strong = new Object();
+    private static WeakReference<Object> weak;
+    private static WeakReference<Object> weakToStrong;
+    private static SoftReference<Object> soft;
+    private static PhantomReference<Object> phantom;
+
+    public static void create() {
+        weak = new WeakReference<>(new Object(), QUEUE);
+        weakToStrong = new WeakReference<>(strong, QUEUE);
+        soft = new SoftReference<>(new Object(), QUEUE);
+        phantom = new PhantomReference<>(new Object(), QUEUE);
+    }
+
+    /** Which references are cleared, as bits in the order of creation. */
+    public static int cleared() {
+        int cleared = 0;
+        if (weak.get() == null) {
+            cleared |= 1;
+        }
+        if (weakToStrong.get() == null) {
+            cleared |= 2;
+        }
+        if (soft.get() == null) {
+            cleared |= 4;
+        }
+        if (phantom.refersTo(null)) {
+            cleared |= 8;
+        }
+        return cleared;
+    }
+
+    public static int enqueued() {
+        int count = 0;
+        while (QUEUE.poll() != null) {
+            count++;
+        }
+        return count;
+    }
+}
@@ -0,0 +1,117 @@
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+
+public class Reflection {
+    public static Object shared;
+    private String label = "initial";
+
+    public Reflection() {
+    }
+
+    public Reflection(String label) {
+        this.label = label;
+    }
+
+    public String label() {
+        return label;
+    }
+
+    private static String echo(String message) {
+        return message;
+    }
+
+    public static void fail(String message) {
+        throw new IllegalStateException(message);
+    }
+
+    public static String className(String name) throws ClassNotFoundException {
+        return Class.forName(name).getName();
+    }
+
+    public static String superclassName() {
+        return Reflection.class.getSuperclass().getName();
+    }
+
+    public static int declaredMethods() {
+        return Reflection.class.getDeclaredMethods().length;
+    }
+
+    public static int declaredConstructors() {
+        return Reflection.class.getDeclaredConstructors().length;
+    }
+
+    public static int declaredFields() {
+        return Reflection.class.getDeclaredFields().length;
+    }
+
+    public static String construct(String label) throws Exception {
+        Constructor<Reflection> constructor = Reflection.class.getDeclaredConstructor(String.class);
+        return constructor.newInstance(label).label();
+    }
+
+    public static Object invoke(String name, String argument) throws Exception {
+        Method method = Reflection.class.getDeclaredMethod(name, String.class);
+        return method.invoke(null, argument);
+    }
+
+    public static String relabel(String label) throws Exception {
+        Reflection reflection = Reflection.class.newInstance();
+        Field field = Reflection.class.getDeclaredField("label");
+        field.set(reflection, label);
+        return (String) field.get(reflection);
+    }
+
+    public static String failure(String message) throws Exception {
+        try {
+            invoke("fail", message);
+            return null;
+        } catch (InvocationTargetException e) {
+            return e.getCause().getMessage();
+        }
+    }
+
+    public static String denied(String message) throws Exception {
+        Method method = Hidden.class.getDeclaredMethod("secret", String.class);
+        try {
+            method.invoke(null, message);
+            return null;
+        } catch (IllegalAccessException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static String instantiated() throws Exception {
+        try {
+            Hidden.class.newInstance();
+            return null;
+        } catch (IllegalAccessException e) {
+            return e.getMessage();
+        }
+    }
+
+    public static String overridden(String message) throws Exception {
+        Method method = Hidden.class.getDeclaredMethod("secret", String.class);
+        method.setAccessible(true);
+        return (String) method.invoke(null, message);
+    }
+
+    public static String missing(String name) {
+        try {
+            Class.forName(name);
+            return null;
+        } catch (ClassNotFoundException e) {
+            return e.getMessage();
+        }
+    }
+}
+
+class Hidden {
+    private Hidden() {
+    }
+
+    private static String secret(String message) {
+        return message;
+    }
+}
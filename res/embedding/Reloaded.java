@@ -0,0 +1,19 @@
+public class Reloaded {
+    private final String greeting = "Hello";
+
+    public static int call() {
+        return new Reloaded().version() * 10 + helper();
+    }
+
+    public int version() {
+        return 1;
+    }
+
+    private static int helper() {
+        return 1;
+    }
+
+    public String greet() {
+        return greeting + " from version " + version();
+    }
+}
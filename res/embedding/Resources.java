@@ -0,0 +1,33 @@
+import java.io.IOException;
+import java.io.InputStream;
+import java.net.URL;
+
+public class Resources {
+    public static String fromClass(String name) throws IOException {
+        return read(Resources.class.getResourceAsStream(name));
+    }
+
+    public static String fromLoader(String name) throws IOException {
+        URL url = Resources.class.getClassLoader().getResource(name);
+        if (url == null) {
+            return null;
+        }
+        return url.toExternalForm() + " " + read(url.openStream());
+    }
+
+    public static String fromSystem(String name) throws IOException {
+        return read(ClassLoader.getSystemResourceAsStream(name));
+    }
+
+    private static String read(InputStream in) throws IOException {
+        if (in == null) {
+            return null;
+        }
+        StringBuilder text = new StringBuilder();
+        for (int b = in.read(); b != -1; b = in.read()) {
+            text.append((char) b);
+        }
+        in.close();
+        return text.toString();
+    }
+}
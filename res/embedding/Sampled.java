@@ -0,0 +1,26 @@
+public class Sampled {
+    static long total;
+
+    static class Spinner implements Runnable {
+        public void run() {
+            spin(200_000);
+        }
+    }
+
+    static void spin(int count) {
+        for (int i = 0; i < count; i++) {
+            total += i;
+        }
+    }
+
+    public static long joined() throws InterruptedException {
+        Thread thread = new Thread(new Spinner());
+        thread.start();
+        thread.join();
+        return total;
+    }
+
+    public static void sleep(long millis) throws InterruptedException {
+        Thread.sleep(millis);
+    }
+}
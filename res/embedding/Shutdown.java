@@ -0,0 +1,18 @@
+public class Shutdown {
+    public static void exit(int status) {
+        Runtime.getRuntime().addShutdownHook(new Thread(new Runnable() {
+            public void run() {
+                System.out.println("hook");
+            }
+        }));
+        try {
+            System.exit(status);
+        } finally {
+            System.out.println("finally");
+        }
+    }
+
+    public static void main(String[] args) {
+        exit(args.length);
+    }
+}
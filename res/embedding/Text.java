@@ -0,0 +1,33 @@
+import java.io.UnsupportedEncodingException;
+import java.nio.charset.Charset;
+import java.nio.charset.StandardCharsets;
+
+public class Text {
+    public static int encodedLength(String text, String charset) throws UnsupportedEncodingException {
+        return text.getBytes(charset).length;
+    }
+
+    public static String roundTrip(String text, String charset) throws UnsupportedEncodingException {
+        return new String(text.getBytes(charset), charset);
+    }
+
+    public static String decodeRange() {
+        byte[] bytes = { 'x', (byte) 0xc3, (byte) 0xa9, 'y' };
+        return new String(bytes, 1, 2, StandardCharsets.UTF_8) + new String(bytes, StandardCharsets.US_ASCII);
+    }
+
+    public static boolean defaultCharset() {
+        return Charset.forName("utf8") == StandardCharsets.UTF_8
+            && Charset.defaultCharset().name().equals("UTF-8")
+            && new String("\u00e9".getBytes()).equals("\u00e9");
+    }
+
+    public static String unsupported() {
+        try {
+            "text".getBytes("EBCDIC");
+            return null;
+        } catch (UnsupportedEncodingException e) {
+            return e.getMessage();
+        }
+    }
+}
@@ -0,0 +1,65 @@
+public class Uncaught {
+    static final StringBuilder log = new StringBuilder();
+
+    static class Recorder implements Thread.UncaughtExceptionHandler {
+        private final String name;
+
+        Recorder(String name) {
+            this.name = name;
+        }
+
+        public void uncaughtException(Thread thread, Throwable exception) {
+            String line = name + ": " + thread.getName() + " " + exception.getMessage() + "\n";
+            log.append(line);
+            System.out.print(line);
+        }
+    }
+
+    static class Failing implements Thread.UncaughtExceptionHandler {
+        public void uncaughtException(Thread thread, Throwable exception) {
+            throw new IllegalStateException("handler failed");
+        }
+    }
+
+    static class Thrower implements Runnable {
+        private final String message;
+
+        Thrower(String message) {
+            this.message = message;
+        }
+
+        public void run() {
+            throw new RuntimeException(message);
+        }
+    }
+
+    static void run(String name, String message, Thread.UncaughtExceptionHandler handler)
+            throws InterruptedException {
+        Thread thread = new Thread(new Thrower(message), name);
+        if (handler != null) {
+            thread.setUncaughtExceptionHandler(handler);
+        }
+        thread.start();
+        thread.join();
+    }
+
+    public static String handlers() throws InterruptedException {
+        run("unhandled", "printed", null);
+        Thread.setDefaultUncaughtExceptionHandler(new Recorder("default"));
+        run("own", "first", new Recorder("thread"));
+        run("other", "second", null);
+        run("failing", "third", new Failing());
+        boolean same = Thread.getDefaultUncaughtExceptionHandler() instanceof Recorder;
+        Thread.setDefaultUncaughtExceptionHandler(null);
+        return log.toString() + same;
+    }
+
+    public static void main(String[] args) {
+        if (args.length > 0) {
+            Thread.currentThread().setUncaughtExceptionHandler(new Recorder("main"));
+        }
+        Thread thread = new Thread(new Thrower("in thread"), "worker");
+        thread.start();
+        throw new IllegalArgumentException("in main");
+    }
+}
@@ -0,0 +1,66 @@
+import java.util.zip.CRC32;
+import java.util.zip.DataFormatException;
+import java.util.zip.Deflater;
+import java.util.zip.Inflater;
+
+public class Zip {
+    public static long crc(String text) {
+        CRC32 crc = new CRC32();
+        byte[] bytes = text.getBytes();
+        crc.update(bytes[0]);
+        crc.update(bytes, 1, bytes.length - 1);
+        return crc.getValue();
+    }
+
+    // Compresses and inflates the text, a few bytes at a time
+    public static String roundTrip(String text, boolean nowrap) throws DataFormatException {
+        byte[] input = text.getBytes();
+        Deflater deflater = new Deflater(Deflater.BEST_COMPRESSION, nowrap);
+        deflater.setInput(input);
+        deflater.finish();
+        byte[] compressed = new byte[input.length + 64];
+        int length = 0;
+        while (!deflater.finished()) {
+            length += deflater.deflate(compressed, length, 5);
+        }
+        deflater.end();
+        if (length >= input.length) {
+            return "not compressed";
+        }
+
+        Inflater inflater = new Inflater(nowrap);
+        inflater.setInput(compressed, 0, length);
+        byte[] output = new byte[input.length + 8];
+        int inflated = 0;
+        while (!inflater.finished()) {
+            inflated += inflater.inflate(output, inflated, 7);
+        }
+        if (inflater.getBytesRead() != length || inflater.getRemaining() != 0) {
+            return "not consumed";
+        }
+        inflater.end();
+        return new String(output, 0, inflated);
+    }
+
+    public static String corrupt() {
+        Inflater inflater = new Inflater();
+        inflater.setInput(new byte[] { 1, 2, 3, 4 });
+        try {
+            inflater.inflate(new byte[16]);
+            return null;
+        } catch (DataFormatException e) {
+            return "DataFormatException";
+        }
+    }
+
+    public static String closed() throws DataFormatException {
+        Inflater inflater = new Inflater();
+        inflater.end();
+        try {
+            inflater.inflate(new byte[16]);
+            return null;
+        } catch (NullPointerException e) {
+            return e.getMessage();
+        }
+    }
+}
@@ -0,0 +1,46 @@
+public class Concat {
+    static class Point {
+        final int x;
+        final int y;
+
+        Point(int x, int y) {
+            this.x = x;
+            this.y = y;
+        }
+
+        @Override
+        public String toString() {
+            return "(" + x + ", " + y + ")";
+        }
+    }
+
+    public static void main(String[] args) {
+        String name = null;
+        char[] letters = {'x', 'y'};
+        System.out.println("a" + 1 + 'c' + true + 2L + 1.5f + 0.25 + name);
+        System.out.println("point " + new Point(3, -4));
+        System.out.println(new StringBuilder().append(letters).append(1e21).append(-0.0f));
+
+        StringBuilder builder = new StringBuilder("hello");
+        builder.insert(0, ">> ").append(' ').append("world");
+        builder.setCharAt(3, 'H');
+        builder.deleteCharAt(builder.length() - 1);
+        System.out.println(builder + " " + builder.length() + " " + builder.charAt(4));
+        builder.setLength(5);
+        System.out.println(builder.reverse());
+        String reversed = new StringBuilder("a\uD83D\uDE00b").reverse().toString();
+        System.out.println(reversed.equals("b\uD83D\uDE00a"));
+
+        String joined = "";
+        for (int i = 0; i < 5; i++) {
+            joined += i;
+        }
+        System.out.println(joined);
+
+        try {
+            builder.charAt(10);
+        } catch (StringIndexOutOfBoundsException e) {
+            System.out.println("caught " + e.getClass().getName());
+        }
+    }
+}
@@ -0,0 +1,41 @@
+public class Control {
+    static int factorial(int n) {
+        return n <= 1 ? 1 : n * factorial(n - 1);
+    }
+
+    public static void main(String[] args) {
+        for (int i = 0; i <= 10; i++) {
+            System.out.println(factorial(i));
+        }
+
+        int[] squares = new int[5];
+        for (int i = 0; i < squares.length; i++) {
+            squares[i] = i * i;
+        }
+        int sum = 0;
+        for (int square : squares) {
+            sum += square;
+        }
+        System.out.println(sum);
+
+        long big = Long.MAX_VALUE;
+        System.out.println(big);
+        System.out.println(big + 1);
+        System.out.println(7 / 2);
+        System.out.println(-7 % 3);
+        System.out.println(7.0 / 2);
+        System.out.println(1.0f / 3);
+        System.out.println(-8 >> 1);
+        System.out.println(-8 >>> 28);
+        System.out.println((byte) 200);
+        System.out.println((char) ('a' + 1));
+        System.out.println(0.1 + 0.2);
+        System.out.println(1e20);
+
+        int[][] grid = new int[3][4];
+        grid[2][3] = 5;
+        System.out.println(grid.length);
+        System.out.println(grid[2].length);
+        System.out.println(grid[2][3]);
+    }
+}
@@ -0,0 +1,31 @@
+public class Daemons {
+    public static void main(String[] args) {
+        Thread daemon = new Thread(new Runnable() {
+            public void run() {
+                while (true) {
+                    try {
+                        Thread.sleep(50);
+                    } catch (InterruptedException e) {
+                        return;
+                    }
+                    Thread.yield();
+                }
+            }
+        }, "daemon");
+        daemon.setDaemon(true);
+        Thread worker = new Thread(new Runnable() {
+            public void run() {
+                System.out.println("worker done");
+            }
+        }, "worker");
+        Runtime.getRuntime().addShutdownHook(new Thread(new Runnable() {
+            public void run() {
+                System.out.println("hook");
+            }
+        }));
+
+        daemon.start();
+        worker.start();
+        System.out.println("main done");
+    }
+}
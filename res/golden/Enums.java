@@ -0,0 +1,61 @@
+public class Enums {
+    enum Planet {
+        MERCURY(3), VENUS(6), EARTH(6);
+
+        private final int radius;
+
+        Planet(int radius) {
+            this.radius = radius;
+        }
+
+        int radius() {
+            return radius;
+        }
+    }
+
+    enum Op {
+        ADD {
+            int apply(int a, int b) { return a + b; }
+        },
+        MUL {
+            int apply(int a, int b) { return a * b; }
+        };
+
+        abstract int apply(int a, int b);
+    }
+
+    static int score(Planet planet) {
+        switch (planet) {
+            case MERCURY: return 1;
+            case EARTH: return 3;
+            default: return 0;
+        }
+    }
+
+    public static int run() {
+        int total = 0;
+        for (Planet planet : Planet.values()) {
+            total += planet.ordinal() * 100 + planet.radius() + score(planet) * 1000;
+        }
+        total += Planet.valueOf("VENUS").radius() * 10000;
+        total += Op.MUL.apply(Op.ADD.apply(2, 3), 4) * 100000;
+        return total;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(run());
+        System.out.println(Planet.EARTH.name());
+        System.out.println(Planet.EARTH);
+        System.out.println(Planet.EARTH.compareTo(Planet.MERCURY));
+        System.out.println(Op.ADD.getDeclaringClass() == Op.class);
+        System.out.println(Planet.class.isEnum());
+        System.out.println(Op.MUL.getClass().isEnum());
+        System.out.println(Op.class.getEnumConstants().length);
+        System.out.println(Planet.values() != Planet.values());
+        try {
+            Planet.valueOf("PLUTO");
+        } catch (IllegalArgumentException e) {
+            System.out.println(e.getMessage());
+        }
+    }
+}
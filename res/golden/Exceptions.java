@@ -0,0 +1,52 @@
+public class Exceptions {
+    static class Failure extends RuntimeException {
+        Failure(String message) {
+            super(message);
+        }
+    }
+
+    static int divide(int a, int b) {
+        try {
+            return a / b;
+        } catch (ArithmeticException e) {
+            System.out.println(e.getMessage());
+            return 0;
+        } finally {
+            System.out.println("finally");
+        }
+    }
+
+    static void fail(int depth) {
+        if (depth == 0) {
+            throw new Failure("deep");
+        }
+        fail(depth - 1);
+    }
+
+    public static void main(String[] args) {
+        System.out.println(divide(6, 3));
+        System.out.println(divide(1, 0));
+
+        try {
+            fail(3);
+        } catch (Failure e) {
+            System.out.println(e.getMessage());
+        }
+
+        try {
+            int[] array = new int[2];
+            array[2] = 1;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println("out of bounds");
+        }
+
+        try {
+            Object string = "string";
+            Exceptions exceptions = (Exceptions) string;
+        } catch (ClassCastException e) {
+            System.out.println("class cast");
+        }
+
+        fail(1);
+    }
+}
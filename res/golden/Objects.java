@@ -0,0 +1,71 @@
+public class Objects {
+    interface Shape {
+        double area();
+    }
+
+    static abstract class Named implements Shape {
+        private final String name;
+
+        Named(String name) {
+            this.name = name;
+        }
+
+        public String toString() {
+            return name;
+        }
+    }
+
+    static class Square extends Named {
+        private final double side;
+
+        Square(double side) {
+            super("square");
+            this.side = side;
+        }
+
+        public double area() {
+            return side * side;
+        }
+    }
+
+    static class Circle extends Named {
+        private final double radius;
+
+        Circle(double radius) {
+            super("circle");
+            this.radius = radius;
+        }
+
+        public double area() {
+            return 3 * radius * radius;
+        }
+    }
+
+    static int counter;
+
+    static {
+        counter = 40;
+    }
+
+    public static void main(String[] args) {
+        Shape[] shapes = { new Square(2), new Circle(1), new Square(0.5) };
+        for (Shape shape : shapes) {
+            System.out.println(shape);
+            System.out.println(shape.area());
+        }
+
+        counter += 2;
+        System.out.println(counter);
+
+        Object object = shapes[1];
+        System.out.println(object instanceof Circle);
+        System.out.println(object instanceof Square);
+        System.out.println(object.equals(shapes[1]));
+        System.out.println(object.equals(shapes[0]));
+        System.out.println("text".equals("te".concat("xt")));
+        System.out.println("Hello".length());
+        System.out.println("Hello".charAt(1));
+        System.out.println("Hello".toUpperCase());
+        System.out.println("Hello".substring(1, 3));
+    }
+}
@@ -0,0 +1,39 @@
+public class Recursion {
+    static int depth;
+
+    static void recurse() {
+        depth++;
+        recurse();
+    }
+
+    static int sum(int n) {
+        return n == 0 ? 0 : n + sum(n - 1);
+    }
+
+    static class Node {
+        Node next;
+
+        @Override
+        public String toString() {
+            return "(" + next + ")";
+        }
+    }
+
+    public static void main(String[] args) {
+        try {
+            recurse();
+        } catch (StackOverflowError e) {
+            System.out.println("overflowed deeper than 1000: " + (depth > 1000));
+        }
+
+        Node node = new Node();
+        node.next = node;
+        try {
+            System.out.println(node);
+        } catch (StackOverflowError e) {
+            System.out.println("overflowed in toString");
+        }
+
+        System.out.println("sum " + sum(1000));
+    }
+}
@@ -0,0 +1,39 @@
+import java.io.IOException;
+import java.net.URL;
+import java.util.Enumeration;
+import java.util.ServiceLoader;
+
+public class Services {
+    public interface Greeter {
+        String greet(String name);
+    }
+
+    public static class English implements Greeter {
+        public String greet(String name) {
+            return "Hello, " + name + "!";
+        }
+    }
+
+    public static class Hungarian implements Greeter {
+        public String greet(String name) {
+            return "Szia, " + name + "!";
+        }
+    }
+
+    public static void main(String[] args) throws IOException {
+        ServiceLoader<Greeter> greeters = ServiceLoader.load(Greeter.class);
+        System.out.println(greeters);
+        for (Greeter greeter : greeters) {
+            System.out.println(greeter.greet("world"));
+        }
+
+        ClassLoader loader = Services.class.getClassLoader();
+        Enumeration<URL> configurations = loader.getResources("META-INF/services/Services$Greeter");
+        int count = 0;
+        while (configurations.hasMoreElements()) {
+            configurations.nextElement();
+            count++;
+        }
+        System.out.println("configurations: " + count);
+    }
+}
@@ -0,0 +1,61 @@
+public class Switches {
+    static String season(int month) {
+        switch (month) {
+            case 12:
+            case 1:
+            case 2:
+                return "winter";
+            case 3:
+            case 4:
+            case 5:
+                return "spring";
+            case 6:
+            case 7:
+            case 8:
+                return "summer";
+            default:
+                return "autumn";
+        }
+    }
+
+    static int sparse(int value) {
+        switch (value) {
+            case -1000:
+                return 1;
+            case 7:
+                return 2;
+            case 100000:
+                return 3;
+            default:
+                return 0;
+        }
+    }
+
+    static int negative(int value) {
+        switch (value) {
+            case -3:
+                return 30;
+            case -2:
+                return 20;
+            case -1:
+                return 10;
+            default:
+                return -1;
+        }
+    }
+
+    public static void main(String[] args) {
+        for (int month = 1; month <= 12; month += 1) {
+            System.out.println(season(month));
+        }
+        System.out.println(sparse(-1000));
+        System.out.println(sparse(7));
+        System.out.println(sparse(100000));
+        System.out.println(sparse(8));
+
+        System.out.println(sparse(Integer.MIN_VALUE));
+        for (int value = -4; value <= 0; value++) {
+            System.out.println(negative(value));
+        }
+    }
+}
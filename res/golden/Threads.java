@@ -0,0 +1,65 @@
+public class Threads {
+    static class Worker extends Thread {
+        private final int id;
+
+        Worker(int id) {
+            super("worker-" + id);
+            this.id = id;
+        }
+
+        @Override
+        public void run() {
+            boolean current = Thread.currentThread() == this;
+            System.out.println(getName() + " running, daemon " + isDaemon() + ", current " + current);
+            if (id == 2) {
+                throw new IllegalStateException("boom");
+            }
+        }
+    }
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread main = Thread.currentThread();
+        System.out.println(main.getName() + " alive " + main.isAlive() + ", daemon " + main.isDaemon());
+
+        Worker first = new Worker(1);
+        System.out.println("before start alive " + first.isAlive());
+        first.start();
+        first.join();
+        System.out.println("after join alive " + first.isAlive());
+        try {
+            first.start();
+        } catch (IllegalThreadStateException e) {
+            System.out.println("restart " + e.getClass().getName());
+        }
+
+        Worker failing = new Worker(2);
+        failing.start();
+        failing.join();
+
+        Thread daemon = new Thread(new Runnable() {
+            public void run() {
+                try {
+                    Thread.sleep(60000);
+                } catch (InterruptedException e) {
+                    System.out.println("daemon interrupted");
+                }
+                System.out.println("daemon done");
+            }
+        }, "daemon");
+        daemon.setDaemon(true);
+        Runtime.getRuntime().addShutdownHook(new Thread(new Runnable() {
+            public void run() {
+                System.out.println("hook");
+            }
+        }));
+        Thread last = new Thread(new Runnable() {
+            public void run() {
+                System.out.println("last non-daemon");
+            }
+        }, "last");
+
+        System.out.println("main done");
+        last.start();
+        daemon.start();
+    }
+}
@@ -0,0 +1,27 @@
+import org.junit.After;
+import org.junit.Before;
+import org.junit.Test;
+
+public class JUnitBase {
+    static String log = "";
+
+    @Before
+    public void baseSetUp() {
+        log += "base-before;";
+    }
+
+    @After
+    public void baseTearDown() {
+        log += "base-after;";
+    }
+
+    @Test
+    public void inherited() {
+        log += "inherited;";
+    }
+
+    @Test
+    public void overridden() {
+        throw new AssertionError("overrides without @Test are not tests");
+    }
+}
@@ -0,0 +1,85 @@
+import org.junit.After;
+import org.junit.AfterClass;
+import org.junit.Before;
+import org.junit.BeforeClass;
+import org.junit.Ignore;
+import org.junit.Test;
+
+public class JUnitSample extends JUnitBase {
+    private int counter;
+
+    @BeforeClass
+    public static void setUpClass() {
+        log = "class;";
+    }
+
+    @AfterClass
+    public static void tearDownClass() {
+        log += "done;";
+    }
+
+    @Before
+    public void setUp() {
+        counter = 1;
+        log += "before;";
+    }
+
+    @After
+    public void tearDown() {
+        log += "after;";
+    }
+
+    @Test
+    public void passes() {
+        if (counter != 1) {
+            throw new AssertionError("not set up");
+        }
+    }
+
+    @Test
+    public void fails() {
+        int sum = 1 + counter;
+        if (sum != 3) {
+            throw new AssertionError("expected:<3> but was:<" + sum + ">");
+        }
+    }
+
+    @Test
+    public void errors() {
+        Object missing = null;
+        missing.hashCode();
+    }
+
+    @Test(expected = ArithmeticException.class)
+    public void divides() {
+        int zero = counter - 1;
+        counter = 1 / zero;
+    }
+
+    @Test(expected = ArithmeticException.class)
+    public void missesException() {
+    }
+
+    @Test(expected = ArithmeticException.class)
+    public void throwsOther() {
+        throw new IllegalStateException("wrong");
+    }
+
+    @Ignore
+    @Test
+    public void ignored() {
+        throw new AssertionError("ignored tests do not run");
+    }
+
+    @Override
+    public void overridden() {
+    }
+
+    public void notATest() {
+        throw new AssertionError("not a test");
+    }
+
+    public static String log() {
+        return log;
+    }
+}
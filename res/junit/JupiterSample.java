@@ -0,0 +1,37 @@
+import org.junit.jupiter.api.AfterEach;
+import org.junit.jupiter.api.BeforeEach;
+import org.junit.jupiter.api.Disabled;
+import org.junit.jupiter.api.Test;
+
+class JupiterSample {
+    private String state;
+
+    @BeforeEach
+    void setUp() {
+        state = "ready";
+    }
+
+    @AfterEach
+    void tearDown() {
+        if (state.equals("broken")) {
+            throw new IllegalStateException("left broken");
+        }
+    }
+
+    @Test
+    void usesState() {
+        if (!state.equals("ready")) {
+            throw new AssertionError("not ready");
+        }
+    }
+
+    @Test
+    void breaksState() {
+        state = "broken";
+    }
+
+    @Disabled
+    @Test
+    void disabled() {
+    }
+}
@@ -0,0 +1,21 @@
+package app;
+
+import greeter.Greeter;
+import words.Words;
+
+public class Main {
+    // Reads words through the transitive dependence of greeter
+    public static int run() {
+        return Greeter.greet() + Words.count();
+    }
+
+    // Compiled with --add-exports words/words.internal=app, which the VM is
+    // not given
+    public static int peek() {
+        return words.internal.Secret.value();
+    }
+
+    public static void main(String[] args) {
+        System.out.println(run());
+    }
+}
@@ -0,0 +1,9 @@
+package greeter;
+
+import words.Words;
+
+public class Greeter {
+    public static int greet() {
+        return Words.count() * 2;
+    }
+}
@@ -0,0 +1,4 @@
+module greeter {
+    requires transitive words;
+    exports greeter;
+}
@@ -0,0 +1,4 @@
+module words {
+    exports words;
+    opens words.internal to app;
+}
@@ -0,0 +1,7 @@
+package words;
+
+public class Words {
+    public static int count() {
+        return 3;
+    }
+}
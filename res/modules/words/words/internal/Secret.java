@@ -0,0 +1,7 @@
+package words.internal;
+
+public class Secret {
+    public static int value() {
+        return 42;
+    }
+}
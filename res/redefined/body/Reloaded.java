@@ -0,0 +1,24 @@
+// The method bodies of ../../embedding/Reloaded.java changed
+public class Reloaded {
+    private final String greeting = "Hello";
+
+    public static int call() {
+        return new Reloaded().version() * 10 + helper();
+    }
+
+    public int version() {
+        return 2;
+    }
+
+    private static int helper() {
+        int sum = 0;
+        for (int i = 0; i < 3; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+
+    public String greet() {
+        return greeting.toUpperCase() + " from version " + version();
+    }
+}
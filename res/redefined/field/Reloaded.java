@@ -0,0 +1,22 @@
+// A field added to ../../embedding/Reloaded.java
+public class Reloaded {
+    private final String greeting = "Hello";
+    private int calls;
+
+    public static int call() {
+        return new Reloaded().version() * 10 + helper();
+    }
+
+    public int version() {
+        return 1;
+    }
+
+    private static int helper() {
+        return 1;
+    }
+
+    public String greet() {
+        calls++;
+        return greeting + " from version " + version();
+    }
+}
@@ -0,0 +1,5 @@
+public class Remote {
+    static String name() {
+        return "remote ";
+    }
+}
@@ -4,13 +4,18 @@
 
 // ConstantValue Attribute -----------------------------------------------------
 
+use std::cell::RefCell;
+use std::io::{Cursor, Read};
+
 use byteorder::{BigEndian, ReadBytesExt};
 
 use crate::class::attributes::VerificationType::{
     Double, Float, Integer, Long, Null, Object, Top, Uninitialized, UninitializedThis,
 };
 use crate::class::constant_pool::{Constant, ConstantPool, ConstantPoolContext};
-use crate::class::{ClassLoadingError, EmptyContext, ReadAll, ReadOne};
+use crate::class::{
+    read_bytes, Anomaly, ClassLoadingError, EmptyContext, ParseLimits, ReadAll, ReadOne,
+};
 
 // =============================================================================
 // CONTEXT
@@ -19,8 +24,23 @@ use crate::class::{ClassLoadingError, EmptyContext, ReadAll, ReadOne};
 /// Context usable when reading [Attribute] elements.
 struct AttributeContext<'a> {
     pub constant_pool: &'a ConstantPool,
+    pub limits: &'a ParseLimits,
     pub name_index: usize,
     pub length: usize,
+    /// How deep the element being read is nested in the attribute.
+    pub depth: usize,
+    pub anomalies: Option<&'a RefCell<Vec<Anomaly>>>,
+}
+
+impl<'a> AttributeContext<'a> {
+    /// The context of an element nested in the one being read.
+    fn nested(&self) -> Result<AttributeContext<'a>, ClassLoadingError> {
+        self.limits.check_nesting_depth(self.depth + 1)?;
+        Ok(AttributeContext {
+            depth: self.depth + 1,
+            ..*self
+        })
+    }
 }
 
 /// Context usable when reading [StackMapTableAttribute] attributes.
@@ -37,7 +57,7 @@ struct StackFrameContext {
 
 #[derive(Debug)]
 pub struct ConstantValueAttribute {
-    const_value_index: u16,
+    pub const_value_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for ConstantValueAttribute {
@@ -54,10 +74,10 @@ impl ReadOne<AttributeContext<'_>> for ConstantValueAttribute {
 
 #[derive(Debug)]
 pub struct ExceptionTableAttribute {
-    start_pc: u16,
-    end_pc: u16,
-    handler_pc: u16,
-    catch_type: u16,
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for ExceptionTableAttribute {
@@ -83,11 +103,11 @@ impl ReadAll<AttributeContext<'_>> for ExceptionTableAttribute {}
 
 #[derive(Debug)]
 pub struct CodeAttribute {
-    max_stack: u16,
-    max_locals: u16,
-    code: Vec<u8>,
-    exception_tables: Vec<ExceptionTableAttribute>,
-    attributes: Vec<Attribute>,
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: Vec<u8>,
+    pub exception_tables: Vec<ExceptionTableAttribute>,
+    pub attributes: Vec<Attribute>,
 }
 
 impl ReadOne<AttributeContext<'_>> for CodeAttribute {
@@ -99,13 +119,17 @@ impl ReadOne<AttributeContext<'_>> for CodeAttribute {
         let max_locals = reader.read_u16::<BigEndian>()?;
 
         let code_length = reader.read_u32::<BigEndian>()? as usize;
-        let mut code = vec![0; code_length];
-        reader.read_exact(&mut code)?;
+        context.limits.check_code_length(code_length)?;
+        let code = read_bytes(reader, code_length)?;
 
         let exception_tables = ExceptionTableAttribute::read_all(reader, context)?;
 
+        let nested = context.nested()?;
         let const_pool_context = ConstantPoolContext {
-            constant_pool: context.constant_pool,
+            constant_pool: nested.constant_pool,
+            limits: nested.limits,
+            depth: nested.depth,
+            anomalies: nested.anomalies,
         };
         let attributes = Attribute::read_all(reader, &const_pool_context)?;
 
@@ -121,7 +145,7 @@ impl ReadOne<AttributeContext<'_>> for CodeAttribute {
 
 // StackMapFrame Attribute -----------------------------------------------------
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ObjectVariableInfo {
     pub constant_index: u16,
 }
@@ -138,7 +162,7 @@ impl ReadOne<EmptyContext> for ObjectVariableInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UninitializedVariableInfo {
     pub offset: u16,
 }
@@ -153,7 +177,7 @@ impl ReadOne<EmptyContext> for UninitializedVariableInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum VerificationType {
     Top,
     Integer,
@@ -197,12 +221,12 @@ impl ReadAll<EmptyContext> for VerificationType {}
 
 #[derive(Debug)]
 pub struct SameFrame {
-    offset_delta: u8,
+    pub offset_delta: u8,
 }
 
 impl ReadOne<StackFrameContext> for SameFrame {
     fn read_one<R: ReadBytesExt>(
-        reader: &mut R,
+        _reader: &mut R,
         context: &StackFrameContext,
     ) -> Result<Self, ClassLoadingError> {
         let offset_delta = context.frame_type;
@@ -212,8 +236,8 @@ impl ReadOne<StackFrameContext> for SameFrame {
 
 #[derive(Debug)]
 pub struct SameLocalsOneStackItemFrame {
-    offset_delta: u8,
-    stack: VerificationType,
+    pub offset_delta: u8,
+    pub stack: VerificationType,
 }
 
 impl ReadOne<StackFrameContext> for SameLocalsOneStackItemFrame {
@@ -232,8 +256,8 @@ impl ReadOne<StackFrameContext> for SameLocalsOneStackItemFrame {
 
 #[derive(Debug)]
 pub struct SameLocalsOneStackItemExtendedFrame {
-    offset_delta: u16,
-    stack: VerificationType,
+    pub offset_delta: u16,
+    pub stack: VerificationType,
 }
 
 impl ReadOne<EmptyContext> for SameLocalsOneStackItemExtendedFrame {
@@ -252,22 +276,28 @@ impl ReadOne<EmptyContext> for SameLocalsOneStackItemExtendedFrame {
 
 #[derive(Debug)]
 pub struct ChopFrame {
-    offset_delta: u16,
+    /// The number of locals chopped, 1 to 3.
+    pub chopped: u8,
+    pub offset_delta: u16,
 }
 
-impl ReadOne<EmptyContext> for ChopFrame {
+impl ReadOne<StackFrameContext> for ChopFrame {
     fn read_one<R: ReadBytesExt>(
         reader: &mut R,
-        _context: &EmptyContext,
+        context: &StackFrameContext,
     ) -> Result<Self, ClassLoadingError> {
+        let chopped = 251 - context.frame_type;
         let offset_delta = reader.read_u16::<BigEndian>()?;
-        Ok(ChopFrame { offset_delta })
+        Ok(ChopFrame {
+            chopped,
+            offset_delta,
+        })
     }
 }
 
 #[derive(Debug)]
 pub struct SameExtendedFrame {
-    offset_delta: u16,
+    pub offset_delta: u16,
 }
 
 impl ReadOne<EmptyContext> for SameExtendedFrame {
@@ -282,8 +312,8 @@ impl ReadOne<EmptyContext> for SameExtendedFrame {
 
 #[derive(Debug)]
 pub struct AppendFrame {
-    offset_delta: u16,
-    locals: Vec<VerificationType>,
+    pub offset_delta: u16,
+    pub locals: Vec<VerificationType>,
 }
 
 impl ReadOne<StackFrameContext> for AppendFrame {
@@ -308,9 +338,9 @@ impl ReadOne<StackFrameContext> for AppendFrame {
 
 #[derive(Debug)]
 pub struct FullFrame {
-    offset_delta: u16,
-    locals: Vec<VerificationType>,
-    stack: Vec<VerificationType>,
+    pub offset_delta: u16,
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
 }
 
 impl ReadOne<EmptyContext> for FullFrame {
@@ -365,7 +395,7 @@ impl ReadOne<AttributeContext<'_>> for StackMapTableAttribute {
             )),
             248..=250 => Ok(StackMapTableAttribute::Chop(ChopFrame::read_one(
                 reader,
-                &EmptyContext::default(),
+                &frame_context,
             )?)),
             251 => Ok(StackMapTableAttribute::SameExtended(
                 SameExtendedFrame::read_one(reader, &EmptyContext::default())?,
@@ -378,12 +408,9 @@ impl ReadOne<AttributeContext<'_>> for StackMapTableAttribute {
                 reader,
                 &EmptyContext::default(),
             )?)),
-            value => Err(ClassLoadingError::new(
-                format!("Unknown frame type {}", value).as_str(),
-            )),
         };
 
-        return frame;
+        frame
     }
 }
 
@@ -393,7 +420,7 @@ impl ReadAll<AttributeContext<'_>> for StackMapTableAttribute {}
 
 #[derive(Debug)]
 pub struct ExceptionIndexAttribute {
-    index: u16,
+    pub index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for ExceptionIndexAttribute {
@@ -412,7 +439,7 @@ impl ReadAll<AttributeContext<'_>> for ExceptionIndexAttribute {}
 
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    struct InnerClassAccessFlags: u16 {
+    pub struct InnerClassAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
         const PROTECTED = 0x0004;
@@ -428,10 +455,10 @@ bitflags::bitflags! {
 
 #[derive(Debug)]
 pub struct InnerClassAttribute {
-    inner_class_info_index: u16,
-    outer_class_info_index: u16,
-    inner_name_index: u16,
-    inner_class_access_flags: InnerClassAccessFlags,
+    pub inner_class_info_index: u16,
+    pub outer_class_info_index: u16,
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: InnerClassAccessFlags,
 }
 
 impl ReadOne<AttributeContext<'_>> for InnerClassAttribute {
@@ -462,8 +489,8 @@ impl ReadAll<AttributeContext<'_>> for InnerClassAttribute {}
 
 #[derive(Debug)]
 pub struct EnclosingMethodAttribute {
-    class_index: u16,
-    method_index: u16,
+    pub class_index: u16,
+    pub method_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for EnclosingMethodAttribute {
@@ -485,7 +512,7 @@ impl ReadOne<AttributeContext<'_>> for EnclosingMethodAttribute {
 
 #[derive(Debug)]
 pub struct SignatureAttribute {
-    signature_index: u16,
+    pub signature_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for SignatureAttribute {
@@ -503,7 +530,7 @@ impl ReadOne<AttributeContext<'_>> for SignatureAttribute {
 
 #[derive(Debug)]
 pub struct SourceFileAttribute {
-    sourcefile_index: u16,
+    pub sourcefile_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for SourceFileAttribute {
@@ -521,7 +548,7 @@ impl ReadOne<AttributeContext<'_>> for SourceFileAttribute {
 
 #[derive(Debug)]
 pub struct SourceDebugExtensionAttribute {
-    debug_info: Vec<u8>,
+    pub debug_info: Vec<u8>,
 }
 
 impl ReadOne<AttributeContext<'_>> for SourceDebugExtensionAttribute {
@@ -529,8 +556,7 @@ impl ReadOne<AttributeContext<'_>> for SourceDebugExtensionAttribute {
         reader: &mut R,
         context: &AttributeContext,
     ) -> Result<Self, ClassLoadingError> {
-        let mut debug_info = vec![0; context.length];
-        reader.read_exact(&mut debug_info)?;
+        let debug_info = read_bytes(reader, context.length)?;
 
         Ok(SourceDebugExtensionAttribute { debug_info })
     }
@@ -538,10 +564,10 @@ impl ReadOne<AttributeContext<'_>> for SourceDebugExtensionAttribute {
 
 // LineNumberTable Attribute ---------------------------------------------------
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LineNumberTableAttribute {
-    start_pc: u16,
-    line_number: u16,
+    pub start_pc: u16,
+    pub line_number: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for LineNumberTableAttribute {
@@ -565,11 +591,11 @@ impl ReadAll<AttributeContext<'_>> for LineNumberTableAttribute {}
 
 #[derive(Debug)]
 pub struct LocalVariableTableAttribute {
-    start_pc: u16,
-    length: u16,
-    name_index: u16,
-    descriptor_index: u16,
-    index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for LocalVariableTableAttribute {
@@ -599,11 +625,11 @@ impl ReadAll<AttributeContext<'_>> for LocalVariableTableAttribute {}
 
 #[derive(Debug)]
 pub struct LocalVariableTypeTableAttribute {
-    start_pc: u16,
-    length: u16,
-    name_index: u16,
-    signature_index: u16,
-    index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub signature_index: u16,
+    pub index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for LocalVariableTypeTableAttribute {
@@ -633,24 +659,26 @@ impl ReadAll<AttributeContext<'_>> for LocalVariableTypeTableAttribute {}
 
 #[derive(Debug)]
 pub struct ConstantElementValueAttribute {
-    const_value_index: u16,
+    /// The type of the constant, one of `BCDFIJSZs`.
+    pub tag: u8,
+    pub const_value_index: u16,
 }
 
-impl ReadOne<AttributeContext<'_>> for ConstantElementValueAttribute {
-    fn read_one<R: ReadBytesExt>(
-        reader: &mut R,
-        _context: &AttributeContext,
-    ) -> Result<Self, ClassLoadingError> {
+impl ConstantElementValueAttribute {
+    fn read_tagged<R: ReadBytesExt>(reader: &mut R, tag: u8) -> Result<Self, ClassLoadingError> {
         let const_value_index = reader.read_u16::<BigEndian>()?;
 
-        Ok(ConstantElementValueAttribute { const_value_index })
+        Ok(ConstantElementValueAttribute {
+            tag,
+            const_value_index,
+        })
     }
 }
 
 #[derive(Debug)]
 pub struct EnumElementValue {
-    type_name_index: u16,
-    const_name_index: u16,
+    pub type_name_index: u16,
+    pub const_name_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for EnumElementValue {
@@ -670,7 +698,7 @@ impl ReadOne<AttributeContext<'_>> for EnumElementValue {
 
 #[derive(Debug)]
 pub struct ClassElementValueAttribute {
-    class_info_index: u16,
+    pub class_info_index: u16,
 }
 
 impl ReadOne<AttributeContext<'_>> for ClassElementValueAttribute {
@@ -686,7 +714,7 @@ impl ReadOne<AttributeContext<'_>> for ClassElementValueAttribute {
 
 #[derive(Debug)]
 pub struct AnnotationElementValue {
-    annotation: AnnotationAttribute,
+    pub annotation: AnnotationAttribute,
 }
 
 impl ReadOne<AttributeContext<'_>> for AnnotationElementValue {
@@ -702,7 +730,7 @@ impl ReadOne<AttributeContext<'_>> for AnnotationElementValue {
 
 #[derive(Debug)]
 pub struct ArrayElementValue {
-    array_values: Vec<ElementValue>,
+    pub array_values: Vec<ElementValue>,
 }
 
 impl ReadOne<AttributeContext<'_>> for ArrayElementValue {
@@ -734,7 +762,7 @@ impl ReadOne<AttributeContext<'_>> for ElementValue {
 
         match tag {
             'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => Ok(ElementValue::Constant(
-                ConstantElementValueAttribute::read_one(reader, context)?,
+                ConstantElementValueAttribute::read_tagged(reader, tag as u8)?,
             )),
             'e' => Ok(ElementValue::Enum(EnumElementValue::read_one(
                 reader, context,
@@ -743,10 +771,12 @@ impl ReadOne<AttributeContext<'_>> for ElementValue {
                 reader, context,
             )?)),
             '@' => Ok(ElementValue::Annotation(AnnotationElementValue::read_one(
-                reader, context,
+                reader,
+                &context.nested()?,
             )?)),
             '[' => Ok(ElementValue::Array(ArrayElementValue::read_one(
-                reader, context,
+                reader,
+                &context.nested()?,
             )?)),
             _ => Err(ClassLoadingError::new(
                 "Unknown tag for annotation element value",
@@ -759,8 +789,8 @@ impl ReadAll<AttributeContext<'_>> for ElementValue {}
 
 #[derive(Debug)]
 pub struct ElementValuePair {
-    element_name_index: u16,
-    value: ElementValue,
+    pub element_name_index: u16,
+    pub value: ElementValue,
 }
 
 impl ReadOne<AttributeContext<'_>> for ElementValuePair {
@@ -787,8 +817,8 @@ impl ReadAll<AttributeContext<'_>> for ElementValuePair {}
 
 #[derive(Debug)]
 pub struct AnnotationAttribute {
-    type_index: u16,
-    element_value_pairs: Vec<ElementValuePair>,
+    pub type_index: u16,
+    pub element_value_pairs: Vec<ElementValuePair>,
 }
 
 impl ReadOne<AttributeContext<'_>> for AnnotationAttribute {
@@ -815,7 +845,7 @@ impl ReadAll<AttributeContext<'_>> for AnnotationAttribute {}
 
 #[derive(Debug)]
 pub struct ParameterAnnotationAttribute {
-    annotations: Vec<AnnotationAttribute>,
+    pub annotations: Vec<AnnotationAttribute>,
 }
 
 impl ReadOne<AttributeContext<'_>> for ParameterAnnotationAttribute {
@@ -840,7 +870,7 @@ impl ReadAll<AttributeContext<'_>> for ParameterAnnotationAttribute {
 
 #[derive(Debug)]
 pub struct AnnotationDefaultAttribute {
-    default_value: ElementValue,
+    pub default_value: ElementValue,
 }
 
 impl ReadOne<AttributeContext<'_>> for AnnotationDefaultAttribute {
@@ -858,8 +888,8 @@ impl ReadOne<AttributeContext<'_>> for AnnotationDefaultAttribute {
 
 #[derive(Debug)]
 pub struct BootstrapMethodAttribute {
-    bootstrap_method_ref: u16,
-    bootstrap_arguments: Vec<u16>,
+    pub bootstrap_method_ref: u16,
+    pub bootstrap_arguments: Vec<u16>,
 }
 
 impl ReadOne<AttributeContext<'_>> for BootstrapMethodAttribute {
@@ -882,12 +912,259 @@ impl ReadOne<AttributeContext<'_>> for BootstrapMethodAttribute {
 
 impl ReadAll<AttributeContext<'_>> for BootstrapMethodAttribute {}
 
+// NestHost Attribute ----------------------------------------------------------
+
+#[derive(Debug)]
+pub struct NestHostAttribute {
+    pub host_class_index: u16,
+}
+
+impl ReadOne<AttributeContext<'_>> for NestHostAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let host_class_index = reader.read_u16::<BigEndian>()?;
+
+        Ok(NestHostAttribute { host_class_index })
+    }
+}
+
+// NestMembers Attribute -------------------------------------------------------
+
+#[derive(Debug)]
+pub struct NestMemberAttribute {
+    pub class_index: u16,
+}
+
+impl ReadOne<AttributeContext<'_>> for NestMemberAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let class_index = reader.read_u16::<BigEndian>()?;
+        Ok(NestMemberAttribute { class_index })
+    }
+}
+
+impl ReadAll<AttributeContext<'_>> for NestMemberAttribute {}
+
+// Module Attribute ------------------------------------------------------------
+
+bitflags::bitflags! {
+    /// The flags of a module, and of the packages it exports or opens.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ModuleFlags: u16 {
+        const OPEN = 0x0020;
+        const SYNTHETIC = 0x1000;
+        const MANDATED = 0x8000;
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct RequiresFlags: u16 {
+        const TRANSITIVE = 0x0020;
+        const STATIC_PHASE = 0x0040;
+        const SYNTHETIC = 0x1000;
+        const MANDATED = 0x8000;
+    }
+}
+
+/// Reads the 16 bit count of constant indices, then the indices.
+fn read_indices<R: ReadBytesExt>(reader: &mut R) -> Result<Vec<u16>, ClassLoadingError> {
+    let count = reader.read_u16::<BigEndian>()? as usize;
+    let mut indices = vec![0; count];
+    reader.read_u16_into::<BigEndian>(&mut indices)?;
+    Ok(indices)
+}
+
+#[derive(Debug)]
+pub struct ModuleRequiresAttribute {
+    pub requires_index: u16,
+    pub requires_flags: RequiresFlags,
+    pub requires_version_index: u16,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModuleRequiresAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let requires_index = reader.read_u16::<BigEndian>()?;
+        let requires_flags = reader.read_u16::<BigEndian>()?;
+        let requires_flags = RequiresFlags::from_bits(requires_flags)
+            .ok_or(ClassLoadingError::new("Invalid module requires flags"))?;
+        let requires_version_index = reader.read_u16::<BigEndian>()?;
+
+        Ok(ModuleRequiresAttribute {
+            requires_index,
+            requires_flags,
+            requires_version_index,
+        })
+    }
+}
+
+impl ReadAll<AttributeContext<'_>> for ModuleRequiresAttribute {}
+
+/// An exported or an opened package, to the modules listed, or to all when
+/// none are.
+#[derive(Debug)]
+pub struct ModuleExportsAttribute {
+    pub package_index: u16,
+    pub flags: ModuleFlags,
+    pub to_indices: Vec<u16>,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModuleExportsAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let package_index = reader.read_u16::<BigEndian>()?;
+        let flags = reader.read_u16::<BigEndian>()?;
+        let flags = ModuleFlags::from_bits(flags)
+            .ok_or(ClassLoadingError::new("Invalid module exports flags"))?;
+        let to_indices = read_indices(reader)?;
+
+        Ok(ModuleExportsAttribute {
+            package_index,
+            flags,
+            to_indices,
+        })
+    }
+}
+
+impl ReadAll<AttributeContext<'_>> for ModuleExportsAttribute {}
+
+#[derive(Debug)]
+pub struct ModuleProvidesAttribute {
+    pub provides_index: u16,
+    pub provides_with_indices: Vec<u16>,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModuleProvidesAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let provides_index = reader.read_u16::<BigEndian>()?;
+        let provides_with_indices = read_indices(reader)?;
+
+        Ok(ModuleProvidesAttribute {
+            provides_index,
+            provides_with_indices,
+        })
+    }
+}
+
+impl ReadAll<AttributeContext<'_>> for ModuleProvidesAttribute {}
+
+#[derive(Debug)]
+pub struct ModuleAttribute {
+    pub module_name_index: u16,
+    pub module_flags: ModuleFlags,
+    pub module_version_index: u16,
+    pub requires: Vec<ModuleRequiresAttribute>,
+    pub exports: Vec<ModuleExportsAttribute>,
+    pub opens: Vec<ModuleExportsAttribute>,
+    pub uses_indices: Vec<u16>,
+    pub provides: Vec<ModuleProvidesAttribute>,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModuleAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let module_name_index = reader.read_u16::<BigEndian>()?;
+        let module_flags = reader.read_u16::<BigEndian>()?;
+        let module_flags = ModuleFlags::from_bits(module_flags)
+            .ok_or(ClassLoadingError::new("Invalid module flags"))?;
+        let module_version_index = reader.read_u16::<BigEndian>()?;
+        let requires = ModuleRequiresAttribute::read_all(reader, context)?;
+        let exports = ModuleExportsAttribute::read_all(reader, context)?;
+        let opens = ModuleExportsAttribute::read_all(reader, context)?;
+        let uses_indices = read_indices(reader)?;
+        let provides = ModuleProvidesAttribute::read_all(reader, context)?;
+
+        Ok(ModuleAttribute {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires,
+            exports,
+            opens,
+            uses_indices,
+            provides,
+        })
+    }
+}
+
+// ModulePackages Attribute ----------------------------------------------------
+
+#[derive(Debug)]
+pub struct ModulePackageAttribute {
+    pub package_index: u16,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModulePackageAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let package_index = reader.read_u16::<BigEndian>()?;
+        Ok(ModulePackageAttribute { package_index })
+    }
+}
+
+impl ReadAll<AttributeContext<'_>> for ModulePackageAttribute {}
+
+// ModuleMainClass Attribute ---------------------------------------------------
+
+#[derive(Debug)]
+pub struct ModuleMainClassAttribute {
+    pub main_class_index: u16,
+}
+
+impl ReadOne<AttributeContext<'_>> for ModuleMainClassAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let main_class_index = reader.read_u16::<BigEndian>()?;
+        Ok(ModuleMainClassAttribute { main_class_index })
+    }
+}
+
+// Scala Attributes ------------------------------------------------------------
+// Covers:
+//  - ScalaSig, holding the version of the pickle scalac stores in an
+//    annotation, or the whole pickle before Scala 2.8
+//  - Scala, marking classes without a pickle of their own
+
+#[derive(Debug)]
+pub struct ScalaAttribute {
+    pub info: Vec<u8>,
+}
+
+impl ReadOne<AttributeContext<'_>> for ScalaAttribute {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        context: &AttributeContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let info = read_bytes(reader, context.length)?;
+
+        Ok(ScalaAttribute { info })
+    }
+}
+
 // Misc Attribute --------------------------------------------------------------
 
 #[derive(Debug)]
 pub struct MiscAttribute {
-    name_index: usize,
-    info: Vec<u8>,
+    pub name_index: usize,
+    pub info: Vec<u8>,
 }
 
 impl ReadOne<AttributeContext<'_>> for MiscAttribute {
@@ -895,8 +1172,7 @@ impl ReadOne<AttributeContext<'_>> for MiscAttribute {
         reader: &mut R,
         context: &AttributeContext,
     ) -> Result<Self, ClassLoadingError> {
-        let mut info = vec![0; context.length];
-        reader.read_exact(&mut info)?;
+        let info = read_bytes(reader, context.length)?;
 
         Ok(MiscAttribute {
             name_index: context.name_index,
@@ -929,6 +1205,13 @@ pub enum Attribute {
     RuntimeInvisibleParameterAnnotations(Vec<ParameterAnnotationAttribute>),
     AnnotationDefault(AnnotationDefaultAttribute),
     BootstrapMethods(Vec<BootstrapMethodAttribute>),
+    NestHost(NestHostAttribute),
+    NestMembers(Vec<NestMemberAttribute>),
+    Module(ModuleAttribute),
+    ModulePackages(Vec<ModulePackageAttribute>),
+    ModuleMainClass(ModuleMainClassAttribute),
+    ScalaSig(ScalaAttribute),
+    Scala(ScalaAttribute),
     Misc(MiscAttribute),
 }
 
@@ -939,11 +1222,12 @@ impl<'a> ReadOne<ConstantPoolContext<'a>> for Attribute {
     ) -> Result<Self, ClassLoadingError> {
         let attribute_name_index = reader.read_u16::<BigEndian>()? as usize;
         let attribute_length = reader.read_u32::<BigEndian>()? as usize;
+        context.limits.check_attribute_length(attribute_length)?;
 
         // Dereference the name from the constant pool
-        let attribute_name = match &context.constant_pool[attribute_name_index] {
+        let attribute_name = match context.constant_pool.get(attribute_name_index) {
             // If the referenced constant is an UTF-8 reference, we are up to spec
-            Constant::Utf8(value) => Ok(&value.string),
+            Some(Constant::Utf8(value)) => Ok(&value.string),
             // Otherwise, we blow up, as nothing else is acceptable
             _ => Err(ClassLoadingError::new(
                 "Referenced attribute name should be an UTF-8 constant",
@@ -952,77 +1236,187 @@ impl<'a> ReadOne<ConstantPoolContext<'a>> for Attribute {
 
         let attribute_context = AttributeContext {
             constant_pool: context.constant_pool,
+            limits: context.limits,
             name_index: attribute_name_index,
             length: attribute_length,
+            depth: context.depth,
+            anomalies: context.anomalies,
         };
+        let _span = tracing::trace_span!(
+            "attribute",
+            attribute = attribute_name.as_str(),
+            length = attribute_length
+        )
+        .entered();
+
+        let anomalies = match context.anomalies {
+            Some(anomalies) if is_noncritical(attribute_name) => anomalies,
+            _ => {
+                // Behind a trait object, as the code attribute holds
+                // attributes of its own
+                let reader: &mut dyn Read = reader;
+                let mut content = reader.take(attribute_length as u64);
+                let attribute =
+                    Attribute::read_content(&mut content, attribute_name, &attribute_context)?;
+                check_length(attribute_name, attribute_length, content.limit())?;
+                return Ok(attribute);
+            }
+        };
+
+        // Read in full first, so that the bytes can be kept when they do not
+        // parse
+        let info = read_bytes(reader, attribute_length)?;
+        let mut content = Cursor::new(info.as_slice());
+        Attribute::read_content(&mut content, attribute_name, &attribute_context)
+            .and_then(|attribute| {
+                let unread = attribute_length as u64 - content.position();
+                check_length(attribute_name, attribute_length, unread)?;
+                Ok(attribute)
+            })
+            .or_else(|error| {
+                anomalies.borrow_mut().push(Anomaly::MalformedAttribute {
+                    name: attribute_name.to_string(),
+                    reason: error.to_string(),
+                });
+                Ok(Attribute::Misc(MiscAttribute {
+                    name_index: attribute_name_index,
+                    info,
+                }))
+            })
+    }
+}
+
+/// Fails for an attribute whose content leaves bytes of its length unread.
+fn check_length(name: &str, length: usize, unread: u64) -> Result<(), ClassLoadingError> {
+    if unread == 0 {
+        return Ok(());
+    }
+    Err(ClassLoadingError::new(&format!(
+        "{} attribute of {} bytes holds {}",
+        name,
+        length,
+        length as u64 - unread
+    )))
+}
 
-        let attribute = match attribute_name.as_str() {
+impl Attribute {
+    /// Reads the content of the attribute of the name.
+    fn read_content<R: ReadBytesExt>(
+        reader: &mut R,
+        name: &str,
+        attribute_context: &AttributeContext,
+    ) -> Result<Attribute, ClassLoadingError> {
+        let attribute = match name {
             "ConstantValue" => Attribute::ConstantValue(ConstantValueAttribute::read_one(
                 reader,
-                &attribute_context,
+                attribute_context,
             )?),
-            "Code" => Attribute::Code(CodeAttribute::read_one(reader, &attribute_context)?),
+            "Code" => Attribute::Code(CodeAttribute::read_one(reader, attribute_context)?),
             "StackMapTable" => Attribute::StackMapTable(StackMapTableAttribute::read_all(
                 reader,
-                &attribute_context,
+                attribute_context,
             )?),
             "Exceptions" => Attribute::Exceptions(ExceptionIndexAttribute::read_all(
                 reader,
-                &attribute_context,
+                attribute_context,
             )?),
             "InnerClasses" => {
-                Attribute::InnerClasses(InnerClassAttribute::read_all(reader, &attribute_context)?)
+                Attribute::InnerClasses(InnerClassAttribute::read_all(reader, attribute_context)?)
             }
             "EnclosingMethod" => Attribute::EnclosingMethod(EnclosingMethodAttribute::read_one(
                 reader,
-                &attribute_context,
+                attribute_context,
             )?),
             "Synthetic" => Attribute::Synthetic(),
             "Signature" => {
-                Attribute::Signature(SignatureAttribute::read_one(reader, &attribute_context)?)
+                Attribute::Signature(SignatureAttribute::read_one(reader, attribute_context)?)
             }
             "SourceFile" => {
-                Attribute::SourceFile(SourceFileAttribute::read_one(reader, &attribute_context)?)
+                Attribute::SourceFile(SourceFileAttribute::read_one(reader, attribute_context)?)
             }
             "SourceDebugExtension" => Attribute::SourceDebugExtension(
-                SourceDebugExtensionAttribute::read_one(reader, &attribute_context)?,
+                SourceDebugExtensionAttribute::read_one(reader, attribute_context)?,
             ),
             "LineNumberTable" => Attribute::LineNumberTable(LineNumberTableAttribute::read_all(
                 reader,
-                &attribute_context,
+                attribute_context,
             )?),
             "LocalVariableTable" => Attribute::LocalVariableTable(
-                LocalVariableTableAttribute::read_all(reader, &attribute_context)?,
+                LocalVariableTableAttribute::read_all(reader, attribute_context)?,
             ),
             "LocalVariableTypeTable" => Attribute::LocalVariableTypeTable(
-                LocalVariableTypeTableAttribute::read_all(reader, &attribute_context)?,
+                LocalVariableTypeTableAttribute::read_all(reader, attribute_context)?,
             ),
             "Deprecated" => Attribute::Deprecated(),
             "RuntimeVisibleAnnotations" => Attribute::RuntimeVisibleAnnotations(
-                AnnotationAttribute::read_all(reader, &attribute_context)?,
+                AnnotationAttribute::read_all(reader, attribute_context)?,
             ),
             "RuntimeInvisibleAnnotations" => Attribute::RuntimeInvisibleAnnotations(
-                AnnotationAttribute::read_all(reader, &attribute_context)?,
+                AnnotationAttribute::read_all(reader, attribute_context)?,
             ),
             "RuntimeVisibleParameterAnnotations" => Attribute::RuntimeVisibleParameterAnnotations(
-                ParameterAnnotationAttribute::read_all(reader, &attribute_context)?,
+                ParameterAnnotationAttribute::read_all(reader, attribute_context)?,
             ),
             "RuntimeInvisibleParameterAnnotations" => {
                 Attribute::RuntimeInvisibleParameterAnnotations(
-                    ParameterAnnotationAttribute::read_all(reader, &attribute_context)?,
+                    ParameterAnnotationAttribute::read_all(reader, attribute_context)?,
                 )
             }
             "AnnotationDefault" => Attribute::AnnotationDefault(
-                AnnotationDefaultAttribute::read_one(reader, &attribute_context)?,
+                AnnotationDefaultAttribute::read_one(reader, attribute_context)?,
             ),
             "BootstrapMethods" => Attribute::BootstrapMethods(BootstrapMethodAttribute::read_all(
                 reader,
-                &attribute_context,
+                attribute_context,
+            )?),
+            "NestHost" => {
+                Attribute::NestHost(NestHostAttribute::read_one(reader, attribute_context)?)
+            }
+            "NestMembers" => {
+                Attribute::NestMembers(NestMemberAttribute::read_all(reader, attribute_context)?)
+            }
+            "Module" => Attribute::Module(ModuleAttribute::read_one(reader, attribute_context)?),
+            "ModulePackages" => Attribute::ModulePackages(ModulePackageAttribute::read_all(
+                reader,
+                attribute_context,
             )?),
-            _ => Attribute::Misc(MiscAttribute::read_one(reader, &attribute_context)?),
+            "ModuleMainClass" => Attribute::ModuleMainClass(ModuleMainClassAttribute::read_one(
+                reader,
+                attribute_context,
+            )?),
+            "ScalaSig" => Attribute::ScalaSig(ScalaAttribute::read_one(reader, attribute_context)?),
+            "Scala" => Attribute::Scala(ScalaAttribute::read_one(reader, attribute_context)?),
+            _ => {
+                tracing::debug!("keeping unknown attribute as raw bytes");
+                Attribute::Misc(MiscAttribute::read_one(reader, attribute_context)?)
+            }
         };
         Ok(attribute)
     }
 }
 
+/// Whether the JVM runs classes without the attribute, which hostile input
+/// may then hold malformed. The annotations and signatures are read by
+/// reflection only.
+fn is_noncritical(name: &str) -> bool {
+    matches!(
+        name,
+        "SourceFile"
+            | "SourceDebugExtension"
+            | "LineNumberTable"
+            | "LocalVariableTable"
+            | "LocalVariableTypeTable"
+            | "Deprecated"
+            | "Synthetic"
+            | "Signature"
+            | "RuntimeVisibleAnnotations"
+            | "RuntimeInvisibleAnnotations"
+            | "RuntimeVisibleParameterAnnotations"
+            | "RuntimeInvisibleParameterAnnotations"
+            | "AnnotationDefault"
+            | "ScalaSig"
+            | "Scala"
+    )
+}
+
 impl ReadAll<ConstantPoolContext<'_>> for Attribute {}
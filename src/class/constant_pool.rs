@@ -1,9 +1,12 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::ops::Index;
 
 use byteorder::{BigEndian, ReadBytesExt};
 
-use crate::class::{ClassLoadingError, EmptyContext, ReadAll, ReadOne};
+use crate::class::{Anomaly, ClassLoadingError, EmptyContext, ParseLimits, ReadAll, ReadOne};
 
 // =============================================================================
 // CONTEXT
@@ -11,14 +14,34 @@ use crate::class::{ClassLoadingError, EmptyContext, ReadAll, ReadOne};
 
 pub struct ConstantPoolContext<'a> {
     pub constant_pool: &'a ConstantPool,
+    pub limits: &'a ParseLimits,
+    /// How deep the attributes being read are nested in other attributes.
+    pub depth: usize,
+    /// Where the anomalies are recorded when parsing hostile input, `None`
+    /// when parsing strictly.
+    pub anomalies: Option<&'a RefCell<Vec<Anomaly>>>,
 }
 
 impl<'a> ConstantPoolContext<'a> {
-    pub fn new(constant_pool: &'a ConstantPool) -> ConstantPoolContext {
-        ConstantPoolContext { constant_pool }
+    pub fn new(
+        constant_pool: &'a ConstantPool,
+        limits: &'a ParseLimits,
+    ) -> ConstantPoolContext<'a> {
+        ConstantPoolContext {
+            constant_pool,
+            limits,
+            depth: 0,
+            anomalies: None,
+        }
     }
 }
 
+/// Context usable when reading [Constant] elements.
+pub(crate) struct ConstantContext<'a> {
+    pub limits: &'a ParseLimits,
+    pub anomalies: Option<&'a RefCell<Vec<Anomaly>>>,
+}
+
 // =============================================================================
 // CONSTANT POOL
 // =============================================================================
@@ -27,7 +50,7 @@ impl<'a> ConstantPoolContext<'a> {
 
 #[derive(Debug)]
 pub struct ConstClass {
-    name_index: u16,
+    pub name_index: u16,
 }
 
 impl ReadOne for ConstClass {
@@ -49,8 +72,8 @@ impl ReadOne for ConstClass {
 
 #[derive(Debug)]
 pub struct ConstClassReference {
-    class_index: u16,
-    name_and_type_index: u16,
+    pub class_index: u16,
+    pub name_and_type_index: u16,
 }
 
 impl ReadOne for ConstClassReference {
@@ -72,7 +95,7 @@ impl ReadOne for ConstClassReference {
 
 #[derive(Debug)]
 pub struct ConstString {
-    string_index: u16,
+    pub string_index: u16,
 }
 
 impl ReadOne for ConstString {
@@ -90,7 +113,7 @@ impl ReadOne for ConstString {
 
 #[derive(Debug)]
 pub struct ConstInteger {
-    value: i32,
+    pub value: i32,
 }
 
 impl ReadOne for ConstInteger {
@@ -108,7 +131,7 @@ impl ReadOne for ConstInteger {
 
 #[derive(Debug)]
 pub struct ConstFloat {
-    value: f32,
+    pub value: f32,
 }
 
 impl ReadOne for ConstFloat {
@@ -126,7 +149,7 @@ impl ReadOne for ConstFloat {
 
 #[derive(Debug)]
 pub struct ConstLong {
-    value: i64,
+    pub value: i64,
 }
 
 impl ReadOne for ConstLong {
@@ -144,7 +167,7 @@ impl ReadOne for ConstLong {
 
 #[derive(Debug)]
 pub struct ConstDouble {
-    value: f64,
+    pub value: f64,
 }
 
 impl ReadOne for ConstDouble {
@@ -162,8 +185,8 @@ impl ReadOne for ConstDouble {
 
 #[derive(Debug)]
 pub struct ConstNameAndType {
-    name_index: u16,
-    descriptor_index: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
 }
 
 impl ReadOne for ConstNameAndType {
@@ -189,7 +212,7 @@ pub struct ConstUtf8 {
 }
 
 impl ConstUtf8 {
-    fn str_length(bytes: &Vec<u8>) -> Result<usize, ClassLoadingError> {
+    pub fn str_length(bytes: &[u8]) -> Result<usize, ClassLoadingError> {
         let mut size = 0;
         let mut index = 0;
         while index < bytes.len() {
@@ -224,25 +247,90 @@ impl ConstUtf8 {
         }
     }
 
-    // fn convert_bytes(bytes: &Vec<u8>) -> Result<String, ClassLoadError> {
-    //     let length = Self::str_length(bytes)?;
-    //     let mut string = String::with_capacity(length);
-    //
-    //     return Ok(string);
-    // }
+    /// Decodes the modified UTF-8 of class files, which encodes U+0000 in two
+    /// bytes and supplementary characters as two surrogates of three bytes.
+    pub fn decode(bytes: Vec<u8>) -> Result<String, ClassLoadingError> {
+        let bytes = match String::from_utf8(bytes) {
+            Ok(string) => return Ok(string),
+            Err(error) => error.into_bytes(),
+        };
+
+        let surrogate = |bytes: &[u8], marker: u8| match bytes {
+            [0xED, second, third, ..] if second & 0xF0 == marker => {
+                Some((((second & 0x0F) as u32) << 6) | (third & 0x3F) as u32)
+            }
+            _ => None,
+        };
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            let rest = &bytes[index..];
+            if rest.starts_with(&[0xC0, 0x80]) {
+                decoded.push(0);
+                index += 2;
+                continue;
+            }
+            let pair =
+                surrogate(rest, 0xA0).zip(rest.get(3..).and_then(|low| surrogate(low, 0xB0)));
+            match pair.and_then(|(high, low)| char::from_u32(0x10000 + (high << 10) + low)) {
+                Some(character) => {
+                    let mut buffer = [0; 4];
+                    decoded.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                    index += 6;
+                }
+                None => {
+                    decoded.push(bytes[index]);
+                    index += 1;
+                }
+            }
+        }
+        Ok(String::from_utf8(decoded)?)
+    }
+
+    /// Encodes the string in the modified UTF-8 of class files.
+    pub fn encode(string: &str) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(string.len());
+        for character in string.chars() {
+            match character as u32 {
+                0 => encoded.extend_from_slice(&[0xC0, 0x80]),
+                0x10000.. => {
+                    for unit in character.encode_utf16(&mut [0; 2]) {
+                        encoded.extend_from_slice(&[
+                            0xE0 | (*unit >> 12) as u8,
+                            0x80 | ((*unit >> 6) & 0x3F) as u8,
+                            0x80 | (*unit & 0x3F) as u8,
+                        ]);
+                    }
+                }
+                _ => {
+                    let mut buffer = [0; 4];
+                    encoded.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                }
+            }
+        }
+        encoded
+    }
 }
 
-impl ReadOne for ConstUtf8 {
+impl ReadOne<ConstantContext<'_>> for ConstUtf8 {
     fn read_one<R: ReadBytesExt>(
         reader: &mut R,
-        _: &EmptyContext,
+        context: &ConstantContext,
     ) -> Result<Self, ClassLoadingError> {
         let length = reader.read_u16::<BigEndian>()?;
 
         let mut bytes: Vec<u8> = vec![0; length as usize];
         reader.read_exact(&mut bytes)?;
-        // let string = Self::convert_bytes(&bytes)?;
-        let string = String::from_utf8(bytes)?;
+        let string = match context.anomalies {
+            Some(anomalies) => Self::decode(bytes.clone()).unwrap_or_else(|_| {
+                let string = String::from_utf8_lossy(&bytes).into_owned();
+                anomalies.borrow_mut().push(Anomaly::InvalidUtf8 {
+                    string: string.clone(),
+                });
+                string
+            }),
+            None => Self::decode(bytes)?,
+        };
 
         Ok(ConstUtf8 { string })
     }
@@ -252,8 +340,8 @@ impl ReadOne for ConstUtf8 {
 
 #[derive(Debug)]
 pub struct ConstMethodHandle {
-    reference_kind: u8,
-    reference_index: u16,
+    pub reference_kind: u8,
+    pub reference_index: u16,
 }
 
 impl ReadOne for ConstMethodHandle {
@@ -275,7 +363,7 @@ impl ReadOne for ConstMethodHandle {
 
 #[derive(Debug)]
 pub struct ConstMethodType {
-    descriptor_index: u16,
+    pub descriptor_index: u16,
 }
 
 impl ReadOne for ConstMethodType {
@@ -292,8 +380,8 @@ impl ReadOne for ConstMethodType {
 
 #[derive(Debug)]
 pub struct ConstInvokeDynamic {
-    bootstrap_method_attr_index: u16,
-    name_and_type_index: u16,
+    pub bootstrap_method_attr_index: u16,
+    pub name_and_type_index: u16,
 }
 
 impl ReadOne for ConstInvokeDynamic {
@@ -311,13 +399,42 @@ impl ReadOne for ConstInvokeDynamic {
     }
 }
 
-// Constant --------------------------------------------------------------------
+// ConstantModule --------------------------------------------------------------
 
-pub struct Skip<T> {
-    value: T,
-    skip: usize,
+#[derive(Debug)]
+pub struct ConstModule {
+    pub name_index: u16,
+}
+
+impl ReadOne for ConstModule {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _: &EmptyContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let name_index = reader.read_u16::<BigEndian>()?;
+        Ok(ConstModule { name_index })
+    }
 }
 
+// ConstantPackage -------------------------------------------------------------
+
+#[derive(Debug)]
+pub struct ConstPackage {
+    pub name_index: u16,
+}
+
+impl ReadOne for ConstPackage {
+    fn read_one<R: ReadBytesExt>(
+        reader: &mut R,
+        _: &EmptyContext,
+    ) -> Result<Self, ClassLoadingError> {
+        let name_index = reader.read_u16::<BigEndian>()?;
+        Ok(ConstPackage { name_index })
+    }
+}
+
+// Constant --------------------------------------------------------------------
+
 #[derive(Debug)]
 pub enum Constant {
     Utf8(ConstUtf8),
@@ -334,18 +451,48 @@ pub enum Constant {
     MethodHandle(ConstMethodHandle),
     MethodType(ConstMethodType),
     InvokeDynamic(ConstInvokeDynamic),
+    Module(ConstModule),
+    Package(ConstPackage),
+}
+
+impl Constant {
+    /// The name of the kind of the constant, as `javap` prints it, e.g.
+    /// `Methodref`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Constant::Utf8(_) => "Utf8",
+            Constant::Integer(_) => "Integer",
+            Constant::Float(_) => "Float",
+            Constant::Long(_) => "Long",
+            Constant::Double(_) => "Double",
+            Constant::Class(_) => "Class",
+            Constant::String(_) => "String",
+            Constant::Field(_) => "Fieldref",
+            Constant::Method(_) => "Methodref",
+            Constant::InterfaceMethod(_) => "InterfaceMethodref",
+            Constant::NameAndType(_) => "NameAndType",
+            Constant::MethodHandle(_) => "MethodHandle",
+            Constant::MethodType(_) => "MethodType",
+            Constant::InvokeDynamic(_) => "InvokeDynamic",
+            Constant::Module(_) => "Module",
+            Constant::Package(_) => "Package",
+        }
+    }
 }
 
-impl ReadOne for Constant {
+impl ReadOne<ConstantContext<'_>> for Constant {
     fn read_one<R: ReadBytesExt>(
         reader: &mut R,
-        _: &EmptyContext,
+        constant_context: &ConstantContext,
     ) -> Result<Self, ClassLoadingError> {
         let tag = reader.read_u8()?;
 
         let context = EmptyContext::default();
         let constant = match tag {
-            1 => Ok(Constant::Utf8(ConstUtf8::read_one(reader, &context)?)),
+            1 => Ok(Constant::Utf8(ConstUtf8::read_one(
+                reader,
+                constant_context,
+            )?)),
             3 => Ok(Constant::Integer(ConstInteger::read_one(reader, &context)?)),
             4 => Ok(Constant::Float(ConstFloat::read_one(reader, &context)?)),
             5 => Ok(Constant::Long(ConstLong::read_one(reader, &context)?)),
@@ -373,18 +520,20 @@ impl ReadOne for Constant {
             18 => Ok(Constant::InvokeDynamic(ConstInvokeDynamic::read_one(
                 reader, &context,
             )?)),
+            19 => Ok(Constant::Module(ConstModule::read_one(reader, &context)?)),
+            20 => Ok(Constant::Package(ConstPackage::read_one(reader, &context)?)),
             _ => Err(ClassLoadingError::new("Cannot match constant tag")),
         }?;
         Ok(constant)
     }
 }
 
-impl ReadAll for Constant {
+impl ReadAll<ConstantContext<'_>> for Constant {
     fn skip_amount(element: &Constant) -> usize {
-        return match *element {
+        match *element {
             Constant::Long(_) | Constant::Double(_) => 1,
             _ => 0,
-        };
+        }
     }
 }
 
@@ -392,36 +541,212 @@ impl ReadAll for Constant {
 
 #[derive(Debug)]
 pub struct ConstantPool {
-    constants: Vec<Constant>,
-    skip_table: Vec<usize>,
+    pub constants: Vec<Constant>,
+    pub skip_table: Vec<usize>,
 }
 
 impl ConstantPool {
-    fn assemble_skip_table(constants: &Vec<Constant>) -> Vec<usize> {
+    pub fn new(constants: Vec<Constant>) -> ConstantPool {
+        let skip_table = ConstantPool::assemble_skip_table(&constants);
+        ConstantPool {
+            constants,
+            skip_table,
+        }
+    }
+
+    /// Collects the slots (zero based) taken by the `Long` and `Double`
+    /// constants, each of which also occupies the slot following it.
+    fn assemble_skip_table(constants: &[Constant]) -> Vec<usize> {
         let mut skip_table = Vec::new();
         for (i, value) in constants.iter().enumerate() {
             match *value {
-                Constant::Long(_) | Constant::Double(_) => skip_table.push(i),
+                Constant::Long(_) | Constant::Double(_) => skip_table.push(i + skip_table.len()),
                 _ => {}
             }
         }
 
-        return skip_table;
+        skip_table
+    }
+
+    /// Returns the constant at the given (one based) index, or `None` if the
+    /// index is out of range or points into the second slot of a wide constant.
+    pub fn get(&self, index: usize) -> Option<&Constant> {
+        let slot = index.checked_sub(1)?;
+        if slot > 0 && self.skip_table.contains(&(slot - 1)) {
+            return None;
+        }
+
+        let skips = self.skip_table.iter().filter(|x| **x < slot).count();
+        self.constants.get(slot - skips)
+    }
+
+    /// Dereferences a UTF-8 constant.
+    pub fn get_utf8(&self, index: u16) -> Result<&str, ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::Utf8(value)) => Ok(&value.string),
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not an UTF-8 constant", index).as_str(),
+            )),
+        }
+    }
+
+    /// Dereferences a class constant to the internal name of the class.
+    pub fn get_class_name(&self, index: u16) -> Result<&str, ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::Class(value)) => self.get_utf8(value.name_index),
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not a class constant", index).as_str(),
+            )),
+        }
+    }
+
+    /// Dereferences a module constant to the name of the module, e.g.
+    /// `java.base`.
+    pub fn get_module_name(&self, index: u16) -> Result<&str, ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::Module(value)) => self.get_utf8(value.name_index),
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not a module constant", index).as_str(),
+            )),
+        }
+    }
+
+    /// Dereferences a package constant to the internal name of the package,
+    /// e.g. `java/lang`.
+    pub fn get_package_name(&self, index: u16) -> Result<&str, ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::Package(value)) => self.get_utf8(value.name_index),
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not a package constant", index).as_str(),
+            )),
+        }
+    }
+
+    /// Dereferences a field, method or interface method constant to the
+    /// internal name of its class, its name and its descriptor.
+    pub fn get_member(&self, index: u16) -> Result<(&str, &str, &str), ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::Field(reference))
+            | Some(Constant::Method(reference))
+            | Some(Constant::InterfaceMethod(reference)) => {
+                let (name, descriptor) = self.get_name_and_type(reference.name_and_type_index)?;
+                Ok((
+                    self.get_class_name(reference.class_index)?,
+                    name,
+                    descriptor,
+                ))
+            }
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not a member reference", index).as_str(),
+            )),
+        }
+    }
+
+    /// Dereferences a name and type constant to the name and descriptor.
+    pub fn get_name_and_type(&self, index: u16) -> Result<(&str, &str), ClassLoadingError> {
+        match self.get(index as usize) {
+            Some(Constant::NameAndType(value)) => Ok((
+                self.get_utf8(value.name_index)?,
+                self.get_utf8(value.descriptor_index)?,
+            )),
+            _ => Err(ClassLoadingError::new(
+                format!("Constant #{} is not a name and type constant", index).as_str(),
+            )),
+        }
+    }
+
+    /// Renders a constant referenced by an instruction like `javap` does,
+    /// e.g. `java/io/PrintStream.println:(I)V` or `"text"`, falling back to
+    /// its index.
+    pub fn describe(&self, index: u16) -> String {
+        let description = match self.get(index as usize) {
+            Some(Constant::Class(_)) => self.get_class_name(index).ok().map(str::to_string),
+            Some(Constant::Field(_))
+            | Some(Constant::Method(_))
+            | Some(Constant::InterfaceMethod(_)) => self
+                .get_member(index)
+                .ok()
+                .map(|(class, name, descriptor)| format!("{}.{}:{}", class, name, descriptor)),
+            Some(Constant::String(string)) => self
+                .get_utf8(string.string_index)
+                .ok()
+                .map(|string| format!("{:?}", string)),
+            Some(Constant::Integer(integer)) => Some(integer.value.to_string()),
+            Some(Constant::Float(float)) => Some(format!("{:?}f", float.value)),
+            Some(Constant::Long(long)) => Some(format!("{}l", long.value)),
+            Some(Constant::Double(double)) => Some(format!("{:?}d", double.value)),
+            Some(Constant::MethodType(method_type)) => self
+                .get_utf8(method_type.descriptor_index)
+                .ok()
+                .map(str::to_string),
+            Some(Constant::InvokeDynamic(invoke_dynamic)) => self
+                .get_name_and_type(invoke_dynamic.name_and_type_index)
+                .ok()
+                .map(|(name, descriptor)| format!("{}:{}", name, descriptor)),
+            Some(Constant::Module(_)) => self.get_module_name(index).ok().map(str::to_string),
+            Some(Constant::Package(_)) => self.get_package_name(index).ok().map(str::to_string),
+            _ => None,
+        };
+        description.unwrap_or_else(|| format!("#{}", index))
+    }
+
+    /// Renders any constant like the constant pool listing of `javap -v`,
+    /// e.g. `name:descriptor` for a name and type, where [describe] only
+    /// renders the constants instructions reference.
+    ///
+    /// [describe]: ConstantPool::describe
+    pub fn describe_entry(&self, index: u16) -> String {
+        match self.get(index as usize) {
+            Some(Constant::Utf8(utf8)) => utf8.string.clone(),
+            Some(Constant::NameAndType(_)) => match self.get_name_and_type(index) {
+                Ok((name, descriptor)) => format!("{}:{}", name, descriptor),
+                Err(_) => format!("#{}", index),
+            },
+            Some(Constant::MethodHandle(handle)) => format!(
+                "{} {}",
+                reference_kind(handle.reference_kind),
+                self.describe(handle.reference_index)
+            ),
+            Some(Constant::InvokeDynamic(invoke_dynamic)) => format!(
+                "#{}:{}",
+                invoke_dynamic.bootstrap_method_attr_index,
+                self.describe(index)
+            ),
+            _ => self.describe(index),
+        }
+    }
+
+    /// The index of the first UTF-8 constant holding the string.
+    pub fn find_utf8(&self, string: &str) -> Option<u16> {
+        self.iter().find_map(|(index, constant)| match constant {
+            Constant::Utf8(value) if value.string == string => u16::try_from(index).ok(),
+            _ => None,
+        })
+    }
+
+    /// Iterates over the constants together with their (one based) indices.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Constant)> {
+        let mut index = 1;
+        self.constants.iter().map(move |constant| {
+            let current = index;
+            index += match constant {
+                Constant::Long(_) | Constant::Double(_) => 2,
+                _ => 1,
+            };
+            (current, constant)
+        })
     }
 }
 
-impl ReadOne for ConstantPool {
+impl ReadOne<ConstantContext<'_>> for ConstantPool {
     fn read_one<R: ReadBytesExt>(
         reader: &mut R,
-        context: &EmptyContext,
+        context: &ConstantContext,
     ) -> Result<Self, ClassLoadingError> {
-        let constants = Constant::read_all_from(reader, context, 1)?;
-        let mut skip_table = ConstantPool::assemble_skip_table(&constants);
-
-        Ok(ConstantPool {
-            constants,
-            skip_table,
-        })
+        let count = Constant::read_count(reader)?;
+        context.limits.check_constants(count)?;
+        let constants = Constant::read_counted(reader, context, 1, count)?;
+        Ok(ConstantPool::new(constants))
     }
 }
 
@@ -429,12 +754,8 @@ impl Index<usize> for ConstantPool {
     type Output = Constant;
 
     fn index(&self, index: usize) -> &Self::Output {
-        let vec_index = (index - 1) as usize;
-
-        let skips: usize = self.skip_table.iter().filter(|x| x < &&vec_index).count();
-        let skipped_index = vec_index - skips;
-
-        return &self.constants[skipped_index];
+        self.get(index)
+            .unwrap_or_else(|| panic!("Invalid constant pool index {}", index))
     }
 }
 
@@ -443,7 +764,338 @@ impl Index<u16> for ConstantPool {
 
     fn index(&self, index: u16) -> &Self::Output {
         let index = index as usize;
-        return ConstantPool::index(self, index);
+        ConstantPool::index(self, index)
+    }
+}
+
+// =============================================================================
+// CONSTANT POOL BUILDER
+// =============================================================================
+
+/// The largest `constant_pool_count` of a class file, one more than the
+/// largest index.
+const MAX_CONSTANT_POOL_COUNT: usize = u16::MAX as usize;
+
+/// A constant by value, floating point ones by their bits, so that equal
+/// constants can be found in a map.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Utf8(String),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    Field(u16, u16),
+    Method(u16, u16),
+    InterfaceMethod(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+impl ConstantKey {
+    fn of(constant: &Constant) -> ConstantKey {
+        match constant {
+            Constant::Utf8(utf8) => ConstantKey::Utf8(utf8.string.clone()),
+            Constant::Integer(integer) => ConstantKey::Integer(integer.value),
+            Constant::Float(float) => ConstantKey::Float(float.value.to_bits()),
+            Constant::Long(long) => ConstantKey::Long(long.value),
+            Constant::Double(double) => ConstantKey::Double(double.value.to_bits()),
+            Constant::Class(class) => ConstantKey::Class(class.name_index),
+            Constant::String(string) => ConstantKey::String(string.string_index),
+            Constant::Field(reference) => {
+                ConstantKey::Field(reference.class_index, reference.name_and_type_index)
+            }
+            Constant::Method(reference) => {
+                ConstantKey::Method(reference.class_index, reference.name_and_type_index)
+            }
+            Constant::InterfaceMethod(reference) => {
+                ConstantKey::InterfaceMethod(reference.class_index, reference.name_and_type_index)
+            }
+            Constant::NameAndType(name_and_type) => {
+                ConstantKey::NameAndType(name_and_type.name_index, name_and_type.descriptor_index)
+            }
+            Constant::MethodHandle(handle) => {
+                ConstantKey::MethodHandle(handle.reference_kind, handle.reference_index)
+            }
+            Constant::MethodType(method_type) => {
+                ConstantKey::MethodType(method_type.descriptor_index)
+            }
+            Constant::InvokeDynamic(invoke_dynamic) => ConstantKey::InvokeDynamic(
+                invoke_dynamic.bootstrap_method_attr_index,
+                invoke_dynamic.name_and_type_index,
+            ),
+            Constant::Module(module) => ConstantKey::Module(module.name_index),
+            Constant::Package(package) => ConstantKey::Package(package.name_index),
+        }
+    }
+
+    fn to_constant(&self) -> Constant {
+        let reference = |class_index, name_and_type_index| ConstClassReference {
+            class_index,
+            name_and_type_index,
+        };
+        match *self {
+            ConstantKey::Utf8(ref string) => Constant::Utf8(ConstUtf8 {
+                string: string.clone(),
+            }),
+            ConstantKey::Integer(value) => Constant::Integer(ConstInteger { value }),
+            ConstantKey::Float(bits) => Constant::Float(ConstFloat {
+                value: f32::from_bits(bits),
+            }),
+            ConstantKey::Long(value) => Constant::Long(ConstLong { value }),
+            ConstantKey::Double(bits) => Constant::Double(ConstDouble {
+                value: f64::from_bits(bits),
+            }),
+            ConstantKey::Class(name_index) => Constant::Class(ConstClass { name_index }),
+            ConstantKey::String(string_index) => Constant::String(ConstString { string_index }),
+            ConstantKey::Field(class, name_and_type) => {
+                Constant::Field(reference(class, name_and_type))
+            }
+            ConstantKey::Method(class, name_and_type) => {
+                Constant::Method(reference(class, name_and_type))
+            }
+            ConstantKey::InterfaceMethod(class, name_and_type) => {
+                Constant::InterfaceMethod(reference(class, name_and_type))
+            }
+            ConstantKey::NameAndType(name_index, descriptor_index) => {
+                Constant::NameAndType(ConstNameAndType {
+                    name_index,
+                    descriptor_index,
+                })
+            }
+            ConstantKey::MethodHandle(reference_kind, reference_index) => {
+                Constant::MethodHandle(ConstMethodHandle {
+                    reference_kind,
+                    reference_index,
+                })
+            }
+            ConstantKey::MethodType(descriptor_index) => {
+                Constant::MethodType(ConstMethodType { descriptor_index })
+            }
+            ConstantKey::InvokeDynamic(bootstrap_method_attr_index, name_and_type_index) => {
+                Constant::InvokeDynamic(ConstInvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                })
+            }
+            ConstantKey::Module(name_index) => Constant::Module(ConstModule { name_index }),
+            ConstantKey::Package(name_index) => Constant::Package(ConstPackage { name_index }),
+        }
+    }
+
+    fn is_wide(&self) -> bool {
+        matches!(self, ConstantKey::Long(_) | ConstantKey::Double(_))
+    }
+}
+
+/// Builds a constant pool for writing a class, adding each distinct
+/// constant once and returning the index it is found at.
+#[derive(Debug)]
+pub struct ConstantPoolBuilder {
+    constants: Vec<ConstantKey>,
+    indices: HashMap<ConstantKey, u16>,
+    /// The index of the next constant, and the `constant_pool_count` of the
+    /// constants so far.
+    next_index: usize,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        ConstantPoolBuilder {
+            constants: Vec::new(),
+            indices: HashMap::new(),
+            next_index: 1,
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> ConstantPoolBuilder {
+        ConstantPoolBuilder::default()
+    }
+
+    /// A builder holding the constants of the pool at their indices, to
+    /// which the constants a transformed class needs can be added.
+    pub fn from_pool(pool: &ConstantPool) -> ConstantPoolBuilder {
+        let mut builder = ConstantPoolBuilder::new();
+        for (index, constant) in pool.iter() {
+            let key = ConstantKey::of(constant);
+            builder.next_index = index + if key.is_wide() { 2 } else { 1 };
+            builder.indices.entry(key.clone()).or_insert(index as u16);
+            builder.constants.push(key);
+        }
+        builder
+    }
+
+    /// The `constant_pool_count` of the pool: one more than the largest
+    /// index, counting both slots of the wide constants.
+    pub fn count(&self) -> usize {
+        self.next_index
+    }
+
+    /// Adds the constant unless an equal one was added already, returning
+    /// its index either way. Fails once the pool holds no more room for it.
+    pub fn add(&mut self, constant: &Constant) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::of(constant))
+    }
+
+    fn intern(&mut self, key: ConstantKey) -> Result<u16, ClassLoadingError> {
+        if let Some(&index) = self.indices.get(&key) {
+            return Ok(index);
+        }
+        let slots = if key.is_wide() { 2 } else { 1 };
+        if self.next_index + slots > MAX_CONSTANT_POOL_COUNT {
+            return Err(ClassLoadingError::new(
+                format!(
+                    "Constant pool is full, it cannot hold more than {} entries",
+                    MAX_CONSTANT_POOL_COUNT - 1
+                )
+                .as_str(),
+            ));
+        }
+        let index = self.next_index as u16;
+        self.next_index += slots;
+        self.indices.insert(key.clone(), index);
+        self.constants.push(key);
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, string: &str) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::Utf8(string.to_string()))
+    }
+
+    pub fn integer(&mut self, value: i32) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::Integer(value))
+    }
+
+    pub fn float(&mut self, value: f32) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::Float(value.to_bits()))
+    }
+
+    pub fn long(&mut self, value: i64) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::Long(value))
+    }
+
+    pub fn double(&mut self, value: f64) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::Double(value.to_bits()))
+    }
+
+    /// Adds a class constant of the internal name, e.g. `java/lang/Object`.
+    pub fn class(&mut self, name: &str) -> Result<u16, ClassLoadingError> {
+        let name_index = self.utf8(name)?;
+        self.intern(ConstantKey::Class(name_index))
+    }
+
+    pub fn string(&mut self, string: &str) -> Result<u16, ClassLoadingError> {
+        let string_index = self.utf8(string)?;
+        self.intern(ConstantKey::String(string_index))
+    }
+
+    pub fn name_and_type(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ClassLoadingError> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        self.intern(ConstantKey::NameAndType(name_index, descriptor_index))
+    }
+
+    pub fn field(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ClassLoadingError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(ConstantKey::Field(class_index, name_and_type_index))
+    }
+
+    pub fn method(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ClassLoadingError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(ConstantKey::Method(class_index, name_and_type_index))
+    }
+
+    pub fn interface_method(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ClassLoadingError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(ConstantKey::InterfaceMethod(
+            class_index,
+            name_and_type_index,
+        ))
+    }
+
+    /// Adds a method handle of the kind (1 to 9, e.g. 6 for `invokestatic`)
+    /// to the field or method constant.
+    pub fn method_handle(
+        &mut self,
+        reference_kind: u8,
+        reference_index: u16,
+    ) -> Result<u16, ClassLoadingError> {
+        self.intern(ConstantKey::MethodHandle(reference_kind, reference_index))
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> Result<u16, ClassLoadingError> {
+        let descriptor_index = self.utf8(descriptor)?;
+        self.intern(ConstantKey::MethodType(descriptor_index))
+    }
+
+    /// Adds a dynamic call site bootstrapped by the entry of the
+    /// `BootstrapMethods` attribute.
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ClassLoadingError> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(ConstantKey::InvokeDynamic(
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        ))
+    }
+
+    pub fn build(self) -> ConstantPool {
+        ConstantPool::new(
+            self.constants
+                .iter()
+                .map(ConstantKey::to_constant)
+                .collect(),
+        )
+    }
+}
+
+/// The name of the kind of a method handle, as `javap` prints it.
+fn reference_kind(kind: u8) -> &'static str {
+    match kind {
+        1 => "REF_getField",
+        2 => "REF_getStatic",
+        3 => "REF_putField",
+        4 => "REF_putStatic",
+        5 => "REF_invokeVirtual",
+        6 => "REF_invokeStatic",
+        7 => "REF_invokeSpecial",
+        8 => "REF_newInvokeSpecial",
+        9 => "REF_invokeInterface",
+        _ => "REF_unknown",
     }
 }
 
@@ -460,6 +1112,113 @@ mod const_utf8_tests {
         let bytes = vec![0x0f, 0x0f];
         let len = ConstUtf8::str_length(&bytes);
 
-        assert_eq!(len,)
+        assert_eq!(len.unwrap(), 2)
+    }
+
+    #[test]
+    fn test_modified_utf8() {
+        // U+0000 in two bytes, U+1F600 as the surrogates D83D and DE00
+        let bytes = vec![b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+        let string = ConstUtf8::decode(bytes.clone()).unwrap();
+        assert_eq!(string, "a\0\u{1F600}");
+        assert_eq!(ConstUtf8::encode(&string), bytes);
+
+        // A lone surrogate has no character to decode to
+        assert!(ConstUtf8::decode(vec![0xED, 0xA0, 0xBD]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod constant_pool_tests {
+    use super::{ConstLong, ConstUtf8, Constant, ConstantPool, ConstantPoolBuilder};
+
+    #[test]
+    fn test_wide_constants_take_two_slots() {
+        let constants = vec![
+            Constant::Long(ConstLong { value: 1 }),
+            Constant::Long(ConstLong { value: 2 }),
+            Constant::Utf8(ConstUtf8 {
+                string: "x".to_string(),
+            }),
+        ];
+        let skip_table = ConstantPool::assemble_skip_table(&constants);
+        let pool = ConstantPool {
+            constants,
+            skip_table,
+        };
+
+        assert!(matches!(
+            pool.get(1),
+            Some(Constant::Long(ConstLong { value: 1 }))
+        ));
+        assert!(pool.get(2).is_none());
+        assert!(matches!(
+            pool.get(3),
+            Some(Constant::Long(ConstLong { value: 2 }))
+        ));
+        assert!(pool.get(4).is_none());
+        assert_eq!(pool.get_utf8(5).unwrap(), "x");
+        assert!(pool.get(6).is_none());
+        assert_eq!(
+            pool.iter().map(|(index, _)| index).collect::<Vec<_>>(),
+            vec![1, 3, 5]
+        );
+    }
+
+    #[test]
+    fn test_describe_entries() {
+        let mut builder = ConstantPoolBuilder::new();
+        let method = builder.method("java/lang/Math", "abs", "(I)I").unwrap();
+        let handle = builder.method_handle(6, method).unwrap();
+        let name_and_type = builder.name_and_type("abs", "(I)I").unwrap();
+        let pool = builder.build();
+
+        assert_eq!(pool.get(method as usize).unwrap().kind(), "Methodref");
+        assert_eq!(pool.describe_entry(method), "java/lang/Math.abs:(I)I");
+        assert_eq!(
+            pool.describe_entry(handle),
+            "REF_invokeStatic java/lang/Math.abs:(I)I"
+        );
+        assert_eq!(pool.describe_entry(name_and_type), "abs:(I)I");
+        assert_eq!(pool.describe_entry(pool.find_utf8("abs").unwrap()), "abs");
+    }
+}
+
+#[cfg(test)]
+mod constant_pool_builder_tests {
+    use super::{Constant, ConstantPoolBuilder};
+
+    #[test]
+    fn test_builder() {
+        let mut builder = ConstantPoolBuilder::new();
+        let println = builder
+            .method("java/io/PrintStream", "println", "(J)V")
+            .unwrap();
+        // The class, its name and the name and type came first
+        assert_eq!(println, 6);
+        assert_eq!(builder.class("java/io/PrintStream").unwrap(), 2);
+        assert_eq!(builder.long(7).unwrap(), 7);
+        assert_eq!(builder.double(0.5).unwrap(), 9);
+        assert_eq!(builder.long(7).unwrap(), 7);
+        assert_eq!(builder.count(), 11);
+
+        let pool = builder.build();
+        assert_eq!(
+            pool.get_member(println).unwrap(),
+            ("java/io/PrintStream", "println", "(J)V")
+        );
+        assert!(matches!(pool.get(9), Some(Constant::Double(_))));
+        assert!(pool.get(10).is_none());
+
+        let mut builder = ConstantPoolBuilder::from_pool(&pool);
+        assert_eq!(builder.utf8("println").unwrap(), 3);
+        assert_eq!(builder.integer(0).unwrap(), 11);
+        for value in 1..65523 {
+            builder.integer(value).unwrap();
+        }
+        assert_eq!(builder.count(), 65534);
+        assert!(builder.long(1).is_err());
+        assert_eq!(builder.integer(65523).unwrap(), 65534);
+        assert!(builder.integer(65524).is_err());
     }
 }
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::str::Chars;
+
+use crate::class::ClassLoadingError;
+
+// =============================================================================
+// FIELD TYPES
+// =============================================================================
+
+/// A type as it appears in field and method descriptors (JVMS 4.3.2).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// Internal name of the class, e.g. `java/lang/Object`.
+    Object(String),
+    Array(Box<FieldType>),
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Result<FieldType, ClassLoadingError> {
+        let mut chars = descriptor.chars();
+        let field_type = FieldType::parse_next(&mut chars, descriptor)?;
+        if chars.next().is_some() {
+            return Err(invalid_descriptor(descriptor));
+        }
+
+        Ok(field_type)
+    }
+
+    fn parse_next(chars: &mut Chars, descriptor: &str) -> Result<FieldType, ClassLoadingError> {
+        let field_type = match chars.next() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            Some('L') => {
+                let name: String = chars.take_while(|c| *c != ';').collect();
+                if name.is_empty() {
+                    return Err(invalid_descriptor(descriptor));
+                }
+                FieldType::Object(name)
+            }
+            Some('[') => FieldType::Array(Box::new(FieldType::parse_next(chars, descriptor)?)),
+            _ => return Err(invalid_descriptor(descriptor)),
+        };
+
+        Ok(field_type)
+    }
+
+    /// Whether values of the type take two slots in locals and on the operand
+    /// stack (`long` and `double`).
+    pub fn is_wide(&self) -> bool {
+        matches!(self, FieldType::Long | FieldType::Double)
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    /// Number of local variable slots a value of the type takes.
+    pub fn slots(&self) -> usize {
+        if self.is_wide() {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    /// Formats the type back into its descriptor form.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(name) => write!(f, "L{};", name),
+            FieldType::Array(component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+// =============================================================================
+// METHOD DESCRIPTORS
+// =============================================================================
+
+/// A parsed method descriptor (JVMS 4.3.3); a `None` return type is `void`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    pub return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Result<MethodDescriptor, ClassLoadingError> {
+        let mut chars = descriptor.chars();
+        if chars.next() != Some('(') {
+            return Err(invalid_descriptor(descriptor));
+        }
+
+        let mut parameters = Vec::new();
+        loop {
+            match chars.clone().next() {
+                Some(')') => {
+                    chars.next();
+                    break;
+                }
+                Some(_) => parameters.push(FieldType::parse_next(&mut chars, descriptor)?),
+                None => return Err(invalid_descriptor(descriptor)),
+            }
+        }
+
+        let return_type = match chars.as_str() {
+            "V" => None,
+            rest => Some(FieldType::parse(rest).map_err(|_| invalid_descriptor(descriptor))?),
+        };
+
+        Ok(MethodDescriptor {
+            parameters,
+            return_type,
+        })
+    }
+
+    /// Number of local variable slots taken by the parameters, not counting
+    /// the receiver of instance methods.
+    pub fn parameter_slots(&self) -> usize {
+        self.parameters.iter().map(FieldType::slots).sum()
+    }
+}
+
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        match &self.return_type {
+            Some(return_type) => write!(f, "){}", return_type),
+            None => write!(f, ")V"),
+        }
+    }
+}
+
+fn invalid_descriptor(descriptor: &str) -> ClassLoadingError {
+    ClassLoadingError::new(format!("Invalid descriptor '{}'", descriptor).as_str())
+}
+
+// ============================================================================
+// DESCRIPTOR TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod descriptor_tests {
+    use super::{FieldType, MethodDescriptor};
+
+    #[test]
+    fn test_method_descriptor_round_trip() {
+        let descriptor = "(IJ[Ljava/lang/String;[[D)Ljava/lang/Object;";
+        let parsed = MethodDescriptor::parse(descriptor).unwrap();
+
+        assert_eq!(parsed.parameters.len(), 4);
+        assert_eq!(parsed.parameter_slots(), 5);
+        assert_eq!(
+            parsed.return_type,
+            Some(FieldType::Object("java/lang/Object".to_string()))
+        );
+        assert_eq!(parsed.to_string(), descriptor);
+    }
+
+    #[test]
+    fn test_invalid_descriptors() {
+        assert!(FieldType::parse("L;").is_err());
+        assert!(FieldType::parse("II").is_err());
+        assert!(MethodDescriptor::parse("(I").is_err());
+        assert!(MethodDescriptor::parse("()").is_err());
+    }
+}
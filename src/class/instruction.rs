@@ -0,0 +1,946 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::class::constant_pool::ConstantPool;
+
+// =============================================================================
+// INSTRUCTIONS
+// =============================================================================
+
+/// A bytecode instruction decoded for the interpreter, with its operands
+/// read and its branch targets resolved to instruction indices. Opcodes with
+/// the same behaviour, e.g. `iload_0` and `aload 0`, decode to the same
+/// instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    Nop,
+    AConstNull,
+    /// `iconst_<i>`, `bipush` and `sipush`.
+    IConst(i32),
+    LConst(i64),
+    FConst(f32),
+    DConst(f64),
+    /// `ldc`, `ldc_w` and `ldc2_w` of the constant pool entry.
+    Ldc(u16),
+    /// `<t>load` and `<t>load_<n>` of the local variable.
+    Load(u16),
+    /// `<t>store` and `<t>store_<n>` of the local variable.
+    Store(u16),
+    /// `<t>aload` of any element type.
+    ArrayLoad,
+    /// `<t>astore` of any element type.
+    ArrayStore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    IAdd,
+    LAdd,
+    FAdd,
+    DAdd,
+    ISub,
+    LSub,
+    FSub,
+    DSub,
+    IMul,
+    LMul,
+    FMul,
+    DMul,
+    IDiv,
+    LDiv,
+    FDiv,
+    DDiv,
+    IRem,
+    LRem,
+    FRem,
+    DRem,
+    INeg,
+    LNeg,
+    FNeg,
+    DNeg,
+    IShl,
+    LShl,
+    IShr,
+    LShr,
+    IUShr,
+    LUShr,
+    IAnd,
+    LAnd,
+    IOr,
+    LOr,
+    IXor,
+    LXor,
+    /// `iinc` of the local variable by the increment.
+    IInc(u16, i32),
+    I2L,
+    I2F,
+    I2D,
+    L2I,
+    L2F,
+    L2D,
+    F2I,
+    F2L,
+    F2D,
+    D2I,
+    D2L,
+    D2F,
+    I2B,
+    I2C,
+    I2S,
+    LCmp,
+    FCmpL,
+    FCmpG,
+    DCmpL,
+    DCmpG,
+    /// `if<cond>`, comparing an int with zero.
+    If(Condition, u32),
+    /// `if_icmp<cond>`.
+    IfICmp(Condition, u32),
+    IfACmpEq(u32),
+    IfACmpNe(u32),
+    /// `goto` and `goto_w`.
+    Goto(u32),
+    /// `jsr` and `jsr_w`, pushing the index of the next instruction as a
+    /// return address.
+    Jsr(u32),
+    /// `ret` to the return address in the local variable.
+    Ret(u16),
+    /// `ireturn`, `lreturn`, `freturn`, `dreturn` and `areturn`.
+    ReturnValue,
+    Return,
+    GetStatic(u16),
+    PutStatic(u16),
+    GetField(u16),
+    PutField(u16),
+    /// `invokevirtual` of the constant pool entry, with the index of the
+    /// inline cache of the call site, assigned by the linker.
+    InvokeVirtual(u16, u32),
+    InvokeSpecial(u16),
+    InvokeStatic(u16),
+    /// `invokeinterface`, like `invokevirtual`.
+    InvokeInterface(u16, u32),
+    New(u16),
+    /// `newarray` of the primitive array type code.
+    NewArray(u8),
+    ANewArray(u16),
+    ArrayLength,
+    AThrow,
+    CheckCast(u16),
+    InstanceOf(u16),
+    MonitorEnter,
+    MonitorExit,
+    /// `multianewarray` of the class with the number of dimensions.
+    MultiANewArray(u16, u8),
+    IfNull(u32),
+    IfNonNull(u32),
+    /// `tableswitch` and `lookupswitch`, of the jump table in
+    /// [DecodedCode::switches].
+    Switch(u32),
+    /// An opcode the interpreter does not implement, or an instruction which
+    /// cannot be decoded.
+    Unsupported(u8),
+}
+
+/// The condition of a conditional branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+}
+
+impl Condition {
+    /// The conditions of `ifeq` to `ifle` and `if_icmpeq` to `if_icmple`, in
+    /// opcode order.
+    const ORDER: [Condition; 6] = [
+        Condition::Eq,
+        Condition::Ne,
+        Condition::Lt,
+        Condition::Ge,
+        Condition::Gt,
+        Condition::Le,
+    ];
+
+    pub fn holds(self, a: i32, b: i32) -> bool {
+        let ordering = a.cmp(&b);
+        match self {
+            Condition::Eq => ordering == Ordering::Equal,
+            Condition::Ne => ordering != Ordering::Equal,
+            Condition::Lt => ordering == Ordering::Less,
+            Condition::Ge => ordering != Ordering::Less,
+            Condition::Gt => ordering == Ordering::Greater,
+            Condition::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// The jump table of a `tableswitch` or `lookupswitch`, its targets resolved
+/// to instruction indices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Switch {
+    pub default: u32,
+    /// The target of every case, strictly sorted by key.
+    pub cases: Vec<(i32, u32)>,
+}
+
+impl Switch {
+    /// The target of the key, found directly in the contiguous cases of a
+    /// `tableswitch`.
+    pub fn target(&self, key: i32) -> u32 {
+        let first = match self.cases.first() {
+            Some(&(first, _)) => first,
+            None => return self.default,
+        };
+        let offset = key as i64 - first as i64;
+        if let Some(&(case, target)) = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.cases.get(offset))
+        {
+            if case == key {
+                return target;
+            }
+        }
+        match self.cases.binary_search_by_key(&key, |&(case, _)| case) {
+            Ok(index) => self.cases[index].1,
+            Err(_) => self.default,
+        }
+    }
+
+    /// Renders the cases like `javap`, with the targets as pcs.
+    pub fn display<'a>(&'a self, pcs: &'a [u32]) -> impl fmt::Display + 'a {
+        SwitchDisplay { switch: self, pcs }
+    }
+}
+
+struct SwitchDisplay<'a> {
+    switch: &'a Switch,
+    pcs: &'a [u32],
+}
+
+impl fmt::Display for SwitchDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{ ")?;
+        for &(key, target) in &self.switch.cases {
+            write!(f, "{}: {}, ", key, self.pcs[target as usize])?;
+        }
+        write!(f, "default: {} }}", self.pcs[self.switch.default as usize])
+    }
+}
+
+// =============================================================================
+// DECODING
+// =============================================================================
+
+/// The instructions of a method's bytecode.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecodedCode {
+    pub instructions: Vec<Instruction>,
+    /// The pc of every instruction.
+    pub pcs: Vec<u32>,
+    /// The jump tables of the [Instruction::Switch] instructions.
+    pub switches: Vec<Switch>,
+}
+
+/// Decodes the bytecode of a method into its instructions and their pcs.
+///
+/// Decoding never fails: opcodes the interpreter does not implement, branches
+/// into the middle of an instruction and truncated instructions all decode to
+/// [Instruction::Unsupported], failing once executed. Decoding stops at an
+/// unknown opcode, whose length is unknown.
+pub fn decode(code: &[u8]) -> DecodedCode {
+    let mut pcs = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        pcs.push(pc as u32);
+        match length(code, pc) {
+            Some(length) => pc += length,
+            None => break,
+        }
+    }
+
+    let mut switches = Vec::new();
+    let instructions = pcs
+        .iter()
+        .map(|&pc| {
+            let pc = pc as usize;
+            match length(code, pc) {
+                Some(length) if pc + length <= code.len() => {
+                    decode_at(code, pc, &pcs, &mut switches)
+                        .unwrap_or(Instruction::Unsupported(code[pc]))
+                }
+                _ => Instruction::Unsupported(code[pc]),
+            }
+        })
+        .collect();
+    DecodedCode {
+        instructions,
+        pcs,
+        switches,
+    }
+}
+
+/// The length of the instruction at `pc`, if its opcode is known.
+fn length(code: &[u8], pc: usize) -> Option<usize> {
+    let opcode = code[pc];
+    let length = match opcode {
+        0x00..=0x0f => 1,
+        0x10 | 0x12 => 2,
+        0x11 | 0x13 | 0x14 => 3,
+        0x15..=0x19 => 2,
+        0x1a..=0x35 => 1,
+        0x36..=0x3a => 2,
+        0x3b..=0x83 => 1,
+        0x84 => 3,
+        0x85..=0x98 => 1,
+        0x99..=0xa8 => 3,
+        0xa9 => 2,
+        // tableswitch, lookupswitch: padded to a multiple of four
+        0xaa | 0xab => {
+            let operands = (pc + 4) & !3;
+            let operand = |index: usize| {
+                let start = operands + 4 * index;
+                code.get(start..start + 4).map(BigEndian::read_i32)
+            };
+            let entries = if opcode == 0xaa {
+                (operand(2)? as i64 - operand(1)? as i64 + 1).max(0) as usize * 4 + 12
+            } else {
+                operand(1)?.max(0) as usize * 8 + 8
+            };
+            operands - pc + entries
+        }
+        0xac..=0xb1 => 1,
+        0xb2..=0xb8 => 3,
+        0xb9 | 0xba => 5,
+        0xbb => 3,
+        0xbc => 2,
+        0xbd => 3,
+        0xbe | 0xbf => 1,
+        0xc0 | 0xc1 => 3,
+        0xc2 | 0xc3 => 1,
+        // wide
+        0xc4 => match code.get(pc + 1)? {
+            0x84 => 6,
+            _ => 4,
+        },
+        0xc5 => 4,
+        0xc6 | 0xc7 => 3,
+        0xc8 | 0xc9 => 5,
+        _ => return None,
+    };
+    Some(length)
+}
+
+/// Decodes the complete instruction at `pc`, `None` for branches to a pc
+/// which is not the start of an instruction.
+fn decode_at(
+    code: &[u8],
+    pc: usize,
+    pcs: &[u32],
+    switches: &mut Vec<Switch>,
+) -> Option<Instruction> {
+    let opcode = code[pc];
+    let u8_operand = || code[pc + 1];
+    let u16_operand = || BigEndian::read_u16(&code[pc + 1..]);
+    let target = |offset: i64| {
+        let target = u32::try_from(pc as i64 + offset).ok()?;
+        pcs.binary_search(&target).ok().map(|index| index as u32)
+    };
+    let branch = || target(BigEndian::read_i16(&code[pc + 1..]) as i64);
+
+    let instruction = match opcode {
+        0x00 => Instruction::Nop,
+        0x01 => Instruction::AConstNull,
+        0x02..=0x08 => Instruction::IConst(opcode as i32 - 0x03),
+        0x09..=0x0a => Instruction::LConst(opcode as i64 - 0x09),
+        0x0b..=0x0d => Instruction::FConst((opcode - 0x0b) as f32),
+        0x0e..=0x0f => Instruction::DConst((opcode - 0x0e) as f64),
+        0x10 => Instruction::IConst(u8_operand() as i8 as i32),
+        0x11 => Instruction::IConst(BigEndian::read_i16(&code[pc + 1..]) as i32),
+        0x12 => Instruction::Ldc(u8_operand() as u16),
+        0x13 | 0x14 => Instruction::Ldc(u16_operand()),
+        0x15..=0x19 => Instruction::Load(u8_operand() as u16),
+        0x1a..=0x2d => Instruction::Load(((opcode - 0x1a) % 4) as u16),
+        0x2e..=0x35 => Instruction::ArrayLoad,
+        0x36..=0x3a => Instruction::Store(u8_operand() as u16),
+        0x3b..=0x4e => Instruction::Store(((opcode - 0x3b) % 4) as u16),
+        0x4f..=0x56 => Instruction::ArrayStore,
+        0x57 => Instruction::Pop,
+        0x58 => Instruction::Pop2,
+        0x59 => Instruction::Dup,
+        0x5a => Instruction::DupX1,
+        0x5b => Instruction::DupX2,
+        0x5c => Instruction::Dup2,
+        0x5d => Instruction::Dup2X1,
+        0x5e => Instruction::Dup2X2,
+        0x5f => Instruction::Swap,
+        0x60 => Instruction::IAdd,
+        0x61 => Instruction::LAdd,
+        0x62 => Instruction::FAdd,
+        0x63 => Instruction::DAdd,
+        0x64 => Instruction::ISub,
+        0x65 => Instruction::LSub,
+        0x66 => Instruction::FSub,
+        0x67 => Instruction::DSub,
+        0x68 => Instruction::IMul,
+        0x69 => Instruction::LMul,
+        0x6a => Instruction::FMul,
+        0x6b => Instruction::DMul,
+        0x6c => Instruction::IDiv,
+        0x6d => Instruction::LDiv,
+        0x6e => Instruction::FDiv,
+        0x6f => Instruction::DDiv,
+        0x70 => Instruction::IRem,
+        0x71 => Instruction::LRem,
+        0x72 => Instruction::FRem,
+        0x73 => Instruction::DRem,
+        0x74 => Instruction::INeg,
+        0x75 => Instruction::LNeg,
+        0x76 => Instruction::FNeg,
+        0x77 => Instruction::DNeg,
+        0x78 => Instruction::IShl,
+        0x79 => Instruction::LShl,
+        0x7a => Instruction::IShr,
+        0x7b => Instruction::LShr,
+        0x7c => Instruction::IUShr,
+        0x7d => Instruction::LUShr,
+        0x7e => Instruction::IAnd,
+        0x7f => Instruction::LAnd,
+        0x80 => Instruction::IOr,
+        0x81 => Instruction::LOr,
+        0x82 => Instruction::IXor,
+        0x83 => Instruction::LXor,
+        0x84 => Instruction::IInc(u8_operand() as u16, code[pc + 2] as i8 as i32),
+        0x85 => Instruction::I2L,
+        0x86 => Instruction::I2F,
+        0x87 => Instruction::I2D,
+        0x88 => Instruction::L2I,
+        0x89 => Instruction::L2F,
+        0x8a => Instruction::L2D,
+        0x8b => Instruction::F2I,
+        0x8c => Instruction::F2L,
+        0x8d => Instruction::F2D,
+        0x8e => Instruction::D2I,
+        0x8f => Instruction::D2L,
+        0x90 => Instruction::D2F,
+        0x91 => Instruction::I2B,
+        0x92 => Instruction::I2C,
+        0x93 => Instruction::I2S,
+        0x94 => Instruction::LCmp,
+        0x95 => Instruction::FCmpL,
+        0x96 => Instruction::FCmpG,
+        0x97 => Instruction::DCmpL,
+        0x98 => Instruction::DCmpG,
+        0x99..=0x9e => Instruction::If(Condition::ORDER[(opcode - 0x99) as usize], branch()?),
+        0x9f..=0xa4 => Instruction::IfICmp(Condition::ORDER[(opcode - 0x9f) as usize], branch()?),
+        0xa5 => Instruction::IfACmpEq(branch()?),
+        0xa6 => Instruction::IfACmpNe(branch()?),
+        0xa7 => Instruction::Goto(branch()?),
+        0xa8 => Instruction::Jsr(branch()?),
+        0xa9 => Instruction::Ret(u8_operand() as u16),
+        0xaa | 0xab => {
+            let switch = decode_switch(code, pc, target)?;
+            switches.push(switch);
+            Instruction::Switch(switches.len() as u32 - 1)
+        }
+        0xac..=0xb0 => Instruction::ReturnValue,
+        0xb1 => Instruction::Return,
+        0xb2 => Instruction::GetStatic(u16_operand()),
+        0xb3 => Instruction::PutStatic(u16_operand()),
+        0xb4 => Instruction::GetField(u16_operand()),
+        0xb5 => Instruction::PutField(u16_operand()),
+        0xb6 => Instruction::InvokeVirtual(u16_operand(), 0),
+        0xb7 => Instruction::InvokeSpecial(u16_operand()),
+        0xb8 => Instruction::InvokeStatic(u16_operand()),
+        0xb9 => Instruction::InvokeInterface(u16_operand(), 0),
+        0xbb => Instruction::New(u16_operand()),
+        0xbc => Instruction::NewArray(u8_operand()),
+        0xbd => Instruction::ANewArray(u16_operand()),
+        0xbe => Instruction::ArrayLength,
+        0xbf => Instruction::AThrow,
+        0xc0 => Instruction::CheckCast(u16_operand()),
+        0xc1 => Instruction::InstanceOf(u16_operand()),
+        0xc2 => Instruction::MonitorEnter,
+        0xc3 => Instruction::MonitorExit,
+        // wide, with a 16-bit local index
+        0xc4 => {
+            let index = BigEndian::read_u16(&code[pc + 2..]);
+            match code[pc + 1] {
+                0x15..=0x19 => Instruction::Load(index),
+                0x36..=0x3a => Instruction::Store(index),
+                0x84 => Instruction::IInc(index, BigEndian::read_i16(&code[pc + 4..]) as i32),
+                0xa9 => Instruction::Ret(index),
+                _ => return None,
+            }
+        }
+        0xc5 => Instruction::MultiANewArray(u16_operand(), code[pc + 3]),
+        0xc6 => Instruction::IfNull(branch()?),
+        0xc7 => Instruction::IfNonNull(branch()?),
+        0xc8 => Instruction::Goto(target(BigEndian::read_i32(&code[pc + 1..]) as i64)?),
+        0xc9 => Instruction::Jsr(target(BigEndian::read_i32(&code[pc + 1..]) as i64)?),
+        _ => Instruction::Unsupported(opcode),
+    };
+    Some(instruction)
+}
+
+/// Decodes the jump table of the `tableswitch` or `lookupswitch` at `pc`,
+/// `None` for an empty range, a negative number of cases or unsorted keys.
+fn decode_switch(code: &[u8], pc: usize, target: impl Fn(i64) -> Option<u32>) -> Option<Switch> {
+    let operands = (pc + 4) & !3;
+    let operand = |index: usize| BigEndian::read_i32(&code[operands + 4 * index..]);
+
+    let default = target(operand(0) as i64)?;
+    let cases = if code[pc] == 0xaa {
+        let (low, high) = (operand(1), operand(2));
+        if low > high {
+            return None;
+        }
+        (low..=high)
+            .enumerate()
+            .map(|(index, key)| Some((key, target(operand(3 + index) as i64)?)))
+            .collect::<Option<Vec<_>>>()?
+    } else {
+        let count = usize::try_from(operand(1)).ok()?;
+        let cases = (0..count)
+            .map(|index| {
+                Some((
+                    operand(2 + 2 * index),
+                    target(operand(3 + 2 * index) as i64)?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if cases.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return None;
+        }
+        cases
+    };
+    Some(Switch { default, cases })
+}
+
+// =============================================================================
+// DISASSEMBLY
+// =============================================================================
+
+/// Renders the instruction at `index` of the decoded `code` like `javap -c`,
+/// e.g. `iload 4`, `if_icmpge 24` or
+/// `invokevirtual java/io/PrintStream.println:(I)V`.
+pub fn disassemble(
+    code: &[u8],
+    decoded: &DecodedCode,
+    index: usize,
+    constant_pool: &ConstantPool,
+) -> String {
+    let pc = decoded.pcs[index] as usize;
+    let opcode = code[pc];
+    let wide = opcode == 0xc4 && pc + 1 < code.len();
+    let name = if wide {
+        format!("wide {}", mnemonic(code[pc + 1]))
+    } else {
+        mnemonic(opcode).to_string()
+    };
+    let explicit_index = wide || matches!(opcode, 0x15..=0x19 | 0x36..=0x3a);
+
+    let operands = match decoded.instructions[index] {
+        Instruction::IConst(value) if matches!(opcode, 0x10 | 0x11) => value.to_string(),
+        Instruction::Load(local) | Instruction::Store(local) if explicit_index => local.to_string(),
+        Instruction::IInc(local, increment) => format!("{} {}", local, increment),
+        Instruction::Ret(local) => local.to_string(),
+        Instruction::If(_, target)
+        | Instruction::IfICmp(_, target)
+        | Instruction::IfACmpEq(target)
+        | Instruction::IfACmpNe(target)
+        | Instruction::Goto(target)
+        | Instruction::Jsr(target)
+        | Instruction::IfNull(target)
+        | Instruction::IfNonNull(target) => decoded.pcs[target as usize].to_string(),
+        Instruction::Switch(switch) => decoded.switches[switch as usize]
+            .display(&decoded.pcs)
+            .to_string(),
+        Instruction::Ldc(constant)
+        | Instruction::GetStatic(constant)
+        | Instruction::PutStatic(constant)
+        | Instruction::GetField(constant)
+        | Instruction::PutField(constant)
+        | Instruction::InvokeVirtual(constant, _)
+        | Instruction::InvokeSpecial(constant)
+        | Instruction::InvokeStatic(constant)
+        | Instruction::InvokeInterface(constant, _)
+        | Instruction::New(constant)
+        | Instruction::ANewArray(constant)
+        | Instruction::CheckCast(constant)
+        | Instruction::InstanceOf(constant) => constant_pool.describe(constant),
+        Instruction::NewArray(array_type) => match array_type {
+            4 => "boolean",
+            5 => "char",
+            6 => "float",
+            7 => "double",
+            8 => "byte",
+            9 => "short",
+            10 => "int",
+            11 => "long",
+            _ => "?",
+        }
+        .to_string(),
+        Instruction::MultiANewArray(class, dimensions) => {
+            format!("{} {}", constant_pool.describe(class), dimensions)
+        }
+        // invokedynamic is not interpreted, but still worth reading
+        Instruction::Unsupported(0xba) if pc + 3 <= code.len() => {
+            constant_pool.describe(BigEndian::read_u16(&code[pc + 1..]))
+        }
+        _ => String::new(),
+    };
+
+    if operands.is_empty() {
+        name
+    } else {
+        format!("{} {}", name, operands)
+    }
+}
+
+// =============================================================================
+// MNEMONICS
+// =============================================================================
+
+/// The mnemonic of an opcode (JVMS 7), e.g. `iload_0`.
+pub fn mnemonic(opcode: u8) -> &'static str {
+    const MNEMONICS: [&str; 0xca] = [
+        "nop",
+        "aconst_null",
+        "iconst_m1",
+        "iconst_0",
+        "iconst_1",
+        "iconst_2",
+        "iconst_3",
+        "iconst_4",
+        "iconst_5",
+        "lconst_0",
+        "lconst_1",
+        "fconst_0",
+        "fconst_1",
+        "fconst_2",
+        "dconst_0",
+        "dconst_1",
+        "bipush",
+        "sipush",
+        "ldc",
+        "ldc_w",
+        "ldc2_w",
+        "iload",
+        "lload",
+        "fload",
+        "dload",
+        "aload",
+        "iload_0",
+        "iload_1",
+        "iload_2",
+        "iload_3",
+        "lload_0",
+        "lload_1",
+        "lload_2",
+        "lload_3",
+        "fload_0",
+        "fload_1",
+        "fload_2",
+        "fload_3",
+        "dload_0",
+        "dload_1",
+        "dload_2",
+        "dload_3",
+        "aload_0",
+        "aload_1",
+        "aload_2",
+        "aload_3",
+        "iaload",
+        "laload",
+        "faload",
+        "daload",
+        "aaload",
+        "baload",
+        "caload",
+        "saload",
+        "istore",
+        "lstore",
+        "fstore",
+        "dstore",
+        "astore",
+        "istore_0",
+        "istore_1",
+        "istore_2",
+        "istore_3",
+        "lstore_0",
+        "lstore_1",
+        "lstore_2",
+        "lstore_3",
+        "fstore_0",
+        "fstore_1",
+        "fstore_2",
+        "fstore_3",
+        "dstore_0",
+        "dstore_1",
+        "dstore_2",
+        "dstore_3",
+        "astore_0",
+        "astore_1",
+        "astore_2",
+        "astore_3",
+        "iastore",
+        "lastore",
+        "fastore",
+        "dastore",
+        "aastore",
+        "bastore",
+        "castore",
+        "sastore",
+        "pop",
+        "pop2",
+        "dup",
+        "dup_x1",
+        "dup_x2",
+        "dup2",
+        "dup2_x1",
+        "dup2_x2",
+        "swap",
+        "iadd",
+        "ladd",
+        "fadd",
+        "dadd",
+        "isub",
+        "lsub",
+        "fsub",
+        "dsub",
+        "imul",
+        "lmul",
+        "fmul",
+        "dmul",
+        "idiv",
+        "ldiv",
+        "fdiv",
+        "ddiv",
+        "irem",
+        "lrem",
+        "frem",
+        "drem",
+        "ineg",
+        "lneg",
+        "fneg",
+        "dneg",
+        "ishl",
+        "lshl",
+        "ishr",
+        "lshr",
+        "iushr",
+        "lushr",
+        "iand",
+        "land",
+        "ior",
+        "lor",
+        "ixor",
+        "lxor",
+        "iinc",
+        "i2l",
+        "i2f",
+        "i2d",
+        "l2i",
+        "l2f",
+        "l2d",
+        "f2i",
+        "f2l",
+        "f2d",
+        "d2i",
+        "d2l",
+        "d2f",
+        "i2b",
+        "i2c",
+        "i2s",
+        "lcmp",
+        "fcmpl",
+        "fcmpg",
+        "dcmpl",
+        "dcmpg",
+        "ifeq",
+        "ifne",
+        "iflt",
+        "ifge",
+        "ifgt",
+        "ifle",
+        "if_icmpeq",
+        "if_icmpne",
+        "if_icmplt",
+        "if_icmpge",
+        "if_icmpgt",
+        "if_icmple",
+        "if_acmpeq",
+        "if_acmpne",
+        "goto",
+        "jsr",
+        "ret",
+        "tableswitch",
+        "lookupswitch",
+        "ireturn",
+        "lreturn",
+        "freturn",
+        "dreturn",
+        "areturn",
+        "return",
+        "getstatic",
+        "putstatic",
+        "getfield",
+        "putfield",
+        "invokevirtual",
+        "invokespecial",
+        "invokestatic",
+        "invokeinterface",
+        "invokedynamic",
+        "new",
+        "newarray",
+        "anewarray",
+        "arraylength",
+        "athrow",
+        "checkcast",
+        "instanceof",
+        "monitorenter",
+        "monitorexit",
+        "wide",
+        "multianewarray",
+        "ifnull",
+        "ifnonnull",
+        "goto_w",
+        "jsr_w",
+    ];
+
+    match opcode {
+        0xca => "breakpoint",
+        0xfe => "impdep1",
+        0xff => "impdep2",
+        opcode => MNEMONICS
+            .get(opcode as usize)
+            .copied()
+            .unwrap_or("<unknown>"),
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod instruction_tests {
+    use super::{decode, mnemonic, Condition, Instruction, Switch};
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(mnemonic(0x00), "nop");
+        assert_eq!(mnemonic(0x2a), "aload_0");
+        assert_eq!(mnemonic(0xb6), "invokevirtual");
+        assert_eq!(mnemonic(0xc9), "jsr_w");
+        assert_eq!(mnemonic(0xe0), "<unknown>");
+    }
+
+    #[test]
+    fn test_decode() {
+        // iconst_0, istore_1, iload_1, bipush 10, if_icmpge +9, iinc 1 1,
+        // goto -9, wide iload 300, wide iinc 300 -1000, wide nop, return
+        let code = [
+            0x03, 0x3c, 0x1b, 0x10, 0x0a, 0xa2, 0x00, 0x09, 0x84, 0x01, 0x01, 0xa7, 0xff, 0xf7,
+            0xc4, 0x15, 0x01, 0x2c, 0xc4, 0x84, 0x01, 0x2c, 0xfc, 0x18, 0xc4, 0x00, 0x00, 0x00,
+            0xb1,
+        ];
+        let decoded = decode(&code);
+        assert_eq!(decoded.pcs, vec![0, 1, 2, 3, 5, 8, 11, 14, 18, 24, 28]);
+        assert_eq!(
+            decoded.instructions,
+            vec![
+                Instruction::IConst(0),
+                Instruction::Store(1),
+                Instruction::Load(1),
+                Instruction::IConst(10),
+                Instruction::IfICmp(Condition::Ge, 7),
+                Instruction::IInc(1, 1),
+                Instruction::Goto(2),
+                Instruction::Load(300),
+                Instruction::IInc(300, -1000),
+                Instruction::Unsupported(0xc4),
+                Instruction::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_switches() {
+        // iload_0, tableswitch 1 to 2 with two bytes of padding, iconst_1,
+        // ireturn, iconst_2, ireturn, iload_0, lookupswitch -5 and 100,
+        // iconst_0, ireturn
+        #[rustfmt::skip]
+        let code = [
+            0x1a,
+            0xaa, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x1b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, 0x19,
+            0x04, 0xac, 0x05, 0xac,
+            0x1a,
+            0xab, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x1b, 0x00, 0x00, 0x00, 0x02,
+            0xff, 0xff, 0xff, 0xfb, 0xff, 0xff, 0xff, 0xfb,
+            0x00, 0x00, 0x00, 0x64, 0xff, 0xff, 0xff, 0xfd,
+            0x03, 0xac,
+        ];
+        let decoded = decode(&code);
+        assert_eq!(decoded.pcs, vec![0, 1, 24, 25, 26, 27, 28, 29, 56, 57]);
+        assert_eq!(decoded.instructions[1], Instruction::Switch(0));
+        assert_eq!(decoded.instructions[7], Instruction::Switch(1));
+        assert_eq!(
+            decoded.switches,
+            vec![
+                Switch {
+                    default: 6,
+                    cases: vec![(1, 2), (2, 4)]
+                },
+                Switch {
+                    default: 8,
+                    cases: vec![(-5, 2), (100, 4)]
+                },
+            ]
+        );
+
+        let (table, lookup) = (&decoded.switches[0], &decoded.switches[1]);
+        assert_eq!(table.target(2), 4);
+        assert_eq!(table.target(i32::MIN), 6);
+        assert_eq!(lookup.target(-5), 2);
+        assert_eq!(lookup.target(0), 8);
+        assert_eq!(
+            lookup.display(&decoded.pcs).to_string(),
+            "{ -5: 24, 100: 26, default: 56 }"
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        // goto +1 into its own operands, an unknown opcode, then a
+        // truncated sipush
+        let decoded = decode(&[0xa7, 0x00, 0x01, 0xfe, 0x11, 0x00]);
+        assert_eq!(decoded.pcs, vec![0, 3]);
+        assert_eq!(
+            decoded.instructions,
+            vec![
+                Instruction::Unsupported(0xa7),
+                Instruction::Unsupported(0xfe)
+            ]
+        );
+
+        assert_eq!(
+            decode(&[0x00, 0x11, 0x00]).instructions,
+            vec![Instruction::Nop, Instruction::Unsupported(0x11)]
+        );
+    }
+}
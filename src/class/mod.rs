@@ -1,14 +1,22 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt::Debug;
-use std::{fmt, io, string};
+use std::io::{Cursor, Read};
+use std::{fmt, io, mem, string};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
 use crate::class::attributes::Attribute;
-use crate::class::constant_pool::{ConstantPool, ConstantPoolContext};
+use crate::class::constant_pool::{ConstantContext, ConstantPool, ConstantPoolContext};
 
 pub mod attributes;
 pub mod constant_pool;
+pub mod descriptor;
+pub mod instruction;
+pub mod module;
+pub mod scala;
+pub mod writer;
 
 // =============================================================================
 // STATIC VALUES
@@ -27,7 +35,7 @@ pub struct ClassLoadingError {
 }
 
 impl ClassLoadingError {
-    fn new(msg: &str) -> ClassLoadingError {
+    pub fn new(msg: &str) -> ClassLoadingError {
         ClassLoadingError {
             details: msg.to_string(),
         }
@@ -40,24 +48,172 @@ impl fmt::Display for ClassLoadingError {
     }
 }
 
-impl Error for ClassLoadingError {
-    fn description(&self) -> &str {
-        &self.details
-    }
-}
+impl Error for ClassLoadingError {}
 
 impl From<io::Error> for ClassLoadingError {
     fn from(err: io::Error) -> Self {
-        ClassLoadingError::new(err.description())
+        ClassLoadingError::new(&err.to_string())
     }
 }
 
 impl From<string::FromUtf8Error> for ClassLoadingError {
     fn from(err: string::FromUtf8Error) -> Self {
-        ClassLoadingError::new(err.description())
+        ClassLoadingError::new(&err.to_string())
+    }
+}
+
+// =============================================================================
+// LIMITS
+// =============================================================================
+
+/// Hard bounds on the structures of a class file, checked while parsing so
+/// that a crafted class is rejected before it can make the parser allocate
+/// or recurse without bound. The defaults admit every class `javac` emits.
+#[derive(Clone, Debug)]
+pub struct ParseLimits {
+    max_constants: usize,
+    max_code_length: usize,
+    max_attribute_length: usize,
+    max_nesting_depth: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_constants: u16::MAX as usize,
+            // The limit of JVMS 4.7.3, as `pc` values are 16 bits
+            max_code_length: u16::MAX as usize,
+            max_attribute_length: 16 << 20,
+            max_nesting_depth: 64,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Limits the entries of the constant pool, counting the constant pool
+    /// count as stored in the class file.
+    pub fn max_constants(mut self, constants: usize) -> Self {
+        self.max_constants = constants;
+        self
+    }
+
+    /// Limits the length of a method's bytecode.
+    pub fn max_code_length(mut self, bytes: usize) -> Self {
+        self.max_code_length = bytes;
+        self
+    }
+
+    /// Limits the length of any attribute.
+    pub fn max_attribute_length(mut self, bytes: usize) -> Self {
+        self.max_attribute_length = bytes;
+        self
+    }
+
+    /// Limits how deep annotation element values, and attributes holding
+    /// attributes of their own, may nest.
+    pub fn max_nesting_depth(mut self, depth: usize) -> Self {
+        self.max_nesting_depth = depth;
+        self
+    }
+
+    fn check(&self, what: &str, value: usize, max: usize) -> Result<(), ClassLoadingError> {
+        if value > max {
+            return Err(ClassLoadingError::new(
+                format!("{} of {} exceeds the limit of {}", what, value, max).as_str(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_constants(&self, count: usize) -> Result<(), ClassLoadingError> {
+        self.check("Constant pool count", count, self.max_constants)
+    }
+
+    pub(crate) fn check_code_length(&self, length: usize) -> Result<(), ClassLoadingError> {
+        self.check("Code length", length, self.max_code_length)
+    }
+
+    pub(crate) fn check_attribute_length(&self, length: usize) -> Result<(), ClassLoadingError> {
+        self.check("Attribute length", length, self.max_attribute_length)
+    }
+
+    pub(crate) fn check_nesting_depth(&self, depth: usize) -> Result<(), ClassLoadingError> {
+        self.check("Nesting depth", depth, self.max_nesting_depth)
+    }
+}
+
+// =============================================================================
+// HOSTILE INPUT
+// =============================================================================
+
+/// A departure from the JVMS which [Class::parse_hostile] let a class off
+/// with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Anomaly {
+    /// An attribute the JVM runs the class without, whose content does not
+    /// parse or disagrees with its length, kept as an [Attribute::Misc] of
+    /// its raw bytes.
+    MalformedAttribute { name: String, reason: String },
+    /// A UTF-8 constant which is not valid modified UTF-8, decoded with
+    /// replacement characters.
+    InvalidUtf8 { string: String },
+    /// A field declared again with the same name and descriptor.
+    DuplicateField { name: String, descriptor: String },
+    /// A method declared again with the same name and descriptor.
+    DuplicateMethod { name: String, descriptor: String },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Anomaly::MalformedAttribute { name, reason } => {
+                write!(
+                    f,
+                    "Malformed {} attribute kept as raw bytes: {}",
+                    name, reason
+                )
+            }
+            Anomaly::InvalidUtf8 { string } => {
+                write!(f, "Invalid modified UTF-8 constant read as {:?}", string)
+            }
+            Anomaly::DuplicateField { name, descriptor } => {
+                write!(f, "Duplicate field {}:{}", name, descriptor)
+            }
+            Anomaly::DuplicateMethod { name, descriptor } => {
+                write!(f, "Duplicate method {}{}", name, descriptor)
+            }
+        }
     }
 }
 
+/// Fails for, or records in hostile mode, the members declared twice with
+/// the same name and descriptor. Members whose name or descriptor is no
+/// UTF-8 constant are left to their users to reject.
+fn check_duplicates(
+    pool: &ConstantPool,
+    members: impl Iterator<Item = (u16, u16)>,
+    duplicate: impl Fn(String, String) -> Anomaly,
+    anomalies: Option<&RefCell<Vec<Anomaly>>>,
+) -> Result<(), ClassLoadingError> {
+    let mut declared = HashSet::new();
+    for (name_index, descriptor_index) in members {
+        let (name, descriptor) = match (pool.get_utf8(name_index), pool.get_utf8(descriptor_index))
+        {
+            (Ok(name), Ok(descriptor)) => (name, descriptor),
+            _ => continue,
+        };
+        if declared.insert((name, descriptor)) {
+            continue;
+        }
+        let anomaly = duplicate(name.to_string(), descriptor.to_string());
+        match anomalies {
+            Some(anomalies) => anomalies.borrow_mut().push(anomaly),
+            None => return Err(ClassLoadingError::new(&anomaly.to_string())),
+        }
+    }
+    Ok(())
+}
+
 // =============================================================================
 // CONTEXT
 // =============================================================================
@@ -69,6 +225,31 @@ struct EmptyContext {}
 // COMMON TRAITS
 // =============================================================================
 
+/// Bytes preallocated at most for the elements of a counted list, which keeps
+/// a forged count from reserving memory the class file does not back up.
+const PREALLOCATED_BYTES: usize = 4096;
+
+/// Reads a block of bytes of the given length, growing the buffer as the
+/// bytes arrive rather than trusting the length up front.
+fn read_bytes<R: ReadBytesExt>(
+    reader: &mut R,
+    length: usize,
+) -> Result<Vec<u8>, ClassLoadingError> {
+    let mut bytes = Vec::new();
+    Read::take(reader, length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(ClassLoadingError::new(
+            format!(
+                "Expected {} bytes, the class file ended after {}",
+                length,
+                bytes.len()
+            )
+            .as_str(),
+        ));
+    }
+    Ok(bytes)
+}
+
 trait ReadOne<C = EmptyContext>
 where
     Self: Sized,
@@ -86,7 +267,7 @@ where
     }
 
     fn skip_amount(_element: &Self) -> usize {
-        return 0;
+        0
     }
 
     fn read_all_from<R: ReadBytesExt>(
@@ -95,7 +276,18 @@ where
         from: usize,
     ) -> Result<Vec<Self>, ClassLoadingError> {
         let count = Self::read_count(reader)?;
-        let mut elements = Vec::with_capacity(count);
+        Self::read_counted(reader, context, from, count)
+    }
+
+    /// Reads the elements of a list whose count was read already.
+    fn read_counted<R: ReadBytesExt>(
+        reader: &mut R,
+        context: &C,
+        from: usize,
+        count: usize,
+    ) -> Result<Vec<Self>, ClassLoadingError> {
+        let mut elements =
+            Vec::with_capacity(count.min(PREALLOCATED_BYTES / mem::size_of::<Self>().max(1)));
 
         let mut index: usize = from;
         while index < count {
@@ -124,7 +316,7 @@ where
 
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    struct FieldAccessFlags: u16 {
+    pub struct FieldAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
         const PROTECTED = 0x0004;
@@ -139,10 +331,10 @@ bitflags::bitflags! {
 
 #[derive(Debug)]
 pub struct FieldInfo {
-    access_flags: FieldAccessFlags,
-    name_index: u16,
-    descriptor_index: u16,
-    attributes: Vec<Attribute>,
+    pub access_flags: FieldAccessFlags,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>,
 }
 
 impl ReadOne<ConstantPoolContext<'_>> for FieldInfo {
@@ -172,7 +364,7 @@ impl ReadAll<ConstantPoolContext<'_>> for FieldInfo {}
 
 #[derive(Debug)]
 pub struct Interface {
-    interface_index: u16,
+    pub interface_index: u16,
 }
 
 impl ReadOne<EmptyContext> for Interface {
@@ -191,7 +383,7 @@ impl ReadAll for Interface {}
 
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    struct MethodAccessFlags: u16 {
+    pub struct MethodAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
         const PROTECTED = 0x0004;
@@ -209,10 +401,10 @@ bitflags::bitflags! {
 
 #[derive(Debug)]
 pub struct MethodInfo {
-    access_flags: MethodAccessFlags,
-    name_index: u16,
-    descriptor_index: u16,
-    attributes: Vec<Attribute>,
+    pub access_flags: MethodAccessFlags,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>,
 }
 
 impl ReadOne<ConstantPoolContext<'_>> for MethodInfo {
@@ -244,7 +436,7 @@ impl ReadAll<ConstantPoolContext<'_>> for MethodInfo {}
 
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    struct ClassAccessFlags: u16 {
+    pub struct ClassAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const FINAL = 0x0010;
         const SUPER = 0x0020;
@@ -253,25 +445,92 @@ bitflags::bitflags! {
         const SYNTHETIC = 0x1000;
         const ANNOTATION = 0x2000;
         const ENUM = 0x4000;
+        const MODULE = 0x8000;
     }
 }
 
 #[derive(Debug)]
 pub struct Class {
-    minor_version: u16,
-    major_version: u16,
-    constant_pool: ConstantPool,
-    access_flags: ClassAccessFlags,
-    this_class: u16,
-    super_class: u16,
-    interfaces: Vec<Interface>,
-    fields: Vec<FieldInfo>,
-    methods: Vec<MethodInfo>,
-    attributes: Vec<Attribute>,
+    pub minor_version: u16,
+    pub major_version: u16,
+    pub constant_pool: ConstantPool,
+    pub access_flags: ClassAccessFlags,
+    pub this_class: u16,
+    pub super_class: u16,
+    pub interfaces: Vec<Interface>,
+    pub fields: Vec<FieldInfo>,
+    pub methods: Vec<MethodInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
 impl Class {
+    /// Internal name of the class, e.g. `java/lang/Object`.
+    pub fn name(&self) -> Result<&str, ClassLoadingError> {
+        self.constant_pool.get_class_name(self.this_class)
+    }
+
+    /// Internal name of the superclass, or `None` for `java/lang/Object`.
+    pub fn super_class_name(&self) -> Result<Option<&str>, ClassLoadingError> {
+        match self.super_class {
+            0 => Ok(None),
+            index => self.constant_pool.get_class_name(index).map(Some),
+        }
+    }
+
+    /// Parses a class file held in memory. Malformed input of any kind is
+    /// reported as an error rather than a panic, making this the entry point
+    /// of the fuzz targets.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Class, ClassLoadingError> {
+        Class::parse_bytes_with_limits(bytes, &ParseLimits::default())
+    }
+
+    pub fn parse_bytes_with_limits(
+        bytes: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<Class, ClassLoadingError> {
+        Class::read_with_limits(&mut Cursor::new(bytes), limits)
+    }
+
     pub fn read<R: ReadBytesExt>(reader: &mut R) -> Result<Class, ClassLoadingError> {
+        Class::read_with_limits(reader, &ParseLimits::default())
+    }
+
+    pub fn read_with_limits<R: ReadBytesExt>(
+        reader: &mut R,
+        limits: &ParseLimits,
+    ) -> Result<Class, ClassLoadingError> {
+        Class::read_class(reader, limits, None)
+    }
+
+    /// Parses a class file of an obfuscated jar, letting it off with the
+    /// tricks obfuscators play on analysis tools, which the JVM tolerates
+    /// or which only break the class once the offending part is used. The
+    /// deviations from [Class::parse_bytes_with_limits] are:
+    ///
+    /// - an attribute the JVM runs the class without, like the debug
+    ///   information, signatures and annotations, is kept as raw bytes when
+    ///   its content is malformed or disagrees with its length
+    /// - a UTF-8 constant holding invalid modified UTF-8 is decoded with
+    ///   replacement characters, writing the class back changing its bytes
+    /// - fields and methods declared twice are kept, each one of them, even
+    ///   though the JVM rejects the class
+    ///
+    /// Every deviation taken is returned along with the class. The class is
+    /// meant for analysis, not for running.
+    pub fn parse_hostile(
+        bytes: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<(Class, Vec<Anomaly>), ClassLoadingError> {
+        let anomalies = RefCell::new(Vec::new());
+        let class = Class::read_class(&mut Cursor::new(bytes), limits, Some(&anomalies))?;
+        Ok((class, anomalies.into_inner()))
+    }
+
+    fn read_class<R: ReadBytesExt>(
+        reader: &mut R,
+        limits: &ParseLimits,
+        anomalies: Option<&RefCell<Vec<Anomaly>>>,
+    ) -> Result<Class, ClassLoadingError> {
         let magic = reader.read_u32::<BigEndian>()?;
         if magic != CLASS_MAGIC {
             return Err(ClassLoadingError::new("Magic header is not matching"));
@@ -281,26 +540,61 @@ impl Class {
 
         let minor_version = reader.read_u16::<BigEndian>()?;
         let major_version = reader.read_u16::<BigEndian>()?;
-        let constant_pool = ConstantPool::read_one(reader, &empty_context)?;
+        let span = tracing::debug_span!(
+            "class",
+            class = tracing::field::Empty,
+            version = %format_args!("{}.{}", major_version, minor_version)
+        );
+        let _entered = span.enter();
+        let constant_pool = ConstantPool::read_one(reader, &ConstantContext { limits, anomalies })?;
         let access_flags = reader.read_u16::<BigEndian>()?;
         let access_flags = ClassAccessFlags::from_bits(access_flags)
             .ok_or(ClassLoadingError::new("Invalid class access flags"))?;
         let this_class = reader.read_u16::<BigEndian>()?;
+        if let Ok(name) = constant_pool.get_class_name(this_class) {
+            span.record("class", name);
+        }
         let super_class = reader.read_u16::<BigEndian>()?;
         let interfaces = Interface::read_all(reader, &empty_context)?;
-        let fields = FieldInfo::read_all(reader, &ConstantPoolContext::new(&constant_pool))?;
-        let methods = MethodInfo::read_all(reader, &ConstantPoolContext::new(&constant_pool))?;
-        let attributes = Attribute::read_all(reader, &ConstantPoolContext::new(&constant_pool))?;
+        let context = ConstantPoolContext {
+            anomalies,
+            ..ConstantPoolContext::new(&constant_pool, limits)
+        };
+        let fields = FieldInfo::read_all(reader, &context)?;
+        check_duplicates(
+            &constant_pool,
+            fields
+                .iter()
+                .map(|field| (field.name_index, field.descriptor_index)),
+            |name, descriptor| Anomaly::DuplicateField { name, descriptor },
+            anomalies,
+        )?;
+        let methods = MethodInfo::read_all(reader, &context)?;
+        check_duplicates(
+            &constant_pool,
+            methods
+                .iter()
+                .map(|method| (method.name_index, method.descriptor_index)),
+            |name, descriptor| Anomaly::DuplicateMethod { name, descriptor },
+            anomalies,
+        )?;
+        let attributes = Attribute::read_all(reader, &context)?;
 
         let mut rest = Vec::new();
-        reader.read(&mut rest)?;
+        reader.read_to_end(&mut rest)?;
         if !rest.is_empty() {
             return Err(ClassLoadingError::new(
                 "Data is still present after reading class file",
             ));
         }
 
-        return Ok(Class {
+        tracing::trace!(
+            constants = constant_pool.constants.len(),
+            fields = fields.len(),
+            methods = methods.len(),
+            "parsed class"
+        );
+        Ok(Class {
             minor_version,
             major_version,
             constant_pool,
@@ -311,6 +605,162 @@ impl Class {
             fields,
             methods,
             attributes,
+        })
+    }
+}
+
+// =============================================================================
+// CLASS TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod class_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::attributes::{Attribute, MiscAttribute};
+    use super::{Anomaly, Class, MethodAccessFlags, MethodInfo, ParseLimits};
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("res/embedding")
+            .join(name);
+        fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_malformed_classes_are_errors() {
+        let bytes = fixture("Calculator.class");
+        assert!(Class::parse_bytes(&bytes).is_ok());
+        for length in 0..bytes.len() {
+            assert!(Class::parse_bytes(&bytes[..length]).is_err());
+        }
+
+        // Corrupted bytes may still parse, but must not panic
+        for index in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[index] ^= 0xFF;
+            let _ = Class::parse_bytes(&corrupted);
+        }
+    }
+
+    #[test]
+    fn test_forged_lengths_are_not_allocated() {
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+        // Constant pool: #1 = Utf8 "Code"
+        bytes.extend_from_slice(&[0, 2, 1, 0, 4]);
+        bytes.extend_from_slice(b"Code");
+        // Flags, this, super, no interfaces or fields, then one method
+        bytes.extend_from_slice(&[0, 0x21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        // A method with a Code attribute claiming 4 GiB of bytecode
+        bytes.extend_from_slice(&[0, 0x09, 0, 1, 0, 1, 0, 1]);
+        bytes.extend_from_slice(&[0, 1, 0, 0, 0, 12, 0, 1, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+        let error = Class::parse_bytes(&bytes).unwrap_err();
+        assert!(error.to_string().contains("Code length of 4294967295"));
+        let limits = ParseLimits::default().max_code_length(usize::MAX);
+        let error = Class::parse_bytes_with_limits(&bytes, &limits).unwrap_err();
+        assert!(error.to_string().contains("Expected 4294967295 bytes"));
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let bytes = fixture("Calculator.class");
+        let limits = ParseLimits::default();
+        assert!(Class::parse_bytes_with_limits(&bytes, &limits.clone().max_constants(8)).is_err());
+        assert!(
+            Class::parse_bytes_with_limits(&bytes, &limits.clone().max_code_length(4)).is_err()
+        );
+        assert!(
+            Class::parse_bytes_with_limits(&bytes, &limits.clone().max_attribute_length(8))
+                .is_err()
+        );
+
+        assert!(Class::parse_bytes(&nested_annotation(32)).is_ok());
+        let error = Class::parse_bytes(&nested_annotation(100_000)).unwrap_err();
+        assert!(error.to_string().contains("Nesting depth of 65"));
+    }
+
+    #[test]
+    fn test_hostile_input() {
+        let mut class = Class::parse_bytes(&fixture("Calculator.class")).unwrap();
+        let pool = &class.constant_pool;
+        let line_numbers = pool.find_utf8("LineNumberTable").unwrap() as usize;
+        let (name_index, descriptor_index) = (
+            class.methods[1].name_index,
+            class.methods[1].descriptor_index,
+        );
+        for attribute in &mut class.methods[1].attributes {
+            if let Attribute::Code(code) = attribute {
+                // No entries, and a length two bytes longer
+                code.attributes = vec![Attribute::Misc(MiscAttribute {
+                    name_index: line_numbers,
+                    info: vec![0, 0, 0, 0],
+                })];
+            }
+        }
+        class.methods.push(MethodInfo {
+            access_flags: MethodAccessFlags::ABSTRACT,
+            name_index,
+            descriptor_index,
+            attributes: Vec::new(),
         });
+        let mut bytes = class.to_bytes().unwrap();
+        let source = bytes
+            .windows(5)
+            .position(|window| window == b".java")
+            .unwrap();
+        bytes[source + 1] = 0xFF;
+
+        assert!(Class::parse_bytes(&bytes).is_err());
+        let (hostile, anomalies) = Class::parse_hostile(&bytes, &ParseLimits::default()).unwrap();
+        assert_eq!(hostile.methods.len(), class.methods.len());
+        assert_eq!(anomalies.len(), 3);
+        assert_eq!(
+            anomalies[0],
+            Anomaly::InvalidUtf8 {
+                string: "Calculator.\u{FFFD}ava".to_string()
+            }
+        );
+        assert!(
+            matches!(&anomalies[1], Anomaly::MalformedAttribute { name, .. } if name == "LineNumberTable")
+        );
+        assert!(matches!(&anomalies[2], Anomaly::DuplicateMethod { .. }));
+
+        // Strict parsing fails on the quirks left
+        bytes[source + 1] = b'j';
+        let error = Class::parse_bytes(&bytes).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("LineNumberTable attribute of 4 bytes holds 2"));
+        class.methods.pop();
+        let bytes = class.to_bytes().unwrap();
+        assert!(Class::parse_bytes(&bytes).is_err());
+        assert_eq!(
+            Class::parse_hostile(&bytes, &ParseLimits::default())
+                .unwrap()
+                .1
+                .len(),
+            1
+        );
+    }
+
+    /// A class annotated with arrays nested to the given depth.
+    fn nested_annotation(depth: usize) -> Vec<u8> {
+        let mut annotation = vec![0, 1, 0, 2, 0, 1, 0, 2];
+        for _ in 0..depth {
+            annotation.extend_from_slice(&[b'[', 0, 1]);
+        }
+        annotation.extend_from_slice(&[b's', 0, 2]);
+
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 3];
+        for name in [&b"RuntimeVisibleAnnotations"[..], b"LA;"].iter() {
+            bytes.extend_from_slice(&[1, 0, name.len() as u8]);
+            bytes.extend_from_slice(name);
+        }
+        bytes.extend_from_slice(&[0, 0x21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1]);
+        bytes.extend_from_slice(&(annotation.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&annotation);
+        bytes
     }
 }
@@ -0,0 +1,487 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::class::attributes::{Attribute, ModuleExportsAttribute, ModuleFlags, RequiresFlags};
+use crate::class::constant_pool::ConstantPool;
+use crate::class::{Class, ClassLoadingError};
+
+/// Internal name of the class declaring a module.
+pub const MODULE_INFO: &str = "module-info";
+
+/// The keywords and literals of Java, which are not identifiers.
+static KEYWORDS: &[&str] = &[
+    "_",
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// Whether the name is a legal module name, or with `/` as the separator a
+/// legal internal package name: identifiers separated by the separator.
+pub fn is_qualified_name(name: &str, separator: char) -> bool {
+    name.split(separator).all(|part| {
+        let mut chars = part.chars();
+        let starts = chars
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$');
+        starts
+            && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+            && !KEYWORDS.contains(&part)
+    })
+}
+
+// =============================================================================
+// MODULE DESCRIPTOR
+// =============================================================================
+
+/// A dependence of a module on another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requires {
+    pub name: String,
+    /// Modules reading this module read the required one too.
+    pub transitive: bool,
+    /// Required at compile time only, resolved only if something else
+    /// requires it.
+    pub static_phase: bool,
+    /// The version of the module at compile time.
+    pub version: Option<String>,
+}
+
+/// A package exported or opened to the listed modules, or to every module
+/// when none are listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exports {
+    /// Internal name of the package, e.g. `com/example/api`.
+    pub package: String,
+    pub targets: Vec<String>,
+}
+
+impl Exports {
+    pub fn is_qualified(&self) -> bool {
+        !self.targets.is_empty()
+    }
+
+    /// Whether the package is exported, or opened, to the module, `None`
+    /// standing for the unnamed module.
+    pub fn is_to(&self, module: Option<&str>) -> bool {
+        match module {
+            Some(module) => !self.is_qualified() || self.targets.iter().any(|t| t == module),
+            None => !self.is_qualified(),
+        }
+    }
+}
+
+/// The implementations of a service a module provides.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provides {
+    /// Internal name of the service class.
+    pub service: String,
+    /// Internal names of the provider classes, in the order the service
+    /// loader instantiates them.
+    pub providers: Vec<String>,
+}
+
+/// What a module declares in its `module-info` class, or what is implied for
+/// an automatic module, a plain jar on the module path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub version: Option<String>,
+    /// Every package is open to deep reflection.
+    pub open: bool,
+    /// A plain jar, reading every module and exporting every package.
+    pub automatic: bool,
+    pub requires: Vec<Requires>,
+    pub exports: Vec<Exports>,
+    pub opens: Vec<Exports>,
+    /// Internal names of the services the module loads.
+    pub uses: Vec<String>,
+    pub provides: Vec<Provides>,
+    /// Internal names of the packages of the module, e.g. `com/example/api`.
+    pub packages: BTreeSet<String>,
+    /// Internal name of the main class, of the `ModuleMainClass` attribute.
+    pub main_class: Option<String>,
+}
+
+impl ModuleDescriptor {
+    /// The descriptor a `module-info` class declares, `None` for other
+    /// classes. Its packages are those the `ModulePackages` attribute lists,
+    /// if any, and those exported and opened.
+    pub fn read(class: &Class) -> Result<Option<ModuleDescriptor>, ClassLoadingError> {
+        let pool = &class.constant_pool;
+        let mut descriptor = None;
+        let mut packages = BTreeSet::new();
+        let mut main_class = None;
+        for attribute in &class.attributes {
+            match attribute {
+                Attribute::Module(module) => {
+                    let requires = module
+                        .requires
+                        .iter()
+                        .map(|requires| {
+                            Ok(Requires {
+                                name: pool.get_module_name(requires.requires_index)?.to_string(),
+                                transitive: requires
+                                    .requires_flags
+                                    .contains(RequiresFlags::TRANSITIVE),
+                                static_phase: requires
+                                    .requires_flags
+                                    .contains(RequiresFlags::STATIC_PHASE),
+                                version: version(pool, requires.requires_version_index)?,
+                            })
+                        })
+                        .collect::<Result<_, ClassLoadingError>>()?;
+                    let uses = module
+                        .uses_indices
+                        .iter()
+                        .map(|&index| pool.get_class_name(index).map(str::to_string))
+                        .collect::<Result<_, _>>()?;
+                    let provides = module
+                        .provides
+                        .iter()
+                        .map(|provides| {
+                            Ok(Provides {
+                                service: pool.get_class_name(provides.provides_index)?.to_string(),
+                                providers: provides
+                                    .provides_with_indices
+                                    .iter()
+                                    .map(|&index| pool.get_class_name(index).map(str::to_string))
+                                    .collect::<Result<_, _>>()?,
+                            })
+                        })
+                        .collect::<Result<_, ClassLoadingError>>()?;
+                    descriptor = Some(ModuleDescriptor {
+                        name: pool.get_module_name(module.module_name_index)?.to_string(),
+                        version: version(pool, module.module_version_index)?,
+                        open: module.module_flags.contains(ModuleFlags::OPEN),
+                        automatic: false,
+                        requires,
+                        exports: exports(pool, &module.exports)?,
+                        opens: exports(pool, &module.opens)?,
+                        uses,
+                        provides,
+                        packages: BTreeSet::new(),
+                        main_class: None,
+                    });
+                }
+                Attribute::ModulePackages(module_packages) => {
+                    for package in module_packages {
+                        packages.insert(pool.get_package_name(package.package_index)?.to_string());
+                    }
+                }
+                Attribute::ModuleMainClass(main) => {
+                    main_class = Some(pool.get_class_name(main.main_class_index)?.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(descriptor.map(|mut descriptor| {
+            packages.extend(descriptor.exports.iter().map(|e| e.package.clone()));
+            packages.extend(descriptor.opens.iter().map(|o| o.package.clone()));
+            descriptor.packages = packages;
+            descriptor.main_class = main_class;
+            descriptor
+        }))
+    }
+
+    /// The descriptor of an automatic module of the packages: open, without
+    /// dependences of its own, exporting every package.
+    pub fn automatic(name: &str, packages: BTreeSet<String>) -> ModuleDescriptor {
+        ModuleDescriptor {
+            name: name.to_string(),
+            version: None,
+            open: true,
+            automatic: true,
+            requires: Vec::new(),
+            exports: packages
+                .iter()
+                .map(|package| Exports {
+                    package: package.clone(),
+                    targets: Vec::new(),
+                })
+                .collect(),
+            opens: Vec::new(),
+            uses: Vec::new(),
+            provides: Vec::new(),
+            packages,
+            main_class: None,
+        }
+    }
+
+    /// Whether the module exports the package to the module, `None`
+    /// standing for the unnamed module.
+    pub fn exports_to(&self, package: &str, module: Option<&str>) -> bool {
+        self.exports
+            .iter()
+            .any(|exports| exports.package == package && exports.is_to(module))
+    }
+
+    /// Whether the module opens the package to deep reflection by the
+    /// module, `None` standing for the unnamed module.
+    pub fn opens_to(&self, package: &str, module: Option<&str>) -> bool {
+        (self.open && self.packages.contains(package))
+            || self
+                .opens
+                .iter()
+                .any(|opens| opens.package == package && opens.is_to(module))
+    }
+}
+
+impl fmt::Display for ModuleDescriptor {
+    /// Describes the module like `java --describe-module`: its name and
+    /// version, then its exports, requires, uses, provides, qualified
+    /// exports, opens and the packages it keeps to itself, one per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", version)?;
+        }
+        if self.open {
+            write!(f, " open")?;
+        }
+        if self.automatic {
+            write!(f, " automatic")?;
+        }
+        writeln!(f)?;
+
+        let mut lines = Vec::new();
+        let mut exports: Vec<_> = self.exports.iter().filter(|e| !e.is_qualified()).collect();
+        exports.sort_by(|a, b| a.package.cmp(&b.package));
+        lines.extend(
+            exports
+                .iter()
+                .map(|e| format!("exports {}", dotted(&e.package))),
+        );
+        let mut requires: Vec<_> = self.requires.iter().collect();
+        requires.sort_by(|a, b| a.name.cmp(&b.name));
+        for requires in requires {
+            let mut line = format!("requires {}", requires.name);
+            if requires.transitive {
+                line.push_str(" transitive");
+            }
+            if requires.static_phase {
+                line.push_str(" static");
+            }
+            lines.push(line);
+        }
+        let mut uses: Vec<_> = self.uses.iter().map(|service| dotted(service)).collect();
+        uses.sort();
+        lines.extend(uses.into_iter().map(|service| format!("uses {}", service)));
+        let mut provides: Vec<_> = self.provides.iter().collect();
+        provides.sort_by(|a, b| a.service.cmp(&b.service));
+        for provides in provides {
+            let providers: Vec<_> = provides.providers.iter().map(|p| dotted(p)).collect();
+            lines.push(format!(
+                "provides {} with {}",
+                dotted(&provides.service),
+                providers.join(" ")
+            ));
+        }
+        lines.extend(qualified("exports", &self.exports));
+        let mut opens: Vec<_> = self.opens.iter().filter(|o| !o.is_qualified()).collect();
+        opens.sort_by(|a, b| a.package.cmp(&b.package));
+        lines.extend(
+            opens
+                .iter()
+                .map(|o| format!("opens {}", dotted(&o.package))),
+        );
+        lines.extend(qualified("opens", &self.opens));
+        for package in &self.packages {
+            let visible = self
+                .exports
+                .iter()
+                .chain(&self.opens)
+                .any(|e| &e.package == package);
+            if !visible {
+                lines.push(format!("contains {}", dotted(package)));
+            }
+        }
+        if let Some(main_class) = &self.main_class {
+            lines.push(format!("main-class {}", dotted(main_class)));
+        }
+
+        for line in lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of a package or class as written in Java source.
+fn dotted(name: &str) -> String {
+    name.replace('/', ".")
+}
+
+/// The lines of the packages exported or opened to some modules only.
+fn qualified(directive: &str, packages: &[Exports]) -> Vec<String> {
+    let mut packages: Vec<_> = packages.iter().filter(|e| e.is_qualified()).collect();
+    packages.sort_by(|a, b| a.package.cmp(&b.package));
+    packages
+        .iter()
+        .map(|e| {
+            let mut targets = e.targets.clone();
+            targets.sort();
+            format!(
+                "qualified {} {} to {}",
+                directive,
+                dotted(&e.package),
+                targets.join(" ")
+            )
+        })
+        .collect()
+}
+
+fn version(pool: &ConstantPool, index: u16) -> Result<Option<String>, ClassLoadingError> {
+    match index {
+        0 => Ok(None),
+        index => Ok(Some(pool.get_utf8(index)?.to_string())),
+    }
+}
+
+fn exports(
+    pool: &ConstantPool,
+    packages: &[ModuleExportsAttribute],
+) -> Result<Vec<Exports>, ClassLoadingError> {
+    packages
+        .iter()
+        .map(|exports| {
+            Ok(Exports {
+                package: pool.get_package_name(exports.package_index)?.to_string(),
+                targets: exports
+                    .to_indices
+                    .iter()
+                    .map(|&index| pool.get_module_name(index).map(str::to_string))
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// MODULE TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod module_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{is_qualified_name, ModuleDescriptor};
+    use crate::class::Class;
+
+    fn read(module: &str) -> ModuleDescriptor {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("res/modules")
+            .join(module)
+            .join("module-info.class");
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        ModuleDescriptor::read(&class).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_read_module_info() {
+        let greeter = read("greeter");
+        assert_eq!(greeter.name, "greeter");
+        assert!(!greeter.open && !greeter.automatic);
+        let requires: Vec<_> = greeter
+            .requires
+            .iter()
+            .map(|requires| (requires.name.as_str(), requires.transitive))
+            .collect();
+        assert_eq!(requires, [("java.base", false), ("words", true)]);
+        assert!(greeter.exports_to("greeter", None));
+        assert!(greeter.packages.contains("greeter"));
+
+        let words = read("words");
+        assert!(words.exports_to("words", Some("app")));
+        assert!(!words.exports_to("words/internal", Some("app")));
+        assert!(words.opens_to("words/internal", Some("app")));
+        assert!(!words.opens_to("words/internal", None));
+    }
+
+    #[test]
+    fn test_describe_module() {
+        assert_eq!(
+            read("words").to_string(),
+            "words\n\
+             exports words\n\
+             requires java.base\n\
+             qualified opens words.internal to app\n"
+        );
+        assert_eq!(
+            read("greeter").to_string(),
+            "greeter\n\
+             exports greeter\n\
+             requires java.base\n\
+             requires words transitive\n"
+        );
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        assert!(is_qualified_name("com.example.app", '.'));
+        assert!(is_qualified_name("com/example/$internal", '/'));
+        assert!(!is_qualified_name("com.example.int", '.'));
+        assert!(!is_qualified_name("guava.31", '.'));
+        assert!(!is_qualified_name("com..example", '.'));
+        assert!(!is_qualified_name("META-INF/versions", '/'));
+    }
+
+    #[test]
+    fn test_other_classes_declare_no_module() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Access.class");
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        assert_eq!(ModuleDescriptor::read(&class).unwrap(), None);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::class::attributes::{Attribute, ElementValue};
+use crate::class::{Class, ClassLoadingError};
+
+const SCALA_SIGNATURE: &str = "Lscala/reflect/ScalaSignature;";
+const SCALA_LONG_SIGNATURE: &str = "Lscala/reflect/ScalaLongSignature;";
+
+// =============================================================================
+// SCALA SIGNATURE
+// =============================================================================
+
+/// Where scalac stored the pickle, the Scala types of a class and its
+/// members, which the Java signatures cannot express.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScalaSignature<'a> {
+    /// The raw pickle of a `ScalaSig` attribute, written before Scala 2.8.
+    Attribute(&'a [u8]),
+    /// The strings of a `ScalaSignature` annotation, or of a
+    /// `ScalaLongSignature` one for pickles too long for a single constant,
+    /// concatenated.
+    Annotation(String),
+    /// A `Scala` attribute, or a `ScalaSig` one without its annotation: a
+    /// class compiled by scalac but pickled with another one, like the
+    /// module class of a companion object.
+    Marker,
+}
+
+impl ScalaSignature<'_> {
+    /// The pickle, decoded from the strings of an annotation, `None` for a
+    /// marker.
+    pub fn pickle(&self) -> Option<Vec<u8>> {
+        match self {
+            ScalaSignature::Attribute(pickle) => Some(pickle.to_vec()),
+            ScalaSignature::Annotation(encoded) => Some(decode_pickle(encoded)),
+            ScalaSignature::Marker => None,
+        }
+    }
+}
+
+/// How the class records having been compiled by scalac, `None` for classes
+/// of other compilers.
+pub fn scala_signature(class: &Class) -> Result<Option<ScalaSignature<'_>>, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let mut marked = false;
+    let mut pickle = None;
+    for attribute in &class.attributes {
+        match attribute {
+            Attribute::RuntimeVisibleAnnotations(annotations) => {
+                for annotation in annotations {
+                    let annotation_type = pool.get_utf8(annotation.type_index)?;
+                    if annotation_type != SCALA_SIGNATURE && annotation_type != SCALA_LONG_SIGNATURE
+                    {
+                        continue;
+                    }
+                    let mut encoded = String::new();
+                    for pair in &annotation.element_value_pairs {
+                        if pool.get_utf8(pair.element_name_index)? != "bytes" {
+                            continue;
+                        }
+                        let values = match &pair.value {
+                            ElementValue::Array(array) => array.array_values.iter().collect(),
+                            value => vec![value],
+                        };
+                        for value in values {
+                            if let ElementValue::Constant(constant) = value {
+                                encoded.push_str(pool.get_utf8(constant.const_value_index)?);
+                            }
+                        }
+                    }
+                    return Ok(Some(ScalaSignature::Annotation(encoded)));
+                }
+            }
+            // Since Scala 2.8 the attribute only holds the version of the
+            // pickle, 5.0, and no entries
+            Attribute::ScalaSig(scala) if scala.info.len() > 3 => pickle = Some(&scala.info),
+            Attribute::ScalaSig(_) | Attribute::Scala(_) => marked = true,
+            _ => {}
+        }
+    }
+    Ok(match pickle {
+        Some(pickle) => Some(ScalaSignature::Attribute(pickle)),
+        None if marked => Some(ScalaSignature::Marker),
+        None => None,
+    })
+}
+
+/// Reverses the encoding of scalac, which spreads the pickle over 7-bit
+/// characters and shifts them by one, wrapping 0x7F to the two-byte zero
+/// of modified UTF-8.
+fn decode_pickle(encoded: &str) -> Vec<u8> {
+    let mut pickle = Vec::with_capacity(encoded.len() * 7 / 8 + 1);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for character in encoded.chars() {
+        let value = (character as u32).wrapping_sub(1) & 0x7F;
+        bits |= value << count;
+        count += 7;
+        if count >= 8 {
+            pickle.push(bits as u8);
+            bits >>= 8;
+            count -= 8;
+        }
+    }
+    // Bits left over pad the last character
+    pickle
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod scala_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{scala_signature, ScalaSignature};
+    use crate::class::attributes::*;
+    use crate::class::constant_pool::ConstantPoolBuilder;
+    use crate::class::Class;
+
+    /// Encodes the pickle like scalac does.
+    fn encode_pickle(pickle: &[u8]) -> String {
+        let mut encoded = String::new();
+        let mut bits = 0u32;
+        let mut count = 0;
+        for &byte in pickle {
+            bits |= (byte as u32) << count;
+            count += 8;
+            while count >= 7 {
+                encoded.push(char::from(((bits & 0x7F) as u8 + 1) & 0x7F));
+                bits >>= 7;
+                count -= 7;
+            }
+        }
+        if count > 0 {
+            encoded.push(char::from(((bits & 0x7F) as u8 + 1) & 0x7F));
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_scala_signature() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden/Hello.class");
+        let mut class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        assert_eq!(scala_signature(&class).unwrap(), None);
+
+        // A pickle holding 0x7F, encoded to the zero of modified UTF-8
+        let pickle: Vec<u8> = (0..=255u8).rev().collect();
+        let mut pool = ConstantPoolBuilder::from_pool(&class.constant_pool);
+        let scala_sig = pool.utf8("ScalaSig").unwrap();
+        let annotations = pool.utf8("RuntimeVisibleAnnotations").unwrap();
+        let type_index = pool.utf8("Lscala/reflect/ScalaSignature;").unwrap();
+        let element_name_index = pool.utf8("bytes").unwrap();
+        let const_value_index = pool.utf8(&encode_pickle(&pickle)).unwrap();
+        class.constant_pool = pool.build();
+        class.attributes.push(Attribute::Misc(MiscAttribute {
+            name_index: scala_sig as usize,
+            info: vec![5, 0, 0],
+        }));
+        class.attributes.push(Attribute::Misc(MiscAttribute {
+            name_index: annotations as usize,
+            info: [&[0, 1][..], &type_index.to_be_bytes(), &[0, 1]]
+                .concat()
+                .into_iter()
+                .chain(element_name_index.to_be_bytes())
+                .chain([b's'])
+                .chain(const_value_index.to_be_bytes())
+                .collect(),
+        }));
+
+        // Written and parsed again, through modified UTF-8
+        let bytes = class.to_bytes().unwrap();
+        assert!(bytes.windows(2).any(|window| window == [0xC0, 0x80]));
+        let class = Class::parse_bytes(&bytes).unwrap();
+        assert!(class.attributes.iter().any(
+            |attribute| matches!(attribute, Attribute::ScalaSig(scala) if scala.info == [5, 0, 0])
+        ));
+        let signature = scala_signature(&class).unwrap().unwrap();
+        assert!(matches!(signature, ScalaSignature::Annotation(_)));
+        assert_eq!(signature.pickle().unwrap(), pickle);
+    }
+}
@@ -0,0 +1,1144 @@
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use crate::class::attributes::{
+    AnnotationAttribute, Attribute, BootstrapMethodAttribute, ElementValue, ElementValuePair,
+    ExceptionIndexAttribute, ExceptionTableAttribute, InnerClassAttribute,
+    LineNumberTableAttribute, LocalVariableTableAttribute, LocalVariableTypeTableAttribute,
+    ModuleExportsAttribute, ModulePackageAttribute, ModuleProvidesAttribute,
+    ModuleRequiresAttribute, NestMemberAttribute, ParameterAnnotationAttribute,
+    StackMapTableAttribute, VerificationType,
+};
+use crate::class::constant_pool::{ConstUtf8, Constant, ConstantPool};
+use crate::class::{Class, ClassLoadingError, FieldInfo, Interface, MethodInfo, CLASS_MAGIC};
+
+// =============================================================================
+// COMMON TRAITS
+// =============================================================================
+
+/// The inverse of reading: writes the element as the class file stores it.
+/// Attributes look their names up in the constant pool.
+trait WriteOne {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()>;
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes a count the class file stores in 16 bits.
+fn write_u16_count<W: Write>(writer: &mut W, count: usize) -> io::Result<()> {
+    let count = u16::try_from(count)
+        .map_err(|_| invalid(format!("{} elements do not fit a class file", count)))?;
+    writer.write_u16::<BigEndian>(count)
+}
+
+/// Writes the elements after their 16 bit count.
+fn write_all<W: Write, T: WriteOne>(
+    writer: &mut W,
+    pool: &ConstantPool,
+    elements: &[T],
+) -> io::Result<()> {
+    write_u16_count(writer, elements.len())?;
+    for element in elements {
+        element.write_one(writer, pool)?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// CLASS
+// =============================================================================
+
+impl Class {
+    /// Writes the class file, which parses back to the same class.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let pool = &self.constant_pool;
+        writer.write_u32::<BigEndian>(CLASS_MAGIC)?;
+        writer.write_u16::<BigEndian>(self.minor_version)?;
+        writer.write_u16::<BigEndian>(self.major_version)?;
+        write_constant_pool(writer, pool)?;
+        writer.write_u16::<BigEndian>(self.access_flags.bits())?;
+        writer.write_u16::<BigEndian>(self.this_class)?;
+        writer.write_u16::<BigEndian>(self.super_class)?;
+        write_all(writer, pool, &self.interfaces)?;
+        write_all(writer, pool, &self.fields)?;
+        write_all(writer, pool, &self.methods)?;
+        write_all(writer, pool, &self.attributes)
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl WriteOne for Interface {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.interface_index)
+    }
+}
+
+impl WriteOne for FieldInfo {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.access_flags.bits())?;
+        writer.write_u16::<BigEndian>(self.name_index)?;
+        writer.write_u16::<BigEndian>(self.descriptor_index)?;
+        write_all(writer, pool, &self.attributes)
+    }
+}
+
+impl WriteOne for MethodInfo {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.access_flags.bits())?;
+        writer.write_u16::<BigEndian>(self.name_index)?;
+        writer.write_u16::<BigEndian>(self.descriptor_index)?;
+        write_all(writer, pool, &self.attributes)
+    }
+}
+
+// =============================================================================
+// CONSTANT POOL
+// =============================================================================
+
+fn write_constant_pool<W: Write>(writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+    // The count includes the unusable slot 0 and the second slots of wide
+    // constants
+    write_u16_count(writer, pool.constants.len() + pool.skip_table.len() + 1)?;
+    for constant in &pool.constants {
+        constant.write_one(writer, pool)?;
+    }
+    Ok(())
+}
+
+impl Constant {
+    /// The number of bytes the constant takes in the class file.
+    pub fn size(&self) -> usize {
+        match self {
+            Constant::Utf8(utf8) => 3 + ConstUtf8::encode(&utf8.string).len(),
+            Constant::Long(_) | Constant::Double(_) => 9,
+            Constant::Integer(_)
+            | Constant::Float(_)
+            | Constant::Field(_)
+            | Constant::Method(_)
+            | Constant::InterfaceMethod(_)
+            | Constant::NameAndType(_)
+            | Constant::InvokeDynamic(_) => 5,
+            Constant::MethodHandle(_) => 4,
+            Constant::Class(_)
+            | Constant::String(_)
+            | Constant::MethodType(_)
+            | Constant::Module(_)
+            | Constant::Package(_) => 3,
+        }
+    }
+}
+
+impl WriteOne for Constant {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        match self {
+            Constant::Utf8(utf8) => {
+                let bytes = ConstUtf8::encode(&utf8.string);
+                writer.write_u8(1)?;
+                write_u16_count(writer, bytes.len())?;
+                writer.write_all(&bytes)
+            }
+            Constant::Integer(integer) => {
+                writer.write_u8(3)?;
+                writer.write_i32::<BigEndian>(integer.value)
+            }
+            Constant::Float(float) => {
+                writer.write_u8(4)?;
+                writer.write_f32::<BigEndian>(float.value)
+            }
+            Constant::Long(long) => {
+                writer.write_u8(5)?;
+                writer.write_i64::<BigEndian>(long.value)
+            }
+            Constant::Double(double) => {
+                writer.write_u8(6)?;
+                writer.write_f64::<BigEndian>(double.value)
+            }
+            Constant::Class(class) => {
+                writer.write_u8(7)?;
+                writer.write_u16::<BigEndian>(class.name_index)
+            }
+            Constant::String(string) => {
+                writer.write_u8(8)?;
+                writer.write_u16::<BigEndian>(string.string_index)
+            }
+            Constant::Field(reference)
+            | Constant::Method(reference)
+            | Constant::InterfaceMethod(reference) => {
+                writer.write_u8(match self {
+                    Constant::Field(_) => 9,
+                    Constant::Method(_) => 10,
+                    _ => 11,
+                })?;
+                writer.write_u16::<BigEndian>(reference.class_index)?;
+                writer.write_u16::<BigEndian>(reference.name_and_type_index)
+            }
+            Constant::NameAndType(name_and_type) => {
+                writer.write_u8(12)?;
+                writer.write_u16::<BigEndian>(name_and_type.name_index)?;
+                writer.write_u16::<BigEndian>(name_and_type.descriptor_index)
+            }
+            Constant::MethodHandle(handle) => {
+                writer.write_u8(15)?;
+                writer.write_u8(handle.reference_kind)?;
+                writer.write_u16::<BigEndian>(handle.reference_index)
+            }
+            Constant::MethodType(method_type) => {
+                writer.write_u8(16)?;
+                writer.write_u16::<BigEndian>(method_type.descriptor_index)
+            }
+            Constant::InvokeDynamic(invoke_dynamic) => {
+                writer.write_u8(18)?;
+                writer.write_u16::<BigEndian>(invoke_dynamic.bootstrap_method_attr_index)?;
+                writer.write_u16::<BigEndian>(invoke_dynamic.name_and_type_index)
+            }
+            Constant::Module(module) => {
+                writer.write_u8(19)?;
+                writer.write_u16::<BigEndian>(module.name_index)
+            }
+            Constant::Package(package) => {
+                writer.write_u8(20)?;
+                writer.write_u16::<BigEndian>(package.name_index)
+            }
+        }
+    }
+}
+
+// =============================================================================
+// ATTRIBUTES
+// =============================================================================
+
+impl Attribute {
+    /// The name the attribute is stored under, `None` for [Attribute::Misc]
+    /// which keeps the index of its own.
+    fn name(&self) -> Option<&'static str> {
+        let name = match self {
+            Attribute::ConstantValue(_) => "ConstantValue",
+            Attribute::Code(_) => "Code",
+            Attribute::StackMapTable(_) => "StackMapTable",
+            Attribute::Exceptions(_) => "Exceptions",
+            Attribute::InnerClasses(_) => "InnerClasses",
+            Attribute::EnclosingMethod(_) => "EnclosingMethod",
+            Attribute::Synthetic() => "Synthetic",
+            Attribute::Signature(_) => "Signature",
+            Attribute::SourceFile(_) => "SourceFile",
+            Attribute::SourceDebugExtension(_) => "SourceDebugExtension",
+            Attribute::LineNumberTable(_) => "LineNumberTable",
+            Attribute::LocalVariableTable(_) => "LocalVariableTable",
+            Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            Attribute::Deprecated() => "Deprecated",
+            Attribute::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+            Attribute::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+            Attribute::RuntimeVisibleParameterAnnotations(_) => {
+                "RuntimeVisibleParameterAnnotations"
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(_) => {
+                "RuntimeInvisibleParameterAnnotations"
+            }
+            Attribute::AnnotationDefault(_) => "AnnotationDefault",
+            Attribute::BootstrapMethods(_) => "BootstrapMethods",
+            Attribute::NestHost(_) => "NestHost",
+            Attribute::NestMembers(_) => "NestMembers",
+            Attribute::Module(_) => "Module",
+            Attribute::ModulePackages(_) => "ModulePackages",
+            Attribute::ModuleMainClass(_) => "ModuleMainClass",
+            Attribute::ScalaSig(_) => "ScalaSig",
+            Attribute::Scala(_) => "Scala",
+            Attribute::Misc(_) => return None,
+        };
+        Some(name)
+    }
+
+    /// The name the attribute is stored under, read from the constant pool
+    /// for [Attribute::Misc].
+    pub fn stored_name<'a>(&self, pool: &'a ConstantPool) -> Result<&'a str, ClassLoadingError> {
+        match (self, self.name()) {
+            (_, Some(name)) => Ok(name),
+            (Attribute::Misc(misc), None) => pool.get_utf8(misc.name_index as u16),
+            (_, None) => unreachable!("Only unknown attributes lack a name"),
+        }
+    }
+
+    /// The number of bytes the attribute takes in the class file, with its
+    /// name and length, and the attributes nested in it.
+    pub fn size(&self, pool: &ConstantPool) -> io::Result<usize> {
+        let mut info = Vec::new();
+        self.write_info(&mut info, pool)?;
+        Ok(6 + info.len())
+    }
+
+    fn write_info<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        match self {
+            Attribute::ConstantValue(constant) => {
+                writer.write_u16::<BigEndian>(constant.const_value_index)
+            }
+            Attribute::Code(code) => {
+                writer.write_u16::<BigEndian>(code.max_stack)?;
+                writer.write_u16::<BigEndian>(code.max_locals)?;
+                let length = u32::try_from(code.code.len())
+                    .map_err(|_| invalid(format!("{} bytes of code", code.code.len())))?;
+                writer.write_u32::<BigEndian>(length)?;
+                writer.write_all(&code.code)?;
+                write_all(writer, pool, &code.exception_tables)?;
+                write_all(writer, pool, &code.attributes)
+            }
+            Attribute::StackMapTable(frames) => write_all(writer, pool, frames),
+            Attribute::Exceptions(exceptions) => write_all(writer, pool, exceptions),
+            Attribute::InnerClasses(inner_classes) => write_all(writer, pool, inner_classes),
+            Attribute::EnclosingMethod(enclosing) => {
+                writer.write_u16::<BigEndian>(enclosing.class_index)?;
+                writer.write_u16::<BigEndian>(enclosing.method_index)
+            }
+            Attribute::Synthetic() | Attribute::Deprecated() => Ok(()),
+            Attribute::Signature(signature) => {
+                writer.write_u16::<BigEndian>(signature.signature_index)
+            }
+            Attribute::SourceFile(source_file) => {
+                writer.write_u16::<BigEndian>(source_file.sourcefile_index)
+            }
+            Attribute::SourceDebugExtension(extension) => writer.write_all(&extension.debug_info),
+            Attribute::LineNumberTable(line_numbers) => write_all(writer, pool, line_numbers),
+            Attribute::LocalVariableTable(variables) => write_all(writer, pool, variables),
+            Attribute::LocalVariableTypeTable(variables) => write_all(writer, pool, variables),
+            Attribute::RuntimeVisibleAnnotations(annotations)
+            | Attribute::RuntimeInvisibleAnnotations(annotations) => {
+                write_all(writer, pool, annotations)
+            }
+            Attribute::RuntimeVisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+                let count = u8::try_from(parameters.len())
+                    .map_err(|_| invalid(format!("{} annotated parameters", parameters.len())))?;
+                writer.write_u8(count)?;
+                for parameter in parameters {
+                    parameter.write_one(writer, pool)?;
+                }
+                Ok(())
+            }
+            Attribute::AnnotationDefault(default) => default.default_value.write_one(writer, pool),
+            Attribute::BootstrapMethods(methods) => write_all(writer, pool, methods),
+            Attribute::NestHost(host) => writer.write_u16::<BigEndian>(host.host_class_index),
+            Attribute::NestMembers(members) => write_all(writer, pool, members),
+            Attribute::Module(module) => {
+                writer.write_u16::<BigEndian>(module.module_name_index)?;
+                writer.write_u16::<BigEndian>(module.module_flags.bits())?;
+                writer.write_u16::<BigEndian>(module.module_version_index)?;
+                write_all(writer, pool, &module.requires)?;
+                write_all(writer, pool, &module.exports)?;
+                write_all(writer, pool, &module.opens)?;
+                write_indices(writer, &module.uses_indices)?;
+                write_all(writer, pool, &module.provides)
+            }
+            Attribute::ModulePackages(packages) => write_all(writer, pool, packages),
+            Attribute::ModuleMainClass(main_class) => {
+                writer.write_u16::<BigEndian>(main_class.main_class_index)
+            }
+            Attribute::ScalaSig(scala) | Attribute::Scala(scala) => writer.write_all(&scala.info),
+            Attribute::Misc(misc) => writer.write_all(&misc.info),
+        }
+    }
+}
+
+impl WriteOne for Attribute {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        let name_index = match (self, self.name()) {
+            (Attribute::Misc(misc), _) => u16::try_from(misc.name_index).ok(),
+            (_, Some(name)) => pool.find_utf8(name),
+            (_, None) => None,
+        }
+        .ok_or_else(|| {
+            invalid(format!(
+                "The constant pool lacks the name of {:?}",
+                self.name()
+            ))
+        })?;
+
+        let mut info = Vec::new();
+        self.write_info(&mut info, pool)?;
+        let length = u32::try_from(info.len())
+            .map_err(|_| invalid(format!("{} bytes of attribute", info.len())))?;
+        writer.write_u16::<BigEndian>(name_index)?;
+        writer.write_u32::<BigEndian>(length)?;
+        writer.write_all(&info)
+    }
+}
+
+impl WriteOne for ExceptionTableAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.start_pc)?;
+        writer.write_u16::<BigEndian>(self.end_pc)?;
+        writer.write_u16::<BigEndian>(self.handler_pc)?;
+        writer.write_u16::<BigEndian>(self.catch_type)
+    }
+}
+
+impl WriteOne for VerificationType {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        match self {
+            VerificationType::Top => writer.write_u8(0),
+            VerificationType::Integer => writer.write_u8(1),
+            VerificationType::Float => writer.write_u8(2),
+            VerificationType::Double => writer.write_u8(3),
+            VerificationType::Long => writer.write_u8(4),
+            VerificationType::Null => writer.write_u8(5),
+            VerificationType::UninitializedThis => writer.write_u8(6),
+            VerificationType::Object(object) => {
+                writer.write_u8(7)?;
+                writer.write_u16::<BigEndian>(object.constant_index)
+            }
+            VerificationType::Uninitialized(uninitialized) => {
+                writer.write_u8(8)?;
+                writer.write_u16::<BigEndian>(uninitialized.offset)
+            }
+        }
+    }
+}
+
+impl WriteOne for StackMapTableAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        match self {
+            StackMapTableAttribute::Same(frame) if frame.offset_delta < 64 => {
+                writer.write_u8(frame.offset_delta)
+            }
+            StackMapTableAttribute::SameLocalsOneStackItem(frame) if frame.offset_delta < 64 => {
+                writer.write_u8(64 + frame.offset_delta)?;
+                frame.stack.write_one(writer, pool)
+            }
+            StackMapTableAttribute::SameLocalsOneStackItemExtended(frame) => {
+                writer.write_u8(247)?;
+                writer.write_u16::<BigEndian>(frame.offset_delta)?;
+                frame.stack.write_one(writer, pool)
+            }
+            StackMapTableAttribute::Chop(frame) if (1..=3).contains(&frame.chopped) => {
+                writer.write_u8(251 - frame.chopped)?;
+                writer.write_u16::<BigEndian>(frame.offset_delta)
+            }
+            StackMapTableAttribute::SameExtended(frame) => {
+                writer.write_u8(251)?;
+                writer.write_u16::<BigEndian>(frame.offset_delta)
+            }
+            StackMapTableAttribute::Append(frame) if (1..=3).contains(&frame.locals.len()) => {
+                writer.write_u8(251 + frame.locals.len() as u8)?;
+                writer.write_u16::<BigEndian>(frame.offset_delta)?;
+                for local in &frame.locals {
+                    local.write_one(writer, pool)?;
+                }
+                Ok(())
+            }
+            StackMapTableAttribute::Full(frame) => {
+                writer.write_u8(255)?;
+                writer.write_u16::<BigEndian>(frame.offset_delta)?;
+                write_all(writer, pool, &frame.locals)?;
+                write_all(writer, pool, &frame.stack)
+            }
+            frame => Err(invalid(format!("{:?} has no frame type", frame))),
+        }
+    }
+}
+
+impl WriteOne for ExceptionIndexAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.index)
+    }
+}
+
+impl WriteOne for InnerClassAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.inner_class_info_index)?;
+        writer.write_u16::<BigEndian>(self.outer_class_info_index)?;
+        writer.write_u16::<BigEndian>(self.inner_name_index)?;
+        writer.write_u16::<BigEndian>(self.inner_class_access_flags.bits())
+    }
+}
+
+impl WriteOne for LineNumberTableAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.start_pc)?;
+        writer.write_u16::<BigEndian>(self.line_number)
+    }
+}
+
+impl WriteOne for LocalVariableTableAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.start_pc)?;
+        writer.write_u16::<BigEndian>(self.length)?;
+        writer.write_u16::<BigEndian>(self.name_index)?;
+        writer.write_u16::<BigEndian>(self.descriptor_index)?;
+        writer.write_u16::<BigEndian>(self.index)
+    }
+}
+
+impl WriteOne for LocalVariableTypeTableAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.start_pc)?;
+        writer.write_u16::<BigEndian>(self.length)?;
+        writer.write_u16::<BigEndian>(self.name_index)?;
+        writer.write_u16::<BigEndian>(self.signature_index)?;
+        writer.write_u16::<BigEndian>(self.index)
+    }
+}
+
+impl WriteOne for ElementValue {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        match self {
+            ElementValue::Constant(constant) => {
+                writer.write_u8(constant.tag)?;
+                writer.write_u16::<BigEndian>(constant.const_value_index)
+            }
+            ElementValue::Enum(value) => {
+                writer.write_u8(b'e')?;
+                writer.write_u16::<BigEndian>(value.type_name_index)?;
+                writer.write_u16::<BigEndian>(value.const_name_index)
+            }
+            ElementValue::Class(class) => {
+                writer.write_u8(b'c')?;
+                writer.write_u16::<BigEndian>(class.class_info_index)
+            }
+            ElementValue::Annotation(value) => {
+                writer.write_u8(b'@')?;
+                value.annotation.write_one(writer, pool)
+            }
+            ElementValue::Array(array) => {
+                writer.write_u8(b'[')?;
+                write_all(writer, pool, &array.array_values)
+            }
+        }
+    }
+}
+
+impl WriteOne for ElementValuePair {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.element_name_index)?;
+        self.value.write_one(writer, pool)
+    }
+}
+
+impl WriteOne for AnnotationAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.type_index)?;
+        write_all(writer, pool, &self.element_value_pairs)
+    }
+}
+
+impl WriteOne for ParameterAnnotationAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, pool: &ConstantPool) -> io::Result<()> {
+        write_all(writer, pool, &self.annotations)
+    }
+}
+
+impl WriteOne for BootstrapMethodAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.bootstrap_method_ref)?;
+        write_u16_count(writer, self.bootstrap_arguments.len())?;
+        for argument in &self.bootstrap_arguments {
+            writer.write_u16::<BigEndian>(*argument)?;
+        }
+        Ok(())
+    }
+}
+
+impl WriteOne for NestMemberAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.class_index)
+    }
+}
+
+/// Writes the constant indices after their 16 bit count.
+fn write_indices<W: Write>(writer: &mut W, indices: &[u16]) -> io::Result<()> {
+    write_u16_count(writer, indices.len())?;
+    for index in indices {
+        writer.write_u16::<BigEndian>(*index)?;
+    }
+    Ok(())
+}
+
+impl WriteOne for ModuleRequiresAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.requires_index)?;
+        writer.write_u16::<BigEndian>(self.requires_flags.bits())?;
+        writer.write_u16::<BigEndian>(self.requires_version_index)
+    }
+}
+
+impl WriteOne for ModuleExportsAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.package_index)?;
+        writer.write_u16::<BigEndian>(self.flags.bits())?;
+        write_indices(writer, &self.to_indices)
+    }
+}
+
+impl WriteOne for ModuleProvidesAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.provides_index)?;
+        write_indices(writer, &self.provides_with_indices)
+    }
+}
+
+impl WriteOne for ModulePackageAttribute {
+    fn write_one<W: Write>(&self, writer: &mut W, _: &ConstantPool) -> io::Result<()> {
+        writer.write_u16::<BigEndian>(self.package_index)
+    }
+}
+
+// =============================================================================
+// WRITER TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod writer_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    use crate::class::attributes::*;
+    use crate::class::constant_pool::*;
+    use crate::class::{
+        Class, ClassAccessFlags, FieldAccessFlags, FieldInfo, Interface, MethodAccessFlags,
+        MethodInfo,
+    };
+
+    /// The names of the attributes, at the start of every generated constant
+    /// pool, followed by the name of an attribute unknown to the parser.
+    const ATTRIBUTE_NAMES: [&str; 27] = [
+        "ConstantValue",
+        "Code",
+        "StackMapTable",
+        "Exceptions",
+        "InnerClasses",
+        "EnclosingMethod",
+        "Synthetic",
+        "Signature",
+        "SourceFile",
+        "SourceDebugExtension",
+        "LineNumberTable",
+        "LocalVariableTable",
+        "LocalVariableTypeTable",
+        "Deprecated",
+        "RuntimeVisibleAnnotations",
+        "RuntimeInvisibleAnnotations",
+        "RuntimeVisibleParameterAnnotations",
+        "RuntimeInvisibleParameterAnnotations",
+        "AnnotationDefault",
+        "BootstrapMethods",
+        "NestHost",
+        "NestMembers",
+        "Module",
+        "ModulePackages",
+        "ModuleMainClass",
+        "ScalaSig",
+        "Scala",
+    ];
+    const UNKNOWN_ATTRIBUTE: usize = ATTRIBUTE_NAMES.len() + 1;
+
+    fn utf8(string: &str) -> Constant {
+        Constant::Utf8(ConstUtf8 {
+            string: string.to_string(),
+        })
+    }
+
+    fn constant() -> impl Strategy<Value = Constant> {
+        let numbers = prop_oneof![
+            any::<i32>().prop_map(|value| Constant::Integer(ConstInteger { value })),
+            any::<f32>().prop_map(|value| Constant::Float(ConstFloat { value })),
+            any::<i64>().prop_map(|value| Constant::Long(ConstLong { value })),
+            any::<f64>().prop_map(|value| Constant::Double(ConstDouble { value })),
+        ];
+        let reference = || {
+            (any::<u16>(), any::<u16>()).prop_map(|(class_index, name_and_type_index)| {
+                ConstClassReference {
+                    class_index,
+                    name_and_type_index,
+                }
+            })
+        };
+        let references = prop_oneof![
+            any::<u16>().prop_map(|name_index| Constant::Class(ConstClass { name_index })),
+            any::<u16>().prop_map(|string_index| Constant::String(ConstString { string_index })),
+            reference().prop_map(Constant::Field),
+            reference().prop_map(Constant::Method),
+            reference().prop_map(Constant::InterfaceMethod),
+            any::<u16>().prop_map(|name_index| Constant::Module(ConstModule { name_index })),
+            any::<u16>().prop_map(|name_index| Constant::Package(ConstPackage { name_index })),
+            (any::<u16>(), any::<u16>()).prop_map(|(name_index, descriptor_index)| {
+                Constant::NameAndType(ConstNameAndType {
+                    name_index,
+                    descriptor_index,
+                })
+            }),
+        ];
+        let dynamic = prop_oneof![
+            (any::<u8>(), any::<u16>()).prop_map(|(reference_kind, reference_index)| {
+                Constant::MethodHandle(ConstMethodHandle {
+                    reference_kind,
+                    reference_index,
+                })
+            }),
+            any::<u16>().prop_map(|descriptor_index| {
+                Constant::MethodType(ConstMethodType { descriptor_index })
+            }),
+            (any::<u16>(), any::<u16>()).prop_map(
+                |(bootstrap_method_attr_index, name_and_type_index)| {
+                    Constant::InvokeDynamic(ConstInvokeDynamic {
+                        bootstrap_method_attr_index,
+                        name_and_type_index,
+                    })
+                }
+            ),
+        ];
+        prop_oneof![
+            ".{0,16}".prop_map(|string| utf8(&string)),
+            numbers,
+            references,
+            dynamic
+        ]
+    }
+
+    fn verification_type() -> impl Strategy<Value = VerificationType> {
+        prop_oneof![
+            select(vec![0, 1, 2, 3, 4, 5, 6]).prop_map(|tag| match tag {
+                0 => VerificationType::Top,
+                1 => VerificationType::Integer,
+                2 => VerificationType::Float,
+                3 => VerificationType::Double,
+                4 => VerificationType::Long,
+                5 => VerificationType::Null,
+                _ => VerificationType::UninitializedThis,
+            }),
+            any::<u16>().prop_map(|constant_index| {
+                VerificationType::Object(ObjectVariableInfo { constant_index })
+            }),
+            any::<u16>().prop_map(|offset| {
+                VerificationType::Uninitialized(UninitializedVariableInfo { offset })
+            }),
+        ]
+    }
+
+    fn frame() -> impl Strategy<Value = StackMapTableAttribute> {
+        prop_oneof![
+            (0u8..64)
+                .prop_map(|offset_delta| StackMapTableAttribute::Same(SameFrame { offset_delta })),
+            (0u8..64, verification_type()).prop_map(|(offset_delta, stack)| {
+                StackMapTableAttribute::SameLocalsOneStackItem(SameLocalsOneStackItemFrame {
+                    offset_delta,
+                    stack,
+                })
+            }),
+            (any::<u16>(), verification_type()).prop_map(|(offset_delta, stack)| {
+                StackMapTableAttribute::SameLocalsOneStackItemExtended(
+                    SameLocalsOneStackItemExtendedFrame {
+                        offset_delta,
+                        stack,
+                    },
+                )
+            }),
+            (1u8..=3, any::<u16>()).prop_map(|(chopped, offset_delta)| {
+                StackMapTableAttribute::Chop(ChopFrame {
+                    chopped,
+                    offset_delta,
+                })
+            }),
+            any::<u16>().prop_map(|offset_delta| {
+                StackMapTableAttribute::SameExtended(SameExtendedFrame { offset_delta })
+            }),
+            (any::<u16>(), vec(verification_type(), 1..=3)).prop_map(|(offset_delta, locals)| {
+                StackMapTableAttribute::Append(AppendFrame {
+                    offset_delta,
+                    locals,
+                })
+            }),
+            (
+                any::<u16>(),
+                vec(verification_type(), 0..4),
+                vec(verification_type(), 0..4)
+            )
+                .prop_map(|(offset_delta, locals, stack)| {
+                    StackMapTableAttribute::Full(FullFrame {
+                        offset_delta,
+                        locals,
+                        stack,
+                    })
+                }),
+        ]
+    }
+
+    fn annotation_of(
+        value: impl Strategy<Value = ElementValue>,
+    ) -> impl Strategy<Value = AnnotationAttribute> {
+        (any::<u16>(), vec((any::<u16>(), value), 0..3)).prop_map(|(type_index, pairs)| {
+            AnnotationAttribute {
+                type_index,
+                element_value_pairs: pairs
+                    .into_iter()
+                    .map(|(element_name_index, value)| ElementValuePair {
+                        element_name_index,
+                        value,
+                    })
+                    .collect(),
+            }
+        })
+    }
+
+    fn element_value() -> BoxedStrategy<ElementValue> {
+        let leaf = prop_oneof![
+            (select(b"BCDFIJSZs".to_vec()), any::<u16>()).prop_map(|(tag, const_value_index)| {
+                ElementValue::Constant(ConstantElementValueAttribute {
+                    tag,
+                    const_value_index,
+                })
+            }),
+            (any::<u16>(), any::<u16>()).prop_map(|(type_name_index, const_name_index)| {
+                ElementValue::Enum(EnumElementValue {
+                    type_name_index,
+                    const_name_index,
+                })
+            }),
+            any::<u16>().prop_map(|class_info_index| {
+                ElementValue::Class(ClassElementValueAttribute { class_info_index })
+            }),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(|array_values| ElementValue::Array(
+                    ArrayElementValue { array_values }
+                )),
+                annotation_of(inner).prop_map(|annotation| {
+                    ElementValue::Annotation(AnnotationElementValue { annotation })
+                }),
+            ]
+        })
+        .boxed()
+    }
+
+    fn annotations() -> impl Strategy<Value = Vec<AnnotationAttribute>> {
+        vec(annotation_of(element_value()), 0..3)
+    }
+
+    fn parameter_annotations() -> impl Strategy<Value = Vec<ParameterAnnotationAttribute>> {
+        vec(
+            annotations().prop_map(|annotations| ParameterAnnotationAttribute { annotations }),
+            0..3,
+        )
+    }
+
+    fn module() -> impl Strategy<Value = ModuleAttribute> {
+        let exports = || {
+            vec(
+                (any::<[u16; 2]>(), vec(any::<u16>(), 0..3)).prop_map(
+                    |([package_index, flags], to_indices)| ModuleExportsAttribute {
+                        package_index,
+                        flags: ModuleFlags::from_bits_truncate(flags),
+                        to_indices,
+                    },
+                ),
+                0..3,
+            )
+        };
+        (
+            any::<[u16; 3]>(),
+            vec(any::<[u16; 3]>(), 0..3),
+            exports(),
+            exports(),
+            vec(any::<u16>(), 0..3),
+            vec((any::<u16>(), vec(any::<u16>(), 0..3)), 0..3),
+        )
+            .prop_map(
+                |([name, flags, version], requires, exports, opens, uses_indices, provides)| {
+                    ModuleAttribute {
+                        module_name_index: name,
+                        module_flags: ModuleFlags::from_bits_truncate(flags),
+                        module_version_index: version,
+                        requires: requires
+                            .into_iter()
+                            .map(|[index, flags, version]| ModuleRequiresAttribute {
+                                requires_index: index,
+                                requires_flags: RequiresFlags::from_bits_truncate(flags),
+                                requires_version_index: version,
+                            })
+                            .collect(),
+                        exports,
+                        opens,
+                        uses_indices,
+                        provides: provides
+                            .into_iter()
+                            .map(|(provides_index, provides_with_indices)| {
+                                ModuleProvidesAttribute {
+                                    provides_index,
+                                    provides_with_indices,
+                                }
+                            })
+                            .collect(),
+                    }
+                },
+            )
+    }
+
+    /// Attributes which hold no attributes of their own.
+    fn leaf_attribute() -> BoxedStrategy<Attribute> {
+        let tables = prop_oneof![
+            vec(frame(), 0..4).prop_map(Attribute::StackMapTable),
+            vec(any::<u16>(), 0..4).prop_map(|indices| Attribute::Exceptions(
+                indices
+                    .into_iter()
+                    .map(|index| ExceptionIndexAttribute { index })
+                    .collect()
+            )),
+            vec(any::<u16>(), 0..4).prop_map(|indices| Attribute::NestMembers(
+                indices
+                    .into_iter()
+                    .map(|class_index| NestMemberAttribute { class_index })
+                    .collect()
+            )),
+            vec(any::<u16>(), 0..4).prop_map(|indices| Attribute::ModulePackages(
+                indices
+                    .into_iter()
+                    .map(|package_index| ModulePackageAttribute { package_index })
+                    .collect()
+            )),
+            module().prop_map(Attribute::Module),
+            vec((any::<[u16; 3]>(), any::<u16>()), 0..4).prop_map(|classes| {
+                Attribute::InnerClasses(
+                    classes
+                        .into_iter()
+                        .map(|([inner, outer, name], flags)| InnerClassAttribute {
+                            inner_class_info_index: inner,
+                            outer_class_info_index: outer,
+                            inner_name_index: name,
+                            inner_class_access_flags: InnerClassAccessFlags::from_bits_truncate(
+                                flags,
+                            ),
+                        })
+                        .collect(),
+                )
+            }),
+            vec(any::<[u16; 2]>(), 0..4).prop_map(|lines| Attribute::LineNumberTable(
+                lines
+                    .into_iter()
+                    .map(|[start_pc, line_number]| LineNumberTableAttribute {
+                        start_pc,
+                        line_number,
+                    })
+                    .collect()
+            )),
+            vec(any::<[u16; 5]>(), 0..4).prop_map(|variables| Attribute::LocalVariableTable(
+                variables
+                    .into_iter()
+                    .map(|[start_pc, length, name_index, descriptor_index, index]| {
+                        LocalVariableTableAttribute {
+                            start_pc,
+                            length,
+                            name_index,
+                            descriptor_index,
+                            index,
+                        }
+                    })
+                    .collect()
+            )),
+            vec(any::<[u16; 5]>(), 0..4).prop_map(|variables| {
+                Attribute::LocalVariableTypeTable(
+                    variables
+                        .into_iter()
+                        .map(|[start_pc, length, name_index, signature_index, index]| {
+                            LocalVariableTypeTableAttribute {
+                                start_pc,
+                                length,
+                                name_index,
+                                signature_index,
+                                index,
+                            }
+                        })
+                        .collect(),
+                )
+            }),
+            vec((any::<u16>(), vec(any::<u16>(), 0..4)), 0..3).prop_map(|methods| {
+                Attribute::BootstrapMethods(
+                    methods
+                        .into_iter()
+                        .map(|(bootstrap_method_ref, bootstrap_arguments)| {
+                            BootstrapMethodAttribute {
+                                bootstrap_method_ref,
+                                bootstrap_arguments,
+                            }
+                        })
+                        .collect(),
+                )
+            }),
+        ];
+        let indices = prop_oneof![
+            any::<u16>().prop_map(|const_value_index| {
+                Attribute::ConstantValue(ConstantValueAttribute { const_value_index })
+            }),
+            any::<[u16; 2]>().prop_map(|[class_index, method_index]| {
+                Attribute::EnclosingMethod(EnclosingMethodAttribute {
+                    class_index,
+                    method_index,
+                })
+            }),
+            any::<u16>().prop_map(|signature_index| {
+                Attribute::Signature(SignatureAttribute { signature_index })
+            }),
+            any::<u16>().prop_map(|sourcefile_index| {
+                Attribute::SourceFile(SourceFileAttribute { sourcefile_index })
+            }),
+            any::<u16>().prop_map(|host_class_index| {
+                Attribute::NestHost(NestHostAttribute { host_class_index })
+            }),
+            any::<u16>().prop_map(|main_class_index| {
+                Attribute::ModuleMainClass(ModuleMainClassAttribute { main_class_index })
+            }),
+            any::<bool>().prop_map(|synthetic| if synthetic {
+                Attribute::Synthetic()
+            } else {
+                Attribute::Deprecated()
+            }),
+        ];
+        let annotations = prop_oneof![
+            annotations().prop_map(Attribute::RuntimeVisibleAnnotations),
+            annotations().prop_map(Attribute::RuntimeInvisibleAnnotations),
+            parameter_annotations().prop_map(Attribute::RuntimeVisibleParameterAnnotations),
+            parameter_annotations().prop_map(Attribute::RuntimeInvisibleParameterAnnotations),
+            element_value().prop_map(|default_value| {
+                Attribute::AnnotationDefault(AnnotationDefaultAttribute { default_value })
+            }),
+        ];
+        let raw = prop_oneof![
+            vec(any::<u8>(), 0..16).prop_map(|debug_info| {
+                Attribute::SourceDebugExtension(SourceDebugExtensionAttribute { debug_info })
+            }),
+            vec(any::<u8>(), 0..16).prop_map(|info| Attribute::ScalaSig(ScalaAttribute { info })),
+            vec(any::<u8>(), 0..16).prop_map(|info| Attribute::Misc(MiscAttribute {
+                name_index: UNKNOWN_ATTRIBUTE,
+                info,
+            })),
+        ];
+        prop_oneof![tables, indices, annotations, raw].boxed()
+    }
+
+    fn attribute() -> impl Strategy<Value = Attribute> {
+        let code = (
+            any::<[u16; 2]>(),
+            vec(any::<u8>(), 0..64),
+            vec(any::<[u16; 4]>(), 0..3),
+            vec(leaf_attribute(), 0..3),
+        )
+            .prop_map(|([max_stack, max_locals], code, handlers, attributes)| {
+                Attribute::Code(CodeAttribute {
+                    max_stack,
+                    max_locals,
+                    code,
+                    exception_tables: handlers
+                        .into_iter()
+                        .map(
+                            |[start_pc, end_pc, handler_pc, catch_type]| ExceptionTableAttribute {
+                                start_pc,
+                                end_pc,
+                                handler_pc,
+                                catch_type,
+                            },
+                        )
+                        .collect(),
+                    attributes,
+                })
+            });
+        prop_oneof![leaf_attribute(), code]
+    }
+
+    fn class() -> impl Strategy<Value = Class> {
+        let member = || (any::<[u16; 3]>(), vec(attribute(), 0..3));
+        (
+            any::<[u16; 2]>(),
+            vec(constant(), 0..16),
+            any::<[u16; 3]>(),
+            vec(any::<u16>(), 0..4),
+            vec(member(), 0..4),
+            vec(member(), 0..4),
+            vec(attribute(), 0..4),
+        )
+            .prop_map(
+                |(
+                    [minor_version, major_version],
+                    constants,
+                    [access_flags, this_class, super_class],
+                    interfaces,
+                    fields,
+                    methods,
+                    attributes,
+                )| {
+                    let names = ATTRIBUTE_NAMES
+                        .iter()
+                        .chain(&["Unknown"])
+                        .map(|name| utf8(name));
+                    Class {
+                        minor_version,
+                        major_version,
+                        constant_pool: ConstantPool::new(names.chain(constants).collect()),
+                        access_flags: ClassAccessFlags::from_bits_truncate(access_flags),
+                        this_class,
+                        super_class,
+                        interfaces: interfaces
+                            .into_iter()
+                            .map(|interface_index| Interface { interface_index })
+                            .collect(),
+                        fields: fields
+                            .into_iter()
+                            .map(
+                                |([flags, name_index, descriptor_index], attributes)| FieldInfo {
+                                    access_flags: FieldAccessFlags::from_bits_truncate(flags),
+                                    name_index,
+                                    descriptor_index,
+                                    attributes,
+                                },
+                            )
+                            .collect(),
+                        methods: methods
+                            .into_iter()
+                            .map(
+                                |([flags, name_index, descriptor_index], attributes)| MethodInfo {
+                                    access_flags: MethodAccessFlags::from_bits_truncate(flags),
+                                    name_index,
+                                    descriptor_index,
+                                    attributes,
+                                },
+                            )
+                            .collect(),
+                        attributes,
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn test_written_classes_parse_back(class in class()) {
+            let bytes = class.to_bytes().unwrap();
+            let parsed = Class::parse_bytes(&bytes).unwrap();
+            prop_assert_eq!(parsed.to_bytes().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_class_files_are_written_back_unchanged() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        for entry in fs::read_dir(root).unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "class")
+            {
+                let bytes = fs::read(&path).unwrap();
+                let class = Class::parse_bytes(&bytes).unwrap();
+                assert_eq!(class.to_bytes().unwrap(), bytes, "{}", path.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod class;
+#[cfg(feature = "vm")]
+pub mod packaging;
+#[cfg(feature = "vm")]
+pub mod vm;
@@ -1,50 +1,2145 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::fs::File;
-use std::io;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing_subscriber::EnvFilter;
 
-use crate::class::Class;
-use crate::packaging::jar;
+use bvm::class::attributes::{Attribute, CodeAttribute};
+use bvm::class::instruction;
+use bvm::class::{Class, ClassLoadingError, MethodInfo, ParseLimits};
+use bvm::packaging::classpath::{ClassPath, ClassPathEntry};
+use bvm::packaging::filter::ClassFilter;
+use bvm::packaging::inventory;
+use bvm::packaging::jdk::JdkImage;
+use bvm::packaging::modulepath::{ModulePath, ModuleReference};
+use bvm::packaging::progress::{Progress, ProgressListener, ProgressTracker};
+use bvm::packaging::watch::ClassPathWatcher;
+#[cfg(unix)]
+use bvm::vm::agent::NativeAgent;
+use bvm::vm::assertions::AssertionStatus;
+#[cfg(feature = "tui")]
+use bvm::vm::browser::ClassBrowser;
+use bvm::vm::callgraph::{CallGraph, MethodRef};
+use bvm::vm::cfg::ControlFlowGraph;
+use bvm::vm::class_cache::{Cached, ClassCache};
+use bvm::vm::clock::VirtualClock;
+use bvm::vm::compatibility::{check_compatibility, Compatibility};
+use bvm::vm::coverage::{CodeCoverage, CoverageFormat};
+use bvm::vm::deadcode::{find_dead_code, KeepRules};
+use bvm::vm::decompiler::decompile_class;
+use bvm::vm::diff::{diff_class_sets, diff_classes};
+use bvm::vm::inference::infer_frames;
+#[cfg(feature = "jit")]
+use bvm::vm::jit::{CompilationMode, JitCompiler};
+use bvm::vm::junit::{run_tests, TestOutcome};
+use bvm::vm::linker::Verification;
+use bvm::vm::metrics::{self, ClassMetrics, ClassSizes, EntryMetrics, EntrySizes};
+use bvm::vm::modules::ModuleGraph;
+use bvm::vm::optimizer::{optimize_class, OptimizationStats};
+use bvm::vm::profiler::{MethodProfiler, ProfileFormat};
+use bvm::vm::regions::try_regions;
+use bvm::vm::registry::ClassRegistry;
+use bvm::vm::repl::Repl;
+use bvm::vm::replay::InteractionLog;
+use bvm::vm::sampler::SamplingProfiler;
+use bvm::vm::search::{find_references, find_subtypes, Pattern};
+use bvm::vm::shrink::{shrink_jar, ShrinkOptions};
+use bvm::vm::strip::{strip_class, Stripped};
+use bvm::vm::trace::{BytecodeTrace, ClassLoadingLog, GcLog};
+use bvm::vm::value::JValue;
+use bvm::vm::{Vm, VmError};
 
-mod class;
-mod packaging;
-mod vm;
+/// How often `--watch` looks for changed classes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
 struct Args {
-    // Colon separated path of classes
-    // #[clap(short, long)]
-    // classpath: Option<String>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Increases the diagnostic log level (-v info, -vv debug, -vvv trace),
+    /// overridden by RUST_LOG
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Format of the diagnostic log written to standard error
+    #[clap(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
+    #[clap(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Colon separated path of classes
+    #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+    classpath: String,
+    /// Path of modular jars, jmods and directories of modules, checking
+    /// the readability and exports of the modules resolved
+    #[clap(short = 'p', long, value_name = "PATH")]
+    module_path: Option<String>,
+    /// Comma separated root modules to resolve besides the main module,
+    /// or ALL-MODULE-PATH for every module of the module path
+    #[clap(
+        long,
+        value_name = "MODULES",
+        value_delimiter = ',',
+        requires = "module_path"
+    )]
+    add_modules: Vec<String>,
+    /// JDK providing the bootstrap classes, defaults to JAVA_HOME
+    #[clap(long)]
+    java_home: Option<PathBuf>,
+    /// Defines the JDK's classes from the class archive, written at exit
+    /// when missing or outdated, speeding up the startup of later runs
+    #[clap(long, value_name = "FILE")]
+    class_archive: Option<PathBuf>,
+    /// Logs every interpreted instruction, optionally only of the methods
+    /// matching the comma separated patterns, e.g. `com.example.*.main`
+    #[clap(long, value_name = "PATTERNS", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    trace_bytecode: Option<String>,
+    /// Logs every class as it is defined, with a summary at exit
+    #[clap(long = "verbose:class")]
+    verbose_class: bool,
+    /// Logs every garbage collection
+    #[clap(long = "verbose:gc")]
+    verbose_gc: bool,
+    /// Profiles the invoked methods, writing the profile at exit
+    #[clap(long)]
+    profile: bool,
+    /// Format of the profile: a report sorted by self time, or collapsed
+    /// stacks for flamegraphs
+    #[clap(long, value_enum, default_value_t = ProfileOutputFormat::Report, requires = "profile")]
+    profile_format: ProfileOutputFormat,
+    /// File to write the profile to, instead of standard error
+    #[clap(long, value_name = "FILE", requires = "profile")]
+    profile_output: Option<PathBuf>,
+    /// Samples the Java stacks, writing them to the file as collapsed stacks
+    #[clap(long, value_name = "FILE")]
+    sample: Option<PathBuf>,
+    /// Milliseconds between two samples
+    #[clap(long, value_name = "MS", default_value_t = 1, requires = "sample")]
+    sample_interval: u64,
+    /// Adds the executed instruction to the samples as the innermost frame
+    #[clap(long, requires = "sample")]
+    sample_opcodes: bool,
+    /// Collects the line and instruction coverage of the application
+    /// classes, writing the report at exit
+    #[clap(long)]
+    coverage: bool,
+    /// Format of the coverage report
+    #[clap(long, value_enum, default_value_t = CoverageOutputFormat::Lcov, requires = "coverage")]
+    coverage_format: CoverageOutputFormat,
+    /// File to write the coverage report to, instead of standard error
+    #[clap(long, value_name = "FILE", requires = "coverage")]
+    coverage_output: Option<PathBuf>,
+    /// Runs on a virtual clock starting at the Unix epoch and only advancing
+    /// as the program sleeps, making its timing reproducible
+    #[clap(long)]
+    virtual_time: bool,
+    /// Records the readings of the host system by the natives, like the time
+    /// and the environment, to the file
+    #[clap(long, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Replays the readings of the host system recorded to the file by
+    /// `--record`, reproducing the recorded run
+    #[clap(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// Size of the stack of every thread, in bytes or with a `k`, `m` or `g`
+    /// suffix, e.g. `1m`
+    #[clap(long = "Xss", value_name = "SIZE", value_parser = parse_size)]
+    xss: Option<usize>,
+    /// Enables assertions: in every class outside the JDK without a target,
+    /// in a package and its subpackages for `PACKAGE...`, `...` standing for
+    /// the unnamed package, or in a class
+    #[clap(
+        long = "ea",
+        visible_alias = "enableassertions",
+        value_name = "PACKAGE...|CLASS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    enable_assertions: Vec<String>,
+    /// Disables assertions, with the targets of --ea. The last option for a
+    /// target wins, more specific targets taking precedence
+    #[clap(
+        long = "da",
+        visible_alias = "disableassertions",
+        value_name = "PACKAGE...|CLASS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    disable_assertions: Vec<String>,
+    /// The --ea and --da options in the order given, collected from the
+    /// matches as clap keeps them apart
+    #[clap(skip)]
+    assertions: AssertionStatus,
+    /// Applies peephole optimizations to the classes as they are loaded,
+    /// before they are interpreted or compiled
+    #[clap(long)]
+    optimize: bool,
+    /// Loads an instrumentation agent, a shared library transforming the
+    /// class files before they are defined, passing it the options
+    #[cfg(unix)]
+    #[clap(long = "agent", value_name = "LIBRARY[=OPTIONS]")]
+    agents: Vec<String>,
+    /// Only interprets methods, never compiling them to native code
+    #[cfg(feature = "jit")]
+    #[clap(long = "Xint", conflicts_with = "xcomp")]
+    xint: bool,
+    /// Compiles methods before they first run, instead of once hot
+    #[cfg(feature = "jit")]
+    #[clap(long = "Xcomp")]
+    xcomp: bool,
+    /// Lists every class of the classpath with its provider, version, size
+    /// and flags, instead of running a main class
+    #[clap(long)]
+    list_classes: bool,
+    /// Format of the class list
+    #[clap(long, value_enum, default_value = "table", requires = "list_classes")]
+    list_format: ReportFormat,
+    /// Directory caching what is read from the classes between runs, keyed
+    /// by their content
+    #[clap(long, value_name = "DIR", requires = "list_classes")]
+    class_cache: Option<PathBuf>,
+    #[clap(flatten)]
+    list_filter: FilterArgs,
+    /// Loads the main class and resolves the references of every class it
+    /// transitively uses, without running anything, reporting the missing
+    /// classes, fields and methods and exiting with 1 if any
+    #[clap(long)]
+    dry_link: bool,
     /// Main class to be executed
-    main_class: String,
+    main_class: Option<String>,
+    /// Arguments passed to the main method
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    arguments: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ProfileOutputFormat {
+    Report,
+    Collapsed,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CoverageOutputFormat {
+    Lcov,
+    JacocoXml,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ReportFormat {
+    Table,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FindKind {
+    /// Classes with a matching name, with the entries providing them
+    Class,
+    /// Classes extending or implementing a matching class
+    Subtypes,
+    /// Instructions using a matching field or method, like
+    /// `java.lang.System#out`
+    References,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StrippedAttributes {
+    /// The `LineNumberTable` of the code
+    LineNumbers,
+    /// The `LocalVariableTable` and `LocalVariableTypeTable` of the code
+    LocalVariables,
+    SourceFile,
+    SourceDebugExtension,
+    /// The annotations only retained in the class files
+    InvisibleAnnotations,
+    /// The annotations reflection reads
+    VisibleAnnotations,
+    /// Nothing, keeping every attribute
+    None,
+}
+
+impl StrippedAttributes {
+    fn flags(attributes: &[StrippedAttributes]) -> Stripped {
+        attributes
+            .iter()
+            .fold(Stripped::empty(), |flags, attributes| {
+                flags
+                    | match attributes {
+                        StrippedAttributes::LineNumbers => Stripped::LINE_NUMBERS,
+                        StrippedAttributes::LocalVariables => Stripped::LOCAL_VARIABLES,
+                        StrippedAttributes::SourceFile => Stripped::SOURCE_FILE,
+                        StrippedAttributes::SourceDebugExtension => {
+                            Stripped::SOURCE_DEBUG_EXTENSION
+                        }
+                        StrippedAttributes::InvisibleAnnotations => Stripped::INVISIBLE_ANNOTATIONS,
+                        StrippedAttributes::VisibleAnnotations => Stripped::VISIBLE_ANNOTATIONS,
+                        StrippedAttributes::None => Stripped::empty(),
+                    }
+            })
+    }
+}
+
+// Selects the classes the tools scanning the whole classpath read. A plain
+// comment, as clap would make a doc comment the about text of `bvm`.
+#[derive(clap::Args, Debug, Default)]
+struct FilterArgs {
+    /// Only reads the classes matching a glob like `com/example/**`, `**`
+    /// crossing packages, or in a package like `com.example`
+    #[clap(long = "include", value_name = "PATTERN")]
+    include: Vec<String>,
+    /// Skips the classes matching a glob like `**/internal/**`, or in a
+    /// package
+    #[clap(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+}
+
+impl FilterArgs {
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Reports classes and packages provided by more than one classpath entry
+    Doctor {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+    /// Writes the control-flow graphs of a class' methods in Graphviz DOT
+    Cfg {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// Class whose methods are drawn
+        class: String,
+        /// Only draws the methods with this name, or name and descriptor,
+        /// e.g. `main` or `main([Ljava/lang/String;)V`
+        method: Option<String>,
+    },
+    /// Prints the bytecode of a class' methods with the inferred types of
+    /// the locals and the operand stack before each instruction
+    Disassemble {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// Class whose methods are printed
+        class: String,
+        /// Only prints the methods with this name, or name and descriptor
+        method: Option<String>,
+    },
+    /// Prints approximate Java source of a class, marking the code it
+    /// cannot reconstruct (experimental)
+    Decompile {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// Class to decompile
+        class: String,
+        /// Only decompiles the methods with this name, or name and
+        /// descriptor
+        method: Option<String>,
+    },
+    /// Reports size and complexity metrics of the methods of the classpath,
+    /// with totals per class and per classpath entry
+    Stats {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        #[clap(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+        #[clap(flatten)]
+        filter: FilterArgs,
+        /// Directory caching the metrics of the classes between runs, keyed
+        /// by their content
+        #[clap(long, value_name = "DIR")]
+        class_cache: Option<PathBuf>,
+        /// Keeps watching the classpath, measuring again the classes
+        /// changed
+        #[clap(long)]
+        watch: bool,
+        /// Reports where the bytes of the class files go instead: the
+        /// constants by kind, the methods and their code, and the
+        /// attributes by name
+        #[clap(long)]
+        pool: bool,
+        /// Only measures these classes, instead of every class of the
+        /// classpath
+        classes: Vec<String>,
+    },
+    /// Verifies the code of the methods of the classpath, reporting the
+    /// classes whose types of locals and operands do not add up
+    Verify {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        #[clap(flatten)]
+        filter: FilterArgs,
+        /// Directory caching the outcomes of the classes between runs, keyed
+        /// by their content
+        #[clap(long, value_name = "DIR")]
+        class_cache: Option<PathBuf>,
+        /// Keeps watching the classpath, verifying again the classes
+        /// changed
+        #[clap(long)]
+        watch: bool,
+        /// Only verifies these classes, instead of every class of the
+        /// classpath
+        classes: Vec<String>,
+    },
+    /// Writes the calls between the methods of the classpath, virtual calls
+    /// going to every implementation of the subtypes of their class
+    Callgraph {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        #[clap(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+        /// Reads obfuscated classes the JVM would reject or only fail once
+        /// used, warning of each deviation from the class file format
+        #[clap(long)]
+        hostile: bool,
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+    /// Reports the classes and methods of the classpath unreachable from the
+    /// entry points
+    Deadcode {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// Entry point, a class whose `main` is called, or a method like
+        /// `com.example.Main#run`; every `main` method by default
+        #[clap(short, long = "entry")]
+        entries: Vec<String>,
+        /// File of classes, methods and annotations to keep, one per line,
+        /// besides the `reflect-config.json` files of the classpath
+        #[clap(short, long = "keep")]
+        keep_files: Vec<PathBuf>,
+        /// Reads obfuscated classes the JVM would reject or only fail once
+        /// used, warning of each deviation from the class file format
+        #[clap(long)]
+        hostile: bool,
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+    /// Reports the differences between two versions of a class file, or of
+    /// the classes of two jars or directories, exiting with 1 if any
+    Diff {
+        /// The old class file, jar or directory
+        old: PathBuf,
+        /// The new class file, jar or directory
+        new: PathBuf,
+        /// Lists the differing instructions of changed methods
+        #[clap(long)]
+        code: bool,
+    },
+    /// Classifies the API changes between two versions of a library as
+    /// compatible or breaking, exiting with 1 if any breaks its clients
+    Compat {
+        /// The old class file, jar or directory
+        old: PathBuf,
+        /// The new class file, jar or directory
+        new: PathBuf,
+    },
+    /// Prints the requires, exports, opens and provides of the module of a
+    /// modular jar, jmod or directory, like `java --describe-module`
+    DescribeModule {
+        /// The modular jar, jmod or directory holding a `module-info` class
+        path: PathBuf,
+    },
+    /// Applies peephole optimizations to the classes of a class file, jar or
+    /// directory, writing the optimized class files to a directory
+    Optimize {
+        /// The class file, jar or directory to optimize
+        input: PathBuf,
+        /// Directory the optimized class files are written to, by internal
+        /// name
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Writes a smaller jar, without the classes and methods unreachable
+    /// from the entry points and without the attributes the code runs
+    /// without
+    Shrink {
+        /// The jar to shrink
+        input: PathBuf,
+        /// The shrunk jar to write
+        #[clap(short, long)]
+        output: PathBuf,
+        /// Entry point, a class whose `main` is called, or a method like
+        /// `com.example.Main#run`; the `Main-Class` of the manifest by
+        /// default
+        #[clap(long = "keep")]
+        entries: Vec<String>,
+        /// File of classes, methods and annotations to keep, one per line,
+        /// besides the service providers and the `reflect-config.json` files
+        /// of the jar
+        #[clap(long = "keep-rules")]
+        keep_files: Vec<PathBuf>,
+        /// The attributes to strip, comma separated
+        #[clap(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [
+                StrippedAttributes::LineNumbers,
+                StrippedAttributes::LocalVariables,
+                StrippedAttributes::SourceFile,
+                StrippedAttributes::SourceDebugExtension,
+                StrippedAttributes::InvisibleAnnotations,
+            ]
+        )]
+        strip: Vec<StrippedAttributes>,
+    },
+    /// Removes debug information and annotations from the classes of a class
+    /// file, jar or directory, writing the stripped class files to a
+    /// directory
+    Strip {
+        /// The class file, jar or directory to strip
+        input: PathBuf,
+        /// Directory the stripped class files are written to, by internal
+        /// name
+        #[clap(short, long)]
+        output: PathBuf,
+        /// The attributes to strip, comma separated
+        #[clap(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [
+                StrippedAttributes::LineNumbers,
+                StrippedAttributes::LocalVariables,
+                StrippedAttributes::SourceFile,
+                StrippedAttributes::SourceDebugExtension,
+            ]
+        )]
+        strip: Vec<StrippedAttributes>,
+    },
+    /// Runs the JUnit 4 or Jupiter tests of the classes, reporting the
+    /// passed, failed and skipped ones, and exiting with 1 if any failed
+    Test {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// JDK providing the bootstrap classes, defaults to JAVA_HOME
+        #[clap(long)]
+        java_home: Option<PathBuf>,
+        /// Test classes to run, by binary or internal name
+        #[clap(required = true)]
+        classes: Vec<String>,
+    },
+    /// Starts an interactive shell on a VM, loading classes, calling their
+    /// static methods and inspecting the results and the heap
+    Repl {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// JDK providing the bootstrap classes, defaults to JAVA_HOME
+        #[clap(long)]
+        java_home: Option<PathBuf>,
+    },
+    /// Browses the packages and classes of the classpath in a terminal UI,
+    /// with their constant pool, fields, methods and disassembly
+    #[cfg(feature = "tui")]
+    Browse {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+    },
+    /// Searches the classpath for classes, subtypes or member references
+    /// matching a pattern, exiting with 1 if none
+    Find {
+        /// Colon separated path of classes
+        #[clap(short, long, env = "CLASSPATH", default_value = ".")]
+        classpath: String,
+        /// Reads the pattern as a regular expression instead of a glob
+        #[clap(long)]
+        regex: bool,
+        #[clap(flatten)]
+        filter: FilterArgs,
+        /// Keeps watching the classpath, searching again when classes
+        /// change
+        #[clap(long)]
+        watch: bool,
+        #[clap(value_enum)]
+        kind: FindKind,
+        /// Glob like `java.util.*List`, `*` and `?` being the wildcards
+        pattern: String,
+    },
+}
+
+fn init_logging(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+/// Opens the classpath, its scans reading the classes the filter selects.
+fn open_class_path(classpath: &str, filter: &FilterArgs) -> Result<ClassPath, String> {
+    let mut class_path = ClassPath::parse(classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    class_path.set_filter(ClassFilter::new(&filter.include, &filter.exclude)?);
+    Ok(class_path)
+}
+
+fn open_registry(classpath: &str, filter: &FilterArgs) -> Result<ClassRegistry, String> {
+    let class_path = open_class_path(classpath, filter)?;
+    ClassRegistry::new(class_path)
+        .map_err(|error| format!("Cannot index classpath '{}': {}", classpath, error))
+}
+
+/// Parses a size in bytes, or in kibi, mebi or gibibytes with a `k`, `m` or
+/// `g` suffix.
+fn parse_size(size: &str) -> Result<usize, String> {
+    let lowercase = size.to_ascii_lowercase();
+    let (digits, unit) = match lowercase.char_indices().last() {
+        Some((index, 'k')) => (&lowercase[..index], 1 << 10),
+        Some((index, 'm')) => (&lowercase[..index], 1 << 20),
+        Some((index, 'g')) => (&lowercase[..index], 1 << 30),
+        _ => (lowercase.as_str(), 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|count| count.checked_mul(unit))
+        .ok_or_else(|| format!("Invalid size '{}'", size))
+}
+
+/// Runs the VM on a host thread with a stack of its own, large enough for
+/// the host frames of the Java stacks when native code calls back into
+/// Java.
+fn run_on_vm_thread(args: RunArgs) -> Result<ExitCode, String> {
+    let xss = args.xss;
+    on_vm_thread(xss, move || run(args))
+}
+
+/// Runs the function on a host thread with a stack large enough for Java
+/// stacks of `xss` bytes.
+fn on_vm_thread<T: Send + 'static>(
+    xss: Option<usize>,
+    run: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    const MIN_HOST_STACK_SIZE: usize = 8 << 20;
+
+    let stack_size = xss.map_or(0, |bytes| bytes.saturating_mul(4));
+    thread::Builder::new()
+        .name("main".to_string())
+        .stack_size(stack_size.max(MIN_HOST_STACK_SIZE))
+        .spawn(run)
+        .map_err(|error| format!("Cannot start the VM thread: {}", error))?
+        .join()
+        .map_err(|_| "The VM thread panicked".to_string())?
+}
+
+#[cfg(feature = "tui")]
+fn browse(classpath: &str) -> Result<(), String> {
+    ClassBrowser::new(open_registry(classpath, &FilterArgs::default())?)
+        .run()
+        .map_err(|error| error.to_string())
+}
+
+/// Reads commands from standard input until `quit` or its end, then shuts
+/// the VM down.
+fn test(
+    classpath: &str,
+    java_home: Option<PathBuf>,
+    classes: &[String],
+) -> Result<ExitCode, String> {
+    let class_path = ClassPath::parse(classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    let mut builder = Vm::builder().class_path(class_path);
+    if let Some(jdk) = java_home.map(JdkImage::new).or_else(JdkImage::from_env) {
+        builder = builder.java_home(jdk.home());
+    }
+    let mut vm = builder.build().map_err(|error| error.to_string())?;
+
+    let (mut passed, mut failed, mut errored, mut skipped) = (0, 0, 0, 0);
+    for class in classes {
+        let name = class.replace('.', "/");
+        let results = run_tests(&mut vm, &name).map_err(|error| match error {
+            VmError::Exception(exception) if exception.linkage_error.is_some() => {
+                format!("Cannot load test class {}: {}", class, exception)
+            }
+            error => error.to_string(),
+        })?;
+        vm.flush().map_err(|error| error.to_string())?;
+
+        println!("{}", name.replace('/', "."));
+        for result in &results {
+            let (status, failure) = match &result.outcome {
+                TestOutcome::Passed => {
+                    passed += 1;
+                    ("PASS", None)
+                }
+                TestOutcome::Failed(failure) => {
+                    failed += 1;
+                    ("FAIL", Some(failure))
+                }
+                TestOutcome::Errored(failure) => {
+                    errored += 1;
+                    ("ERROR", Some(failure))
+                }
+                TestOutcome::Skipped => {
+                    skipped += 1;
+                    ("SKIP", None)
+                }
+            };
+            println!(
+                "  {:<5} {} ({:.1} ms)",
+                status,
+                result.name,
+                result.duration.as_secs_f64() * 1000.0
+            );
+            if let Some(failure) = failure {
+                println!("        {}", failure.description);
+                // The stack trace starts with the description of the
+                // exception, unless the test did not throw the one expected
+                let lines = failure.stack_trace.iter().flat_map(|trace| trace.lines());
+                for line in lines.skip_while(|line| *line == failure.description) {
+                    println!("        {}", line.replace('\t', "    "));
+                }
+            }
+        }
+    }
+    println!(
+        "Tests run: {}, passed: {}, failed: {}, errors: {}, skipped: {}",
+        passed + failed + errored + skipped,
+        passed,
+        failed,
+        errored,
+        skipped
+    );
+
+    vm.shutdown().map_err(|error| error.to_string())?;
+    Ok(if failed + errored == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn repl(classpath: &str, java_home: Option<PathBuf>) -> Result<ExitCode, String> {
+    let class_path = ClassPath::parse(classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    let mut builder = Vm::builder().class_path(class_path);
+    if let Some(jdk) = java_home.map(JdkImage::new).or_else(JdkImage::from_env) {
+        builder = builder.java_home(jdk.home());
+    }
+    let vm = builder.build().map_err(|error| error.to_string())?;
+
+    let mut repl = Repl::new(vm);
+    println!("bvm shell, type help for the commands");
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().map_err(|error| error.to_string())?;
+        line.clear();
+        if io::stdin()
+            .read_line(&mut line)
+            .map_err(|error| error.to_string())?
+            == 0
+        {
+            break;
+        }
+        match repl.execute(&line) {
+            Ok(Some(output)) => print!("{}", output),
+            Ok(None) => break,
+            Err(error) => eprintln!("Error: {}", error),
+        }
+        repl.vm().flush().map_err(|error| error.to_string())?;
+    }
+    match repl.into_vm().shutdown() {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(VmError::Exit(status)) => Ok(ExitCode::from(status as u8)),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn run(args: RunArgs) -> Result<ExitCode, String> {
+    if args.list_classes {
+        return list_classes(
+            &args.classpath,
+            args.list_format,
+            &args.list_filter,
+            args.class_cache.as_deref(),
+        )
+        .map(|_| ExitCode::SUCCESS);
+    }
+    if !args.list_filter.is_empty() {
+        return Err("--include and --exclude only apply to --list-classes".to_string());
+    }
+    let main_class = args
+        .main_class
+        .ok_or_else(|| "No main class specified".to_string())?;
+    let main_class = main_class.replace('.', "/");
+
+    let classpath = args.classpath;
+    let class_path = ClassPath::parse(&classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    let mut builder = Vm::builder().class_path(class_path);
+    if let Some(path) = &args.module_path {
+        let module_path = ModulePath::parse(path)
+            .map_err(|error| format!("Cannot read module path '{}': {}", path, error))?;
+        // The module holding the main class is the main module, a root like
+        // the modules added.
+        let package = main_class
+            .rsplit_once('/')
+            .map_or("", |(package, _)| package);
+        let main_module = module_path
+            .modules()
+            .iter()
+            .find(|module| module.descriptor.packages.contains(package));
+        let mut roots: Vec<&str> = args.add_modules.iter().map(String::as_str).collect();
+        roots.extend(main_module.map(|module| module.descriptor.name.as_str()));
+        let graph =
+            ModuleGraph::resolve(&module_path, &roots).map_err(|error| error.to_string())?;
+        builder = builder.modules(graph);
+    }
+    if let Some(jdk) = args
+        .java_home
+        .map(JdkImage::new)
+        .or_else(JdkImage::from_env)
+    {
+        builder = builder.java_home(jdk.home());
+    }
+    if let Some(path) = args.class_archive {
+        builder = builder.class_archive(path);
+    }
+    if args.virtual_time {
+        builder = builder.clock(VirtualClock::default());
+    }
+    if let Some(path) = &args.record {
+        let file = File::create(path)
+            .map_err(|error| format!("Cannot create '{}': {}", path.display(), error))?;
+        builder = builder.interactions(InteractionLog::record(BufWriter::new(file)));
+    }
+    if let Some(path) = &args.replay {
+        let log = File::open(path)
+            .and_then(|file| InteractionLog::replay(BufReader::new(file)))
+            .map_err(|error| format!("Cannot read '{}': {}", path.display(), error))?;
+        builder = builder.interactions(log);
+    }
+    if let Some(bytes) = args.xss {
+        builder = builder.stack_size(bytes);
+    }
+    builder = builder.assertions(args.assertions);
+    if args.optimize {
+        builder = builder.optimize_bytecode(true);
+    }
+    #[cfg(unix)]
+    for agent in &args.agents {
+        let (library, options) = agent.split_once('=').unwrap_or((agent, ""));
+        let agent = NativeAgent::load(Path::new(library), options)
+            .map_err(|error| format!("Cannot load agent '{}': {}", library, error))?;
+        builder = builder.transformer(agent);
+    }
+    if args.verbose_class {
+        builder = builder.log_class_loading(ClassLoadingLog::new());
+    }
+    if args.verbose_gc {
+        builder = builder.log_gc(GcLog::new());
+    }
+    if args.profile {
+        let format = match args.profile_format {
+            ProfileOutputFormat::Report => ProfileFormat::Report,
+            ProfileOutputFormat::Collapsed => ProfileFormat::Collapsed,
+        };
+        let mut profiler = MethodProfiler::new().format(format);
+        if let Some(path) = &args.profile_output {
+            let file = File::create(path)
+                .map_err(|error| format!("Cannot create '{}': {}", path.display(), error))?;
+            profiler = profiler.output(file);
+        }
+        builder = builder.profile(profiler);
+    }
+    if let Some(path) = &args.sample {
+        let file = File::create(path)
+            .map_err(|error| format!("Cannot create '{}': {}", path.display(), error))?;
+        let sampler = SamplingProfiler::new()
+            .interval(Duration::from_millis(args.sample_interval))
+            .opcodes(args.sample_opcodes)
+            .output(file);
+        builder = builder.sample(sampler);
+    }
+    if args.coverage {
+        let format = match args.coverage_format {
+            CoverageOutputFormat::Lcov => CoverageFormat::Lcov,
+            CoverageOutputFormat::JacocoXml => CoverageFormat::JacocoXml,
+        };
+        let mut coverage = CodeCoverage::new().format(format);
+        if let Some(path) = &args.coverage_output {
+            let file = File::create(path)
+                .map_err(|error| format!("Cannot create '{}': {}", path.display(), error))?;
+            coverage = coverage.output(file);
+        }
+        builder = builder.coverage(coverage);
+    }
+    if let Some(patterns) = &args.trace_bytecode {
+        let trace = patterns
+            .split(',')
+            .filter(|pattern| !pattern.is_empty())
+            .fold(BytecodeTrace::new(), BytecodeTrace::filter);
+        builder = builder.trace_bytecode(trace);
+    }
+    #[cfg(feature = "jit")]
+    {
+        let mode = if args.xint {
+            CompilationMode::Interpreted
+        } else if args.xcomp {
+            CompilationMode::Eager
+        } else {
+            CompilationMode::Tiered
+        };
+        builder = builder.jit(JitCompiler::new().mode(mode));
+    }
+    // Like HotSpot, dump the threads on SIGQUIT, sent by Ctrl+\ in a terminal
+    #[cfg(unix)]
+    {
+        let flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGQUIT, flag.clone())
+            .map_err(|error| format!("Cannot handle SIGQUIT: {}", error))?;
+        builder = builder.dump_threads_on(flag);
+    }
+    let mut vm = builder.build().map_err(|error| error.to_string())?;
+
+    vm.find_class(&main_class)
+        .map_err(|_| format!("Could not find or load main class {}", main_class))?;
+    if args.dry_link {
+        return dry_link(&mut vm, &main_class);
+    }
+    let arguments: Vec<&str> = args.arguments.iter().map(String::as_str).collect();
+    let arguments = vm
+        .new_string_array(&arguments)
+        .map_err(|error| error.to_string())?;
+    let result = vm.invoke_static(
+        &main_class,
+        "main",
+        "([Ljava/lang/String;)V",
+        &[JValue::Object(arguments)],
+    );
+
+    // Like the `java` launcher, an exception escaping `main` fails the run
+    // even when a handler took care of it, unlike those of other threads
+    let exit_code = match result {
+        Ok(_) => Ok(ExitCode::SUCCESS),
+        Err(VmError::Exception(exception)) => vm
+            .dispatch_uncaught_exception(exception.object)
+            .map(|_| ExitCode::FAILURE),
+        Err(VmError::NoSuchMethod(_)) => {
+            return Err(format!(
+                "Main method not found in class {}, please define the main method as:\n   public static void main(String[] args)",
+                main_class.replace('/', ".")
+            ))
+        }
+        Err(error) => Err(error),
+    };
+    let exit_code = match exit_code {
+        Ok(exit_code) => exit_code,
+        // The shutdown hooks already ran as part of the exit
+        Err(VmError::Exit(status)) => {
+            vm.flush().map_err(|error| error.to_string())?;
+            return Ok(ExitCode::from(status as u8));
+        }
+        Err(error) => return Err(error.to_string()),
+    };
+
+    match vm.shutdown() {
+        // A thread run at shutdown exited, running the hooks
+        Err(VmError::Exit(status)) => {
+            vm.flush().map_err(|error| error.to_string())?;
+            Ok(ExitCode::from(status as u8))
+        }
+        result => result.map(|_| exit_code).map_err(|error| error.to_string()),
+    }
+}
+
+fn dry_link(vm: &mut Vm, main_class: &str) -> Result<ExitCode, String> {
+    let report = vm.dry_link(main_class).map_err(|error| error.to_string())?;
+    for error in &report.errors {
+        println!("{}", error);
+    }
+    println!(
+        "Linked {} classes, {} unresolved references",
+        report.classes.len(),
+        report.errors.len()
+    );
+    Ok(if report.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn doctor(classpath: &str, filter: &FilterArgs) -> Result<(), String> {
+    let registry = open_registry(classpath, filter)?;
+
+    let duplicates = registry.duplicate_classes();
+    let split_packages = registry.split_packages();
+    if duplicates.is_empty() && split_packages.is_empty() {
+        println!("No duplicate classes or split packages found");
+        return Ok(());
+    }
+
+    if !duplicates.is_empty() {
+        println!("Duplicate classes ({}):", duplicates.len());
+        for duplicate in &duplicates {
+            println!("  {}", duplicate.name);
+            for (index, provider) in duplicate.providers.iter().enumerate() {
+                let role = if index == 0 { "used" } else { "shadowed" };
+                println!("    {:>8}: {}", role, provider.display());
+            }
+        }
+    }
+
+    if !split_packages.is_empty() {
+        println!("Split packages ({}):", split_packages.len());
+        for split_package in &split_packages {
+            println!("  {}", split_package.package);
+            for provider in &split_package.providers {
+                println!("    {}", provider.display());
+            }
+        }
+    }
+
+    Ok(())
 }
 
-fn main() {
-    // let args = Args::parse();
+/// Loads the class, given by its binary or internal name, from the classpath.
+fn load_class(classpath: &str, class_name: &str) -> Result<Class, String> {
+    let class_path = ClassPath::parse(classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    let bytes = match class_path.find_class(class_name) {
+        Ok(Some((_, bytes))) => bytes,
+        Ok(None) => return Err(format!("Class {} not found", class_name)),
+        Err(error) => return Err(format!("Cannot read class {}: {}", class_name, error)),
+    };
+    Class::parse_bytes(&bytes)
+        .map_err(|error| format!("Cannot parse class {}: {}", class_name, error))
+}
 
-    // let files = [
-    //     "/home/baprof/Downloads/rt11jar/java.desktop/com/sun/beans/editors/ByteEditor.class",
-    //     "/home/baprof/Downloads/rt11jar/java.desktop/com/sun/beans/editors/ColorEditor.class",
-    // ];
+/// The methods of the class with code, with their name and descriptor,
+/// only those with the name, or name and descriptor, if given.
+fn methods_with_code<'a>(
+    class: &'a Class,
+    class_name: &str,
+    method_name: Option<&str>,
+) -> Result<Vec<(&'a MethodInfo, String, &'a CodeAttribute)>, String> {
+    let pool = &class.constant_pool;
+    let mut methods = Vec::new();
+    for method in &class.methods {
+        let name = pool
+            .get_utf8(method.name_index)
+            .map_err(|error| error.to_string())?;
+        let descriptor = pool
+            .get_utf8(method.descriptor_index)
+            .map_err(|error| error.to_string())?;
+        let signature = format!("{}{}", name, descriptor);
+        if method_name.is_some_and(|method_name| method_name != name && method_name != signature) {
+            continue;
+        }
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            });
+        if let Some(code) = code {
+            methods.push((method, signature, code));
+        }
+    }
+
+    if methods.is_empty() {
+        return Err(match method_name {
+            Some(method_name) => format!("No method {} with code in {}", method_name, class_name),
+            None => format!("No method with code in {}", class_name),
+        });
+    }
+    Ok(methods)
+}
 
-    // for file in files {
-    //     let class_file = File::open(file).unwrap();
-    //     let mut class_reader = io::BufReader::new(class_file);
-    //     println!("Reading class {}", file);
-    //     match Class::read(&mut class_reader) {
-    //         Ok(class) => (),
-    //         Err(error) => println!("\t -> {:?}", error),
-    //     }
-    // }
+fn cfg(classpath: &str, class_name: &str, method_name: Option<&str>) -> Result<(), String> {
+    let class_name = class_name.replace('.', "/");
+    let class = load_class(classpath, &class_name)?;
+    let pool = &class.constant_pool;
+
+    let mut dot = String::new();
+    writeln!(dot, "digraph \"{}\" {{", class_name).unwrap();
+    writeln!(dot, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+    let methods = methods_with_code(&class, &class_name, method_name)?;
+    for (drawn, (_, signature, code)) in methods.into_iter().enumerate() {
+        let decoded = instruction::decode(&code.code);
+        let graph = ControlFlowGraph::build(&decoded, &code.exception_tables);
+        writeln!(dot, "  subgraph cluster_{} {{", drawn).unwrap();
+        writeln!(dot, "    label=\"{}\";", signature.replace('"', "\\\"")).unwrap();
+        graph
+            .write_dot(
+                &mut dot,
+                &format!("m{}_", drawn),
+                |index| {
+                    format!(
+                        "{}: {}",
+                        decoded.pcs[index],
+                        instruction::disassemble(&code.code, &decoded, index, pool)
+                    )
+                },
+                |catch_type| match catch_type {
+                    0 => "any".to_string(),
+                    index => pool.describe(index),
+                },
+            )
+            .unwrap();
+        writeln!(dot, "  }}").unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+
+    print!("{}", dot);
+    Ok(())
+}
+
+fn disassemble(classpath: &str, class_name: &str, method_name: Option<&str>) -> Result<(), String> {
+    let class_name = class_name.replace('.', "/");
+    let class = load_class(classpath, &class_name)?;
+    let pool = &class.constant_pool;
+
+    let mut listing = String::new();
+    for (method, signature, code) in methods_with_code(&class, &class_name, method_name)? {
+        writeln!(listing, "{}", signature).unwrap();
+        writeln!(
+            listing,
+            "  stack={}, locals={}",
+            code.max_stack, code.max_locals
+        )
+        .unwrap();
+        let decoded = instruction::decode(&code.code);
+        let frames = match infer_frames(&class, method, code) {
+            Ok(frames) => Some(frames),
+            Err(error) => {
+                writeln!(listing, "  // types not inferred: {}", error).unwrap();
+                None
+            }
+        };
+        for (index, pc) in decoded.pcs.iter().enumerate() {
+            let instruction = instruction::disassemble(&code.code, &decoded, index, pool);
+            let frame = frames.as_ref().map(|frames| match &frames[index] {
+                Some(frame) => frame.to_string(),
+                None => "unreachable".to_string(),
+            });
+            match frame {
+                Some(frame) => writeln!(listing, "  {:>4}: {:<32} // {}", pc, instruction, frame),
+                None => writeln!(listing, "  {:>4}: {}", pc, instruction),
+            }
+            .unwrap();
+        }
+        if !code.exception_tables.is_empty() {
+            writeln!(listing, "  Exception table:").unwrap();
+            for entry in &code.exception_tables {
+                let catch_type = match entry.catch_type {
+                    0 => "any".to_string(),
+                    index => pool.describe(index),
+                };
+                writeln!(
+                    listing,
+                    "    {} {} {} {}",
+                    entry.start_pc, entry.end_pc, entry.handler_pc, catch_type
+                )
+                .unwrap();
+            }
+            writeln!(listing, "  Try regions:").unwrap();
+            let pc = |index: usize| {
+                decoded
+                    .pcs
+                    .get(index)
+                    .copied()
+                    .unwrap_or(code.code.len() as u32)
+            };
+            for region in try_regions(&decoded, &code.exception_tables) {
+                region
+                    .write(&mut listing, 4, &pc, &|catch_type| {
+                        pool.describe(catch_type)
+                    })
+                    .unwrap();
+            }
+        }
+        writeln!(listing).unwrap();
+    }
+
+    print!("{}", listing);
+    Ok(())
+}
+
+fn decompile(classpath: &str, class_name: &str, method_name: Option<&str>) -> Result<(), String> {
+    let class = load_class(classpath, &class_name.replace('.', "/"))?;
+    let source = decompile_class(&class, method_name).map_err(|error| error.to_string())?;
+    print!("{}", source);
+    Ok(())
+}
+
+fn stats(
+    classpath: &str,
+    format: ReportFormat,
+    filter: &FilterArgs,
+    class_cache: Option<&Path>,
+    watch: bool,
+    class_names: &[String],
+    pool: bool,
+) -> Result<(), String> {
+    if pool {
+        let measure = |bytes: &[u8]| ClassSizes::new(&Class::parse_bytes(bytes)?);
+        let print = |class_path: &ClassPath, index: &[BTreeMap<String, ClassSizes>], all| {
+            print_sizes(class_path, index, format, all)
+        };
+        measure_classes(
+            classpath,
+            filter,
+            class_cache,
+            watch,
+            class_names,
+            measure,
+            print,
+        )
+    } else {
+        let measure = |bytes: &[u8]| ClassMetrics::new(&Class::parse_bytes(bytes)?);
+        let print = |class_path: &ClassPath, index: &[BTreeMap<String, ClassMetrics>], all| {
+            print_stats(class_path, index, format, all)
+        };
+        measure_classes(
+            classpath,
+            filter,
+            class_cache,
+            watch,
+            class_names,
+            measure,
+            print,
+        )
+    }
+}
+
+/// Verifies the classes of the classpath, or the ones named, failing if any
+/// does not verify at the last report.
+fn verify(
+    classpath: &str,
+    filter: &FilterArgs,
+    class_cache: Option<&Path>,
+    watch: bool,
+    class_names: &[String],
+) -> Result<ExitCode, String> {
+    let failed = Cell::new(0);
+    let print = |class_path: &ClassPath, index: &[BTreeMap<String, Verification>], _| {
+        let mut verified = 0;
+        let mut failures = 0;
+        for (entry, classes) in class_path.entries().iter().zip(index) {
+            for verification in classes.values() {
+                verified += 1;
+                if let Some(error) = &verification.error {
+                    println!("{}: {}", entry.path().display(), error);
+                    failures += 1;
+                }
+            }
+        }
+        println!("{} classes verified, {} failed", verified, failures);
+        failed.set(failures);
+    };
+    measure_classes(
+        classpath,
+        filter,
+        class_cache,
+        watch,
+        class_names,
+        |bytes| Verification::new(&Class::parse_bytes(bytes)?),
+        print,
+    )?;
+    Ok(match failed.get() {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::from(1),
+    })
+}
+
+/// Measures the classes of the classpath, or the ones named, and prints
+/// the report, again after every change if watching.
+fn measure_classes<T: Cached + Clone>(
+    classpath: &str,
+    filter: &FilterArgs,
+    class_cache: Option<&Path>,
+    watch: bool,
+    class_names: &[String],
+    compute: impl Fn(&[u8]) -> Result<T, ClassLoadingError>,
+    print: impl Fn(&ClassPath, &[BTreeMap<String, T>], bool),
+) -> Result<(), String> {
+    let mut class_path = open_class_path(classpath, filter)?;
+    let cache = class_cache.map(open_class_cache).transpose()?;
+    let class_names: Vec<String> = class_names
+        .iter()
+        .map(|name| name.replace('.', "/"))
+        .collect();
+    for class_name in &class_names {
+        if let Ok(None) = class_path.find_class(class_name) {
+            return Err(format!("Class {} not found", class_name));
+        }
+    }
+
+    let measure = |entry: &ClassPathEntry, name: &str| -> Result<Option<T>, String> {
+        let bytes = match entry.read_class(name) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Ok(None),
+            Err(error) => return Err(format!("Cannot read class {}: {}", name, error)),
+        };
+        let metrics = match &cache {
+            Some(cache) => cache.get_or_compute(&bytes, || compute(&bytes)),
+            None => compute(&bytes),
+        };
+        match metrics {
+            Ok(metrics) => Ok(Some(metrics)),
+            Err(error) => {
+                let path = entry.path().display();
+                eprintln!("Warning: skipping class {} of {}: {}", name, path, error);
+                Ok(None)
+            }
+        }
+    };
+    let selected = |name: &str| class_names.is_empty() || class_names.iter().any(|c| c == name);
+
+    let mut names = Vec::new();
+    for (position, entry) in class_path.entries().iter().enumerate() {
+        let entry_names = class_path.class_names(position).map_err(|error| {
+            format!(
+                "Cannot list classes of {}: {}",
+                entry.path().display(),
+                error
+            )
+        })?;
+        names.push(
+            entry_names
+                .into_iter()
+                .filter(|name| selected(name))
+                .collect::<Vec<_>>(),
+        );
+    }
+    let mut terminal = TerminalProgress::new();
+    let total = names.iter().map(Vec::len).sum();
+    let mut progress = ProgressTracker::new("Measuring classes", total, listener(&mut terminal));
+
+    // The metrics of the classes of each entry, by name
+    let mut index = Vec::new();
+    for (entry, names) in class_path.entries().iter().zip(names) {
+        let mut classes = BTreeMap::new();
+        for name in names {
+            let metrics = measure(entry, &name)?;
+            progress.advance(metrics.is_none());
+            if let Some(metrics) = metrics {
+                classes.insert(name, metrics);
+            }
+        }
+        index.push(classes);
+    }
+    progress.finish();
+    print(&class_path, &index, class_names.is_empty());
+    if !watch {
+        return Ok(());
+    }
+
+    let mut watcher = watch_class_path(&class_path)?;
+    loop {
+        let changes = match watcher.wait(&mut class_path, WATCH_INTERVAL) {
+            Ok(changes) => changes,
+            Err(error) => {
+                eprintln!("Warning: cannot watch classpath '{}': {}", classpath, error);
+                continue;
+            }
+        };
+        let changes: Vec<_> = changes
+            .into_iter()
+            .filter(|change| selected(&change.class))
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        for change in &changes {
+            let classes = &mut index[change.entry];
+            classes.remove(&change.class);
+            if change.removed {
+                continue;
+            }
+            let entry = &class_path.entries()[change.entry];
+            match measure(entry, &change.class) {
+                Ok(Some(metrics)) => {
+                    classes.insert(change.class.clone(), metrics);
+                }
+                Ok(None) => {}
+                Err(error) => eprintln!("Warning: {}", error),
+            }
+        }
+        eprintln!("{} classes changed", changes.len());
+        print(&class_path, &index, class_names.is_empty());
+    }
+}
+
+/// The path and classes of the entries of the classpath to report, those
+/// without any class too when reporting every class.
+fn report_entries<'a, T: Clone>(
+    class_path: &'a ClassPath,
+    index: &'a [BTreeMap<String, T>],
+    all_classes: bool,
+) -> impl Iterator<Item = (String, Vec<T>)> + 'a {
+    class_path
+        .entries()
+        .iter()
+        .zip(index)
+        .filter(move |(_, classes)| !classes.is_empty() || all_classes)
+        .map(|(entry, classes)| {
+            (
+                entry.path().display().to_string(),
+                classes.values().cloned().collect(),
+            )
+        })
+}
+
+fn print_stats(
+    class_path: &ClassPath,
+    index: &[BTreeMap<String, ClassMetrics>],
+    format: ReportFormat,
+    all_classes: bool,
+) {
+    let entries: Vec<EntryMetrics> = report_entries(class_path, index, all_classes)
+        .map(|(path, classes)| EntryMetrics { path, classes })
+        .collect();
+
+    let mut output = String::new();
+    match format {
+        ReportFormat::Table => metrics::write_table(&mut output, &entries),
+        ReportFormat::Json => metrics::write_json(&mut output, &entries),
+    }
+    .unwrap();
+    print!("{}", output);
+}
+
+fn print_sizes(
+    class_path: &ClassPath,
+    index: &[BTreeMap<String, ClassSizes>],
+    format: ReportFormat,
+    all_classes: bool,
+) {
+    let entries: Vec<EntrySizes> = report_entries(class_path, index, all_classes)
+        .map(|(path, classes)| EntrySizes { path, classes })
+        .collect();
+
+    let mut output = String::new();
+    match format {
+        ReportFormat::Table => metrics::write_sizes_table(&mut output, &entries),
+        ReportFormat::Json => metrics::write_sizes_json(&mut output, &entries),
+    }
+    .unwrap();
+    print!("{}", output);
+}
+
+/// Draws the progress of long tasks as a bar on the terminal, when stderr
+/// is one.
+struct TerminalProgress {
+    bar: Option<ProgressBar>,
+    errors: usize,
+}
+
+impl TerminalProgress {
+    fn new() -> Option<TerminalProgress> {
+        io::stderr().is_terminal().then_some(TerminalProgress {
+            bar: None,
+            errors: 0,
+        })
+    }
+}
+
+impl ProgressListener for TerminalProgress {
+    fn update(&mut self, progress: &Progress) {
+        let bar = self.bar.get_or_insert_with(|| {
+            let style = ProgressStyle::with_template(
+                "{msg} [{wide_bar}] {pos}/{len} ({percent}%), {eta} left",
+            )
+            .unwrap()
+            .progress_chars("=> ");
+            let bar = ProgressBar::new(progress.total as u64).with_style(style);
+            bar.set_message(progress.task);
+            bar
+        });
+        if progress.errors != self.errors {
+            self.errors = progress.errors;
+            bar.set_message(format!("{} ({} errors)", progress.task, progress.errors));
+        }
+        bar.set_position(progress.processed as u64);
+    }
+
+    fn finish(&mut self, _: &Progress) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+fn listener(terminal: &mut Option<TerminalProgress>) -> Option<&mut dyn ProgressListener> {
+    terminal
+        .as_mut()
+        .map(|terminal| terminal as &mut dyn ProgressListener)
+}
+
+/// Watches the classpath of a tool run with `--watch`.
+fn watch_class_path(class_path: &ClassPath) -> Result<ClassPathWatcher, String> {
+    let watcher = ClassPathWatcher::new(class_path)
+        .map_err(|error| format!("Cannot watch classpath: {}", error))?;
+    eprintln!("Watching the classpath for changes, press Ctrl-C to stop");
+    Ok(watcher)
+}
+
+/// Parses every class of the classpath, the first provider of each name
+/// winning, skipping with a warning those failing to parse.
+/// Parses the classes of the classpath, strictly unless they are hostile,
+/// see [Class::parse_hostile].
+fn load_all_classes(
+    classpath: &str,
+    hostile: bool,
+    filter: &FilterArgs,
+) -> Result<Vec<Class>, String> {
+    let registry = open_registry(classpath, filter)?;
+    let mut terminal = TerminalProgress::new();
+    let total = registry.class_names().count();
+    let mut progress = ProgressTracker::new("Parsing classes", total, listener(&mut terminal));
+    let mut classes = Vec::new();
+    for name in registry.class_names() {
+        let bytes = match registry.read_class(name) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                progress.advance(false);
+                continue;
+            }
+            Err(error) => return Err(format!("Cannot read class {}: {}", name, error)),
+        };
+        let parsed = match hostile {
+            true => {
+                Class::parse_hostile(&bytes, &ParseLimits::default()).map(|(class, anomalies)| {
+                    for anomaly in anomalies {
+                        eprintln!("Warning: class {}: {}", name, anomaly);
+                    }
+                    class
+                })
+            }
+            false => Class::parse_bytes(&bytes),
+        };
+        progress.advance(parsed.is_err());
+        match parsed {
+            Ok(class) => classes.push(class),
+            Err(error) => eprintln!("Warning: skipping class {}: {}", name, error),
+        }
+    }
+    progress.finish();
+    Ok(classes)
+}
+
+fn list_classes(
+    classpath: &str,
+    format: ReportFormat,
+    filter: &FilterArgs,
+    class_cache: Option<&Path>,
+) -> Result<(), String> {
+    let class_path = open_class_path(classpath, filter)?;
+    let cache = class_cache.map(open_class_cache).transpose()?;
+    let mut terminal = TerminalProgress::new();
+    let listings = inventory::list_classes(&class_path, cache.as_ref(), listener(&mut terminal))
+        .map_err(|error| format!("Cannot read classpath '{}': {}", classpath, error))?;
+
+    let mut output = String::new();
+    match format {
+        ReportFormat::Table => inventory::write_table(&mut output, &listings),
+        ReportFormat::Json => inventory::write_json(&mut output, &listings),
+    }
+    .unwrap();
+    print!("{}", output);
+    Ok(())
+}
+
+fn open_class_cache(directory: &Path) -> Result<ClassCache, String> {
+    ClassCache::open(directory).map_err(|error| {
+        format!(
+            "Cannot open class cache '{}': {}",
+            directory.display(),
+            error
+        )
+    })
+}
+
+fn callgraph(
+    classpath: &str,
+    format: GraphFormat,
+    hostile: bool,
+    filter: &FilterArgs,
+) -> Result<(), String> {
+    let classes = load_all_classes(classpath, hostile, filter)?;
+    let graph = CallGraph::build(&classes).map_err(|error| error.to_string())?;
+
+    let mut output = String::new();
+    match format {
+        GraphFormat::Dot => graph.write_dot(&mut output),
+        GraphFormat::Json => graph.write_json(&mut output),
+    }
+    .unwrap();
+    print!("{}", output);
+    Ok(())
+}
+
+fn deadcode(
+    classpath: &str,
+    entries: &[String],
+    keep_files: &[PathBuf],
+    hostile: bool,
+    filter: &FilterArgs,
+) -> Result<(), String> {
+    let classes = load_all_classes(classpath, hostile, filter)?;
+    let graph = CallGraph::build(&classes).map_err(|error| error.to_string())?;
+
+    let main = |class: &str| MethodRef::new(class, "main", "([Ljava/lang/String;)V");
+    let mut entry_points = Vec::new();
+    for entry in entries {
+        let entry = entry.replace('.', "/");
+        let methods: Vec<&MethodRef> = match entry.split_once('#') {
+            Some((class, name)) => graph
+                .methods
+                .iter()
+                .filter(|method| method.class == class && method.name == name)
+                .collect(),
+            None => graph.methods.get(&main(&entry)).into_iter().collect(),
+        };
+        if methods.is_empty() {
+            return Err(format!("No entry point {}", entry));
+        }
+        entry_points.extend(methods.into_iter().cloned());
+    }
+    if entries.is_empty() {
+        let mains = graph
+            .methods
+            .iter()
+            .filter(|method| **method == main(&method.class));
+        entry_points.extend(mains.cloned());
+    }
+
+    let mut keep = KeepRules::default();
+    for path in keep_files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+        keep.parse(&text);
+    }
+    let class_path = ClassPath::parse(classpath)
+        .map_err(|error| format!("Cannot open classpath '{}': {}", classpath, error))?;
+    for entry in class_path.entries() {
+        let names = entry.resource_names().map_err(|error| error.to_string())?;
+        for name in names
+            .iter()
+            .filter(|name| name.ends_with("reflect-config.json"))
+        {
+            if let Ok(Some(bytes)) = entry.read_resource(name) {
+                keep.parse_reflection_config(&String::from_utf8_lossy(&bytes));
+            }
+        }
+    }
+
+    let dead = find_dead_code(&classes, &graph, &entry_points, &keep)
+        .map_err(|error| error.to_string())?;
+    println!("Unused classes ({}):", dead.classes.len());
+    for class in &dead.classes {
+        println!("  {}", class);
+    }
+    println!("Unused methods ({}):", dead.methods.len());
+    for method in &dead.methods {
+        println!("  {}", method);
+    }
+    Ok(())
+}
+
+/// Parses the class file, or every class of the jar or directory.
+fn load_classes_at(path: &Path) -> Result<Vec<Class>, String> {
+    let parse = |name: &str, bytes: &[u8]| {
+        Class::parse_bytes(bytes).map_err(|error| format!("Cannot parse class {}: {}", name, error))
+    };
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "class")
+    {
+        let bytes = std::fs::read(path)
+            .map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+        return Ok(vec![parse(&path.display().to_string(), &bytes)?]);
+    }
+
+    let entry = ClassPathEntry::open(path)
+        .map_err(|error| format!("Cannot open {}: {}", path.display(), error))?;
+    let names = entry
+        .class_names()
+        .map_err(|error| format!("Cannot list classes of {}: {}", path.display(), error))?;
+    let mut classes = Vec::new();
+    for name in names {
+        match entry.read_class(&name) {
+            Ok(Some(bytes)) => classes.push(parse(&name, &bytes)?),
+            Ok(None) => {}
+            Err(error) => return Err(format!("Cannot read class {}: {}", name, error)),
+        }
+    }
+    Ok(classes)
+}
+
+fn diff(old: &Path, new: &Path, code: bool) -> Result<ExitCode, String> {
+    let (old, new) = (load_classes_at(old)?, load_classes_at(new)?);
+    let differences = if old.len() == 1 && new.len() == 1 {
+        diff_classes(&old[0], &new[0], code)
+    } else {
+        diff_class_sets(&old, &new, code)
+    }
+    .map_err(|error| error.to_string())?;
+
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    Ok(if differences.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn compat(old: &Path, new: &Path) -> Result<ExitCode, String> {
+    let (old, new) = (load_classes_at(old)?, load_classes_at(new)?);
+    let report = check_compatibility(&old, &new).map_err(|error| error.to_string())?;
+
+    for change in &report.changes {
+        println!("{}", change);
+    }
+    let compatibility = report.compatibility();
+    println!(
+        "{} changes, {}, requiring a {} version",
+        report.changes.len(),
+        compatibility,
+        report.required_bump()
+    );
+    Ok(match compatibility {
+        Compatibility::Compatible => ExitCode::SUCCESS,
+        _ => ExitCode::from(1),
+    })
+}
+
+fn describe_module(path: &Path) -> Result<(), String> {
+    let module = ModuleReference::read(path)
+        .map_err(|error| format!("Cannot read '{}': {}", path.display(), error))?
+        .ok_or_else(|| format!("No module descriptor found in '{}'", path.display()))?;
+    if module.descriptor.automatic {
+        println!("No module descriptor found. Derived automatic module.\n");
+    }
+    print!("{}", module.descriptor);
+    Ok(())
+}
+
+fn optimize(input: &Path, output: &Path) -> Result<(), String> {
+    let mut stats = OptimizationStats::default();
+    for mut class in load_classes_at(input)? {
+        let name = class.name().map_err(|error| error.to_string())?.to_string();
+        stats.add(
+            &optimize_class(&mut class)
+                .map_err(|error| format!("Cannot optimize class {}: {}", name, error))?,
+        );
+        let path = output.join(format!("{}.class", name));
+        let bytes = class
+            .to_bytes()
+            .map_err(|error| format!("Cannot write class {}: {}", name, error))?;
+        std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, bytes))
+            .map_err(|error| format!("Cannot write {}: {}", path.display(), error))?;
+    }
+    println!("{}", stats);
+    Ok(())
+}
+
+fn shrink(
+    input: &Path,
+    output: &Path,
+    entries: &[String],
+    keep_files: &[PathBuf],
+    strip: &[StrippedAttributes],
+) -> Result<(), String> {
+    let mut keep = KeepRules::default();
+    for path in keep_files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+        keep.parse(&text);
+    }
+    let options = ShrinkOptions {
+        entries: entries.to_vec(),
+        keep,
+        strip: StrippedAttributes::flags(strip),
+    };
+    let reader = File::open(input)
+        .map(BufReader::new)
+        .map_err(|error| format!("Cannot open {}: {}", input.display(), error))?;
+    let writer = File::create(output)
+        .map(BufWriter::new)
+        .map_err(|error| format!("Cannot create {}: {}", output.display(), error))?;
+    let stats = shrink_jar(reader, writer, &options)
+        .map_err(|error| format!("Cannot shrink {}: {}", input.display(), error))?;
+    println!("{}", stats);
+    Ok(())
+}
+
+fn strip_classes(input: &Path, output: &Path, strip: &[StrippedAttributes]) -> Result<(), String> {
+    let stripped = StrippedAttributes::flags(strip);
+    let (mut classes, mut removed) = (0, 0);
+    for mut class in load_classes_at(input)? {
+        let name = class.name().map_err(|error| error.to_string())?.to_string();
+        removed += strip_class(&mut class, stripped)
+            .map_err(|error| format!("Cannot strip class {}: {}", name, error))?;
+        classes += 1;
+        let path = output.join(format!("{}.class", name));
+        let bytes = class
+            .to_bytes()
+            .map_err(|error| format!("Cannot write class {}: {}", name, error))?;
+        std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, bytes))
+            .map_err(|error| format!("Cannot write {}: {}", path.display(), error))?;
+    }
+    println!("{} attributes stripped from {} classes", removed, classes);
+    Ok(())
+}
+
+fn find(
+    classpath: &str,
+    regex: bool,
+    filter: &FilterArgs,
+    watch: bool,
+    kind: FindKind,
+    pattern: &str,
+) -> Result<ExitCode, String> {
+    let pattern = match regex {
+        true => Pattern::regex(pattern),
+        false => Pattern::glob(pattern),
+    }
+    .map_err(|error| format!("Invalid pattern: {}", error))?;
+
+    let mut class_path = open_class_path(classpath, filter)?;
+    let mut index = FindIndex {
+        providers: BTreeMap::new(),
+        classes: BTreeMap::new(),
+        parse: !matches!(kind, FindKind::Class),
+    };
+    for (position, entry) in class_path.entries().iter().enumerate() {
+        let names = class_path.class_names(position).map_err(|error| {
+            format!(
+                "Cannot list classes of {}: {}",
+                entry.path().display(),
+                error
+            )
+        })?;
+        for name in names {
+            index.update(&class_path, position, &name, false)?;
+        }
+    }
+    let found = print_found(&class_path, &index, kind, &pattern)?;
+    if !watch {
+        return Ok(match found {
+            0 => ExitCode::from(1),
+            _ => ExitCode::SUCCESS,
+        });
+    }
+
+    let mut watcher = watch_class_path(&class_path)?;
+    loop {
+        let changes = match watcher.wait(&mut class_path, WATCH_INTERVAL) {
+            Ok(changes) => changes,
+            Err(error) => {
+                eprintln!("Warning: cannot watch classpath '{}': {}", classpath, error);
+                continue;
+            }
+        };
+        for change in &changes {
+            if let Err(error) =
+                index.update(&class_path, change.entry, &change.class, change.removed)
+            {
+                eprintln!("Warning: {}", error);
+            }
+        }
+        eprintln!("{} classes changed", changes.len());
+        print_found(&class_path, &index, kind, &pattern)?;
+    }
+}
+
+/// The classes of the classpath `find` searches, with the entries providing
+/// them, the class of the first one parsed unless only names are searched.
+struct FindIndex {
+    providers: BTreeMap<String, Vec<usize>>,
+    classes: BTreeMap<String, Class>,
+    parse: bool,
+}
+
+impl FindIndex {
+    /// Records the class added to, changed in or removed from the entry,
+    /// parsing it again unless an earlier entry shadows it.
+    fn update(
+        &mut self,
+        class_path: &ClassPath,
+        entry: usize,
+        name: &str,
+        removed: bool,
+    ) -> Result<(), String> {
+        let providers = self.providers.entry(name.to_string()).or_default();
+        providers.retain(|&provider| provider != entry);
+        if !removed {
+            let position = providers.partition_point(|&provider| provider < entry);
+            providers.insert(position, entry);
+        }
+        let first = providers.first().copied();
+        if providers.is_empty() {
+            self.providers.remove(name);
+        }
+        if !self.parse {
+            return Ok(());
+        }
+
+        let first = match first {
+            Some(first) if first < entry => return Ok(()),
+            Some(first) => first,
+            None => {
+                self.classes.remove(name);
+                return Ok(());
+            }
+        };
+        self.classes.remove(name);
+        let bytes = match class_path.entries()[first].read_class(name) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Ok(()),
+            Err(error) => return Err(format!("Cannot read class {}: {}", name, error)),
+        };
+        match Class::parse_bytes(&bytes) {
+            Ok(class) => {
+                self.classes.insert(name.to_string(), class);
+            }
+            Err(error) => eprintln!("Warning: skipping class {}: {}", name, error),
+        }
+        Ok(())
+    }
+}
+
+/// Prints what matches the pattern, returning how many matched.
+fn print_found(
+    class_path: &ClassPath,
+    index: &FindIndex,
+    kind: FindKind,
+    pattern: &Pattern,
+) -> Result<usize, String> {
+    let mut found = 0;
+    match kind {
+        FindKind::Class => {
+            for (name, providers) in &index.providers {
+                if !pattern.matches(name) {
+                    continue;
+                }
+                found += 1;
+                for (position, &provider) in providers.iter().enumerate() {
+                    let path = class_path.entries()[provider].path();
+                    let shadowed = if position > 0 { " (shadowed)" } else { "" };
+                    println!("{} {}{}", name, path.display(), shadowed);
+                }
+            }
+        }
+        FindKind::Subtypes => {
+            let subtypes = find_subtypes(index.classes.values(), pattern)
+                .map_err(|error| error.to_string())?;
+            for (class, supertypes) in &subtypes {
+                println!("{}: {}", class, supertypes.join(", "));
+            }
+            found = subtypes.len();
+        }
+        FindKind::References => {
+            let references = find_references(index.classes.values(), pattern)
+                .map_err(|error| error.to_string())?;
+            for reference in &references {
+                println!(
+                    "{}.{} pc {}: {}",
+                    reference.class, reference.method, reference.pc, reference.member
+                );
+            }
+            found = references.len();
+        }
+    }
+    Ok(found)
+}
+
+/// The `--ea` and `--da` options applied in the order they were given, so
+/// that the last one for a target wins.
+fn assertion_status(matches: &ArgMatches) -> AssertionStatus {
+    let mut options = Vec::new();
+    for (id, enabled) in [("enable_assertions", true), ("disable_assertions", false)] {
+        if let (Some(indices), Some(targets)) =
+            (matches.indices_of(id), matches.get_many::<String>(id))
+        {
+            options.extend(
+                indices
+                    .zip(targets)
+                    .map(|(index, target)| (index, enabled, target)),
+            );
+        }
+    }
+    options.sort_by_key(|(index, _, _)| *index);
+
+    let mut status = AssertionStatus::default();
+    for (_, enabled, target) in options {
+        status.set(target, enabled);
+    }
+    status
+}
 
-    let rt_jar_file = File::open("/Users/bhegyi/.sdkman/candidates/java/8.0.372-zulu/zulu-8.jdk/Contents/Home/jre/lib/rt.jar").unwrap();
-    let rt_jar_reader = io::BufReader::new(rt_jar_file);
-    jar::load_jar(rt_jar_reader);
+fn main() -> ExitCode {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    args.run.assertions = assertion_status(&matches);
+    init_logging(args.verbose, args.log_format);
 
-    let main_class_file = File::open("res/Main.class").unwrap();
-    let mut main_class_reader = io::BufReader::new(main_class_file);
+    let result = match args.command {
+        Some(Command::Doctor { classpath, filter }) => {
+            doctor(&classpath, &filter).map(|_| ExitCode::SUCCESS)
+        }
+        Some(Command::Cfg {
+            classpath,
+            class,
+            method,
+        }) => cfg(&classpath, &class, method.as_deref()).map(|_| ExitCode::SUCCESS),
+        Some(Command::Disassemble {
+            classpath,
+            class,
+            method,
+        }) => disassemble(&classpath, &class, method.as_deref()).map(|_| ExitCode::SUCCESS),
+        Some(Command::Decompile {
+            classpath,
+            class,
+            method,
+        }) => decompile(&classpath, &class, method.as_deref()).map(|_| ExitCode::SUCCESS),
+        Some(Command::Stats {
+            classpath,
+            format,
+            filter,
+            class_cache,
+            watch,
+            pool,
+            classes,
+        }) => stats(
+            &classpath,
+            format,
+            &filter,
+            class_cache.as_deref(),
+            watch,
+            &classes,
+            pool,
+        )
+        .map(|_| ExitCode::SUCCESS),
+        Some(Command::Verify {
+            classpath,
+            filter,
+            class_cache,
+            watch,
+            classes,
+        }) => verify(&classpath, &filter, class_cache.as_deref(), watch, &classes),
+        Some(Command::Callgraph {
+            classpath,
+            format,
+            hostile,
+            filter,
+        }) => callgraph(&classpath, format, hostile, &filter).map(|_| ExitCode::SUCCESS),
+        Some(Command::Deadcode {
+            classpath,
+            entries,
+            keep_files,
+            hostile,
+            filter,
+        }) => {
+            deadcode(&classpath, &entries, &keep_files, hostile, &filter).map(|_| ExitCode::SUCCESS)
+        }
+        Some(Command::Diff { old, new, code }) => diff(&old, &new, code),
+        Some(Command::Compat { old, new }) => compat(&old, &new),
+        Some(Command::DescribeModule { path }) => describe_module(&path).map(|_| ExitCode::SUCCESS),
+        Some(Command::Optimize { input, output }) => {
+            optimize(&input, &output).map(|_| ExitCode::SUCCESS)
+        }
+        Some(Command::Shrink {
+            input,
+            output,
+            entries,
+            keep_files,
+            strip,
+        }) => shrink(&input, &output, &entries, &keep_files, &strip).map(|_| ExitCode::SUCCESS),
+        Some(Command::Strip {
+            input,
+            output,
+            strip,
+        }) => strip_classes(&input, &output, &strip).map(|_| ExitCode::SUCCESS),
+        Some(Command::Find {
+            classpath,
+            regex,
+            filter,
+            watch,
+            kind,
+            pattern,
+        }) => find(&classpath, regex, &filter, watch, kind, &pattern),
+        Some(Command::Test {
+            classpath,
+            java_home,
+            classes,
+        }) => on_vm_thread(None, move || test(&classpath, java_home, &classes)),
+        Some(Command::Repl {
+            classpath,
+            java_home,
+        }) => on_vm_thread(None, move || repl(&classpath, java_home)),
+        #[cfg(feature = "tui")]
+        Some(Command::Browse { classpath }) => browse(&classpath).map(|_| ExitCode::SUCCESS),
+        None => run_on_vm_thread(args.run),
+    };
 
-    let main_class = Class::read(&mut main_class_reader).unwrap();
-    println!("{:#?}", main_class);
+    match result {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,650 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::packaging::filter::ClassFilter;
+use crate::packaging::jar::{is_class_file, Jar};
+use crate::packaging::remote::{self, RemoteJar};
+use crate::packaging::source::ClassSource;
+
+// =============================================================================
+// CLASSPATH ENTRY
+// =============================================================================
+
+/// Directory inside a jmod file holding the class files and resources.
+static JMOD_CLASSES: &str = "classes/";
+
+/// Directory of a Spring Boot jar holding the classes of the application.
+static BOOT_CLASSES: &str = "BOOT-INF/classes/";
+
+/// Directory of a Spring Boot jar holding the jars of the libraries.
+static BOOT_LIB: &str = "BOOT-INF/lib/";
+
+/// The order of the libraries of a Spring Boot jar, when not in name order.
+static BOOT_CLASSPATH_INDEX: &str = "BOOT-INF/classpath.idx";
+
+/// A single root of the classpath: either a directory tree of class files, a
+/// jar file, a directory of a jar file like the classes of a Spring Boot
+/// jar, a jmod file of a JDK image, or a [ClassSource] of the embedder.
+pub enum ClassPathEntry {
+    Directory(PathBuf),
+    Jar(Jar),
+    JarDirectory {
+        jar: Jar,
+        /// The `/` terminated directory, e.g. `BOOT-INF/classes/`.
+        prefix: String,
+        /// The jar path followed by `!/` and the directory.
+        path: PathBuf,
+    },
+    Jmod(Jar),
+    Source(Box<dyn ClassSource>),
+}
+
+impl ClassPathEntry {
+    /// Opens the entry at the given path, deciding its kind based on whether
+    /// the path is a directory or a file, or the URL of a [RemoteJar].
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ClassPathEntry> {
+        let path = path.as_ref();
+        if let Some(url) = path.to_str().filter(|path| remote::is_remote(path)) {
+            Ok(ClassPathEntry::Source(Box::new(RemoteJar::open(url)?)))
+        } else if path.is_dir() {
+            Ok(ClassPathEntry::Directory(path.to_path_buf()))
+        } else if matches!(path.extension(), Some(x) if x == "jmod") {
+            Ok(ClassPathEntry::Jmod(Jar::open(path)?))
+        } else {
+            Ok(ClassPathEntry::Jar(Jar::open(path)?))
+        }
+    }
+
+    /// The entries a Spring Boot jar provides besides itself, in the order
+    /// of its launcher: its classes, then the jars of its libraries. Other
+    /// entries provide none.
+    pub fn nested_entries(&self) -> io::Result<Vec<ClassPathEntry>> {
+        let jar = match self {
+            ClassPathEntry::Jar(jar) => jar,
+            _ => return Ok(Vec::new()),
+        };
+        let names = jar.entry_names();
+        let mut entries = Vec::new();
+        if names.iter().any(|name| name.starts_with(BOOT_CLASSES)) {
+            entries.push(ClassPathEntry::JarDirectory {
+                jar: Jar::open(jar.path())?,
+                prefix: BOOT_CLASSES.to_string(),
+                path: PathBuf::from(format!("{}!/{}", jar.path().display(), BOOT_CLASSES)),
+            });
+        }
+
+        let mut libraries: Vec<&String> = names
+            .iter()
+            .filter(|name| {
+                matches!(name.strip_prefix(BOOT_LIB), Some(file) if file.ends_with(".jar") && !file.contains('/'))
+            })
+            .collect();
+        libraries.sort();
+        if let Some(index) = jar.read_entry(BOOT_CLASSPATH_INDEX)? {
+            // Lines like `- "BOOT-INF/lib/spring-core-6.1.0.jar"`
+            let index = String::from_utf8_lossy(&index);
+            let order: Vec<&str> = index
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix('-'))
+                .map(|name| name.trim().trim_matches('"'))
+                .collect();
+            libraries.sort_by_key(|name| {
+                order
+                    .iter()
+                    .position(|listed| listed == name)
+                    .unwrap_or(order.len())
+            });
+        }
+        for name in libraries {
+            if let Some(library) = jar.open_nested(name)? {
+                entries.push(ClassPathEntry::Jar(library));
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            ClassPathEntry::Directory(path) => path,
+            ClassPathEntry::Jar(jar) | ClassPathEntry::Jmod(jar) => jar.path(),
+            ClassPathEntry::JarDirectory { path, .. } => path,
+            ClassPathEntry::Source(source) => source.location(),
+        }
+    }
+
+    /// `/` separated names of every file in the entry, in a stable, sorted
+    /// order.
+    pub fn resource_names(&self) -> io::Result<Vec<String>> {
+        let mut names = match self {
+            ClassPathEntry::Directory(root) => {
+                let mut files = Vec::new();
+                collect_files(root, root, &mut files)?;
+                files
+            }
+            ClassPathEntry::Jar(jar) => jar
+                .entry_names()
+                .into_iter()
+                .filter(|name| !name.ends_with('/'))
+                .collect(),
+            ClassPathEntry::JarDirectory { jar, prefix, .. } => jar
+                .entry_names()
+                .into_iter()
+                .filter(|name| !name.ends_with('/'))
+                .filter_map(|name| name.strip_prefix(prefix.as_str()).map(String::from))
+                .collect(),
+            ClassPathEntry::Jmod(jmod) => jmod
+                .entry_names()
+                .into_iter()
+                .filter(|name| !name.ends_with('/'))
+                .filter_map(|name| name.strip_prefix(JMOD_CLASSES).map(String::from))
+                .collect(),
+            ClassPathEntry::Source(source) => source.resource_names()?,
+        };
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Internal names (e.g. `java/lang/Object`) of every class in the entry,
+    /// in a stable, sorted order.
+    pub fn class_names(&self) -> io::Result<Vec<String>> {
+        let names = self
+            .resource_names()?
+            .into_iter()
+            .filter(|name| is_class_file(name))
+            .map(|name| name.trim_end_matches(".class").to_string())
+            .collect();
+
+        Ok(names)
+    }
+
+    /// Reads the bytes of the class with the given internal name, if this
+    /// entry provides it.
+    pub fn read_class(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        self.read_resource(&format!("{}.class", name))
+    }
+
+    /// Reads the bytes of an arbitrary `/` separated resource, if this entry
+    /// provides it. Names escaping the entry's root are never resolved.
+    pub fn read_resource(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let name = name.trim_start_matches('/');
+        if name.is_empty() || name.split('/').any(|part| part == "..") {
+            return Ok(None);
+        }
+
+        match self {
+            ClassPathEntry::Directory(root) => read_optional_file(&root.join(name)),
+            ClassPathEntry::Jar(jar) => jar.read_entry(name),
+            ClassPathEntry::JarDirectory { jar, prefix, .. } => {
+                jar.read_entry(&format!("{}{}", prefix, name))
+            }
+            ClassPathEntry::Jmod(jmod) => jmod.read_entry(&format!("{}{}", JMOD_CLASSES, name)),
+            ClassPathEntry::Source(source) => source.read_resource(name),
+        }
+    }
+
+    /// The URL `ClassLoader.getResource` reports for a resource of this
+    /// entry, like `file:/app/classes/app.properties` for a directory or
+    /// `jar:file:/app/app.jar!/app.properties` for a jar.
+    pub fn resource_url(&self, name: &str) -> String {
+        let name = name.trim_start_matches('/');
+        match self {
+            ClassPathEntry::Directory(root) => {
+                format!("file:{}/{}", absolute(root).display(), name)
+            }
+            ClassPathEntry::Jar(jar) => {
+                format!("jar:file:{}!/{}", absolute(jar.path()).display(), name)
+            }
+            ClassPathEntry::JarDirectory { path, .. } => {
+                format!("jar:file:{}{}", absolute(path).display(), name)
+            }
+            ClassPathEntry::Jmod(jmod) => format!(
+                "jar:file:{}!/{}{}",
+                absolute(jmod.path()).display(),
+                JMOD_CLASSES,
+                name
+            ),
+            ClassPathEntry::Source(source) => {
+                format!("jar:{}!/{}", source.location().display(), name)
+            }
+        }
+    }
+}
+
+/// The path relative to the working directory made absolute, without
+/// resolving links or requiring it to exist.
+fn absolute(path: &Path) -> PathBuf {
+    match std::env::current_dir() {
+        Ok(directory) => directory.join(path),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Recursively collects the files under `directory` as `/` separated paths
+/// relative to `root`.
+fn collect_files(root: &Path, directory: &Path, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let components: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            files.push(components.join("/"));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_optional_file(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+// =============================================================================
+// CLASSPATH
+// =============================================================================
+
+/// An ordered list of [ClassPathEntry] roots. Lookups always consult the
+/// entries in order, so the first entry providing a class wins.
+///
+/// Scans of every class, like those of the tools and [ClassRegistry], list
+/// only the classes the [ClassFilter] selects, while lookups by name still
+/// find any class.
+///
+/// [ClassRegistry]: crate::vm::registry::ClassRegistry
+#[derive(Default)]
+pub struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+    filter: ClassFilter,
+}
+
+impl ClassPath {
+    /// Parses a classpath specification, using the platform's path separator
+    /// (`:` on Unix, `;` on Windows) between the entries. Like the reference
+    /// launcher, an entry `dir/*` stands for the jars of the directory, in
+    /// name order, a missing directory standing for none.
+    pub fn parse(specification: &str) -> io::Result<ClassPath> {
+        let mut class_path = ClassPath::default();
+        for path in split_class_path(specification) {
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            for path in expand_wildcard(path)? {
+                class_path.push_opened(path)?;
+            }
+        }
+
+        Ok(class_path)
+    }
+
+    /// Opens each of the paths as an entry, in order.
+    pub fn open_all<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> io::Result<ClassPath> {
+        let mut class_path = ClassPath::default();
+        for path in paths {
+            class_path.push_opened(path)?;
+        }
+
+        Ok(class_path)
+    }
+
+    pub fn push(&mut self, entry: ClassPathEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Opens the path as an entry, followed by its
+    /// [nested entries](ClassPathEntry::nested_entries).
+    fn push_opened<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let entry = ClassPathEntry::open(path)?;
+        let nested = entry.nested_entries()?;
+        self.push(entry);
+        self.entries.extend(nested);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[ClassPathEntry] {
+        &self.entries
+    }
+
+    pub fn set_filter(&mut self, filter: ClassFilter) {
+        self.filter = filter;
+    }
+
+    pub fn filter(&self) -> &ClassFilter {
+        &self.filter
+    }
+
+    /// Internal names of the classes of the entry at the index the filter
+    /// selects, in a stable, sorted order.
+    pub fn class_names(&self, index: usize) -> io::Result<Vec<String>> {
+        let mut names = self.entries[index].class_names()?;
+        names.retain(|name| self.filter.matches(name));
+        Ok(names)
+    }
+
+    /// Replaces the entry at the index, e.g. with the jar reopened after it
+    /// was rewritten.
+    pub fn replace(&mut self, index: usize, entry: ClassPathEntry) {
+        self.entries[index] = entry;
+    }
+
+    /// Finds the first entry providing the class, returning its index in
+    /// [ClassPath::entries] together with the bytes of the class.
+    pub fn find_class(&self, name: &str) -> io::Result<Option<(usize, Vec<u8>)>> {
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(bytes) = entry.read_class(name)? {
+                return Ok(Some((index, bytes)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads a resource (e.g. `META-INF/MANIFEST.MF`) from the first entry
+    /// providing it. Entries failing to read are treated as not providing it,
+    /// matching `ClassLoader.getResource` returning `null`.
+    pub fn resource(&self, name: &str) -> Option<Vec<u8>> {
+        self.entries
+            .iter()
+            .find_map(|entry| entry.read_resource(name).ok().flatten())
+    }
+
+    /// Reads a resource from every entry providing it, in classpath order, the
+    /// way `ClassLoader.getResources` enumerates them.
+    pub fn resources(&self, name: &str) -> Vec<Vec<u8>> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.read_resource(name).ok().flatten())
+            .collect()
+    }
+
+    /// The [URL](ClassPathEntry::resource_url) of the resource in the first
+    /// entry providing it.
+    pub fn resource_url(&self, name: &str) -> Option<String> {
+        self.entries
+            .iter()
+            .find(|entry| matches!(entry.read_resource(name), Ok(Some(_))))
+            .map(|entry| entry.resource_url(name))
+    }
+
+    /// The [URLs](ClassPathEntry::resource_url) of the resource in every entry
+    /// providing it, in classpath order.
+    pub fn resource_urls(&self, name: &str) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.read_resource(name), Ok(Some(_))))
+            .map(|entry| entry.resource_url(name))
+            .collect()
+    }
+
+    /// Reads the resource at one of the [URLs](ClassPathEntry::resource_url)
+    /// of the entries, if it is theirs.
+    pub fn resource_at(&self, url: &str) -> Option<Vec<u8>> {
+        self.entries.iter().find_map(|entry| {
+            let name = url.strip_prefix(&entry.resource_url(""))?;
+            entry.read_resource(name).ok().flatten()
+        })
+    }
+}
+
+/// The jar files of the directory of a `dir/*` entry, otherwise the entry.
+fn expand_wildcard(path: PathBuf) -> io::Result<Vec<PathBuf>> {
+    let is_wildcard = path.file_name().is_some_and(|name| name == "*")
+        && !path.to_str().is_some_and(remote::is_remote);
+    if !is_wildcard {
+        return Ok(vec![path]);
+    }
+
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut jars = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_jar = matches!(path.extension(), Some(x) if x.eq_ignore_ascii_case("jar"));
+        if is_jar && path.is_file() {
+            jars.push(path);
+        }
+    }
+    jars.sort();
+    Ok(jars)
+}
+
+/// Splits a classpath specification into its entries, keeping together the
+/// URLs the `:` separator of Unix also appears in, after their scheme and
+/// before their port.
+fn split_class_path(specification: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for path in std::env::split_paths(specification) {
+        let joined = match (paths.last().and_then(|last| last.to_str()), path.to_str()) {
+            (Some(scheme @ ("http" | "https")), Some(rest)) if rest.starts_with("//") => {
+                Some(format!("{}:{}", scheme, rest))
+            }
+            (Some(url), Some(rest))
+                if remote::is_remote(url)
+                    && !url.split_once("//").unwrap().1.contains(['/', ':'])
+                    && rest.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                Some(format!("{}:{}", url, rest))
+            }
+            _ => None,
+        };
+        match joined {
+            Some(joined) => *paths.last_mut().unwrap() = PathBuf::from(joined),
+            None => paths.push(path),
+        }
+    }
+    paths
+}
+
+// ============================================================================
+// CLASSPATH TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod class_path_tests {
+    use std::fs;
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::{split_class_path, ClassPath, ClassPathEntry};
+
+    fn resource_dir(name: &str, resources: &[(&str, &[u8])]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("bvm-classpath-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (resource, bytes) in resources {
+            let path = root.join(resource);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, bytes).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_resource_resolved_in_order() {
+        let first = resource_dir("first", &[("config/app.properties", b"first")]);
+        let second = resource_dir(
+            "second",
+            &[("config/app.properties", b"second"), ("banner.txt", b"hi")],
+        );
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&first).unwrap());
+        class_path.push(ClassPathEntry::open(&second).unwrap());
+
+        assert_eq!(
+            class_path.resource("config/app.properties").unwrap(),
+            b"first"
+        );
+        assert_eq!(class_path.resource("/banner.txt").unwrap(), b"hi");
+        assert_eq!(class_path.resource("missing.txt"), None);
+        assert_eq!(class_path.resource("../outside.txt"), None);
+    }
+
+    #[test]
+    fn test_resource_urls() {
+        let directory = resource_dir("urls", &[("config/app.properties", b"directory")]);
+        let jar = zip(&[("config/app.properties", b"jar", CompressionMethod::Deflated)]);
+        let root = resource_dir("urls-jar", &[("app.jar", &jar)]);
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&directory).unwrap());
+        class_path.push(ClassPathEntry::open(root.join("app.jar")).unwrap());
+
+        let urls = class_path.resource_urls("config/app.properties");
+        assert_eq!(
+            class_path.resource_url("config/app.properties").as_ref(),
+            urls.first()
+        );
+        assert_eq!(
+            urls,
+            vec![
+                format!("file:{}/config/app.properties", directory.display()),
+                format!(
+                    "jar:file:{}!/config/app.properties",
+                    root.join("app.jar").display()
+                ),
+            ]
+        );
+        assert_eq!(class_path.resource_at(&urls[0]).unwrap(), b"directory");
+        assert_eq!(class_path.resource_at(&urls[1]).unwrap(), b"jar");
+        assert_eq!(
+            class_path.resource_urls("missing.txt"),
+            Vec::<String>::new()
+        );
+        assert_eq!(class_path.resource_at("file:/missing.txt"), None);
+        let _ = fs::remove_dir_all(&directory);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    fn zip(files: &[(&str, &[u8], CompressionMethod)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes, compression) in files {
+            let options = FileOptions::default().compression_method(*compression);
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_spring_boot_jar() {
+        let library = |class: &str| zip(&[(class, b"library", CompressionMethod::Deflated)]);
+        let stored = library("com/lib/Stored.class");
+        let deflated = library("com/lib/Deflated.class");
+        let index = b"- \"BOOT-INF/lib/z-first.jar\"\n- \"BOOT-INF/lib/a-second.jar\"\n";
+        let jar = zip(&[
+            (
+                "org/springframework/boot/loader/JarLauncher.class",
+                b"launcher",
+                CompressionMethod::Deflated,
+            ),
+            (
+                "BOOT-INF/classes/com/example/App.class",
+                b"app",
+                CompressionMethod::Deflated,
+            ),
+            ("BOOT-INF/classpath.idx", index, CompressionMethod::Deflated),
+            (
+                "BOOT-INF/lib/a-second.jar",
+                &deflated,
+                CompressionMethod::Deflated,
+            ),
+            (
+                "BOOT-INF/lib/z-first.jar",
+                &stored,
+                CompressionMethod::Stored,
+            ),
+        ]);
+        let root = resource_dir("boot", &[("app.jar", &jar)]);
+        let path = root.join("app.jar");
+
+        let class_path = ClassPath::parse(path.to_str().unwrap()).unwrap();
+        let paths: Vec<_> = class_path
+            .entries()
+            .iter()
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+        let nested = |name: &str| PathBuf::from(format!("{}!/{}", path.display(), name));
+        assert_eq!(
+            paths,
+            vec![
+                path.clone(),
+                nested("BOOT-INF/classes/"),
+                nested("BOOT-INF/lib/z-first.jar"),
+                nested("BOOT-INF/lib/a-second.jar"),
+            ]
+        );
+        assert_eq!(
+            class_path.entries()[1].class_names().unwrap(),
+            vec!["com/example/App"]
+        );
+        assert_eq!(
+            class_path.find_class("com/example/App").unwrap(),
+            Some((1, b"app".to_vec()))
+        );
+        assert_eq!(
+            class_path.find_class("com/lib/Stored").unwrap(),
+            Some((2, b"library".to_vec()))
+        );
+        assert_eq!(
+            class_path.find_class("com/lib/Deflated").unwrap(),
+            Some((3, b"library".to_vec()))
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_wildcard_expanded_to_jars() {
+        let root = resource_dir("wildcard", &[("notes.txt", b"not a jar")]);
+        for name in ["b.jar", "a.JAR"].iter() {
+            let mut jar = ZipWriter::new(fs::File::create(root.join(name)).unwrap());
+            jar.finish().unwrap();
+        }
+
+        let wildcard = root.join("*");
+        let class_path = ClassPath::parse(wildcard.to_str().unwrap()).unwrap();
+        let paths: Vec<_> = class_path
+            .entries()
+            .iter()
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+        assert_eq!(paths, vec![root.join("a.JAR"), root.join("b.jar")]);
+
+        let missing = root.join("missing/*");
+        let class_path = ClassPath::parse(missing.to_str().unwrap()).unwrap();
+        assert!(class_path.entries().is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_urls_kept_together() {
+        assert_eq!(
+            split_class_path("lib:http://host:8080/app.jar:https://host/b.jar:c"),
+            vec![
+                PathBuf::from("lib"),
+                PathBuf::from("http://host:8080/app.jar"),
+                PathBuf::from("https://host/b.jar"),
+                PathBuf::from("c"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use regex::Regex;
+
+// =============================================================================
+// CLASS FILTER
+// =============================================================================
+
+/// Selects the classes a scan of the classpath reads, by their internal
+/// names, before any of them is parsed: the classes matching an include
+/// pattern, or any without one, and none matching an exclude pattern.
+///
+/// Patterns are globs over internal names, or binary names whose dots are
+/// read as slashes: `*` and `?` match within a package, `**` across them,
+/// so that `com/example/**` selects a package and its subpackages and
+/// `**/internal/**` any `internal` package. A pattern without wildcards is
+/// a package prefix, `com.example` standing for `com/example/**`.
+#[derive(Clone, Debug, Default)]
+pub struct ClassFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl ClassFilter {
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<ClassFilter, String> {
+        let compile = |patterns: &[S]| {
+            patterns
+                .iter()
+                .map(|pattern| compile(pattern.as_ref()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ClassFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether the filter selects every class.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the class of the internal name is selected.
+    pub fn matches(&self, class_name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(class_name)))
+            && !self.exclude.iter().any(|glob| glob.is_match(class_name))
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    let mut glob = pattern.replace('.', "/");
+    if glob.is_empty() {
+        return Err("Empty class pattern".to_string());
+    }
+    if !glob.contains(['*', '?']) {
+        glob = format!("{}/**", glob.trim_end_matches('/'));
+    }
+
+    let mut regex = String::from("^");
+    let mut rest = glob.as_str();
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            regex.push_str("(?:.*/)?");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = after;
+            continue;
+        }
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|error| format!("Invalid class pattern '{}': {}", pattern, error))
+}
+
+// ============================================================================
+// CLASS FILTER TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod filter_tests {
+    use super::ClassFilter;
+
+    #[test]
+    fn test_globs() {
+        let filter =
+            ClassFilter::new(&["com/example/**", "org.acme"], &["**/internal/**"]).unwrap();
+        assert!(filter.matches("com/example/Main"));
+        assert!(filter.matches("com/example/util/Strings"));
+        assert!(filter.matches("org/acme/Tool"));
+        assert!(!filter.matches("org/acmecorp/Tool"));
+        assert!(!filter.matches("com/example/internal/Secret"));
+        assert!(!filter.matches("com/example/util/internal/Secret"));
+        assert!(!filter.matches("java/lang/Object"));
+
+        let filter = ClassFilter::new(&["java/*/Str?ng"], &[]).unwrap();
+        assert!(filter.matches("java/lang/String"));
+        assert!(!filter.matches("java/lang/ref/String"));
+
+        let filter = ClassFilter::new::<&str>(&[], &["internal/**"]).unwrap();
+        assert!(filter.matches("com/example/Main"));
+        assert!(!filter.matches("internal/Main"));
+        assert!(ClassFilter::default().is_empty());
+    }
+}
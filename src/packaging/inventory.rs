@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::io;
+use std::path::PathBuf;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::class::{Class, ClassAccessFlags};
+use crate::packaging::classpath::ClassPath;
+use crate::packaging::progress::{ProgressListener, ProgressTracker};
+use crate::vm::class_cache::{Cached, ClassCache};
+use crate::vm::metrics::json_string;
+
+// =============================================================================
+// CLASS LISTING
+// =============================================================================
+
+/// A class file of a classpath entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassListing {
+    /// Internal name, e.g. `java/lang/Object`.
+    pub name: String,
+    /// Path of the entry providing the class.
+    pub provider: PathBuf,
+    /// Index of the entry in the classpath.
+    pub entry: usize,
+    /// Whether an earlier entry provides a class of the same name.
+    pub shadowed: bool,
+    /// Size of the class file in bytes.
+    pub size: usize,
+    pub major_version: u16,
+    pub minor_version: u16,
+    /// Flags of the class, none when it fails to parse.
+    pub access_flags: Option<ClassAccessFlags>,
+}
+
+impl ClassListing {
+    /// The version as `major.minor`, with the Java release introducing it.
+    pub fn version(&self) -> String {
+        match self.major_version {
+            45..=48 => format!(
+                "{}.{} (Java 1.{})",
+                self.major_version,
+                self.minor_version,
+                self.major_version - 44
+            ),
+            49.. => format!(
+                "{}.{} (Java {})",
+                self.major_version,
+                self.minor_version,
+                self.major_version - 44
+            ),
+            _ => format!("{}.{}", self.major_version, self.minor_version),
+        }
+    }
+
+    /// The lowercase names of the flags, space separated.
+    pub fn flags(&self) -> String {
+        match self.access_flags {
+            Some(flags) => flags
+                .iter_names()
+                .map(|(name, _)| name.to_lowercase())
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => "invalid".to_string(),
+        }
+    }
+}
+
+/// The flags of a class file, none when it fails to parse.
+struct ParsedFlags(Option<ClassAccessFlags>);
+
+impl Cached for ParsedFlags {
+    const KIND: &'static str = "flags-1";
+
+    fn write(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        match self.0 {
+            Some(flags) => {
+                output.write_u8(1)?;
+                output.write_u16::<BigEndian>(flags.bits())
+            }
+            None => output.write_u8(0),
+        }
+    }
+
+    fn read(input: &mut dyn io::Read) -> io::Result<Self> {
+        Ok(ParsedFlags(match input.read_u8()? {
+            0 => None,
+            _ => Some(ClassAccessFlags::from_bits_retain(
+                input.read_u16::<BigEndian>()?,
+            )),
+        }))
+    }
+}
+
+/// Lists every class file of every entry of the classpath its filter
+/// selects, in classpath order, including those shadowed by an earlier
+/// entry. The flags of the classes are read from the cache, if any, when an
+/// earlier listing parsed the same class files. The listener, if any, is
+/// shown the classes read.
+pub fn list_classes(
+    class_path: &ClassPath,
+    cache: Option<&ClassCache>,
+    listener: Option<&mut dyn ProgressListener>,
+) -> io::Result<Vec<ClassListing>> {
+    let names = (0..class_path.entries().len())
+        .map(|index| class_path.class_names(index))
+        .collect::<io::Result<Vec<_>>>()?;
+    let total = names.iter().map(Vec::len).sum();
+    let mut progress = ProgressTracker::new("Listing classes", total, listener);
+
+    let mut listings = Vec::new();
+    let mut seen = HashSet::new();
+    for ((index, entry), names) in class_path.entries().iter().enumerate().zip(names) {
+        for name in names {
+            let bytes = match entry.read_class(&name)? {
+                Some(bytes) => bytes,
+                None => {
+                    progress.advance(false);
+                    continue;
+                }
+            };
+            let version = |offset: usize| {
+                bytes
+                    .get(offset..offset + 2)
+                    .map_or(0, |version| u16::from_be_bytes([version[0], version[1]]))
+            };
+            let parse = || {
+                let flags = Class::parse_bytes(&bytes)
+                    .ok()
+                    .map(|class| class.access_flags);
+                Ok::<_, io::Error>(ParsedFlags(flags))
+            };
+            let flags = match cache {
+                Some(cache) => cache.get_or_compute(&bytes, parse)?,
+                None => parse()?,
+            };
+            progress.advance(flags.0.is_none());
+            listings.push(ClassListing {
+                shadowed: !seen.insert(name.clone()),
+                provider: entry.path().to_path_buf(),
+                entry: index,
+                size: bytes.len(),
+                minor_version: version(4),
+                major_version: version(6),
+                access_flags: flags.0,
+                name,
+            });
+        }
+    }
+    progress.finish();
+    Ok(listings)
+}
+
+// =============================================================================
+// OUTPUT
+// =============================================================================
+
+/// Writes the classes grouped by provider, with the count and size of the
+/// classes of each, like `unzip -l`.
+pub fn write_table<W: Write>(writer: &mut W, listings: &[ClassListing]) -> fmt::Result {
+    let mut groups: Vec<&[ClassListing]> = Vec::new();
+    let mut rest = listings;
+    while let Some(first) = rest.first() {
+        let end = rest
+            .iter()
+            .position(|listing| listing.entry != first.entry)
+            .unwrap_or(rest.len());
+        groups.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    for group in &groups {
+        writeln!(writer, "{}", group[0].provider.display())?;
+        writeln!(
+            writer,
+            "  {:>8}  {:<16}  {:<24}  class",
+            "size", "version", "flags"
+        )?;
+        for listing in group.iter() {
+            let shadowed = if listing.shadowed { " (shadowed)" } else { "" };
+            writeln!(
+                writer,
+                "  {:>8}  {:<16}  {:<24}  {}{}",
+                listing.size,
+                listing.version(),
+                listing.flags(),
+                listing.name,
+                shadowed
+            )?;
+        }
+        write_total(writer, "  ", group)?;
+    }
+    if groups.len() > 1 {
+        writeln!(writer, "total")?;
+        write_total(writer, "  ", listings)?;
+    }
+    Ok(())
+}
+
+fn write_total<W: Write>(writer: &mut W, indent: &str, listings: &[ClassListing]) -> fmt::Result {
+    let size: usize = listings.iter().map(|listing| listing.size).sum();
+    writeln!(
+        writer,
+        "{}{} classes, {} bytes",
+        indent,
+        listings.len(),
+        size
+    )
+}
+
+/// Writes the classes as a JSON array of objects.
+pub fn write_json<W: Write>(writer: &mut W, listings: &[ClassListing]) -> fmt::Result {
+    write!(writer, "[")?;
+    for (index, listing) in listings.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        let flags = match listing.access_flags {
+            Some(_) => json_string(&listing.flags()),
+            None => "null".to_string(),
+        };
+        write!(
+            writer,
+            "{{\"name\":{},\"provider\":{},\"shadowed\":{},\"size\":{},\
+             \"major_version\":{},\"minor_version\":{},\"flags\":{}}}",
+            json_string(&listing.name),
+            json_string(&listing.provider.display().to_string()),
+            listing.shadowed,
+            listing.size,
+            listing.major_version,
+            listing.minor_version,
+            flags
+        )?;
+    }
+    writeln!(writer, "]")
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod inventory_tests {
+    use std::path::PathBuf;
+
+    use super::list_classes;
+    use crate::class::ClassAccessFlags;
+    use crate::packaging::classpath::ClassPath;
+    use crate::packaging::progress::Progress;
+
+    #[test]
+    fn test_list_classes() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden");
+        let class_path = ClassPath::open_all([&root, &root]).unwrap();
+        let mut processed = 0;
+        let mut listener = |progress: &Progress| processed = progress.processed;
+        let listings = list_classes(&class_path, None, Some(&mut listener)).unwrap();
+        assert_eq!(processed, listings.len());
+
+        let hello: Vec<_> = listings
+            .iter()
+            .filter(|listing| listing.name == "Hello")
+            .collect();
+        assert_eq!(hello.len(), 2);
+        assert!(!hello[0].shadowed);
+        assert!(hello[1].shadowed);
+        assert!(hello[0].major_version >= 52);
+        assert!(hello[0]
+            .access_flags
+            .unwrap()
+            .contains(ClassAccessFlags::SUPER));
+        assert_eq!(
+            hello[0].size as u64,
+            std::fs::metadata(root.join("Hello.class")).unwrap().len()
+        );
+    }
+}
@@ -1,28 +1,151 @@
-use crate::class::Class;
-use std::io::{Read, Seek};
-use std::path::Path;
-use zip::result::ZipResult;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::result::ZipError;
+use zip::{CompressionMethod, ZipArchive};
 
-fn is_class_file(path: &str) -> bool {
+pub fn is_class_file(path: &str) -> bool {
     let path = Path::new(path);
-    match path.extension() {
-        Some(x) if x == "class" => true,
-        _ => false,
-    }
+    matches!(path.extension(), Some(x) if x == "class")
+}
+
+// =============================================================================
+// JAR FILE
+// =============================================================================
+
+/// What the archive of a jar is read from.
+trait Source: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> Source for T {}
+
+/// An opened jar file, which can be queried for its entries by name.
+///
+/// The archive is kept open for the lifetime of the [Jar], so entries can be
+/// read lazily as the classes are requested.
+pub struct Jar {
+    path: PathBuf,
+    /// The file the jar lies in, uncompressed, and where it starts, unless
+    /// it was read into memory.
+    file: Option<(PathBuf, u64)>,
+    archive: Mutex<ZipArchive<Box<dyn Source>>>,
 }
 
-pub fn load_jar<R: Read + Seek>(reader: R) -> ZipResult<()> {
-    let mut zip = zip::ZipArchive::new(reader)?;
-    for file_index in 0..zip.len() {
-        let mut file = zip.by_index(file_index)?;
-        if is_class_file(file.name()) {
-            println!("Reading class {}", file.name());
-            match Class::read(&mut file) {
-                Ok(_) => (),
-                Err(error) => println!("\t -> {:?}", error),
+impl Jar {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Jar> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let archive = ZipArchive::new(Box::new(BufReader::new(file)) as Box<dyn Source>)?;
+
+        Ok(Jar {
+            file: Some((path.clone(), 0)),
+            path,
+            archive: Mutex::new(archive),
+        })
+    }
+
+    /// Opens the jar held by an entry of this one, like a library in
+    /// `BOOT-INF/lib/` of a Spring Boot jar, named like
+    /// `app.jar!/BOOT-INF/lib/library.jar`. A jar stored uncompressed, as
+    /// Spring Boot stores them, is read in place, otherwise it is read into
+    /// memory.
+    pub fn open_nested(&self, name: &str) -> io::Result<Option<Jar>> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut entry = match archive.by_name(name) {
+            Ok(entry) => entry,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let (file, source): (_, Box<dyn Source>) = match &self.file {
+            Some((path, offset)) if entry.compression() == CompressionMethod::Stored => {
+                let start = offset + entry.data_start();
+                let window = Window::new(File::open(path)?, start, entry.size())?;
+                (
+                    Some((path.clone(), start)),
+                    Box::new(BufReader::new(window)),
+                )
             }
-        }
+            _ => {
+                let mut bytes = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut bytes)?;
+                (None, Box::new(Cursor::new(bytes)))
+            }
+        };
+
+        Ok(Some(Jar {
+            path: PathBuf::from(format!("{}!/{}", self.path.display(), name)),
+            file,
+            archive: Mutex::new(ZipArchive::new(source)?),
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
-    Ok(())
+    /// Names of all the entries (files and directories) in the jar.
+    pub fn entry_names(&self) -> Vec<String> {
+        let archive = self.archive.lock().unwrap();
+        archive.file_names().map(String::from).collect()
+    }
+
+    /// Reads the content of the entry with the given name, if present.
+    pub fn read_entry(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut bytes = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+}
+
+/// The bytes of a file from `start` to `start + length`, read as a file of
+/// their own.
+struct Window {
+    file: File,
+    start: u64,
+    length: u64,
+    position: u64,
+}
+
+impl Window {
+    fn new(mut file: File, start: u64, length: u64) -> io::Result<Window> {
+        file.seek(SeekFrom::Start(start))?;
+        Ok(Window {
+            file,
+            start,
+            length,
+            position: 0,
+        })
+    }
+}
+
+impl Read for Window {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let left = self.length.saturating_sub(self.position);
+        let length = (buffer.len() as u64).min(left) as usize;
+        let read = self.file.read(&mut buffer[..length])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for Window {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(delta) => self.length.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        self.file.seek(SeekFrom::Start(self.start + position))?;
+        self.position = position;
+        Ok(position)
+    }
 }
@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::packaging::classpath::ClassPath;
+
+/// Modules the JDK defines to the platform class loader instead of the
+/// bootstrap one (as of JDK 17).
+static PLATFORM_MODULES: &[&str] = &[
+    "java.compiler",
+    "java.net.http",
+    "java.scripting",
+    "java.security.jgss",
+    "java.smartcardio",
+    "java.sql",
+    "java.sql.rowset",
+    "java.transaction.xa",
+    "java.xml.crypto",
+    "jdk.accessibility",
+    "jdk.charsets",
+    "jdk.crypto.cryptoki",
+    "jdk.crypto.ec",
+    "jdk.dynalink",
+    "jdk.httpserver",
+    "jdk.jsobject",
+    "jdk.localedata",
+    "jdk.naming.dns",
+    "jdk.security.auth",
+    "jdk.security.jgss",
+    "jdk.xml.dom",
+    "jdk.zipfs",
+];
+
+/// A JDK installation providing the classes of the bootstrap and platform
+/// class loaders.
+///
+/// Both the modular layout (`jmods/*.jmod`, JDK 9+) and the legacy one
+/// (`rt.jar` plus `lib/ext`, JDK 8) are understood. Images only shipping the
+/// `lib/modules` jimage are not supported.
+pub struct JdkImage {
+    home: PathBuf,
+}
+
+impl JdkImage {
+    pub fn new<P: AsRef<Path>>(home: P) -> JdkImage {
+        JdkImage {
+            home: home.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Locates the JDK pointed at by the `JAVA_HOME` environment variable.
+    pub fn from_env() -> Option<JdkImage> {
+        std::env::var_os("JAVA_HOME").map(JdkImage::new)
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// Classes defined by the bootstrap class loader.
+    pub fn boot_class_path(&self) -> io::Result<ClassPath> {
+        ClassPath::open_all(self.boot_class_path_files()?)
+    }
+
+    /// The files of the [JdkImage::boot_class_path], without opening them.
+    pub fn boot_class_path_files(&self) -> io::Result<Vec<PathBuf>> {
+        if let Some(jmods) = self.jmods()? {
+            // java.base first, so the core classes are resolved fastest
+            let (base, rest): (Vec<_>, Vec<_>) = jmods
+                .into_iter()
+                .filter(|jmod| !PLATFORM_MODULES.contains(&module_name(jmod).as_str()))
+                .partition(|jmod| module_name(jmod) == "java.base");
+            return Ok(base.into_iter().chain(rest).collect());
+        }
+
+        Ok(vec![
+            self.home.join("jre/lib/rt.jar"),
+            self.home.join("lib/rt.jar"),
+        ]
+        .into_iter()
+        .filter(|rt_jar| rt_jar.is_file())
+        .collect())
+    }
+
+    /// Classes defined by the platform class loader (the extension class
+    /// loader before JDK 9).
+    pub fn platform_class_path(&self) -> io::Result<ClassPath> {
+        ClassPath::open_all(self.platform_class_path_files()?)
+    }
+
+    /// The files of the [JdkImage::platform_class_path], without opening them.
+    pub fn platform_class_path_files(&self) -> io::Result<Vec<PathBuf>> {
+        if let Some(jmods) = self.jmods()? {
+            return Ok(jmods
+                .into_iter()
+                .filter(|jmod| PLATFORM_MODULES.contains(&module_name(jmod).as_str()))
+                .collect());
+        }
+
+        let mut jars = Vec::new();
+        for ext in [self.home.join("jre/lib/ext"), self.home.join("lib/ext")] {
+            jars.extend(sorted_files(&ext, "jar")?.unwrap_or_default());
+        }
+        Ok(jars)
+    }
+
+    fn jmods(&self) -> io::Result<Option<Vec<PathBuf>>> {
+        sorted_files(&self.home.join("jmods"), "jmod")
+    }
+}
+
+fn module_name(jmod: &Path) -> String {
+    jmod.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Files with the given extension in the directory, sorted by name, or `None`
+/// if the directory does not exist.
+fn sorted_files(directory: &Path, extension: &str) -> io::Result<Option<Vec<PathBuf>>> {
+    if !directory.is_dir() {
+        return Ok(None);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if matches!(path.extension(), Some(x) if x == extension) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(Some(files))
+}
@@ -1 +1,11 @@
+pub mod classpath;
+pub mod filter;
+pub mod inventory;
 pub mod jar;
+pub mod jdk;
+pub mod modulepath;
+pub mod progress;
+pub mod remote;
+pub mod services;
+pub mod source;
+pub mod watch;
@@ -0,0 +1,391 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::class::module::{is_qualified_name, ModuleDescriptor, Provides, MODULE_INFO};
+use crate::class::{Class, ClassLoadingError};
+use crate::packaging::classpath::ClassPathEntry;
+use crate::packaging::services::{parse_provider_configuration, SERVICES_DIRECTORY};
+
+/// The manifest of a jar, naming the automatic module of the jar.
+static MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+// =============================================================================
+// MODULE REFERENCE
+// =============================================================================
+
+/// A module found on the module path: its descriptor, and the jar, jmod or
+/// directory holding its classes.
+#[derive(Clone, Debug)]
+pub struct ModuleReference {
+    pub descriptor: ModuleDescriptor,
+    pub location: PathBuf,
+}
+
+impl ModuleReference {
+    /// Reads the module at the path: a modular jar, a jmod, or a directory
+    /// holding a `module-info` class, or a plain jar as an automatic module.
+    /// Directories without a `module-info` class hold no module.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Option<ModuleReference>> {
+        let path = path.as_ref();
+        let entry = ClassPathEntry::open(path)?;
+        let names = entry.class_names()?;
+        let mut packages = BTreeSet::new();
+        for name in &names {
+            match name.rfind('/') {
+                Some(end) if !name.starts_with("META-INF/") => {
+                    packages.insert(name[..end].to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let descriptor = match entry.read_class(MODULE_INFO)? {
+            Some(bytes) => {
+                let invalid = |error: ClassLoadingError| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid module-info in {}: {}", path.display(), error),
+                    )
+                };
+                let class = Class::parse_bytes(&bytes).map_err(invalid)?;
+                let mut descriptor = ModuleDescriptor::read(&class)
+                    .map_err(invalid)?
+                    .ok_or_else(|| invalid(ClassLoadingError::new("no Module attribute")))?;
+                descriptor.packages.extend(packages);
+                descriptor
+            }
+            None if path.is_dir() => return Ok(None),
+            None => automatic_module(&entry, packages)?,
+        };
+
+        Ok(Some(ModuleReference {
+            descriptor,
+            location: path.to_path_buf(),
+        }))
+    }
+
+    /// Opens the classes of the module as a classpath entry.
+    pub fn open(&self) -> io::Result<ClassPathEntry> {
+        ClassPathEntry::open(&self.location)
+    }
+}
+
+/// The automatic module of a plain jar, like `java.lang.module.ModuleFinder`
+/// derives it: named by the `Automatic-Module-Name` of the manifest, or else
+/// by the name of the jar without its version, like `foo.bar` of version
+/// `1.2` for `foo-bar-1.2.jar`. Its packages are those of its classes with
+/// legal names, its main class the `Main-Class` of the manifest, and it
+/// provides the services of its `META-INF/services` files.
+fn automatic_module(
+    entry: &ClassPathEntry,
+    packages: BTreeSet<String>,
+) -> io::Result<ModuleDescriptor> {
+    let file_name = entry
+        .path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Unable to derive module descriptor for {}: {}",
+                file_name, message
+            ),
+        )
+    };
+
+    let manifest = entry
+        .read_resource(MANIFEST)?
+        .map(|manifest| String::from_utf8_lossy(&manifest).into_owned())
+        .unwrap_or_default();
+    let (name, version) = match manifest_attribute(&manifest, "Automatic-Module-Name") {
+        Some(name) if is_qualified_name(&name, '.') => (name, None),
+        Some(name) => {
+            return Err(invalid(format!(
+                "Automatic-Module-Name: {} is not a legal module name",
+                name
+            )))
+        }
+        None => {
+            let name = module_name_of_file(&file_name)
+                .ok_or_else(|| invalid("no module name in the file name".to_string()))?;
+            if !is_qualified_name(&name, '.') {
+                return Err(invalid(format!("{} is not a legal module name", name)));
+            }
+            (name, module_version_of_file(&file_name))
+        }
+    };
+
+    let packages = packages
+        .into_iter()
+        .filter(|package| is_qualified_name(package, '/'))
+        .collect();
+    let mut descriptor = ModuleDescriptor::automatic(&name, packages);
+    descriptor.version = version;
+    descriptor.main_class = manifest_attribute(&manifest, "Main-Class")
+        .map(|main_class| main_class.replace('.', "/"))
+        .filter(|main_class| descriptor.packages.contains(package_of(main_class)));
+
+    for resource in entry.resource_names()? {
+        let service = match resource.strip_prefix(SERVICES_DIRECTORY) {
+            Some(service) if is_qualified_name(service, '.') => service.replace('.', "/"),
+            _ => continue,
+        };
+        let providers = entry.read_resource(&resource)?.unwrap_or_default();
+        let providers: Vec<String> = parse_provider_configuration(&providers)
+            .into_iter()
+            .map(|provider| provider.replace('.', "/"))
+            .filter(|provider| descriptor.packages.contains(package_of(provider)))
+            .collect();
+        if !providers.is_empty() {
+            descriptor.provides.push(Provides { service, providers });
+        }
+    }
+    Ok(descriptor)
+}
+
+/// The value of an attribute of the main section of a manifest.
+fn manifest_attribute(manifest: &str, name: &str) -> Option<String> {
+    manifest
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+}
+
+/// The package of the class, by its internal name, empty for the unnamed
+/// package.
+fn package_of(class: &str) -> &str {
+    class.rfind('/').map_or("", |end| &class[..end])
+}
+
+/// Derives the name of an automatic module from the name of its jar, like
+/// `java.lang.module.ModuleFinder` does.
+pub fn module_name_of_file(file_name: &str) -> Option<String> {
+    let name = file_name.strip_suffix(".jar").unwrap_or(file_name);
+    let name = match version_start(name) {
+        Some(start) => &name[..start],
+        None => name,
+    };
+    let name: Vec<&str> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect();
+    match name.is_empty() {
+        true => None,
+        false => Some(name.join(".")),
+    }
+}
+
+/// Derives the version of an automatic module from the name of its jar,
+/// what follows the first hyphen followed by a digit, e.g. `1.2` of
+/// `foo-bar-1.2.jar`.
+pub fn module_version_of_file(file_name: &str) -> Option<String> {
+    let name = file_name.strip_suffix(".jar").unwrap_or(file_name);
+    version_start(name).map(|start| name[start + 1..].to_string())
+}
+
+/// Where the version starts in the name of a jar, at its hyphen.
+fn version_start(name: &str) -> Option<usize> {
+    let version = Regex::new(r"-(\d+(\.|$))").unwrap();
+    version.find(name).map(|found| found.start())
+}
+
+// =============================================================================
+// MODULE PATH
+// =============================================================================
+
+/// The modules of a module path, in the order found: each entry of the path
+/// a module, or a directory whose jars, jmods and module directories are
+/// modules, in name order. The first module of a name wins.
+#[derive(Debug, Default)]
+pub struct ModulePath {
+    modules: Vec<ModuleReference>,
+}
+
+impl ModulePath {
+    /// Parses a module path specification, using the platform's path
+    /// separator between the entries. Two modules of the same name in one
+    /// directory are an error, like on the reference JVM.
+    pub fn parse(specification: &str) -> io::Result<ModulePath> {
+        let mut module_path = ModulePath::default();
+        for path in std::env::split_paths(specification) {
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            if let Some(module) = ModuleReference::read(&path)? {
+                module_path.push(module);
+                continue;
+            }
+
+            let mut paths = Vec::new();
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+                let is_module = path.is_dir()
+                    || matches!(path.extension(), Some(x) if x == "jar" || x == "jmod");
+                if is_module {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            let mut names = BTreeSet::new();
+            for path in paths {
+                let module = match ModuleReference::read(&path)? {
+                    Some(module) => module,
+                    None => continue,
+                };
+                if !names.insert(module.descriptor.name.clone()) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Two versions of module {} found in {}",
+                            module.descriptor.name,
+                            path.parent().unwrap_or(&path).display()
+                        ),
+                    ));
+                }
+                module_path.push(module);
+            }
+        }
+
+        Ok(module_path)
+    }
+
+    /// Adds the module, unless one of its name was found already.
+    pub fn push(&mut self, module: ModuleReference) {
+        if self.find(&module.descriptor.name).is_none() {
+            self.modules.push(module);
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ModuleReference> {
+        self.modules
+            .iter()
+            .find(|module| module.descriptor.name == name)
+    }
+
+    pub fn modules(&self) -> &[ModuleReference] {
+        &self.modules
+    }
+}
+
+// ============================================================================
+// MODULE PATH TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod module_path_tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::{module_name_of_file, module_version_of_file, ModulePath, ModuleReference};
+
+    #[test]
+    fn test_automatic_module_names() {
+        let name = |file: &str| module_name_of_file(file);
+        assert_eq!(name("foo-bar-1.2.3.jar").as_deref(), Some("foo.bar"));
+        assert_eq!(name("commons_io-2.11.0.jar").as_deref(), Some("commons.io"));
+        assert_eq!(name("guava-31.1-jre.jar").as_deref(), Some("guava"));
+        assert_eq!(name("..a..b..jar").as_deref(), Some("a.b"));
+        assert_eq!(name("-1.0.jar"), None);
+
+        let version = |file: &str| module_version_of_file(file);
+        assert_eq!(version("foo-bar-1.2.3.jar").as_deref(), Some("1.2.3"));
+        assert_eq!(version("guava-31.1-jre.jar").as_deref(), Some("31.1-jre"));
+        assert_eq!(version("foo-bar.jar"), None);
+    }
+
+    fn jar(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("bvm-modulepath-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(name);
+        let mut jar = ZipWriter::new(fs::File::create(&path).unwrap());
+        for (file, contents) in files {
+            jar.start_file(*file, FileOptions::default()).unwrap();
+            jar.write_all(contents.as_bytes()).unwrap();
+        }
+        jar.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_automatic_modules() {
+        let path = jar(
+            "commons-text-1.10.0.jar",
+            &[
+                ("META-INF/MANIFEST.MF", "Main-Class: org.text.Main\n"),
+                (
+                    "META-INF/services/org.text.spi.Codec",
+                    "# codecs\norg.text.impl.Rot13\nmissing.Codec\n",
+                ),
+                ("org/text/Main.class", ""),
+                ("org/text/impl/Rot13.class", ""),
+                ("META-INF/versions/11/org/text/Main.class", ""),
+                ("native-libs/Loader.class", ""),
+            ],
+        );
+        let descriptor = ModuleReference::read(&path).unwrap().unwrap().descriptor;
+        assert_eq!(descriptor.name, "commons.text");
+        assert_eq!(descriptor.version.as_deref(), Some("1.10.0"));
+        assert!(descriptor.automatic);
+        let packages: Vec<_> = descriptor.packages.iter().map(String::as_str).collect();
+        assert_eq!(packages, ["org/text", "org/text/impl"]);
+        assert_eq!(descriptor.main_class.as_deref(), Some("org/text/Main"));
+        assert_eq!(descriptor.provides.len(), 1);
+        assert_eq!(descriptor.provides[0].service, "org/text/spi/Codec");
+        assert_eq!(descriptor.provides[0].providers, ["org/text/impl/Rot13"]);
+
+        let path = jar(
+            "named-2.0.jar",
+            &[(
+                "META-INF/MANIFEST.MF",
+                "Manifest-Version: 1.0\nAutomatic-Module-Name: org.named\n",
+            )],
+        );
+        let descriptor = ModuleReference::read(&path).unwrap().unwrap().descriptor;
+        assert_eq!(
+            (descriptor.name.as_str(), descriptor.version),
+            ("org.named", None)
+        );
+
+        let path = jar("native-int.jar", &[]);
+        assert_eq!(
+            ModuleReference::read(&path).unwrap_err().to_string(),
+            "Unable to derive module descriptor for native-int.jar: native.int is not a legal \
+             module name"
+        );
+        let path = jar(
+            "invalid.jar",
+            &[("META-INF/MANIFEST.MF", "Automatic-Module-Name: org.1st\n")],
+        );
+        assert!(ModuleReference::read(&path).is_err());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_module_directory() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/modules");
+        let module_path = ModulePath::parse(root.to_str().unwrap()).unwrap();
+        let names: Vec<_> = module_path
+            .modules()
+            .iter()
+            .map(|module| module.descriptor.name.as_str())
+            .collect();
+        assert_eq!(names, ["app", "greeter", "words"]);
+
+        let words = &module_path.find("words").unwrap().descriptor;
+        let packages: Vec<_> = words.packages.iter().map(String::as_str).collect();
+        assert_eq!(packages, ["words", "words/internal"]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+// =============================================================================
+// PROGRESS
+// =============================================================================
+
+/// How far a task over the classes of a classpath has gone, like listing
+/// the classes of `rt.jar`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// What the task does, e.g. `Listing classes`.
+    pub task: &'static str,
+    /// The classes processed, those failing included.
+    pub processed: usize,
+    /// The classes to process.
+    pub total: usize,
+    /// The classes failing so far.
+    pub errors: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// The time left, guessed from the pace so far, none before the first
+    /// class is processed.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.processed == 0 {
+            return None;
+        }
+        let left = self.total.saturating_sub(self.processed);
+        Some(self.elapsed.mul_f64(left as f64 / self.processed as f64))
+    }
+}
+
+/// Shown the progress of long tasks, e.g. drawing a progress bar, or
+/// forwarding it to the UI of an embedder. Closures taking a [Progress] are
+/// listeners.
+pub trait ProgressListener {
+    /// Called after every class processed, so implementations showing the
+    /// progress limit how often they do.
+    fn update(&mut self, progress: &Progress);
+
+    /// Called once the task is done, with the final counts.
+    fn finish(&mut self, progress: &Progress) {
+        self.update(progress);
+    }
+}
+
+impl<F: FnMut(&Progress)> ProgressListener for F {
+    fn update(&mut self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Counts the classes a task processes, updating the listener, if any.
+pub struct ProgressTracker<'a> {
+    listener: Option<&'a mut dyn ProgressListener>,
+    progress: Progress,
+    started: Instant,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(
+        task: &'static str,
+        total: usize,
+        listener: Option<&'a mut dyn ProgressListener>,
+    ) -> ProgressTracker<'a> {
+        ProgressTracker {
+            listener,
+            progress: Progress {
+                task,
+                processed: 0,
+                total,
+                errors: 0,
+                elapsed: Duration::ZERO,
+            },
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts a class processed, or failing.
+    pub fn advance(&mut self, failed: bool) {
+        self.progress.processed += 1;
+        self.progress.errors += failed as usize;
+        if let Some(listener) = &mut self.listener {
+            self.progress.elapsed = self.started.elapsed();
+            listener.update(&self.progress);
+        }
+    }
+
+    /// Ends the task, returning the final counts.
+    pub fn finish(mut self) -> Progress {
+        self.progress.elapsed = self.started.elapsed();
+        if let Some(listener) = &mut self.listener {
+            listener.finish(&self.progress);
+        }
+        self.progress
+    }
+}
+
+// ============================================================================
+// PROGRESS TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod progress_tests {
+    use std::time::Duration;
+
+    use super::{Progress, ProgressListener, ProgressTracker};
+
+    #[test]
+    fn test_eta() {
+        let mut progress = Progress {
+            task: "Listing classes",
+            processed: 0,
+            total: 100,
+            errors: 0,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(progress.eta(), None);
+        progress.processed = 25;
+        assert_eq!(progress.eta(), Some(Duration::from_secs(3)));
+        progress.processed = 100;
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_tracker() {
+        let mut updates = Vec::new();
+        let mut listener =
+            |progress: &Progress| updates.push((progress.processed, progress.errors));
+        let mut tracker = ProgressTracker::new(
+            "Listing classes",
+            3,
+            Some(&mut listener as &mut dyn ProgressListener),
+        );
+        tracker.advance(false);
+        tracker.advance(true);
+        tracker.advance(false);
+        let progress = tracker.finish();
+        assert_eq!(
+            (progress.processed, progress.total, progress.errors),
+            (3, 3, 1)
+        );
+        assert_eq!(updates, vec![(1, 0), (2, 1), (3, 1), (3, 1)]);
+    }
+}
@@ -0,0 +1,837 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::packaging::source::ClassSource;
+
+/// The bytes fetched by a request, and kept together in memory.
+const BLOCK_SIZE: u64 = 256 * 1024;
+
+/// The blocks kept in memory, all dropped once exceeded.
+const MAX_BLOCKS: usize = 64;
+
+const MAX_REDIRECTS: usize = 5;
+
+/// The capacity reserved for an entry of a jar, larger ones growing as they
+/// are read rather than trusting the size the jar declares.
+const MAX_ENTRY_CAPACITY: u64 = 16 * 1024 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether a classpath entry is the URL of a remote jar.
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+// =============================================================================
+// RANGE READERS
+// =============================================================================
+
+/// A remote file read by ranges of bytes, like the range requests of HTTP.
+pub trait RangeReader: Send {
+    /// The length of the file in bytes.
+    fn size(&self) -> u64;
+
+    /// Identifies the version of the file, like the `ETag` of HTTP, so that
+    /// the entries of another version are not read from the disk cache.
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Reads up to `length` bytes from `start`, fewer only at the end of the
+    /// file.
+    fn read_range(&mut self, start: u64, length: u64) -> io::Result<Vec<u8>>;
+}
+
+/// A file served over HTTP/1.1, or over HTTPS with the `https` feature.
+/// Servers ignoring the ranges are supported when they send the length of
+/// the file, though it is then downloaded from its start by every request.
+///
+/// Other clients, e.g. one with its own TLS configuration, can be plugged
+/// in as a [RangeReader] with [RemoteJar::with_reader].
+pub struct HttpFile {
+    url: String,
+    size: u64,
+    version: Option<String>,
+}
+
+impl HttpFile {
+    pub fn open(url: &str) -> io::Result<HttpFile> {
+        let response = get(url, 0, 0)?;
+        let size = match response.status {
+            206 => response.content_range().map(|(_, _, size)| size),
+            200 => response.content_length(),
+            status => return Err(status_error(url, status)),
+        };
+        let size = size.ok_or_else(|| invalid_data(format!("{} has no length", url)))?;
+        let version = response
+            .header("etag")
+            .or_else(|| response.header("last-modified"))
+            .map(str::to_string);
+        Ok(HttpFile {
+            url: url.to_string(),
+            size,
+            version,
+        })
+    }
+}
+
+impl RangeReader for HttpFile {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn read_range(&mut self, start: u64, length: u64) -> io::Result<Vec<u8>> {
+        let end = (start + length).min(self.size);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        let mut response = get(&self.url, start, end - 1)?;
+        match response.status {
+            206 if response.content_range() == Some((start, end - 1, self.size))
+                && response.body.len() as u64 == end - start =>
+            {
+                Ok(response.body)
+            }
+            206 => Err(invalid_data(format!(
+                "{} answered another range than bytes {}-{}",
+                self.url,
+                start,
+                end - 1
+            ))),
+            200 if response.body.len() as u64 >= end => {
+                response.body.truncate(end as usize);
+                Ok(response.body.split_off(start as usize))
+            }
+            status => Err(status_error(&self.url, status)),
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    /// The headers by lowercase name.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        self.header("content-length")
+            .and_then(|length| length.parse().ok())
+    }
+
+    /// The first and last bytes of a partial content, and the length of the
+    /// whole file, from a `Content-Range: bytes first-last/length`.
+    fn content_range(&self) -> Option<(u64, u64, u64)> {
+        let range = self.header("content-range")?.strip_prefix("bytes ")?;
+        let (range, size) = range.split_once('/')?;
+        let (first, last) = range.split_once('-')?;
+        Some((
+            first.trim().parse().ok()?,
+            last.trim().parse().ok()?,
+            size.trim().parse().ok()?,
+        ))
+    }
+}
+
+/// Requests the bytes from `first` to `last` included, following the
+/// redirects but never from `https://` to `http://`.
+fn get(url: &str, first: u64, last: u64) -> io::Result<Response> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = request(&url, first, last)?;
+        let location = match response.status {
+            301 | 302 | 303 | 307 | 308 => response.header("location"),
+            _ => return Ok(response),
+        };
+        let location = location.ok_or_else(|| invalid_data(format!("{} has no location", url)))?;
+        url = redirect(&url, location)?;
+    }
+    Err(invalid_data(format!("{} redirects too many times", url)))
+}
+
+/// The URL redirected to from the URL, refusing to leave `https://` for
+/// `http://`.
+fn redirect(url: &str, location: &str) -> io::Result<String> {
+    let target = resolve(url, location)?;
+    if url.starts_with("https://") && !target.starts_with("https://") {
+        let message = format!("{} redirects to {} without TLS", url, target);
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+    }
+    Ok(target)
+}
+
+/// The URL of the `Location` of a redirect from the base URL, which may be
+/// relative to it, keeping the scheme of the base unless it names one.
+fn resolve(base: &str, location: &str) -> io::Result<String> {
+    if location.contains("://") {
+        return Ok(location.to_string());
+    }
+    let (scheme, authority, path) = split_url(base)?;
+    if let Some(location) = location.strip_prefix("//") {
+        return Ok(format!("{}://{}", scheme, location));
+    }
+    let path = match location.strip_prefix('/') {
+        Some(_) => location.to_string(),
+        None => {
+            let path = path.split(['?', '#']).next().unwrap_or("/");
+            let directory = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+            format!("{}{}", directory, location)
+        }
+    };
+    Ok(format!(
+        "{}://{}{}",
+        scheme,
+        authority,
+        remove_dot_segments(&path)
+    ))
+}
+
+/// Removes the `.` and `..` segments of an absolute path, see RFC 3986.
+fn remove_dot_segments(path: &str) -> String {
+    let (path, query) = match path.find(['?', '#']) {
+        Some(index) => path.split_at(index),
+        None => (path, ""),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    let mut names = path.split('/').skip(1).peekable();
+    while let Some(name) = names.next() {
+        let last = names.peek().is_none();
+        match name {
+            ".." => {
+                segments.pop();
+                if last {
+                    segments.push("");
+                }
+            }
+            "." if last => segments.push(""),
+            "." => {}
+            _ => segments.push(name),
+        }
+    }
+    format!("/{}{}", segments.join("/"), query)
+}
+
+/// A connection to a server, plain or encrypted.
+trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+fn connect(scheme: &str, authority: &str) -> io::Result<Box<dyn Connection>> {
+    let port = match scheme {
+        "https" => 443,
+        _ => 80,
+    };
+    // The port follows the last colon, unless it is within an IPv6 address
+    let (host, address) = match authority.rfind(':') {
+        Some(colon) if !authority[colon..].contains(']') => {
+            (&authority[..colon], authority.to_string())
+        }
+        _ => (authority, format!("{}:{}", authority, port)),
+    };
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    match scheme {
+        "https" => tls::connect(host, stream),
+        _ => Ok(Box::new(stream)),
+    }
+}
+
+fn request(url: &str, first: u64, last: u64) -> io::Result<Response> {
+    let (scheme, authority, path) = split_url(url)?;
+    let mut stream = connect(scheme, authority)?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nRange: bytes={}-{}\r\nUser-Agent: bvm\r\n\
+         Accept-Encoding: identity\r\nConnection: close\r\n\r\n",
+        path, authority, first, last
+    )?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid_data(format!("{} answered {:?}", url, line.trim_end())))?;
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    // At most the bytes up to the last one requested are read, whatever the
+    // lengths the server announces, the connection being closed after
+    let limit = last + 1;
+    if response
+        .header("transfer-encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        while (response.body.len() as u64) < limit {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim_end().split(';').next().unwrap_or("");
+            let size = u64::from_str_radix(size, 16)
+                .map_err(|_| invalid_data(format!("{} sent a malformed chunk", url)))?;
+            if size == 0 {
+                break;
+            }
+            let size = size.min(limit - response.body.len() as u64);
+            read_exactly(&mut reader, size, &mut response.body)?;
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(length) = response.content_length() {
+        read_exactly(&mut reader, length.min(limit), &mut response.body)?;
+    } else {
+        match reader.take(limit).read_to_end(&mut response.body) {
+            // Servers closing the connection without a TLS close_notify
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {}
+            result => {
+                result?;
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Appends `length` bytes read from the reader to the buffer, which grows
+/// with the bytes actually received.
+fn read_exactly(reader: &mut impl Read, length: u64, buffer: &mut Vec<u8>) -> io::Result<()> {
+    let read = reader.take(length).read_to_end(buffer)?;
+    if (read as u64) < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// The scheme, the authority, host and port, and the path of an `http://`
+/// or `https://` URL.
+fn split_url(url: &str) -> io::Result<(&str, &str, &str)> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .filter(|(scheme, _)| matches!(*scheme, "http" | "https"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, url.to_string()))?;
+    Ok(match rest.find('/') {
+        Some(slash) => (scheme, &rest[..slash], &rest[slash..]),
+        None => (scheme, rest, "/"),
+    })
+}
+
+fn status_error(url: &str, status: u16) -> io::Error {
+    io::Error::other(format!("{} answered HTTP {}", url, status))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// =============================================================================
+// TLS
+// =============================================================================
+
+#[cfg(feature = "https")]
+mod tls {
+    use std::convert::TryFrom;
+    use std::io;
+    use std::net::TcpStream;
+    use std::sync::{Arc, OnceLock};
+
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+    use super::Connection;
+
+    /// The client configuration, trusting the Mozilla roots.
+    fn config() -> io::Result<Arc<ClientConfig>> {
+        static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        if let Some(config) = CONFIG.get() {
+            return Ok(config.clone());
+        }
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(CONFIG.get_or_init(|| Arc::new(config)).clone())
+    }
+
+    /// Encrypts the connection to the host.
+    pub(super) fn connect(host: &str, stream: TcpStream) -> io::Result<Box<dyn Connection>> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let name = ServerName::try_from(host.to_string())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        let connection = ClientConnection::new(config()?, name).map_err(io::Error::other)?;
+        Ok(Box::new(StreamOwned::new(connection, stream)))
+    }
+}
+
+#[cfg(not(feature = "https"))]
+mod tls {
+    use std::io;
+    use std::net::TcpStream;
+
+    use super::Connection;
+
+    pub(super) fn connect(_: &str, _: TcpStream) -> io::Result<Box<dyn Connection>> {
+        let message = "https:// needs bvm built with the `https` feature, or a RangeReader \
+                       passed to RemoteJar::with_reader";
+        Err(io::Error::new(io::ErrorKind::Unsupported, message))
+    }
+}
+
+// =============================================================================
+// LAZY FILE
+// =============================================================================
+
+/// A [RangeReader] read and seeked like a local file, fetching the blocks it
+/// is read from on demand.
+struct LazyFile {
+    reader: Box<dyn RangeReader>,
+    position: u64,
+    blocks: HashMap<u64, Vec<u8>>,
+}
+
+impl Read for LazyFile {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if buffer.is_empty() || self.position >= self.reader.size() {
+            return Ok(0);
+        }
+        let index = self.position / BLOCK_SIZE;
+        if !self.blocks.contains_key(&index) {
+            if self.blocks.len() >= MAX_BLOCKS {
+                self.blocks.clear();
+            }
+            let block = self.reader.read_range(index * BLOCK_SIZE, BLOCK_SIZE)?;
+            self.blocks.insert(index, block);
+        }
+        let block = &self.blocks[&index];
+        let offset = (self.position - index * BLOCK_SIZE) as usize;
+        let length = buffer.len().min(block.len().saturating_sub(offset));
+        buffer[..length].copy_from_slice(&block[offset..offset + length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for LazyFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.reader.size().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.position)
+    }
+}
+
+// =============================================================================
+// REMOTE JAR
+// =============================================================================
+
+/// A jar on a server, the entry of `--classpath http://host/app.jar`. Only
+/// its central directory is fetched when opened, each entry being downloaded
+/// when first read and cached on disk, for the following runs.
+pub struct RemoteJar {
+    location: PathBuf,
+    archive: Mutex<ZipArchive<LazyFile>>,
+    /// The directory of the cached entries of this version of the jar.
+    cache: Option<PathBuf>,
+}
+
+impl RemoteJar {
+    /// Opens the jar at the `http://` or `https://` URL, caching its entries in the
+    /// `bvm/remote` directory of the user's cache.
+    pub fn open(url: &str) -> io::Result<RemoteJar> {
+        let reader = Box::new(HttpFile::open(url)?);
+        RemoteJar::with_reader(url, reader, default_cache_dir())
+    }
+
+    /// Opens the jar read by the reader, e.g. a client of HTTPS, caching its
+    /// entries in the directory if any.
+    pub fn with_reader(
+        location: &str,
+        reader: Box<dyn RangeReader>,
+        cache_dir: Option<PathBuf>,
+    ) -> io::Result<RemoteJar> {
+        let cache = cache_dir.map(|directory| {
+            let mut key = Sha256::new();
+            key.update(location.as_bytes());
+            key.update(reader.size().to_be_bytes());
+            key.update(reader.version().unwrap_or("").as_bytes());
+            let key: String = key
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            directory.join(key)
+        });
+        let file = LazyFile {
+            reader,
+            position: 0,
+            blocks: HashMap::new(),
+        };
+        Ok(RemoteJar {
+            location: PathBuf::from(location),
+            archive: Mutex::new(ZipArchive::new(file)?),
+            cache,
+        })
+    }
+
+    fn download(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let mut bytes = Vec::with_capacity(file.size().min(MAX_ENTRY_CAPACITY) as usize);
+        file.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+}
+
+impl ClassSource for RemoteJar {
+    fn location(&self) -> &Path {
+        &self.location
+    }
+
+    fn resource_names(&self) -> io::Result<Vec<String>> {
+        let archive = self.archive.lock().unwrap();
+        Ok(archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(String::from)
+            .collect())
+    }
+
+    fn read_resource(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let cached = self.cache.as_ref().map(|cache| cache.join(name));
+        if let Some(bytes) = cached.as_ref().and_then(|path| fs::read(path).ok()) {
+            return Ok(Some(bytes));
+        }
+        let bytes = self.download(name)?;
+        if let (Some(path), Some(bytes)) = (cached, &bytes) {
+            if let Err(error) = write_atomically(&path, bytes) {
+                tracing::warn!(path = %path.display(), %error, "cannot cache remote entry");
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// `$XDG_CACHE_HOME/bvm/remote`, or `~/.cache/bvm/remote`.
+fn default_cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache.join("bvm").join("remote"))
+}
+
+/// Writes the file through a temporary one, so that concurrent runs never
+/// read it partially written.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}.part", std::process::id()));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, path)
+}
+
+// ============================================================================
+// REMOTE JAR TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod remote_tests {
+    use std::io::{BufRead, BufReader, Cursor, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::{fs, io, thread};
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::{redirect, resolve, HttpFile, RangeReader, RemoteJar};
+    use crate::packaging::source::ClassSource;
+
+    fn jar() -> Vec<u8> {
+        let mut jar = ZipWriter::new(Cursor::new(Vec::new()));
+        jar.add_directory("com/example/", FileOptions::default())
+            .unwrap();
+        jar.start_file("com/example/Main.class", FileOptions::default())
+            .unwrap();
+        jar.write_all(&[0xCA; 1000]).unwrap();
+        jar.start_file("config.properties", FileOptions::default())
+            .unwrap();
+        jar.write_all(b"remote=true").unwrap();
+        jar.finish().unwrap().into_inner()
+    }
+
+    struct Served {
+        bytes: Arc<Vec<u8>>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl RangeReader for Served {
+        fn size(&self) -> u64 {
+            self.bytes.len() as u64
+        }
+
+        fn read_range(&mut self, start: u64, length: u64) -> io::Result<Vec<u8>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let end = (start + length).min(self.size());
+            Ok(self.bytes[start as usize..end as usize].to_vec())
+        }
+    }
+
+    #[test]
+    fn test_entries_cached_on_disk() {
+        let cache = std::env::temp_dir().join(format!("bvm-remote-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache);
+        let bytes = Arc::new(jar());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let open = || {
+            let reader = Box::new(Served {
+                bytes: bytes.clone(),
+                requests: requests.clone(),
+            });
+            RemoteJar::with_reader("memory:app.jar", reader, Some(cache.clone())).unwrap()
+        };
+
+        let jar = open();
+        let mut names = jar.resource_names().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["com/example/Main.class", "config.properties"]);
+        assert_eq!(
+            jar.read_resource("com/example/Main.class").unwrap(),
+            Some(vec![0xCA; 1000])
+        );
+        assert_eq!(jar.read_resource("missing.txt").unwrap(), None);
+
+        // Reopened, the entry is read from the cache
+        let jar = open();
+        let opened = requests.load(Ordering::SeqCst);
+        assert_eq!(
+            jar.read_resource("com/example/Main.class").unwrap(),
+            Some(vec![0xCA; 1000])
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), opened);
+        let _ = fs::remove_dir_all(&cache);
+    }
+
+    #[test]
+    fn test_http_range_requests() {
+        let bytes = jar();
+        let bytes_len = bytes.len();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                let mut path = String::new();
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(request) = line.strip_prefix("GET ") {
+                        path = request.split(' ').next().unwrap().to_string();
+                    }
+                    if let Some(bytes) = line.strip_prefix("Range: bytes=") {
+                        let (first, last) = bytes.split_once('-').unwrap();
+                        range = Some((first.parse().unwrap(), last.parse::<usize>().unwrap()));
+                    }
+                }
+                if path != "/app.jar" {
+                    write!(stream, "HTTP/1.1 302 Found\r\nLocation: ../app.jar\r\n\r\n").unwrap();
+                    continue;
+                }
+                let (first, last) = range.unwrap();
+                let last = last.min(bytes.len() - 1);
+                let body = &bytes[first..=last];
+                write!(
+                    stream,
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n\
+                     Content-Length: {}\r\nETag: \"1\"\r\n\r\n",
+                    first,
+                    last,
+                    bytes.len(),
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        // Redirected to /app.jar
+        let url = format!("http://127.0.0.1:{}/old/app.jar", port);
+        let file = HttpFile::open(&url).unwrap();
+        assert_eq!(file.size(), bytes_len as u64);
+        assert_eq!(file.version(), Some("\"1\""));
+        let jar = RemoteJar::with_reader(&url, Box::new(file), None).unwrap();
+        assert_eq!(
+            jar.read_resource("config.properties").unwrap(),
+            Some(b"remote=true".to_vec())
+        );
+    }
+
+    /// Serves the raw responses the function gives for the requested
+    /// ranges, returning the port.
+    fn serve(respond: impl Fn(usize, usize) -> Vec<u8> + Send + 'static) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut range = (0, 0);
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(bytes) = line.strip_prefix("Range: bytes=") {
+                        let (first, last) = bytes.split_once('-').unwrap();
+                        range = (first.parse().unwrap(), last.parse().unwrap());
+                    }
+                }
+                let _ = stream.write_all(&respond(range.0, range.1));
+            }
+        });
+        port
+    }
+
+    fn http_file(port: u16, size: u64) -> HttpFile {
+        HttpFile {
+            url: format!("http://127.0.0.1:{}/app.jar", port),
+            size,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn test_announced_lengths_not_trusted() {
+        let port = serve(|_, _| {
+            let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 1099511627776\r\n\r\n".to_vec();
+            response.extend_from_slice(b"0123456789");
+            response
+        });
+        // Only the bytes up to the last requested one are read
+        let mut file = http_file(port, 1 << 40);
+        assert_eq!(file.read_range(2, 4).unwrap(), b"2345");
+        assert_eq!(
+            file.read_range(0, 20).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let port = serve(|_, _| {
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffff\r\n0123456789"
+                .to_vec()
+        });
+        let mut file = http_file(port, 10);
+        assert_eq!(file.read_range(0, 6).unwrap(), b"012345");
+    }
+
+    #[test]
+    fn test_content_range_checked() {
+        let port = serve(|first, last| {
+            let mut response = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/10\r\n\r\n",
+                first + 1,
+                last + 1
+            )
+            .into_bytes();
+            response.extend_from_slice(&b"0123456789"[first + 1..=last.min(8) + 1]);
+            response
+        });
+        let mut file = http_file(port, 10);
+        let error = file.read_range(2, 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("another range than bytes 2-5"));
+    }
+
+    #[test]
+    fn test_resolve_redirects() {
+        let base = "https://example.com/lib/v1/app.jar?token=1";
+        let resolved = |location| resolve(base, location).unwrap();
+
+        assert_eq!(
+            resolved("http://mirror.org/app.jar"),
+            "http://mirror.org/app.jar"
+        );
+        assert_eq!(
+            resolved("//cdn.example.com/app.jar"),
+            "https://cdn.example.com/app.jar"
+        );
+        assert_eq!(resolved("/app.jar"), "https://example.com/app.jar");
+        assert_eq!(
+            resolved("app-2.jar"),
+            "https://example.com/lib/v1/app-2.jar"
+        );
+        assert_eq!(
+            resolved("../v2/./app.jar?a=b"),
+            "https://example.com/lib/v2/app.jar?a=b"
+        );
+        assert_eq!(resolved("../../../app.jar"), "https://example.com/app.jar");
+
+        assert_eq!(
+            redirect(base, "//cdn.example.com/app.jar").unwrap(),
+            "https://cdn.example.com/app.jar"
+        );
+        assert_eq!(
+            redirect(base, "http://mirror.org/app.jar")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            redirect("http://example.com/app.jar", "https://mirror.org/app.jar").unwrap(),
+            "https://mirror.org/app.jar"
+        );
+    }
+}
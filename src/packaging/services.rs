@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::packaging::classpath::ClassPath;
+
+/// Directory holding the provider-configuration files used by `ServiceLoader`.
+pub static SERVICES_DIRECTORY: &str = "META-INF/services/";
+
+/// Parses a provider-configuration file as described by `ServiceLoader`: one
+/// fully qualified provider class name per line, with `#` starting a comment
+/// and surrounding whitespace ignored.
+pub fn parse_provider_configuration(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|line| match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        })
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The service providers declared on a [ClassPath], keyed by the binary name
+/// of the service interface (e.g. `java.sql.Driver`).
+#[derive(Debug, Default)]
+pub struct ServiceProviders {
+    providers: BTreeMap<String, Vec<String>>,
+}
+
+impl ServiceProviders {
+    /// Scans every entry of the classpath for provider-configuration files.
+    pub fn scan(class_path: &ClassPath) -> io::Result<ServiceProviders> {
+        let mut services = ServiceProviders::default();
+        for entry in class_path.entries() {
+            for name in entry.resource_names()? {
+                let service = match name.strip_prefix(SERVICES_DIRECTORY) {
+                    Some(service) if !service.is_empty() && !service.contains('/') => service,
+                    _ => continue,
+                };
+
+                if let Some(bytes) = entry.read_resource(&name)? {
+                    for provider in parse_provider_configuration(&bytes) {
+                        services.add(service, provider);
+                    }
+                }
+            }
+        }
+
+        Ok(services)
+    }
+
+    /// Registers a provider, ignoring the ones already known for the service
+    /// so that each provider is only enumerated once, in classpath order.
+    pub fn add(&mut self, service: &str, provider: String) {
+        let providers = self.providers.entry(service.to_string()).or_default();
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    }
+
+    /// The providers declared for the service, in classpath order.
+    pub fn providers(&self, service: &str) -> &[String] {
+        self.providers
+            .get(service)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Binary names of every service with at least one declared provider.
+    pub fn services(&self) -> impl Iterator<Item = &str> {
+        self.providers.keys().map(String::as_str)
+    }
+}
+
+// ============================================================================
+// SERVICES TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod services_tests {
+    use super::parse_provider_configuration;
+
+    #[test]
+    fn test_parse_provider_configuration() {
+        let bytes = b"# Drivers\ncom.example.Driver  # primary\n\n  com.example.Fallback\n";
+        let providers = parse_provider_configuration(bytes);
+
+        assert_eq!(
+            providers,
+            vec!["com.example.Driver", "com.example.Fallback"]
+        );
+    }
+}
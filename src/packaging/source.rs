@@ -0,0 +1,341 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::{pin, Pin};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+// =============================================================================
+// CLASS SOURCES
+// =============================================================================
+
+/// A backend serving the classes and resources of a classpath entry from
+/// anywhere but the file system, like a repository over HTTP or a bucket of
+/// S3, pushed to the classpath as [ClassPathEntry::Source].
+///
+/// Sources are read by the class loaders, which may run on several threads.
+///
+/// [ClassPathEntry::Source]: crate::packaging::classpath::ClassPathEntry::Source
+pub trait ClassSource: Send + Sync {
+    /// Where the classes come from, e.g. the URL of the repository, shown
+    /// where the path of a jar or directory would be.
+    fn location(&self) -> &Path;
+
+    /// `/` separated names of every resource the source provides, class
+    /// files included, in any order.
+    fn resource_names(&self) -> io::Result<Vec<String>>;
+
+    /// Reads the bytes of a `/` separated resource, if the source provides
+    /// it. Names never start with `/` nor contain `..`.
+    fn read_resource(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// A future that can be sent to the thread of a runtime.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// A [ClassSource] whose reads are futures, for backends written against
+/// an async runtime, like most HTTP or S3 clients. The futures own what they
+/// need, so that they can be spawned, e.g. an `Arc` of the HTTP client.
+///
+/// The class loaders read through [BlockingSource]. While one of its reads
+/// is pending, the VM runs its other started threads rather than blocking,
+/// see [poll_reads].
+pub trait AsyncClassSource: Send + Sync + 'static {
+    /// See [ClassSource::location].
+    fn location(&self) -> &Path;
+
+    /// See [ClassSource::resource_names].
+    fn resource_names(&self) -> BoxFuture<io::Result<Vec<String>>>;
+
+    /// See [ClassSource::read_resource].
+    fn read_resource(&self, name: &str) -> BoxFuture<io::Result<Option<Vec<u8>>>>;
+}
+
+/// Runs a future to completion on a runtime, e.g. with a tokio runtime
+/// `move |future| { handle.spawn(future); }`.
+pub type Spawner = Box<dyn Fn(BoxFuture<()>) + Send + Sync>;
+
+/// The synchronous [ClassSource] reading an [AsyncClassSource], its futures
+/// either polled on the loading thread, or spawned on a runtime providing
+/// what they need to make progress, like the I/O driver of tokio. The
+/// loading thread blocks until the read completes, unless it is within
+/// [poll_reads].
+pub struct BlockingSource<S> {
+    source: S,
+    spawn: Option<Spawner>,
+    /// The reads left pending by [poll_reads], by resource name, resumed by
+    /// the next read of the resource.
+    pending: Mutex<HashMap<String, Read<Option<Vec<u8>>>>>,
+}
+
+/// A read of an [AsyncClassSource] in flight.
+enum Read<T> {
+    /// Polled on the loading thread.
+    Polled(BoxFuture<io::Result<T>>),
+    /// Spawned on a runtime, sending its result once complete.
+    Spawned(mpsc::Receiver<io::Result<T>>),
+}
+
+impl<T> Read<T> {
+    /// Polls the read once, without blocking.
+    fn poll(&mut self) -> Poll<io::Result<T>> {
+        match self {
+            Read::Polled(future) => {
+                let waker = Waker::from(Arc::new(Unpark(thread::current())));
+                future.as_mut().poll(&mut Context::from_waker(&waker))
+            }
+            Read::Spawned(receiver) => match receiver.try_recv() {
+                Ok(result) => Poll::Ready(result),
+                Err(mpsc::TryRecvError::Empty) => Poll::Pending,
+                Err(mpsc::TryRecvError::Disconnected) => Poll::Ready(Err(dropped())),
+            },
+        }
+    }
+
+    /// Blocks until the read completes.
+    fn wait(self) -> io::Result<T> {
+        match self {
+            Read::Polled(future) => block_on(future),
+            Read::Spawned(receiver) => receiver.recv().unwrap_or_else(|_| Err(dropped())),
+        }
+    }
+}
+
+fn dropped() -> io::Error {
+    io::Error::other("the runtime dropped the read")
+}
+
+impl<S: AsyncClassSource> BlockingSource<S> {
+    /// Polls the futures of the source on the loading thread, for sources
+    /// needing no runtime.
+    pub fn new(source: S) -> Self {
+        BlockingSource {
+            source,
+            spawn: None,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Spawns the futures of the source with the spawner.
+    pub fn with_spawner<F>(source: S, spawn: F) -> Self
+    where
+        F: Fn(BoxFuture<()>) + Send + Sync + 'static,
+    {
+        BlockingSource {
+            source,
+            spawn: Some(Box::new(spawn)),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts the read, spawning its future on the runtime if any.
+    fn start<T: Send + 'static>(&self, future: BoxFuture<io::Result<T>>) -> Read<T> {
+        let spawn = match &self.spawn {
+            Some(spawn) => spawn,
+            None => return Read::Polled(future),
+        };
+        let (sender, receiver) = mpsc::sync_channel(1);
+        spawn(Box::pin(async move {
+            let _ = sender.send(future.await);
+        }));
+        Read::Spawned(receiver)
+    }
+}
+
+impl<S: AsyncClassSource> ClassSource for BlockingSource<S> {
+    fn location(&self) -> &Path {
+        self.source.location()
+    }
+
+    fn resource_names(&self) -> io::Result<Vec<String>> {
+        self.start(self.source.resource_names()).wait()
+    }
+
+    fn read_resource(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let pending = self.pending.lock().unwrap().remove(name);
+        let mut read = pending.unwrap_or_else(|| self.start(self.source.read_resource(name)));
+        if !POLLING.get() {
+            return read.wait();
+        }
+        match read.poll() {
+            Poll::Ready(result) => result,
+            Poll::Pending => {
+                self.pending.lock().unwrap().insert(name.to_string(), read);
+                PENDING.set(true);
+                let message = format!("the read of {} is pending", name);
+                Err(io::Error::new(io::ErrorKind::WouldBlock, message))
+            }
+        }
+    }
+}
+
+thread_local! {
+    /// Whether the reads of the current thread are within [poll_reads].
+    static POLLING: Cell<bool> = const { Cell::new(false) };
+    /// Whether one of them was left pending.
+    static PENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs the function, e.g. the loading of a class, with the reads of the
+/// [BlockingSource]s failing with [io::ErrorKind::WouldBlock] rather than
+/// blocking. `Poll::Pending` means a read was left pending, the function
+/// to be called again once the caller did something else, the read then
+/// resuming where it was.
+pub fn poll_reads<T>(function: impl FnOnce() -> T) -> Poll<T> {
+    let polling = POLLING.replace(true);
+    let pending = PENDING.replace(false);
+    let result = function();
+    POLLING.set(polling);
+    match PENDING.replace(pending) {
+        true => Poll::Pending,
+        false => Poll::Ready(result),
+    }
+}
+
+/// Wakes a thread parked until a future can make progress.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls the future on the current thread, parking it while pending.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+// ============================================================================
+// CLASS SOURCE TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod source_tests {
+    use std::collections::BTreeMap;
+    use std::future::Future;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::thread;
+
+    use super::{block_on, poll_reads, AsyncClassSource, BlockingSource, BoxFuture};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+    use crate::packaging::source::ClassSource;
+
+    /// Pending once, like a read waiting for the network.
+    struct Delayed<T>(Option<T>, bool);
+
+    impl<T: Unpin> Future for Delayed<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<T> {
+            if !self.1 {
+                self.1 = true;
+                context.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(self.0.take().unwrap())
+        }
+    }
+
+    struct Remote {
+        location: PathBuf,
+        resources: Arc<BTreeMap<String, Vec<u8>>>,
+    }
+
+    impl AsyncClassSource for Remote {
+        fn location(&self) -> &Path {
+            &self.location
+        }
+
+        fn resource_names(&self) -> BoxFuture<io::Result<Vec<String>>> {
+            let names = self.resources.keys().cloned().collect();
+            Box::pin(Delayed(Some(Ok(names)), false))
+        }
+
+        fn read_resource(&self, name: &str) -> BoxFuture<io::Result<Option<Vec<u8>>>> {
+            let resources = self.resources.clone();
+            let name = name.to_string();
+            Box::pin(async move {
+                Delayed(Some(()), false).await;
+                Ok(resources.get(&name).cloned())
+            })
+        }
+    }
+
+    fn remote() -> Remote {
+        let resources = [
+            ("com/example/Main.class", b"main".to_vec()),
+            ("META-INF/MANIFEST.MF", b"manifest".to_vec()),
+        ];
+        Remote {
+            location: PathBuf::from("https://repository.example.com/app.jar"),
+            resources: Arc::new(
+                resources
+                    .iter()
+                    .map(|(name, bytes)| (name.to_string(), bytes.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_blocking_source() {
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::Source(Box::new(BlockingSource::new(
+            remote(),
+        ))));
+        // A runtime of a thread per read
+        class_path.push(ClassPathEntry::Source(Box::new(
+            BlockingSource::with_spawner(remote(), |future| {
+                thread::spawn(move || block_on(future));
+            }),
+        )));
+
+        for entry in class_path.entries() {
+            assert_eq!(
+                entry.path(),
+                Path::new("https://repository.example.com/app.jar")
+            );
+            assert_eq!(entry.class_names().unwrap(), vec!["com/example/Main"]);
+            assert_eq!(
+                entry.read_class("com/example/Main").unwrap().unwrap(),
+                b"main"
+            );
+        }
+        assert_eq!(
+            class_path.find_class("com/example/Main").unwrap(),
+            Some((0, b"main".to_vec()))
+        );
+        assert_eq!(class_path.resources("META-INF/MANIFEST.MF").len(), 2);
+        assert_eq!(class_path.resource("../secret"), None);
+    }
+
+    #[test]
+    fn test_pending_reads_resumed() {
+        let source = BlockingSource::new(remote());
+        let read = || source.read_resource("com/example/Main.class");
+
+        assert!(poll_reads(read).is_pending());
+        match poll_reads(read) {
+            Poll::Ready(bytes) => assert_eq!(bytes.unwrap().unwrap(), b"main"),
+            Poll::Pending => panic!("the read was not resumed"),
+        }
+        // Outside of `poll_reads`, the reads block
+        assert_eq!(read().unwrap().unwrap(), b"main");
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+use crate::packaging::filter::ClassFilter;
+use crate::packaging::jar::is_class_file;
+
+// =============================================================================
+// CLASS CHANGES
+// =============================================================================
+
+/// A class file of a classpath entry added, rewritten or deleted since the
+/// last poll of a [ClassPathWatcher].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassChange {
+    /// The index of the entry in [ClassPath::entries].
+    pub entry: usize,
+    /// The internal name of the class, e.g. `com/example/Main`.
+    pub class: String,
+    pub removed: bool,
+}
+
+/// What tells a file rewritten apart, without reading it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    length: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> io::Result<Option<Stamp>> {
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(Some(Stamp {
+                modified: metadata.modified().ok(),
+                length: metadata.len(),
+            })),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// The state of an entry at the last poll.
+enum Snapshot {
+    /// The stamps of the class files of a directory, by class name.
+    Directory(BTreeMap<String, Stamp>),
+    /// The stamp of a jar or jmod, whose classes all change with it, and
+    /// their names.
+    Archive(Option<Stamp>, Vec<String>),
+    /// Class sources, and the entries nested in jars, are not watched.
+    Unwatched,
+}
+
+// =============================================================================
+// CLASSPATH WATCHER
+// =============================================================================
+
+/// Watches the directories, jars and jmods of a classpath for the class
+/// files changed since, so that tools can update what they computed from the
+/// classes instead of reading the whole classpath again.
+///
+/// The watcher of the platform, inotify, FSEvents or ReadDirectoryChangesW,
+/// tells when files change under the entries, and comparing the stamps of
+/// the class files then tells which. Where the platform cannot watch an
+/// entry, e.g. a directory missing when the watch starts, the watcher polls
+/// the stamps every interval instead. A rewritten jar reports every class it
+/// holds, and is opened again.
+pub struct ClassPathWatcher {
+    snapshots: Vec<Snapshot>,
+    notifier: Option<Notifier>,
+}
+
+/// The watcher of the platform, and the events it sends.
+struct Notifier {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl Notifier {
+    /// Watches the directories of the entries, and the directories holding
+    /// their jars and jmods, as these are often replaced rather than
+    /// rewritten.
+    fn new(class_path: &ClassPath) -> notify::Result<Notifier> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for entry in class_path.entries() {
+            match entry {
+                ClassPathEntry::Directory(root) => watcher.watch(root, RecursiveMode::Recursive)?,
+                ClassPathEntry::Jar(_) | ClassPathEntry::Jmod(_) if entry.path().is_file() => {
+                    let parent = match entry.path().parent() {
+                        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+                        Some(parent) => parent,
+                        None => continue,
+                    };
+                    watcher.watch(parent, RecursiveMode::NonRecursive)?
+                }
+                _ => {}
+            }
+        }
+        Ok(Notifier {
+            _watcher: watcher,
+            events,
+        })
+    }
+}
+
+impl ClassPathWatcher {
+    /// Watches the entries of the classpath from their current state.
+    pub fn new(class_path: &ClassPath) -> io::Result<ClassPathWatcher> {
+        let snapshots = class_path
+            .entries()
+            .iter()
+            .map(|entry| snapshot(entry, class_path.filter()))
+            .collect::<io::Result<_>>()?;
+        let notifier = match Notifier::new(class_path) {
+            Ok(notifier) => Some(notifier),
+            Err(error) => {
+                tracing::debug!(%error, "cannot watch the classpath, polling it");
+                None
+            }
+        };
+        Ok(ClassPathWatcher {
+            snapshots,
+            notifier,
+        })
+    }
+
+    /// The classes changed since the last poll, sorted by entry and name,
+    /// those the filter of the classpath selects. Rewritten jars and jmods
+    /// are opened again in the classpath.
+    pub fn poll(&mut self, class_path: &mut ClassPath) -> io::Result<Vec<ClassChange>> {
+        let filter = class_path.filter().clone();
+        let mut changes = Vec::new();
+        for (index, snapshot) in self.snapshots.iter_mut().enumerate() {
+            let path = class_path.entries()[index].path().to_path_buf();
+            let mut change = |class: &str, removed: bool| {
+                changes.push(ClassChange {
+                    entry: index,
+                    class: class.to_string(),
+                    removed,
+                })
+            };
+            match snapshot {
+                Snapshot::Directory(stamps) => {
+                    let current = directory_stamps(&path, &filter)?;
+                    for (class, stamp) in &current {
+                        if stamps.get(class) != Some(stamp) {
+                            change(class, false);
+                        }
+                    }
+                    for class in stamps.keys() {
+                        if !current.contains_key(class) {
+                            change(class, true);
+                        }
+                    }
+                    *stamps = current;
+                }
+                Snapshot::Archive(stamp, classes) => {
+                    let current = Stamp::of(&path)?;
+                    if current == *stamp {
+                        continue;
+                    }
+                    let reopened = match current {
+                        Some(_) => Some(ClassPathEntry::open(&path)?),
+                        None => None,
+                    };
+                    let current_classes = match &reopened {
+                        Some(entry) => selected(entry, &filter)?,
+                        None => Vec::new(),
+                    };
+                    for class in classes.iter() {
+                        if current_classes.binary_search(class).is_err() {
+                            change(class, true);
+                        }
+                    }
+                    for class in &current_classes {
+                        change(class, false);
+                    }
+                    if let Some(reopened) = reopened {
+                        class_path.replace(index, reopened);
+                    }
+                    *stamp = current;
+                    *classes = current_classes;
+                }
+                Snapshot::Unwatched => {}
+            }
+        }
+        changes.sort_by(|a, b| (a.entry, &a.class).cmp(&(b.entry, &b.class)));
+        Ok(changes)
+    }
+
+    /// Waits until classes change, then until files stop changing for the
+    /// interval, so that the class files a compiler writes one after the
+    /// other are reported together. Without the watcher of the platform, the
+    /// classpath is polled every interval.
+    pub fn wait(
+        &mut self,
+        class_path: &mut ClassPath,
+        interval: Duration,
+    ) -> io::Result<Vec<ClassChange>> {
+        let mut changes = Vec::new();
+        loop {
+            match &self.notifier {
+                Some(notifier) => {
+                    // Blocks for the first change, then lets the writes settle
+                    if changes.is_empty() && notifier.events.recv().is_err() {
+                        self.notifier = None;
+                        continue;
+                    }
+                    while notifier.events.recv_timeout(interval).is_ok() {}
+                }
+                None => thread::sleep(interval),
+            }
+            let polled = self.poll(class_path)?;
+            if polled.is_empty() && !changes.is_empty() {
+                return Ok(merge(changes));
+            }
+            changes.extend(polled);
+        }
+    }
+}
+
+fn snapshot(entry: &ClassPathEntry, filter: &ClassFilter) -> io::Result<Snapshot> {
+    Ok(match entry {
+        ClassPathEntry::Directory(root) => Snapshot::Directory(directory_stamps(root, filter)?),
+        // Nested in a jar, the entry has no file of its own
+        ClassPathEntry::Jar(_) | ClassPathEntry::Jmod(_) if entry.path().is_file() => {
+            Snapshot::Archive(Stamp::of(entry.path())?, selected(entry, filter)?)
+        }
+        _ => Snapshot::Unwatched,
+    })
+}
+
+fn selected(entry: &ClassPathEntry, filter: &ClassFilter) -> io::Result<Vec<String>> {
+    let mut names = entry.class_names()?;
+    names.retain(|name| filter.matches(name));
+    Ok(names)
+}
+
+/// The stamps of the class files under the root the filter selects, none
+/// if it was deleted.
+fn directory_stamps(root: &Path, filter: &ClassFilter) -> io::Result<BTreeMap<String, Stamp>> {
+    let mut stamps = BTreeMap::new();
+    if root.is_dir() {
+        collect_stamps(root, root, filter, &mut stamps)?;
+    }
+    Ok(stamps)
+}
+
+fn collect_stamps(
+    root: &Path,
+    directory: &Path,
+    filter: &ClassFilter,
+    stamps: &mut BTreeMap<String, Stamp>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_stamps(root, &path, filter, stamps)?;
+            continue;
+        }
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+        let components: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let name = components.join("/");
+        if !is_class_file(&name) {
+            continue;
+        }
+        let class = name.trim_end_matches(".class");
+        if !filter.matches(class) {
+            continue;
+        }
+        // Deleted since listed
+        if let Some(stamp) = Stamp::of(&path)? {
+            stamps.insert(class.to_string(), stamp);
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the last change of each class, those of successive polls.
+fn merge(changes: Vec<ClassChange>) -> Vec<ClassChange> {
+    let mut merged = BTreeMap::new();
+    for change in changes {
+        merged.insert((change.entry, change.class.clone()), change);
+    }
+    merged.into_values().collect()
+}
+
+// ============================================================================
+// CLASSPATH WATCHER TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod watch_tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{ClassChange, ClassPathWatcher};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+
+    fn change(class: &str, removed: bool) -> ClassChange {
+        ClassChange {
+            entry: 0,
+            class: class.to_string(),
+            removed,
+        }
+    }
+
+    #[test]
+    fn test_changed_classes() {
+        let root = std::env::temp_dir().join(format!("bvm-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("com/example")).unwrap();
+        fs::write(root.join("com/example/Main.class"), b"main").unwrap();
+        fs::write(root.join("com/example/Util.class"), b"util").unwrap();
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::Directory(PathBuf::from(&root)));
+        let mut watcher = ClassPathWatcher::new(&class_path).unwrap();
+        assert_eq!(watcher.poll(&mut class_path).unwrap(), vec![]);
+
+        fs::write(root.join("com/example/Main.class"), b"main, edited").unwrap();
+        fs::remove_file(root.join("com/example/Util.class")).unwrap();
+        fs::write(root.join("com/example/Added.class"), b"added").unwrap();
+        fs::write(root.join("com/example/notes.txt"), b"not a class").unwrap();
+        assert_eq!(
+            watcher.poll(&mut class_path).unwrap(),
+            vec![
+                change("com/example/Added", false),
+                change("com/example/Main", false),
+                change("com/example/Util", true),
+            ]
+        );
+        assert_eq!(watcher.poll(&mut class_path).unwrap(), vec![]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_wait_for_changes() {
+        let root = std::env::temp_dir().join(format!("bvm-watch-wait-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("com/example")).unwrap();
+        fs::write(root.join("com/example/Main.class"), b"main").unwrap();
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::Directory(PathBuf::from(&root)));
+        let mut watcher = ClassPathWatcher::new(&class_path).unwrap();
+        let writer = {
+            let root = root.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                fs::write(root.join("com/example/Main.class"), b"main, edited").unwrap();
+                fs::write(root.join("com/example/Added.class"), b"added").unwrap();
+            })
+        };
+        let changes = watcher
+            .wait(&mut class_path, Duration::from_millis(100))
+            .unwrap();
+        writer.join().unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                change("com/example/Added", false),
+                change("com/example/Main", false),
+            ]
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,197 @@
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::path::Path;
+
+use crate::vm::loader::LoaderId;
+
+// =============================================================================
+// TRANSFORMERS
+// =============================================================================
+
+/// Rewrites class files before the VM defines them, like the
+/// `ClassFileTransformer` of a `java.lang.instrument` agent, e.g. to add
+/// coverage counters or tracing. Transformers are registered through
+/// [VmBuilder::transformer](crate::vm::VmBuilder::transformer) and run in
+/// registration order, each seeing the class file the previous one returned.
+///
+/// Class files can be edited with [Class](crate::class::Class), parsing the
+/// bytes and writing them back with `to_bytes`. Loaders may define classes
+/// concurrently, so transformers are shared between threads.
+pub trait ClassFileTransformer: Send + Sync {
+    /// Returns the transformed class file of the class with the internal
+    /// name, about to be defined by the loader, or `None` to leave it as is.
+    fn transform(&self, name: &str, loader: LoaderId, class_file: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl<F> ClassFileTransformer for F
+where
+    F: Fn(&str, LoaderId, &[u8]) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn transform(&self, name: &str, loader: LoaderId, class_file: &[u8]) -> Option<Vec<u8>> {
+        self(name, loader, class_file)
+    }
+}
+
+// =============================================================================
+// NATIVE AGENTS
+// =============================================================================
+
+/// A transformer implemented by a shared library, written in C, or in Rust
+/// as a `cdylib`, loaded by `bvm --agent <library>[=<options>]`. The library
+/// exports the functions
+///
+/// ```c
+/// // Optional, called once when the agent is loaded, with the options or
+/// // an empty string. Returning non-zero refuses to start the VM.
+/// int bvm_agent_load(const char *options);
+///
+/// // Returns the transformed class file allocated by the agent, storing
+/// // its length, or NULL to leave the class file as is. `name` is the
+/// // internal name of the class. Called from any thread.
+/// uint8_t *bvm_agent_transform(const char *name, const uint8_t *class_file,
+///                              size_t length, size_t *transformed_length);
+///
+/// // Frees a class file returned by bvm_agent_transform.
+/// void bvm_agent_free(uint8_t *class_file, size_t length);
+/// ```
+#[cfg(unix)]
+pub struct NativeAgent {
+    library: *mut libc::c_void,
+    transform: TransformFn,
+    free: FreeFn,
+}
+
+#[cfg(unix)]
+type LoadFn = unsafe extern "C" fn(*const libc::c_char) -> libc::c_int;
+#[cfg(unix)]
+type TransformFn = unsafe extern "C" fn(
+    *const libc::c_char,
+    *const u8,
+    libc::size_t,
+    *mut libc::size_t,
+) -> *mut u8;
+#[cfg(unix)]
+type FreeFn = unsafe extern "C" fn(*mut u8, libc::size_t);
+
+#[cfg(unix)]
+impl NativeAgent {
+    /// Loads the library and runs its `bvm_agent_load` with the options.
+    pub fn load(path: &Path, options: &str) -> io::Result<NativeAgent> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(invalid_input)?;
+        let options = CString::new(options).map_err(invalid_input)?;
+        // SAFETY: loading a library runs its initializers, which the user
+        // trusts by passing it as an agent.
+        let library = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if library.is_null() {
+            return Err(dl_error());
+        }
+        let symbols = symbol(library, "bvm_agent_transform")
+            .and_then(|transform| Ok((transform, symbol(library, "bvm_agent_free")?)));
+        let (transform, free) = match symbols {
+            Ok(symbols) => symbols,
+            Err(error) => {
+                // SAFETY: nothing of the library was called.
+                unsafe { libc::dlclose(library) };
+                return Err(error);
+            }
+        };
+        // SAFETY: the agent ABI declares the symbols with these signatures.
+        let agent = unsafe {
+            NativeAgent {
+                library,
+                transform: std::mem::transmute::<*mut libc::c_void, TransformFn>(transform),
+                free: std::mem::transmute::<*mut libc::c_void, FreeFn>(free),
+            }
+        };
+        if let Ok(load) = symbol(library, "bvm_agent_load") {
+            // SAFETY: as above, with the options outliving the call.
+            let status =
+                unsafe { std::mem::transmute::<*mut libc::c_void, LoadFn>(load)(options.as_ptr()) };
+            if status != 0 {
+                let message = format!("the agent refused to load with status {}", status);
+                return Err(io::Error::other(message));
+            }
+        }
+        Ok(agent)
+    }
+}
+
+#[cfg(unix)]
+impl ClassFileTransformer for NativeAgent {
+    fn transform(&self, name: &str, _loader: LoaderId, class_file: &[u8]) -> Option<Vec<u8>> {
+        let name = std::ffi::CString::new(name).ok()?;
+        let mut length = 0;
+        // SAFETY: the arguments are valid for the duration of the call, as
+        // the agent ABI requires.
+        let transformed = unsafe {
+            (self.transform)(
+                name.as_ptr(),
+                class_file.as_ptr(),
+                class_file.len(),
+                &mut length,
+            )
+        };
+        if transformed.is_null() {
+            return None;
+        }
+        // SAFETY: the agent returned `length` bytes, which are copied before
+        // being handed back to it.
+        unsafe {
+            let bytes = std::slice::from_raw_parts(transformed, length).to_vec();
+            (self.free)(transformed, length);
+            Some(bytes)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NativeAgent {
+    fn drop(&mut self) {
+        // SAFETY: the functions of the library are no longer called.
+        unsafe { libc::dlclose(self.library) };
+    }
+}
+
+// SAFETY: the agent ABI requires the functions to be callable from any
+// thread.
+#[cfg(unix)]
+unsafe impl Send for NativeAgent {}
+#[cfg(unix)]
+unsafe impl Sync for NativeAgent {}
+
+#[cfg(unix)]
+fn symbol(library: *mut libc::c_void, name: &str) -> io::Result<*mut libc::c_void> {
+    let name = std::ffi::CString::new(name).map_err(invalid_input)?;
+    // SAFETY: the library is open and the name a C string.
+    let symbol = unsafe { libc::dlsym(library, name.as_ptr()) };
+    if symbol.is_null() {
+        return Err(dl_error());
+    }
+    Ok(symbol)
+}
+
+/// The error of the last failed `dl*` call.
+#[cfg(unix)]
+fn dl_error() -> io::Error {
+    // SAFETY: `dlerror` returns a C string, or NULL without an error.
+    let message = unsafe {
+        let error = libc::dlerror();
+        if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            std::ffi::CStr::from_ptr(error)
+                .to_string_lossy()
+                .into_owned()
+        }
+    };
+    io::Error::other(message)
+}
+
+#[cfg(unix)]
+fn invalid_input<E: std::error::Error + Send + Sync + 'static>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
@@ -0,0 +1,541 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::UNIX_EPOCH;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::packaging::classpath::ClassPathEntry;
+
+/// `BVMA`, opening every class archive.
+const MAGIC: u32 = 0x4256_4d41;
+const VERSION: u32 = 2;
+
+/// Bytes of a class record: the offset and length of its name, the file
+/// providing it, and the offsets and lengths of its archived class file and
+/// links.
+const RECORD_SIZE: usize = 4 + 2 + 2 + 8 + 4 + 8 + 4;
+
+/// The fewest bytes of a file of a loader: the length of its path, its size
+/// and modification time.
+const MIN_FILE_SIZE: usize = 2 + 8 + 8;
+
+/// The loaders whose classes are archived, the bootstrap and the platform
+/// one, as they are backed by the JDK image.
+pub const ARCHIVED_LOADERS: usize = 2;
+
+// =============================================================================
+// CLASS ARCHIVE
+// =============================================================================
+
+/// A class archive, similar to HotSpot's CDS archives: the index of the
+/// classes the JDK image provides, with the class files defined by an earlier
+/// run and their [ArchivedLinks], mapped into memory.
+///
+/// Opening the JDK image to index its classes dominates the startup of short
+/// runs. A VM started from an archive looks classes up in the archive
+/// instead, reads the archived ones straight from the mapping and opens the
+/// image's files only for the classes the earlier run did not define. The
+/// archived classes are linked with the links of the earlier run rather than
+/// by looking their members up through their hierarchy.
+///
+/// An archive is only used with the files it was written from, unchanged.
+pub struct ClassArchive {
+    mapping: Mapping,
+    loaders: Vec<ArchivedLoader>,
+}
+
+/// The part of an archive covering one loader.
+struct ArchivedLoader {
+    files: Vec<ArchivedFile>,
+    /// Offset of the class records, sorted by name.
+    records: usize,
+    classes: usize,
+}
+
+/// A file of a loader's classpath, as it was when archived.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ArchivedFile {
+    pub(crate) path: PathBuf,
+    size: u64,
+    /// Nanoseconds since the Unix epoch.
+    modified: u64,
+}
+
+impl ArchivedFile {
+    pub(crate) fn of(path: &Path) -> io::Result<ArchivedFile> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Ok(ArchivedFile {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+struct Record {
+    name: (usize, usize),
+    file: usize,
+    class: (usize, usize),
+    links: (usize, usize),
+}
+
+/// What linking an archived class resolved in the run which archived it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArchivedLinks {
+    /// The resolved constant pool: the class declaring the member each
+    /// field and method reference resolved to, by constant pool index.
+    pub(crate) resolved: Vec<(u16, String)>,
+    /// The vtable: the class declaring the implementation selected on the
+    /// instances of the class, by name and descriptor of the method.
+    pub(crate) vtable: Vec<(String, String, String)>,
+}
+
+impl ArchivedLinks {
+    fn read(bytes: &[u8]) -> io::Result<ArchivedLinks> {
+        let mut reader = Cursor::new(bytes);
+        let mut links = ArchivedLinks::default();
+        for _ in 0..reader.read_u16::<BigEndian>()? {
+            let index = reader.read_u16::<BigEndian>()?;
+            links.resolved.push((index, read_string(&mut reader)?));
+        }
+        for _ in 0..reader.read_u16::<BigEndian>()? {
+            links.vtable.push((
+                read_string(&mut reader)?,
+                read_string(&mut reader)?,
+                read_string(&mut reader)?,
+            ));
+        }
+        Ok(links)
+    }
+
+    fn write(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.write_u16::<BigEndian>(u16::try_from(self.resolved.len()).map_err(too_large)?)?;
+        for (index, class) in &self.resolved {
+            bytes.write_u16::<BigEndian>(*index)?;
+            write_string(&mut bytes, class)?;
+        }
+        bytes.write_u16::<BigEndian>(u16::try_from(self.vtable.len()).map_err(too_large)?)?;
+        for (name, descriptor, class) in &self.vtable {
+            write_string(&mut bytes, name)?;
+            write_string(&mut bytes, descriptor)?;
+            write_string(&mut bytes, class)?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl ClassArchive {
+    /// Maps the archive at the path, if it was written from the classpath
+    /// files of each archived loader. Missing, stale and unreadable archives
+    /// are logged and ignored.
+    pub fn open(path: &Path, files: [&[PathBuf]; ARCHIVED_LOADERS]) -> Option<ClassArchive> {
+        let archive = match File::open(path).and_then(|file| ClassArchive::read(&file)) {
+            Ok(archive) => archive,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+            Err(error) => {
+                tracing::warn!(archive = %path.display(), %error, "ignoring class archive");
+                return None;
+            }
+        };
+
+        for (loader, files) in archive.loaders.iter().zip(files) {
+            let current: io::Result<Vec<ArchivedFile>> =
+                files.iter().map(|file| ArchivedFile::of(file)).collect();
+            if current.ok().as_ref() != Some(&loader.files) {
+                tracing::info!(archive = %path.display(), "ignoring stale class archive");
+                return None;
+            }
+        }
+        Some(archive)
+    }
+
+    fn read(file: &File) -> io::Result<ClassArchive> {
+        let mapping = Mapping::of(file)?;
+        let mut reader = Cursor::new(mapping.bytes());
+        if reader.read_u32::<BigEndian>()? != MAGIC {
+            return Err(invalid_data("not a class archive"));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(invalid_data("unsupported class archive version"));
+        }
+
+        let mut loaders = Vec::with_capacity(ARCHIVED_LOADERS);
+        for _ in 0..ARCHIVED_LOADERS {
+            let file_count = reader.read_u32::<BigEndian>()? as usize;
+            let remaining = mapping.bytes().len() - reader.position() as usize;
+            if file_count > remaining / MIN_FILE_SIZE {
+                return Err(invalid_data("truncated class archive"));
+            }
+            let mut files = Vec::with_capacity(file_count);
+            for _ in 0..file_count {
+                files.push(ArchivedFile {
+                    path: PathBuf::from(read_string(&mut reader)?),
+                    size: reader.read_u64::<BigEndian>()?,
+                    modified: reader.read_u64::<BigEndian>()?,
+                });
+            }
+            let classes = reader.read_u32::<BigEndian>()? as usize;
+            let records = reader.read_u64::<BigEndian>()? as usize;
+            let end = classes
+                .checked_mul(RECORD_SIZE)
+                .and_then(|size| size.checked_add(records));
+            if end.is_none_or(|end| end > mapping.bytes().len()) {
+                return Err(invalid_data("truncated class archive"));
+            }
+            loaders.push(ArchivedLoader {
+                files,
+                records,
+                classes,
+            });
+        }
+
+        Ok(ClassArchive { mapping, loaders })
+    }
+
+    fn record(&self, loader: usize, index: usize) -> Record {
+        let offset = self.loaders[loader].records + index * RECORD_SIZE;
+        let mut reader = Cursor::new(&self.mapping.bytes()[offset..offset + RECORD_SIZE]);
+        let mut read = || -> io::Result<Record> {
+            Ok(Record {
+                name: (
+                    reader.read_u32::<BigEndian>()? as usize,
+                    reader.read_u16::<BigEndian>()? as usize,
+                ),
+                file: reader.read_u16::<BigEndian>()? as usize,
+                class: (
+                    reader.read_u64::<BigEndian>()? as usize,
+                    reader.read_u32::<BigEndian>()? as usize,
+                ),
+                links: (
+                    reader.read_u64::<BigEndian>()? as usize,
+                    reader.read_u32::<BigEndian>()? as usize,
+                ),
+            })
+        };
+        read().expect("Records are within the archive")
+    }
+
+    /// The bytes at the offset and length, empty if out of bounds.
+    fn slice(&self, (offset, length): (usize, usize)) -> &[u8] {
+        let range = offset..offset.saturating_add(length);
+        self.mapping.bytes().get(range).unwrap_or_default()
+    }
+
+    fn find(&self, loader: usize, name: &str) -> Option<Record> {
+        let (mut low, mut high) = (0, self.loaders[loader].classes);
+        while low < high {
+            let middle = (low + high) / 2;
+            let record = self.record(loader, middle);
+            match self.slice(record.name).cmp(name.as_bytes()) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Some(record),
+            }
+        }
+        None
+    }
+}
+
+fn read_string(reader: &mut Cursor<&[u8]>) -> io::Result<String> {
+    let length = reader.read_u16::<BigEndian>()?;
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("invalid string in class archive"))
+}
+
+fn write_string(writer: &mut Vec<u8>, string: &str) -> io::Result<()> {
+    writer.write_u16::<BigEndian>(u16::try_from(string.len()).map_err(too_large)?)?;
+    writer.write_all(string.as_bytes())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// =============================================================================
+// ARCHIVED CLASSPATH
+// =============================================================================
+
+/// The classes of a loader, looked up in an archive.
+pub(crate) struct ArchivedClassPath {
+    archive: Arc<ClassArchive>,
+    loader: usize,
+    /// The files of the loader's classpath, opened on first use.
+    entries: Vec<OnceLock<ClassPathEntry>>,
+}
+
+/// A class file found on an [ArchivedClassPath].
+pub(crate) struct FoundClass<'a> {
+    pub(crate) source: PathBuf,
+    pub(crate) bytes: Cow<'a, [u8]>,
+    /// Whether the class file was read from the archive.
+    pub(crate) archived: bool,
+    /// The links of the archived class, if the run archiving it linked it.
+    pub(crate) links: Option<ArchivedLinks>,
+}
+
+impl ArchivedClassPath {
+    pub(crate) fn new(archive: Arc<ClassArchive>, loader: usize) -> Self {
+        let files = archive.loaders[loader].files.len();
+        ArchivedClassPath {
+            archive,
+            loader,
+            entries: (0..files).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    pub(crate) fn files(&self) -> &[ArchivedFile] {
+        &self.archive.loaders[self.loader].files
+    }
+
+    /// Reads the class, from the archive if it holds its class file.
+    pub(crate) fn read_class(&self, name: &str) -> io::Result<Option<FoundClass<'_>>> {
+        let record = match self.archive.find(self.loader, name) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let source = match self.files().get(record.file) {
+            Some(file) => file.path.clone(),
+            None => return Err(invalid_data("invalid file in class archive")),
+        };
+        if record.class.1 > 0 {
+            let links = match record.links.1 {
+                0 => None,
+                _ => Some(ArchivedLinks::read(self.archive.slice(record.links))?),
+            };
+            return Ok(Some(FoundClass {
+                source,
+                bytes: Cow::Borrowed(self.archive.slice(record.class)),
+                archived: true,
+                links,
+            }));
+        }
+
+        let entry = match self.entries[record.file].get() {
+            Some(entry) => entry,
+            None => {
+                let entry = ClassPathEntry::open(&source)?;
+                self.entries[record.file].get_or_init(|| entry)
+            }
+        };
+        Ok(entry.read_class(name)?.map(|bytes| FoundClass {
+            source,
+            bytes: Cow::Owned(bytes),
+            archived: false,
+            links: None,
+        }))
+    }
+
+    /// Every class of the loader, sorted by name, with the index of the file
+    /// providing it, and its archived class file and links.
+    pub(crate) fn classes(&self) -> impl Iterator<Item = ArchivedClass<'_>> + '_ {
+        (0..self.archive.loaders[self.loader].classes).filter_map(move |index| {
+            let record = self.archive.record(self.loader, index);
+            let name = std::str::from_utf8(self.archive.slice(record.name)).ok()?;
+            let class = self.archive.slice(record.class);
+            let links = self.archive.slice(record.links);
+            Some(ArchivedClass {
+                name,
+                file: record.file,
+                class: Some(class).filter(|class| !class.is_empty()),
+                links: Some(links).filter(|links| !links.is_empty()),
+            })
+        })
+    }
+}
+
+/// A class of an [ArchivedClassPath], see [ArchivedClassPath::classes].
+pub(crate) struct ArchivedClass<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) file: usize,
+    pub(crate) class: Option<&'a [u8]>,
+    pub(crate) links: Option<&'a [u8]>,
+}
+
+// =============================================================================
+// WRITING
+// =============================================================================
+
+/// What an archive holds for a loader.
+pub(crate) struct LoaderSnapshot<'a> {
+    pub(crate) files: Vec<ArchivedFile>,
+    /// Every class of the loader, sorted by name.
+    pub(crate) classes: Vec<ClassSnapshot<'a>>,
+}
+
+pub(crate) struct ClassSnapshot<'a> {
+    pub(crate) name: &'a str,
+    /// The index of the file providing the class.
+    pub(crate) file: usize,
+    /// The class file, for the classes to archive.
+    pub(crate) class: Option<Cow<'a, [u8]>>,
+    /// The encoded [ArchivedLinks] of the class, if it was linked.
+    pub(crate) links: Option<Cow<'a, [u8]>>,
+}
+
+impl ClassSnapshot<'_> {
+    /// The links the VM resolved for the class replacing the archived ones.
+    pub(crate) fn link(&mut self, links: &ArchivedLinks) -> io::Result<()> {
+        self.links = Some(Cow::Owned(links.write()?));
+        Ok(())
+    }
+}
+
+/// Writes the archive of the loaders to the path, replacing the file at once
+/// so that VMs still mapping the previous archive are not affected.
+pub(crate) fn write(path: &Path, loaders: &[LoaderSnapshot]) -> io::Result<()> {
+    let header = |records: &[u64]| -> io::Result<Vec<u8>> {
+        let mut header = Vec::new();
+        header.write_u32::<BigEndian>(MAGIC)?;
+        header.write_u32::<BigEndian>(VERSION)?;
+        for (loader, &records) in loaders.iter().zip(records) {
+            header.write_u32::<BigEndian>(count(loader.files.len())?)?;
+            for file in &loader.files {
+                let path = file.path.to_string_lossy();
+                header.write_u16::<BigEndian>(u16::try_from(path.len()).map_err(too_large)?)?;
+                header.write_all(path.as_bytes())?;
+                header.write_u64::<BigEndian>(file.size)?;
+                header.write_u64::<BigEndian>(file.modified)?;
+            }
+            header.write_u32::<BigEndian>(count(loader.classes.len())?)?;
+            header.write_u64::<BigEndian>(records)?;
+        }
+        Ok(header)
+    };
+
+    // The header has the same size whatever the offsets of the records.
+    let mut offset = header(&[0; ARCHIVED_LOADERS])?.len();
+    let mut records = Vec::with_capacity(loaders.len());
+    for loader in loaders {
+        records.push(offset as u64);
+        offset += loader.classes.len() * RECORD_SIZE;
+    }
+    let mut archive = header(&records)?;
+
+    let classes = || loaders.iter().flat_map(|loader| &loader.classes);
+    let size = |bytes: &Option<Cow<[u8]>>| bytes.as_ref().map_or(0, |bytes| bytes.len());
+    let names_size: usize = classes().map(|class| class.name.len()).sum();
+    let classes_size: usize = classes().map(|class| size(&class.class)).sum();
+    let mut name_offset = offset;
+    let mut class_offset = name_offset + names_size;
+    let mut links_offset = class_offset + classes_size;
+    for class in classes() {
+        let (class_size, links_size) = (size(&class.class), size(&class.links));
+        archive.write_u32::<BigEndian>(u32::try_from(name_offset).map_err(too_large)?)?;
+        archive.write_u16::<BigEndian>(u16::try_from(class.name.len()).map_err(too_large)?)?;
+        archive.write_u16::<BigEndian>(u16::try_from(class.file).map_err(too_large)?)?;
+        archive.write_u64::<BigEndian>(class_offset as u64)?;
+        archive.write_u32::<BigEndian>(u32::try_from(class_size).map_err(too_large)?)?;
+        archive.write_u64::<BigEndian>(links_offset as u64)?;
+        archive.write_u32::<BigEndian>(u32::try_from(links_size).map_err(too_large)?)?;
+        name_offset += class.name.len();
+        class_offset += class_size;
+        links_offset += links_size;
+    }
+    for class in classes() {
+        archive.write_all(class.name.as_bytes())?;
+    }
+    for class in classes() {
+        archive.write_all(class.class.as_deref().unwrap_or_default())?;
+    }
+    for class in classes() {
+        archive.write_all(class.links.as_deref().unwrap_or_default())?;
+    }
+
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, archive)?;
+    fs::rename(&temporary, path)
+}
+
+fn count(count: usize) -> io::Result<u32> {
+    u32::try_from(count).map_err(too_large)
+}
+
+fn too_large<E>(_: E) -> io::Error {
+    invalid_data("too large for a class archive")
+}
+
+// =============================================================================
+// MAPPING
+// =============================================================================
+
+/// A file mapped into memory read-only.
+#[cfg(unix)]
+struct Mapping {
+    pointer: *mut libc::c_void,
+    length: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    fn of(file: &File) -> io::Result<Mapping> {
+        use std::os::unix::io::AsRawFd;
+
+        let length = usize::try_from(file.metadata()?.len()).map_err(too_large)?;
+        if length == 0 {
+            return Err(invalid_data("empty class archive"));
+        }
+        // SAFETY: a fresh private mapping of the open file, which archives
+        // are never modified in place.
+        let pointer = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                length,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if pointer == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { pointer, length })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: the mapping is readable and lives as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.pointer as *const u8, self.length) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: the mapping is no longer borrowed.
+        unsafe { libc::munmap(self.pointer, self.length) };
+    }
+}
+
+// SAFETY: the mapping is read-only.
+#[cfg(unix)]
+unsafe impl Send for Mapping {}
+#[cfg(unix)]
+unsafe impl Sync for Mapping {}
+
+/// The file read into memory, where mapping it is not supported.
+#[cfg(not(unix))]
+struct Mapping(Vec<u8>);
+
+#[cfg(not(unix))]
+impl Mapping {
+    fn of(mut file: &File) -> io::Result<Mapping> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Mapping(bytes))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
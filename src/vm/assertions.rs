@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+/// Which classes run their `assert` statements, as `java`'s `-ea` and `-da`
+/// options configure it. Guest classes ask through
+/// `Class.desiredAssertionStatus` when initialized, caching the answer in
+/// their `$assertionsDisabled` field.
+///
+/// A rule for a class takes precedence over the rules for its package and
+/// the enclosing packages, the innermost one first, which take precedence
+/// over the default. Like in the JDK, the default never applies to the
+/// classes of the bootstrap loader. Disabled everywhere unless configured.
+#[derive(Clone, Debug, Default)]
+pub struct AssertionStatus {
+    default: bool,
+    /// By binary package name, the unnamed package being empty.
+    packages: HashMap<String, bool>,
+    /// By binary class name.
+    classes: HashMap<String, bool>,
+}
+
+impl AssertionStatus {
+    /// Enables assertions like `-ea[:target]`, see [AssertionStatus::set].
+    pub fn enable(mut self, target: &str) -> Self {
+        self.set(target, true);
+        self
+    }
+
+    /// Disables assertions like `-da[:target]`, see [AssertionStatus::set].
+    pub fn disable(mut self, target: &str) -> Self {
+        self.set(target, false);
+        self
+    }
+
+    /// Sets the status of the target: the default for an empty one, the
+    /// package and its subpackages for one ending in `...`, with `...` alone
+    /// standing for the unnamed package, and otherwise the class. A later
+    /// rule for the same target replaces the earlier one.
+    pub fn set(&mut self, target: &str, enabled: bool) {
+        let target = target.replace('/', ".");
+        if target.is_empty() {
+            self.default = enabled;
+        } else if let Some(package) = target.strip_suffix("...") {
+            self.packages.insert(package.to_string(), enabled);
+        } else {
+            self.classes.insert(target, enabled);
+        }
+    }
+
+    /// Whether the class of the internal or binary name should have its
+    /// assertions enabled.
+    pub fn desired(&self, class: &str, bootstrap: bool) -> bool {
+        let class = class.replace('/', ".");
+        if let Some(enabled) = self.classes.get(&class) {
+            return *enabled;
+        }
+        // The unnamed package only has its own rule, it does not enclose the
+        // named ones.
+        let mut package = class.rfind('.').map_or("", |index| &class[..index]);
+        loop {
+            if let Some(enabled) = self.packages.get(package) {
+                return *enabled;
+            }
+            match package.rfind('.') {
+                Some(index) => package = &package[..index],
+                None => return self.default_for(bootstrap),
+            }
+        }
+    }
+
+    fn default_for(&self, bootstrap: bool) -> bool {
+        self.default && !bootstrap
+    }
+}
+
+#[cfg(test)]
+mod assertions_tests {
+    use super::AssertionStatus;
+
+    #[test]
+    fn test_package_hierarchy() {
+        let status = AssertionStatus::default()
+            .enable("com.example...")
+            .disable("com.example.slow...")
+            .enable("com.example.slow.Checked");
+
+        assert!(status.desired("com/example/Main", false));
+        assert!(status.desired("com/example/deep/Nested$Inner", false));
+        assert!(!status.desired("com/example/slow/Loop", false));
+        assert!(!status.desired("com/example/slow/deeper/Loop", false));
+        assert!(status.desired("com/example/slow/Checked", false));
+        assert!(!status.desired("com/examples/Main", false));
+        assert!(!status.desired("Main", false));
+    }
+
+    #[test]
+    fn test_default_and_unnamed_package() {
+        let status = AssertionStatus::default().enable("").disable("...");
+        assert!(!status.desired("Main", false));
+        assert!(status.desired("org.example.Main", false));
+        assert!(!status.desired("java/util/HashMap", true));
+
+        let status = AssertionStatus::default().enable("java.util...").enable("");
+        assert!(status.desired("java/util/HashMap", true));
+        assert!(!status.desired("java/lang/String", true));
+
+        let status = AssertionStatus::default().enable("...").disable("...");
+        assert!(!status.desired("Main", false));
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+use bitflags::Flags;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::class::attributes::Attribute;
+use crate::class::instruction;
+use crate::class::Class;
+use crate::vm::registry::{package_of, ClassRegistry};
+
+/// The rows moved by page up and page down.
+const PAGE: usize = 20;
+
+const HELP: &str =
+    "q quit  / search  tab switch side  1-4 or \u{2190}\u{2192} panes  enter open  n/N next match";
+
+/// A view of the class opened in the browser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pane {
+    ConstantPool,
+    Fields,
+    Methods,
+    Disassembly,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 4] = [
+        Pane::ConstantPool,
+        Pane::Fields,
+        Pane::Methods,
+        Pane::Disassembly,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Pane::ConstantPool => "Constant pool",
+            Pane::Fields => "Fields",
+            Pane::Methods => "Methods",
+            Pane::Disassembly => "Disassembly",
+        }
+    }
+
+    fn index(self) -> usize {
+        Pane::ALL.iter().position(|pane| *pane == self).unwrap()
+    }
+}
+
+/// A row of the tree of packages and classes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Row {
+    /// A package by internal name, empty for the unnamed package.
+    Package { name: String, expanded: bool },
+    /// A class by internal name.
+    Class(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Focus {
+    Tree,
+    Details,
+}
+
+/// What the typed characters edit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Keys,
+    /// The filter of the tree.
+    Filter,
+    /// The text searched in the pane.
+    Find,
+}
+
+struct OpenClass {
+    name: String,
+    title: String,
+    /// The lines of every pane, in the order of [Pane::ALL].
+    lines: [Vec<String>; 4],
+    /// The line of the disassembly every method starts at.
+    method_lines: Vec<usize>,
+}
+
+/// The state of `bvm browse`, a terminal UI browsing the classes of a
+/// classpath: a tree of their packages, filtered by name, and panes with the
+/// constant pool, fields, methods and disassembly of the opened class,
+/// searched for text.
+///
+/// The state is driven by [ClassBrowser::handle_key] and drawn by
+/// [ClassBrowser::draw], [ClassBrowser::run] doing both on the terminal.
+pub struct ClassBrowser {
+    registry: ClassRegistry,
+    /// The classes of every package, by internal name.
+    packages: BTreeMap<String, Vec<String>>,
+    expanded: BTreeSet<String>,
+    filter: String,
+    rows: Vec<Row>,
+    tree: ListState,
+    class: Option<OpenClass>,
+    pane: Pane,
+    details: ListState,
+    find: String,
+    focus: Focus,
+    input: Input,
+    status: String,
+}
+
+impl ClassBrowser {
+    pub fn new(registry: ClassRegistry) -> Self {
+        let mut packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for name in registry.class_names() {
+            packages
+                .entry(package_of(name).to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+        let mut browser = ClassBrowser {
+            registry,
+            packages,
+            expanded: BTreeSet::new(),
+            filter: String::new(),
+            rows: Vec::new(),
+            tree: ListState::default().with_selected(Some(0)),
+            class: None,
+            pane: Pane::ConstantPool,
+            details: ListState::default().with_selected(Some(0)),
+            find: String::new(),
+            focus: Focus::Tree,
+            input: Input::Keys,
+            status: String::new(),
+        };
+        browser.refresh_rows();
+        browser
+    }
+
+    /// Takes over the terminal until the user quits.
+    pub fn run(mut self) -> io::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// The rows of the tree, only the classes matching the filter, with
+    /// their packages expanded, when there is one.
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// The internal name of the opened class.
+    pub fn class_name(&self) -> Option<&str> {
+        self.class.as_ref().map(|class| class.name.as_str())
+    }
+
+    /// The lines of a pane of the opened class.
+    pub fn pane_lines(&self, pane: Pane) -> &[String] {
+        match &self.class {
+            Some(class) => &class.lines[pane.index()],
+            None => &[],
+        }
+    }
+
+    pub fn pane(&self) -> Pane {
+        self.pane
+    }
+
+    /// The line of the pane under the cursor.
+    pub fn details_line(&self) -> usize {
+        self.details.selected().unwrap_or(0)
+    }
+
+    /// Keeps the classes whose name contains the filter, ignoring the case,
+    /// in internal or binary form.
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+        self.refresh_rows();
+    }
+
+    /// Moves the cursor of the pane to the next line containing the text,
+    /// from the line after the cursor, or the previous one backwards.
+    pub fn find(&mut self, text: &str, forward: bool) -> bool {
+        self.find = text.to_string();
+        let lines = self.pane_lines(self.pane);
+        if text.is_empty() || lines.is_empty() {
+            return false;
+        }
+        let current = self.details_line();
+        let count = lines.len();
+        let found = (1..=count)
+            .map(|offset| match forward {
+                true => (current + offset) % count,
+                false => (current + count - offset % count) % count,
+            })
+            .find(|line| lines[*line].contains(text));
+        match found {
+            Some(line) => {
+                self.details.select(Some(line));
+                self.status.clear();
+            }
+            None => self.status = format!("'{}' not found", text),
+        }
+        found.is_some()
+    }
+
+    /// Opens the class, showing why in the panes if it cannot be parsed.
+    pub fn open(&mut self, name: &str) {
+        let class = match self.registry.read_class(name) {
+            Ok(Some(bytes)) => Class::parse_bytes(&bytes).map_err(|error| error.to_string()),
+            Ok(None) => Err("class not found".to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        self.class = Some(match class {
+            Ok(class) => describe_class(name, &class),
+            Err(error) => OpenClass {
+                name: name.to_string(),
+                title: name.to_string(),
+                lines: std::array::from_fn(|_| vec![format!("Cannot read {}: {}", name, error)]),
+                method_lines: Vec::new(),
+            },
+        });
+        self.details.select(Some(0));
+    }
+
+    pub fn select_pane(&mut self, pane: Pane) {
+        if self.pane != pane {
+            self.pane = pane;
+            self.details.select(Some(0));
+        }
+    }
+
+    /// Handles a key press, returning false once the user quits.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+        match self.input {
+            Input::Keys => {}
+            input => {
+                self.edit_input(input, key.code);
+                return true;
+            }
+        }
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('/') => {
+                self.input = match self.focus {
+                    Focus::Tree => Input::Filter,
+                    Focus::Details => Input::Find,
+                };
+                if self.input == Input::Find {
+                    self.find.clear();
+                }
+            }
+            KeyCode::Tab | KeyCode::BackTab if self.class.is_some() => {
+                self.focus = match self.focus {
+                    Focus::Tree => Focus::Details,
+                    Focus::Details => Focus::Tree,
+                };
+            }
+            KeyCode::Char(digit @ '1'..='4') => {
+                self.select_pane(Pane::ALL[digit as usize - '1' as usize]);
+            }
+            _ => match self.focus {
+                Focus::Tree => self.tree_key(key.code),
+                Focus::Details => self.details_key(key.code),
+            },
+        }
+        true
+    }
+
+    fn edit_input(&mut self, input: Input, code: KeyCode) {
+        match code {
+            KeyCode::Enter => {
+                self.input = Input::Keys;
+                if input == Input::Find {
+                    let find = self.find.clone();
+                    self.find(&find, true);
+                }
+            }
+            KeyCode::Esc => {
+                self.input = Input::Keys;
+                match input {
+                    Input::Filter => self.set_filter(""),
+                    _ => self.find.clear(),
+                }
+            }
+            KeyCode::Backspace => {
+                match input {
+                    Input::Filter => self.filter.pop(),
+                    _ => self.find.pop(),
+                };
+                self.refresh_rows();
+            }
+            KeyCode::Char(c) => {
+                match input {
+                    Input::Filter => self.filter.push(c),
+                    _ => self.find.push(c),
+                }
+                self.refresh_rows();
+            }
+            _ => {}
+        }
+    }
+
+    fn tree_key(&mut self, code: KeyCode) {
+        let selected = self.tree.selected().unwrap_or(0);
+        match code {
+            KeyCode::Esc if !self.filter.is_empty() => self.set_filter(""),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => match self.rows.get(selected) {
+                Some(Row::Package { name, expanded }) => {
+                    if !*expanded {
+                        self.expanded.insert(name.clone());
+                    } else if self.filter.is_empty() {
+                        self.expanded.remove(name);
+                    }
+                    self.refresh_rows();
+                }
+                Some(Row::Class(name)) => {
+                    let name = name.clone();
+                    self.open(&name);
+                    self.focus = Focus::Details;
+                }
+                None => {}
+            },
+            KeyCode::Left | KeyCode::Char('h') => {
+                let package = self.rows[..=selected.min(self.rows.len().saturating_sub(1))]
+                    .iter()
+                    .rposition(|row| matches!(row, Row::Package { .. }));
+                if let Some(row) = package {
+                    if let Row::Package { name, .. } = &self.rows[row] {
+                        self.expanded.remove(&name.clone());
+                    }
+                    self.tree.select(Some(row));
+                    self.refresh_rows();
+                }
+            }
+            code => {
+                let line = moved(selected, self.rows.len(), code);
+                self.tree.select(Some(line));
+            }
+        }
+    }
+
+    fn details_key(&mut self, code: KeyCode) {
+        let selected = self.details_line();
+        match code {
+            KeyCode::Esc => self.focus = Focus::Tree,
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.select_pane(Pane::ALL[(self.pane.index() + 3) % 4]);
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.select_pane(Pane::ALL[(self.pane.index() + 1) % 4]);
+            }
+            KeyCode::Char('n') => {
+                let find = self.find.clone();
+                self.find(&find, true);
+            }
+            KeyCode::Char('N') => {
+                let find = self.find.clone();
+                self.find(&find, false);
+            }
+            KeyCode::Enter if self.pane == Pane::Methods => {
+                let line = self
+                    .class
+                    .as_ref()
+                    .and_then(|class| class.method_lines.get(selected).copied());
+                if let Some(line) = line {
+                    self.select_pane(Pane::Disassembly);
+                    self.details.select(Some(line));
+                }
+            }
+            code => {
+                let count = self.pane_lines(self.pane).len();
+                self.details.select(Some(moved(selected, count, code)));
+            }
+        }
+    }
+
+    fn refresh_rows(&mut self) {
+        let filter = self.filter.to_lowercase().replace('/', ".");
+        let mut rows = Vec::new();
+        for (package, classes) in &self.packages {
+            let mut matching = classes
+                .iter()
+                .filter(|class| {
+                    filter.is_empty() || class.to_lowercase().replace('/', ".").contains(&filter)
+                })
+                .peekable();
+            if matching.peek().is_none() {
+                continue;
+            }
+            let expanded = !filter.is_empty() || self.expanded.contains(package);
+            rows.push(Row::Package {
+                name: package.clone(),
+                expanded,
+            });
+            if expanded {
+                rows.extend(matching.map(|class| Row::Class(class.clone())));
+            }
+        }
+        self.rows = rows;
+        let selected = self.tree.selected().unwrap_or(0);
+        self.tree
+            .select(Some(selected.min(self.rows.len().saturating_sub(1))));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [tree, details] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Package { name, expanded } => {
+                    let marker = if *expanded { '\u{25be}' } else { '\u{25b8}' };
+                    let name = match name.as_str() {
+                        "" => "(unnamed package)".to_string(),
+                        name => name.replace('/', "."),
+                    };
+                    ListItem::new(format!("{} {}", marker, name))
+                }
+                Row::Class(name) => {
+                    let simple = name.rsplit('/').next().unwrap_or(name);
+                    ListItem::new(format!("    {}", simple))
+                }
+            })
+            .collect();
+        let title = match self.filter.as_str() {
+            "" => format!(" Classes ({}) ", self.registry.class_names().count()),
+            filter => format!(" Classes /{} ", filter),
+        };
+        let list = List::new(items)
+            .block(self.block(title, Focus::Tree))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree, &mut self.tree);
+
+        let title = match &self.class {
+            Some(class) => format!(" {} ", class.title),
+            None => " No class opened ".to_string(),
+        };
+        let block = self.block(title, Focus::Details);
+        let inner = block.inner(details);
+        frame.render_widget(block, details);
+        let [tabs, lines] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        let titles = Pane::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, pane)| format!("{} {}", index + 1, pane.title()));
+        let tabs_widget = Tabs::new(titles)
+            .select(self.pane.index())
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+        frame.render_widget(tabs_widget, tabs);
+        let find = self.find.as_str();
+        // Borrows the lines apart from the state of the list they are drawn in
+        let pane_lines = match &self.class {
+            Some(class) => class.lines[self.pane.index()].as_slice(),
+            None => &[],
+        };
+        let items: Vec<ListItem> = pane_lines
+            .iter()
+            .map(|line| {
+                let item = ListItem::new(line.as_str());
+                match !find.is_empty() && line.contains(find) {
+                    true => item.style(Style::default().fg(Color::Yellow)),
+                    false => item,
+                }
+            })
+            .collect();
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, lines, &mut self.details);
+
+        let status_line = match self.input {
+            Input::Filter => Line::from(format!("filter classes: {}", self.filter)),
+            Input::Find => Line::from(format!("find in pane: {}", self.find)),
+            Input::Keys if !self.status.is_empty() => Line::from(self.status.as_str()),
+            Input::Keys => Line::from(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn block(&self, title: String, focus: Focus) -> Block<'static> {
+        let style = match self.focus == focus {
+            true => Style::default().fg(Color::Cyan),
+            false => Style::default(),
+        };
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(style)
+            .title(title)
+    }
+}
+
+/// The line the navigation key moves the cursor from the selected line to,
+/// in a list of the count of lines.
+fn moved(selected: usize, count: usize, code: KeyCode) -> usize {
+    let last = count.saturating_sub(1);
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => selected.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => (selected + 1).min(last),
+        KeyCode::PageUp => selected.saturating_sub(PAGE),
+        KeyCode::PageDown => (selected + PAGE).min(last),
+        KeyCode::Home | KeyCode::Char('g') => 0,
+        KeyCode::End | KeyCode::Char('G') => last,
+        _ => selected,
+    }
+}
+
+// =============================================================================
+// PANES
+// =============================================================================
+
+fn describe_class(name: &str, class: &Class) -> OpenClass {
+    let pool = &class.constant_pool;
+    let mut title = format!(
+        "{} {} (version {}.{})",
+        modifiers(&class.access_flags),
+        name.replace('/', "."),
+        class.major_version,
+        class.minor_version
+    );
+    if let Ok(Some(super_class)) = class.super_class_name() {
+        title = format!("{} extends {}", title, super_class.replace('/', "."));
+    }
+
+    let constants = pool
+        .iter()
+        .map(|(index, constant)| {
+            let value = pool.describe_entry(index as u16);
+            format!("#{:<5} {:<18} {}", index, constant.kind(), value)
+        })
+        .collect();
+
+    let fields = class
+        .fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{}{}: {}",
+                with_space(modifiers(&field.access_flags)),
+                pool.get_utf8(field.name_index).unwrap_or("?"),
+                pool.get_utf8(field.descriptor_index).unwrap_or("?")
+            )
+        })
+        .collect();
+
+    let mut methods = Vec::new();
+    let mut disassembly = Vec::new();
+    let mut method_lines = Vec::new();
+    for method in &class.methods {
+        let signature = format!(
+            "{}{}{}",
+            with_space(modifiers(&method.access_flags)),
+            pool.get_utf8(method.name_index).unwrap_or("?"),
+            pool.get_utf8(method.descriptor_index).unwrap_or("?")
+        );
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            });
+        method_lines.push(disassembly.len());
+        disassembly.push(signature.clone());
+        match code {
+            Some(code) => {
+                methods.push(format!(
+                    "{}  // {} bytes, stack={}, locals={}",
+                    signature,
+                    code.code.len(),
+                    code.max_stack,
+                    code.max_locals
+                ));
+                let decoded = instruction::decode(&code.code);
+                for (index, pc) in decoded.pcs.iter().enumerate() {
+                    let instruction = instruction::disassemble(&code.code, &decoded, index, pool);
+                    disassembly.push(format!("  {:>4}: {}", pc, instruction));
+                }
+                for entry in &code.exception_tables {
+                    let catch_type = match entry.catch_type {
+                        0 => "any".to_string(),
+                        index => pool.describe(index),
+                    };
+                    disassembly.push(format!(
+                        "  catch {} {}..{} -> {}",
+                        catch_type, entry.start_pc, entry.end_pc, entry.handler_pc
+                    ));
+                }
+            }
+            None => {
+                methods.push(signature);
+                disassembly.push("  // no code".to_string());
+            }
+        }
+        disassembly.push(String::new());
+    }
+
+    OpenClass {
+        name: name.to_string(),
+        title,
+        lines: [constants, fields, methods, disassembly],
+        method_lines,
+    }
+}
+
+/// The lowercase names of the flags set, space separated.
+fn modifiers<F: Flags>(flags: &F) -> String {
+    let names: Vec<String> = flags
+        .iter_names()
+        .map(|(name, _)| name.to_lowercase())
+        .collect();
+    names.join(" ")
+}
+
+fn with_space(modifiers: String) -> String {
+    match modifiers.is_empty() {
+        true => modifiers,
+        false => modifiers + " ",
+    }
+}
+
+#[cfg(test)]
+mod browser_tests {
+    use std::path::PathBuf;
+
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use ratatui::Terminal;
+
+    use super::{ClassBrowser, Pane, Row};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::registry::ClassRegistry;
+
+    fn browser() -> ClassBrowser {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(root).unwrap());
+        ClassBrowser::new(ClassRegistry::new(class_path).unwrap())
+    }
+
+    fn press(browser: &mut ClassBrowser, code: KeyCode) {
+        assert!(browser.handle_key(KeyEvent::from(code)));
+    }
+
+    #[test]
+    fn test_filter_and_open() {
+        let mut browser = browser();
+        assert_eq!(
+            browser.rows(),
+            [Row::Package {
+                name: String::new(),
+                expanded: false
+            }]
+        );
+
+        browser.set_filter("dispatch$s");
+        let rows: Vec<&Row> = browser.rows().iter().collect();
+        assert_eq!(
+            rows[1..],
+            [
+                &Row::Class("Dispatch$Shape".to_string()),
+                &Row::Class("Dispatch$Square".to_string())
+            ]
+        );
+
+        // Opening the selected class from the tree
+        press(&mut browser, KeyCode::Down);
+        press(&mut browser, KeyCode::Enter);
+        assert_eq!(browser.class_name(), Some("Dispatch$Shape"));
+        assert!(browser.pane_lines(Pane::ConstantPool)[0].starts_with("#1 "));
+    }
+
+    #[test]
+    fn test_panes_and_find() {
+        let mut browser = browser();
+        browser.open("Calculator");
+        let methods = browser.pane_lines(Pane::Methods);
+        let add = methods
+            .iter()
+            .position(|method| method.contains("add("))
+            .unwrap();
+
+        // Enter on a method jumps to its disassembly
+        press(&mut browser, KeyCode::Tab);
+        press(&mut browser, KeyCode::Char('3'));
+        for _ in 0..add {
+            press(&mut browser, KeyCode::Down);
+        }
+        press(&mut browser, KeyCode::Enter);
+        assert_eq!(browser.pane(), Pane::Disassembly);
+        let line = browser.details_line();
+        assert!(browser.pane_lines(Pane::Disassembly)[line].contains("add("));
+
+        assert!(browser.find("return", true));
+        assert!(browser.pane_lines(Pane::Disassembly)[browser.details_line()].contains("return"));
+        assert!(!browser.find("no such text", true));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| browser.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Calculator"));
+        assert!(screen.contains("4 Disassembly"));
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use crate::class::attributes::Attribute;
+use crate::class::constant_pool::Constant;
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::Class;
+use crate::vm::natives::lang::{double_to_string, float_to_string, value_chars};
+use crate::vm::runtime::ClassId;
+use crate::vm::value::Value;
+use crate::vm::{Unwind, Vm};
+
+/// The bootstrap class of the string concatenation javac emits since Java 9.
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+
+/// Tags of the recipes of `makeConcatWithConstants`.
+const ARGUMENT_TAG: char = '\u{1}';
+const CONSTANT_TAG: char = '\u{2}';
+
+/// An `invokedynamic` call site. The VM implements the bootstrap methods it
+/// supports itself instead of running them, linking the call site to its
+/// native behaviour.
+#[derive(Debug)]
+pub(crate) enum CallSite {
+    /// `StringConcatFactory.makeConcat` and `makeConcatWithConstants`,
+    /// converting the arguments of the parameter types like `String.valueOf`.
+    Concat {
+        parameters: Vec<FieldType>,
+        parts: Vec<ConcatPart>,
+    },
+}
+
+impl CallSite {
+    /// The number of arguments the call site takes off the operand stack.
+    pub(crate) fn argument_count(&self) -> usize {
+        match self {
+            CallSite::Concat { parameters, .. } => parameters.len(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ConcatPart {
+    Literal(Vec<u16>),
+    /// The argument at the index.
+    Argument(usize),
+}
+
+impl Vm {
+    /// Links the `invokedynamic` call site of the class' constant pool, once,
+    /// throwing `BootstrapMethodError` if its bootstrap method is not
+    /// supported.
+    pub(crate) fn resolve_call_site(
+        &mut self,
+        class: ClassId,
+        index: u16,
+    ) -> Result<Arc<CallSite>, Unwind> {
+        if let Some(site) = self.call_sites.get(&(class, index)) {
+            return Ok(site.clone());
+        }
+
+        let source = self.constant_pool_of(class)?;
+        let site = match link(&source.class, index) {
+            Ok(site) => Arc::new(site),
+            Err(message) => {
+                return Err(self.throw_new("java/lang/BootstrapMethodError", Some(message)))
+            }
+        };
+        self.call_sites.insert((class, index), site.clone());
+        Ok(site)
+    }
+
+    /// Invokes the call site with the arguments of its descriptor.
+    pub(crate) fn invoke_call_site(
+        &mut self,
+        site: &CallSite,
+        arguments: &[Value],
+    ) -> Result<Value, Unwind> {
+        match site {
+            CallSite::Concat { parameters, parts } => {
+                let mut chars = Vec::new();
+                for part in parts {
+                    match part {
+                        ConcatPart::Literal(literal) => chars.extend_from_slice(literal),
+                        ConcatPart::Argument(index) => {
+                            chars.extend(value_chars(self, arguments[*index], &parameters[*index])?)
+                        }
+                    }
+                }
+                Ok(Value::Reference(Some(self.create_string(chars)?)))
+            }
+        }
+    }
+}
+
+// =============================================================================
+// LINKING
+// =============================================================================
+
+/// Links the call site of the `InvokeDynamic` constant to the behaviour of
+/// its bootstrap method.
+fn link(class: &Class, index: u16) -> Result<CallSite, String> {
+    let pool = &class.constant_pool;
+    let dynamic = match pool.get(index as usize) {
+        Some(Constant::InvokeDynamic(dynamic)) => dynamic,
+        _ => return Err(format!("Constant #{} is not an invokedynamic", index)),
+    };
+    let (_, descriptor) = pool
+        .get_name_and_type(dynamic.name_and_type_index)
+        .map_err(|error| error.to_string())?;
+    let descriptor = MethodDescriptor::parse(descriptor).map_err(|error| error.to_string())?;
+
+    let bootstrap = class
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(methods) => {
+                methods.get(dynamic.bootstrap_method_attr_index as usize)
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            format!(
+                "Missing bootstrap method #{}",
+                dynamic.bootstrap_method_attr_index
+            )
+        })?;
+    let (owner, name, _) = match pool.get(bootstrap.bootstrap_method_ref as usize) {
+        Some(Constant::MethodHandle(handle)) => pool
+            .get_member(handle.reference_index)
+            .map_err(|error| error.to_string())?,
+        _ => return Err("Bootstrap method is not a method handle".to_string()),
+    };
+
+    let parameters = descriptor.parameters;
+    let parts = match (owner, name) {
+        (STRING_CONCAT_FACTORY, "makeConcat") => {
+            (0..parameters.len()).map(ConcatPart::Argument).collect()
+        }
+        (STRING_CONCAT_FACTORY, "makeConcatWithConstants") => {
+            let constants = bootstrap
+                .bootstrap_arguments
+                .iter()
+                .map(|&constant| constant_chars(class, constant))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (recipe, constants) = match constants.split_first() {
+                Some((recipe, constants)) => (String::from_utf16_lossy(recipe), constants),
+                None => return Err("Missing string concatenation recipe".to_string()),
+            };
+            concat_recipe(&recipe, constants, parameters.len())?
+        }
+        _ => {
+            return Err(format!(
+                "Unsupported bootstrap method {}.{}",
+                owner.replace('/', "."),
+                name
+            ))
+        }
+    };
+
+    Ok(CallSite::Concat { parameters, parts })
+}
+
+/// The text of a static argument of a bootstrap method.
+fn constant_chars(class: &Class, index: u16) -> Result<Vec<u16>, String> {
+    let pool = &class.constant_pool;
+    let text = match pool.get(index as usize) {
+        Some(Constant::String(string)) => pool
+            .get_utf8(string.string_index)
+            .map_err(|error| error.to_string())?
+            .to_string(),
+        Some(Constant::Integer(integer)) => integer.value.to_string(),
+        Some(Constant::Long(long)) => long.value.to_string(),
+        Some(Constant::Float(float)) => float_to_string(float.value),
+        Some(Constant::Double(double)) => double_to_string(double.value),
+        _ => return Err(format!("Unsupported bootstrap argument #{}", index)),
+    };
+    Ok(text.encode_utf16().collect())
+}
+
+/// Splits the recipe into literals, with its constants inlined, and the
+/// arguments in order.
+fn concat_recipe(
+    recipe: &str,
+    constants: &[Vec<u16>],
+    arguments: usize,
+) -> Result<Vec<ConcatPart>, String> {
+    let mut parts = Vec::new();
+    let mut literal = Vec::new();
+    let (mut argument, mut constant) = (0, 0);
+    for character in recipe.chars() {
+        match character {
+            ARGUMENT_TAG => {
+                if !literal.is_empty() {
+                    parts.push(ConcatPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(ConcatPart::Argument(argument));
+                argument += 1;
+            }
+            CONSTANT_TAG => {
+                let value = constants
+                    .get(constant)
+                    .ok_or("Missing constant of the concatenation recipe")?;
+                literal.extend_from_slice(value);
+                constant += 1;
+            }
+            character => {
+                let mut buffer = [0; 2];
+                literal.extend_from_slice(character.encode_utf16(&mut buffer));
+            }
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(ConcatPart::Literal(literal));
+    }
+
+    if argument != arguments {
+        return Err(format!(
+            "Concatenation recipe takes {} arguments, the call site {}",
+            argument, arguments
+        ));
+    }
+    Ok(parts)
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod call_site_tests {
+    use super::*;
+
+    #[test]
+    fn test_concat_recipe() {
+        let utf16 = |string: &str| string.encode_utf16().collect::<Vec<_>>();
+        let parts = concat_recipe("a=\u{1}, \u{2}\u{1}!", &[utf16("b=")], 2).unwrap();
+        assert_eq!(
+            parts,
+            vec![
+                ConcatPart::Literal(utf16("a=")),
+                ConcatPart::Argument(0),
+                ConcatPart::Literal(utf16(", b=")),
+                ConcatPart::Argument(1),
+                ConcatPart::Literal(utf16("!")),
+            ]
+        );
+
+        assert!(concat_recipe("\u{2}", &[], 0).is_err());
+        assert!(concat_recipe("\u{1}", &[], 2).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Write};
+
+use crate::class::attributes::Attribute;
+use crate::class::instruction::{decode, Instruction};
+use crate::class::{Class, ClassAccessFlags, ClassLoadingError, MethodAccessFlags};
+use crate::vm::metrics::json_string;
+
+// =============================================================================
+// CALL GRAPH
+// =============================================================================
+
+/// A method by its class, name and descriptor.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodRef {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodRef {
+    pub fn new(class: &str, name: &str, descriptor: &str) -> Self {
+        MethodRef {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for MethodRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}:{}", self.class, self.name, self.descriptor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CallKind {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+}
+
+impl fmt::Display for CallKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallKind::Static => write!(f, "static"),
+            CallKind::Special => write!(f, "special"),
+            CallKind::Virtual => write!(f, "virtual"),
+            CallKind::Interface => write!(f, "interface"),
+        }
+    }
+}
+
+/// A method the invoke instruction at the pc of the caller may run.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Call {
+    pub caller: MethodRef,
+    pub pc: u32,
+    pub callee: MethodRef,
+    pub kind: CallKind,
+}
+
+/// The methods of a set of classes and the calls between them.
+///
+/// Virtual and interface calls go to every implementation a subtype of the
+/// referenced class inherits, a class hierarchy analysis. Methods of classes
+/// outside the set are leaves, called as referenced. `invokedynamic` call
+/// sites are not followed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallGraph {
+    /// The methods declared by the classes of the set.
+    pub methods: BTreeSet<MethodRef>,
+    /// The calls, sorted by caller and pc.
+    pub calls: Vec<Call>,
+}
+
+/// What resolution needs to know of a class.
+pub(crate) struct ClassNode {
+    pub(crate) super_class: Option<String>,
+    pub(crate) interfaces: Vec<String>,
+    pub(crate) is_abstract: bool,
+    /// Names and descriptors of the methods, with whether they are abstract.
+    pub(crate) methods: BTreeMap<(String, String), bool>,
+}
+
+/// The subtyping relations between a set of classes.
+pub(crate) struct Hierarchy {
+    pub(crate) classes: BTreeMap<String, ClassNode>,
+    /// Direct subclasses and implementors of each class.
+    subtypes: BTreeMap<String, Vec<String>>,
+}
+
+impl Hierarchy {
+    pub(crate) fn new(classes: &[Class]) -> Result<Self, ClassLoadingError> {
+        let mut hierarchy = Hierarchy {
+            classes: BTreeMap::new(),
+            subtypes: BTreeMap::new(),
+        };
+        for class in classes {
+            let pool = &class.constant_pool;
+            let name = class.name()?.to_string();
+            let super_class = class.super_class_name()?.map(String::from);
+            let interfaces = class
+                .interfaces
+                .iter()
+                .map(|interface| {
+                    pool.get_class_name(interface.interface_index)
+                        .map(String::from)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut methods = BTreeMap::new();
+            for method in &class.methods {
+                let key = (
+                    pool.get_utf8(method.name_index)?.to_string(),
+                    pool.get_utf8(method.descriptor_index)?.to_string(),
+                );
+                methods.insert(
+                    key,
+                    method.access_flags.contains(MethodAccessFlags::ABSTRACT),
+                );
+            }
+
+            for parent in super_class.iter().chain(&interfaces) {
+                hierarchy
+                    .subtypes
+                    .entry(parent.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
+            let flags = class.access_flags;
+            let is_abstract = flags.contains(ClassAccessFlags::ABSTRACT)
+                || flags.contains(ClassAccessFlags::INTERFACE);
+            hierarchy.classes.insert(
+                name,
+                ClassNode {
+                    super_class,
+                    interfaces,
+                    is_abstract,
+                    methods,
+                },
+            );
+        }
+        Ok(hierarchy)
+    }
+
+    /// The class declaring the method the class has, with whether it is
+    /// abstract, searching the superclasses then the superinterfaces for a
+    /// non abstract one. Classes outside the set are not searched.
+    pub(crate) fn resolve(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(String, bool)> {
+        let key = (name.to_string(), descriptor.to_string());
+        let mut current = Some(class.to_string());
+        let mut interfaces = VecDeque::new();
+        while let Some(class) = current {
+            let node = match self.classes.get(&class) {
+                Some(node) => node,
+                None => break,
+            };
+            if let Some(&is_abstract) = node.methods.get(&key) {
+                return Some((class, is_abstract));
+            }
+            interfaces.extend(node.interfaces.iter().cloned());
+            current = node.super_class.clone();
+        }
+
+        let mut abstract_declaration = None;
+        let mut seen = BTreeSet::new();
+        while let Some(interface) = interfaces.pop_front() {
+            if !seen.insert(interface.clone()) {
+                continue;
+            }
+            let node = match self.classes.get(&interface) {
+                Some(node) => node,
+                None => continue,
+            };
+            match node.methods.get(&key) {
+                Some(false) => return Some((interface, false)),
+                Some(true) if abstract_declaration.is_none() => {
+                    abstract_declaration = Some((interface, true))
+                }
+                _ => interfaces.extend(node.interfaces.iter().cloned()),
+            }
+        }
+        abstract_declaration
+    }
+
+    /// The class and its transitive subclasses and implementors.
+    fn subtypes_of(&self, class: &str) -> BTreeSet<String> {
+        let mut subtypes = BTreeSet::new();
+        let mut pending = vec![class.to_string()];
+        while let Some(class) = pending.pop() {
+            if let Some(children) = self.subtypes.get(&class) {
+                pending.extend(children.iter().cloned());
+            }
+            subtypes.insert(class);
+        }
+        subtypes
+    }
+
+    /// The methods a call of the referenced method may run.
+    fn targets(&self, kind: CallKind, referenced: &MethodRef) -> Vec<MethodRef> {
+        let resolve = |class: &str| {
+            self.resolve(class, &referenced.name, &referenced.descriptor)
+                .map(|(class, is_abstract)| {
+                    let method = MethodRef::new(&class, &referenced.name, &referenced.descriptor);
+                    (method, is_abstract)
+                })
+        };
+        let declared = resolve(&referenced.class)
+            .map(|(method, _)| method)
+            .unwrap_or_else(|| referenced.clone());
+        match kind {
+            CallKind::Static | CallKind::Special => vec![declared],
+            CallKind::Virtual | CallKind::Interface => {
+                let mut targets: BTreeSet<MethodRef> = self
+                    .subtypes_of(&referenced.class)
+                    .iter()
+                    .filter(|class| {
+                        self.classes
+                            .get(*class)
+                            .is_some_and(|node| !node.is_abstract)
+                    })
+                    .filter_map(|class| resolve(class))
+                    .filter(|(_, is_abstract)| !is_abstract)
+                    .map(|(method, _)| method)
+                    .collect();
+                if targets.is_empty() {
+                    targets.insert(declared);
+                }
+                targets.into_iter().collect()
+            }
+        }
+    }
+}
+
+impl CallGraph {
+    pub fn build(classes: &[Class]) -> Result<Self, ClassLoadingError> {
+        let hierarchy = Hierarchy::new(classes)?;
+        let mut graph = CallGraph::default();
+        for class in classes {
+            let pool = &class.constant_pool;
+            let class_name = class.name()?;
+            for method in &class.methods {
+                let caller = MethodRef::new(
+                    class_name,
+                    pool.get_utf8(method.name_index)?,
+                    pool.get_utf8(method.descriptor_index)?,
+                );
+                graph.methods.insert(caller.clone());
+                let code = method
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::Code(code) => Some(code),
+                        _ => None,
+                    });
+                let code = match code {
+                    Some(code) => decode(&code.code),
+                    None => continue,
+                };
+
+                for (&instruction, &pc) in code.instructions.iter().zip(&code.pcs) {
+                    let (kind, index) = match instruction {
+                        Instruction::InvokeStatic(index) => (CallKind::Static, index),
+                        Instruction::InvokeSpecial(index) => (CallKind::Special, index),
+                        Instruction::InvokeVirtual(index, _) => (CallKind::Virtual, index),
+                        Instruction::InvokeInterface(index, _) => (CallKind::Interface, index),
+                        _ => continue,
+                    };
+                    let (class, name, descriptor) = pool.get_member(index)?;
+                    let referenced = MethodRef::new(class, name, descriptor);
+                    for callee in hierarchy.targets(kind, &referenced) {
+                        graph.calls.push(Call {
+                            caller: caller.clone(),
+                            pc,
+                            callee,
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+        graph.calls.sort();
+        Ok(graph)
+    }
+
+    /// The calls the method makes.
+    pub fn callees<'a>(&'a self, caller: &'a MethodRef) -> impl Iterator<Item = &'a Call> {
+        let start = self.calls.partition_point(|call| call.caller < *caller);
+        self.calls[start..]
+            .iter()
+            .take_while(move |call| call.caller == *caller)
+    }
+
+    /// The calls of the method.
+    pub fn callers<'a>(&'a self, callee: &'a MethodRef) -> impl Iterator<Item = &'a Call> {
+        self.calls.iter().filter(move |call| call.callee == *callee)
+    }
+
+    /// The methods the entry points may call, directly or not, them
+    /// included.
+    pub fn reachable<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a MethodRef>,
+    ) -> BTreeSet<MethodRef> {
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<MethodRef> = entries.into_iter().cloned().collect();
+        while let Some(method) = pending.pop() {
+            if reached.contains(&method) {
+                continue;
+            }
+            pending.extend(self.callees(&method).map(|call| call.callee.clone()));
+            reached.insert(method);
+        }
+        reached
+    }
+
+    /// Writes the graph in Graphviz DOT, one edge per distinct caller,
+    /// callee and kind, methods outside the set of classes dashed.
+    pub fn write_dot<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        writeln!(writer, "digraph calls {{")?;
+        writeln!(writer, "  node [shape=box, fontname=\"monospace\"];")?;
+        for method in &self.methods {
+            writeln!(writer, "  \"{}\";", escape(&method.to_string()))?;
+        }
+        let external: BTreeSet<&MethodRef> = self
+            .calls
+            .iter()
+            .map(|call| &call.callee)
+            .filter(|callee| !self.methods.contains(*callee))
+            .collect();
+        for method in external {
+            writeln!(
+                writer,
+                "  \"{}\" [style=dashed];",
+                escape(&method.to_string())
+            )?;
+        }
+        let edges: BTreeSet<(&MethodRef, &MethodRef, CallKind)> = self
+            .calls
+            .iter()
+            .map(|call| (&call.caller, &call.callee, call.kind))
+            .collect();
+        for (caller, callee, kind) in edges {
+            writeln!(
+                writer,
+                "  \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape(&caller.to_string()),
+                escape(&callee.to_string()),
+                kind
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes the graph as a JSON object with the `methods` of the set of
+    /// classes and every call site's `calls`.
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        write!(writer, "{{\"methods\":[")?;
+        for (index, method) in self.methods.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{}", json_string(&method.to_string()))?;
+        }
+        write!(writer, "],\"calls\":[")?;
+        for (index, call) in self.calls.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"caller\":{},\"pc\":{},\"callee\":{},\"kind\":\"{}\"}}",
+                json_string(&call.caller.to_string()),
+                call.pc,
+                json_string(&call.callee.to_string()),
+                call.kind
+            )?;
+        }
+        writeln!(writer, "]}}")
+    }
+}
+
+/// Escapes a Graphviz string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod callgraph_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{CallGraph, CallKind, MethodRef};
+    use crate::class::Class;
+
+    #[test]
+    fn test_call_graph() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let classes: Vec<Class> = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "class")
+            })
+            .filter(|path| path.to_string_lossy().contains("Dispatch"))
+            .map(|path| Class::parse_bytes(&fs::read(path).unwrap()).unwrap())
+            .collect();
+        let graph = CallGraph::build(&classes).unwrap();
+
+        // total(Shape[], int) calls shape.area() on the interface
+        let total = MethodRef::new("Dispatch", "total", "([LDispatch$Shape;I)I");
+        let mut areas: Vec<String> = graph
+            .callees(&total)
+            .filter(|call| call.kind == CallKind::Interface)
+            .map(|call| call.callee.class.clone())
+            .collect();
+        areas.dedup();
+        assert_eq!(
+            areas,
+            vec![
+                "Dispatch$Circle",
+                "Dispatch$Empty",
+                "Dispatch$Rectangle",
+                "Dispatch$Square",
+                "Dispatch$Triangle",
+                "Dispatch$Unit"
+            ]
+        );
+
+        let object = MethodRef::new("java/lang/Object", "<init>", "()V");
+        assert!(graph.reachable([&total]).len() > areas.len());
+        assert!(graph.callers(&object).count() > 0);
+        assert!(!graph.methods.contains(&object));
+    }
+}
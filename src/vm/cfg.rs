@@ -0,0 +1,366 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use crate::class::attributes::ExceptionTableAttribute;
+use crate::class::instruction::{DecodedCode, Instruction};
+
+// =============================================================================
+// CONTROL-FLOW GRAPH
+// =============================================================================
+
+/// A maximal run of instructions entered only at its first one and left only
+/// after its last one, or by an exception.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Indices of the instructions of the block.
+    pub instructions: Range<usize>,
+    /// Blocks control flows to from the last instruction.
+    pub successors: Vec<usize>,
+    /// Handler blocks of the exceptions thrown in the block, with their
+    /// catch type's constant pool index, 0 for `finally` handlers.
+    pub exception_successors: Vec<(usize, u16)>,
+}
+
+/// The basic blocks of a method, in bytecode order, the first one being its
+/// entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Splits the decoded code into basic blocks. Blocks also start and end
+    /// at the bounds of the ranges covered by exception handlers, so a block
+    /// is either entirely covered by a handler or not at all.
+    pub fn build(code: &DecodedCode, exception_table: &[ExceptionTableAttribute]) -> Self {
+        let count = code.instructions.len();
+        if count == 0 {
+            return ControlFlowGraph::default();
+        }
+        // The instruction at, or the end of the code for, a pc
+        let index_of = |pc: u16| code.pcs.partition_point(|&start| start < pc as u32);
+
+        let mut leaders = vec![false; count + 1];
+        leaders[0] = true;
+        leaders[count] = true;
+        // jsr returns to the instruction after it, from any ret
+        let return_sites: Vec<usize> = (0..count)
+            .filter(|&index| matches!(code.instructions[index], Instruction::Jsr(_)))
+            .map(|index| index + 1)
+            .filter(|&index| index < count)
+            .collect();
+        for (index, &instruction) in code.instructions.iter().enumerate() {
+            let (targets, falls_through) = branches(code, instruction, &return_sites);
+            if !targets.is_empty() || !falls_through {
+                leaders[index + 1] = true;
+            }
+            for target in targets {
+                leaders[target] = true;
+            }
+        }
+        for entry in exception_table {
+            leaders[index_of(entry.start_pc)] = true;
+            leaders[index_of(entry.end_pc)] = true;
+            leaders[index_of(entry.handler_pc)] = true;
+        }
+
+        let starts: Vec<usize> = (0..count).filter(|&index| leaders[index]).collect();
+        let block_of = |index: usize| starts.partition_point(|&start| start <= index) - 1;
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(block, &start)| {
+                let end = starts.get(block + 1).copied().unwrap_or(count);
+                let (targets, falls_through) =
+                    branches(code, code.instructions[end - 1], &return_sites);
+                let mut successors: Vec<usize> = targets.into_iter().map(block_of).collect();
+                if falls_through && end < count {
+                    successors.insert(0, block + 1);
+                }
+                dedup(&mut successors);
+
+                let exception_successors = exception_table
+                    .iter()
+                    .filter(|entry| {
+                        (index_of(entry.start_pc)..index_of(entry.end_pc)).contains(&start)
+                    })
+                    .filter(|entry| index_of(entry.handler_pc) < count)
+                    .map(|entry| (block_of(index_of(entry.handler_pc)), entry.catch_type))
+                    .collect();
+
+                BasicBlock {
+                    instructions: start..end,
+                    successors,
+                    exception_successors,
+                }
+            })
+            .collect();
+        ControlFlowGraph { blocks }
+    }
+
+    /// The block holding the instruction.
+    pub fn block_of(&self, instruction: usize) -> Option<usize> {
+        let block = self
+            .blocks
+            .partition_point(|block| block.instructions.start <= instruction);
+        block
+            .checked_sub(1)
+            .filter(|&block| self.blocks[block].instructions.contains(&instruction))
+    }
+
+    /// The immediate dominator of every block, the entry being its own and
+    /// blocks unreachable from it having none. Exception edges count as
+    /// edges, so a handler is dominated by the blocks it covers.
+    pub fn dominators(&self) -> Vec<Option<usize>> {
+        let count = self.blocks.len();
+        let successors = |block: usize| {
+            let block = &self.blocks[block];
+            block.successors.iter().copied().chain(
+                block
+                    .exception_successors
+                    .iter()
+                    .map(|&(handler, _)| handler),
+            )
+        };
+
+        // Reverse postorder, iteratively
+        let mut order = Vec::with_capacity(count);
+        let mut visited = vec![false; count];
+        let mut stack = Vec::new();
+        if count > 0 {
+            visited[0] = true;
+            stack.push((0, successors(0).collect::<Vec<_>>()));
+        }
+        while let Some((block, pending)) = stack.last_mut() {
+            match pending.pop() {
+                Some(successor) if !visited[successor] => {
+                    visited[successor] = true;
+                    let next = successors(successor).collect();
+                    stack.push((successor, next));
+                }
+                Some(_) => {}
+                None => {
+                    order.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+        let mut position = vec![usize::MAX; count];
+        for (index, &block) in order.iter().enumerate() {
+            position[block] = index;
+        }
+        let mut predecessors = vec![Vec::new(); count];
+        for &block in &order {
+            for successor in successors(block) {
+                predecessors[successor].push(block);
+            }
+        }
+
+        // Cooper, Harvey and Kennedy's iteration to the fixed point
+        let mut dominators = vec![None; count];
+        if count > 0 {
+            dominators[0] = Some(0);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut dominator: Option<usize> = None;
+                for &predecessor in &predecessors[block] {
+                    if dominators[predecessor].is_none() {
+                        continue;
+                    }
+                    dominator = Some(match dominator {
+                        None => predecessor,
+                        Some(mut other) => {
+                            let mut predecessor = predecessor;
+                            while predecessor != other {
+                                while position[predecessor] > position[other] {
+                                    predecessor = dominators[predecessor].unwrap();
+                                }
+                                while position[other] > position[predecessor] {
+                                    other = dominators[other].unwrap();
+                                }
+                            }
+                            other
+                        }
+                    });
+                }
+                if dominator.is_some() && dominators[block] != dominator {
+                    dominators[block] = dominator;
+                    changed = true;
+                }
+            }
+        }
+        dominators
+    }
+
+    /// Writes the blocks as Graphviz nodes named by the prefix and the block
+    /// index, labeled with their instructions, followed by the edges between
+    /// them, exception edges dashed and labeled with their catch type.
+    pub fn write_dot<W: Write>(
+        &self,
+        writer: &mut W,
+        prefix: &str,
+        instruction: impl Fn(usize) -> String,
+        catch_type: impl Fn(u16) -> String,
+    ) -> fmt::Result {
+        for (index, block) in self.blocks.iter().enumerate() {
+            let label: String = block
+                .instructions
+                .clone()
+                .map(|index| format!("{}\\l", escape(&instruction(index))))
+                .collect();
+            writeln!(writer, "    {}{} [label=\"{}\"];", prefix, index, label)?;
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            for successor in &block.successors {
+                writeln!(
+                    writer,
+                    "    {}{} -> {}{};",
+                    prefix, index, prefix, successor
+                )?;
+            }
+            for (handler, catch) in &block.exception_successors {
+                writeln!(
+                    writer,
+                    "    {}{} -> {}{} [style=dashed, label=\"{}\"];",
+                    prefix,
+                    index,
+                    prefix,
+                    handler,
+                    escape(&catch_type(*catch))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The instructions control can flow to from the instruction, other than the
+/// next one, and whether it flows to the next one.
+fn branches(
+    code: &DecodedCode,
+    instruction: Instruction,
+    return_sites: &[usize],
+) -> (Vec<usize>, bool) {
+    match instruction {
+        Instruction::If(_, target)
+        | Instruction::IfICmp(_, target)
+        | Instruction::IfACmpEq(target)
+        | Instruction::IfACmpNe(target)
+        | Instruction::IfNull(target)
+        | Instruction::IfNonNull(target) => (vec![target as usize], true),
+        Instruction::Goto(target) => (vec![target as usize], false),
+        // The subroutine returns to the next instruction through its ret,
+        // which is the edge carrying the state the subroutine leaves
+        Instruction::Jsr(target) => (vec![target as usize], false),
+        Instruction::Ret(_) => (return_sites.to_vec(), false),
+        Instruction::Switch(index) => {
+            let switch = &code.switches[index as usize];
+            let mut targets = vec![switch.default as usize];
+            targets.extend(switch.cases.iter().map(|&(_, target)| target as usize));
+            (targets, false)
+        }
+        Instruction::ReturnValue | Instruction::Return | Instruction::AThrow => (Vec::new(), false),
+        _ => (Vec::new(), true),
+    }
+}
+
+/// Removes repeated blocks, keeping the first occurrence of each.
+fn dedup(blocks: &mut Vec<usize>) {
+    let mut seen = Vec::with_capacity(blocks.len());
+    blocks.retain(|block| {
+        let new = !seen.contains(block);
+        seen.push(*block);
+        new
+    });
+}
+
+/// Escapes a Graphviz string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod cfg_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{BasicBlock, ControlFlowGraph};
+    use crate::class::attributes::Attribute;
+    use crate::class::instruction::{decode, disassemble};
+    use crate::class::Class;
+
+    #[test]
+    fn test_control_flow_graph() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        let pool = &class.constant_pool;
+        let safe_divide = pool.find_utf8("safeDivide").unwrap();
+        let code = class
+            .methods
+            .iter()
+            .find(|method| method.name_index == safe_divide)
+            .and_then(|method| {
+                method
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::Code(code) => Some(code),
+                        _ => None,
+                    })
+            })
+            .unwrap();
+
+        // try { return a / b; } catch (ArithmeticException e) { return 0; }
+        let decoded = decode(&code.code);
+        let graph = ControlFlowGraph::build(&decoded, &code.exception_tables);
+        let catch_type = code.exception_tables[0].catch_type;
+        assert_eq!(
+            graph.blocks,
+            vec![
+                BasicBlock {
+                    instructions: 0..3,
+                    successors: vec![1],
+                    exception_successors: vec![(2, catch_type)],
+                },
+                BasicBlock {
+                    instructions: 3..4,
+                    successors: vec![],
+                    exception_successors: vec![],
+                },
+                BasicBlock {
+                    instructions: 4..7,
+                    successors: vec![],
+                    exception_successors: vec![],
+                },
+            ]
+        );
+        assert_eq!(graph.block_of(5), Some(2));
+        assert_eq!(graph.block_of(7), None);
+
+        let mut dot = String::new();
+        graph
+            .write_dot(
+                &mut dot,
+                "b",
+                |index| disassemble(&code.code, &decoded, index, pool),
+                |index| pool.describe(index),
+            )
+            .unwrap();
+        assert_eq!(
+            dot,
+            "    b0 [label=\"iload_0\\liload_1\\lidiv\\l\"];\n\
+             \x20   b1 [label=\"ireturn\\l\"];\n\
+             \x20   b2 [label=\"astore_2\\liconst_0\\lireturn\\l\"];\n\
+             \x20   b0 -> b1;\n\
+             \x20   b0 -> b2 [style=dashed, label=\"java/lang/ArithmeticException\"];\n"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use sha2::{Digest, Sha256};
+
+/// `BVMC`, opening every cached value.
+const MAGIC: u32 = 0x4256_4d43;
+const VERSION: u32 = 1;
+
+// =============================================================================
+// CACHED VALUES
+// =============================================================================
+
+/// A value computed from a class file, like its metrics, that a
+/// [ClassCache] stores in a compact binary encoding.
+pub trait Cached: Sized {
+    /// Names the kind of the values, and the version of their encoding, to
+    /// be changed with it: the values of other kinds, or encoded by older
+    /// versions, are never read.
+    const KIND: &'static str;
+
+    fn write(&self, output: &mut dyn Write) -> io::Result<()>;
+
+    fn read(input: &mut dyn Read) -> io::Result<Self>;
+}
+
+/// Writes a string as its length and UTF-8 bytes.
+pub fn write_string(output: &mut dyn Write, string: &str) -> io::Result<()> {
+    let length = u32::try_from(string.len()).map_err(|_| invalid_data("string too long"))?;
+    output.write_u32::<BigEndian>(length)?;
+    output.write_all(string.as_bytes())
+}
+
+/// Reads a string written by [write_string].
+pub fn read_string(input: &mut dyn Read) -> io::Result<String> {
+    let length = input.read_u32::<BigEndian>()?;
+    let mut bytes = Vec::new();
+    input.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("invalid string in class cache"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// =============================================================================
+// CLASS CACHE
+// =============================================================================
+
+/// A directory of values computed from class files, keyed by the SHA-256 of
+/// the class files, so that tools run again over a large classpath only
+/// parse the classes that changed since.
+///
+/// Values are written to a temporary file renamed into place, so that runs
+/// sharing the directory never read one partially written, and unreadable
+/// ones are computed again.
+pub struct ClassCache {
+    directory: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ClassCache {
+    /// Opens the cache in the directory, creating it if missing.
+    pub fn open(directory: &Path) -> io::Result<ClassCache> {
+        fs::create_dir_all(directory)?;
+        Ok(ClassCache {
+            directory: directory.to_path_buf(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// The value of the class file, read from the cache when an earlier run
+    /// computed it, otherwise computed and stored unless it fails.
+    pub fn get_or_compute<T, E, F>(&self, class_file: &[u8], compute: F) -> Result<T, E>
+    where
+        T: Cached,
+        F: FnOnce() -> Result<T, E>,
+    {
+        let path = self.path::<T>(class_file);
+        match fs::read(&path).and_then(|bytes| decode(&bytes)) {
+            Ok(value) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
+            }
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                tracing::debug!(path = %path.display(), %error, "ignoring cached value");
+            }
+            Err(_) => {}
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = compute()?;
+        if let Err(error) = self.store(&path, &value) {
+            tracing::warn!(path = %path.display(), %error, "cannot cache value");
+        }
+        Ok(value)
+    }
+
+    /// The values read from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The values computed, not found in the cache.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// `<directory>/<kind>/<first byte of hash>/<rest of hash>`, in hex.
+    fn path<T: Cached>(&self, class_file: &[u8]) -> PathBuf {
+        let hash: String = Sha256::digest(class_file)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.directory
+            .join(T::KIND)
+            .join(&hash[..2])
+            .join(&hash[2..])
+    }
+
+    fn store<T: Cached>(&self, path: &Path, value: &T) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.write_u32::<BigEndian>(MAGIC)?;
+        bytes.write_u32::<BigEndian>(VERSION)?;
+        value.write(&mut bytes)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(format!(".{}.part", std::process::id()));
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, path)
+    }
+}
+
+fn decode<T: Cached>(bytes: &[u8]) -> io::Result<T> {
+    let mut input = Cursor::new(bytes);
+    if input.read_u32::<BigEndian>()? != MAGIC || input.read_u32::<BigEndian>()? != VERSION {
+        return Err(invalid_data("not a cached value of this version"));
+    }
+    let value = T::read(&mut input)?;
+    if input.position() != bytes.len() as u64 {
+        return Err(invalid_data("trailing bytes after cached value"));
+    }
+    Ok(value)
+}
+
+// ============================================================================
+// CLASS CACHE TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod class_cache_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::ClassCache;
+    use crate::class::{Class, ClassLoadingError};
+    use crate::vm::metrics::ClassMetrics;
+
+    #[test]
+    fn test_values_computed_once() {
+        let directory =
+            std::env::temp_dir().join(format!("bvm-class-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let bytes = fs::read(root.join("Calculator.class")).unwrap();
+        let metrics = || -> Result<ClassMetrics, ClassLoadingError> {
+            ClassMetrics::new(&Class::parse_bytes(&bytes)?)
+        };
+
+        let cache = ClassCache::open(&directory).unwrap();
+        let computed = cache.get_or_compute(&bytes, metrics).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+        // A later run reads the value without parsing the class
+        let cache = ClassCache::open(&directory).unwrap();
+        let cached = cache
+            .get_or_compute(&bytes, || -> Result<ClassMetrics, ClassLoadingError> {
+                panic!("computed again")
+            })
+            .unwrap();
+        assert_eq!(cached, computed);
+        assert_eq!((cache.hits(), cache.misses()), (1, 0));
+
+        // A corrupted value is computed again
+        let files: Vec<_> = walk(&directory);
+        assert_eq!(files.len(), 1);
+        fs::write(&files[0], b"BVMC").unwrap();
+        assert_eq!(cache.get_or_compute(&bytes, metrics).unwrap(), computed);
+        assert_eq!(cache.misses(), 1);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    fn walk(directory: &std::path::Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            match path.is_dir() {
+                true => files.extend(walk(&path)),
+                false => files.push(path),
+            }
+        }
+        files
+    }
+}
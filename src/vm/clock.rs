@@ -0,0 +1,93 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The time observed by guest code through `System.currentTimeMillis` and
+/// `System.nanoTime`. Embedders can provide their own to virtualize it.
+pub trait Clock: Send {
+    /// Milliseconds since the Unix epoch.
+    fn current_time_millis(&self) -> i64;
+
+    /// Nanoseconds since an arbitrary but fixed origin.
+    fn nano_time(&self) -> i64;
+
+    /// Blocks the guest for the duration, like `Thread.sleep` and the timed
+    /// waits do.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The host's wall clock, with `nanoTime` measured from the creation of the
+/// clock.
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn current_time_millis(&self) -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_millis() as i64,
+            Err(error) => -(error.duration().as_millis() as i64),
+        }
+    }
+
+    fn nano_time(&self) -> i64 {
+        self.origin.elapsed().as_nanos() as i64
+    }
+}
+
+/// A clock only advancing as the guest sleeps, from a fixed time, so that
+/// programs reading the time run the same whatever the speed of the host.
+/// Busy waiting for the time to pass never ends on it.
+pub struct VirtualClock {
+    epoch_millis: i64,
+    elapsed: Mutex<Duration>,
+}
+
+impl VirtualClock {
+    /// A clock starting at the milliseconds since the Unix epoch.
+    pub fn new(epoch_millis: i64) -> Self {
+        VirtualClock {
+            epoch_millis,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        VirtualClock::new(0)
+    }
+}
+
+impl Clock for VirtualClock {
+    fn current_time_millis(&self) -> i64 {
+        self.epoch_millis + self.elapsed().as_millis() as i64
+    }
+
+    fn nano_time(&self) -> i64 {
+        self.elapsed().as_nanos() as i64
+    }
+
+    fn sleep(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
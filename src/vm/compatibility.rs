@@ -0,0 +1,472 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::class::attributes::Attribute;
+use crate::class::{Class, ClassAccessFlags, ClassLoadingError};
+
+// =============================================================================
+// CHANGES
+// =============================================================================
+
+/// How a change affects the clients of a library, from the least to the
+/// most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    /// Clients keep compiling and linking.
+    Compatible,
+    /// Compiled clients keep linking, but their sources may not compile.
+    SourceIncompatible,
+    /// Compiled clients may fail to link or behave differently.
+    BinaryIncompatible,
+}
+
+impl Compatibility {
+    /// The part of a semantic version the changes require to bump.
+    pub fn required_bump(self, changed: bool) -> &'static str {
+        match self {
+            Compatibility::BinaryIncompatible | Compatibility::SourceIncompatible => "major",
+            Compatibility::Compatible if changed => "minor",
+            Compatibility::Compatible => "patch",
+        }
+    }
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compatibility::Compatible => write!(f, "compatible"),
+            Compatibility::SourceIncompatible => write!(f, "source incompatible"),
+            Compatibility::BinaryIncompatible => write!(f, "binary incompatible"),
+        }
+    }
+}
+
+/// A change of the API of a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub compatibility: Compatibility,
+    /// The changed class, field or method, e.g. `method Foo.run:()V`.
+    pub subject: String,
+    pub description: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.compatibility, self.subject, self.description
+        )
+    }
+}
+
+/// The API changes between two versions of a library.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub changes: Vec<Change>,
+}
+
+impl CompatibilityReport {
+    /// The most severe compatibility of the changes.
+    pub fn compatibility(&self) -> Compatibility {
+        self.changes
+            .iter()
+            .map(|change| change.compatibility)
+            .max()
+            .unwrap_or(Compatibility::Compatible)
+    }
+
+    pub fn required_bump(&self) -> &'static str {
+        self.compatibility().required_bump(!self.changes.is_empty())
+    }
+
+    fn push(&mut self, compatibility: Compatibility, subject: &str, description: &str) {
+        self.changes.push(Change {
+            compatibility,
+            subject: subject.to_string(),
+            description: description.to_string(),
+        });
+    }
+}
+
+// =============================================================================
+// RULES
+// =============================================================================
+
+const PUBLIC: u16 = 0x0001;
+const PRIVATE: u16 = 0x0002;
+const PROTECTED: u16 = 0x0004;
+const STATIC: u16 = 0x0008;
+const FINAL: u16 = 0x0010;
+const ABSTRACT: u16 = 0x0400;
+
+/// How widely a member can be accessed: private, package, protected or
+/// public.
+fn visibility(flags: u16) -> u8 {
+    if flags & PUBLIC != 0 {
+        3
+    } else if flags & PROTECTED != 0 {
+        2
+    } else if flags & PRIVATE != 0 {
+        0
+    } else {
+        1
+    }
+}
+
+/// Whether clients in other packages may use the member.
+fn is_api(flags: u16) -> bool {
+    visibility(flags) >= 2
+}
+
+/// A field or a method of a class.
+struct Member {
+    flags: u16,
+    constant: Option<String>,
+}
+
+/// Compares the API of two versions of a library, the public and protected
+/// classes and members other packages may use, following the binary
+/// compatibility rules of chapter 13 of the Java Language Specification.
+///
+/// Removing or narrowing what clients may use breaks them, as does changing
+/// a descriptor, which removes the old member. Adding abstract methods to
+/// interfaces or classes only breaks the sources of the implementations,
+/// and changing the value of a constant is binary incompatible as compiled
+/// clients inline the old one.
+pub fn check_compatibility(
+    old: &[Class],
+    new: &[Class],
+) -> Result<CompatibilityReport, ClassLoadingError> {
+    let by_name = |classes: &[Class]| -> Result<BTreeMap<String, usize>, ClassLoadingError> {
+        classes
+            .iter()
+            .enumerate()
+            .map(|(index, class)| Ok((class.name()?.to_string(), index)))
+            .collect()
+    };
+    let (old_names, new_names) = (by_name(old)?, by_name(new)?);
+
+    let mut report = CompatibilityReport::default();
+    for (name, &index) in &old_names {
+        let old_class = &old[index];
+        if !old_class.access_flags.contains(ClassAccessFlags::PUBLIC) {
+            continue;
+        }
+        let subject = format!("class {}", name);
+        match new_names.get(name) {
+            None => report.push(Compatibility::BinaryIncompatible, &subject, "removed"),
+            Some(&index) => check_class(old_class, &new[index], new, &new_names, &mut report)?,
+        }
+    }
+    for (name, &index) in &new_names {
+        if !old_names.contains_key(name)
+            && new[index].access_flags.contains(ClassAccessFlags::PUBLIC)
+        {
+            report.push(
+                Compatibility::Compatible,
+                &format!("class {}", name),
+                "added",
+            );
+        }
+    }
+    Ok(report)
+}
+
+fn check_class(
+    old: &Class,
+    new: &Class,
+    classes: &[Class],
+    names: &BTreeMap<String, usize>,
+    report: &mut CompatibilityReport,
+) -> Result<(), ClassLoadingError> {
+    use Compatibility::*;
+
+    let name = new.name()?;
+    let subject = format!("class {}", name);
+    let (old_flags, new_flags) = (old.access_flags, new.access_flags);
+    if !new_flags.contains(ClassAccessFlags::PUBLIC) {
+        report.push(BinaryIncompatible, &subject, "no longer public");
+        return Ok(());
+    }
+    let interface = ClassAccessFlags::INTERFACE;
+    if old_flags.contains(interface) != new_flags.contains(interface) {
+        report.push(
+            BinaryIncompatible,
+            &subject,
+            "changed between class and interface",
+        );
+        return Ok(());
+    }
+    let is_interface = new_flags.contains(interface);
+    if !is_interface {
+        if !old_flags.contains(ClassAccessFlags::FINAL)
+            && new_flags.contains(ClassAccessFlags::FINAL)
+        {
+            report.push(BinaryIncompatible, &subject, "made final");
+        }
+        if !old_flags.contains(ClassAccessFlags::ABSTRACT)
+            && new_flags.contains(ClassAccessFlags::ABSTRACT)
+        {
+            report.push(BinaryIncompatible, &subject, "made abstract");
+        }
+    }
+
+    // Direct supertypes must stay supertypes, possibly through the new
+    // classes of the set
+    let old_supertypes = direct_supertypes(old)?;
+    let mut new_supertypes = Vec::new();
+    let mut pending = vec![new];
+    while let Some(class) = pending.pop() {
+        for parent in direct_supertypes(class)? {
+            if !new_supertypes.contains(&parent) {
+                if let Some(&index) = names.get(parent) {
+                    pending.push(&classes[index]);
+                }
+                new_supertypes.push(parent);
+            }
+        }
+    }
+    for supertype in &old_supertypes {
+        if !new_supertypes.contains(supertype) {
+            report.push(
+                BinaryIncompatible,
+                &subject,
+                &format!("no longer a subtype of {}", supertype),
+            );
+        }
+    }
+
+    let old_fields = fields(old)?;
+    let new_fields = fields(new)?;
+    for ((field, descriptor), old_field) in &old_fields {
+        if !is_api(old_field.flags) {
+            continue;
+        }
+        let subject = format!("field {}.{}:{}", name, field, descriptor);
+        let new_field = match new_fields.get(&(field.clone(), descriptor.clone())) {
+            Some(new_field) => new_field,
+            None => {
+                let retyped = new_fields.keys().any(|(other, _)| other == field);
+                let description = if retyped { "type changed" } else { "removed" };
+                report.push(BinaryIncompatible, &subject, description);
+                continue;
+            }
+        };
+        check_member(old_field.flags, new_field.flags, &subject, report);
+        if old_field.flags & FINAL == 0 && new_field.flags & FINAL != 0 {
+            report.push(BinaryIncompatible, &subject, "made final");
+        }
+        if old_field.constant != new_field.constant && old_field.constant.is_some() {
+            report.push(
+                BinaryIncompatible,
+                &subject,
+                &format!(
+                    "constant {} changed to {}, compiled clients keep the old value",
+                    old_field.constant.as_deref().unwrap_or_default(),
+                    new_field.constant.as_deref().unwrap_or("none")
+                ),
+            );
+        }
+    }
+    for ((field, descriptor), new_field) in &new_fields {
+        if is_api(new_field.flags) && !old_fields.contains_key(&(field.clone(), descriptor.clone()))
+        {
+            let subject = format!("field {}.{}:{}", name, field, descriptor);
+            report.push(Compatible, &subject, "added");
+        }
+    }
+
+    let class_final = new_flags.contains(ClassAccessFlags::FINAL);
+    let old_methods = methods(old)?;
+    let new_methods = methods(new)?;
+    for ((method, descriptor), old_method) in &old_methods {
+        if !is_api(old_method.flags) || method == "<clinit>" {
+            continue;
+        }
+        let subject = format!("method {}.{}:{}", name, method, descriptor);
+        let new_method = match new_methods.get(&(method.clone(), descriptor.clone())) {
+            Some(new_method) => new_method,
+            None => {
+                let overloaded = new_methods
+                    .iter()
+                    .any(|((other, _), new_method)| other == method && is_api(new_method.flags));
+                let description = if overloaded {
+                    "descriptor changed"
+                } else {
+                    "removed"
+                };
+                report.push(BinaryIncompatible, &subject, description);
+                continue;
+            }
+        };
+        check_member(old_method.flags, new_method.flags, &subject, report);
+        let is_static = new_method.flags & STATIC != 0;
+        if old_method.flags & FINAL == 0
+            && new_method.flags & FINAL != 0
+            && !is_static
+            && !class_final
+        {
+            report.push(BinaryIncompatible, &subject, "made final");
+        }
+        if old_method.flags & ABSTRACT == 0 && new_method.flags & ABSTRACT != 0 {
+            report.push(BinaryIncompatible, &subject, "made abstract");
+        }
+    }
+    for ((method, descriptor), new_method) in &new_methods {
+        let key = (method.clone(), descriptor.clone());
+        if !is_api(new_method.flags) || old_methods.contains_key(&key) {
+            continue;
+        }
+        let subject = format!("method {}.{}:{}", name, method, descriptor);
+        if new_method.flags & ABSTRACT != 0 {
+            let owner = if is_interface { "interface" } else { "class" };
+            report.push(
+                SourceIncompatible,
+                &subject,
+                &format!(
+                    "abstract method added to {}, implementations must add it",
+                    owner
+                ),
+            );
+        } else {
+            report.push(Compatible, &subject, "added");
+        }
+    }
+    Ok(())
+}
+
+/// The superclass and the interfaces of the class.
+fn direct_supertypes(class: &Class) -> Result<Vec<&str>, ClassLoadingError> {
+    let mut supertypes: Vec<&str> = class.super_class_name()?.into_iter().collect();
+    for interface in &class.interfaces {
+        supertypes.push(
+            class
+                .constant_pool
+                .get_class_name(interface.interface_index)?,
+        );
+    }
+    Ok(supertypes)
+}
+
+/// Checks the changes of visibility and of `static` of a kept member.
+fn check_member(old: u16, new: u16, subject: &str, report: &mut CompatibilityReport) {
+    if visibility(new) < visibility(old) {
+        let description = match visibility(new) {
+            2 => "narrowed to protected",
+            1 => "narrowed to package private",
+            _ => "narrowed to private",
+        };
+        report.push(Compatibility::BinaryIncompatible, subject, description);
+    }
+    if old & STATIC != new & STATIC {
+        let description = if new & STATIC != 0 {
+            "made static"
+        } else {
+            "no longer static"
+        };
+        report.push(Compatibility::BinaryIncompatible, subject, description);
+    }
+}
+
+fn fields(class: &Class) -> Result<BTreeMap<(String, String), Member>, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    class
+        .fields
+        .iter()
+        .map(|field| {
+            let constant = field
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::ConstantValue(value) => Some(pool.describe(value.const_value_index)),
+                    _ => None,
+                });
+            let key = (
+                pool.get_utf8(field.name_index)?.to_string(),
+                pool.get_utf8(field.descriptor_index)?.to_string(),
+            );
+            let flags = field.access_flags.bits();
+            Ok((key, Member { flags, constant }))
+        })
+        .collect()
+}
+
+fn methods(class: &Class) -> Result<BTreeMap<(String, String), Member>, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    class
+        .methods
+        .iter()
+        .map(|method| {
+            let key = (
+                pool.get_utf8(method.name_index)?.to_string(),
+                pool.get_utf8(method.descriptor_index)?.to_string(),
+            );
+            let flags = method.access_flags.bits();
+            Ok((
+                key,
+                Member {
+                    flags,
+                    constant: None,
+                },
+            ))
+        })
+        .collect()
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod compatibility_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{check_compatibility, Compatibility};
+    use crate::class::{Class, MethodAccessFlags};
+
+    #[test]
+    fn test_check_compatibility() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let bytes = fs::read(path).unwrap();
+        let old = vec![Class::parse_bytes(&bytes).unwrap()];
+        let mut new = vec![Class::parse_bytes(&bytes).unwrap()];
+        let report = check_compatibility(&old, &new).unwrap();
+        assert!(report.changes.is_empty());
+        assert_eq!(report.required_bump(), "patch");
+
+        // divide(int, int) made package private, greet(String) removed
+        let pool = &new[0].constant_pool;
+        let (divide, greet) = (
+            pool.find_utf8("divide").unwrap(),
+            pool.find_utf8("greet").unwrap(),
+        );
+        for method in &mut new[0].methods {
+            if method.name_index == divide {
+                method.access_flags.remove(MethodAccessFlags::PUBLIC);
+            }
+        }
+        new[0].methods.retain(|method| method.name_index != greet);
+
+        let report = check_compatibility(&old, &new).unwrap();
+        let changes: Vec<String> = report
+            .changes
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "binary incompatible: method Calculator.divide:(II)I: narrowed to package private",
+                "binary incompatible: method Calculator.greet:(Ljava/lang/String;)Ljava/lang/String;: removed",
+            ]
+        );
+        assert_eq!(report.compatibility(), Compatibility::BinaryIncompatible);
+        assert_eq!(report.required_bump(), "major");
+
+        let report = check_compatibility(&new, &old).unwrap();
+        assert_eq!(report.compatibility(), Compatibility::Compatible);
+        assert_eq!(report.required_bump(), "minor");
+    }
+}
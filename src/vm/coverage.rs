@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::class::MethodAccessFlags;
+use crate::vm::loader::LoaderId;
+use crate::vm::runtime::{RuntimeClass, RuntimeMethod};
+
+/// How the coverage report is written when the VM exits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// The tracefile of `lcov`, read by `genhtml` and most coverage services.
+    Lcov,
+    /// The XML report of JaCoCo, read by CI servers and code review tools.
+    JacocoXml,
+}
+
+/// Identifies a method by the address of its runtime representation, which
+/// the VM keeps alive as long as its class.
+type MethodKey = usize;
+
+fn key(method: &RuntimeMethod) -> MethodKey {
+    method as *const RuntimeMethod as MethodKey
+}
+
+/// The executions of a method.
+struct MethodHits {
+    calls: u64,
+    /// How many times each decoded instruction was executed.
+    instructions: Vec<u64>,
+}
+
+/// Counts how many times the instructions of every interpreted method run,
+/// written as an LCOV or JaCoCo XML report of the classes defined by the
+/// application class loader when the VM exits. Lines are mapped from the
+/// `LineNumberTable` of the methods, so classes compiled without line
+/// numbers only report instruction and method coverage.
+///
+/// Methods are always interpreted while coverage is collected.
+pub struct CodeCoverage {
+    format: CoverageFormat,
+    output: Box<dyn Write + Send>,
+    slots: HashMap<MethodKey, usize>,
+    methods: Vec<MethodHits>,
+}
+
+impl CodeCoverage {
+    /// A collector writing an LCOV tracefile to standard error.
+    pub fn new() -> Self {
+        CodeCoverage {
+            format: CoverageFormat::Lcov,
+            output: Box::new(io::stderr()),
+            slots: HashMap::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn format(mut self, format: CoverageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// The index of the hits of a method with code, passed to [Self::hit].
+    pub(crate) fn slot(&mut self, method: &RuntimeMethod) -> usize {
+        let methods = &mut self.methods;
+        *self.slots.entry(key(method)).or_insert_with(|| {
+            let instructions = method
+                .code
+                .as_ref()
+                .map_or(0, |code| code.instructions.len());
+            methods.push(MethodHits {
+                calls: 0,
+                instructions: vec![0; instructions],
+            });
+            methods.len() - 1
+        })
+    }
+
+    /// Records an invocation of a method with code.
+    pub(crate) fn enter(&mut self, method: &RuntimeMethod) {
+        let slot = self.slot(method);
+        self.methods[slot].calls += 1;
+    }
+
+    /// Records the execution of the instruction with the index.
+    pub(crate) fn hit(&mut self, slot: usize, instruction: usize) {
+        self.methods[slot].instructions[instruction] += 1;
+    }
+
+    /// Writes the report of the classes defined by the application loader.
+    pub(crate) fn write(&mut self, classes: &[RuntimeClass]) -> io::Result<()> {
+        let mut reports: Vec<ClassReport> = classes
+            .iter()
+            .filter(|class| class.defining_loader == LoaderId::APPLICATION)
+            .filter(|class| class.source.is_some())
+            .map(|class| self.class_report(class))
+            .collect();
+        reports.sort_by(|a, b| a.name.cmp(b.name));
+
+        match self.format {
+            CoverageFormat::Lcov => write_lcov(&mut self.output, &reports)?,
+            CoverageFormat::JacocoXml => write_jacoco(&mut self.output, &reports)?,
+        }
+        self.output.flush()
+    }
+
+    fn class_report<'a>(&self, class: &'a RuntimeClass) -> ClassReport<'a> {
+        let methods = class
+            .methods
+            .iter()
+            .filter(|method| !method.access_flags.contains(MethodAccessFlags::SYNTHETIC))
+            .filter_map(|method| {
+                let code = method.code.as_ref()?;
+                let hits = self
+                    .slots
+                    .get(&key(method))
+                    .map(|&slot| &self.methods[slot]);
+                let mut report = MethodReport {
+                    method,
+                    calls: hits.map_or(0, |hits| hits.calls),
+                    instructions: Counter::default(),
+                    lines: BTreeMap::new(),
+                };
+                for (index, &pc) in code.instruction_pcs.iter().enumerate() {
+                    let count = hits.map_or(0, |hits| hits.instructions[index]);
+                    report.instructions.add(count > 0);
+                    if let Some(line) = code.line_number(pc as usize) {
+                        report.lines.entry(line).or_default().add(count);
+                    }
+                }
+                Some(report)
+            })
+            .collect();
+
+        let name: &str = &class.name;
+        let source_file = class.source_file().unwrap_or_else(|| {
+            let simple_name = name.rsplit('/').next().unwrap_or(name);
+            let outermost = simple_name.split('$').next().unwrap_or(simple_name);
+            format!("{}.java", outermost)
+        });
+        ClassReport {
+            name,
+            source_file,
+            methods,
+        }
+    }
+}
+
+impl Default for CodeCoverage {
+    fn default() -> Self {
+        CodeCoverage::new()
+    }
+}
+
+// =============================================================================
+// REPORT
+// =============================================================================
+
+/// Missed and covered items, like the counters of JaCoCo.
+#[derive(Clone, Copy, Default)]
+struct Counter {
+    missed: u64,
+    covered: u64,
+}
+
+impl Counter {
+    fn add(&mut self, covered: bool) {
+        if covered {
+            self.covered += 1;
+        } else {
+            self.missed += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Counter) {
+        self.missed += other.missed;
+        self.covered += other.covered;
+    }
+}
+
+/// The instructions of a source line.
+#[derive(Clone, Copy, Default)]
+struct LineHits {
+    instructions: Counter,
+    /// How many times the most executed instruction of the line ran.
+    count: u64,
+}
+
+impl LineHits {
+    fn add(&mut self, count: u64) {
+        self.instructions.add(count > 0);
+        self.count = self.count.max(count);
+    }
+
+    fn merge(&mut self, other: LineHits) {
+        self.instructions.merge(other.instructions);
+        self.count = self.count.max(other.count);
+    }
+
+    fn is_covered(&self) -> bool {
+        self.instructions.covered > 0
+    }
+}
+
+struct MethodReport<'a> {
+    method: &'a RuntimeMethod,
+    calls: u64,
+    instructions: Counter,
+    lines: BTreeMap<u16, LineHits>,
+}
+
+impl MethodReport<'_> {
+    fn is_covered(&self) -> bool {
+        self.instructions.covered > 0
+    }
+}
+
+struct ClassReport<'a> {
+    name: &'a str,
+    source_file: String,
+    methods: Vec<MethodReport<'a>>,
+}
+
+impl ClassReport<'_> {
+    fn package(&self) -> &str {
+        self.name
+            .rsplit_once('/')
+            .map_or("", |(package, _)| package)
+    }
+
+    /// The path of the source file relative to the source root.
+    fn source_path(&self) -> String {
+        match self.package() {
+            "" => self.source_file.clone(),
+            package => format!("{}/{}", package, self.source_file),
+        }
+    }
+
+    fn lines(&self) -> BTreeMap<u16, LineHits> {
+        merge_lines(self.methods.iter().map(|method| &method.lines))
+    }
+}
+
+fn merge_lines<'a>(
+    lines: impl IntoIterator<Item = &'a BTreeMap<u16, LineHits>>,
+) -> BTreeMap<u16, LineHits> {
+    let mut merged: BTreeMap<u16, LineHits> = BTreeMap::new();
+    for lines in lines {
+        for (&line, &hits) in lines {
+            merged.entry(line).or_default().merge(hits);
+        }
+    }
+    merged
+}
+
+fn line_counter(lines: &BTreeMap<u16, LineHits>) -> Counter {
+    let mut counter = Counter::default();
+    for hits in lines.values() {
+        counter.add(hits.is_covered());
+    }
+    counter
+}
+
+/// The name of a method in reports, with its class and descriptor.
+fn method_name(class: &ClassReport, method: &RuntimeMethod) -> String {
+    format!("{}.{}{}", class.name, method.name, method.descriptor)
+}
+
+// LCOV ------------------------------------------------------------------------
+
+/// Writes one record per source file, with the classes it declares.
+fn write_lcov(output: &mut dyn Write, classes: &[ClassReport]) -> io::Result<()> {
+    let mut files: BTreeMap<String, Vec<&ClassReport>> = BTreeMap::new();
+    for class in classes {
+        files.entry(class.source_path()).or_default().push(class);
+    }
+
+    for (path, classes) in files {
+        writeln!(output, "TN:")?;
+        writeln!(output, "SF:{}", path)?;
+        let mut functions = Counter::default();
+        for class in &classes {
+            for method in &class.methods {
+                let name = method_name(class, method.method);
+                if let Some(&line) = method.lines.keys().next() {
+                    writeln!(output, "FN:{},{}", line, name)?;
+                }
+                writeln!(output, "FNDA:{},{}", method.calls, name)?;
+                functions.add(method.is_covered());
+            }
+        }
+        writeln!(output, "FNF:{}", functions.missed + functions.covered)?;
+        writeln!(output, "FNH:{}", functions.covered)?;
+        let lines = merge_lines(
+            classes
+                .iter()
+                .flat_map(|class| &class.methods)
+                .map(|method| &method.lines),
+        );
+        for (line, hits) in &lines {
+            writeln!(output, "DA:{},{}", line, hits.count)?;
+        }
+        let counter = line_counter(&lines);
+        writeln!(output, "LF:{}", counter.missed + counter.covered)?;
+        writeln!(output, "LH:{}", counter.covered)?;
+        writeln!(output, "end_of_record")?;
+    }
+    Ok(())
+}
+
+// JACOCO XML ------------------------------------------------------------------
+
+/// The counters of a report element.
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    instructions: Counter,
+    lines: Counter,
+    methods: Counter,
+    classes: Counter,
+}
+
+impl Counters {
+    fn merge(&mut self, other: Counters) {
+        self.instructions.merge(other.instructions);
+        self.lines.merge(other.lines);
+        self.methods.merge(other.methods);
+        self.classes.merge(other.classes);
+    }
+
+    /// Writes the counters counting anything, which is how JaCoCo omits the
+    /// empty ones.
+    fn write(&self, output: &mut dyn Write, indent: &str) -> io::Result<()> {
+        let counters = [
+            ("INSTRUCTION", self.instructions),
+            ("LINE", self.lines),
+            ("METHOD", self.methods),
+            ("CLASS", self.classes),
+        ];
+        for (kind, counter) in counters {
+            if counter.missed + counter.covered > 0 {
+                writeln!(
+                    output,
+                    r#"{}<counter type="{}" missed="{}" covered="{}"/>"#,
+                    indent, kind, counter.missed, counter.covered
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn method_counters(method: &MethodReport) -> Counters {
+    let mut counters = Counters {
+        instructions: method.instructions,
+        lines: line_counter(&method.lines),
+        ..Counters::default()
+    };
+    counters.methods.add(method.is_covered());
+    counters
+}
+
+fn class_counters(class: &ClassReport) -> Counters {
+    let mut counters = Counters::default();
+    for method in &class.methods {
+        counters.merge(method_counters(method));
+    }
+    counters.lines = line_counter(&class.lines());
+    counters.classes.add(counters.methods.covered > 0);
+    counters
+}
+
+/// Writes the classes grouped by package, each followed by the line
+/// coverage of its source files.
+fn write_jacoco(output: &mut dyn Write, classes: &[ClassReport]) -> io::Result<()> {
+    let mut packages: BTreeMap<&str, Vec<&ClassReport>> = BTreeMap::new();
+    for class in classes {
+        packages.entry(class.package()).or_default().push(class);
+    }
+
+    writeln!(
+        output,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(
+        output,
+        r#"<!DOCTYPE report PUBLIC "-//JACOCO//DTD Report 1.1//EN" "report.dtd">"#
+    )?;
+    writeln!(output, r#"<report name="bvm">"#)?;
+    let mut total = Counters::default();
+    for (package, classes) in packages {
+        writeln!(output, r#"  <package name="{}">"#, escape(package))?;
+        let mut package_counters = Counters::default();
+        for class in &classes {
+            writeln!(
+                output,
+                r#"    <class name="{}" sourcefilename="{}">"#,
+                escape(class.name),
+                escape(&class.source_file)
+            )?;
+            for method in &class.methods {
+                let line = match method.lines.keys().next() {
+                    Some(line) => format!(r#" line="{}""#, line),
+                    None => String::new(),
+                };
+                writeln!(
+                    output,
+                    r#"      <method name="{}" desc="{}"{}>"#,
+                    escape(&method.method.name),
+                    escape(&method.method.descriptor),
+                    line
+                )?;
+                method_counters(method).write(output, "        ")?;
+                writeln!(output, "      </method>")?;
+            }
+            let counters = class_counters(class);
+            counters.write(output, "      ")?;
+            writeln!(output, "    </class>")?;
+            package_counters.merge(counters);
+        }
+
+        let mut source_files: BTreeMap<&str, Vec<&ClassReport>> = BTreeMap::new();
+        for class in &classes {
+            source_files
+                .entry(&class.source_file)
+                .or_default()
+                .push(class);
+        }
+        for (source_file, classes) in source_files {
+            writeln!(output, r#"    <sourcefile name="{}">"#, escape(source_file))?;
+            let lines = merge_lines(
+                classes
+                    .iter()
+                    .flat_map(|class| &class.methods)
+                    .map(|method| &method.lines),
+            );
+            for (line, hits) in &lines {
+                writeln!(
+                    output,
+                    r#"      <line nr="{}" mi="{}" ci="{}" mb="0" cb="0"/>"#,
+                    line, hits.instructions.missed, hits.instructions.covered
+                )?;
+            }
+            let mut counters = Counters::default();
+            for class in classes {
+                counters.merge(class_counters(class));
+            }
+            counters.lines = line_counter(&lines);
+            counters.write(output, "      ")?;
+            writeln!(output, "    </sourcefile>")?;
+        }
+        package_counters.write(output, "    ")?;
+        writeln!(output, "  </package>")?;
+        total.merge(package_counters);
+    }
+    total.write(output, "  ")?;
+    writeln!(output, "</report>")
+}
+
+/// Escapes the text of an XML attribute value, like the `<init>` methods.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
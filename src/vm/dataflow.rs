@@ -0,0 +1,396 @@
+use std::collections::VecDeque;
+
+use crate::class::instruction::{DecodedCode, Instruction};
+use crate::vm::cfg::ControlFlowGraph;
+
+// =============================================================================
+// FRAMEWORK
+// =============================================================================
+
+/// The facts of an analysis at a program point, ordered by how much they
+/// tell: joining facts of merging paths only ever moves up.
+pub trait Lattice: Clone + PartialEq {
+    /// Joins the other fact into this one, returning whether it changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Facts flow from the entry along the edges, e.g. reaching definitions.
+    Forward,
+    /// Facts flow from the exits against the edges, e.g. liveness.
+    Backward,
+}
+
+/// A dataflow analysis over the instructions of a method.
+pub trait Analysis {
+    type Fact: Lattice;
+
+    const DIRECTION: Direction;
+
+    /// The fact no path has contributed to yet.
+    fn bottom(&self) -> Self::Fact;
+
+    /// The fact at the entry of the method when going forward, or at its
+    /// returns and throws when going backward.
+    fn boundary(&self) -> Self::Fact;
+
+    /// Applies the effect of the instruction to the fact holding before it,
+    /// or after it when going backward.
+    fn transfer(&self, index: usize, instruction: Instruction, fact: &mut Self::Fact);
+
+    /// The fact a forward analysis passes to the handler catching the
+    /// constant pool's `catch_type`, 0 for `finally`, from a point of a
+    /// covered block. Unchanged by default.
+    fn exception_edge(&self, fact: &Self::Fact, catch_type: u16) -> Self::Fact {
+        let _ = catch_type;
+        fact.clone()
+    }
+}
+
+/// The facts holding at the start and at the end of every basic block, in
+/// bytecode order whatever the direction of the analysis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataflowResults<F> {
+    pub entry: Vec<F>,
+    pub exit: Vec<F>,
+}
+
+/// Runs the analysis to its fixed point with a worklist over the blocks.
+///
+/// Exceptions may leave a block after any of its instructions: going
+/// forward, handlers receive the facts of every point of the blocks they
+/// cover, going backward every point of a covered block joins the facts at
+/// the start of its handlers.
+pub fn solve<A: Analysis>(
+    analysis: &A,
+    code: &DecodedCode,
+    graph: &ControlFlowGraph,
+) -> DataflowResults<A::Fact> {
+    let count = graph.blocks.len();
+    let mut results = DataflowResults {
+        entry: vec![analysis.bottom(); count],
+        exit: vec![analysis.bottom(); count],
+    };
+    if count == 0 {
+        return results;
+    }
+    if A::DIRECTION == Direction::Forward {
+        results.entry[0] = analysis.boundary();
+    }
+
+    let mut predecessors = vec![Vec::new(); count];
+    for (index, block) in graph.blocks.iter().enumerate() {
+        let handlers = block
+            .exception_successors
+            .iter()
+            .map(|&(handler, _)| handler);
+        for successor in block.successors.iter().copied().chain(handlers) {
+            predecessors[successor].push(index);
+        }
+    }
+
+    let mut pending: VecDeque<usize> = match A::DIRECTION {
+        Direction::Forward => (0..count).collect(),
+        Direction::Backward => (0..count).rev().collect(),
+    };
+    let mut queued = vec![true; count];
+    while let Some(index) = pending.pop_front() {
+        queued[index] = false;
+        let block = &graph.blocks[index];
+        let instructions = block.instructions.clone();
+
+        match A::DIRECTION {
+            Direction::Forward => {
+                let mut fact = results.entry[index].clone();
+                // What each handler sees: the fact at any point of the block
+                let mut thrown: Vec<A::Fact> = block
+                    .exception_successors
+                    .iter()
+                    .map(|&(_, catch_type)| analysis.exception_edge(&fact, catch_type))
+                    .collect();
+                for instruction in instructions {
+                    analysis.transfer(instruction, code.instructions[instruction], &mut fact);
+                    for (thrown, &(_, catch_type)) in
+                        thrown.iter_mut().zip(&block.exception_successors)
+                    {
+                        thrown.join(&analysis.exception_edge(&fact, catch_type));
+                    }
+                }
+                results.exit[index] = fact;
+
+                let mut updated = Vec::new();
+                for &successor in &block.successors {
+                    if results.entry[successor].join(&results.exit[index]) {
+                        updated.push(successor);
+                    }
+                }
+                for (&(handler, _), thrown) in block.exception_successors.iter().zip(&thrown) {
+                    if results.entry[handler].join(thrown) {
+                        updated.push(handler);
+                    }
+                }
+                for successor in updated {
+                    if !queued[successor] {
+                        queued[successor] = true;
+                        pending.push_back(successor);
+                    }
+                }
+            }
+            Direction::Backward => {
+                let mut fact = analysis.bottom();
+                if block.successors.is_empty() {
+                    fact.join(&analysis.boundary());
+                }
+                for &successor in &block.successors {
+                    fact.join(&results.entry[successor]);
+                }
+                let mut thrown = analysis.bottom();
+                for &(handler, _) in &block.exception_successors {
+                    thrown.join(&results.entry[handler]);
+                }
+                results.exit[index] = fact.clone();
+                for instruction in instructions.rev() {
+                    fact.join(&thrown);
+                    analysis.transfer(instruction, code.instructions[instruction], &mut fact);
+                }
+
+                if results.entry[index] != fact {
+                    results.entry[index] = fact;
+                    for &predecessor in &predecessors[index] {
+                        if !queued[predecessor] {
+                            queued[predecessor] = true;
+                            pending.push_back(predecessor);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    results
+}
+
+// =============================================================================
+// BIT SETS
+// =============================================================================
+
+/// A fixed size set of small integers, joined by union.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(size: usize) -> Self {
+        BitSet {
+            words: vec![0; size.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, value: usize) {
+        self.words[value / 64] |= 1 << (value % 64);
+    }
+
+    pub fn remove(&mut self, value: usize) {
+        self.words[value / 64] &= !(1 << (value % 64));
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        self.words
+            .get(value / 64)
+            .is_some_and(|word| word & (1 << (value % 64)) != 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| index * 64 + bit)
+        })
+    }
+}
+
+impl Lattice for BitSet {
+    fn join(&mut self, other: &Self) -> bool {
+        let mut changed = false;
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            let joined = *word | other;
+            changed |= joined != *word;
+            *word = joined;
+        }
+        changed
+    }
+}
+
+// =============================================================================
+// ANALYSES
+// =============================================================================
+
+/// The local variable an instruction reads, if any. Wide values are tracked
+/// by their first slot.
+fn read_local(instruction: Instruction) -> Option<u16> {
+    match instruction {
+        Instruction::Load(local) | Instruction::IInc(local, _) | Instruction::Ret(local) => {
+            Some(local)
+        }
+        _ => None,
+    }
+}
+
+/// The local variable an instruction writes, if any.
+fn written_local(instruction: Instruction) -> Option<u16> {
+    match instruction {
+        Instruction::Store(local) | Instruction::IInc(local, _) => Some(local),
+        _ => None,
+    }
+}
+
+/// A write of a local variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Definition {
+    pub local: u16,
+    /// The writing instruction, `None` for the parameters defined on entry.
+    pub instruction: Option<usize>,
+}
+
+/// Which writes of the locals may have been the last ones at each point.
+/// Facts are sets of indices into [ReachingDefinitions::definitions].
+pub struct ReachingDefinitions {
+    pub definitions: Vec<Definition>,
+    parameter_slots: u16,
+}
+
+impl ReachingDefinitions {
+    /// The definitions of the code, the parameters taking the first
+    /// `parameter_slots` locals.
+    pub fn new(code: &DecodedCode, parameter_slots: u16) -> Self {
+        let parameters = (0..parameter_slots).map(|local| Definition {
+            local,
+            instruction: None,
+        });
+        let writes = code
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &instruction)| {
+                Some(Definition {
+                    local: written_local(instruction)?,
+                    instruction: Some(index),
+                })
+            });
+        ReachingDefinitions {
+            definitions: parameters.chain(writes).collect(),
+            parameter_slots,
+        }
+    }
+}
+
+impl Analysis for ReachingDefinitions {
+    type Fact = BitSet;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self) -> BitSet {
+        BitSet::new(self.definitions.len())
+    }
+
+    fn boundary(&self) -> BitSet {
+        let mut fact = self.bottom();
+        for parameter in 0..self.parameter_slots as usize {
+            fact.insert(parameter);
+        }
+        fact
+    }
+
+    fn transfer(&self, index: usize, instruction: Instruction, fact: &mut BitSet) {
+        let local = match written_local(instruction) {
+            Some(local) => local,
+            None => return,
+        };
+        for (id, definition) in self.definitions.iter().enumerate() {
+            if definition.local == local {
+                fact.remove(id);
+            }
+            if definition.instruction == Some(index) {
+                fact.insert(id);
+            }
+        }
+    }
+}
+
+/// Which locals may still be read before being written again at each point.
+/// Facts are sets of local indices.
+pub struct Liveness {
+    pub max_locals: u16,
+}
+
+impl Analysis for Liveness {
+    type Fact = BitSet;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn bottom(&self) -> BitSet {
+        BitSet::new(self.max_locals as usize)
+    }
+
+    fn boundary(&self) -> BitSet {
+        self.bottom()
+    }
+
+    fn transfer(&self, _: usize, instruction: Instruction, fact: &mut BitSet) {
+        if let Some(local) = written_local(instruction) {
+            fact.remove(local as usize);
+        }
+        if let Some(local) = read_local(instruction) {
+            fact.insert(local as usize);
+        }
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod dataflow_tests {
+    use super::{solve, BitSet, Liveness, ReachingDefinitions};
+    use crate::class::instruction::decode;
+    use crate::vm::cfg::ControlFlowGraph;
+
+    #[test]
+    fn test_dataflow() {
+        // int count(int n) { int i = 0; while (i < n) i++; return i; }
+        let code = decode(&[
+            0x03, 0x3c, 0x1b, 0x1a, 0xa2, 0x00, 0x09, 0x84, 0x01, 0x01, 0xa7, 0xff, 0xf8, 0x1b,
+            0xac,
+        ]);
+        let graph = ControlFlowGraph::build(&code, &[]);
+        assert_eq!(graph.blocks.len(), 4);
+
+        let liveness = solve(&Liveness { max_locals: 2 }, &code, &graph);
+        let live: Vec<Vec<usize>> = liveness
+            .entry
+            .iter()
+            .map(|fact| fact.iter().collect())
+            .collect();
+        assert_eq!(live, vec![vec![0], vec![0, 1], vec![0, 1], vec![1]]);
+
+        // The parameter, i = 0 and i++
+        let analysis = ReachingDefinitions::new(&code, 1);
+        assert_eq!(analysis.definitions.len(), 3);
+        let reaching = solve(&analysis, &code, &graph);
+        let reaching: Vec<Vec<usize>> = reaching
+            .exit
+            .iter()
+            .map(|fact| fact.iter().collect())
+            .collect();
+        assert_eq!(
+            reaching,
+            vec![vec![0, 1], vec![0, 1, 2], vec![0, 2], vec![0, 1, 2]]
+        );
+
+        let mut bits = BitSet::new(130);
+        bits.insert(129);
+        assert!(bits.contains(129) && !bits.contains(128));
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeSet;
+
+use crate::class::attributes::Attribute;
+use crate::class::constant_pool::Constant;
+use crate::class::instruction::{decode, Instruction};
+use crate::class::{Class, ClassLoadingError, MethodAccessFlags};
+use crate::vm::callgraph::{CallGraph, Hierarchy, MethodRef};
+
+// =============================================================================
+// KEEP RULES
+// =============================================================================
+
+/// Classes and methods used in ways the bytecode does not show, e.g. by
+/// reflection, which are kept along with everything they call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeepRules {
+    /// Internal names of classes whose methods are all kept.
+    pub classes: BTreeSet<String>,
+    /// Classes and names of methods kept whatever their descriptor.
+    pub methods: BTreeSet<(String, String)>,
+    /// Descriptors of annotations keeping the classes and methods they
+    /// annotate, e.g. `Lcom/example/Keep;`.
+    pub annotations: BTreeSet<String>,
+}
+
+impl KeepRules {
+    /// Adds the rules of a keep file, one per line: a class like
+    /// `com.example.Plugin`, a method like `com.example.Plugin#load`, or an
+    /// annotation like `@com.example.Keep`. `#` starts a comment line.
+    pub fn parse(&mut self, text: &str) {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let internal = |name: &str| name.trim().replace('.', "/");
+            if let Some(annotation) = line.strip_prefix('@') {
+                self.annotations
+                    .insert(format!("L{};", internal(annotation)));
+            } else if let Some((class, method)) = line.split_once('#') {
+                self.methods
+                    .insert((internal(class), method.trim().to_string()));
+            } else {
+                self.classes.insert(internal(line));
+            }
+        }
+    }
+
+    /// Adds the classes of a GraalVM `reflect-config.json`. Every `"name"`
+    /// it holds is kept as a class, method names included, which at worst
+    /// keeps nothing more.
+    pub fn parse_reflection_config(&mut self, json: &str) {
+        let mut rest = json;
+        while let Some(start) = rest.find("\"name\"") {
+            rest = rest[start + 6..].trim_start();
+            let value = match rest.strip_prefix(':') {
+                Some(value) => value.trim_start(),
+                None => continue,
+            };
+            if let Some(value) = value.strip_prefix('"') {
+                if let Some(end) = value.find('"') {
+                    self.classes.insert(value[..end].replace('.', "/"));
+                    rest = &value[end..];
+                }
+            }
+        }
+    }
+
+    fn keeps(&self, class: &str, method: &str, annotated: bool) -> bool {
+        annotated
+            || self.classes.contains(class)
+            || self
+                .methods
+                .contains(&(class.to_string(), method.to_string()))
+    }
+}
+
+// =============================================================================
+// UNUSED CODE
+// =============================================================================
+
+/// The classes and methods of a set of classes no entry point uses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeadCode {
+    /// Classes none of whose methods run and no used code refers to.
+    pub classes: Vec<String>,
+    /// Methods with code never called, of the used classes.
+    pub methods: Vec<MethodRef>,
+}
+
+/// Finds the code unreachable from the entry points and the kept classes
+/// and methods in the call graph of the classes.
+///
+/// Beyond the calls of the graph, the static initializers of used classes
+/// run, methods of constructed classes which may override a method of a
+/// class outside the set are called by it, and methods referred to by
+/// method handles, e.g. lambda bodies, are called through them.
+pub fn find_dead_code(
+    classes: &[Class],
+    graph: &CallGraph,
+    entries: &[MethodRef],
+    keep: &KeepRules,
+) -> Result<DeadCode, ClassLoadingError> {
+    let hierarchy = Hierarchy::new(classes)?;
+    let mut roots: BTreeSet<MethodRef> = entries.iter().cloned().collect();
+    for class in classes {
+        let pool = &class.constant_pool;
+        let class_name = class.name()?;
+        let class_annotated = is_annotated(class, &class.attributes, keep)?;
+        for method in &class.methods {
+            let name = pool.get_utf8(method.name_index)?;
+            if keep.keeps(
+                class_name,
+                name,
+                class_annotated || is_annotated(class, &method.attributes, keep)?,
+            ) {
+                let descriptor = pool.get_utf8(method.descriptor_index)?;
+                roots.insert(MethodRef::new(class_name, name, descriptor));
+            }
+        }
+    }
+
+    // Grows the roots with the implicit calls of the used code until none
+    // is left
+    let (reachable, used) = loop {
+        let reachable = graph.reachable(&roots);
+        let mut used = BTreeSet::new();
+        let mut implicit = BTreeSet::new();
+        for class in classes {
+            let class_name = class.name()?;
+            let methods: Vec<&MethodRef> = reachable
+                .iter()
+                .filter(|method| method.class == class_name)
+                .collect();
+            if methods.is_empty() && !keep.classes.contains(class_name) {
+                continue;
+            }
+            used.insert(class_name.to_string());
+            references(class, &methods, &mut used, &mut implicit)?;
+            if methods.iter().any(|method| method.name == "<init>") {
+                overrides(class, &hierarchy, &mut implicit)?;
+            }
+        }
+        // Superclasses and interfaces of used classes are used too
+        let mut pending: Vec<String> = used.iter().cloned().collect();
+        while let Some(class) = pending.pop() {
+            if let Some(node) = hierarchy.classes.get(&class) {
+                for parent in node.super_class.iter().chain(&node.interfaces) {
+                    if used.insert(parent.clone()) {
+                        pending.push(parent.clone());
+                    }
+                }
+            }
+        }
+        for class in &used {
+            implicit.insert(MethodRef::new(class, "<clinit>", "()V"));
+        }
+
+        let count = roots.len();
+        roots.extend(
+            implicit
+                .into_iter()
+                .filter(|method| graph.methods.contains(method)),
+        );
+        if roots.len() == count {
+            break (reachable, used);
+        }
+    };
+
+    let mut dead = DeadCode::default();
+    for class in classes {
+        let pool = &class.constant_pool;
+        let class_name = class.name()?;
+        if !used.contains(class_name) {
+            dead.classes.push(class_name.to_string());
+            continue;
+        }
+        for method in &class.methods {
+            let has_code = method
+                .attributes
+                .iter()
+                .any(|attribute| matches!(attribute, Attribute::Code(_)));
+            let method = MethodRef::new(
+                class_name,
+                pool.get_utf8(method.name_index)?,
+                pool.get_utf8(method.descriptor_index)?,
+            );
+            if has_code && !reachable.contains(&method) {
+                dead.methods.push(method);
+            }
+        }
+    }
+    dead.classes.sort();
+    dead.methods.sort();
+    Ok(dead)
+}
+
+fn is_annotated(
+    class: &Class,
+    attributes: &[Attribute],
+    keep: &KeepRules,
+) -> Result<bool, ClassLoadingError> {
+    for attribute in attributes {
+        let annotations = match attribute {
+            Attribute::RuntimeVisibleAnnotations(annotations)
+            | Attribute::RuntimeInvisibleAnnotations(annotations) => annotations,
+            _ => continue,
+        };
+        for annotation in annotations {
+            let descriptor = class.constant_pool.get_utf8(annotation.type_index)?;
+            if keep.annotations.contains(descriptor) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Adds the classes the reachable methods of the class refer to, and the
+/// targets of the method handles of the class.
+fn references(
+    class: &Class,
+    methods: &[&MethodRef],
+    used: &mut BTreeSet<String>,
+    implicit: &mut BTreeSet<MethodRef>,
+) -> Result<(), ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let element = |name: &str| {
+        name.trim_start_matches('[')
+            .trim_start_matches('L')
+            .trim_end_matches(';')
+            .to_string()
+    };
+    for method in &class.methods {
+        let name = pool.get_utf8(method.name_index)?;
+        let descriptor = pool.get_utf8(method.descriptor_index)?;
+        if !methods
+            .iter()
+            .any(|method| method.name == name && method.descriptor == descriptor)
+        {
+            continue;
+        }
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            });
+        let code = match code {
+            Some(code) => decode(&code.code),
+            None => continue,
+        };
+        for instruction in code.instructions {
+            match instruction {
+                Instruction::New(index)
+                | Instruction::ANewArray(index)
+                | Instruction::CheckCast(index)
+                | Instruction::InstanceOf(index)
+                | Instruction::MultiANewArray(index, _) => {
+                    used.insert(element(pool.get_class_name(index)?));
+                }
+                Instruction::Ldc(index) => {
+                    if let Some(Constant::Class(_)) = pool.get(index as usize) {
+                        used.insert(element(pool.get_class_name(index)?));
+                    }
+                }
+                Instruction::GetStatic(index)
+                | Instruction::PutStatic(index)
+                | Instruction::GetField(index)
+                | Instruction::PutField(index)
+                | Instruction::InvokeStatic(index)
+                | Instruction::InvokeSpecial(index)
+                | Instruction::InvokeVirtual(index, _)
+                | Instruction::InvokeInterface(index, _) => {
+                    used.insert(element(pool.get_member(index)?.0));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (_, constant) in pool.iter() {
+        if let Constant::MethodHandle(handle) = constant {
+            // Handles of fields are no calls
+            if handle.reference_kind >= 5 {
+                let (class, name, descriptor) = pool.get_member(handle.reference_index)?;
+                implicit.insert(MethodRef::new(class, name, descriptor));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the instance methods of a constructed class not declared by its
+/// supertypes of the set, which may override a method of a class outside
+/// of it, like `toString`, and be called by it.
+fn overrides(
+    class: &Class,
+    hierarchy: &Hierarchy,
+    implicit: &mut BTreeSet<MethodRef>,
+) -> Result<(), ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let class_name = class.name()?;
+    let parents: Vec<&String> = match hierarchy.classes.get(class_name) {
+        Some(node) => node.super_class.iter().chain(&node.interfaces).collect(),
+        None => return Ok(()),
+    };
+    for method in &class.methods {
+        let flags = method.access_flags;
+        if flags.intersects(MethodAccessFlags::STATIC | MethodAccessFlags::PRIVATE) {
+            continue;
+        }
+        let name = pool.get_utf8(method.name_index)?;
+        let descriptor = pool.get_utf8(method.descriptor_index)?;
+        if name == "<init>" {
+            continue;
+        }
+        let inherited = parents
+            .iter()
+            .any(|parent| hierarchy.resolve(parent, name, descriptor).is_some());
+        if !inherited {
+            implicit.insert(MethodRef::new(class_name, name, descriptor));
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod deadcode_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{find_dead_code, KeepRules};
+    use crate::class::Class;
+    use crate::vm::callgraph::{CallGraph, MethodRef};
+
+    #[test]
+    fn test_dead_code() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden");
+        let classes: Vec<Class> = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "class")
+            })
+            .map(|path| Class::parse_bytes(&fs::read(path).unwrap()).unwrap())
+            .collect();
+        let graph = CallGraph::build(&classes).unwrap();
+        let entries = [MethodRef::new("Objects", "main", "([Ljava/lang/String;)V")];
+
+        let dead = find_dead_code(&classes, &graph, &entries, &KeepRules::default()).unwrap();
+        assert!(dead.classes.contains(&"Hello".to_string()));
+        assert!(!dead.classes.contains(&"Objects$Named".to_string()));
+        // The constructor javac adds is never called
+        assert!(dead
+            .methods
+            .contains(&MethodRef::new("Objects", "<init>", "()V")));
+
+        let mut keep = KeepRules::default();
+        keep.parse("# Loaded by name\nHello\n");
+        keep.parse_reflection_config("[{\"name\": \"Wide\", \"methods\": [{\"name\": \"main\"}]}]");
+        let dead = find_dead_code(&classes, &graph, &entries, &keep).unwrap();
+        assert!(!dead.classes.contains(&"Hello".to_string()));
+        assert!(!dead.classes.contains(&"Wide".to_string()));
+        assert!(dead.classes.contains(&"Control".to_string()));
+    }
+}
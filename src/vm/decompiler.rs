@@ -0,0 +1,2010 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::class::attributes::{Attribute, CodeAttribute};
+use crate::class::constant_pool::{Constant, ConstantPool};
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::instruction::{decode, Condition, DecodedCode, Instruction};
+use crate::class::{
+    Class, ClassAccessFlags, ClassLoadingError, FieldAccessFlags, FieldInfo, MethodAccessFlags,
+    MethodInfo,
+};
+use crate::vm::cfg::ControlFlowGraph;
+use crate::vm::inference::{infer_frames, Frame, InferredType};
+use crate::vm::regions::{try_regions, TryRegion};
+
+/// Marks what the decompiler could not turn into source.
+const UNSUPPORTED: &str = "not decompiled";
+
+// =============================================================================
+// CLASSES
+// =============================================================================
+
+/// Decompiles the class into approximate Java source: fields, method
+/// signatures and, for the methods simple enough, their bodies, built from
+/// expressions, if/else, loops, switches and try statements. Code that does
+/// not fit these shapes is left as comments saying `not decompiled`.
+///
+/// The output reads like the source but is not meant to compile: locals are
+/// declared at the top of their method, booleans and chars show as ints, and
+/// values left on the operand stack across branches, e.g. by the `?:`
+/// operator, go through `s<depth>` variables.
+///
+/// Only the methods with the name, or name and descriptor, are decompiled
+/// when one is given.
+pub fn decompile_class(class: &Class, method: Option<&str>) -> Result<String, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let name = class.name()?;
+    let mut source = String::new();
+    writeln!(
+        source,
+        "// Decompiled from {}.class by bvm, an approximation of its source",
+        name
+    )
+    .unwrap();
+
+    let flags = class.access_flags;
+    let mut header = String::new();
+    if flags.contains(ClassAccessFlags::PUBLIC) {
+        header.push_str("public ");
+    }
+    let kind = if flags.contains(ClassAccessFlags::ANNOTATION) {
+        "@interface"
+    } else if flags.contains(ClassAccessFlags::INTERFACE) {
+        "interface"
+    } else if flags.contains(ClassAccessFlags::ENUM) {
+        "enum"
+    } else {
+        if flags.contains(ClassAccessFlags::ABSTRACT) {
+            header.push_str("abstract ");
+        }
+        if flags.contains(ClassAccessFlags::FINAL) {
+            header.push_str("final ");
+        }
+        "class"
+    };
+    write!(header, "{} {}", kind, simple_name(name)).unwrap();
+    let super_class = class.super_class_name()?;
+    if let Some(super_class) = super_class.filter(|_| kind == "class") {
+        if super_class != "java/lang/Object" {
+            write!(header, " extends {}", class_name(super_class)).unwrap();
+        }
+    }
+    let interfaces = class
+        .interfaces
+        .iter()
+        .map(|interface| {
+            pool.get_class_name(interface.interface_index)
+                .map(class_name)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !interfaces.is_empty() && !flags.contains(ClassAccessFlags::ANNOTATION) {
+        let keyword = if kind == "interface" {
+            "extends"
+        } else {
+            "implements"
+        };
+        write!(header, " {} {}", keyword, interfaces.join(", ")).unwrap();
+    }
+    writeln!(source, "{} {{", header).unwrap();
+
+    if method.is_none() {
+        for field in &class.fields {
+            writeln!(source, "    {}", field_declaration(pool, field)?).unwrap();
+        }
+    }
+
+    let mut decompiled = 0;
+    for method_info in &class.methods {
+        let method_name = pool.get_utf8(method_info.name_index)?;
+        let descriptor = pool.get_utf8(method_info.descriptor_index)?;
+        let signature = format!("{}{}", method_name, descriptor);
+        if method.is_some_and(|method| method != method_name && method != signature) {
+            continue;
+        }
+        decompiled += 1;
+        writeln!(source).unwrap();
+        write_method(&mut source, class, method_info)?;
+    }
+    if decompiled == 0 {
+        if let Some(method) = method {
+            return Err(ClassLoadingError::new(&format!(
+                "No method {} in {}",
+                method, name
+            )));
+        }
+    }
+    writeln!(source, "}}").unwrap();
+    Ok(source)
+}
+
+fn field_declaration(pool: &ConstantPool, field: &FieldInfo) -> Result<String, ClassLoadingError> {
+    let flags = field.access_flags;
+    let mut declaration = String::new();
+    for (flag, modifier) in [
+        (FieldAccessFlags::PUBLIC, "public "),
+        (FieldAccessFlags::PRIVATE, "private "),
+        (FieldAccessFlags::PROTECTED, "protected "),
+        (FieldAccessFlags::STATIC, "static "),
+        (FieldAccessFlags::FINAL, "final "),
+        (FieldAccessFlags::VOLATILE, "volatile "),
+        (FieldAccessFlags::TRANSIENT, "transient "),
+    ] {
+        if flags.contains(flag) {
+            declaration.push_str(modifier);
+        }
+    }
+    let field_type = FieldType::parse(pool.get_utf8(field.descriptor_index)?)?;
+    write!(
+        declaration,
+        "{} {}",
+        type_name(&field_type),
+        pool.get_utf8(field.name_index)?
+    )
+    .unwrap();
+    for attribute in &field.attributes {
+        if let Attribute::ConstantValue(constant) = attribute {
+            let value = constant_expr(pool, constant.const_value_index);
+            write!(declaration, " = {}", value.text).unwrap();
+        }
+    }
+    declaration.push(';');
+    Ok(declaration)
+}
+
+fn write_method(
+    source: &mut String,
+    class: &Class,
+    method: &MethodInfo,
+) -> Result<(), ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let name = pool.get_utf8(method.name_index)?;
+    let descriptor = MethodDescriptor::parse(pool.get_utf8(method.descriptor_index)?)?;
+    let flags = method.access_flags;
+    let is_static = flags.contains(MethodAccessFlags::STATIC);
+    let code = method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        });
+
+    // Parameters are named by the local variable table, when there is one
+    let mut parameters = Vec::new();
+    let mut slot = if is_static { 0 } else { 1 };
+    for (index, parameter) in descriptor.parameters.iter().enumerate() {
+        let name = code
+            .and_then(|code| table_name(pool, code, slot, 0))
+            .unwrap_or_else(|| format!("arg{}", index));
+        parameters.push((slot, name, parameter.clone()));
+        slot += parameter.slots() as u16;
+    }
+
+    let mut signature = String::new();
+    for (flag, modifier) in [
+        (MethodAccessFlags::PUBLIC, "public "),
+        (MethodAccessFlags::PRIVATE, "private "),
+        (MethodAccessFlags::PROTECTED, "protected "),
+        (MethodAccessFlags::STATIC, "static "),
+        (MethodAccessFlags::FINAL, "final "),
+        (MethodAccessFlags::SYNCHRONIZED, "synchronized "),
+        (MethodAccessFlags::NATIVE, "native "),
+        (MethodAccessFlags::ABSTRACT, "abstract "),
+    ] {
+        if flags.contains(flag) && !(name == "<clinit>" && flag == MethodAccessFlags::STATIC) {
+            signature.push_str(modifier);
+        }
+    }
+    let class_name = class.name()?;
+    match name {
+        "<clinit>" => signature.push_str("static"),
+        _ => {
+            match (name, &descriptor.return_type) {
+                ("<init>", _) => signature.push_str(&simple_name(class_name)),
+                (_, Some(return_type)) => {
+                    write!(signature, "{} {}", type_name(return_type), name).unwrap()
+                }
+                (_, None) => write!(signature, "void {}", name).unwrap(),
+            }
+            let count = parameters.len();
+            let parameters: Vec<String> = parameters
+                .iter()
+                .enumerate()
+                .map(|(index, (_, name, parameter))| {
+                    let varargs = index + 1 == count && flags.contains(MethodAccessFlags::VARARGS);
+                    match parameter {
+                        FieldType::Array(component) if varargs => {
+                            format!("{}... {}", type_name(component), name)
+                        }
+                        _ => format!("{} {}", type_name(parameter), name),
+                    }
+                })
+                .collect();
+            write!(signature, "({})", parameters.join(", ")).unwrap();
+        }
+    }
+    for attribute in &method.attributes {
+        if let Attribute::Exceptions(exceptions) = attribute {
+            let names = exceptions
+                .iter()
+                .map(|exception| pool.get_class_name(exception.index).map(self::class_name))
+                .collect::<Result<Vec<_>, _>>()?;
+            write!(signature, " throws {}", names.join(", ")).unwrap();
+        }
+    }
+
+    let code = match code {
+        Some(code) => code,
+        None => {
+            writeln!(source, "    {};", signature).unwrap();
+            return Ok(());
+        }
+    };
+    writeln!(source, "    {} {{", signature).unwrap();
+    let named: Vec<(u16, String)> = parameters
+        .into_iter()
+        .map(|(slot, name, _)| (slot, name))
+        .collect();
+    match MethodDecompiler::new(class, method, code, named).and_then(|mut decompiler| {
+        let body = decompiler.decompile(name, descriptor.return_type.is_none())?;
+        Ok((decompiler.declarations, body))
+    }) {
+        Ok((declarations, body)) => {
+            for (name, declared_type) in &declarations {
+                writeln!(source, "        {} {};", declared_type, name).unwrap();
+            }
+            write_statements(source, &body, 2);
+        }
+        Err(reason) => writeln!(source, "        // {}: {}", UNSUPPORTED, reason).unwrap(),
+    }
+    writeln!(source, "    }}").unwrap();
+    Ok(())
+}
+
+// =============================================================================
+// NAMES
+// =============================================================================
+
+/// The name of a class in source: `java.lang` classes and nested classes by
+/// their simple name, others by their qualified one.
+fn class_name(internal: &str) -> String {
+    if internal.starts_with('[') {
+        return match FieldType::parse(internal) {
+            Ok(array) => type_name(&array),
+            Err(_) => internal.to_string(),
+        };
+    }
+    let name = internal
+        .strip_prefix("java/lang/")
+        .filter(|name| !name.contains('/'))
+        .unwrap_or(internal);
+    name.replace(['/', '$'], ".")
+}
+
+/// The name a class declares itself with.
+fn simple_name(internal: &str) -> String {
+    let name = internal.rsplit('/').next().unwrap_or(internal);
+    name.rsplit('$').next().unwrap_or(name).to_string()
+}
+
+fn type_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Byte => "byte".to_string(),
+        FieldType::Char => "char".to_string(),
+        FieldType::Double => "double".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Int => "int".to_string(),
+        FieldType::Long => "long".to_string(),
+        FieldType::Short => "short".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Object(name) => class_name(name),
+        FieldType::Array(component) => format!("{}[]", type_name(component)),
+    }
+}
+
+fn inferred_type_name(inferred: &InferredType) -> String {
+    match inferred {
+        InferredType::Int => "int".to_string(),
+        InferredType::Float => "float".to_string(),
+        InferredType::Long => "long".to_string(),
+        InferredType::Double => "double".to_string(),
+        InferredType::Reference(name) => class_name(name),
+        _ => "Object".to_string(),
+    }
+}
+
+/// The name the local variable table gives the slot at the pc.
+fn table_name(pool: &ConstantPool, code: &CodeAttribute, slot: u16, pc: u32) -> Option<String> {
+    code.attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            Attribute::LocalVariableTable(variables) => Some(variables),
+            _ => None,
+        })
+        .flatten()
+        .find(|variable| {
+            variable.index == slot
+                && variable.start_pc as u32 <= pc
+                && pc <= variable.start_pc as u32 + variable.length as u32
+        })
+        .and_then(|variable| pool.get_utf8(variable.name_index).ok())
+        .map(str::to_string)
+}
+
+/// Writes the string as a Java literal.
+fn string_literal(string: &str) -> String {
+    let mut literal = String::from("\"");
+    for character in string.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            character if character.is_control() => {
+                write!(literal, "\\u{:04x}", character as u32).unwrap()
+            }
+            character => literal.push(character),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// =============================================================================
+// EXPRESSIONS
+// =============================================================================
+
+// Precedences, from the loosest to the tightest binding
+const ASSIGNMENT: u8 = 1;
+const OR: u8 = 5;
+const XOR: u8 = 6;
+const AND: u8 = 7;
+const EQUALITY: u8 = 8;
+const RELATIONAL: u8 = 9;
+const SHIFT: u8 = 10;
+const ADDITIVE: u8 = 11;
+const MULTIPLICATIVE: u8 = 12;
+const UNARY: u8 = 13;
+const PRIMARY: u8 = 14;
+
+#[derive(Clone, Debug, PartialEq)]
+enum ExprKind {
+    Plain,
+    /// The object created by the `new` at the instruction, not constructed
+    /// yet.
+    Uninitialized(usize),
+    /// A `StringBuilder` with the parts appended so far, and whether each
+    /// is a string.
+    Builder(Vec<(Expr, bool)>),
+    /// The result of `lcmp`, `fcmp<op>` or `dcmp<op>`, to compare in the
+    /// branch using it.
+    Comparison(Box<Expr>, Box<Expr>),
+    /// The exception a handler is entered with.
+    Caught,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Expr {
+    text: String,
+    precedence: u8,
+    /// The locals read, to evaluate the expression before they change.
+    locals: Vec<u16>,
+    /// Whether evaluating the expression has side effects, like a call.
+    effects: bool,
+    kind: ExprKind,
+}
+
+impl Expr {
+    fn new(text: impl Into<String>, precedence: u8) -> Expr {
+        Expr {
+            text: text.into(),
+            precedence,
+            locals: Vec::new(),
+            effects: false,
+            kind: ExprKind::Plain,
+        }
+    }
+
+    fn primary(text: impl Into<String>) -> Expr {
+        Expr::new(text, PRIMARY)
+    }
+
+    /// An expression built from others, reading what they read.
+    fn compound(text: String, precedence: u8, parts: &[&Expr]) -> Expr {
+        Expr {
+            text,
+            precedence,
+            locals: parts
+                .iter()
+                .flat_map(|part| part.locals.iter().copied())
+                .collect(),
+            effects: parts.iter().any(|part| part.effects),
+            kind: ExprKind::Plain,
+        }
+    }
+
+    /// A call, or an other expression with side effects or creating an
+    /// object.
+    fn effect(text: String, parts: &[&Expr]) -> Expr {
+        let mut call = Expr::compound(text, PRIMARY, parts);
+        call.effects = true;
+        call
+    }
+
+    /// The text, in parentheses unless it binds at least as tightly as the
+    /// precedence.
+    fn at(&self, precedence: u8) -> String {
+        if self.precedence >= precedence {
+            self.text.clone()
+        } else {
+            format!("({})", self.text)
+        }
+    }
+
+    fn binary(left: &Expr, operator: &str, right: &Expr, precedence: u8) -> Expr {
+        let text = format!(
+            "{} {} {}",
+            left.at(precedence),
+            operator,
+            right.at(precedence + 1)
+        );
+        Expr::compound(text, precedence, &[left, right])
+    }
+
+    fn unary(prefix: &str, operand: &Expr) -> Expr {
+        Expr::compound(
+            format!("{}{}", prefix, operand.at(UNARY)),
+            UNARY,
+            &[operand],
+        )
+    }
+}
+
+fn constant_expr(pool: &ConstantPool, index: u16) -> Expr {
+    match pool.get(index as usize) {
+        Some(Constant::Integer(integer)) => int_literal(integer.value),
+        Some(Constant::Float(float)) => float_literal(float.value),
+        Some(Constant::Long(long)) => {
+            Expr::new(format!("{}L", long.value), precedence_of(long.value))
+        }
+        Some(Constant::Double(double)) => double_literal(double.value),
+        Some(Constant::String(string)) => match pool.get_utf8(string.string_index) {
+            Ok(string) => Expr::primary(string_literal(string)),
+            Err(_) => Expr::primary(format!("/* {} */ null", UNSUPPORTED)),
+        },
+        Some(Constant::Class(_)) => match pool.get_class_name(index) {
+            Ok(name) => Expr::primary(format!("{}.class", class_name(name))),
+            Err(_) => Expr::primary(format!("/* {} */ null", UNSUPPORTED)),
+        },
+        _ => Expr::primary(format!(
+            "/* {}: {} */ null",
+            UNSUPPORTED,
+            pool.describe(index)
+        )),
+    }
+}
+
+fn precedence_of(value: i64) -> u8 {
+    if value < 0 {
+        UNARY
+    } else {
+        PRIMARY
+    }
+}
+
+fn int_literal(value: i32) -> Expr {
+    Expr::new(value.to_string(), precedence_of(value as i64))
+}
+
+fn float_literal(value: f32) -> Expr {
+    match value {
+        _ if value.is_nan() => Expr::primary("Float.NaN"),
+        f32::INFINITY => Expr::primary("Float.POSITIVE_INFINITY"),
+        f32::NEG_INFINITY => Expr::primary("Float.NEGATIVE_INFINITY"),
+        _ => Expr::new(
+            format!("{:?}f", value),
+            if value.is_sign_negative() {
+                UNARY
+            } else {
+                PRIMARY
+            },
+        ),
+    }
+}
+
+fn double_literal(value: f64) -> Expr {
+    match value {
+        _ if value.is_nan() => Expr::primary("Double.NaN"),
+        f64::INFINITY => Expr::primary("Double.POSITIVE_INFINITY"),
+        f64::NEG_INFINITY => Expr::primary("Double.NEGATIVE_INFINITY"),
+        _ => Expr::new(
+            format!("{:?}", value),
+            if value.is_sign_negative() {
+                UNARY
+            } else {
+                PRIMARY
+            },
+        ),
+    }
+}
+
+/// A string concatenation of the parts, starting with `""` unless one of
+/// the first two is a string, so that numbers are not added up.
+fn concatenation(parts: &[(Expr, bool)]) -> Expr {
+    let mut text = String::new();
+    let mut exprs: Vec<&Expr> = Vec::new();
+    if !parts.iter().take(2).any(|&(_, string)| string) {
+        text.push_str("\"\"");
+    }
+    for (part, _) in parts {
+        if !text.is_empty() {
+            text.push_str(" + ");
+            text.push_str(&part.at(ADDITIVE + 1));
+        } else {
+            text.push_str(&part.at(ADDITIVE));
+        }
+        exprs.push(part);
+    }
+    Expr::compound(text, ADDITIVE, &exprs)
+}
+
+/// A branch condition, comparing the left operand with the right one.
+#[derive(Clone, Debug, PartialEq)]
+struct Comparison {
+    left: Expr,
+    condition: Condition,
+    right: Expr,
+}
+
+impl Comparison {
+    fn render(&self, negated: bool) -> String {
+        let condition = if negated {
+            match self.condition {
+                Condition::Eq => Condition::Ne,
+                Condition::Ne => Condition::Eq,
+                Condition::Lt => Condition::Ge,
+                Condition::Ge => Condition::Lt,
+                Condition::Gt => Condition::Le,
+                Condition::Le => Condition::Gt,
+            }
+        } else {
+            self.condition
+        };
+        let (operator, precedence) = match condition {
+            Condition::Eq => ("==", EQUALITY),
+            Condition::Ne => ("!=", EQUALITY),
+            Condition::Lt => ("<", RELATIONAL),
+            Condition::Ge => (">=", RELATIONAL),
+            Condition::Gt => (">", RELATIONAL),
+            Condition::Le => ("<=", RELATIONAL),
+        };
+        Expr::binary(&self.left, operator, &self.right, precedence).text
+    }
+}
+
+// =============================================================================
+// STATEMENTS
+// =============================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+enum Stmt {
+    /// A statement, with its semicolon.
+    Line(String),
+    /// A store of the caught exception in the local.
+    Caught(String),
+    If {
+        condition: String,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    While {
+        condition: String,
+        body: Vec<Stmt>,
+    },
+    Switch {
+        value: String,
+        cases: Vec<(Vec<String>, Vec<Stmt>)>,
+    },
+    Try {
+        body: Vec<Stmt>,
+        /// The header of each clause, like `catch (Exception e)`.
+        catches: Vec<(String, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
+}
+
+impl Stmt {
+    /// Whether control never goes on to the next statement.
+    fn jumps(&self) -> bool {
+        match self {
+            Stmt::Line(line) => ["return", "throw ", "break;", "continue;"]
+                .iter()
+                .any(|keyword| line.starts_with(keyword)),
+            _ => false,
+        }
+    }
+}
+
+fn write_statements(source: &mut String, statements: &[Stmt], depth: usize) {
+    let indent = "    ".repeat(depth);
+    for statement in statements {
+        match statement {
+            Stmt::Line(line) => writeln!(source, "{}{}", indent, line).unwrap(),
+            Stmt::Caught(name) => {
+                writeln!(source, "{}// {} = caught exception", indent, name).unwrap()
+            }
+            Stmt::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                writeln!(source, "{}if ({}) {{", indent, condition).unwrap();
+                write_statements(source, then, depth + 1);
+                match otherwise.as_slice() {
+                    [] => {}
+                    [nested @ Stmt::If { .. }] => {
+                        write!(source, "{}}} else ", indent).unwrap();
+                        let mut chained = String::new();
+                        write_statements(&mut chained, std::slice::from_ref(nested), depth);
+                        source.push_str(chained.trim_start());
+                        continue;
+                    }
+                    _ => {
+                        writeln!(source, "{}}} else {{", indent).unwrap();
+                        write_statements(source, otherwise, depth + 1);
+                    }
+                }
+                writeln!(source, "{}}}", indent).unwrap();
+            }
+            Stmt::While { condition, body } => {
+                writeln!(source, "{}while ({}) {{", indent, condition).unwrap();
+                write_statements(source, body, depth + 1);
+                writeln!(source, "{}}}", indent).unwrap();
+            }
+            Stmt::Switch { value, cases } => {
+                writeln!(source, "{}switch ({}) {{", indent, value).unwrap();
+                for (labels, body) in cases {
+                    for label in labels {
+                        writeln!(source, "{}    {}", indent, label).unwrap();
+                    }
+                    write_statements(source, body, depth + 2);
+                }
+                writeln!(source, "{}}}", indent).unwrap();
+            }
+            Stmt::Try {
+                body,
+                catches,
+                finally,
+            } => {
+                writeln!(source, "{}try {{", indent).unwrap();
+                write_statements(source, body, depth + 1);
+                for (header, handler) in catches {
+                    writeln!(source, "{}}} {} {{", indent, header).unwrap();
+                    write_statements(source, handler, depth + 1);
+                }
+                if let Some(finally) = finally {
+                    writeln!(source, "{}}} finally {{", indent).unwrap();
+                    write_statements(source, finally, depth + 1);
+                }
+                writeln!(source, "{}}}", indent).unwrap();
+            }
+        }
+    }
+}
+
+// =============================================================================
+// METHODS
+// =============================================================================
+
+/// How control leaves a basic block.
+#[derive(Clone, Debug, PartialEq)]
+enum Exit {
+    /// To the next block.
+    Next,
+    Goto(usize),
+    /// To the block when the comparison holds, to the next one otherwise.
+    Branch(Comparison, usize),
+    /// To the block of the key, `None` for the default.
+    Switch(Expr, Vec<(Option<i32>, usize)>),
+    /// Out of the method, by a return or a throw.
+    End,
+}
+
+/// Where jumps go when they do not need a statement of their own.
+#[derive(Clone, Copy, Debug, Default)]
+struct Context {
+    /// The block control goes on to after the statements of the range.
+    follow: Option<usize>,
+    /// The block a `break` goes to.
+    breaks: Option<usize>,
+    /// The block a `continue` goes to.
+    continues: Option<usize>,
+}
+
+struct MethodDecompiler<'a> {
+    class_name: &'a str,
+    super_name: Option<&'a str>,
+    pool: &'a ConstantPool,
+    class: &'a Class,
+    code: &'a CodeAttribute,
+    decoded: DecodedCode,
+    frames: Vec<Option<Frame>>,
+    graph: ControlFlowGraph,
+    is_static: bool,
+    parameters: Vec<(u16, String)>,
+    /// The locals stored to, other than the parameters, with their type.
+    declarations: Vec<(String, String)>,
+    /// The statements and the exit of every block.
+    blocks: Vec<(Vec<Stmt>, Exit)>,
+}
+
+impl<'a> MethodDecompiler<'a> {
+    fn new(
+        class: &'a Class,
+        method: &'a MethodInfo,
+        code: &'a CodeAttribute,
+        parameters: Vec<(u16, String)>,
+    ) -> Result<Self, String> {
+        let decoded = decode(&code.code);
+        for (index, instruction) in decoded.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Jsr(_) | Instruction::Ret(_) => {
+                    return Err("jsr and ret subroutines".to_string())
+                }
+                Instruction::Unsupported(0xba) => {}
+                Instruction::Unsupported(opcode) => {
+                    return Err(format!(
+                        "unsupported opcode 0x{:02x} at pc {}",
+                        opcode, decoded.pcs[index]
+                    ))
+                }
+                _ => {}
+            }
+        }
+        let frames = infer_frames(class, method, code).map_err(|error| error.to_string())?;
+        let graph = ControlFlowGraph::build(&decoded, &code.exception_tables);
+        Ok(MethodDecompiler {
+            class_name: class.name().map_err(|error| error.to_string())?,
+            super_name: class.super_class_name().ok().flatten(),
+            pool: &class.constant_pool,
+            class,
+            code,
+            decoded,
+            frames,
+            graph,
+            is_static: method.access_flags.contains(MethodAccessFlags::STATIC),
+            parameters,
+            declarations: Vec::new(),
+            blocks: Vec::new(),
+        })
+    }
+
+    fn decompile(&mut self, name: &str, void: bool) -> Result<Vec<Stmt>, String> {
+        for block in 0..self.graph.blocks.len() {
+            let block = self.execute(block)?;
+            self.blocks.push(block);
+        }
+        let regions = try_regions(&self.decoded, &self.code.exception_tables);
+        let mut body = self.structure(
+            0..self.graph.blocks.len(),
+            &regions,
+            Context::default(),
+            None,
+        );
+
+        // Leave out what the compiler adds implicitly
+        if void && body.last() == Some(&Stmt::Line("return;".to_string())) {
+            body.pop();
+        }
+        if name == "<init>" && body.first() == Some(&Stmt::Line("super();".to_string())) {
+            body.remove(0);
+        }
+        Ok(body)
+    }
+
+    // Locals ------------------------------------------------------------------
+
+    /// The name of the local at the pc, from the local variable table, the
+    /// parameters, or the slot and the kind of its value.
+    fn local_name(&self, slot: u16, pc: u32, inferred: Option<&InferredType>) -> String {
+        if let Some(name) = table_name(self.pool, self.code, slot, pc) {
+            return name;
+        }
+        if slot == 0 && !self.is_static {
+            return "this".to_string();
+        }
+        if let Some((_, name)) = self.parameters.iter().find(|(other, _)| *other == slot) {
+            return name.clone();
+        }
+        let prefix = match inferred {
+            Some(InferredType::Int) => "i",
+            Some(InferredType::Long) => "l",
+            Some(InferredType::Float) => "f",
+            Some(InferredType::Double) => "d",
+            _ => "o",
+        };
+        format!("{}{}", prefix, slot)
+    }
+
+    fn declare(&mut self, name: &str, declared_type: String) {
+        let parameter = self.parameters.iter().any(|(_, other)| other == name);
+        if name != "this" && !parameter && !self.declarations.iter().any(|(other, _)| other == name)
+        {
+            self.declarations.push((name.to_string(), declared_type));
+        }
+    }
+
+    /// The declared type of the local at the pc, or the inferred one.
+    fn local_type(&self, slot: u16, pc: u32, inferred: Option<&InferredType>) -> String {
+        let declared = self
+            .code
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::LocalVariableTable(variables) => Some(variables),
+                _ => None,
+            })
+            .flatten()
+            .find(|variable| {
+                variable.index == slot
+                    && variable.start_pc as u32 <= pc
+                    && pc <= variable.start_pc as u32 + variable.length as u32
+            })
+            .and_then(|variable| self.pool.get_utf8(variable.descriptor_index).ok())
+            .and_then(|descriptor| FieldType::parse(descriptor).ok());
+        match (declared, inferred) {
+            (Some(declared), _) => type_name(&declared),
+            (None, Some(inferred)) => inferred_type_name(inferred),
+            (None, None) => "Object".to_string(),
+        }
+    }
+
+    fn stack_type(&self, index: usize, depth: usize) -> String {
+        self.frames
+            .get(index)
+            .and_then(Option::as_ref)
+            .and_then(|frame| frame.stack.get(depth))
+            .map_or_else(|| "Object".to_string(), inferred_type_name)
+    }
+
+    /// Stores the values on the stack matching the predicate in the
+    /// variables of their depth, keeping their order of evaluation.
+    fn spill(
+        &mut self,
+        stack: &mut [Expr],
+        statements: &mut Vec<Stmt>,
+        index: usize,
+        predicate: impl Fn(usize, &Expr) -> bool,
+    ) {
+        for (depth, expr) in stack.iter_mut().enumerate() {
+            let name = format!("s{}", depth);
+            if expr.text == name
+                || matches!(expr.kind, ExprKind::Uninitialized(_) | ExprKind::Caught)
+                || !predicate(depth, expr)
+            {
+                continue;
+            }
+            let declared_type = self.stack_type(index, depth);
+            self.declare(&name, declared_type);
+            statements.push(Stmt::Line(format!("{} = {};", name, expr.at(ASSIGNMENT))));
+            *expr = Expr::primary(name);
+        }
+    }
+
+    // Blocks ------------------------------------------------------------------
+
+    fn block_of(&self, instruction: usize) -> usize {
+        self.graph
+            .block_of(instruction)
+            .unwrap_or(self.graph.blocks.len())
+    }
+
+    /// Runs the instructions of the block on expressions, turning them into
+    /// statements when they have an effect.
+    fn execute(&mut self, block: usize) -> Result<(Vec<Stmt>, Exit), String> {
+        let instructions = self.graph.blocks[block].instructions.clone();
+        let start = instructions.start;
+        let depth = match &self.frames[start] {
+            Some(frame) => frame.stack.len(),
+            None => return Ok((Vec::new(), Exit::End)),
+        };
+        let pc = self.decoded.pcs[start];
+        let handler = self
+            .code
+            .exception_tables
+            .iter()
+            .any(|entry| entry.handler_pc as u32 == pc);
+        let mut stack: Vec<Expr> = (0..depth)
+            .map(|depth| {
+                if handler {
+                    Expr {
+                        kind: ExprKind::Caught,
+                        ..Expr::primary("caught")
+                    }
+                } else {
+                    Expr::primary(format!("s{}", depth))
+                }
+            })
+            .collect();
+
+        let mut statements = Vec::new();
+        let mut exit = Exit::Next;
+        for index in instructions {
+            let instruction = self.decoded.instructions[index];
+            exit = self.step(index, instruction, &mut stack, &mut statements)?;
+        }
+        if exit != Exit::End && !stack.is_empty() {
+            let last = self.graph.blocks[block].instructions.end - 1;
+            let successor = match &exit {
+                Exit::Goto(target) => self.graph.blocks[*target].instructions.start,
+                _ => last + 1,
+            };
+            self.spill(&mut stack, &mut statements, successor, |_, _| true);
+        }
+        Ok((statements, exit))
+    }
+
+    fn pop(stack: &mut Vec<Expr>) -> Result<Expr, String> {
+        stack
+            .pop()
+            .ok_or_else(|| "operand stack underflow".to_string())
+    }
+
+    fn pop_many(stack: &mut Vec<Expr>, count: usize) -> Result<Vec<Expr>, String> {
+        if stack.len() < count {
+            return Err("operand stack underflow".to_string());
+        }
+        Ok(stack.split_off(stack.len() - count))
+    }
+
+    /// Emits the statement, after the values on the stack whose evaluation
+    /// it could change.
+    fn emit(
+        &mut self,
+        statement: String,
+        written: Option<u16>,
+        index: usize,
+        stack: &mut [Expr],
+        statements: &mut Vec<Stmt>,
+    ) {
+        self.spill(stack, statements, index, |_, expr| {
+            expr.effects || written.is_some_and(|slot| expr.locals.contains(&slot))
+        });
+        statements.push(Stmt::Line(statement));
+    }
+
+    /// Copies the `take` slots on top of the stack `under` slots below
+    /// them, like the `dup` instructions do.
+    fn duplicate(
+        &mut self,
+        index: usize,
+        take: usize,
+        under: usize,
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+    ) -> Result<(), String> {
+        let wide: Vec<bool> = match &self.frames[index] {
+            Some(frame) if frame.stack.len() == stack.len() => {
+                frame.stack.iter().map(InferredType::is_wide).collect()
+            }
+            _ => vec![false; stack.len()],
+        };
+        let mut slots: Vec<Option<usize>> = Vec::new();
+        for (depth, &wide) in wide.iter().enumerate() {
+            slots.push(Some(depth));
+            if wide {
+                slots.push(None);
+            }
+        }
+        if take + under > slots.len() {
+            return Err("operand stack underflow".to_string());
+        }
+        let top: Vec<Option<usize>> = slots[slots.len() - take..].to_vec();
+        // Values copied are evaluated once, along with those before them
+        let deepest = top.iter().flatten().copied().max().unwrap_or(0);
+        self.spill(stack, statements, index, |depth, expr| {
+            expr.effects && depth <= deepest
+        });
+        let position = slots.len() - take - under;
+        slots.splice(position..position, top);
+        let old = std::mem::take(stack);
+        stack.extend(slots.into_iter().flatten().map(|depth| old[depth].clone()));
+        Ok(())
+    }
+
+    fn step(
+        &mut self,
+        index: usize,
+        instruction: Instruction,
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+    ) -> Result<Exit, String> {
+        let pool = self.pool;
+        let pc = self.decoded.pcs[index];
+        let next_pc = self
+            .decoded
+            .pcs
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.code.code.len() as u32);
+        let frame_local = |slot: u16| {
+            self.frames[index]
+                .as_ref()
+                .and_then(|frame| frame.locals.get(slot as usize))
+                .cloned()
+        };
+        let binary = |stack: &mut Vec<Expr>, operator: &str, precedence: u8| {
+            let right = Self::pop(stack)?;
+            let left = Self::pop(stack)?;
+            stack.push(Expr::binary(&left, operator, &right, precedence));
+            Ok::<(), String>(())
+        };
+        let cast = |stack: &mut Vec<Expr>, target: &str| {
+            let value = Self::pop(stack)?;
+            stack.push(Expr::compound(
+                format!("({}) {}", target, value.at(UNARY)),
+                UNARY,
+                &[&value],
+            ));
+            Ok::<(), String>(())
+        };
+
+        match instruction {
+            Instruction::Nop => {}
+            Instruction::AConstNull => stack.push(Expr::primary("null")),
+            Instruction::IConst(value) => stack.push(int_literal(value)),
+            Instruction::LConst(value) => stack.push(Expr::primary(format!("{}L", value))),
+            Instruction::FConst(value) => stack.push(float_literal(value)),
+            Instruction::DConst(value) => stack.push(double_literal(value)),
+            Instruction::Ldc(constant) => stack.push(constant_expr(pool, constant)),
+            Instruction::Load(slot) => {
+                let name = self.local_name(slot, pc, frame_local(slot).as_ref());
+                let mut local = Expr::primary(name);
+                local.locals.push(slot);
+                stack.push(local);
+            }
+            Instruction::Store(slot) => {
+                let value = Self::pop(stack)?;
+                let inferred = self.frames[index]
+                    .as_ref()
+                    .and_then(|frame| frame.stack.last())
+                    .cloned();
+                let name = self.local_name(slot, next_pc, inferred.as_ref());
+                if value.kind == ExprKind::Caught {
+                    statements.push(Stmt::Caught(name));
+                } else {
+                    let declared_type = self.local_type(slot, next_pc, inferred.as_ref());
+                    self.declare(&name, declared_type);
+                    let statement = format!("{} = {};", name, value.at(ASSIGNMENT));
+                    self.emit(statement, Some(slot), index, stack, statements);
+                }
+            }
+            Instruction::IInc(slot, increment) => {
+                let name = self.local_name(slot, pc, Some(&InferredType::Int));
+                let statement = match increment {
+                    1 => format!("{}++;", name),
+                    -1 => format!("{}--;", name),
+                    increment if increment < 0 => format!("{} -= {};", name, -(increment as i64)),
+                    increment => format!("{} += {};", name, increment),
+                };
+                self.emit(statement, Some(slot), index, stack, statements);
+            }
+            Instruction::ArrayLoad => {
+                let element = Self::pop(stack)?;
+                let array = Self::pop(stack)?;
+                let text = format!("{}[{}]", array.at(PRIMARY), element.text);
+                stack.push(Expr::compound(text, PRIMARY, &[&array, &element]));
+            }
+            Instruction::ArrayStore => {
+                let value = Self::pop(stack)?;
+                let element = Self::pop(stack)?;
+                let array = Self::pop(stack)?;
+                let statement = format!(
+                    "{}[{}] = {};",
+                    array.at(PRIMARY),
+                    element.text,
+                    value.at(ASSIGNMENT)
+                );
+                self.emit(statement, None, index, stack, statements);
+            }
+            Instruction::Pop | Instruction::Pop2 => {
+                let wide = self.frames[index]
+                    .as_ref()
+                    .and_then(|frame| frame.stack.last())
+                    .is_some_and(InferredType::is_wide);
+                let count = if instruction == Instruction::Pop2 && !wide {
+                    2
+                } else {
+                    1
+                };
+                for value in Self::pop_many(stack, count)?.into_iter().rev() {
+                    if value.effects {
+                        let statement = format!("{};", value.text);
+                        self.emit(statement, None, index, stack, statements);
+                    }
+                }
+            }
+            Instruction::Dup => self.duplicate(index, 1, 0, stack, statements)?,
+            Instruction::DupX1 => self.duplicate(index, 1, 1, stack, statements)?,
+            Instruction::DupX2 => self.duplicate(index, 1, 2, stack, statements)?,
+            Instruction::Dup2 => self.duplicate(index, 2, 0, stack, statements)?,
+            Instruction::Dup2X1 => self.duplicate(index, 2, 1, stack, statements)?,
+            Instruction::Dup2X2 => self.duplicate(index, 2, 2, stack, statements)?,
+            Instruction::Swap => {
+                let top = Self::pop(stack)?;
+                let below = Self::pop(stack)?;
+                stack.push(top);
+                stack.push(below);
+            }
+            Instruction::IAdd | Instruction::LAdd | Instruction::FAdd | Instruction::DAdd => {
+                binary(stack, "+", ADDITIVE)?
+            }
+            Instruction::ISub | Instruction::LSub | Instruction::FSub | Instruction::DSub => {
+                binary(stack, "-", ADDITIVE)?
+            }
+            Instruction::IMul | Instruction::LMul | Instruction::FMul | Instruction::DMul => {
+                binary(stack, "*", MULTIPLICATIVE)?
+            }
+            Instruction::IDiv | Instruction::LDiv | Instruction::FDiv | Instruction::DDiv => {
+                binary(stack, "/", MULTIPLICATIVE)?
+            }
+            Instruction::IRem | Instruction::LRem | Instruction::FRem | Instruction::DRem => {
+                binary(stack, "%", MULTIPLICATIVE)?
+            }
+            Instruction::IShl | Instruction::LShl => binary(stack, "<<", SHIFT)?,
+            Instruction::IShr | Instruction::LShr => binary(stack, ">>", SHIFT)?,
+            Instruction::IUShr | Instruction::LUShr => binary(stack, ">>>", SHIFT)?,
+            Instruction::IAnd | Instruction::LAnd => binary(stack, "&", AND)?,
+            Instruction::IOr | Instruction::LOr => binary(stack, "|", OR)?,
+            Instruction::IXor | Instruction::LXor => binary(stack, "^", XOR)?,
+            Instruction::INeg | Instruction::LNeg | Instruction::FNeg | Instruction::DNeg => {
+                let value = Self::pop(stack)?;
+                stack.push(Expr::unary("-", &value));
+            }
+            Instruction::I2L | Instruction::F2L | Instruction::D2L => cast(stack, "long")?,
+            Instruction::I2F | Instruction::L2F | Instruction::D2F => cast(stack, "float")?,
+            Instruction::I2D | Instruction::L2D | Instruction::F2D => cast(stack, "double")?,
+            Instruction::L2I | Instruction::F2I | Instruction::D2I => cast(stack, "int")?,
+            Instruction::I2B => cast(stack, "byte")?,
+            Instruction::I2C => cast(stack, "char")?,
+            Instruction::I2S => cast(stack, "short")?,
+            Instruction::LCmp
+            | Instruction::FCmpL
+            | Instruction::FCmpG
+            | Instruction::DCmpL
+            | Instruction::DCmpG => {
+                let right = Self::pop(stack)?;
+                let left = Self::pop(stack)?;
+                let class = match instruction {
+                    Instruction::LCmp => "Long",
+                    Instruction::FCmpL | Instruction::FCmpG => "Float",
+                    _ => "Double",
+                };
+                let text = format!("{}.compare({}, {})", class, left.text, right.text);
+                let mut comparison = Expr::compound(text, PRIMARY, &[&left, &right]);
+                comparison.kind = ExprKind::Comparison(Box::new(left), Box::new(right));
+                stack.push(comparison);
+            }
+            Instruction::If(condition, target) => {
+                let value = Self::pop(stack)?;
+                let comparison = match value.kind {
+                    ExprKind::Comparison(left, right) => Comparison {
+                        left: *left,
+                        condition,
+                        right: *right,
+                    },
+                    _ => Comparison {
+                        left: value,
+                        condition,
+                        right: Expr::primary("0"),
+                    },
+                };
+                return Ok(Exit::Branch(comparison, self.block_of(target as usize)));
+            }
+            Instruction::IfICmp(condition, target) => {
+                let right = Self::pop(stack)?;
+                let left = Self::pop(stack)?;
+                let comparison = Comparison {
+                    left,
+                    condition,
+                    right,
+                };
+                return Ok(Exit::Branch(comparison, self.block_of(target as usize)));
+            }
+            Instruction::IfACmpEq(target)
+            | Instruction::IfACmpNe(target)
+            | Instruction::IfNull(target)
+            | Instruction::IfNonNull(target) => {
+                let right = match instruction {
+                    Instruction::IfNull(_) | Instruction::IfNonNull(_) => Expr::primary("null"),
+                    _ => Self::pop(stack)?,
+                };
+                let left = Self::pop(stack)?;
+                let condition = match instruction {
+                    Instruction::IfACmpEq(_) | Instruction::IfNull(_) => Condition::Eq,
+                    _ => Condition::Ne,
+                };
+                let comparison = Comparison {
+                    left,
+                    condition,
+                    right,
+                };
+                return Ok(Exit::Branch(comparison, self.block_of(target as usize)));
+            }
+            Instruction::Goto(target) => return Ok(Exit::Goto(self.block_of(target as usize))),
+            Instruction::Jsr(_) | Instruction::Ret(_) => {
+                return Err("jsr and ret subroutines".to_string())
+            }
+            Instruction::ReturnValue => {
+                let value = Self::pop(stack)?;
+                statements.push(Stmt::Line(format!("return {};", value.text)));
+                return Ok(Exit::End);
+            }
+            Instruction::Return => {
+                statements.push(Stmt::Line("return;".to_string()));
+                return Ok(Exit::End);
+            }
+            Instruction::GetStatic(field) => {
+                let target = self.static_field(field)?;
+                stack.push(Expr::primary(target));
+            }
+            Instruction::PutStatic(field) => {
+                let value = Self::pop(stack)?;
+                let statement =
+                    format!("{} = {};", self.static_field(field)?, value.at(ASSIGNMENT));
+                self.emit(statement, None, index, stack, statements);
+            }
+            Instruction::GetField(field) => {
+                let object = Self::pop(stack)?;
+                let (_, name, _) = pool.get_member(field).map_err(|error| error.to_string())?;
+                let text = format!("{}.{}", object.at(PRIMARY), name);
+                stack.push(Expr::compound(text, PRIMARY, &[&object]));
+            }
+            Instruction::PutField(field) => {
+                let value = Self::pop(stack)?;
+                let object = Self::pop(stack)?;
+                let (_, name, _) = pool.get_member(field).map_err(|error| error.to_string())?;
+                let statement = format!(
+                    "{}.{} = {};",
+                    object.at(PRIMARY),
+                    name,
+                    value.at(ASSIGNMENT)
+                );
+                self.emit(statement, None, index, stack, statements);
+            }
+            Instruction::InvokeVirtual(method, _)
+            | Instruction::InvokeSpecial(method)
+            | Instruction::InvokeStatic(method)
+            | Instruction::InvokeInterface(method, _) => {
+                self.invoke(index, instruction, method, stack, statements)?
+            }
+            Instruction::New(class) => {
+                let name = pool
+                    .get_class_name(class)
+                    .map_err(|error| error.to_string())?;
+                stack.push(Expr {
+                    kind: ExprKind::Uninitialized(index),
+                    effects: true,
+                    ..Expr::primary(format!("new {}", class_name(name)))
+                });
+            }
+            Instruction::NewArray(code) => {
+                let length = Self::pop(stack)?;
+                let element = match code {
+                    4 => "boolean",
+                    5 => "char",
+                    6 => "float",
+                    7 => "double",
+                    8 => "byte",
+                    9 => "short",
+                    10 => "int",
+                    _ => "long",
+                };
+                let text = format!("new {}[{}]", element, length.text);
+                stack.push(Expr::effect(text, &[&length]));
+            }
+            Instruction::ANewArray(class) => {
+                let length = Self::pop(stack)?;
+                let element = pool
+                    .get_class_name(class)
+                    .map_err(|error| error.to_string())?;
+                let text = new_array(&class_name(element), &[&length]);
+                stack.push(Expr::effect(text, &[&length]));
+            }
+            Instruction::MultiANewArray(class, dimensions) => {
+                let lengths = Self::pop_many(stack, dimensions as usize)?;
+                let array = pool
+                    .get_class_name(class)
+                    .map_err(|error| error.to_string())?;
+                // The element type of the innermost array created
+                let mut element = FieldType::parse(array).map_err(|error| error.to_string())?;
+                for _ in 0..dimensions {
+                    if let FieldType::Array(component) = element {
+                        element = *component;
+                    }
+                }
+                let element = type_name(&element);
+                let lengths: Vec<&Expr> = lengths.iter().collect();
+                let text = new_array(&element, &lengths);
+                stack.push(Expr::effect(text, &lengths));
+            }
+            Instruction::ArrayLength => {
+                let array = Self::pop(stack)?;
+                let text = format!("{}.length", array.at(PRIMARY));
+                stack.push(Expr::compound(text, PRIMARY, &[&array]));
+            }
+            Instruction::AThrow => {
+                let exception = Self::pop(stack)?;
+                statements.push(Stmt::Line(format!("throw {};", exception.text)));
+                return Ok(Exit::End);
+            }
+            Instruction::CheckCast(class) => {
+                let name = pool
+                    .get_class_name(class)
+                    .map_err(|error| error.to_string())?;
+                cast(stack, &class_name(name))?;
+            }
+            Instruction::InstanceOf(class) => {
+                let value = Self::pop(stack)?;
+                let name = pool
+                    .get_class_name(class)
+                    .map_err(|error| error.to_string())?;
+                let text = format!("{} instanceof {}", value.at(RELATIONAL), class_name(name));
+                stack.push(Expr::compound(text, RELATIONAL, &[&value]));
+            }
+            Instruction::MonitorEnter | Instruction::MonitorExit => {
+                let monitor = Self::pop(stack)?;
+                let operation = if instruction == Instruction::MonitorEnter {
+                    "enter"
+                } else {
+                    "exit"
+                };
+                let statement = format!("// monitor{} {}", operation, monitor.text);
+                self.emit(statement, None, index, stack, statements);
+            }
+            Instruction::Switch(switch) => {
+                let value = Self::pop(stack)?;
+                let switch = &self.decoded.switches[switch as usize];
+                let mut cases: Vec<(Option<i32>, usize)> = switch
+                    .cases
+                    .iter()
+                    .filter(|&&(_, target)| target != switch.default)
+                    .map(|&(key, target)| (Some(key), self.block_of(target as usize)))
+                    .collect();
+                cases.push((None, self.block_of(switch.default as usize)));
+                return Ok(Exit::Switch(value, cases));
+            }
+            Instruction::Unsupported(_) => self.invoke_dynamic(index, stack, statements)?,
+        }
+        Ok(Exit::Next)
+    }
+
+    fn static_field(&self, field: u16) -> Result<String, String> {
+        let (owner, name, _) = self
+            .pool
+            .get_member(field)
+            .map_err(|error| error.to_string())?;
+        Ok(if owner == self.class_name {
+            name.to_string()
+        } else {
+            format!("{}.{}", class_name(owner), name)
+        })
+    }
+
+    fn invoke(
+        &mut self,
+        index: usize,
+        instruction: Instruction,
+        method: u16,
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+    ) -> Result<(), String> {
+        let (owner, name, descriptor) = self
+            .pool
+            .get_member(method)
+            .map_err(|error| error.to_string())?;
+        let descriptor = MethodDescriptor::parse(descriptor).map_err(|error| error.to_string())?;
+        let arguments = Self::pop_many(stack, descriptor.parameters.len())?;
+        let argument_list = arguments
+            .iter()
+            .map(|argument| argument.text.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let argument_refs: Vec<&Expr> = arguments.iter().collect();
+
+        if let Instruction::InvokeStatic(_) = instruction {
+            let text = if owner == self.class_name {
+                format!("{}({})", name, argument_list)
+            } else {
+                format!("{}.{}({})", class_name(owner), name, argument_list)
+            };
+            let call = Expr::effect(text, &argument_refs);
+            return self.result(
+                index,
+                call,
+                descriptor.return_type.is_none(),
+                stack,
+                statements,
+            );
+        }
+
+        let receiver = Self::pop(stack)?;
+        if name == "<init>" {
+            if let ExprKind::Uninitialized(created) = receiver.kind {
+                let is_builder =
+                    owner == "java/lang/StringBuilder" || owner == "java/lang/StringBuffer";
+                let kind = match descriptor.parameters.as_slice() {
+                    [] if is_builder => ExprKind::Builder(Vec::new()),
+                    [FieldType::Object(string)] if is_builder && string == "java/lang/String" => {
+                        ExprKind::Builder(vec![(arguments[0].clone(), true)])
+                    }
+                    _ => ExprKind::Plain,
+                };
+                let text = format!("new {}({})", class_name(owner), argument_list);
+                let constructed = Expr {
+                    kind,
+                    ..Expr::effect(text, &argument_refs)
+                };
+                let mut replaced = false;
+                for expr in stack.iter_mut() {
+                    if expr.kind == ExprKind::Uninitialized(created) {
+                        *expr = constructed.clone();
+                        replaced = true;
+                    }
+                }
+                if !replaced {
+                    let statement = format!("{};", constructed.text);
+                    self.emit(statement, None, index, stack, statements);
+                }
+                return Ok(());
+            }
+            let keyword = if receiver.text == "this" && owner == self.class_name {
+                "this".to_string()
+            } else if receiver.text == "this" && Some(owner) == self.super_name {
+                "super".to_string()
+            } else {
+                format!("{}.<init>", receiver.at(PRIMARY))
+            };
+            let statement = format!("{}({});", keyword, argument_list);
+            self.emit(statement, None, index, stack, statements);
+            return Ok(());
+        }
+
+        // Appends to a builder, and its conversion to a string
+        if let ExprKind::Builder(parts) = &receiver.kind {
+            if name == "append" && arguments.len() == 1 {
+                let string =
+                    descriptor.parameters[0] == FieldType::Object("java/lang/String".to_string());
+                let mut parts = parts.clone();
+                parts.push((arguments[0].clone(), string));
+                let text = format!("{}.append({})", receiver.at(PRIMARY), argument_list);
+                let mut appended = Expr::effect(text, &[&receiver, &arguments[0]]);
+                appended.kind = ExprKind::Builder(parts);
+                stack.push(appended);
+                return Ok(());
+            }
+            if name == "toString" && arguments.is_empty() {
+                stack.push(concatenation(parts));
+                return Ok(());
+            }
+        }
+
+        let text = match instruction {
+            Instruction::InvokeSpecial(_)
+                if receiver.text == "this" && owner != self.class_name =>
+            {
+                format!("super.{}({})", name, argument_list)
+            }
+            _ => format!("{}.{}({})", receiver.at(PRIMARY), name, argument_list),
+        };
+        let mut parts = argument_refs;
+        parts.push(&receiver);
+        let call = Expr::effect(text, &parts);
+        self.result(
+            index,
+            call,
+            descriptor.return_type.is_none(),
+            stack,
+            statements,
+        )
+    }
+
+    /// Pushes the result of the call, or emits it as a statement.
+    fn result(
+        &mut self,
+        index: usize,
+        call: Expr,
+        void: bool,
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+    ) -> Result<(), String> {
+        if void {
+            let statement = format!("{};", call.text);
+            self.emit(statement, None, index, stack, statements);
+        } else {
+            stack.push(call);
+        }
+        Ok(())
+    }
+
+    /// `invokedynamic`, turned back into a string concatenation for those
+    /// `javac` emits for `+` on strings.
+    fn invoke_dynamic(
+        &mut self,
+        index: usize,
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+    ) -> Result<(), String> {
+        let pc = self.decoded.pcs[index] as usize;
+        let constant = match self.code.code.get(pc + 1..pc + 3) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+            None => return Err(format!("truncated invokedynamic at pc {}", pc)),
+        };
+        let dynamic = match self.pool.get(constant as usize) {
+            Some(Constant::InvokeDynamic(dynamic)) => dynamic,
+            _ => return Err(format!("invalid invokedynamic at pc {}", pc)),
+        };
+        let (name, descriptor) = self
+            .pool
+            .get_name_and_type(dynamic.name_and_type_index)
+            .map_err(|error| error.to_string())?;
+        let descriptor = MethodDescriptor::parse(descriptor).map_err(|error| error.to_string())?;
+        let arguments = Self::pop_many(stack, descriptor.parameters.len())?;
+
+        let bootstrap_arguments = self
+            .class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::BootstrapMethods(methods) => {
+                    methods.get(dynamic.bootstrap_method_attr_index as usize)
+                }
+                _ => None,
+            })
+            .map(|method| method.bootstrap_arguments.as_slice())
+            .unwrap_or_default();
+        let recipe = match bootstrap_arguments
+            .first()
+            .map(|&index| self.pool.get(index as usize))
+        {
+            Some(Some(Constant::String(string))) => self.pool.get_utf8(string.string_index).ok(),
+            _ => None,
+        };
+        if let (Some(recipe), "makeConcatWithConstants") = (recipe, name) {
+            let mut parts: Vec<(Expr, bool)> = Vec::new();
+            let mut literal = String::new();
+            let mut arguments = arguments.iter().zip(&descriptor.parameters);
+            let mut constants = bootstrap_arguments[1..].iter();
+            let string_type = FieldType::Object("java/lang/String".to_string());
+            for character in recipe.chars() {
+                let part = match character {
+                    '\u{1}' => arguments
+                        .next()
+                        .map(|(argument, parameter)| (argument.clone(), *parameter == string_type)),
+                    '\u{2}' => constants
+                        .next()
+                        .map(|&constant| (constant_expr(self.pool, constant), true)),
+                    character => {
+                        literal.push(character);
+                        continue;
+                    }
+                };
+                if !literal.is_empty() {
+                    parts.push((Expr::primary(string_literal(&literal)), true));
+                    literal.clear();
+                }
+                parts.extend(part);
+            }
+            if !literal.is_empty() || parts.is_empty() {
+                parts.push((Expr::primary(string_literal(&literal)), true));
+            }
+            stack.push(concatenation(&parts));
+            return Ok(());
+        }
+
+        let argument_list = arguments
+            .iter()
+            .map(|argument| argument.text.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let text = format!(
+            "/* invokedynamic, {} */ {}({})",
+            UNSUPPORTED, name, argument_list
+        );
+        let call = Expr::effect(text, &arguments.iter().collect::<Vec<_>>());
+        self.result(
+            index,
+            call,
+            descriptor.return_type.is_none(),
+            stack,
+            statements,
+        )
+    }
+
+    // Structuring -------------------------------------------------------------
+
+    /// The statement a jump to the block needs, if any, `last` telling
+    /// whether it ends the range.
+    fn jump(&self, target: usize, context: Context, last: bool) -> Vec<Stmt> {
+        if last && context.follow == Some(target) {
+            return Vec::new();
+        }
+        let statement = if context.breaks == Some(target) {
+            "break;".to_string()
+        } else if context.continues == Some(target) {
+            "continue;".to_string()
+        } else {
+            let pc = match self.graph.blocks.get(target) {
+                Some(block) => self.decoded.pcs[block.instructions.start],
+                None => self.code.code.len() as u32,
+            };
+            format!("// {}: goto {}", UNSUPPORTED, pc)
+        };
+        vec![Stmt::Line(statement)]
+    }
+
+    /// The context of a range ending at the block, within a range ending at
+    /// `end`.
+    fn nested(&self, context: Context, block: usize, end: usize) -> Context {
+        Context {
+            follow: if block == end {
+                context.follow
+            } else {
+                Some(block)
+            },
+            ..context
+        }
+    }
+
+    /// Turns the blocks of the range into statements, `looping` being the
+    /// header of the loop the range is the body of.
+    fn structure(
+        &self,
+        range: Range<usize>,
+        regions: &[TryRegion],
+        context: Context,
+        looping: Option<usize>,
+    ) -> Vec<Stmt> {
+        let mut statements = Vec::new();
+        let mut block = range.start;
+        while block < range.end {
+            let start = self.graph.blocks[block].instructions.start;
+            if self.frames[start].is_none() {
+                block += 1;
+                continue;
+            }
+            let region = regions
+                .iter()
+                .find(|region| self.block_of(region.body.start) == block)
+                .filter(|region| self.block_of(region.extent().end) <= range.end);
+
+            // Loops, unless a try statement starting with them holds them
+            let back_edge = (block..range.end)
+                .rev()
+                .find(|&source| self.graph.blocks[source].successors.contains(&block))
+                .filter(|_| looping != Some(block));
+            if let Some(source) = back_edge {
+                let inside_try =
+                    region.is_some_and(|region| self.block_of(region.extent().end) > source);
+                if !inside_try {
+                    statements.push(self.structure_loop(block, source, regions));
+                    block = source + 1;
+                    continue;
+                }
+            }
+            if let Some(region) = region {
+                statements.push(self.structure_try(region, block, range.end, context));
+                block = self.block_of(region.extent().end);
+                continue;
+            }
+
+            let (block_statements, exit) = &self.blocks[block];
+            statements.extend(block_statements.iter().cloned());
+            let last = block + 1 == range.end;
+            match exit {
+                Exit::Next | Exit::End => block += 1,
+                Exit::Goto(target) => {
+                    if *target != block + 1 || last {
+                        statements.extend(self.jump(*target, context, last));
+                    }
+                    block += 1;
+                }
+                Exit::Branch(comparison, target) => {
+                    let target = *target;
+                    let jumps_out = target == range.end
+                        && target != context.follow.unwrap_or(usize::MAX)
+                        && (context.breaks == Some(target) || context.continues == Some(target));
+                    if block < target && target <= range.end && !jumps_out {
+                        // if (!condition) { then } else { otherwise }
+                        let else_end = match &self.blocks[target - 1].1 {
+                            Exit::Goto(end) if target - 1 > block && *end > target => {
+                                Some(*end).filter(|&end| end <= range.end)
+                            }
+                            _ => None,
+                        };
+                        match else_end {
+                            Some(end) => {
+                                let inner = self.nested(context, end, range.end);
+                                statements.push(Stmt::If {
+                                    condition: comparison.render(true),
+                                    then: self.structure(block + 1..target, regions, inner, None),
+                                    otherwise: self.structure(target..end, regions, inner, None),
+                                });
+                                block = end;
+                            }
+                            None => {
+                                let inner = self.nested(context, target, range.end);
+                                statements.push(Stmt::If {
+                                    condition: comparison.render(true),
+                                    then: self.structure(block + 1..target, regions, inner, None),
+                                    otherwise: Vec::new(),
+                                });
+                                block = target;
+                            }
+                        }
+                    } else {
+                        statements.push(Stmt::If {
+                            condition: comparison.render(false),
+                            then: self.jump(target, context, false),
+                            otherwise: Vec::new(),
+                        });
+                        block += 1;
+                    }
+                }
+                Exit::Switch(value, cases) => {
+                    match self.structure_switch(block, value, cases, range.end, regions, context) {
+                        Some((switch, end)) => {
+                            statements.push(switch);
+                            block = end;
+                        }
+                        None => {
+                            statements.push(Stmt::Line(format!(
+                                "// {}: switch ({})",
+                                UNSUPPORTED, value.text
+                            )));
+                            block += 1;
+                        }
+                    }
+                }
+            }
+        }
+        if !statements.last().is_some_and(Stmt::jumps)
+            && context.follow != Some(range.end)
+            && self.falls_out(range.clone())
+        {
+            statements.extend(self.jump(range.end, context, true));
+        }
+        statements
+    }
+
+    /// Whether control may leave the range to the block after it by falling
+    /// through its last block.
+    fn falls_out(&self, range: Range<usize>) -> bool {
+        match range
+            .end
+            .checked_sub(1)
+            .and_then(|last| self.blocks.get(last))
+        {
+            Some((_, Exit::End)) | None => false,
+            Some((_, Exit::Goto(target))) => *target == range.end,
+            Some(_) => range.end < self.blocks.len() || range.end == range.start,
+        }
+    }
+
+    fn structure_loop(&self, header: usize, source: usize, regions: &[TryRegion]) -> Stmt {
+        let exit = source + 1;
+        let inner = Context {
+            follow: Some(header),
+            breaks: Some(exit),
+            continues: Some(header),
+        };
+        // while (condition) when the header only tests it
+        if let (statements, Exit::Branch(comparison, target)) = &self.blocks[header] {
+            if statements.is_empty() && *target == exit && header < source {
+                return Stmt::While {
+                    condition: comparison.render(true),
+                    body: self.structure(header + 1..exit, regions, inner, None),
+                };
+            }
+        }
+        Stmt::While {
+            condition: "true".to_string(),
+            body: self.structure(header..exit, regions, inner, Some(header)),
+        }
+    }
+
+    fn structure_switch(
+        &self,
+        block: usize,
+        value: &Expr,
+        cases: &[(Option<i32>, usize)],
+        end: usize,
+        regions: &[TryRegion],
+        context: Context,
+    ) -> Option<(Stmt, usize)> {
+        let mut starts: Vec<usize> = cases.iter().map(|&(_, target)| target).collect();
+        starts.sort_unstable();
+        starts.dedup();
+        if starts.first().is_some_and(|&first| first <= block)
+            || starts.last().is_some_and(|&last| last > end)
+        {
+            return None;
+        }
+        let last_start = *starts.last()?;
+        // Breaks jump past the last case
+        let switch_end = (starts[0]..last_start)
+            .filter_map(|inner| match &self.blocks[inner].1 {
+                Exit::Goto(target) if *target >= last_start && *target <= end => Some(*target),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(end);
+        starts.retain(|&start| start < switch_end);
+
+        let inner = Context {
+            breaks: Some(switch_end),
+            ..context
+        };
+        let mut clauses = Vec::new();
+        for (position, &start) in starts.iter().enumerate() {
+            let next = starts.get(position + 1).copied().unwrap_or(switch_end);
+            let labels = cases
+                .iter()
+                .filter(|&&(_, target)| target == start)
+                .map(|&(key, _)| match key {
+                    Some(key) => format!("case {}:", key),
+                    None => "default:".to_string(),
+                })
+                .collect();
+            let clause_context = Context {
+                follow: Some(next),
+                ..inner
+            };
+            let body = self.structure(start..next, regions, clause_context, None);
+            clauses.push((labels, body));
+        }
+        let switch = Stmt::Switch {
+            value: value.text.clone(),
+            cases: clauses,
+        };
+        Some((switch, switch_end))
+    }
+
+    fn structure_try(
+        &self,
+        region: &TryRegion,
+        block: usize,
+        end: usize,
+        context: Context,
+    ) -> Stmt {
+        let region_end = self.block_of(region.extent().end);
+        let inner = self.nested(context, region_end, end);
+        let mut handlers: Vec<usize> = region
+            .catches
+            .iter()
+            .map(|clause| self.block_of(clause.handler.start))
+            .chain(
+                region
+                    .finally
+                    .as_ref()
+                    .map(|finally| self.block_of(finally.start)),
+            )
+            .collect();
+        handlers.sort_unstable();
+        let handler_end = |start: usize| {
+            handlers
+                .iter()
+                .copied()
+                .find(|&handler| handler > start)
+                .unwrap_or(region_end)
+        };
+
+        let body_end = handlers.first().copied().unwrap_or(region_end);
+        let body = self.structure(block..body_end, &region.nested, inner, None);
+        let mut catches = Vec::new();
+        for clause in &region.catches {
+            let start = self.block_of(clause.handler.start);
+            let mut handler =
+                self.structure(start..handler_end(start), &region.nested, inner, None);
+            let name = match handler.first() {
+                Some(Stmt::Caught(name)) => {
+                    let name = name.clone();
+                    handler.remove(0);
+                    name
+                }
+                _ => "e".to_string(),
+            };
+            let types: Vec<String> = clause
+                .catch_types
+                .iter()
+                .map(|&index| {
+                    self.pool
+                        .get_class_name(index)
+                        .map_or_else(|_| "Throwable".to_string(), class_name)
+                })
+                .collect();
+            catches.push((format!("catch ({} {})", types.join(" | "), name), handler));
+        }
+        let finally = region.finally.as_ref().map(|finally| {
+            let start = self.block_of(finally.start);
+            let mut handler =
+                self.structure(start..handler_end(start), &region.nested, inner, None);
+            // The handler stores what was thrown, runs the block and throws
+            // it again
+            if let Some(Stmt::Caught(name)) = handler.first().cloned() {
+                handler.remove(0);
+                if handler.last() == Some(&Stmt::Line(format!("throw {};", name))) {
+                    handler.pop();
+                }
+            }
+            handler
+        });
+        Stmt::Try {
+            body,
+            catches,
+            finally,
+        }
+    }
+}
+
+/// `new T[a][b]` for the lengths of the outer dimensions, the element type
+/// possibly being an array itself.
+fn new_array(element: &str, lengths: &[&Expr]) -> String {
+    let (base, suffix) = match element.find('[') {
+        Some(bracket) => element.split_at(bracket),
+        None => (element, ""),
+    };
+    let dimensions: String = lengths
+        .iter()
+        .map(|length| format!("[{}]", length.text))
+        .collect();
+    format!("new {}{}{}", base, dimensions, suffix)
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod decompiler_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::decompile_class;
+    use crate::class::Class;
+
+    fn decompile(path: &str, method: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path);
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        decompile_class(&class, Some(method)).unwrap()
+    }
+
+    #[test]
+    fn test_decompile() {
+        let add = decompile("res/embedding/Calculator.class", "add");
+        assert!(add.contains("public class Calculator {"));
+        assert!(add.contains(
+            "public static int add(int arg0, int arg1) {\n        return arg0 + arg1;\n    }"
+        ));
+        assert!(!add.contains("fibonacci"));
+
+        let safe_divide = decompile("res/embedding/Calculator.class", "safeDivide");
+        assert!(safe_divide
+            .contains("} catch (ArithmeticException o2) {\n            return 0;\n        }"));
+
+        // Loops and an array created and filled
+        let main = decompile("res/golden/Objects.class", "main");
+        assert!(
+            main.contains("s0 = new Objects.Shape[3];\n        s0[0] = new Objects.Square(2.0);")
+        );
+        assert!(main.contains("while (i4 < i3) {"));
+        assert!(!main.contains(super::UNSUPPORTED));
+
+        let divide = decompile("res/golden/Exceptions.class", "divide");
+        assert!(
+            divide.contains("} finally {\n            System.out.println(\"finally\");\n        }")
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bitflags::Flags;
+
+use crate::class::attributes::{Attribute, CodeAttribute};
+use crate::class::constant_pool::ConstantPool;
+use crate::class::instruction::{decode, disassemble};
+use crate::class::{Class, ClassLoadingError};
+
+// =============================================================================
+// DIFFERENCES
+// =============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifferenceKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A difference between two versions of a class, or of a set of classes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub kind: DifferenceKind,
+    /// What differs, e.g. `class Foo`, `field Foo.x:I` or `method
+    /// Foo.run:()V`.
+    pub subject: String,
+    /// How a changed subject changed, empty otherwise.
+    pub details: Vec<String>,
+}
+
+impl Difference {
+    fn new(kind: DifferenceKind, subject: String) -> Self {
+        Difference {
+            kind,
+            subject,
+            details: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = match self.kind {
+            DifferenceKind::Added => '+',
+            DifferenceKind::Removed => '-',
+            DifferenceKind::Changed => '~',
+        };
+        write!(f, "{} {}", sign, self.subject)?;
+        for detail in &self.details {
+            write!(f, "\n    {}", detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two versions of a class: its flags and supertypes, and its
+/// fields and methods by name and descriptor, with their flags, constant
+/// values and code. Code compares by its disassembly, so moving constants
+/// around the pool makes no difference; `code_lines` lists the differing
+/// instructions instead of only noting the change.
+pub fn diff_classes(
+    old: &Class,
+    new: &Class,
+    code_lines: bool,
+) -> Result<Vec<Difference>, ClassLoadingError> {
+    let name = new.name()?;
+    let mut differences = Vec::new();
+
+    let mut details = Vec::new();
+    if old.major_version != new.major_version || old.minor_version != new.minor_version {
+        details.push(format!(
+            "version {}.{} -> {}.{}",
+            old.major_version, old.minor_version, new.major_version, new.minor_version
+        ));
+    }
+    if old.access_flags != new.access_flags {
+        details.push(format!(
+            "flags {} -> {}",
+            flag_names(&old.access_flags),
+            flag_names(&new.access_flags)
+        ));
+    }
+    let (old_super, new_super) = (old.super_class_name()?, new.super_class_name()?);
+    if old_super != new_super {
+        details.push(format!(
+            "superclass {} -> {}",
+            old_super.unwrap_or("none"),
+            new_super.unwrap_or("none")
+        ));
+    }
+    let (old_interfaces, new_interfaces) = (interfaces(old)?, interfaces(new)?);
+    for interface in &old_interfaces {
+        if !new_interfaces.contains(interface) {
+            details.push(format!("no longer implements {}", interface));
+        }
+    }
+    for interface in &new_interfaces {
+        if !old_interfaces.contains(interface) {
+            details.push(format!("implements {}", interface));
+        }
+    }
+    if !details.is_empty() {
+        differences.push(Difference {
+            kind: DifferenceKind::Changed,
+            subject: format!("class {}", name),
+            details,
+        });
+    }
+
+    let old_fields = members(Member::fields(old)?);
+    let new_fields = members(Member::fields(new)?);
+    diff_members(
+        name,
+        "field",
+        &old_fields,
+        &new_fields,
+        &mut differences,
+        |old, new| {
+            let mut details = Vec::new();
+            if old.flags != new.flags {
+                details.push(format!("flags {} -> {}", old.flags, new.flags));
+            }
+            if old.constant != new.constant {
+                let constant =
+                    |value: &Option<String>| value.clone().unwrap_or_else(|| "none".into());
+                details.push(format!(
+                    "constant {} -> {}",
+                    constant(&old.constant),
+                    constant(&new.constant)
+                ));
+            }
+            details
+        },
+    );
+
+    let old_methods = members(Member::methods(old)?);
+    let new_methods = members(Member::methods(new)?);
+    diff_members(
+        name,
+        "method",
+        &old_methods,
+        &new_methods,
+        &mut differences,
+        |old, new| {
+            let mut details = Vec::new();
+            if old.flags != new.flags {
+                details.push(format!("flags {} -> {}", old.flags, new.flags));
+            }
+            if old.code != new.code {
+                if code_lines {
+                    details.extend(diff_lines(&old.code, &new.code));
+                } else {
+                    details.push("code changed".to_string());
+                }
+            }
+            details
+        },
+    );
+    Ok(differences)
+}
+
+/// Compares two versions of a set of classes, e.g. the classes of two
+/// versions of a jar, matching the classes by name.
+pub fn diff_class_sets(
+    old: &[Class],
+    new: &[Class],
+    code_lines: bool,
+) -> Result<Vec<Difference>, ClassLoadingError> {
+    let (old, new) = (by_name(old)?, by_name(new)?);
+
+    let mut differences = Vec::new();
+    for (name, old_class) in &old {
+        match new.get(name) {
+            Some(new_class) => differences.extend(diff_classes(old_class, new_class, code_lines)?),
+            None => differences.push(Difference::new(
+                DifferenceKind::Removed,
+                format!("class {}", name),
+            )),
+        }
+    }
+    for name in new.keys().filter(|name| !old.contains_key(*name)) {
+        differences.push(Difference::new(
+            DifferenceKind::Added,
+            format!("class {}", name),
+        ));
+    }
+    Ok(differences)
+}
+
+fn by_name(classes: &[Class]) -> Result<BTreeMap<String, &Class>, ClassLoadingError> {
+    classes
+        .iter()
+        .map(|class| Ok((class.name()?.to_string(), class)))
+        .collect()
+}
+
+/// What is compared of a field or a method.
+struct Member {
+    key: (String, String),
+    flags: String,
+    /// The `ConstantValue` of a field.
+    constant: Option<String>,
+    /// The disassembled instructions of a method.
+    code: Vec<String>,
+}
+
+impl Member {
+    fn fields(class: &Class) -> Result<Vec<Member>, ClassLoadingError> {
+        let pool = &class.constant_pool;
+        class
+            .fields
+            .iter()
+            .map(|field| {
+                let constant = field
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::ConstantValue(value) => {
+                            Some(pool.describe(value.const_value_index))
+                        }
+                        _ => None,
+                    });
+                Ok(Member {
+                    key: key(pool, field.name_index, field.descriptor_index)?,
+                    flags: flag_names(&field.access_flags),
+                    constant,
+                    code: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    fn methods(class: &Class) -> Result<Vec<Member>, ClassLoadingError> {
+        let pool = &class.constant_pool;
+        class
+            .methods
+            .iter()
+            .map(|method| {
+                let code = method
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::Code(code) => Some(instructions(code, pool)),
+                        _ => None,
+                    });
+                Ok(Member {
+                    key: key(pool, method.name_index, method.descriptor_index)?,
+                    flags: flag_names(&method.access_flags),
+                    constant: None,
+                    code: code.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn interfaces(class: &Class) -> Result<Vec<&str>, ClassLoadingError> {
+    class
+        .interfaces
+        .iter()
+        .map(|interface| {
+            class
+                .constant_pool
+                .get_class_name(interface.interface_index)
+        })
+        .collect()
+}
+
+fn key(
+    pool: &ConstantPool,
+    name_index: u16,
+    descriptor_index: u16,
+) -> Result<(String, String), ClassLoadingError> {
+    Ok((
+        pool.get_utf8(name_index)?.to_string(),
+        pool.get_utf8(descriptor_index)?.to_string(),
+    ))
+}
+
+fn members(members: Vec<Member>) -> BTreeMap<(String, String), Member> {
+    members
+        .into_iter()
+        .map(|member| (member.key.clone(), member))
+        .collect()
+}
+
+fn instructions(code: &CodeAttribute, pool: &ConstantPool) -> Vec<String> {
+    let decoded = decode(&code.code);
+    (0..decoded.instructions.len())
+        .map(|index| disassemble(&code.code, &decoded, index, pool))
+        .collect()
+}
+
+fn diff_members(
+    class: &str,
+    what: &str,
+    old: &BTreeMap<(String, String), Member>,
+    new: &BTreeMap<(String, String), Member>,
+    differences: &mut Vec<Difference>,
+    compare: impl Fn(&Member, &Member) -> Vec<String>,
+) {
+    let subject = |(name, descriptor): &(String, String)| {
+        format!("{} {}.{}:{}", what, class, name, descriptor)
+    };
+    for (key, old_member) in old {
+        match new.get(key) {
+            None => differences.push(Difference::new(DifferenceKind::Removed, subject(key))),
+            Some(new_member) => {
+                let details = compare(old_member, new_member);
+                if !details.is_empty() {
+                    differences.push(Difference {
+                        kind: DifferenceKind::Changed,
+                        subject: subject(key),
+                        details,
+                    });
+                }
+            }
+        }
+    }
+    for key in new.keys().filter(|key| !old.contains_key(*key)) {
+        differences.push(Difference::new(DifferenceKind::Added, subject(key)));
+    }
+}
+
+/// The lowercase names of the flags set, space separated.
+fn flag_names<F: Flags>(flags: &F) -> String {
+    let names: Vec<String> = flags
+        .iter_names()
+        .map(|(name, _)| name.to_lowercase())
+        .collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(" ")
+    }
+}
+
+/// The lines removed, prefixed by `-`, and added, prefixed by `+`, from the
+/// old lines to the new ones, along their longest common subsequence.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<String> {
+    // common[i][j]: length of the longest common subsequence of old[i..]
+    // and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod diff_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{diff_classes, DifferenceKind};
+    use crate::class::attributes::Attribute;
+    use crate::class::{Class, MethodAccessFlags};
+
+    #[test]
+    fn test_diff_classes() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let bytes = fs::read(path).unwrap();
+        let old = Class::parse_bytes(&bytes).unwrap();
+        let mut new = Class::parse_bytes(&bytes).unwrap();
+        assert!(diff_classes(&old, &new, true).unwrap().is_empty());
+
+        // add(int, int) subtracting, divide(int, int) made package private
+        // and greet(String) removed
+        let name =
+            |class: &Class, index: u16| class.constant_pool.get_utf8(index).unwrap().to_string();
+        let names: Vec<String> = new
+            .methods
+            .iter()
+            .map(|method| name(&new, method.name_index))
+            .collect();
+        for (method, name) in new.methods.iter_mut().zip(&names) {
+            match name.as_str() {
+                "add" => {
+                    for attribute in &mut method.attributes {
+                        if let Attribute::Code(code) = attribute {
+                            code.code[2] = 0x64;
+                        }
+                    }
+                }
+                "divide" => method.access_flags.remove(MethodAccessFlags::PUBLIC),
+                _ => {}
+            }
+        }
+        let greet = new.constant_pool.find_utf8("greet").unwrap();
+        new.methods.retain(|method| method.name_index != greet);
+
+        let differences = diff_classes(&old, &new, true).unwrap();
+        let rendered: Vec<String> = differences
+            .iter()
+            .map(|difference| difference.to_string())
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "~ method Calculator.add:(II)I\n    - iadd\n    + isub",
+                "~ method Calculator.divide:(II)I\n    flags public static -> static",
+                "- method Calculator.greet:(Ljava/lang/String;)Ljava/lang/String;",
+            ]
+        );
+        assert_eq!(differences[2].kind, DifferenceKind::Removed);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use crate::class::constant_pool::Constant;
+use crate::vm::loader::LoaderId;
+use crate::vm::runtime::ClassId;
+use crate::vm::{Unwind, Vm, VmError};
+
+/// A symbolic reference of a class of the closure which cannot be resolved.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LinkError {
+    /// The binary name of the class making the reference.
+    pub class: String,
+    /// The binary name of the `LinkageError` resolving it throws, e.g.
+    /// `java.lang.NoSuchMethodError`.
+    pub error: String,
+    pub message: Option<String>,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.class, self.error)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of [Vm::dry_link].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DryLinkReport {
+    /// The binary names of the classes whose references were resolved, in
+    /// the order they were reached.
+    pub classes: Vec<String>,
+    /// The references failing to resolve, each reported once per class.
+    pub errors: Vec<LinkError>,
+}
+
+impl Vm {
+    /// Loads the class, given by its internal name, and resolves every class,
+    /// field and method reference of the classes it transitively refers to,
+    /// without initializing or running any of them. The errors are collected
+    /// instead of thrown, so that one pass reports everything missing.
+    ///
+    /// Only the classes of the application's loaders are walked: their
+    /// references to the JDK are resolved, but not the JDK's own ones.
+    /// Dynamically computed constants and call sites are left alone, as
+    /// resolving them runs their bootstrap methods.
+    pub fn dry_link(&mut self, class: &str) -> Result<DryLinkReport, VmError> {
+        let root = self.find_class(class)?;
+        let mut report = DryLinkReport::default();
+        let mut reported = HashSet::new();
+        let mut reached = HashSet::new();
+        reached.insert(root);
+        let mut pending = VecDeque::from(vec![root]);
+
+        while let Some(class) = pending.pop_front() {
+            let runtime_class = self.class(class);
+            let mut referenced: Vec<ClassId> = runtime_class
+                .super_class
+                .into_iter()
+                .chain(runtime_class.interfaces.iter().copied())
+                .collect();
+            let source = match &runtime_class.source {
+                Some(source) if runtime_class.defining_loader != LoaderId::BOOTSTRAP => {
+                    source.clone()
+                }
+                _ => continue,
+            };
+            report.classes.push(runtime_class.java_name());
+
+            for (index, constant) in source.class.constant_pool.iter() {
+                let index = index as u16;
+                let resolved = match constant {
+                    Constant::Class(_) => self.resolve_class_ref(class, index).and_then(|id| {
+                        let name = source
+                            .class
+                            .constant_pool
+                            .get_class_name(index)
+                            .map_err(VmError::from)?;
+                        self.element_class(class, name)
+                            .map(|element| element.unwrap_or(id))
+                    }),
+                    Constant::Field(_) => self
+                        .resolve_field_ref(class, index)
+                        .map(|field| field.class),
+                    Constant::Method(_) | Constant::InterfaceMethod(_) => self
+                        .resolve_method_ref(class, index)
+                        .map(|method| method.class),
+                    _ => continue,
+                };
+                match resolved {
+                    Ok(id) => referenced.push(id),
+                    Err(Unwind::Throw(exception)) => {
+                        let exception = self.describe_exception(exception);
+                        let error = LinkError {
+                            class: self.class(class).java_name(),
+                            error: exception.class_name,
+                            message: exception.message,
+                        };
+                        if reported.insert(error.clone()) {
+                            report.errors.push(error);
+                        }
+                    }
+                    Err(unwind) => return self.complete(Err(unwind)),
+                }
+            }
+
+            for id in referenced {
+                if reached.insert(id) {
+                    pending.push_back(id);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// The class of the elements of the array class of the name, `None` for
+    /// other classes and arrays of primitives.
+    fn element_class(&mut self, class: ClassId, name: &str) -> Result<Option<ClassId>, Unwind> {
+        match name.trim_start_matches('[').strip_prefix('L') {
+            Some(element) if name.starts_with('[') => {
+                let element = element.trim_end_matches(';');
+                let loader = self.class(class).defining_loader;
+                self.load_class(loader, element).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
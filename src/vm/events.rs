@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use crate::vm::runtime::{ClassId, RuntimeClass, RuntimeMethod};
+use crate::vm::value::ObjectRef;
+use crate::vm::Vm;
+
+// =============================================================================
+// LISTENER
+// =============================================================================
+
+/// Callbacks for the events of a running VM, in the spirit of JVMTI, for
+/// building debuggers, coverage tools and monitors. Every callback does
+/// nothing by default, so listeners only implement the events they need.
+pub trait VmEventListener: Send {
+    /// A class was defined from a class file or built into the VM. Array
+    /// classes do not generate the event.
+    fn class_loaded(&mut self, _class: &RuntimeClass) {}
+
+    /// The method, declared by the class, is about to run.
+    fn method_entry(&mut self, _class: &RuntimeClass, _method: &RuntimeMethod) {}
+
+    /// The method returned or was left by an exception.
+    fn method_exit(&mut self, _class: &RuntimeClass, _method: &RuntimeMethod) {}
+
+    /// An exception of the class was thrown, at the instruction of the method
+    /// if Java code was running.
+    fn exception_thrown(
+        &mut self,
+        _exception: ObjectRef,
+        _class: &RuntimeClass,
+        _location: Option<(&RuntimeMethod, usize)>,
+    ) {
+    }
+
+    /// An exception of the class was caught by the handler of the method.
+    fn exception_caught(
+        &mut self,
+        _exception: ObjectRef,
+        _class: &RuntimeClass,
+        _method: &RuntimeMethod,
+        _handler_pc: usize,
+    ) {
+    }
+
+    /// A Java thread started running. Guest code still runs on the single
+    /// thread of the embedder, so the thread events are not generated yet.
+    fn thread_start(&mut self, _name: &str) {}
+
+    /// A Java thread terminated.
+    fn thread_end(&mut self, _name: &str) {}
+
+    /// A garbage collection started.
+    fn gc_start(&mut self) {}
+
+    /// A garbage collection finished.
+    fn gc_end(&mut self) {}
+}
+
+// =============================================================================
+// DISPATCH
+// =============================================================================
+
+impl Vm {
+    pub(crate) fn fire_class_loaded(&mut self, class: ClassId) {
+        for listener in &mut self.listeners {
+            listener.class_loaded(&self.classes[class.index()]);
+        }
+    }
+
+    pub(crate) fn fire_method_entry(&mut self, method: &Arc<RuntimeMethod>) {
+        for listener in &mut self.listeners {
+            listener.method_entry(&self.classes[method.class.index()], method);
+        }
+    }
+
+    pub(crate) fn fire_method_exit(&mut self, method: &Arc<RuntimeMethod>) {
+        for listener in &mut self.listeners {
+            listener.method_exit(&self.classes[method.class.index()], method);
+        }
+    }
+
+    /// Reports the exception as thrown by the instruction being executed.
+    pub(crate) fn fire_exception_thrown(&mut self, exception: ObjectRef) {
+        if self.listeners.is_empty() {
+            return;
+        }
+
+        let class = &self.classes[self.class_of(exception).index()];
+        let location = self
+            .thread
+            .frames
+            .last()
+            .map(|frame| (frame.method.as_ref(), frame.pc()));
+        for listener in &mut self.listeners {
+            listener.exception_thrown(exception, class, location);
+        }
+    }
+
+    /// Reports the exception as caught by the handler of the current frame.
+    pub(crate) fn fire_exception_caught(&mut self, exception: ObjectRef, handler_pc: usize) {
+        if self.listeners.is_empty() {
+            return;
+        }
+
+        let class = &self.classes[self.class_of(exception).index()];
+        let method = match self.thread.frames.last() {
+            Some(frame) => frame.method.as_ref(),
+            None => return,
+        };
+        for listener in &mut self.listeners {
+            listener.exception_caught(exception, class, method, handler_pc);
+        }
+    }
+
+    pub(crate) fn fire_gc_start(&mut self) {
+        for listener in &mut self.listeners {
+            listener.gc_start();
+        }
+    }
+
+    pub(crate) fn fire_gc_end(&mut self) {
+        for listener in &mut self.listeners {
+            listener.gc_end();
+        }
+    }
+}
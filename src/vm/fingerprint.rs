@@ -0,0 +1,562 @@
+use std::fmt;
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::class::attributes::{
+    AnnotationAttribute, Attribute, CodeAttribute, ElementValue, ExceptionTableAttribute,
+    ModuleAttribute,
+};
+use crate::class::constant_pool::ConstantPool;
+use crate::class::instruction::{decode, DecodedCode, Instruction};
+use crate::class::{Class, ClassLoadingError};
+use crate::packaging::classpath::ClassPathEntry;
+use crate::packaging::jar::is_class_file;
+
+// =============================================================================
+// FINGERPRINTS
+// =============================================================================
+
+/// A SHA-256 of what a class, or a jar, means rather than of its bytes, so
+/// that build systems can tell a rebuild made no difference.
+///
+/// The fingerprint of a class covers its version, flags, supertypes, and its
+/// fields and methods in name order with their attributes and code, the
+/// constants they refer to resolved. It ignores the order of the constant
+/// pool and of the members, the widths of `ldc` and `goto` this order
+/// implies, and the attributes of debuggers and the verifier: source files,
+/// line numbers, local variables and stack maps.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Fingerprint({})", self)
+    }
+}
+
+/// Feeds records to the hash, each prefixed by its length so that no two
+/// sequences of records hash alike.
+struct Hasher(Sha256);
+
+impl Hasher {
+    fn new() -> Self {
+        Hasher(Sha256::new())
+    }
+
+    fn record(&mut self, record: &[u8]) {
+        self.0.update((record.len() as u64).to_be_bytes());
+        self.0.update(record);
+    }
+
+    fn finish(self) -> Fingerprint {
+        Fingerprint(self.0.finalize().into())
+    }
+}
+
+/// The fingerprint of the meaning of the class.
+pub fn fingerprint_class(class: &Class) -> Result<Fingerprint, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let mut hasher = Hasher::new();
+    hasher.record(
+        format!(
+            "class {} {}.{} {:#06x} extends {}",
+            class.name()?,
+            class.major_version,
+            class.minor_version,
+            class.access_flags.bits(),
+            class.super_class_name()?.unwrap_or("")
+        )
+        .as_bytes(),
+    );
+    for interface in &class.interfaces {
+        let name = pool.get_class_name(interface.interface_index)?;
+        hasher.record(format!("implements {}", name).as_bytes());
+    }
+    for record in attributes(pool, &class.attributes)? {
+        hasher.record(record.as_bytes());
+    }
+
+    let mut members = Vec::new();
+    for field in &class.fields {
+        let mut records = vec![format!(
+            "field {}:{} {:#06x}",
+            pool.get_utf8(field.name_index)?,
+            pool.get_utf8(field.descriptor_index)?,
+            field.access_flags.bits()
+        )];
+        records.extend(attributes(pool, &field.attributes)?);
+        members.push(records);
+    }
+    for method in &class.methods {
+        let mut records = vec![format!(
+            "method {}{} {:#06x}",
+            pool.get_utf8(method.name_index)?,
+            pool.get_utf8(method.descriptor_index)?,
+            method.access_flags.bits()
+        )];
+        records.extend(attributes(pool, &method.attributes)?);
+        members.push(records);
+    }
+    members.sort();
+    for records in members {
+        for record in records {
+            hasher.record(record.as_bytes());
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// The fingerprint of the resources of a jar or a directory, by name: the
+/// fingerprints of the classes, and the bytes of the other resources and of
+/// the classes failing to parse. Their order and timestamps make no
+/// difference.
+pub fn fingerprint_entry(entry: &ClassPathEntry) -> io::Result<Fingerprint> {
+    let mut hasher = Hasher::new();
+    for name in entry.resource_names()? {
+        let bytes = match entry.read_resource(&name)? {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let class = match is_class_file(&name) {
+            true => Class::parse_bytes(&bytes).and_then(|class| fingerprint_class(&class)),
+            false => Err(ClassLoadingError::new("not a class")),
+        };
+        hasher.record(name.as_bytes());
+        match class {
+            Ok(fingerprint) => hasher.record(&fingerprint.0),
+            Err(_) => hasher.record(&Sha256::digest(&bytes)),
+        }
+    }
+    Ok(hasher.finish())
+}
+
+// =============================================================================
+// CANONICAL FORMS
+// =============================================================================
+
+/// The attributes, one record each in name order, without those of
+/// debuggers and the verifier.
+fn attributes(
+    pool: &ConstantPool,
+    attributes: &[Attribute],
+) -> Result<Vec<String>, ClassLoadingError> {
+    let mut records = Vec::new();
+    for attribute in attributes {
+        let record = match attribute {
+            Attribute::ConstantValue(value) => {
+                format!("ConstantValue {}", pool.describe(value.const_value_index))
+            }
+            Attribute::Code(code) => self::code(pool, code)?,
+            Attribute::Exceptions(exceptions) => {
+                let mut names = exceptions
+                    .iter()
+                    .map(|exception| pool.get_class_name(exception.index))
+                    .collect::<Result<Vec<_>, _>>()?;
+                names.sort_unstable();
+                format!("Exceptions {}", names.join(" "))
+            }
+            Attribute::InnerClasses(inner_classes) => {
+                let mut lines = Vec::new();
+                for inner in inner_classes {
+                    lines.push(format!(
+                        "{} {} {} {:#06x}",
+                        pool.describe(inner.inner_class_info_index),
+                        optional(pool, inner.outer_class_info_index),
+                        optional(pool, inner.inner_name_index),
+                        inner.inner_class_access_flags.bits()
+                    ));
+                }
+                lines.sort();
+                format!("InnerClasses {}", lines.join(", "))
+            }
+            Attribute::EnclosingMethod(enclosing) => format!(
+                "EnclosingMethod {} {}",
+                pool.get_class_name(enclosing.class_index)?,
+                optional(pool, enclosing.method_index)
+            ),
+            Attribute::Synthetic() => "Synthetic".to_string(),
+            Attribute::Deprecated() => "Deprecated".to_string(),
+            Attribute::Signature(signature) => {
+                format!("Signature {}", pool.get_utf8(signature.signature_index)?)
+            }
+            Attribute::RuntimeVisibleAnnotations(annotations) => {
+                format!("RuntimeVisibleAnnotations {}", list(pool, annotations))
+            }
+            Attribute::RuntimeInvisibleAnnotations(annotations) => {
+                format!("RuntimeInvisibleAnnotations {}", list(pool, annotations))
+            }
+            Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                let parameters: Vec<_> = parameters
+                    .iter()
+                    .map(|parameter| list(pool, &parameter.annotations))
+                    .collect();
+                format!(
+                    "RuntimeVisibleParameterAnnotations {}",
+                    parameters.join(" ")
+                )
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+                let parameters: Vec<_> = parameters
+                    .iter()
+                    .map(|parameter| list(pool, &parameter.annotations))
+                    .collect();
+                format!(
+                    "RuntimeInvisibleParameterAnnotations {}",
+                    parameters.join(" ")
+                )
+            }
+            Attribute::AnnotationDefault(default) => {
+                format!("AnnotationDefault {}", value(pool, &default.default_value))
+            }
+            Attribute::BootstrapMethods(methods) => {
+                let methods: Vec<_> = methods
+                    .iter()
+                    .map(|method| {
+                        let arguments: Vec<_> = method
+                            .bootstrap_arguments
+                            .iter()
+                            .map(|&argument| pool.describe_entry(argument))
+                            .collect();
+                        format!(
+                            "{}({})",
+                            pool.describe_entry(method.bootstrap_method_ref),
+                            arguments.join(", ")
+                        )
+                    })
+                    .collect();
+                format!("BootstrapMethods {}", methods.join(", "))
+            }
+            Attribute::NestHost(host) => {
+                format!("NestHost {}", pool.get_class_name(host.host_class_index)?)
+            }
+            Attribute::NestMembers(members) => {
+                let mut names = members
+                    .iter()
+                    .map(|member| pool.get_class_name(member.class_index))
+                    .collect::<Result<Vec<_>, _>>()?;
+                names.sort_unstable();
+                format!("NestMembers {}", names.join(" "))
+            }
+            Attribute::Module(module) => self::module(pool, module),
+            Attribute::ModulePackages(packages) => {
+                let mut names: Vec<_> = packages
+                    .iter()
+                    .map(|package| pool.describe_entry(package.package_index))
+                    .collect();
+                names.sort_unstable();
+                format!("ModulePackages {}", names.join(" "))
+            }
+            Attribute::ModuleMainClass(main_class) => format!(
+                "ModuleMainClass {}",
+                pool.describe_entry(main_class.main_class_index)
+            ),
+            Attribute::ScalaSig(scala) => format!("ScalaSig {:?}", scala.info),
+            Attribute::Scala(scala) => format!("Scala {:?}", scala.info),
+            // Unknown attributes may refer to the constant pool, but are
+            // kept as they are
+            Attribute::Misc(misc) => {
+                format!("{} {:?}", optional(pool, misc.name_index as u16), misc.info)
+            }
+            Attribute::StackMapTable(_)
+            | Attribute::SourceFile(_)
+            | Attribute::SourceDebugExtension(_)
+            | Attribute::LineNumberTable(_)
+            | Attribute::LocalVariableTable(_)
+            | Attribute::LocalVariableTypeTable(_) => continue,
+        };
+        records.push(record);
+    }
+    records.sort();
+    Ok(records)
+}
+
+/// The directives of the module, each kind in name order.
+fn module(pool: &ConstantPool, module: &ModuleAttribute) -> String {
+    let names = |indices: &[u16]| {
+        let mut names: Vec<_> = indices
+            .iter()
+            .map(|&index| pool.describe_entry(index))
+            .collect();
+        names.sort_unstable();
+        names.join(" ")
+    };
+    let mut lines = vec![format!(
+        "Module {} {:#06x} {}",
+        pool.describe_entry(module.module_name_index),
+        module.module_flags.bits(),
+        optional(pool, module.module_version_index)
+    )];
+    let mut directives = Vec::new();
+    for requires in &module.requires {
+        directives.push(format!(
+            "requires {} {:#06x} {}",
+            pool.describe_entry(requires.requires_index),
+            requires.requires_flags.bits(),
+            optional(pool, requires.requires_version_index)
+        ));
+    }
+    for (kind, packages) in [("exports", &module.exports), ("opens", &module.opens)] {
+        for package in packages {
+            directives.push(format!(
+                "{} {} {:#06x} to {}",
+                kind,
+                pool.describe_entry(package.package_index),
+                package.flags.bits(),
+                names(&package.to_indices)
+            ));
+        }
+    }
+    directives.push(format!("uses {}", names(&module.uses_indices)));
+    // The service loader instantiates the providers in their order
+    for provides in &module.provides {
+        let providers: Vec<_> = provides
+            .provides_with_indices
+            .iter()
+            .map(|&index| pool.describe_entry(index))
+            .collect();
+        directives.push(format!(
+            "provides {} with {}",
+            pool.describe_entry(provides.provides_index),
+            providers.join(" ")
+        ));
+    }
+    directives.sort();
+    lines.extend(directives);
+    lines.join("\n")
+}
+
+/// The instructions of the code by index rather than pc, their constants
+/// resolved, then its exception handlers and nested attributes.
+fn code(pool: &ConstantPool, code: &CodeAttribute) -> Result<String, ClassLoadingError> {
+    let decoded = decode(&code.code);
+    let mut record = format!("Code {} {}", code.max_stack, code.max_locals);
+    for (index, instruction) in decoded.instructions.iter().enumerate() {
+        let constant = match *instruction {
+            Instruction::Ldc(constant)
+            | Instruction::GetStatic(constant)
+            | Instruction::PutStatic(constant)
+            | Instruction::GetField(constant)
+            | Instruction::PutField(constant)
+            | Instruction::InvokeVirtual(constant, _)
+            | Instruction::InvokeSpecial(constant)
+            | Instruction::InvokeStatic(constant)
+            | Instruction::InvokeInterface(constant, _)
+            | Instruction::New(constant)
+            | Instruction::ANewArray(constant)
+            | Instruction::CheckCast(constant)
+            | Instruction::InstanceOf(constant)
+            | Instruction::MultiANewArray(constant, _) => Some(constant),
+            _ => None,
+        };
+        let line = match (instruction, constant) {
+            (Instruction::MultiANewArray(_, dimensions), Some(class)) => {
+                format!("MultiANewArray {} {}", pool.describe(class), dimensions)
+            }
+            (_, Some(constant)) => {
+                let debug = format!("{:?}", instruction);
+                let name = debug.split('(').next().unwrap_or_default();
+                format!("{} {}", name, pool.describe_entry(constant))
+            }
+            (Instruction::Switch(switch), _) => {
+                format!("Switch {:?}", decoded.switches[*switch as usize])
+            }
+            // The operands of the instructions the decoder leaves alone,
+            // like invokedynamic
+            (Instruction::Unsupported(_), _) => {
+                let bytes = operands(&code.code, &decoded, index);
+                match bytes {
+                    [0xba, high, low, ..] => format!(
+                        "invokedynamic {}",
+                        pool.describe_entry(u16::from_be_bytes([*high, *low]))
+                    ),
+                    bytes => format!("{:?}", bytes),
+                }
+            }
+            _ => format!("{:?}", instruction),
+        };
+        record.push('\n');
+        record.push_str(&line);
+    }
+
+    for handler in &code.exception_tables {
+        record.push('\n');
+        record.push_str(&exception_handler(pool, &decoded, code.code.len(), handler));
+    }
+    for attribute in attributes(pool, &code.attributes)? {
+        record.push('\n');
+        record.push_str(&attribute);
+    }
+    Ok(record)
+}
+
+/// The bytes of the instruction at the index.
+fn operands<'a>(code: &'a [u8], decoded: &DecodedCode, index: usize) -> &'a [u8] {
+    let start = decoded.pcs[index] as usize;
+    let end = decoded
+        .pcs
+        .get(index + 1)
+        .map_or(code.len(), |&pc| pc as usize);
+    &code[start.min(code.len())..end.min(code.len())]
+}
+
+fn exception_handler(
+    pool: &ConstantPool,
+    decoded: &DecodedCode,
+    length: usize,
+    handler: &ExceptionTableAttribute,
+) -> String {
+    // The instruction starting at the pc, or after the last one at the end
+    let index = |pc: u16| match decoded.pcs.binary_search(&(pc as u32)) {
+        Ok(index) => index.to_string(),
+        Err(_) if pc as usize == length => decoded.pcs.len().to_string(),
+        Err(_) => format!("pc {}", pc),
+    };
+    let catch_type = match handler.catch_type {
+        0 => "any".to_string(),
+        index => pool.describe(index),
+    };
+    format!(
+        "catch {} {}..{} -> {}",
+        catch_type,
+        index(handler.start_pc),
+        index(handler.end_pc),
+        index(handler.handler_pc)
+    )
+}
+
+/// The constant, if the index is not zero.
+fn optional(pool: &ConstantPool, index: u16) -> String {
+    match index {
+        0 => "-".to_string(),
+        index => pool.describe_entry(index),
+    }
+}
+
+fn list(pool: &ConstantPool, annotations: &[AnnotationAttribute]) -> String {
+    let annotations: Vec<_> = annotations
+        .iter()
+        .map(|annotation| self::annotation(pool, annotation))
+        .collect();
+    format!("[{}]", annotations.join(", "))
+}
+
+fn annotation(pool: &ConstantPool, annotation: &AnnotationAttribute) -> String {
+    let elements: Vec<_> = annotation
+        .element_value_pairs
+        .iter()
+        .map(|pair| {
+            format!(
+                "{}={}",
+                pool.describe_entry(pair.element_name_index),
+                value(pool, &pair.value)
+            )
+        })
+        .collect();
+    format!(
+        "@{}({})",
+        pool.describe_entry(annotation.type_index),
+        elements.join(", ")
+    )
+}
+
+fn value(pool: &ConstantPool, value: &ElementValue) -> String {
+    match value {
+        ElementValue::Constant(constant) => format!(
+            "{}{}",
+            constant.tag as char,
+            pool.describe_entry(constant.const_value_index)
+        ),
+        ElementValue::Enum(constant) => format!(
+            "{}.{}",
+            pool.describe_entry(constant.type_name_index),
+            pool.describe_entry(constant.const_name_index)
+        ),
+        ElementValue::Class(class) => {
+            format!("{}.class", pool.describe_entry(class.class_info_index))
+        }
+        ElementValue::Annotation(nested) => annotation(pool, &nested.annotation),
+        ElementValue::Array(array) => {
+            let values: Vec<_> = array
+                .array_values
+                .iter()
+                .map(|element| self::value(pool, element))
+                .collect();
+            format!("{{{}}}", values.join(", "))
+        }
+    }
+}
+
+// ============================================================================
+// FINGERPRINT TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod fingerprint_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{fingerprint_class, fingerprint_entry};
+    use crate::class::Class;
+    use crate::packaging::classpath::ClassPathEntry;
+    use crate::vm::optimizer::optimize_class;
+
+    fn root() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding")
+    }
+
+    #[test]
+    fn test_debug_attributes_ignored() {
+        let bytes = fs::read(root().join("Calculator.class")).unwrap();
+        let class = Class::parse_bytes(&bytes).unwrap();
+        let fingerprint = fingerprint_class(&class).unwrap();
+
+        // Without its line numbers and source file, the class is the same
+        let mut stripped = Class::parse_bytes(&bytes).unwrap();
+        stripped.attributes.clear();
+        for method in &mut stripped.methods {
+            for attribute in &mut method.attributes {
+                if let crate::class::attributes::Attribute::Code(code) = attribute {
+                    code.attributes.clear();
+                }
+            }
+        }
+        assert_eq!(fingerprint_class(&stripped).unwrap(), fingerprint);
+
+        let other = fs::read(root().join("Arithmetic.class")).unwrap();
+        let other = Class::parse_bytes(&other).unwrap();
+        assert_ne!(fingerprint_class(&other).unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn test_code_changes_noticed() {
+        let bytes = fs::read(root().join("Calculator.class")).unwrap();
+        let class = Class::parse_bytes(&bytes).unwrap();
+        let mut optimized = Class::parse_bytes(&bytes).unwrap();
+        let stats = optimize_class(&mut optimized).unwrap();
+        if stats.to_string().starts_with("0 ") {
+            return;
+        }
+        assert_ne!(
+            fingerprint_class(&optimized).unwrap(),
+            fingerprint_class(&class).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_entry_fingerprint() {
+        let entry = ClassPathEntry::open(root()).unwrap();
+        let fingerprint = fingerprint_entry(&entry).unwrap();
+        assert_eq!(fingerprint_entry(&entry).unwrap(), fingerprint);
+        assert_eq!(fingerprint.to_string().len(), 64);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::vm::heap::HeapObject;
+use crate::vm::limits::ExecutionLimits;
+use crate::vm::natives::reference::{self, REFERENT_SLOT};
+use crate::vm::runtime::ReferenceKind;
+use crate::vm::trace::GcLog;
+use crate::vm::value::{JValue, ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// Heap size triggering the first collection.
+const INITIAL_THRESHOLD: usize = 8 << 20;
+
+// =============================================================================
+// STATISTICS
+// =============================================================================
+
+/// Allocation and collection totals of the heap, see [Vm::heap_stats]. Sizes
+/// are the estimates of [HeapObject::size].
+///
+/// [HeapObject::size]: crate::vm::heap::HeapObject::size
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Objects allocated since the VM was built.
+    pub allocated_objects: u64,
+    pub allocated_bytes: u64,
+    /// Objects on the heap, including the unreachable ones not collected yet.
+    pub used_objects: usize,
+    pub used_bytes: usize,
+    /// Bytes taken by the objects which survived the last collection.
+    pub live_bytes: usize,
+    pub collections: u64,
+    pub collected_objects: u64,
+    pub collected_bytes: u64,
+    /// Total pause time of the collections.
+    pub gc_time: Duration,
+}
+
+// =============================================================================
+// COLLECTOR
+// =============================================================================
+
+/// Why a collection ran.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GcCause {
+    /// The heap grew past the collection threshold.
+    Threshold,
+    /// Java code called `System.gc()`.
+    SystemGc,
+    /// The embedder called [Vm::collect_garbage].
+    Embedder,
+}
+
+impl fmt::Display for GcCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GcCause::Threshold => write!(f, "Heap Threshold"),
+            GcCause::SystemGc => write!(f, "System.gc()"),
+            GcCause::Embedder => write!(f, "Embedder Request"),
+        }
+    }
+}
+
+/// State of the mark-sweep collector.
+///
+/// Collections only run at safepoints: between two instructions of the
+/// outermost interpreter loop, or between two calls of the embedder. There
+/// every live reference is held by a frame, a static field, a class mirror,
+/// an interned string, a shutdown hook or the embedder, while natives may
+/// hold references the collector cannot see.
+pub(crate) struct Collector {
+    /// Heap size triggering the next collection.
+    threshold: usize,
+    /// Set by `System.gc()`, collecting at the next safepoint.
+    requested: bool,
+    /// Objects returned to the embedder, alive until released.
+    pinned: HashSet<ObjectRef>,
+    collections: u64,
+    collected_objects: u64,
+    collected_bytes: u64,
+    live_bytes: usize,
+    time: Duration,
+    log: Option<GcLog>,
+}
+
+impl Collector {
+    pub(crate) fn new(log: Option<GcLog>, limits: &ExecutionLimits) -> Collector {
+        Collector {
+            threshold: threshold(0, limits),
+            requested: false,
+            pinned: HashSet::new(),
+            collections: 0,
+            collected_objects: 0,
+            collected_bytes: 0,
+            live_bytes: 0,
+            time: Duration::ZERO,
+            log,
+        }
+    }
+}
+
+/// The heap size triggering the collection after the one leaving `live`
+/// bytes: twice the live set, and halfway to the heap limit at most.
+fn threshold(live: usize, limits: &ExecutionLimits) -> usize {
+    let threshold = INITIAL_THRESHOLD.max(live.saturating_mul(2));
+    match limits.heap_bytes() {
+        Some(max) => threshold.min(live + max.saturating_sub(live) / 2),
+        None => threshold,
+    }
+}
+
+impl Vm {
+    /// Frees the objects no longer reachable from Java code or from the
+    /// references held by the embedder. Called from a native, the collection
+    /// is deferred until the native returns.
+    pub fn collect_garbage(&mut self) -> Result<(), VmError> {
+        if self.invocations > 0 {
+            self.request_gc();
+            return Ok(());
+        }
+        self.collect(GcCause::Embedder).map_err(VmError::Io)
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        HeapStats {
+            allocated_objects: self.heap.allocated_objects(),
+            allocated_bytes: self.heap.allocated_bytes(),
+            used_objects: self.heap.len(),
+            used_bytes: self.heap.size(),
+            live_bytes: self.gc.live_bytes,
+            collections: self.gc.collections,
+            collected_objects: self.gc.collected_objects,
+            collected_bytes: self.gc.collected_bytes,
+            gc_time: self.gc.time,
+        }
+    }
+
+    /// The bytes the heap may hold before the next collection, as reported
+    /// by `Runtime.totalMemory()`: the collection threshold, or the heap size
+    /// once allocations went past it.
+    pub(crate) fn total_memory(&self) -> usize {
+        self.gc.threshold.max(self.heap.size())
+    }
+
+    /// The bytes the heap may grow to, as reported by `Runtime.maxMemory()`,
+    /// or `None` without a heap limit.
+    pub(crate) fn max_memory(&self) -> Option<usize> {
+        self.limits.heap_bytes()
+    }
+
+    /// Lets the collector free an object returned to the embedder once Java
+    /// code no longer references it.
+    pub fn release(&mut self, object: ObjectRef) {
+        self.gc.pinned.remove(&object);
+    }
+
+    /// Keeps the object alive until the embedder releases it.
+    pub(crate) fn pin(&mut self, object: ObjectRef) -> ObjectRef {
+        self.gc.pinned.insert(object);
+        object
+    }
+
+    /// Converts a value returned to the embedder, pinning the object it
+    /// references.
+    pub(crate) fn export(&mut self, value: Value) -> JValue {
+        if let Value::Reference(Some(object)) = value {
+            self.pin(object);
+        }
+        JValue::from(value)
+    }
+
+    /// Requests a collection at the next safepoint, like `System.gc()`.
+    pub(crate) fn request_gc(&mut self) {
+        self.gc.requested = true;
+        self.request_poll();
+    }
+
+    /// Allocates the object, polling before the next instruction if the heap
+    /// is due a collection or exceeds its limit.
+    pub(crate) fn allocate(&mut self, object: HeapObject) -> ObjectRef {
+        let object = self.heap.allocate(object);
+        let size = self.heap.size();
+        if size >= self.gc.threshold || self.limits.heap_bytes().is_some_and(|max| size > max) {
+            self.request_poll();
+        }
+        object
+    }
+
+    /// Collects if a collection is due and the interpreter is at a safepoint.
+    pub(crate) fn safepoint(&mut self) -> Result<(), Unwind> {
+        if self.invocations != 1 {
+            return Ok(());
+        }
+
+        let cause = if self.gc.requested {
+            GcCause::SystemGc
+        } else if self.heap.size() >= self.gc.threshold {
+            GcCause::Threshold
+        } else {
+            return Ok(());
+        };
+        self.collect(cause)
+            .map_err(|error| Unwind::Error(VmError::Io(error)))
+    }
+
+    fn collect(&mut self, cause: GcCause) -> io::Result<()> {
+        self.fire_gc_start();
+        let started = Instant::now();
+        let before = self.heap.size();
+
+        let marked = self.mark();
+        let freed = self.heap.sweep(&marked);
+
+        let after = self.heap.size();
+        let pause = started.elapsed();
+        self.gc.requested = false;
+        self.gc.threshold = threshold(after, &self.limits);
+        self.gc.collections += 1;
+        self.gc.collected_objects += freed as u64;
+        self.gc.collected_bytes += before.saturating_sub(after) as u64;
+        self.gc.live_bytes = after;
+        self.gc.time += pause;
+        self.fire_gc_end();
+
+        match &mut self.gc.log {
+            Some(log) => log.collected(cause, before, after, pause),
+            None => Ok(()),
+        }
+    }
+
+    /// The references the collector traces from.
+    fn roots(&self) -> Vec<ObjectRef> {
+        let mut roots: Vec<ObjectRef> = self.gc.pinned.iter().copied().collect();
+        let stacks = self.suspended_stacks.iter().chain([&self.thread]);
+        for frame in stacks.flat_map(|stack| &stack.frames) {
+            roots.extend(references(&frame.locals));
+            roots.extend(references(&frame.stack));
+            roots.extend(&frame.monitors);
+        }
+        for class in &self.classes {
+            roots.extend(references(&class.static_values));
+            roots.extend(class.mirror);
+        }
+        roots.extend(self.interned_strings.values());
+        roots.extend(&self.shutdown_hooks);
+        roots.extend(self.main_thread);
+        roots.extend(self.loader_objects.values());
+        roots.extend(self.default_uncaught_handler);
+        roots.extend(&self.started_threads);
+        roots.extend(&self.running_threads);
+        roots
+    }
+
+    /// Marks the objects reachable from the roots, indexed by object.
+    fn mark(&mut self) -> Vec<bool> {
+        let mut marking = Marking {
+            marked: vec![false; self.heap.capacity()],
+            pending: self.roots(),
+            discovered: Vec::new(),
+        };
+        self.trace(&mut marking);
+        self.process_references(&mut marking);
+        marking.marked
+    }
+
+    /// Marks the pending objects and the ones they reach, without tracing
+    /// the referents of the references found.
+    fn trace(&self, marking: &mut Marking) {
+        while let Some(object) = marking.pending.pop() {
+            if marking.marked[object.index()] {
+                continue;
+            }
+            marking.marked[object.index()] = true;
+
+            let heap_object = self.heap.get(object);
+            match self.class(heap_object.class).reference {
+                Some(kind) => {
+                    marking.discovered.push((object, kind));
+                    let fields = heap_object.fields().iter().enumerate();
+                    marking.pending.extend(
+                        fields
+                            .filter(|(slot, _)| *slot != REFERENT_SLOT)
+                            .filter_map(|(_, value)| as_reference(*value)),
+                    );
+                }
+                None => marking.pending.extend(self.heap.references(object)),
+            }
+        }
+    }
+
+    /// Clears the references whose referents are only reachable through
+    /// references, and enqueues them on their queues. Soft referents are
+    /// kept unless the heap is more than three quarters full, and stay alive
+    /// along with everything they reach.
+    fn process_references(&mut self, marking: &mut Marking) {
+        let short_of_memory = self
+            .limits
+            .heap_bytes()
+            .is_some_and(|max| self.heap.size() > max / 4 * 3);
+        if !short_of_memory {
+            let mut traced = 0;
+            while traced < marking.discovered.len() {
+                let soft: Vec<ObjectRef> = marking.discovered[traced..]
+                    .iter()
+                    .filter(|(_, kind)| *kind == ReferenceKind::Soft)
+                    .filter_map(|(reference, _)| self.referent(*reference))
+                    .collect();
+                traced = marking.discovered.len();
+                marking.pending.extend(soft);
+                self.trace(marking);
+            }
+        }
+
+        for (reference, _) in mem::take(&mut marking.discovered) {
+            let unreachable = self
+                .referent(reference)
+                .is_some_and(|referent| !marking.marked[referent.index()]);
+            if !unreachable {
+                continue;
+            }
+            self.heap.get_mut(reference).fields_mut()[REFERENT_SLOT] = Value::NULL;
+            reference::enqueue(self, reference);
+        }
+    }
+
+    fn referent(&self, reference: ObjectRef) -> Option<ObjectRef> {
+        as_reference(self.heap.get(reference).fields()[REFERENT_SLOT])
+    }
+}
+
+/// The progress of marking the reachable objects.
+struct Marking {
+    /// Whether the object is reachable, by index.
+    marked: Vec<bool>,
+    /// Reachable objects whose references are not traced yet.
+    pending: Vec<ObjectRef>,
+    /// The reachable references, whose referents are not traced.
+    discovered: Vec<(ObjectRef, ReferenceKind)>,
+}
+
+fn references(values: &[Value]) -> impl Iterator<Item = ObjectRef> + '_ {
+    values.iter().copied().filter_map(as_reference)
+}
+
+fn as_reference(value: Value) -> Option<ObjectRef> {
+    match value {
+        Value::Reference(reference) => reference,
+        _ => None,
+    }
+}
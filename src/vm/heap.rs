@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+
+use crate::class::descriptor::FieldType;
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::charset::Encoding;
+use crate::vm::natives::io::Pipe;
+use crate::vm::natives::nio::{Channel, Mapping};
+use crate::vm::natives::zip::{Deflater, Inflater};
+use crate::vm::runtime::ClassId;
+use crate::vm::value::{ObjectRef, Value};
+
+// =============================================================================
+// OBJECTS
+// =============================================================================
+
+/// Elements of an array, stored unboxed per element type. `boolean[]` shares
+/// the byte representation, as `baload`/`bastore` work on both.
+#[derive(Clone, Debug)]
+pub enum ArrayData {
+    Byte(Vec<i8>),
+    Char(Vec<u16>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Reference(Vec<Option<ObjectRef>>),
+}
+
+impl ArrayData {
+    /// Creates a zeroed array of the given component type.
+    pub fn new(component: &FieldType, length: usize) -> ArrayData {
+        match component {
+            FieldType::Byte | FieldType::Boolean => ArrayData::Byte(vec![0; length]),
+            FieldType::Char => ArrayData::Char(vec![0; length]),
+            FieldType::Short => ArrayData::Short(vec![0; length]),
+            FieldType::Int => ArrayData::Int(vec![0; length]),
+            FieldType::Long => ArrayData::Long(vec![0; length]),
+            FieldType::Float => ArrayData::Float(vec![0.0; length]),
+            FieldType::Double => ArrayData::Double(vec![0.0; length]),
+            FieldType::Object(_) | FieldType::Array(_) => ArrayData::Reference(vec![None; length]),
+        }
+    }
+
+    /// The estimated number of bytes taken by the elements of an array of
+    /// the given component type.
+    pub fn size_of(component: &FieldType, length: usize) -> usize {
+        let element = match component {
+            FieldType::Byte | FieldType::Boolean => 1,
+            FieldType::Char | FieldType::Short => 2,
+            FieldType::Int | FieldType::Float => 4,
+            FieldType::Long | FieldType::Double => 8,
+            FieldType::Object(_) | FieldType::Array(_) => 4,
+        };
+        length.saturating_mul(element)
+    }
+
+    /// The estimated number of bytes taken by the elements.
+    pub fn size(&self) -> usize {
+        let element = match self {
+            ArrayData::Byte(_) => 1,
+            ArrayData::Char(_) | ArrayData::Short(_) => 2,
+            ArrayData::Int(_) | ArrayData::Float(_) | ArrayData::Reference(_) => 4,
+            ArrayData::Long(_) | ArrayData::Double(_) => 8,
+        };
+        self.len() * element
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ArrayData::Byte(values) => values.len(),
+            ArrayData::Char(values) => values.len(),
+            ArrayData::Short(values) => values.len(),
+            ArrayData::Int(values) => values.len(),
+            ArrayData::Long(values) => values.len(),
+            ArrayData::Float(values) => values.len(),
+            ArrayData::Double(values) => values.len(),
+            ArrayData::Reference(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the element as an operand stack value.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        let value = match self {
+            ArrayData::Byte(values) => Value::Int(*values.get(index)? as i32),
+            ArrayData::Char(values) => Value::Int(*values.get(index)? as i32),
+            ArrayData::Short(values) => Value::Int(*values.get(index)? as i32),
+            ArrayData::Int(values) => Value::Int(*values.get(index)?),
+            ArrayData::Long(values) => Value::Long(*values.get(index)?),
+            ArrayData::Float(values) => Value::Float(*values.get(index)?),
+            ArrayData::Double(values) => Value::Double(*values.get(index)?),
+            ArrayData::Reference(values) => Value::Reference(*values.get(index)?),
+        };
+
+        Some(value)
+    }
+
+    /// Stores an operand stack value into the element, truncating `int`
+    /// values to the width of the element type. Returns `false` when the
+    /// index is out of bounds.
+    pub fn set(&mut self, index: usize, value: Value) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+
+        match (self, value) {
+            (ArrayData::Byte(values), Value::Int(value)) => values[index] = value as i8,
+            (ArrayData::Char(values), Value::Int(value)) => values[index] = value as u16,
+            (ArrayData::Short(values), Value::Int(value)) => values[index] = value as i16,
+            (ArrayData::Int(values), Value::Int(value)) => values[index] = value,
+            (ArrayData::Long(values), Value::Long(value)) => values[index] = value,
+            (ArrayData::Float(values), Value::Float(value)) => values[index] = value,
+            (ArrayData::Double(values), Value::Double(value)) => values[index] = value,
+            (ArrayData::Reference(values), Value::Reference(value)) => values[index] = value,
+            (_, value) => panic!("Cannot store {:?} into array", value),
+        }
+
+        true
+    }
+}
+
+/// A frame of a captured Java stack trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackTraceElement {
+    /// Internal name of the declaring class.
+    pub class_name: String,
+    pub method_name: String,
+    pub file_name: Option<String>,
+    pub line_number: Option<u16>,
+}
+
+impl fmt::Display for StackTraceElement {
+    /// Formats the element the way `printStackTrace` does, e.g.
+    /// `Main.main(Main.java:5)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}(",
+            self.class_name.replace('/', "."),
+            self.method_name
+        )?;
+        match (&self.file_name, self.line_number) {
+            (Some(file_name), Some(line_number)) => write!(f, "{}:{})", file_name, line_number),
+            (Some(file_name), None) => write!(f, "{})", file_name),
+            (None, _) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+/// The standard streams guest code can write to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StandardStream {
+    Out,
+    Err,
+}
+
+/// State attached to objects of built-in classes, invisible to Java code.
+#[derive(Clone, Debug)]
+pub enum NativeData {
+    None,
+    /// The UTF-16 contents of a `java.lang.String`.
+    String(Arc<[u16]>),
+    /// The UTF-16 contents of a `java.lang.StringBuilder`, growing in place.
+    Builder(Vec<u16>),
+    /// The stack captured when a `java.lang.Throwable` was filled in.
+    Backtrace(Vec<StackTraceElement>),
+    /// The stream a `java.io.PrintStream` writes to.
+    Stream(StandardStream),
+    /// The host pipe of a `java.io.InputStream` or `java.io.OutputStream`,
+    /// shared behind a lock like the zip streams.
+    Pipe(Arc<Mutex<Pipe>>),
+    /// The class a `java.lang.Class` object represents.
+    Class(ClassId),
+    /// The loader a `java.lang.ClassLoader` object represents.
+    Loader(LoaderId),
+    /// The encoding a `java.nio.charset.Charset` stands for.
+    Charset(Encoding),
+    /// The running checksum of a `java.util.zip.CRC32`.
+    Checksum(crc32fast::Hasher),
+    /// The stream of a `java.util.zip.Inflater`, behind a shared lock as
+    /// the backend's streams cannot be cloned.
+    Inflater(Arc<Mutex<Inflater>>),
+    /// The stream of a `java.util.zip.Deflater`, shared like an
+    /// inflater's.
+    Deflater(Arc<Mutex<Deflater>>),
+    /// The values of the `java.lang.ThreadLocal`s of a `java.lang.Thread`,
+    /// by the thread-local.
+    ThreadLocals(HashMap<ObjectRef, Value>),
+    /// The values a `java.lang.ClassValue` computed, by class.
+    ClassValues(HashMap<ClassId, Value>),
+    /// The host process a `java.lang.Process` controls.
+    Process(Arc<Mutex<Child>>),
+    /// The open file of a `java.nio.channels.FileChannel`.
+    Channel(Arc<Mutex<Channel>>),
+    /// The mapped region of a `java.nio.MappedByteBuffer`, unmapped once
+    /// the buffer is collected.
+    Mapping(Arc<Mapping>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ObjectData {
+    /// Instance field values, indexed by the field slots of the class.
+    Fields(Vec<Value>),
+    Array(ArrayData),
+}
+
+#[derive(Clone, Debug)]
+pub struct HeapObject {
+    pub class: ClassId,
+    pub data: ObjectData,
+    pub native: NativeData,
+    /// The identity hash code, assigned when first asked for and kept in the
+    /// object, so that it survives the object moving or its slot being
+    /// reused.
+    pub identity_hash: Option<i32>,
+}
+
+impl HeapObject {
+    pub fn fields(&self) -> &[Value] {
+        match &self.data {
+            ObjectData::Fields(fields) => fields,
+            ObjectData::Array(_) => &[],
+        }
+    }
+
+    pub fn fields_mut(&mut self) -> &mut [Value] {
+        match &mut self.data {
+            ObjectData::Fields(fields) => fields,
+            ObjectData::Array(_) => &mut [],
+        }
+    }
+
+    pub fn array(&self) -> Option<&ArrayData> {
+        match &self.data {
+            ObjectData::Array(array) => Some(array),
+            ObjectData::Fields(_) => None,
+        }
+    }
+
+    pub fn array_mut(&mut self) -> Option<&mut ArrayData> {
+        match &mut self.data {
+            ObjectData::Array(array) => Some(array),
+            ObjectData::Fields(_) => None,
+        }
+    }
+
+    /// The estimated number of bytes taken by the object, counting a header,
+    /// its fields or elements and the contents of strings and builders.
+    pub fn size(&self) -> usize {
+        const HEADER: usize = 16;
+        let data = match &self.data {
+            ObjectData::Fields(fields) => fields.len() * 8,
+            ObjectData::Array(array) => array.size(),
+        };
+        let native = match &self.native {
+            NativeData::String(chars) => chars.len() * 2,
+            NativeData::Builder(chars) => chars.capacity() * 2,
+            _ => 0,
+        };
+
+        HEADER + data + native
+    }
+}
+
+// =============================================================================
+// HEAP
+// =============================================================================
+
+/// The first state of the generator of identity hash codes.
+const HASH_SEED: u32 = 0x9e37_79b9;
+
+/// Storage of every object allocated by the VM, addressed by [ObjectRef].
+/// The slots of collected objects are reused by later allocations.
+#[derive(Default)]
+pub struct Heap {
+    objects: Vec<Option<HeapObject>>,
+    /// Indices of the empty slots.
+    free: Vec<u32>,
+    /// Estimated bytes taken by the allocated objects.
+    size: usize,
+    allocated_objects: u64,
+    allocated_bytes: u64,
+    /// State of the generator of identity hash codes.
+    hash_state: u32,
+}
+
+impl Heap {
+    pub fn allocate(&mut self, object: HeapObject) -> ObjectRef {
+        let size = object.size();
+        self.size += size;
+        self.allocated_objects += 1;
+        self.allocated_bytes += size as u64;
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index as usize] = Some(object);
+                ObjectRef(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjectRef((self.objects.len() - 1) as u32)
+            }
+        }
+    }
+
+    pub fn get(&self, object: ObjectRef) -> &HeapObject {
+        self.objects[object.index()]
+            .as_ref()
+            .expect("Dangling object reference")
+    }
+
+    pub fn get_mut(&mut self, object: ObjectRef) -> &mut HeapObject {
+        self.objects[object.index()]
+            .as_mut()
+            .expect("Dangling object reference")
+    }
+
+    /// The identity hash code of the object, as returned by
+    /// `System.identityHashCode`: stable for the lifetime of the object and
+    /// never zero. Like HotSpot, the hash is drawn from a xorshift generator
+    /// when first asked for, the same sequence in every run so that hashes
+    /// reproduce, and stored in the object.
+    pub fn identity_hash(&mut self, object: ObjectRef) -> i32 {
+        if let Some(hash) = self.get(object).identity_hash {
+            return hash;
+        }
+
+        let hash = loop {
+            let mut state = match self.hash_state {
+                0 => HASH_SEED,
+                state => state,
+            };
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            self.hash_state = state;
+            match (state & 0x7fff_ffff) as i32 {
+                0 => continue,
+                hash => break hash,
+            }
+        };
+        self.get_mut(object).identity_hash = Some(hash);
+        hash
+    }
+
+    /// The estimated number of bytes taken by the allocated objects.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of objects currently allocated.
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of objects allocated over the lifetime of the heap.
+    pub fn allocated_objects(&self) -> u64 {
+        self.allocated_objects
+    }
+
+    /// Estimated bytes allocated over the lifetime of the heap.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocated_bytes
+    }
+
+    /// Number of slots, one past the highest index of an object.
+    pub(crate) fn capacity(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// The objects referenced by the fields or elements of the object, and
+    /// by its native state.
+    pub(crate) fn references(&self, object: ObjectRef) -> Vec<ObjectRef> {
+        let heap_object = self.get(object);
+        let mut references: Vec<ObjectRef> = match &heap_object.data {
+            ObjectData::Fields(fields) => fields
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Reference(reference) => *reference,
+                    _ => None,
+                })
+                .collect(),
+            ObjectData::Array(ArrayData::Reference(elements)) => {
+                elements.iter().flatten().copied().collect()
+            }
+            ObjectData::Array(_) => Vec::new(),
+        };
+        let reference = |value: &Value| match value {
+            Value::Reference(reference) => *reference,
+            _ => None,
+        };
+        match &heap_object.native {
+            NativeData::ThreadLocals(values) => {
+                for (local, value) in values {
+                    references.push(*local);
+                    references.extend(reference(value));
+                }
+            }
+            NativeData::ClassValues(values) => {
+                references.extend(values.values().filter_map(reference))
+            }
+            _ => {}
+        }
+        references
+    }
+
+    /// Frees the objects not marked, indexed by object, returning the number
+    /// of objects freed.
+    pub(crate) fn sweep(&mut self, marked: &[bool]) -> usize {
+        let mut freed = 0;
+        self.size = 0;
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            match slot {
+                Some(_) if !marked[index] => {
+                    *slot = None;
+                    self.free.push(index as u32);
+                    freed += 1;
+                }
+                // Strings get their contents after allocation and builders
+                // grow, so the size is recounted rather than decremented
+                Some(object) => self.size += object.size(),
+                None => {}
+            }
+        }
+        freed
+    }
+}
@@ -0,0 +1,930 @@
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::class::attributes::CodeAttribute;
+use crate::class::constant_pool::{Constant, ConstantPool};
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::instruction::{decode, DecodedCode, Instruction};
+use crate::class::{Class, ClassLoadingError, MethodAccessFlags, MethodInfo};
+use crate::vm::cfg::ControlFlowGraph;
+use crate::vm::dataflow::{solve, Analysis, Direction, Lattice};
+
+// =============================================================================
+// TYPES
+// =============================================================================
+
+/// The type of a local or an operand stack entry, as far as the bytecode
+/// tells without loading other classes. `boolean`, `byte`, `char` and
+/// `short` values are ints.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InferredType {
+    /// An unassigned local, the second slot of a wide one, or one holding
+    /// different types on merging paths.
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// Internal name of a class, or descriptor of an array type.
+    Reference(String),
+    /// An object created by the `new` at the pc, not constructed yet.
+    Uninitialized(u32),
+    /// `this` in a constructor, before the superclass constructor ran.
+    UninitializedThis,
+    /// The instruction index a `jsr` returns to.
+    ReturnAddress(u32),
+}
+
+impl InferredType {
+    const OBJECT: &'static str = "java/lang/Object";
+
+    pub fn from_field_type(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Long => InferredType::Long,
+            FieldType::Float => InferredType::Float,
+            FieldType::Double => InferredType::Double,
+            FieldType::Object(name) => InferredType::Reference(name.clone()),
+            FieldType::Array(_) => InferredType::Reference(field_type.to_string()),
+            _ => InferredType::Int,
+        }
+    }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, InferredType::Long | InferredType::Double)
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            InferredType::Null
+                | InferredType::Reference(_)
+                | InferredType::Uninitialized(_)
+                | InferredType::UninitializedThis
+        )
+    }
+
+    /// The type both types are instances of, if any. Without the class
+    /// hierarchy, distinct classes merge to `java/lang/Object`, and the
+    /// return addresses of a subroutine called from several sites merge to
+    /// the first of them, as its `ret` flows to every return site anyway.
+    fn merge(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            _ if self == other => Some(self.clone()),
+            (InferredType::Null, InferredType::Reference(_)) => Some(other.clone()),
+            (InferredType::Reference(_), InferredType::Null) => Some(self.clone()),
+            (InferredType::Reference(_), InferredType::Reference(_)) => {
+                Some(InferredType::Reference(InferredType::OBJECT.to_string()))
+            }
+            (InferredType::ReturnAddress(a), InferredType::ReturnAddress(b)) => {
+                Some(InferredType::ReturnAddress(*a.min(b)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InferredType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InferredType::Top => write!(f, "top"),
+            InferredType::Int => write!(f, "int"),
+            InferredType::Float => write!(f, "float"),
+            InferredType::Long => write!(f, "long"),
+            InferredType::Double => write!(f, "double"),
+            InferredType::Null => write!(f, "null"),
+            InferredType::Reference(name) => write!(f, "{}", name),
+            InferredType::Uninitialized(pc) => write!(f, "uninitialized({})", pc),
+            InferredType::UninitializedThis => write!(f, "uninitializedThis"),
+            InferredType::ReturnAddress(_) => write!(f, "returnAddress"),
+        }
+    }
+}
+
+/// The types of the locals and the operand stack before an instruction. The
+/// stack holds one entry per value, wide ones included, while wide locals
+/// are followed by a [InferredType::Top] slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub locals: Vec<InferredType>,
+    pub stack: Vec<InferredType>,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |types: &[InferredType]| {
+            types
+                .iter()
+                .map(InferredType::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "locals [{}] stack [{}]",
+            join(&self.locals),
+            join(&self.stack)
+        )
+    }
+}
+
+/// Bytecode whose types do not add up, e.g. an `iadd` of floats.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InferenceError {
+    pub pc: u32,
+    pub message: String,
+}
+
+impl fmt::Display for InferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pc {}: {}", self.pc, self.message)
+    }
+}
+
+// =============================================================================
+// INFERENCE
+// =============================================================================
+
+/// Simulates the method's code on types, returning the frame before each
+/// decoded instruction, `None` for unreachable ones.
+pub fn infer_frames(
+    class: &Class,
+    method: &MethodInfo,
+    code: &CodeAttribute,
+) -> Result<Vec<Option<Frame>>, InferenceError> {
+    let failure = |error: ClassLoadingError| InferenceError {
+        pc: 0,
+        message: error.to_string(),
+    };
+    let pool = &class.constant_pool;
+    let class_name = class.name().map_err(failure)?;
+    let name = pool.get_utf8(method.name_index).map_err(failure)?;
+    let descriptor = pool
+        .get_utf8(method.descriptor_index)
+        .and_then(MethodDescriptor::parse)
+        .map_err(failure)?;
+
+    let mut locals = Vec::new();
+    if !method.access_flags.contains(MethodAccessFlags::STATIC) {
+        locals.push(if name == "<init>" && class_name != InferredType::OBJECT {
+            InferredType::UninitializedThis
+        } else {
+            InferredType::Reference(class_name.to_string())
+        });
+    }
+    for parameter in &descriptor.parameters {
+        let parameter = InferredType::from_field_type(parameter);
+        let wide = parameter.is_wide();
+        locals.push(parameter);
+        if wide {
+            locals.push(InferredType::Top);
+        }
+    }
+    if locals.len() < code.max_locals as usize {
+        locals.resize(code.max_locals as usize, InferredType::Top);
+    }
+
+    let decoded = decode(&code.code);
+    let graph = ControlFlowGraph::build(&decoded, &code.exception_tables);
+    let analysis = TypeAnalysis {
+        class_name,
+        code: &code.code,
+        decoded: &decoded,
+        constant_pool: pool,
+        entry: Frame {
+            locals,
+            stack: Vec::new(),
+        },
+    };
+    let results = solve(&analysis, &decoded, &graph);
+
+    let mut frames = vec![None; decoded.instructions.len()];
+    for (block, mut state) in graph.blocks.iter().zip(results.entry) {
+        for index in block.instructions.clone() {
+            match &state {
+                State::Unreached => break,
+                State::Invalid { pc, message } => {
+                    return Err(InferenceError {
+                        pc: pc.unwrap_or(decoded.pcs[index]),
+                        message: message.clone(),
+                    })
+                }
+                State::Frame(frame) => frames[index] = Some(frame.clone()),
+            }
+            analysis.transfer(index, decoded.instructions[index], &mut state);
+            if let State::Invalid { pc, message } = state {
+                return Err(InferenceError {
+                    pc: pc.unwrap_or(decoded.pcs[index]),
+                    message,
+                });
+            }
+        }
+    }
+    Ok(frames)
+}
+
+/// The fact of the type analysis.
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    Unreached,
+    Frame(Frame),
+    /// The code is malformed at the pc, unknown for merges.
+    Invalid {
+        pc: Option<u32>,
+        message: String,
+    },
+}
+
+impl Lattice for State {
+    fn join(&mut self, other: &Self) -> bool {
+        let joined = match (&*self, other) {
+            (State::Invalid { .. }, _) | (_, State::Unreached) => return false,
+            (State::Unreached, _) | (_, State::Invalid { .. }) => other.clone(),
+            (State::Frame(frame), State::Frame(other)) => match frame.join(other) {
+                Ok(frame) => State::Frame(frame),
+                Err(message) => State::Invalid { pc: None, message },
+            },
+        };
+        let changed = *self != joined;
+        *self = joined;
+        changed
+    }
+}
+
+impl Frame {
+    fn join(&self, other: &Frame) -> Result<Frame, String> {
+        if self.stack.len() != other.stack.len() {
+            return Err(format!(
+                "Stack heights {} and {} differ where paths merge",
+                self.stack.len(),
+                other.stack.len()
+            ));
+        }
+        let stack = self
+            .stack
+            .iter()
+            .zip(&other.stack)
+            .map(|(a, b)| {
+                a.merge(b)
+                    .ok_or_else(|| format!("Stack holds {} and {} where paths merge", a, b))
+            })
+            .collect::<Result<_, _>>()?;
+        let locals = self
+            .locals
+            .iter()
+            .zip(&other.locals)
+            .map(|(a, b)| a.merge(b).unwrap_or(InferredType::Top))
+            .collect();
+        Ok(Frame { locals, stack })
+    }
+
+    fn pop(&mut self) -> Result<InferredType, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "Operand stack underflow".to_string())
+    }
+
+    /// Pops a value of the type, any reference for `java/lang/Object`.
+    fn pop_expecting(&mut self, expected: &InferredType) -> Result<InferredType, String> {
+        let value = self.pop()?;
+        let matches = match expected {
+            InferredType::Reference(_) => value.is_reference(),
+            _ => value == *expected,
+        };
+        if matches {
+            Ok(value)
+        } else {
+            Err(format!(
+                "Expected {} on the stack, found {}",
+                expected, value
+            ))
+        }
+    }
+
+    fn pop_reference(&mut self) -> Result<InferredType, String> {
+        self.pop_expecting(&InferredType::Reference(InferredType::OBJECT.to_string()))
+    }
+
+    fn local(&self, index: u16) -> Result<&InferredType, String> {
+        match self.locals.get(index as usize) {
+            Some(InferredType::Top) => Err(format!("Local {} is not assigned", index)),
+            Some(value) => Ok(value),
+            None => Err(format!("Local {} is beyond max_locals", index)),
+        }
+    }
+
+    fn store(&mut self, index: u16, value: InferredType) -> Result<(), String> {
+        let index = index as usize;
+        let slots = if value.is_wide() { 2 } else { 1 };
+        if index + slots > self.locals.len() {
+            return Err(format!("Local {} is beyond max_locals", index));
+        }
+        // Overwrites the second half of a wide value
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = InferredType::Top;
+        }
+        if slots == 2 {
+            self.locals[index + 1] = InferredType::Top;
+        }
+        self.locals[index] = value;
+        Ok(())
+    }
+
+    /// Copies the values taking the top `copied` slots below the values
+    /// taking the next `skipped` ones, like `dup_x1` or `dup2_x2`.
+    fn dup(&mut self, copied: usize, skipped: usize) -> Result<(), String> {
+        let mut take = |slots: usize| {
+            let mut values = Vec::new();
+            let mut taken = 0;
+            while taken < slots {
+                let value = self.pop()?;
+                taken += if value.is_wide() { 2 } else { 1 };
+                values.insert(0, value);
+            }
+            if taken == slots {
+                Ok(values)
+            } else {
+                Err("Stack manipulation splits a long or double".to_string())
+            }
+        };
+        let copies = take(copied)?;
+        let skips = take(skipped)?;
+        self.stack.extend(copies.iter().cloned());
+        self.stack.extend(skips);
+        self.stack.extend(copies);
+        Ok(())
+    }
+}
+
+/// Type inference as a forward dataflow analysis.
+struct TypeAnalysis<'a> {
+    class_name: &'a str,
+    code: &'a [u8],
+    decoded: &'a DecodedCode,
+    constant_pool: &'a ConstantPool,
+    entry: Frame,
+}
+
+impl Analysis for TypeAnalysis<'_> {
+    type Fact = State;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self) -> State {
+        State::Unreached
+    }
+
+    fn boundary(&self) -> State {
+        State::Frame(self.entry.clone())
+    }
+
+    fn transfer(&self, index: usize, instruction: Instruction, state: &mut State) {
+        if let State::Frame(frame) = state {
+            if let Err(message) = self.step(index, instruction, frame) {
+                *state = State::Invalid {
+                    pc: Some(self.decoded.pcs[index]),
+                    message,
+                };
+            }
+        }
+    }
+
+    fn exception_edge(&self, state: &State, catch_type: u16) -> State {
+        match state {
+            State::Frame(frame) => {
+                let caught = match catch_type {
+                    0 => Ok("java/lang/Throwable"),
+                    index => self.constant_pool.get_class_name(index),
+                };
+                match caught {
+                    Ok(caught) => State::Frame(Frame {
+                        locals: frame.locals.clone(),
+                        stack: vec![InferredType::Reference(caught.to_string())],
+                    }),
+                    Err(error) => State::Invalid {
+                        pc: None,
+                        message: error.to_string(),
+                    },
+                }
+            }
+            _ => state.clone(),
+        }
+    }
+}
+
+impl TypeAnalysis<'_> {
+    /// The type of the values the `<t>load`, `<t>store`, `<t>aload`,
+    /// `<t>astore` or `<t>return` opcode works on.
+    fn opcode_type(opcode: u8) -> InferredType {
+        let kind = match opcode {
+            0x15..=0x19 => opcode - 0x15,
+            0x1a..=0x2d => (opcode - 0x1a) / 4,
+            0x2e..=0x35 => opcode - 0x2e,
+            0x36..=0x3a => opcode - 0x36,
+            0x3b..=0x4e => (opcode - 0x3b) / 4,
+            0x4f..=0x56 => opcode - 0x4f,
+            0xac..=0xb0 => opcode - 0xac,
+            _ => 0,
+        };
+        match kind {
+            1 => InferredType::Long,
+            2 => InferredType::Float,
+            3 => InferredType::Double,
+            4 => InferredType::Reference(InferredType::OBJECT.to_string()),
+            _ => InferredType::Int,
+        }
+    }
+
+    fn member(&self, index: u16) -> Result<(&str, &str, &str), String> {
+        self.constant_pool
+            .get_member(index)
+            .map_err(|error| error.to_string())
+    }
+
+    fn field_type(descriptor: &str) -> Result<InferredType, String> {
+        FieldType::parse(descriptor)
+            .map(|field_type| InferredType::from_field_type(&field_type))
+            .map_err(|error| error.to_string())
+    }
+
+    fn class_name(&self, index: u16) -> Result<&str, String> {
+        self.constant_pool
+            .get_class_name(index)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Pops the arguments of the call and pushes its result.
+    fn call(&self, frame: &mut Frame, descriptor: &str) -> Result<(), String> {
+        let descriptor = MethodDescriptor::parse(descriptor).map_err(|error| error.to_string())?;
+        for parameter in descriptor.parameters.iter().rev() {
+            frame.pop_expecting(&InferredType::from_field_type(parameter))?;
+        }
+        if let Some(return_type) = &descriptor.return_type {
+            frame.stack.push(InferredType::from_field_type(return_type));
+        }
+        Ok(())
+    }
+
+    /// Pops the arguments and the receiver of the call, returning the
+    /// receiver, and pushes its result.
+    fn call_with_receiver(
+        &self,
+        frame: &mut Frame,
+        descriptor: &str,
+    ) -> Result<InferredType, String> {
+        let descriptor = MethodDescriptor::parse(descriptor).map_err(|error| error.to_string())?;
+        for parameter in descriptor.parameters.iter().rev() {
+            frame.pop_expecting(&InferredType::from_field_type(parameter))?;
+        }
+        let receiver = frame.pop_reference()?;
+        if let Some(return_type) = &descriptor.return_type {
+            frame.stack.push(InferredType::from_field_type(return_type));
+        }
+        Ok(receiver)
+    }
+
+    /// Marks the receiver of a constructor of the class as initialized
+    /// wherever it is held.
+    fn construct(
+        &self,
+        frame: &mut Frame,
+        class: &str,
+        receiver: InferredType,
+    ) -> Result<(), String> {
+        let constructed = match &receiver {
+            InferredType::UninitializedThis => self.class_name.to_string(),
+            InferredType::Uninitialized(pc) => {
+                let index = self.decoded.pcs.binary_search(pc).ok();
+                match index.map(|index| self.decoded.instructions[index]) {
+                    Some(Instruction::New(created)) => self.class_name(created)?.to_string(),
+                    _ => return Err(format!("No new at pc {}", pc)),
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Constructor {}.<init> called on {}",
+                    class, receiver
+                ))
+            }
+        };
+        for value in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+            if *value == receiver {
+                *value = InferredType::Reference(constructed.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn step(
+        &self,
+        index: usize,
+        instruction: Instruction,
+        frame: &mut Frame,
+    ) -> Result<(), String> {
+        use InferredType::*;
+
+        let pc = self.decoded.pcs[index] as usize;
+        let truncated = || format!("Truncated instruction at pc {}", pc);
+        let opcode = match self.code[pc] {
+            0xc4 => *self.code.get(pc + 1).ok_or_else(truncated)?,
+            opcode => opcode,
+        };
+        let unary = |frame: &mut Frame, operand: InferredType, result: InferredType| {
+            frame.pop_expecting(&operand)?;
+            frame.stack.push(result);
+            Ok::<(), String>(())
+        };
+        let binary = |frame: &mut Frame, left: InferredType, right: InferredType| {
+            frame.pop_expecting(&right)?;
+            frame.pop_expecting(&left)?;
+            frame.stack.push(left);
+            Ok::<(), String>(())
+        };
+
+        match instruction {
+            Instruction::Nop | Instruction::Goto(_) | Instruction::Return => {}
+            Instruction::AConstNull => frame.stack.push(Null),
+            Instruction::IConst(_) => frame.stack.push(Int),
+            Instruction::LConst(_) => frame.stack.push(Long),
+            Instruction::FConst(_) => frame.stack.push(Float),
+            Instruction::DConst(_) => frame.stack.push(Double),
+            Instruction::Ldc(constant) => {
+                let value = match self.constant_pool.get(constant as usize) {
+                    Some(Constant::Integer(_)) => Int,
+                    Some(Constant::Float(_)) => Float,
+                    Some(Constant::Long(_)) => Long,
+                    Some(Constant::Double(_)) => Double,
+                    Some(Constant::String(_)) => Reference("java/lang/String".to_string()),
+                    Some(Constant::Class(_)) => Reference("java/lang/Class".to_string()),
+                    Some(Constant::MethodType(_)) => {
+                        Reference("java/lang/invoke/MethodType".to_string())
+                    }
+                    Some(Constant::MethodHandle(_)) => {
+                        Reference("java/lang/invoke/MethodHandle".to_string())
+                    }
+                    _ => return Err(format!("Constant #{} cannot be loaded", constant)),
+                };
+                frame.stack.push(value);
+            }
+            Instruction::Load(local) => {
+                let value = frame.local(local)?.clone();
+                let expected = Self::opcode_type(opcode);
+                let matches = match expected {
+                    Reference(_) => value.is_reference() || matches!(value, ReturnAddress(_)),
+                    _ => value == expected,
+                };
+                if !matches {
+                    return Err(format!(
+                        "Expected {} in local {}, found {}",
+                        expected, local, value
+                    ));
+                }
+                frame.stack.push(value);
+            }
+            Instruction::Store(local) => {
+                let value = frame.pop()?;
+                let expected = Self::opcode_type(opcode);
+                let matches = match expected {
+                    Reference(_) => value.is_reference() || matches!(value, ReturnAddress(_)),
+                    _ => value == expected,
+                };
+                if !matches {
+                    return Err(format!(
+                        "Expected {} on the stack, found {}",
+                        expected, value
+                    ));
+                }
+                frame.store(local, value)?;
+            }
+            Instruction::ArrayLoad => {
+                frame.pop_expecting(&Int)?;
+                let array = frame.pop_reference()?;
+                let element = match (&array, Self::opcode_type(opcode)) {
+                    (Null, Reference(_)) => Null,
+                    (Reference(descriptor), Reference(_)) if descriptor.starts_with('[') => {
+                        match Self::field_type(&descriptor[1..])? {
+                            Reference(element) => Reference(element),
+                            _ => return Err(format!("aaload from {}", descriptor)),
+                        }
+                    }
+                    (_, Reference(_)) => return Err(format!("Expected an array, found {}", array)),
+                    (_, element) => element,
+                };
+                frame.stack.push(element);
+            }
+            Instruction::ArrayStore => {
+                frame.pop_expecting(&Self::opcode_type(opcode))?;
+                frame.pop_expecting(&Int)?;
+                frame.pop_reference()?;
+            }
+            Instruction::Pop => {
+                if frame.pop()?.is_wide() {
+                    return Err("Stack manipulation splits a long or double".to_string());
+                }
+            }
+            Instruction::Pop2 => {
+                let value = frame.pop()?;
+                if !value.is_wide() {
+                    let second = frame.pop()?;
+                    if second.is_wide() {
+                        return Err("Stack manipulation splits a long or double".to_string());
+                    }
+                }
+            }
+            Instruction::Dup => frame.dup(1, 0)?,
+            Instruction::DupX1 => frame.dup(1, 1)?,
+            Instruction::DupX2 => frame.dup(1, 2)?,
+            Instruction::Dup2 => frame.dup(2, 0)?,
+            Instruction::Dup2X1 => frame.dup(2, 1)?,
+            Instruction::Dup2X2 => frame.dup(2, 2)?,
+            Instruction::Swap => {
+                frame.dup(1, 1)?;
+                frame.pop()?;
+            }
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IMul
+            | Instruction::IDiv
+            | Instruction::IRem
+            | Instruction::IShl
+            | Instruction::IShr
+            | Instruction::IUShr
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor => binary(frame, Int, Int)?,
+            Instruction::LAdd
+            | Instruction::LSub
+            | Instruction::LMul
+            | Instruction::LDiv
+            | Instruction::LRem
+            | Instruction::LAnd
+            | Instruction::LOr
+            | Instruction::LXor => binary(frame, Long, Long)?,
+            Instruction::LShl | Instruction::LShr | Instruction::LUShr => binary(frame, Long, Int)?,
+            Instruction::FAdd
+            | Instruction::FSub
+            | Instruction::FMul
+            | Instruction::FDiv
+            | Instruction::FRem => binary(frame, Float, Float)?,
+            Instruction::DAdd
+            | Instruction::DSub
+            | Instruction::DMul
+            | Instruction::DDiv
+            | Instruction::DRem => binary(frame, Double, Double)?,
+            Instruction::INeg | Instruction::I2B | Instruction::I2C | Instruction::I2S => {
+                unary(frame, Int, Int)?
+            }
+            Instruction::LNeg => unary(frame, Long, Long)?,
+            Instruction::FNeg => unary(frame, Float, Float)?,
+            Instruction::DNeg => unary(frame, Double, Double)?,
+            Instruction::I2L => unary(frame, Int, Long)?,
+            Instruction::I2F => unary(frame, Int, Float)?,
+            Instruction::I2D => unary(frame, Int, Double)?,
+            Instruction::L2I => unary(frame, Long, Int)?,
+            Instruction::L2F => unary(frame, Long, Float)?,
+            Instruction::L2D => unary(frame, Long, Double)?,
+            Instruction::F2I => unary(frame, Float, Int)?,
+            Instruction::F2L => unary(frame, Float, Long)?,
+            Instruction::F2D => unary(frame, Float, Double)?,
+            Instruction::D2I => unary(frame, Double, Int)?,
+            Instruction::D2L => unary(frame, Double, Long)?,
+            Instruction::D2F => unary(frame, Double, Float)?,
+            Instruction::LCmp => {
+                binary(frame, Long, Long)?;
+                unary(frame, Long, Int)?;
+            }
+            Instruction::FCmpL | Instruction::FCmpG => {
+                binary(frame, Float, Float)?;
+                unary(frame, Float, Int)?;
+            }
+            Instruction::DCmpL | Instruction::DCmpG => {
+                binary(frame, Double, Double)?;
+                unary(frame, Double, Int)?;
+            }
+            Instruction::IInc(local, _) => {
+                if *frame.local(local)? != Int {
+                    return Err(format!("Expected int in local {}", local));
+                }
+            }
+            Instruction::If(..) | Instruction::Switch(_) => {
+                frame.pop_expecting(&Int)?;
+            }
+            Instruction::IfICmp(..) => {
+                frame.pop_expecting(&Int)?;
+                frame.pop_expecting(&Int)?;
+            }
+            Instruction::IfACmpEq(_) | Instruction::IfACmpNe(_) => {
+                frame.pop_reference()?;
+                frame.pop_reference()?;
+            }
+            Instruction::IfNull(_)
+            | Instruction::IfNonNull(_)
+            | Instruction::AThrow
+            | Instruction::MonitorEnter
+            | Instruction::MonitorExit => {
+                frame.pop_reference()?;
+            }
+            Instruction::Jsr(_) => frame.stack.push(ReturnAddress(index as u32 + 1)),
+            Instruction::Ret(local) => {
+                if !matches!(frame.local(local)?, ReturnAddress(_)) {
+                    return Err(format!("Expected a return address in local {}", local));
+                }
+            }
+            Instruction::ReturnValue => {
+                frame.pop_expecting(&Self::opcode_type(opcode))?;
+            }
+            Instruction::GetStatic(field) => {
+                let (_, _, descriptor) = self.member(field)?;
+                frame.stack.push(Self::field_type(descriptor)?);
+            }
+            Instruction::PutStatic(field) => {
+                let (_, _, descriptor) = self.member(field)?;
+                frame.pop_expecting(&Self::field_type(descriptor)?)?;
+            }
+            Instruction::GetField(field) => {
+                let (_, _, descriptor) = self.member(field)?;
+                frame.pop_reference()?;
+                frame.stack.push(Self::field_type(descriptor)?);
+            }
+            Instruction::PutField(field) => {
+                let (_, _, descriptor) = self.member(field)?;
+                frame.pop_expecting(&Self::field_type(descriptor)?)?;
+                frame.pop_reference()?;
+            }
+            Instruction::InvokeVirtual(method, _) | Instruction::InvokeInterface(method, _) => {
+                let (_, _, descriptor) = self.member(method)?;
+                self.call_with_receiver(frame, descriptor)?;
+            }
+            Instruction::InvokeSpecial(method) => {
+                let (class, name, descriptor) = self.member(method)?;
+                let receiver = self.call_with_receiver(frame, descriptor)?;
+                if name == "<init>" {
+                    self.construct(frame, class, receiver)?;
+                }
+            }
+            Instruction::InvokeStatic(method) => {
+                let (_, _, descriptor) = self.member(method)?;
+                self.call(frame, descriptor)?;
+            }
+            Instruction::New(_) => frame.stack.push(Uninitialized(pc as u32)),
+            Instruction::NewArray(array_type) => {
+                frame.pop_expecting(&Int)?;
+                let element = match array_type {
+                    4 => "Z",
+                    5 => "C",
+                    6 => "F",
+                    7 => "D",
+                    8 => "B",
+                    9 => "S",
+                    10 => "I",
+                    11 => "J",
+                    _ => return Err(format!("Unknown array type {}", array_type)),
+                };
+                frame.stack.push(Reference(format!("[{}", element)));
+            }
+            Instruction::ANewArray(class) => {
+                frame.pop_expecting(&Int)?;
+                let class = self.class_name(class)?;
+                frame.stack.push(Reference(if class.starts_with('[') {
+                    format!("[{}", class)
+                } else {
+                    format!("[L{};", class)
+                }));
+            }
+            Instruction::ArrayLength => {
+                frame.pop_reference()?;
+                frame.stack.push(Int);
+            }
+            Instruction::CheckCast(class) => {
+                frame.pop_reference()?;
+                frame
+                    .stack
+                    .push(Reference(self.class_name(class)?.to_string()));
+            }
+            Instruction::InstanceOf(_) => {
+                frame.pop_reference()?;
+                frame.stack.push(Int);
+            }
+            Instruction::MultiANewArray(class, dimensions) => {
+                for _ in 0..dimensions {
+                    frame.pop_expecting(&Int)?;
+                }
+                frame
+                    .stack
+                    .push(Reference(self.class_name(class)?.to_string()));
+            }
+            // invokedynamic
+            Instruction::Unsupported(0xba) => {
+                let operands = self.code.get(pc + 1..pc + 3).ok_or_else(truncated)?;
+                let constant = BigEndian::read_u16(operands);
+                let descriptor = match self.constant_pool.get(constant as usize) {
+                    Some(Constant::InvokeDynamic(invoke_dynamic)) => {
+                        self.constant_pool
+                            .get_name_and_type(invoke_dynamic.name_and_type_index)
+                            .map_err(|error| error.to_string())?
+                            .1
+                    }
+                    _ => return Err(format!("Constant #{} is not an invokedynamic", constant)),
+                };
+                self.call(frame, descriptor)?;
+            }
+            Instruction::Unsupported(opcode) => {
+                return Err(format!("Cannot decode opcode 0x{:02x}", opcode))
+            }
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod inference_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{infer_frames, InferredType};
+    use crate::class::attributes::{Attribute, CodeAttribute};
+    use crate::class::{Class, MethodInfo};
+
+    fn code(method: &MethodInfo) -> &CodeAttribute {
+        method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_infer_frames() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let mut class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        let find = |class: &Class, name: &str| {
+            let name = class.constant_pool.find_utf8(name).unwrap();
+            class
+                .methods
+                .iter()
+                .position(|method| method.name_index == name)
+                .unwrap()
+        };
+
+        // try { return a / b; } catch (ArithmeticException e) { return 0; }
+        let safe_divide = &class.methods[find(&class, "safeDivide")];
+        let frames = infer_frames(&class, safe_divide, code(safe_divide)).unwrap();
+        assert_eq!(
+            frames[4].as_ref().unwrap().stack,
+            vec![InferredType::Reference(
+                "java/lang/ArithmeticException".to_string()
+            )]
+        );
+        assert_eq!(
+            frames[5].as_ref().unwrap().to_string(),
+            "locals [int, int, java/lang/ArithmeticException] stack []"
+        );
+
+        // add(int, int) adding a float: iload_0, fconst_0, iadd, ireturn
+        let add = find(&class, "add");
+        for attribute in &mut class.methods[add].attributes {
+            if let Attribute::Code(code) = attribute {
+                code.code[1] = 0x0b;
+            }
+        }
+        let add = &class.methods[add];
+        let error = infer_frames(&class, add, code(add)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pc 2: Expected int on the stack, found float"
+        );
+    }
+
+    #[test]
+    fn test_truncated_instructions() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let mut class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        let add = class.constant_pool.find_utf8("add").unwrap();
+        let add = class
+            .methods
+            .iter()
+            .position(|method| method.name_index == add)
+            .unwrap();
+
+        // A wide without the instruction it widens, and an invokedynamic
+        // without its constant
+        for truncated in [vec![0xc4], vec![0xba, 0x00]] {
+            for attribute in &mut class.methods[add].attributes {
+                if let Attribute::Code(code) = attribute {
+                    code.code = truncated.clone();
+                }
+            }
+            let method = &class.methods[add];
+            let error = infer_frames(&class, method, code(method)).unwrap_err();
+            assert_eq!(error.to_string(), "pc 0: Truncated instruction at pc 0");
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use crate::class::instruction::Instruction;
+use crate::vm::runtime::{ClassId, RuntimeMethod};
+use crate::vm::value::ObjectRef;
+use crate::vm::{Unwind, Vm};
+
+/// Receiver classes an inline cache records before the call site counts as
+/// megamorphic.
+const POLYMORPHIC_LIMIT: usize = 4;
+
+/// The targets an `invokevirtual` or `invokeinterface` call site selected,
+/// by receiver class. Sites seeing one class are monomorphic, then
+/// polymorphic until [POLYMORPHIC_LIMIT] classes, when they turn
+/// megamorphic and other receivers always have their target looked up.
+#[derive(Debug, Default)]
+pub(crate) struct InlineCache {
+    /// The method referenced by the call site, once resolved.
+    pub(crate) resolved: Option<Arc<RuntimeMethod>>,
+    pub(crate) targets: Vec<(ClassId, Arc<RuntimeMethod>)>,
+}
+
+impl InlineCache {
+    pub(crate) fn is_megamorphic(&self) -> bool {
+        self.targets.len() == POLYMORPHIC_LIMIT
+    }
+}
+
+impl Vm {
+    /// Gives every `invokevirtual` and `invokeinterface` instruction an
+    /// inline cache of its own.
+    pub(crate) fn allocate_inline_caches(&mut self, instructions: &mut [Instruction]) {
+        for instruction in instructions {
+            if let Instruction::InvokeVirtual(_, cache) | Instruction::InvokeInterface(_, cache) =
+                instruction
+            {
+                *cache = self.inline_caches.len() as u32;
+                self.inline_caches.push(InlineCache::default());
+            }
+        }
+    }
+
+    /// Resolves the method referenced by the call site, once.
+    pub(crate) fn resolve_cached(
+        &mut self,
+        class: ClassId,
+        index: u16,
+        cache: u32,
+    ) -> Result<Arc<RuntimeMethod>, Unwind> {
+        if let Some(resolved) = &self.inline_caches[cache as usize].resolved {
+            return Ok(resolved.clone());
+        }
+
+        let resolved = self.resolve_method_ref(class, index)?;
+        self.inline_caches[cache as usize].resolved = Some(resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Selects the implementation invoked on the receiver by the call site,
+    /// looking it up only for receiver classes missing from its cache.
+    pub(crate) fn select_cached(
+        &mut self,
+        receiver: ObjectRef,
+        resolved: Arc<RuntimeMethod>,
+        cache: u32,
+    ) -> Result<Arc<RuntimeMethod>, Unwind> {
+        let class = self.class_of(receiver);
+        let cache = cache as usize;
+        let hit = self.inline_caches[cache]
+            .targets
+            .iter()
+            .find(|(receiver_class, _)| *receiver_class == class);
+        if let Some((_, target)) = hit {
+            return Ok(target.clone());
+        }
+
+        let target = self.select_virtual(receiver, resolved)?;
+        let cache = &mut self.inline_caches[cache];
+        if !cache.is_megamorphic() {
+            cache.targets.push((class, target.clone()));
+        }
+        Ok(target)
+    }
+}
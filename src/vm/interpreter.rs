@@ -0,0 +1,1651 @@
+use std::io::Write;
+use std::mem;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::class::descriptor::FieldType;
+use crate::class::instruction::mnemonic;
+use crate::class::instruction::Instruction;
+use crate::class::{ClassAccessFlags, MethodAccessFlags};
+use crate::vm::heap::{ArrayData, HeapObject, NativeData, ObjectData};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::NativeFn;
+use crate::vm::runtime::{ClassId, ClassKind, MethodCode, RuntimeMethod};
+use crate::vm::sampler::SamplingProfiler;
+use crate::vm::thread::Frame;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The bytes of the stack taken up by running a method from native code,
+/// standing for the host frames of the interpreter, so that a deep chain
+/// of such calls overflows the Java stack before the host's.
+const HOST_FRAMES_SIZE: usize = 2 * 1024;
+
+/// Why the interpreter stopped executing the current frame.
+enum Exit {
+    /// The method returned, with its result unless it is `void`.
+    Return(Option<Value>),
+    /// The method invokes another one with the given arguments.
+    Invoke(Arc<RuntimeMethod>, Vec<Value>),
+}
+
+// =============================================================================
+// INVOCATION
+// =============================================================================
+
+impl Vm {
+    /// Invokes the method with the receiver (for instance methods) and the
+    /// arguments, running it to completion.
+    pub(crate) fn invoke(
+        &mut self,
+        method: Arc<RuntimeMethod>,
+        arguments: &[Value],
+    ) -> Result<Option<Value>, Unwind> {
+        if let Some(native) = method.native {
+            return self.call_native(&method, native, arguments);
+        }
+        if method.code.is_none() {
+            return Err(self.throw_abstract_method_error(&method));
+        }
+
+        // The host frames running the method take up the stack as well
+        self.reserve_stack(HOST_FRAMES_SIZE)?;
+        let base = self.thread.frames.len();
+        let result = self.push_frame(method, arguments).and_then(|_| {
+            self.invocations += 1;
+            let result = self.run(base);
+            self.invocations -= 1;
+            result
+        });
+        self.pop_frames_to(base);
+        self.thread.size -= HOST_FRAMES_SIZE;
+        result
+    }
+
+    /// Invokes an instance method on the object, selected by its class.
+    pub(crate) fn invoke_virtual(
+        &mut self,
+        object: ObjectRef,
+        name: &str,
+        descriptor: &str,
+        arguments: &[Value],
+    ) -> Result<Option<Value>, Unwind> {
+        let class = self.class_of(object);
+        let method = match self
+            .member_symbols(name, descriptor)
+            .and_then(|(name, descriptor)| self.find_virtual(class, &name, &descriptor))
+        {
+            Some(method) => method,
+            None => {
+                let message = format!("{}.{}{}", self.class(class).java_name(), name, descriptor);
+                return Err(self.throw_new("java/lang/AbstractMethodError", Some(message)));
+            }
+        };
+
+        let mut values = Vec::with_capacity(arguments.len() + 1);
+        values.push(Value::Reference(Some(object)));
+        values.extend_from_slice(arguments);
+        self.invoke(method, &values)
+    }
+
+    /// Calls the Rust implementation of a method. The natives and built-in
+    /// methods reaching the host system are subject to the policy of the VM.
+    fn call_native(
+        &mut self,
+        method: &Arc<RuntimeMethod>,
+        native: NativeFn,
+        arguments: &[Value],
+    ) -> Result<Option<Value>, Unwind> {
+        if let Some(permission) = method.permission {
+            self.check_permission(permission)?;
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(method, None);
+        }
+        self.fire_method_entry(method);
+        self.invocations += 1;
+        let below = self.thread.frames.len();
+        self.thread.natives.push((below, method.clone()));
+        let result = native(self, arguments);
+        self.thread.natives.pop();
+        self.invocations -= 1;
+        self.fire_method_exit(method);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave();
+        }
+        self.check_replay()?;
+        result
+    }
+
+    /// Takes up the bytes of the stack of the current thread, throwing
+    /// `StackOverflowError` instead when they do not fit in it.
+    fn reserve_stack(&mut self, size: usize) -> Result<(), Unwind> {
+        if self.thread.size + size > self.stack_size {
+            return Err(self.throw_new("java/lang/StackOverflowError", None));
+        }
+        self.thread.size += size;
+        Ok(())
+    }
+
+    fn push_frame(
+        &mut self,
+        method: Arc<RuntimeMethod>,
+        arguments: &[Value],
+    ) -> Result<(), Unwind> {
+        self.reserve_stack(Frame::size(&method))?;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(&method, Some(self.thread.frames.len() + 1));
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.enter(&method);
+        }
+        self.fire_method_entry(&method);
+        let mut frame = Frame::new(method, arguments);
+        if frame
+            .method
+            .access_flags
+            .contains(MethodAccessFlags::SYNCHRONIZED)
+        {
+            let monitor = match arguments.first() {
+                _ if frame.method.is_static() => self.mirror(frame.class).ok(),
+                Some(Value::Reference(receiver)) => *receiver,
+                _ => None,
+            };
+            frame.monitors.extend(monitor);
+        }
+        self.thread.frames.push(frame);
+        Ok(())
+    }
+
+    /// Pops the frames above `depth`.
+    fn pop_frames_to(&mut self, depth: usize) {
+        if let Some(popped) = self.thread.frames.get(depth..) {
+            let size: usize = popped.iter().map(|frame| Frame::size(&frame.method)).sum();
+            self.thread.size -= size;
+        }
+        if !self.listeners.is_empty() {
+            while self.thread.frames.len() > depth {
+                if let Some(frame) = self.thread.frames.pop() {
+                    self.fire_method_exit(&frame.method);
+                }
+            }
+        }
+        self.thread.frames.truncate(depth);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave_frames_above(depth);
+        }
+    }
+
+    fn throw_abstract_method_error(&mut self, method: &RuntimeMethod) -> Unwind {
+        let message = format!(
+            "{}.{}{}",
+            self.class(method.class).java_name(),
+            method.name,
+            method.descriptor
+        );
+        let class = if method.access_flags.contains(MethodAccessFlags::NATIVE) {
+            "java/lang/UnsatisfiedLinkError"
+        } else {
+            "java/lang/AbstractMethodError"
+        };
+        self.throw_new(class, Some(message))
+    }
+
+    /// Runs the frames above `base` until the one at `base` returns.
+    fn run(&mut self, base: usize) -> Result<Option<Value>, Unwind> {
+        loop {
+            let exit = match self.execute() {
+                Ok(exit) => exit,
+                Err(Unwind::Throw(exception)) => {
+                    self.handle_exception(exception, base)?;
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            match exit {
+                Exit::Return(value) => {
+                    self.pop_frames_to(self.thread.frames.len() - 1);
+                    if self.thread.frames.len() == base {
+                        return Ok(value);
+                    }
+                    self.complete_invoke(value);
+                }
+                Exit::Invoke(method, arguments) => {
+                    if let Some(native) = method.native {
+                        match self.call_native(&method, native, &arguments) {
+                            Ok(value) => self.complete_invoke(value),
+                            Err(Unwind::Throw(exception)) => {
+                                self.handle_exception(exception, base)?
+                            }
+                            Err(error) => return Err(error),
+                        }
+                    } else if method.code.is_none() {
+                        let exception = self.throw_abstract_method_error(&method);
+                        match exception {
+                            Unwind::Throw(exception) => self.handle_exception(exception, base)?,
+                            error => return Err(error),
+                        }
+                    } else if let Err(unwind) = self.push_frame(method, &arguments) {
+                        match unwind {
+                            Unwind::Throw(exception) => self.handle_exception(exception, base)?,
+                            error => return Err(error),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pushes the result of the completed invocation onto the invoking frame,
+    /// and moves it past the invoke instruction.
+    fn complete_invoke(&mut self, value: Option<Value>) {
+        let frame = self.frame();
+        frame.stack.extend(value);
+        frame.ip += 1;
+    }
+
+    /// Unwinds the frames above `base` until one with a matching exception
+    /// handler, which continues at the handler. Rethrows the exception if no
+    /// frame catches it.
+    fn handle_exception(&mut self, exception: ObjectRef, base: usize) -> Result<(), Unwind> {
+        while self.thread.frames.len() > base {
+            if let Some(handler_pc) = self.find_handler(exception) {
+                let frame = self.frame();
+                frame.ip = frame
+                    .method
+                    .code
+                    .as_ref()
+                    .and_then(|code| code.instruction_index(handler_pc))
+                    .ok_or_else(|| {
+                        Unwind::Error(VmError::Internal(format!(
+                            "Invalid exception handler pc {} in {}{}",
+                            handler_pc, frame.method.name, frame.method.descriptor
+                        )))
+                    })?;
+                frame.stack.clear();
+                frame.stack.push(Value::Reference(Some(exception)));
+                self.fire_exception_caught(exception, handler_pc);
+                return Ok(());
+            }
+            self.pop_frames_to(self.thread.frames.len() - 1);
+        }
+
+        Err(Unwind::Throw(exception))
+    }
+
+    /// The handler of the current frame covering its pc and catching the
+    /// exception. Catch types which cannot be loaded do not match.
+    fn find_handler(&mut self, exception: ObjectRef) -> Option<usize> {
+        let frame = self.thread.frames.last()?;
+        let (method, pc, class) = (frame.method.clone(), frame.pc(), frame.class);
+        let loader = self.class(class).defining_loader;
+
+        for handler in &method.code.as_ref()?.exception_handlers {
+            if pc < handler.start_pc as usize || pc >= handler.end_pc as usize {
+                continue;
+            }
+            let catches = match &handler.catch_type {
+                None => true,
+                Some(catch_type) => match self.load_class(loader, catch_type) {
+                    Ok(catch_type) => self.is_instance(exception, catch_type),
+                    Err(_) => false,
+                },
+            };
+            if catches {
+                return Some(handler.handler_pc as usize);
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn frame(&mut self) -> &mut Frame {
+        self.thread
+            .frames
+            .last_mut()
+            .expect("No frame is being executed")
+    }
+}
+
+// =============================================================================
+// EXCEPTIONS
+// =============================================================================
+
+impl Vm {
+    /// Creates an exception of the given bootstrap class with the message and
+    /// the current stack trace, to be thrown.
+    pub(crate) fn throw_new(&mut self, class: &str, message: Option<String>) -> Unwind {
+        let exception = (|| {
+            let class = self.load_class(LoaderId::BOOTSTRAP, class)?;
+            self.initialize_class(class)?;
+            let exception = self.instantiate(class)?;
+            if let Some(message) = message {
+                let message = self.create_string(message.encode_utf16().collect())?;
+                self.set_field(exception, "detailMessage", Value::Reference(Some(message)));
+            }
+            self.fill_in_stack_trace(exception);
+            self.fire_exception_thrown(exception);
+            Ok(exception)
+        })();
+
+        match exception {
+            Ok(exception) => Unwind::Throw(exception),
+            Err(unwind) => unwind,
+        }
+    }
+
+    /// Captures the stack of the current thread into the throwable, leaving
+    /// out the frames constructing it.
+    pub(crate) fn fill_in_stack_trace(&mut self, throwable: ObjectRef) {
+        let class = self.class_of(throwable);
+        let skipped = self
+            .thread
+            .frames
+            .iter()
+            .rev()
+            .take_while(|frame| {
+                frame.method.name == "<init>" && self.is_assignable(class, frame.class)
+            })
+            .count();
+
+        let backtrace = self.thread.frames[..self.thread.frames.len() - skipped]
+            .iter()
+            .rev()
+            .map(|frame| self.stack_trace_element(frame))
+            .collect();
+        self.heap.get_mut(throwable).native = NativeData::Backtrace(backtrace);
+    }
+
+    fn throw_null_pointer(&mut self) -> Unwind {
+        self.throw_new("java/lang/NullPointerException", None)
+    }
+}
+
+// =============================================================================
+// EXECUTION
+// =============================================================================
+
+/// Converts an `int` to the representation of the narrower field type, as
+/// `putfield`/`putstatic` store them.
+fn narrow(value: Value, field_type: &FieldType) -> Value {
+    match (value, field_type) {
+        (Value::Int(value), FieldType::Boolean) => Value::Int(value & 1),
+        (Value::Int(value), FieldType::Byte) => Value::Int(value as i8 as i32),
+        (Value::Int(value), FieldType::Char) => Value::Int(value as u16 as i32),
+        (Value::Int(value), FieldType::Short) => Value::Int(value as i16 as i32),
+        (value, _) => value,
+    }
+}
+
+fn compare<T: PartialOrd>(a: T, b: T, nan_result: i32) -> i32 {
+    if a > b {
+        1
+    } else if a == b {
+        0
+    } else if a < b {
+        -1
+    } else {
+        nan_result
+    }
+}
+
+/// The instruction index, operand stack and local variables of the frame
+/// being executed, moved out of it while the interpreter runs so that they
+/// are accessed without looking up the frame.
+///
+/// The rest of the VM looks at the stack and locals of the frame only while
+/// the interpreter polls, which moves the registers back, and at its
+/// instruction index while an instruction resolves, allocates, invokes or
+/// throws, which stores it first.
+#[derive(Default)]
+struct Registers {
+    ip: usize,
+    stack: Vec<Value>,
+    locals: Vec<Value>,
+}
+
+impl Registers {
+    /// Exchanges the registers with the ones held by the frame.
+    fn swap(&mut self, frame: &mut Frame) {
+        mem::swap(&mut self.ip, &mut frame.ip);
+        mem::swap(&mut self.stack, &mut frame.stack);
+        mem::swap(&mut self.locals, &mut frame.locals);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Operand stack underflow")
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop_int(&mut self) -> i32 {
+        match self.pop() {
+            Value::Int(value) => value,
+            value => panic!("Expected int on the operand stack, got {:?}", value),
+        }
+    }
+
+    fn pop_long(&mut self) -> i64 {
+        match self.pop() {
+            Value::Long(value) => value,
+            value => panic!("Expected long on the operand stack, got {:?}", value),
+        }
+    }
+
+    fn pop_float(&mut self) -> f32 {
+        match self.pop() {
+            Value::Float(value) => value,
+            value => panic!("Expected float on the operand stack, got {:?}", value),
+        }
+    }
+
+    fn pop_double(&mut self) -> f64 {
+        match self.pop() {
+            Value::Double(value) => value,
+            value => panic!("Expected double on the operand stack, got {:?}", value),
+        }
+    }
+
+    fn pop_reference(&mut self) -> Option<ObjectRef> {
+        match self.pop() {
+            Value::Reference(value) => value,
+            value => panic!("Expected reference on the operand stack, got {:?}", value),
+        }
+    }
+
+    /// Pops a category 1 value, the only kind the instructions treating the
+    /// stack as untyped slots may take on their own.
+    fn pop_category1(&mut self) -> Value {
+        match self.pop() {
+            value if value.is_wide() || value == Value::Top => {
+                panic!(
+                    "Expected a category 1 value on the operand stack, got {:?}",
+                    value
+                )
+            }
+            value => value,
+        }
+    }
+
+    /// Pops a category 1 value, or two of them as a pair; a category 2 value
+    /// is a pair on its own. The operand stack holds `long` and `double`
+    /// values in one entry, while the instructions count them as two slots,
+    /// so a pair never takes one half of them.
+    fn pop_pair(&mut self) -> Vec<Value> {
+        let top = self.pop();
+        if top.is_wide() {
+            vec![top]
+        } else {
+            let below = self.pop_category1();
+            vec![below, top]
+        }
+    }
+
+    /// `dup`: ..., value → ..., value, value
+    fn dup(&mut self) {
+        let value = self.pop_category1();
+        self.push_all(&[value, value]);
+    }
+
+    /// `dup_x1`: ..., value2, value1 → ..., value1, value2, value1
+    fn dup_x1(&mut self) {
+        let (top, below) = (self.pop_category1(), self.pop_category1());
+        self.push_all(&[top, below, top]);
+    }
+
+    /// `dup_x2`: the top category 1 value inserted below the pair under it,
+    /// two category 1 values or one category 2 value.
+    fn dup_x2(&mut self) {
+        let top = self.pop_category1();
+        let below = self.pop_pair();
+        self.push(top);
+        self.push_all(&below);
+        self.push(top);
+    }
+
+    /// `dup2`: the top pair duplicated.
+    fn dup2(&mut self) {
+        let pair = self.pop_pair();
+        self.push_all(&pair);
+        self.push_all(&pair);
+    }
+
+    /// `dup2_x1`: the top pair inserted below the category 1 value under it.
+    fn dup2_x1(&mut self) {
+        let pair = self.pop_pair();
+        let below = self.pop_category1();
+        self.push_all(&pair);
+        self.push(below);
+        self.push_all(&pair);
+    }
+
+    /// `dup2_x2`: the top pair inserted below the pair under it, in any of
+    /// the four forms of the category of the values.
+    fn dup2_x2(&mut self) {
+        let pair = self.pop_pair();
+        let below = self.pop_pair();
+        self.push_all(&pair);
+        self.push_all(&below);
+        self.push_all(&pair);
+    }
+
+    /// `swap`: ..., value2, value1 → ..., value1, value2
+    fn swap_top(&mut self) {
+        let (top, below) = (self.pop_category1(), self.pop_category1());
+        self.push_all(&[top, below]);
+    }
+
+    fn push_all(&mut self, values: &[Value]) {
+        self.stack.extend_from_slice(values);
+    }
+
+    /// Pops the arguments of the method off the operand stack, receiver first.
+    fn pop_arguments(&mut self, method: &RuntimeMethod) -> Vec<Value> {
+        self.pop_values(method.argument_count())
+    }
+
+    fn pop_values(&mut self, count: usize) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - count)
+    }
+
+    fn load_local(&mut self, index: u16) {
+        let value = self.locals[index as usize];
+        if value == Value::Top {
+            panic!("Local {} holds no value, or the second half of one", index);
+        }
+        self.push(value);
+    }
+
+    /// Stores the top of the stack to the local, a `long` or `double` taking
+    /// the next local too. Storing to the second half of a `long` or
+    /// `double` invalidates it (JVMS 2.6.1).
+    fn store_local(&mut self, index: u16) {
+        let index = index as usize;
+        let value = self.pop();
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = Value::Top;
+        }
+        self.locals[index] = value;
+        if value.is_wide() {
+            self.locals[index + 1] = Value::Top;
+        }
+    }
+}
+
+impl Vm {
+    /// Pops an object reference, throwing `NullPointerException` for `null`.
+    fn pop_non_null(&mut self, registers: &mut Registers) -> Result<ObjectRef, Unwind> {
+        match registers.pop_reference() {
+            Some(object) => Ok(object),
+            None => Err(self.throw_null_pointer()),
+        }
+    }
+
+    /// Pops the index and array reference of an array access instruction,
+    /// checking both.
+    fn pop_array_index(&mut self, registers: &mut Registers) -> Result<(ObjectRef, usize), Unwind> {
+        let index = registers.pop_int();
+        let array = self.pop_non_null(registers)?;
+        let length = self.heap.get(array).array().map_or(0, ArrayData::len);
+        if index < 0 || index as usize >= length {
+            let message = format!("Index {} out of bounds for length {}", index, length);
+            return Err(self.throw_new("java/lang/ArrayIndexOutOfBoundsException", Some(message)));
+        }
+
+        Ok((array, index as usize))
+    }
+
+    fn array_load(&mut self, registers: &mut Registers) -> Result<(), Unwind> {
+        let (array, index) = self.pop_array_index(registers)?;
+        let value = self
+            .heap
+            .get(array)
+            .array()
+            .and_then(|array| array.get(index))
+            .expect("Array element checked to be in bounds");
+        registers.push(value);
+        Ok(())
+    }
+
+    fn array_store(&mut self, registers: &mut Registers) -> Result<(), Unwind> {
+        let value = registers.pop();
+        let (array, index) = self.pop_array_index(registers)?;
+        if let (Value::Reference(Some(element)), ClassKind::Array(component)) =
+            (value, &self.class(self.class_of(array)).kind)
+        {
+            let array_class = self.class(self.class_of(array));
+            let assignable = self
+                .array_component(array_class, component)
+                .is_some_and(|component| self.is_instance(element, component));
+            if !assignable {
+                let message = self.class(self.class_of(element)).java_name();
+                return Err(self.throw_new("java/lang/ArrayStoreException", Some(message)));
+            }
+        }
+
+        if let Some(array) = self.heap.get_mut(array).array_mut() {
+            array.set(index, value);
+        }
+        Ok(())
+    }
+    /// Creates an array of the given array class, throwing
+    /// `NegativeArraySizeException` for negative lengths.
+    pub(crate) fn create_array(
+        &mut self,
+        class: ClassId,
+        length: i32,
+    ) -> Result<ObjectRef, Unwind> {
+        if length < 0 {
+            return Err(self.throw_new(
+                "java/lang/NegativeArraySizeException",
+                Some(length.to_string()),
+            ));
+        }
+        let data = match &self.class(class).kind {
+            ClassKind::Array(component) => {
+                self.check_heap(ArrayData::size_of(component, length as usize))?;
+                ArrayData::new(component, length as usize)
+            }
+            _ => {
+                return Err(Unwind::Error(VmError::Internal(format!(
+                    "{} is not an array class",
+                    self.class(class).name
+                ))))
+            }
+        };
+
+        Ok(self.allocate(HeapObject {
+            class,
+            data: ObjectData::Array(data),
+            native: NativeData::None,
+            identity_hash: None,
+        }))
+    }
+
+    /// Creates the nested arrays of `multianewarray`.
+    fn new_multi_array(&mut self, class: ClassId, lengths: &[i32]) -> Result<ObjectRef, Unwind> {
+        let array = self.create_array(class, lengths[0])?;
+        if lengths.len() > 1 {
+            let component = match &self.class(class).kind {
+                ClassKind::Array(component) => component.to_string(),
+                _ => unreachable!(),
+            };
+            let loader = self.class(class).defining_loader;
+            let component = self.load_class(loader, &component)?;
+            for index in 0..lengths[0] as usize {
+                let element = self.new_multi_array(component, &lengths[1..])?;
+                if let Some(array) = self.heap.get_mut(array).array_mut() {
+                    array.set(index, Value::Reference(Some(element)));
+                }
+            }
+        }
+
+        Ok(array)
+    }
+
+    fn write_thread_dump(&mut self) -> Result<(), Unwind> {
+        let dump = self.thread_dump();
+        self.stdout
+            .write_all(dump.as_bytes())
+            .and_then(|_| self.stdout.flush())
+            .map_err(|error| Unwind::Error(VmError::Io(error)))
+    }
+
+    fn trace_instruction(
+        &mut self,
+        method: &RuntimeMethod,
+        code: &MethodCode,
+        registers: &Registers,
+    ) -> Result<(), Unwind> {
+        let class = &self.classes[method.class.index()].name;
+        let pc = code.instruction_pcs[registers.ip] as usize;
+        let mnemonic = mnemonic(code.code[pc]);
+        let instruction = match code.instructions[registers.ip] {
+            Instruction::Switch(index) => format!(
+                "{} {}",
+                mnemonic,
+                code.switches[index as usize].display(&code.instruction_pcs)
+            ),
+            _ => mnemonic.to_string(),
+        };
+        if let Some(trace) = &mut self.trace {
+            trace
+                .log(
+                    class,
+                    &method.name,
+                    &method.descriptor,
+                    pc,
+                    &instruction,
+                    &registers.stack,
+                )
+                .map_err(|error| Unwind::Error(VmError::Io(error)))?;
+        }
+        Ok(())
+    }
+
+    /// Records the stack of every thread as a sample if one is due, the
+    /// current thread being about to execute the opcode, if it is in
+    /// bytecode.
+    pub(crate) fn take_requested_sample(&mut self, opcode: Option<u8>) {
+        if !self
+            .sampler
+            .as_ref()
+            .is_some_and(SamplingProfiler::take_request)
+        {
+            return;
+        }
+
+        let current = self.current_thread_id();
+        let mut samples = Vec::new();
+        for thread in self.threads() {
+            let stack = match self.java_thread(thread) {
+                Some(stack) => stack,
+                None => continue,
+            };
+            let native = |method: &RuntimeMethod| {
+                let class = self.class(method.class).java_name();
+                format!("{}.{}[native]", class, method.name)
+            };
+            // The natives interleave with the frames they call back into
+            let mut natives = stack.natives.iter().peekable();
+            let mut frames = Vec::new();
+            for (depth, frame) in stack.frames.iter().enumerate() {
+                while let Some((_, method)) = natives.next_if(|(below, _)| *below == depth) {
+                    frames.push(native(method));
+                }
+                let class = self.class(frame.class).java_name();
+                frames.push(format!("{}.{}", class, frame.method.name));
+            }
+            frames.extend(natives.map(|(_, method)| native(method)));
+            if let Some(opcode) = opcode.filter(|_| thread == current) {
+                if self
+                    .sampler
+                    .as_ref()
+                    .is_some_and(SamplingProfiler::includes_opcodes)
+                {
+                    frames.push(format!("[{}]", mnemonic(opcode)));
+                }
+            }
+            if !frames.is_empty() {
+                samples.push(frames);
+            }
+        }
+        if let Some(sampler) = &mut self.sampler {
+            for frames in samples {
+                sampler.record(frames);
+            }
+        }
+    }
+
+    /// Makes the interpreter poll before the next instruction.
+    pub(crate) fn request_poll(&mut self) {
+        self.next_poll = 0;
+    }
+
+    /// Polls about to execute the current instruction, with the registers
+    /// moved back into the frame for the collector and thread dumps.
+    fn poll(&mut self, code: &MethodCode, registers: &mut Registers) -> Result<(), Unwind> {
+        let opcode = code.code[code.instruction_pcs[registers.ip] as usize];
+        registers.swap(self.frame());
+        #[cfg(feature = "jit")]
+        self.count_interpreted_poll();
+        let polled = self.poll_at(opcode);
+        registers.swap(self.frame());
+        polled
+    }
+
+    /// Checks the limits of the guest, collects garbage if due and takes the
+    /// requested sample or thread dump, about to execute the opcode. Polls
+    /// run every few instructions, a prime number of them so that samples
+    /// do not keep landing on the same instruction of a loop, or when
+    /// requested.
+    pub(crate) fn poll_at(&mut self, opcode: u8) -> Result<(), Unwind> {
+        const POLL_INTERVAL: u64 = 1021;
+
+        self.check_limits()?;
+        self.safepoint()?;
+        self.take_requested_sample(Some(opcode));
+        if self
+            .thread_dump
+            .as_ref()
+            .is_some_and(|flag| flag.swap(false, Ordering::Relaxed))
+        {
+            self.write_thread_dump()?;
+        }
+
+        let next_poll = self.executed_instructions + POLL_INTERVAL;
+        self.next_poll = match self.limits.instructions() {
+            Some(max) => next_poll.min(max.saturating_add(1)),
+            None => next_poll,
+        };
+        Ok(())
+    }
+
+    /// Executes instructions of the current frame until it returns or invokes
+    /// another method.
+    fn execute(&mut self) -> Result<Exit, Unwind> {
+        #[cfg(feature = "jit")]
+        if let Some(value) = self.execute_compiled()? {
+            return Ok(Exit::Return(value));
+        }
+
+        let method = self.frame().method.clone();
+        let mut registers = Registers::default();
+        registers.swap(self.frame());
+        let exit = self.interpret(&method, &mut registers);
+        registers.swap(self.frame());
+        exit
+    }
+
+    /// Runs the decoded instructions of the method on the registers of its
+    /// frame. Instructions only moving values between the registers are
+    /// executed here, the others by [Vm::interpret_in_runtime].
+    fn interpret(
+        &mut self,
+        method: &RuntimeMethod,
+        registers: &mut Registers,
+    ) -> Result<Exit, Unwind> {
+        let code = method.code.as_ref().expect("Executed method has code");
+        let traced = match &self.trace {
+            Some(trace) => trace.traces(&self.class(method.class).name, &method.name),
+            None => false,
+        };
+        let coverage = self.coverage.as_mut().map(|coverage| coverage.slot(method));
+
+        loop {
+            let instruction = code.instructions[registers.ip];
+            self.executed_instructions += 1;
+            if self.executed_instructions >= self.next_poll {
+                self.poll(code, registers)?;
+            }
+            if traced {
+                self.trace_instruction(method, code, registers)?;
+            }
+            if let (Some(slot), Some(coverage)) = (coverage, &mut self.coverage) {
+                coverage.hit(slot, registers.ip);
+            }
+
+            match instruction {
+                Instruction::Nop => {}
+                Instruction::AConstNull => registers.push(Value::NULL),
+                Instruction::IConst(value) => registers.push(Value::Int(value)),
+                Instruction::LConst(value) => registers.push(Value::Long(value)),
+                Instruction::FConst(value) => registers.push(Value::Float(value)),
+                Instruction::DConst(value) => registers.push(Value::Double(value)),
+                Instruction::Load(index) => registers.load_local(index),
+                Instruction::Store(index) => registers.store_local(index),
+                Instruction::Pop => {
+                    registers.pop_category1();
+                }
+                Instruction::Pop2 => {
+                    registers.pop_pair();
+                }
+                Instruction::Dup => registers.dup(),
+                Instruction::DupX1 => registers.dup_x1(),
+                Instruction::DupX2 => registers.dup_x2(),
+                Instruction::Dup2 => registers.dup2(),
+                Instruction::Dup2X1 => registers.dup2_x1(),
+                Instruction::Dup2X2 => registers.dup2_x2(),
+                Instruction::Swap => registers.swap_top(),
+                Instruction::IAdd => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a.wrapping_add(b)));
+                }
+                Instruction::LAdd => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a.wrapping_add(b)));
+                }
+                Instruction::FAdd => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    registers.push(Value::Float(a + b));
+                }
+                Instruction::DAdd => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    registers.push(Value::Double(a + b));
+                }
+                Instruction::ISub => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a.wrapping_sub(b)));
+                }
+                Instruction::LSub => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a.wrapping_sub(b)));
+                }
+                Instruction::FSub => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    registers.push(Value::Float(a - b));
+                }
+                Instruction::DSub => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    registers.push(Value::Double(a - b));
+                }
+                Instruction::IMul => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a.wrapping_mul(b)));
+                }
+                Instruction::LMul => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a.wrapping_mul(b)));
+                }
+                Instruction::FMul => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    registers.push(Value::Float(a * b));
+                }
+                Instruction::DMul => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    registers.push(Value::Double(a * b));
+                }
+                Instruction::IDiv => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    if b == 0 {
+                        return Err(self.throw_division_by_zero(registers));
+                    }
+                    registers.push(Value::Int(a.wrapping_div(b)));
+                }
+                Instruction::LDiv => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    if b == 0 {
+                        return Err(self.throw_division_by_zero(registers));
+                    }
+                    registers.push(Value::Long(a.wrapping_div(b)));
+                }
+                Instruction::FDiv => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    registers.push(Value::Float(a / b));
+                }
+                Instruction::DDiv => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    registers.push(Value::Double(a / b));
+                }
+                Instruction::IRem => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    if b == 0 {
+                        return Err(self.throw_division_by_zero(registers));
+                    }
+                    registers.push(Value::Int(a.wrapping_rem(b)));
+                }
+                Instruction::LRem => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    if b == 0 {
+                        return Err(self.throw_division_by_zero(registers));
+                    }
+                    registers.push(Value::Long(a.wrapping_rem(b)));
+                }
+                // Rust's `%` truncates like Java's, unlike the IEEE 754
+                // remainder rounding to the nearest
+                Instruction::FRem => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    registers.push(Value::Float(a % b));
+                }
+                Instruction::DRem => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    registers.push(Value::Double(a % b));
+                }
+                Instruction::INeg => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Int(value.wrapping_neg()));
+                }
+                Instruction::LNeg => {
+                    let value = registers.pop_long();
+                    registers.push(Value::Long(value.wrapping_neg()));
+                }
+                Instruction::FNeg => {
+                    let value = registers.pop_float();
+                    registers.push(Value::Float(-value));
+                }
+                Instruction::DNeg => {
+                    let value = registers.pop_double();
+                    registers.push(Value::Double(-value));
+                }
+                Instruction::IShl => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a.wrapping_shl(b as u32)));
+                }
+                Instruction::LShl => {
+                    let (b, a) = (registers.pop_int(), registers.pop_long());
+                    registers.push(Value::Long(a.wrapping_shl(b as u32)));
+                }
+                Instruction::IShr => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a.wrapping_shr(b as u32)));
+                }
+                Instruction::LShr => {
+                    let (b, a) = (registers.pop_int(), registers.pop_long());
+                    registers.push(Value::Long(a.wrapping_shr(b as u32)));
+                }
+                Instruction::IUShr => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int((a as u32).wrapping_shr(b as u32) as i32));
+                }
+                Instruction::LUShr => {
+                    let (b, a) = (registers.pop_int(), registers.pop_long());
+                    registers.push(Value::Long((a as u64).wrapping_shr(b as u32) as i64));
+                }
+                Instruction::IAnd => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a & b));
+                }
+                Instruction::LAnd => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a & b));
+                }
+                Instruction::IOr => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a | b));
+                }
+                Instruction::LOr => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a | b));
+                }
+                Instruction::IXor => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    registers.push(Value::Int(a ^ b));
+                }
+                Instruction::LXor => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Long(a ^ b));
+                }
+                Instruction::IInc(index, increment) => {
+                    let local = &mut registers.locals[index as usize];
+                    if let Value::Int(value) = *local {
+                        *local = Value::Int(value.wrapping_add(increment));
+                    }
+                }
+                Instruction::I2L => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Long(value as i64));
+                }
+                Instruction::I2F => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Float(value as f32));
+                }
+                Instruction::I2D => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Double(value as f64));
+                }
+                Instruction::L2I => {
+                    let value = registers.pop_long();
+                    registers.push(Value::Int(value as i32));
+                }
+                Instruction::L2F => {
+                    let value = registers.pop_long();
+                    registers.push(Value::Float(value as f32));
+                }
+                Instruction::L2D => {
+                    let value = registers.pop_long();
+                    registers.push(Value::Double(value as f64));
+                }
+                // Rust's casts saturate and map NaN to 0 like Java's
+                Instruction::F2I => {
+                    let value = registers.pop_float();
+                    registers.push(Value::Int(value as i32));
+                }
+                Instruction::F2L => {
+                    let value = registers.pop_float();
+                    registers.push(Value::Long(value as i64));
+                }
+                Instruction::F2D => {
+                    let value = registers.pop_float();
+                    registers.push(Value::Double(value as f64));
+                }
+                Instruction::D2I => {
+                    let value = registers.pop_double();
+                    registers.push(Value::Int(value as i32));
+                }
+                Instruction::D2L => {
+                    let value = registers.pop_double();
+                    registers.push(Value::Long(value as i64));
+                }
+                Instruction::D2F => {
+                    let value = registers.pop_double();
+                    registers.push(Value::Float(value as f32));
+                }
+                Instruction::I2B => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Int(value as i8 as i32));
+                }
+                Instruction::I2C => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Int(value as u16 as i32));
+                }
+                Instruction::I2S => {
+                    let value = registers.pop_int();
+                    registers.push(Value::Int(value as i16 as i32));
+                }
+                Instruction::LCmp => {
+                    let (b, a) = (registers.pop_long(), registers.pop_long());
+                    registers.push(Value::Int(compare(a, b, 0)));
+                }
+                Instruction::FCmpL | Instruction::FCmpG => {
+                    let (b, a) = (registers.pop_float(), registers.pop_float());
+                    let nan_result = if instruction == Instruction::FCmpL {
+                        -1
+                    } else {
+                        1
+                    };
+                    registers.push(Value::Int(compare(a, b, nan_result)));
+                }
+                Instruction::DCmpL | Instruction::DCmpG => {
+                    let (b, a) = (registers.pop_double(), registers.pop_double());
+                    let nan_result = if instruction == Instruction::DCmpL {
+                        -1
+                    } else {
+                        1
+                    };
+                    registers.push(Value::Int(compare(a, b, nan_result)));
+                }
+                Instruction::If(condition, target) => {
+                    if condition.holds(registers.pop_int(), 0) {
+                        registers.ip = target as usize;
+                        continue;
+                    }
+                }
+                Instruction::IfICmp(condition, target) => {
+                    let (b, a) = (registers.pop_int(), registers.pop_int());
+                    if condition.holds(a, b) {
+                        registers.ip = target as usize;
+                        continue;
+                    }
+                }
+                Instruction::IfACmpEq(target) | Instruction::IfACmpNe(target) => {
+                    let (b, a) = (registers.pop_reference(), registers.pop_reference());
+                    if (a == b) == matches!(instruction, Instruction::IfACmpEq(_)) {
+                        registers.ip = target as usize;
+                        continue;
+                    }
+                }
+                Instruction::IfNull(target) | Instruction::IfNonNull(target) => {
+                    let value = registers.pop_reference();
+                    if value.is_none() == matches!(instruction, Instruction::IfNull(_)) {
+                        registers.ip = target as usize;
+                        continue;
+                    }
+                }
+                Instruction::Goto(target) => {
+                    registers.ip = target as usize;
+                    continue;
+                }
+                Instruction::Jsr(target) => {
+                    registers.push(Value::ReturnAddress(registers.ip as u32 + 1));
+                    registers.ip = target as usize;
+                    continue;
+                }
+                Instruction::Ret(index) => {
+                    registers.ip = match registers.locals[index as usize] {
+                        Value::ReturnAddress(ip) => ip as usize,
+                        value => panic!(
+                            "Expected a return address in local {}, got {:?}",
+                            index, value
+                        ),
+                    };
+                    continue;
+                }
+                Instruction::Switch(index) => {
+                    let key = registers.pop_int();
+                    registers.ip = code.switches[index as usize].target(key) as usize;
+                    continue;
+                }
+                Instruction::ReturnValue => return Ok(Exit::Return(Some(registers.pop()))),
+                Instruction::Return => return Ok(Exit::Return(None)),
+                instruction => {
+                    self.frame().ip = registers.ip;
+                    if let Some(exit) = self.interpret_in_runtime(method, instruction, registers)? {
+                        return Ok(exit);
+                    }
+                }
+            }
+
+            registers.ip += 1;
+        }
+    }
+
+    /// Executes an instruction which resolves, allocates, invokes or throws,
+    /// with the instruction index stored into the frame. Returns the exit of
+    /// the frame for invocations.
+    fn interpret_in_runtime(
+        &mut self,
+        method: &RuntimeMethod,
+        instruction: Instruction,
+        registers: &mut Registers,
+    ) -> Result<Option<Exit>, Unwind> {
+        let class = method.class;
+        match instruction {
+            Instruction::Ldc(index) => {
+                let value = self.load_constant(class, index)?;
+                registers.push(value);
+            }
+            Instruction::ArrayLoad => self.array_load(registers)?,
+            Instruction::ArrayStore => self.array_store(registers)?,
+            Instruction::GetStatic(index) => {
+                let field = self.resolve_field_ref(class, index)?;
+                self.check_static(field.is_static(), true, "field", field.class, &field.name)?;
+                self.initialize_class(field.class)?;
+                let value = self.class(field.class).static_values[field.slot];
+                registers.push(value);
+            }
+            Instruction::PutStatic(index) => {
+                let field = self.resolve_field_ref(class, index)?;
+                self.check_static(field.is_static(), true, "field", field.class, &field.name)?;
+                self.initialize_class(field.class)?;
+                let value = narrow(registers.pop(), &field.field_type);
+                self.class_mut(field.class).static_values[field.slot] = value;
+            }
+            Instruction::GetField(index) => {
+                let field = self.resolve_field_ref(class, index)?;
+                self.check_static(field.is_static(), false, "field", field.class, &field.name)?;
+                let object = self.pop_non_null(registers)?;
+                let value = self.heap.get(object).fields()[field.slot];
+                registers.push(value);
+            }
+            Instruction::PutField(index) => {
+                let field = self.resolve_field_ref(class, index)?;
+                self.check_static(field.is_static(), false, "field", field.class, &field.name)?;
+                let value = narrow(registers.pop(), &field.field_type);
+                let object = self.pop_non_null(registers)?;
+                if let ObjectData::Fields(fields) = &mut self.heap.get_mut(object).data {
+                    fields[field.slot] = value;
+                }
+            }
+            Instruction::InvokeVirtual(index, cache)
+            | Instruction::InvokeInterface(index, cache) => {
+                let resolved = self.resolve_cached(class, index, cache)?;
+                let (owner, name) = (resolved.class, &resolved.name);
+                self.check_static(resolved.is_static(), false, "method", owner, name)?;
+                let arguments = registers.pop_arguments(&resolved);
+                let receiver = match arguments[0] {
+                    Value::Reference(Some(receiver)) => receiver,
+                    _ => return Err(self.throw_null_pointer()),
+                };
+                let method = self.select_cached(receiver, resolved, cache)?;
+                return Ok(Some(Exit::Invoke(method, arguments)));
+            }
+            Instruction::InvokeSpecial(index) | Instruction::InvokeStatic(index) => {
+                let resolved = self.resolve_method_ref(class, index)?;
+                let expected = matches!(instruction, Instruction::InvokeStatic(_));
+                let (owner, name) = (resolved.class, &resolved.name);
+                self.check_static(resolved.is_static(), expected, "method", owner, name)?;
+                let arguments = registers.pop_arguments(&resolved);
+                let method = match instruction {
+                    Instruction::InvokeStatic(_) => {
+                        self.initialize_class(resolved.class)?;
+                        resolved
+                    }
+                    _ => self.select_special(class, resolved),
+                };
+                return Ok(Some(Exit::Invoke(method, arguments)));
+            }
+            Instruction::New(index) => {
+                let target = self.resolve_class_ref(class, index)?;
+                let target_class = self.class(target);
+                if target_class.is_interface()
+                    || target_class
+                        .access_flags
+                        .contains(ClassAccessFlags::ABSTRACT)
+                {
+                    let message = target_class.java_name();
+                    return Err(self.throw_new("java/lang/InstantiationError", Some(message)));
+                }
+                self.initialize_class(target)?;
+                let object = self.instantiate(target)?;
+                registers.push(Value::Reference(Some(object)));
+            }
+            Instruction::NewArray(atype) => {
+                let name = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    atype => {
+                        return Err(Unwind::Error(VmError::Internal(format!(
+                            "Invalid newarray type {}",
+                            atype
+                        ))))
+                    }
+                };
+                let array_class = self.load_class(LoaderId::BOOTSTRAP, name)?;
+                let length = registers.pop_int();
+                let array = self.create_array(array_class, length)?;
+                registers.push(Value::Reference(Some(array)));
+            }
+            Instruction::ANewArray(index) => {
+                let component = self.resolve_class_ref(class, index)?;
+                let component = self.class(component);
+                let name = if component.is_array() {
+                    format!("[{}", component.name)
+                } else {
+                    format!("[L{};", component.name)
+                };
+                let array_class = self.load_class(self.class(class).defining_loader, &name)?;
+                let length = registers.pop_int();
+                let array = self.create_array(array_class, length)?;
+                registers.push(Value::Reference(Some(array)));
+            }
+            Instruction::ArrayLength => {
+                let array = self.pop_non_null(registers)?;
+                let length = self.heap.get(array).array().map_or(0, ArrayData::len);
+                registers.push(Value::Int(length as i32));
+            }
+            Instruction::AThrow => {
+                let exception = self.pop_non_null(registers)?;
+                self.fire_exception_thrown(exception);
+                return Err(Unwind::Throw(exception));
+            }
+            Instruction::CheckCast(index) => {
+                let target = self.resolve_class_ref(class, index)?;
+                if let Some(&Value::Reference(Some(object))) = registers.stack.last() {
+                    if !self.is_instance(object, target) {
+                        let message = format!(
+                            "class {} cannot be cast to class {}",
+                            self.class(self.class_of(object)).java_name(),
+                            self.class(target).java_name()
+                        );
+                        return Err(self.throw_new("java/lang/ClassCastException", Some(message)));
+                    }
+                }
+            }
+            Instruction::InstanceOf(index) => {
+                let target = self.resolve_class_ref(class, index)?;
+                let result = match registers.pop_reference() {
+                    Some(object) => self.is_instance(object, target),
+                    None => false,
+                };
+                registers.push(Value::Int(result as i32));
+            }
+            // a single thread owns every monitor, entering only records it
+            // for thread dumps
+            Instruction::MonitorEnter => {
+                let object = self.pop_non_null(registers)?;
+                self.frame().monitors.push(object);
+            }
+            Instruction::MonitorExit => {
+                let object = self.pop_non_null(registers)?;
+                let monitors = &mut self.frame().monitors;
+                if let Some(index) = monitors.iter().rposition(|&entered| entered == object) {
+                    monitors.remove(index);
+                }
+            }
+            Instruction::MultiANewArray(index, dimensions) => {
+                let array_class = self.resolve_class_ref(class, index)?;
+                let stack = &mut registers.stack;
+                let lengths: Vec<i32> = stack
+                    .split_off(stack.len() - dimensions as usize)
+                    .into_iter()
+                    .map(|length| match length {
+                        Value::Int(length) => length,
+                        value => panic!("Expected int array length, got {:?}", value),
+                    })
+                    .collect();
+                if let Some(length) = lengths.iter().find(|length| **length < 0) {
+                    return Err(self.throw_new(
+                        "java/lang/NegativeArraySizeException",
+                        Some(length.to_string()),
+                    ));
+                }
+                let array = self.new_multi_array(array_class, &lengths)?;
+                registers.push(Value::Reference(Some(array)));
+            }
+            // invokedynamic, of the call sites the VM links itself
+            Instruction::Unsupported(0xba) => {
+                let code = method.code.as_ref().expect("Executed method has code");
+                let pc = code.instruction_pcs[registers.ip] as usize;
+                let index = u16::from_be_bytes([code.code[pc + 1], code.code[pc + 2]]);
+                let site = self.resolve_call_site(class, index)?;
+                let arguments = registers.pop_values(site.argument_count());
+                let result = self.invoke_call_site(&site, &arguments)?;
+                registers.push(result);
+            }
+            _ => {
+                let code = method.code.as_ref().expect("Executed method has code");
+                let pc = code.instruction_pcs[registers.ip] as usize;
+                return Err(Unwind::Error(VmError::Internal(format!(
+                    "Unsupported opcode 0x{:02x} at {}.{}{} pc {}",
+                    code.code[pc],
+                    self.class(class).name,
+                    method.name,
+                    method.descriptor,
+                    pc
+                ))));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn throw_division_by_zero(&mut self, registers: &Registers) -> Unwind {
+        self.frame().ip = registers.ip;
+        self.throw_new(
+            "java/lang/ArithmeticException",
+            Some("/ by zero".to_string()),
+        )
+    }
+
+    /// Selects the method invoked by `invokespecial` from the current class:
+    /// superclass methods are looked up from the direct superclass (JVMS
+    /// 6.5 `invokespecial`).
+    fn select_special(&self, current: ClassId, resolved: Arc<RuntimeMethod>) -> Arc<RuntimeMethod> {
+        let resolved_class = self.class(resolved.class);
+        if resolved.name == "<init>"
+            || resolved.is_private()
+            || resolved.class == current
+            || resolved_class.is_interface()
+            || !self.is_assignable(current, resolved.class)
+        {
+            return resolved;
+        }
+
+        self.class(current)
+            .super_class
+            .and_then(|super_class| {
+                self.find_virtual(super_class, &resolved.name, &resolved.descriptor)
+            })
+            .unwrap_or(resolved)
+    }
+
+    /// Selects the implementation invoked by `invokevirtual` and
+    /// `invokeinterface` on the receiver.
+    pub(crate) fn select_virtual(
+        &mut self,
+        receiver: ObjectRef,
+        resolved: Arc<RuntimeMethod>,
+    ) -> Result<Arc<RuntimeMethod>, Unwind> {
+        if resolved.is_private() {
+            return Ok(resolved);
+        }
+
+        let class = self.class_of(receiver);
+        match self.find_virtual(class, &resolved.name, &resolved.descriptor) {
+            Some(method) => Ok(method),
+            None => {
+                let message = format!(
+                    "{}.{}{}",
+                    self.class(class).java_name(),
+                    resolved.name,
+                    resolved.descriptor
+                );
+                Err(self.throw_new("java/lang/AbstractMethodError", Some(message)))
+            }
+        }
+    }
+}
+
+// =============================================================================
+// REGISTERS TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod registers_tests {
+    use super::Registers;
+    use crate::vm::value::Value::{self, Double, Int, Long, Top};
+
+    fn stack(values: &[Value]) -> Registers {
+        Registers {
+            stack: values.to_vec(),
+            ..Registers::default()
+        }
+    }
+
+    fn after(values: &[Value], instruction: fn(&mut Registers)) -> Vec<Value> {
+        let mut registers = stack(values);
+        instruction(&mut registers);
+        registers.stack
+    }
+
+    #[test]
+    fn test_pop() {
+        assert_eq!(
+            after(&[Int(1), Int(2)], |r| {
+                r.pop_category1();
+            }),
+            [Int(1)]
+        );
+        assert_eq!(
+            after(&[Int(1), Int(2), Int(3)], |r| {
+                r.pop_pair();
+            }),
+            [Int(1)]
+        );
+        assert_eq!(
+            after(&[Int(1), Long(2)], |r| {
+                r.pop_pair();
+            }),
+            [Int(1)]
+        );
+    }
+
+    #[test]
+    fn test_dup() {
+        assert_eq!(after(&[Int(1)], Registers::dup), [Int(1), Int(1)]);
+        assert_eq!(
+            after(&[Int(1), Int(2)], Registers::dup_x1),
+            [Int(2), Int(1), Int(2)]
+        );
+    }
+
+    #[test]
+    fn test_dup_x2() {
+        // Form 1: three category 1 values
+        assert_eq!(
+            after(&[Int(1), Int(2), Int(3)], Registers::dup_x2),
+            [Int(3), Int(1), Int(2), Int(3)]
+        );
+        // Form 2: a category 1 value over a category 2 value
+        assert_eq!(
+            after(&[Long(1), Int(2)], Registers::dup_x2),
+            [Int(2), Long(1), Int(2)]
+        );
+    }
+
+    #[test]
+    fn test_dup2() {
+        assert_eq!(
+            after(&[Int(1), Int(2)], Registers::dup2),
+            [Int(1), Int(2), Int(1), Int(2)]
+        );
+        assert_eq!(
+            after(&[Double(1.0)], Registers::dup2),
+            [Double(1.0), Double(1.0)]
+        );
+    }
+
+    #[test]
+    fn test_dup2_x1() {
+        assert_eq!(
+            after(&[Int(1), Int(2), Int(3)], Registers::dup2_x1),
+            [Int(2), Int(3), Int(1), Int(2), Int(3)]
+        );
+        assert_eq!(
+            after(&[Int(1), Long(2)], Registers::dup2_x1),
+            [Long(2), Int(1), Long(2)]
+        );
+    }
+
+    #[test]
+    fn test_dup2_x2() {
+        // Form 1: four category 1 values
+        assert_eq!(
+            after(&[Int(1), Int(2), Int(3), Int(4)], Registers::dup2_x2),
+            [Int(3), Int(4), Int(1), Int(2), Int(3), Int(4)]
+        );
+        // Form 2: a category 2 value over two category 1 values
+        assert_eq!(
+            after(&[Int(1), Int(2), Long(3)], Registers::dup2_x2),
+            [Long(3), Int(1), Int(2), Long(3)]
+        );
+        // Form 3: two category 1 values over a category 2 value
+        assert_eq!(
+            after(&[Double(1.0), Int(2), Int(3)], Registers::dup2_x2),
+            [Int(2), Int(3), Double(1.0), Int(2), Int(3)]
+        );
+        // Form 4: two category 2 values
+        assert_eq!(
+            after(&[Long(1), Double(2.0)], Registers::dup2_x2),
+            [Double(2.0), Long(1), Double(2.0)]
+        );
+    }
+
+    #[test]
+    fn test_swap() {
+        assert_eq!(
+            after(&[Int(1), Int(2)], Registers::swap_top),
+            [Int(2), Int(1)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a category 1 value")]
+    fn test_pop_of_half_a_long() {
+        after(&[Long(1)], |r| {
+            r.pop_category1();
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a category 1 value")]
+    fn test_pair_splitting_a_long() {
+        after(&[Long(1), Int(2)], Registers::dup2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a category 1 value")]
+    fn test_swap_of_a_double() {
+        after(&[Int(1), Double(2.0)], Registers::swap_top);
+    }
+
+    #[test]
+    fn test_wide_locals() {
+        let mut registers = Registers {
+            stack: vec![Int(3), Long(1)],
+            locals: vec![Top; 4],
+            ..Registers::default()
+        };
+        registers.store_local(1);
+        assert_eq!(registers.locals, [Top, Long(1), Top, Top]);
+        registers.load_local(1);
+        assert_eq!(registers.stack, [Int(3), Long(1)]);
+
+        // Storing to its second half invalidates the long
+        registers.pop();
+        registers.store_local(2);
+        assert_eq!(registers.locals, [Top, Top, Int(3), Top]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Local 2 holds no value")]
+    fn test_load_of_second_half() {
+        let mut registers = Registers {
+            stack: vec![Double(1.0)],
+            locals: vec![Top; 3],
+            ..Registers::default()
+        };
+        registers.store_local(1);
+        registers.load_local(2);
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::VecDeque;
+
+use crate::class::descriptor::FieldType;
+use crate::class::instruction::Instruction;
+use crate::vm::runtime::{MethodCode, RuntimeMethod};
+
+// =============================================================================
+// KINDS
+// =============================================================================
+
+/// The type of a local variable or operand stack entry, as far as compiled
+/// code is concerned. Compiled code only computes with primitives, leaving
+/// references where the interpreter put them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+    /// The second slot of a wide local, a local not assigned yet, or one
+    /// assigned different kinds on the paths reaching the instruction.
+    Top,
+}
+
+impl Kind {
+    pub(crate) fn of(field_type: &FieldType) -> Kind {
+        match field_type {
+            FieldType::Long => Kind::Long,
+            FieldType::Float => Kind::Float,
+            FieldType::Double => Kind::Double,
+            FieldType::Object(_) | FieldType::Array(_) => Kind::Reference,
+            _ => Kind::Int,
+        }
+    }
+
+    pub(crate) fn is_primitive(self) -> bool {
+        !matches!(self, Kind::Reference | Kind::Top)
+    }
+
+    pub(crate) fn is_wide(self) -> bool {
+        matches!(self, Kind::Long | Kind::Double)
+    }
+}
+
+/// The kinds of the locals and of the operand stack entries before an
+/// instruction. Like [Value](crate::vm::value::Value)s, `long` and `double`
+/// take one stack entry but two locals.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FrameState {
+    pub(crate) locals: Vec<Kind>,
+    pub(crate) stack: Vec<Kind>,
+}
+
+impl FrameState {
+    fn pop(&mut self, kind: Kind) -> Option<()> {
+        match self.stack.pop() {
+            Some(popped) if popped == kind => Some(()),
+            _ => None,
+        }
+    }
+
+    fn pop_category1(&mut self) -> Option<Kind> {
+        match self.stack.pop()? {
+            Kind::Int => Some(Kind::Int),
+            Kind::Float => Some(Kind::Float),
+            _ => None,
+        }
+    }
+
+    /// Pops the operands of a binary operation and pushes its result.
+    fn binary(&mut self, kind: Kind) -> Option<()> {
+        self.pop(kind)?;
+        self.pop(kind)?;
+        self.stack.push(kind);
+        Some(())
+    }
+
+    fn convert(&mut self, from: Kind, to: Kind) -> Option<()> {
+        self.pop(from)?;
+        self.stack.push(to);
+        Some(())
+    }
+
+    /// Merges the state of another path reaching the same instruction,
+    /// returning whether the state changed. Fails if the operand stacks
+    /// differ, which verified code never does.
+    fn merge(&mut self, other: &FrameState) -> Result<bool, ()> {
+        if self.stack != other.stack || self.locals.len() != other.locals.len() {
+            return Err(());
+        }
+        let mut changed = false;
+        for (local, other) in self.locals.iter_mut().zip(&other.locals) {
+            if *local != *other && *local != Kind::Top {
+                *local = Kind::Top;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+// =============================================================================
+// ANALYSIS
+// =============================================================================
+
+/// The frame states of a method's instructions, found by abstract
+/// interpretation from its entry.
+#[derive(Debug)]
+pub(crate) struct Analysis {
+    /// The state before each instruction, `None` for the instructions
+    /// compiled code does not reach.
+    pub(crate) states: Vec<Option<FrameState>>,
+    /// Whether compiled code executes the instruction, or deoptimizes to
+    /// the interpreter before it.
+    pub(crate) supported: Vec<bool>,
+}
+
+impl Analysis {
+    /// Analyzes the instructions of the method, or fails if two paths reach
+    /// an instruction with different operand stacks.
+    pub(crate) fn of(
+        method: &RuntimeMethod,
+        code: &MethodCode,
+        instructions: &[Instruction],
+    ) -> Option<Analysis> {
+        let count = instructions.len();
+        let mut analysis = Analysis {
+            states: vec![None; count],
+            supported: vec![false; count],
+        };
+        if count == 0 {
+            return Some(analysis);
+        }
+
+        let mut locals = Vec::with_capacity(code.max_locals as usize);
+        if !method.is_static() {
+            locals.push(Kind::Reference);
+        }
+        for parameter in &method.parsed_descriptor.parameters {
+            let kind = Kind::of(parameter);
+            locals.push(kind);
+            if kind.is_wide() {
+                locals.push(Kind::Top);
+            }
+        }
+        if locals.len() < code.max_locals as usize {
+            locals.resize(code.max_locals as usize, Kind::Top);
+        }
+        analysis.states[0] = Some(FrameState {
+            locals,
+            stack: Vec::new(),
+        });
+
+        let mut pending = VecDeque::from(vec![0]);
+        while let Some(ip) = pending.pop_front() {
+            let mut state = analysis.states[ip].clone()?;
+            let instruction = instructions[ip];
+            let successors = match step(instruction, ip, &mut state) {
+                Some(successors) => successors,
+                None => {
+                    analysis.supported[ip] = false;
+                    continue;
+                }
+            };
+            analysis.supported[ip] = true;
+
+            for &successor in successors.iter().flatten() {
+                if successor >= count {
+                    analysis.supported[ip] = false;
+                    break;
+                }
+                let changed = match &mut analysis.states[successor] {
+                    Some(existing) => existing.merge(&state).ok()?,
+                    empty => {
+                        *empty = Some(state.clone());
+                        true
+                    }
+                };
+                if changed {
+                    pending.push_back(successor);
+                }
+            }
+        }
+
+        Some(analysis)
+    }
+
+    /// The deepest operand stack of the reached instructions.
+    pub(crate) fn stack_depth(&self) -> usize {
+        self.states
+            .iter()
+            .flatten()
+            .map(|state| state.stack.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Whether the instruction ends a basic block.
+pub(crate) fn is_terminator(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::If(..)
+            | Instruction::IfICmp(..)
+            | Instruction::Goto(_)
+            | Instruction::ReturnValue
+            | Instruction::Return
+    )
+}
+
+/// The branch target of the instruction, if it branches.
+pub(crate) fn branch_target(instruction: Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::If(_, target) | Instruction::IfICmp(_, target) | Instruction::Goto(target) => {
+            Some(target as usize)
+        }
+        _ => None,
+    }
+}
+
+/// Applies the instruction at `ip` to the state, returning its successors,
+/// or `None` if compiled code does not support it.
+fn step(instruction: Instruction, ip: usize, state: &mut FrameState) -> Option<[Option<usize>; 2]> {
+    let next = Some(ip + 1);
+    match instruction {
+        Instruction::Nop => {}
+        Instruction::IConst(_) => state.stack.push(Kind::Int),
+        Instruction::LConst(_) => state.stack.push(Kind::Long),
+        Instruction::FConst(_) => state.stack.push(Kind::Float),
+        Instruction::DConst(_) => state.stack.push(Kind::Double),
+        Instruction::Load(index) => {
+            let kind = *state.locals.get(index as usize)?;
+            if !kind.is_primitive() {
+                return None;
+            }
+            state.stack.push(kind);
+        }
+        Instruction::Store(index) => {
+            let index = index as usize;
+            let kind = *state.stack.last()?;
+            if index + usize::from(kind.is_wide()) >= state.locals.len() {
+                return None;
+            }
+            state.stack.pop();
+            state.locals[index] = kind;
+            if kind.is_wide() {
+                state.locals[index + 1] = Kind::Top;
+            }
+        }
+        Instruction::Pop => {
+            state.pop_category1()?;
+        }
+        Instruction::Pop2 => {
+            if !state.stack.last()?.is_wide() {
+                state.pop_category1()?;
+                state.pop_category1()?;
+            } else {
+                state.stack.pop();
+            }
+        }
+        Instruction::Dup => {
+            let kind = state.pop_category1()?;
+            state.stack.extend([kind, kind]);
+        }
+        Instruction::Dup2 => {
+            let top = *state.stack.last()?;
+            if top.is_wide() {
+                state.stack.push(top);
+            } else {
+                let top = state.pop_category1()?;
+                let below = state.pop_category1()?;
+                state.stack.extend([below, top, below, top]);
+            }
+        }
+        Instruction::Swap => {
+            let top = state.pop_category1()?;
+            let below = state.pop_category1()?;
+            state.stack.extend([top, below]);
+        }
+        Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IRem
+        | Instruction::IShl
+        | Instruction::IShr
+        | Instruction::IUShr
+        | Instruction::IAnd
+        | Instruction::IOr
+        | Instruction::IXor => state.binary(Kind::Int)?,
+        Instruction::LAdd
+        | Instruction::LSub
+        | Instruction::LMul
+        | Instruction::LDiv
+        | Instruction::LRem
+        | Instruction::LAnd
+        | Instruction::LOr
+        | Instruction::LXor => state.binary(Kind::Long)?,
+        Instruction::LShl | Instruction::LShr | Instruction::LUShr => {
+            state.pop(Kind::Int)?;
+            state.convert(Kind::Long, Kind::Long)?;
+        }
+        Instruction::FAdd | Instruction::FSub | Instruction::FMul | Instruction::FDiv => {
+            state.binary(Kind::Float)?
+        }
+        Instruction::DAdd | Instruction::DSub | Instruction::DMul | Instruction::DDiv => {
+            state.binary(Kind::Double)?
+        }
+        Instruction::INeg => state.convert(Kind::Int, Kind::Int)?,
+        Instruction::LNeg => state.convert(Kind::Long, Kind::Long)?,
+        Instruction::FNeg => state.convert(Kind::Float, Kind::Float)?,
+        Instruction::DNeg => state.convert(Kind::Double, Kind::Double)?,
+        Instruction::IInc(index, _) => {
+            if state.locals.get(index as usize) != Some(&Kind::Int) {
+                return None;
+            }
+        }
+        Instruction::I2L => state.convert(Kind::Int, Kind::Long)?,
+        Instruction::I2F => state.convert(Kind::Int, Kind::Float)?,
+        Instruction::I2D => state.convert(Kind::Int, Kind::Double)?,
+        Instruction::L2I => state.convert(Kind::Long, Kind::Int)?,
+        Instruction::L2F => state.convert(Kind::Long, Kind::Float)?,
+        Instruction::L2D => state.convert(Kind::Long, Kind::Double)?,
+        Instruction::F2I => state.convert(Kind::Float, Kind::Int)?,
+        Instruction::F2L => state.convert(Kind::Float, Kind::Long)?,
+        Instruction::F2D => state.convert(Kind::Float, Kind::Double)?,
+        Instruction::D2I => state.convert(Kind::Double, Kind::Int)?,
+        Instruction::D2L => state.convert(Kind::Double, Kind::Long)?,
+        Instruction::D2F => state.convert(Kind::Double, Kind::Float)?,
+        Instruction::I2B | Instruction::I2C | Instruction::I2S => {
+            state.convert(Kind::Int, Kind::Int)?
+        }
+        Instruction::LCmp => {
+            state.pop(Kind::Long)?;
+            state.convert(Kind::Long, Kind::Int)?;
+        }
+        Instruction::FCmpL | Instruction::FCmpG => {
+            state.pop(Kind::Float)?;
+            state.convert(Kind::Float, Kind::Int)?;
+        }
+        Instruction::DCmpL | Instruction::DCmpG => {
+            state.pop(Kind::Double)?;
+            state.convert(Kind::Double, Kind::Int)?;
+        }
+        Instruction::If(_, target) => {
+            state.pop(Kind::Int)?;
+            return Some([next, Some(target as usize)]);
+        }
+        Instruction::IfICmp(_, target) => {
+            state.pop(Kind::Int)?;
+            state.pop(Kind::Int)?;
+            return Some([next, Some(target as usize)]);
+        }
+        Instruction::Goto(target) => return Some([Some(target as usize), None]),
+        Instruction::ReturnValue => {
+            if !state.stack.pop()?.is_primitive() {
+                return None;
+            }
+            return Some([None, None]);
+        }
+        Instruction::Return => return Some([None, None]),
+        _ => return None,
+    }
+    Some([next, None])
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+    use crate::class::descriptor::MethodDescriptor;
+    use crate::class::instruction::{decode, Condition};
+    use crate::class::MethodAccessFlags;
+    use crate::vm::runtime::ClassId;
+    use crate::vm::symbol::SymbolTable;
+
+    fn method(descriptor: &str, bytecode: &[u8]) -> RuntimeMethod {
+        let decoded = decode(bytecode);
+        let mut symbols = SymbolTable::default();
+        RuntimeMethod {
+            class: ClassId(0),
+            name: symbols.intern("test"),
+            descriptor: symbols.intern(descriptor),
+            parsed_descriptor: MethodDescriptor::parse(descriptor).unwrap(),
+            access_flags: MethodAccessFlags::STATIC,
+            code: Some(MethodCode {
+                max_stack: 4,
+                max_locals: 4,
+                code: bytecode.to_vec(),
+                instructions: decoded.instructions,
+                instruction_pcs: decoded.pcs,
+                switches: decoded.switches,
+                exception_handlers: Vec::new(),
+                line_numbers: Vec::new(),
+            }),
+            native: None,
+            permission: None,
+        }
+    }
+
+    #[test]
+    fn test_analysis() {
+        // long sum(int n, Object o) {
+        //     long total = 0;
+        //     while (n > 0) total += n--;
+        //     return total + o.hashCode();
+        // }
+        let method = method(
+            "(ILjava/lang/Object;)J",
+            &[
+                0x09, // lconst_0
+                0x41, // lstore_2
+                0x1a, // iload_0
+                0x9e, 0x00, 0x0e, // ifle +14
+                0x20, // lload_2
+                0x1a, // iload_0
+                0x84, 0x00, 0xff, // iinc 0 -1
+                0x85, // i2l
+                0x61, // ladd
+                0x41, // lstore_2
+                0xa7, 0xff, 0xf4, // goto -12
+                0x20, // lload_2
+                0x2b, // aload_1
+                0xb6, 0x00, 0x01, // invokevirtual #1
+                0x85, // i2l
+                0x61, // ladd
+                0xad, // lreturn
+            ],
+        );
+        let code = method.code.as_ref().unwrap();
+        assert_eq!(code.instructions[3], Instruction::If(Condition::Le, 11));
+
+        let analysis = Analysis::of(&method, code, &code.instructions).unwrap();
+        let loop_header = analysis.states[2].as_ref().unwrap();
+        assert_eq!(
+            loop_header.locals,
+            vec![Kind::Int, Kind::Reference, Kind::Long, Kind::Top]
+        );
+        assert!(loop_header.stack.is_empty());
+
+        // Loading the reference deoptimizes, so the rest is never compiled.
+        assert!(analysis.supported[..12].iter().all(|&supported| supported));
+        assert!(!analysis.supported[12]);
+        assert_eq!(
+            analysis.states[12].as_ref().map(|state| &state.stack),
+            Some(&vec![Kind::Long])
+        );
+        assert!(analysis.states[13].is_none());
+    }
+}
@@ -0,0 +1,673 @@
+use std::mem;
+
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::{types, AbiParam, Block, InstBuilder, MemFlags, SigRef, Type, Value};
+use cranelift_frontend::{FuncInstBuilder, FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::JITModule;
+use cranelift_module::{Module, ModuleError};
+
+use crate::class::instruction::{Condition, Instruction};
+use crate::vm::jit::analysis::{branch_target, is_terminator, Analysis, Kind};
+use crate::vm::jit::JitContext;
+
+/// The native code of a method. It returns -1 once the method returned, its
+/// result in the first operand stack slot of the context, or the index of
+/// the instruction to continue at in the interpreter, with the frame state
+/// before it written to the context.
+pub(crate) type Entry = unsafe extern "C" fn(*mut JitContext) -> i64;
+
+/// The status of compiled code which ran its method to completion.
+pub(crate) const RETURNED: i64 = -1;
+
+/// Returned by the poll callback if polling failed, making compiled code
+/// deoptimize.
+pub(crate) const POLL_FAILED: i64 = i64::MIN;
+
+/// The primitive kinds, each local and operand stack slot having a variable
+/// of each.
+const KINDS: [Kind; 4] = [Kind::Int, Kind::Long, Kind::Float, Kind::Double];
+
+/// Compiles the instructions reached by the analysis, returning the entry of
+/// the compiled code and the size of its machine code.
+pub(crate) fn compile(
+    module: &mut JITModule,
+    instructions: &[Instruction],
+    analysis: &Analysis,
+) -> Result<(Entry, usize), Box<ModuleError>> {
+    let pointer = module.target_config().pointer_type();
+    let mut signature = module.make_signature();
+    signature.params.push(AbiParam::new(pointer));
+    signature.returns.push(AbiParam::new(types::I64));
+    let mut poll = module.make_signature();
+    poll.params.extend([
+        AbiParam::new(pointer),
+        AbiParam::new(types::I64),
+        AbiParam::new(types::I64),
+    ]);
+    poll.returns.push(AbiParam::new(types::I64));
+
+    let id = module.declare_anonymous_function(&signature)?;
+    let mut context = module.make_context();
+    context.func.signature = signature;
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+    let poll = builder.import_signature(poll);
+    Translator::new(builder, instructions, analysis, pointer, poll).translate();
+
+    module.define_function(id, &mut context)?;
+    let size = context
+        .compiled_code()
+        .map_or(0, |code| code.code_info().total_size as usize);
+    module.clear_context(&mut context);
+    module.finalize_definitions()?;
+    let entry = module.get_finalized_function(id);
+    // SAFETY: the function was compiled with the signature of `Entry`.
+    Ok((unsafe { mem::transmute::<*const u8, Entry>(entry) }, size))
+}
+
+// =============================================================================
+// TRANSLATION
+// =============================================================================
+
+/// Translates decoded instructions to Cranelift IR, keeping the locals and
+/// operand stack entries in variables, one per slot and kind.
+struct Translator<'a, 'b> {
+    builder: FunctionBuilder<'b>,
+    instructions: &'a [Instruction],
+    analysis: &'a Analysis,
+    pointer: Type,
+    poll: SigRef,
+    /// The block of each instruction starting one.
+    blocks: Vec<Option<Block>>,
+    locals: usize,
+    context: Value,
+    locals_buffer: Value,
+    stack_buffer: Value,
+    /// The instructions left before the next poll is due.
+    fuel: Variable,
+    /// The kinds on the operand stack at the current instruction.
+    stack: Vec<Kind>,
+    /// The end of the instructions whose execution was accounted for on
+    /// entering the current block.
+    accounted: usize,
+    /// Backward branches whose poll checks are still to be emitted, by their
+    /// block and target.
+    back_edges: Vec<(Block, usize)>,
+}
+
+impl<'a, 'b> Translator<'a, 'b> {
+    fn new(
+        builder: FunctionBuilder<'b>,
+        instructions: &'a [Instruction],
+        analysis: &'a Analysis,
+        pointer: Type,
+        poll: SigRef,
+    ) -> Self {
+        let locals = analysis.states[0]
+            .as_ref()
+            .map_or(0, |state| state.locals.len());
+        let depth = analysis.stack_depth();
+        Translator {
+            builder,
+            instructions,
+            analysis,
+            pointer,
+            poll,
+            blocks: Vec::new(),
+            locals,
+            context: Value::new(0),
+            locals_buffer: Value::new(0),
+            stack_buffer: Value::new(0),
+            fuel: Variable::new((locals + depth) * KINDS.len()),
+            stack: Vec::new(),
+            accounted: 0,
+            back_edges: Vec::new(),
+        }
+    }
+
+    fn translate(mut self) {
+        let analysis = self.analysis;
+        let count = self.instructions.len();
+        let mut leaders = vec![false; count];
+        leaders[0] = true;
+        for (ip, &instruction) in self.instructions.iter().enumerate() {
+            if analysis.states[ip].is_none() || !analysis.supported[ip] {
+                continue;
+            }
+            if let Some(target) = branch_target(instruction) {
+                leaders[target] = true;
+            }
+            if matches!(instruction, Instruction::If(..) | Instruction::IfICmp(..)) {
+                leaders[ip + 1] = true;
+            }
+        }
+        self.blocks = leaders
+            .iter()
+            .map(|&leader| {
+                if leader {
+                    Some(self.builder.create_block())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        self.enter();
+
+        let mut open = false;
+        for (ip, &instruction) in self.instructions.iter().enumerate() {
+            let state = match &analysis.states[ip] {
+                Some(state) => state,
+                None => continue,
+            };
+            if let Some(block) = self.blocks[ip] {
+                if open {
+                    self.builder.ins().jump(block, &[]);
+                }
+                self.builder.switch_to_block(block);
+                let length = self.block_length(ip, &leaders);
+                self.accounted = ip + length;
+                let fuel = self.builder.use_var(self.fuel);
+                let fuel = self.builder.ins().iadd_imm(fuel, -(length as i64));
+                self.builder.def_var(self.fuel, fuel);
+            }
+
+            self.stack.clone_from(&state.stack);
+            open = if analysis.supported[ip] {
+                self.instruction(ip, instruction)
+            } else {
+                self.deoptimize(ip, 0);
+                false
+            };
+            for (block, target) in mem::take(&mut self.back_edges) {
+                self.back_edge(block, target);
+            }
+        }
+
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+    }
+
+    /// Loads the arguments and the fuel from the context, defining every
+    /// variable before jumping to the first instruction.
+    fn enter(&mut self) {
+        let entry = self.builder.create_block();
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+        self.context = self.builder.block_params(entry)[0];
+        self.locals_buffer = self.load_context(self.pointer, JitContext::LOCALS);
+        self.stack_buffer = self.load_context(self.pointer, JitContext::STACK);
+
+        for index in 0..self.fuel.index() {
+            let kind = KINDS[index % KINDS.len()];
+            let variable = Variable::new(index);
+            self.builder.declare_var(variable, ir_type(kind));
+            let zero = match kind {
+                Kind::Float => self.builder.ins().f32const(Ieee32::with_float(0.0)),
+                Kind::Double => self.builder.ins().f64const(Ieee64::with_float(0.0)),
+                _ => self.builder.ins().iconst(ir_type(kind), 0),
+            };
+            self.builder.def_var(variable, zero);
+        }
+        self.builder.declare_var(self.fuel, types::I64);
+        let fuel = self.load_context(types::I64, JitContext::FUEL);
+        self.builder.def_var(self.fuel, fuel);
+
+        let analysis = self.analysis;
+        if let Some(state) = &analysis.states[0] {
+            for (index, &kind) in state.locals.iter().enumerate() {
+                if kind.is_primitive() {
+                    let raw = self.builder.ins().load(
+                        types::I64,
+                        MemFlags::trusted(),
+                        self.locals_buffer,
+                        8 * index as i32,
+                    );
+                    let value = self.unpack(kind, raw);
+                    self.builder.def_var(self.local(index, kind), value);
+                }
+            }
+        }
+        let first = self.blocks[0].expect("The first instruction starts a block");
+        self.builder.ins().jump(first, &[]);
+    }
+
+    /// The number of instructions the block starting at `ip` executes, up to
+    /// the next block or the first instruction deoptimizing.
+    fn block_length(&self, start: usize, leaders: &[bool]) -> usize {
+        let mut ip = start;
+        while ip < leaders.len()
+            && (ip == start || !leaders[ip])
+            && self.analysis.supported[ip]
+            && self.analysis.states[ip].is_some()
+        {
+            ip += 1;
+            if is_terminator(self.instructions[ip - 1]) {
+                break;
+            }
+        }
+        ip - start
+    }
+
+    /// Translates the instruction, returning whether execution continues
+    /// with the next one.
+    fn instruction(&mut self, ip: usize, instruction: Instruction) -> bool {
+        match instruction {
+            Instruction::Nop => {}
+            Instruction::IConst(value) => {
+                let value = self.ins().iconst(types::I32, value as i64);
+                self.push(Kind::Int, value);
+            }
+            Instruction::LConst(value) => {
+                let value = self.ins().iconst(types::I64, value);
+                self.push(Kind::Long, value);
+            }
+            Instruction::FConst(value) => {
+                let value = self.ins().f32const(Ieee32::with_float(value));
+                self.push(Kind::Float, value);
+            }
+            Instruction::DConst(value) => {
+                let value = self.ins().f64const(Ieee64::with_float(value));
+                self.push(Kind::Double, value);
+            }
+            Instruction::Load(index) => {
+                let index = index as usize;
+                let kind = self.analysis.states[ip].as_ref().expect("Reached").locals[index];
+                let value = self.builder.use_var(self.local(index, kind));
+                self.push(kind, value);
+            }
+            Instruction::Store(index) => {
+                let (kind, value) = self.pop();
+                self.builder
+                    .def_var(self.local(index as usize, kind), value);
+            }
+            Instruction::Pop => {
+                self.pop();
+            }
+            Instruction::Pop2 => {
+                let (kind, _) = self.pop();
+                if !kind.is_wide() {
+                    self.pop();
+                }
+            }
+            Instruction::Dup => {
+                let (kind, value) = self.pop();
+                self.push(kind, value);
+                self.push(kind, value);
+            }
+            Instruction::Dup2 => {
+                let (top_kind, top) = self.pop();
+                if top_kind.is_wide() {
+                    self.push(top_kind, top);
+                } else {
+                    let (below_kind, below) = self.pop();
+                    self.push(below_kind, below);
+                    self.push(top_kind, top);
+                    self.push(below_kind, below);
+                }
+                self.push(top_kind, top);
+            }
+            Instruction::Swap => {
+                let (top_kind, top) = self.pop();
+                let (below_kind, below) = self.pop();
+                self.push(top_kind, top);
+                self.push(below_kind, below);
+            }
+            Instruction::IAdd | Instruction::LAdd => self.binary(|ins, a, b| ins.iadd(a, b)),
+            Instruction::ISub | Instruction::LSub => self.binary(|ins, a, b| ins.isub(a, b)),
+            Instruction::IMul | Instruction::LMul => self.binary(|ins, a, b| ins.imul(a, b)),
+            Instruction::IAnd | Instruction::LAnd => self.binary(|ins, a, b| ins.band(a, b)),
+            Instruction::IOr | Instruction::LOr => self.binary(|ins, a, b| ins.bor(a, b)),
+            Instruction::IXor | Instruction::LXor => self.binary(|ins, a, b| ins.bxor(a, b)),
+            // Cranelift masks shift amounts to the width of the value, like Java.
+            Instruction::IShl | Instruction::LShl => self.binary(|ins, a, b| ins.ishl(a, b)),
+            Instruction::IShr | Instruction::LShr => self.binary(|ins, a, b| ins.sshr(a, b)),
+            Instruction::IUShr | Instruction::LUShr => self.binary(|ins, a, b| ins.ushr(a, b)),
+            Instruction::FAdd | Instruction::DAdd => self.binary(|ins, a, b| ins.fadd(a, b)),
+            Instruction::FSub | Instruction::DSub => self.binary(|ins, a, b| ins.fsub(a, b)),
+            Instruction::FMul | Instruction::DMul => self.binary(|ins, a, b| ins.fmul(a, b)),
+            Instruction::FDiv | Instruction::DDiv => self.binary(|ins, a, b| ins.fdiv(a, b)),
+            Instruction::IDiv | Instruction::LDiv => self.divide(ip, false),
+            Instruction::IRem | Instruction::LRem => self.divide(ip, true),
+            Instruction::INeg | Instruction::LNeg => self.unary(|ins, a| ins.ineg(a)),
+            Instruction::FNeg | Instruction::DNeg => self.unary(|ins, a| ins.fneg(a)),
+            Instruction::IInc(index, increment) => {
+                let local = self.local(index as usize, Kind::Int);
+                let value = self.builder.use_var(local);
+                let value = self.ins().iadd_imm(value, increment as i64);
+                self.builder.def_var(local, value);
+            }
+            Instruction::I2L => self.convert(Kind::Long, |ins, a| ins.sextend(types::I64, a)),
+            Instruction::I2F | Instruction::L2F => {
+                self.convert(Kind::Float, |ins, a| ins.fcvt_from_sint(types::F32, a))
+            }
+            Instruction::I2D | Instruction::L2D => {
+                self.convert(Kind::Double, |ins, a| ins.fcvt_from_sint(types::F64, a))
+            }
+            Instruction::L2I => self.convert(Kind::Int, |ins, a| ins.ireduce(types::I32, a)),
+            // Saturating conversions, with NaN converting to zero like in Java.
+            Instruction::F2I | Instruction::D2I => {
+                self.convert(Kind::Int, |ins, a| ins.fcvt_to_sint_sat(types::I32, a))
+            }
+            Instruction::F2L | Instruction::D2L => {
+                self.convert(Kind::Long, |ins, a| ins.fcvt_to_sint_sat(types::I64, a))
+            }
+            Instruction::F2D => self.convert(Kind::Double, |ins, a| ins.fpromote(types::F64, a)),
+            Instruction::D2F => self.convert(Kind::Float, |ins, a| ins.fdemote(types::F32, a)),
+            Instruction::I2B => self.narrow(types::I8, true),
+            Instruction::I2C => self.narrow(types::I16, false),
+            Instruction::I2S => self.narrow(types::I16, true),
+            Instruction::LCmp => {
+                let (_, b) = self.pop();
+                let (_, a) = self.pop();
+                let greater = self.ins().icmp(IntCC::SignedGreaterThan, a, b);
+                let less = self.ins().icmp(IntCC::SignedLessThan, a, b);
+                let value = self.compare(greater, less);
+                self.push(Kind::Int, value);
+            }
+            Instruction::FCmpL | Instruction::FCmpG | Instruction::DCmpL | Instruction::DCmpG => {
+                let (_, b) = self.pop();
+                let (_, a) = self.pop();
+                let greater = self.ins().fcmp(FloatCC::GreaterThan, a, b);
+                let less = self.ins().fcmp(FloatCC::LessThan, a, b);
+                let ordered = self.compare(greater, less);
+                let unordered = self.ins().fcmp(FloatCC::Unordered, a, b);
+                let nan = match instruction {
+                    Instruction::FCmpL | Instruction::DCmpL => -1,
+                    _ => 1,
+                };
+                let nan = self.ins().iconst(types::I32, nan);
+                let value = self.ins().select(unordered, nan, ordered);
+                self.push(Kind::Int, value);
+            }
+            Instruction::If(condition, target) => {
+                let (_, value) = self.pop();
+                let taken = self.ins().icmp_imm(int_cc(condition), value, 0);
+                self.branch(ip, taken, target as usize);
+                return false;
+            }
+            Instruction::IfICmp(condition, target) => {
+                let (_, b) = self.pop();
+                let (_, a) = self.pop();
+                let taken = self.ins().icmp(int_cc(condition), a, b);
+                self.branch(ip, taken, target as usize);
+                return false;
+            }
+            Instruction::Goto(target) => {
+                let destination = self.destination(ip, target as usize);
+                self.ins().jump(destination, &[]);
+                return false;
+            }
+            Instruction::ReturnValue => {
+                let (kind, value) = self.pop();
+                let raw = self.pack(kind, value);
+                let buffer = self.stack_buffer;
+                self.ins().store(MemFlags::trusted(), raw, buffer, 0);
+                self.exit(RETURNED, 0);
+                return false;
+            }
+            Instruction::Return => {
+                self.exit(RETURNED, 0);
+                return false;
+            }
+            _ => unreachable!("Analysis only admits supported instructions"),
+        }
+        true
+    }
+
+    fn branch(&mut self, ip: usize, taken: Value, target: usize) {
+        let destination = self.destination(ip, target);
+        let next = self.blocks[ip + 1].expect("Conditional branches are followed by a block");
+        self.builder.ins().brif(taken, destination, &[], next, &[]);
+    }
+
+    /// The block a branch to the target jumps to. Backward branches go
+    /// through a check polling when due, so that loops in compiled code
+    /// still honour the limits and requests polls serve.
+    fn destination(&mut self, ip: usize, target: usize) -> Block {
+        let block = self.blocks[target].expect("Branch targets start blocks");
+        if target > ip {
+            return block;
+        }
+        let check = self.builder.create_block();
+        self.back_edges.push((check, target));
+        check
+    }
+
+    /// Emits the poll check of a backward branch to the target. The poll
+    /// happens before executing the target, like in the interpreter.
+    fn back_edge(&mut self, check: Block, target: usize) {
+        let block = self.blocks[target].expect("Branch targets start blocks");
+        let poll = self.builder.create_block();
+        self.builder.set_cold_block(poll);
+
+        self.builder.switch_to_block(check);
+        let fuel = self.builder.use_var(self.fuel);
+        let due = self
+            .builder
+            .ins()
+            .icmp_imm(IntCC::SignedLessThanOrEqual, fuel, 1);
+        self.builder.ins().brif(due, poll, &[], block, &[]);
+
+        self.builder.switch_to_block(poll);
+        let callback = self.load_context(self.pointer, JitContext::POLL);
+        let target_ip = self.builder.ins().iconst(types::I64, target as i64);
+        let call =
+            self.builder
+                .ins()
+                .call_indirect(self.poll, callback, &[self.context, target_ip, fuel]);
+        let fuel = self.builder.inst_results(call)[0];
+        self.builder.def_var(self.fuel, fuel);
+        let failed = self.builder.ins().icmp_imm(IntCC::Equal, fuel, POLL_FAILED);
+        self.deoptimize_if(failed, target, 0);
+        self.builder.ins().jump(block, &[]);
+    }
+
+    /// Divides the operands on the stack, deoptimizing on a zero divisor so
+    /// that the interpreter throws the `ArithmeticException`.
+    fn divide(&mut self, ip: usize, remainder: bool) {
+        let (kind, b) = self.pop();
+        let (_, a) = self.pop();
+        let zero = self.builder.ins().icmp_imm(IntCC::Equal, b, 0);
+        self.deoptimize_if(zero, ip, self.accounted - ip);
+
+        // `MIN / -1` overflows, trapping in Cranelift but not in Java.
+        let minus_one = self.ins().icmp_imm(IntCC::Equal, b, -1);
+        let one = self.ins().iconst(ir_type(kind), 1);
+        let divisor = self.ins().select(minus_one, one, b);
+        let value = if remainder {
+            self.ins().srem(a, divisor)
+        } else {
+            let quotient = self.ins().sdiv(a, divisor);
+            let negated = self.ins().ineg(a);
+            self.ins().select(minus_one, negated, quotient)
+        };
+        self.push(kind, value);
+    }
+
+    fn binary(&mut self, operation: impl FnOnce(FuncInstBuilder<'_, 'b>, Value, Value) -> Value) {
+        let (_, b) = self.pop();
+        let (kind, a) = self.pop();
+        let value = operation(self.builder.ins(), a, b);
+        self.push(kind, value);
+    }
+
+    fn unary(&mut self, operation: impl FnOnce(FuncInstBuilder<'_, 'b>, Value) -> Value) {
+        let (kind, a) = self.pop();
+        let value = operation(self.builder.ins(), a);
+        self.push(kind, value);
+    }
+
+    fn convert(
+        &mut self,
+        to: Kind,
+        operation: impl FnOnce(FuncInstBuilder<'_, 'b>, Value) -> Value,
+    ) {
+        let (_, a) = self.pop();
+        let value = operation(self.builder.ins(), a);
+        self.push(to, value);
+    }
+
+    /// Truncates the int to the narrower type and extends it back.
+    fn narrow(&mut self, to: Type, signed: bool) {
+        let (_, a) = self.pop();
+        let narrow = self.builder.ins().ireduce(to, a);
+        let value = if signed {
+            self.builder.ins().sextend(types::I32, narrow)
+        } else {
+            self.builder.ins().uextend(types::I32, narrow)
+        };
+        self.push(Kind::Int, value);
+    }
+
+    /// The result of a comparison: 1 if greater, -1 if less, 0 otherwise.
+    fn compare(&mut self, greater: Value, less: Value) -> Value {
+        let greater = self.builder.ins().uextend(types::I32, greater);
+        let less = self.builder.ins().uextend(types::I32, less);
+        self.builder.ins().isub(greater, less)
+    }
+
+    fn deoptimize_if(&mut self, condition: Value, ip: usize, unexecuted: usize) {
+        let deoptimize = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(deoptimize);
+        self.builder
+            .ins()
+            .brif(condition, deoptimize, &[], next, &[]);
+        self.builder.switch_to_block(deoptimize);
+        self.deoptimize(ip, unexecuted);
+        self.builder.switch_to_block(next);
+    }
+
+    /// Writes the frame state before the instruction to the context and
+    /// returns to the interpreter, to continue with the instruction. The
+    /// `unexecuted` instructions accounted for in advance are given back.
+    fn deoptimize(&mut self, ip: usize, unexecuted: usize) {
+        let analysis = self.analysis;
+        let state = analysis.states[ip]
+            .as_ref()
+            .expect("Deoptimizing at a reached instruction");
+        for (index, &kind) in state.locals.iter().enumerate() {
+            if kind.is_primitive() {
+                let value = self.builder.use_var(self.local(index, kind));
+                let raw = self.pack(kind, value);
+                self.builder.ins().store(
+                    MemFlags::trusted(),
+                    raw,
+                    self.locals_buffer,
+                    8 * index as i32,
+                );
+            }
+        }
+        for (depth, &kind) in state.stack.iter().enumerate() {
+            let value = self.builder.use_var(self.stack_slot(depth, kind));
+            let raw = self.pack(kind, value);
+            self.builder.ins().store(
+                MemFlags::trusted(),
+                raw,
+                self.stack_buffer,
+                8 * depth as i32,
+            );
+        }
+        self.exit(ip as i64, unexecuted);
+    }
+
+    /// Stores the fuel left and returns the status.
+    fn exit(&mut self, status: i64, unexecuted: usize) {
+        let mut fuel = self.builder.use_var(self.fuel);
+        if unexecuted > 0 {
+            fuel = self.builder.ins().iadd_imm(fuel, unexecuted as i64);
+        }
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), fuel, self.context, JitContext::FUEL);
+        let status = self.builder.ins().iconst(types::I64, status);
+        self.builder.ins().return_(&[status]);
+    }
+
+    fn ins(&mut self) -> FuncInstBuilder<'_, 'b> {
+        self.builder.ins()
+    }
+
+    fn pop(&mut self) -> (Kind, Value) {
+        let kind = self.stack.pop().expect("Analysis checked the stack depth");
+        let value = self
+            .builder
+            .use_var(self.stack_slot(self.stack.len(), kind));
+        (kind, value)
+    }
+
+    fn push(&mut self, kind: Kind, value: Value) {
+        self.builder
+            .def_var(self.stack_slot(self.stack.len(), kind), value);
+        self.stack.push(kind);
+    }
+
+    fn local(&self, index: usize, kind: Kind) -> Variable {
+        Variable::new(index * KINDS.len() + kind_index(kind))
+    }
+
+    fn stack_slot(&self, depth: usize, kind: Kind) -> Variable {
+        Variable::new((self.locals + depth) * KINDS.len() + kind_index(kind))
+    }
+
+    fn load_context(&mut self, ty: Type, offset: i32) -> Value {
+        self.builder
+            .ins()
+            .load(ty, MemFlags::trusted(), self.context, offset)
+    }
+
+    /// Converts a value to the 64 bits of a context slot.
+    fn pack(&mut self, kind: Kind, value: Value) -> Value {
+        match kind {
+            Kind::Int => self.ins().sextend(types::I64, value),
+            Kind::Float => {
+                let bits = self.ins().bitcast(types::I32, MemFlags::new(), value);
+                self.ins().uextend(types::I64, bits)
+            }
+            Kind::Double => self.ins().bitcast(types::I64, MemFlags::new(), value),
+            _ => value,
+        }
+    }
+
+    fn unpack(&mut self, kind: Kind, raw: Value) -> Value {
+        match kind {
+            Kind::Int => self.ins().ireduce(types::I32, raw),
+            Kind::Float => {
+                let bits = self.ins().ireduce(types::I32, raw);
+                self.ins().bitcast(types::F32, MemFlags::new(), bits)
+            }
+            Kind::Double => self.ins().bitcast(types::F64, MemFlags::new(), raw),
+            _ => raw,
+        }
+    }
+}
+
+fn kind_index(kind: Kind) -> usize {
+    KINDS
+        .iter()
+        .position(|&primitive| primitive == kind)
+        .expect("Only primitives live in variables")
+}
+
+fn ir_type(kind: Kind) -> Type {
+    match kind {
+        Kind::Int => types::I32,
+        Kind::Float => types::F32,
+        Kind::Double => types::F64,
+        _ => types::I64,
+    }
+}
+
+fn int_cc(condition: Condition) -> IntCC {
+    match condition {
+        Condition::Eq => IntCC::Equal,
+        Condition::Ne => IntCC::NotEqual,
+        Condition::Lt => IntCC::SignedLessThan,
+        Condition::Ge => IntCC::SignedGreaterThanOrEqual,
+        Condition::Gt => IntCC::SignedGreaterThan,
+        Condition::Le => IntCC::SignedLessThanOrEqual,
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::default_libcall_names;
+
+use crate::class::constant_pool::{Constant, ConstantPool};
+use crate::class::instruction::Instruction;
+use crate::vm::jit::analysis::{Analysis, Kind};
+use crate::vm::jit::compiler::{Entry, POLL_FAILED, RETURNED};
+use crate::vm::jit::queue::{CompileQueue, Task};
+use crate::vm::runtime::RuntimeMethod;
+use crate::vm::value::Value;
+use crate::vm::{Unwind, Vm};
+
+mod analysis;
+mod compiler;
+mod queue;
+
+/// Hotness after which methods are compiled by default.
+const DEFAULT_THRESHOLD: u32 = 1000;
+
+/// Bytes of machine code kept by default.
+const DEFAULT_CODE_CACHE: usize = 32 * 1024 * 1024;
+
+/// The hotness a method gains when a poll lands in its interpreted code,
+/// most likely in a loop: the instructions of a poll interval are worth a
+/// few dozen short invocations.
+const LOOP_HOTNESS: u32 = 32;
+
+/// Deoptimizations after which compiled code is reviewed, and discarded if
+/// it ran fewer than [MIN_INSTRUCTIONS_PER_DEOPTIMIZATION] instructions per
+/// deoptimization on average: handing frames over would cost more than
+/// compiled code saves.
+const DEOPTIMIZATION_REVIEW: u32 = 100;
+const MIN_INSTRUCTIONS_PER_DEOPTIMIZATION: u64 = 100;
+
+// =============================================================================
+// COMPILER
+// =============================================================================
+
+/// Compiles hot methods to native code with Cranelift, configured through
+/// [VmBuilder::jit](crate::vm::VmBuilder::jit).
+///
+/// Every method has a hotness counter, counting its invocations and the
+/// polls landing in its interpreted loops. Once the hotness reaches the
+/// `threshold`, the method is queued for compilation on a background thread,
+/// hottest first, and its compiled code runs whenever the method is invoked
+/// after that. Compiled code implements the primitive arithmetic, local
+/// variables and branches of the method; at any other instruction it
+/// deoptimizes, handing its frame over to the interpreter for the rest of
+/// the invocation. Methods already running stay interpreted, there is no
+/// on-stack replacement.
+///
+/// The code cache holds the compiled code up to a size, evicting the least
+/// recently invoked methods to make room, which are compiled again once hot
+/// again.
+pub struct JitCompiler {
+    mode: CompilationMode,
+    threshold: u32,
+    background: bool,
+    code_cache: usize,
+    /// The compilation state of the invoked methods, by address: methods are
+    /// never freed, since classes are never unloaded.
+    methods: HashMap<usize, MethodState>,
+    /// Started on the first method compiled in the background.
+    queue: Option<CompileQueue>,
+    /// Methods queued and not installed yet.
+    queued: usize,
+    /// Counts the invocations of compiled code, ordering them by recency.
+    clock: u64,
+    stats: JitStats,
+}
+
+/// When methods are compiled, see [JitCompiler::mode].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompilationMode {
+    /// Methods are only ever interpreted, like HotSpot's `-Xint`.
+    Interpreted,
+    /// Methods are interpreted until hot, then compiled.
+    Tiered,
+    /// Methods are compiled before they first run, waiting for the
+    /// compilation, like HotSpot's `-Xcomp`.
+    Eager,
+}
+
+impl JitCompiler {
+    pub fn new() -> Self {
+        JitCompiler {
+            mode: CompilationMode::Tiered,
+            threshold: DEFAULT_THRESHOLD,
+            background: true,
+            code_cache: DEFAULT_CODE_CACHE,
+            methods: HashMap::new(),
+            queue: None,
+            queued: 0,
+            clock: 0,
+            stats: JitStats::default(),
+        }
+    }
+
+    pub fn mode(mut self, mode: CompilationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Compiles methods once their hotness reaches the threshold, 1 compiling
+    /// them on their first invocation.
+    pub fn threshold(mut self, hotness: u32) -> Self {
+        self.threshold = hotness.max(1);
+        self
+    }
+
+    /// Whether hot methods are compiled on a background thread, or right
+    /// away, with the invocation waiting for the compilation.
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Limits the bytes of machine code kept at once.
+    pub fn code_cache(mut self, bytes: usize) -> Self {
+        self.code_cache = bytes;
+        self
+    }
+
+    /// Counts an invocation of the method, returning its compiled code once
+    /// it is hot and compiled.
+    fn invoked(
+        &mut self,
+        method: &Arc<RuntimeMethod>,
+        constant_pool: Option<&ConstantPool>,
+    ) -> Option<Arc<CompiledMethod>> {
+        if self.mode == CompilationMode::Interpreted {
+            return None;
+        }
+        if self.queued > 0 {
+            self.take_compiled();
+        }
+
+        let threshold = match self.mode {
+            CompilationMode::Eager => 1,
+            _ => self.threshold,
+        };
+        let state = self
+            .methods
+            .entry(Arc::as_ptr(method) as usize)
+            .or_insert(MethodState::Interpreted(0));
+        match state {
+            MethodState::Compiled {
+                code, last_used, ..
+            } => {
+                self.clock += 1;
+                *last_used = self.clock;
+                return Some(code.clone());
+            }
+            MethodState::Rejected => return None,
+            MethodState::Queued(hotness) => {
+                hotness.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            MethodState::Interpreted(hotness) => {
+                *hotness = hotness.saturating_add(1);
+                if *hotness < threshold {
+                    return None;
+                }
+            }
+        }
+        self.hot(method, constant_pool)
+    }
+
+    /// Counts a poll landing in interpreted code of the method.
+    fn polled(&mut self, method: &Arc<RuntimeMethod>) {
+        match self.methods.get_mut(&(Arc::as_ptr(method) as usize)) {
+            Some(MethodState::Interpreted(hotness)) => {
+                *hotness = hotness.saturating_add(LOOP_HOTNESS);
+            }
+            Some(MethodState::Queued(hotness)) => {
+                hotness.fetch_add(LOOP_HOTNESS, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /// Compiles the method which just became hot, or queues it.
+    fn hot(
+        &mut self,
+        method: &Arc<RuntimeMethod>,
+        constant_pool: Option<&ConstantPool>,
+    ) -> Option<Arc<CompiledMethod>> {
+        let code = method.code.as_ref()?;
+        let instructions = inline_constants(&code.instructions, constant_pool);
+        let analysis = Analysis::of(method, code, &instructions)
+            // Compiled code deoptimizing right away would only slow the
+            // method down.
+            .filter(|analysis| analysis.supported.first().copied().unwrap_or(false));
+        let analysis = match analysis {
+            Some(analysis) => analysis,
+            None => return self.install(method, None),
+        };
+
+        if self.mode == CompilationMode::Tiered && self.background {
+            let hotness = Arc::new(AtomicU32::new(self.threshold));
+            self.queue
+                .get_or_insert_with(CompileQueue::start)
+                .push(Task {
+                    method: method.clone(),
+                    instructions,
+                    analysis,
+                    hotness: hotness.clone(),
+                });
+            self.queued += 1;
+            self.methods
+                .insert(Arc::as_ptr(method) as usize, MethodState::Queued(hotness));
+            return None;
+        }
+        let compiled = CompiledMethod::compile(method, &instructions, analysis);
+        self.install(method, compiled)
+    }
+
+    /// Installs the methods compiled in the background.
+    fn take_compiled(&mut self) {
+        let compiled: Vec<_> = match &self.queue {
+            Some(queue) => queue.compiled().collect(),
+            None => return,
+        };
+        for (method, code) in compiled {
+            self.queued -= 1;
+            // The methods discarded while queued stay out of the code cache
+            let key = Arc::as_ptr(&method) as usize;
+            if matches!(self.methods.get(&key), Some(MethodState::Queued(_))) {
+                self.install(&method, code);
+            }
+        }
+    }
+
+    /// Forgets the method, freeing its compiled code, once it was replaced
+    /// by a class redefinition.
+    fn discard(&mut self, method: &Arc<RuntimeMethod>) {
+        let key = Arc::as_ptr(method) as usize;
+        if let Some(MethodState::Compiled { code, .. }) = self.methods.remove(&key) {
+            self.stats.code_cache_size -= code.size;
+        }
+    }
+
+    /// Records the outcome of compiling the method, making room for its code
+    /// in the code cache.
+    fn install(
+        &mut self,
+        method: &Arc<RuntimeMethod>,
+        code: Option<CompiledMethod>,
+    ) -> Option<Arc<CompiledMethod>> {
+        let key = Arc::as_ptr(method) as usize;
+        let code = match code {
+            Some(code) if code.size <= self.code_cache => code,
+            _ => {
+                self.stats.rejected_methods += 1;
+                self.methods.insert(key, MethodState::Rejected);
+                return None;
+            }
+        };
+        while self.stats.code_cache_size + code.size > self.code_cache {
+            self.evict_least_recently_used();
+        }
+
+        let code = Arc::new(code);
+        self.clock += 1;
+        self.stats.compiled_methods += 1;
+        self.stats.code_cache_size += code.size;
+        let state = MethodState::Compiled {
+            code: code.clone(),
+            deoptimizations: 0,
+            instructions: 0,
+            last_used: self.clock,
+        };
+        self.methods.insert(key, state);
+        Some(code)
+    }
+
+    /// Evicts the compiled method invoked least recently, leaving it to the
+    /// interpreter until it is hot again.
+    fn evict_least_recently_used(&mut self) {
+        let evicted = self
+            .methods
+            .iter()
+            .filter_map(|(&key, state)| match state {
+                MethodState::Compiled { last_used, .. } => Some((*last_used, key)),
+                _ => None,
+            })
+            .min()
+            .map(|(_, key)| key);
+        if let Some(key) = evicted {
+            if let Some(MethodState::Compiled { code, .. }) =
+                self.methods.insert(key, MethodState::Interpreted(0))
+            {
+                self.stats.code_cache_size -= code.size;
+                self.stats.evicted_methods += 1;
+            }
+        }
+    }
+
+    /// Records that compiled code of the method deoptimized after running
+    /// the instructions, discarding the code if it does not pay off.
+    fn deoptimized(&mut self, method: &Arc<RuntimeMethod>, executed: u64) {
+        self.stats.deoptimizations += 1;
+        let state = match self.methods.get_mut(&(Arc::as_ptr(method) as usize)) {
+            Some(state) => state,
+            None => return,
+        };
+        if let MethodState::Compiled {
+            code,
+            deoptimizations,
+            instructions,
+            ..
+        } = state
+        {
+            *deoptimizations += 1;
+            *instructions += executed;
+            if *deoptimizations < DEOPTIMIZATION_REVIEW {
+                return;
+            }
+            if *instructions / u64::from(*deoptimizations) < MIN_INSTRUCTIONS_PER_DEOPTIMIZATION {
+                self.stats.code_cache_size -= code.size;
+                self.stats.rejected_methods += 1;
+                *state = MethodState::Rejected;
+            } else {
+                *deoptimizations = 0;
+                *instructions = 0;
+            }
+        }
+    }
+}
+
+impl Default for JitCompiler {
+    fn default() -> Self {
+        JitCompiler::new()
+    }
+}
+
+/// Replaces the `ldc`s of numeric constants with the constants themselves,
+/// which compiled code supports.
+fn inline_constants(
+    instructions: &[Instruction],
+    constant_pool: Option<&ConstantPool>,
+) -> Vec<Instruction> {
+    instructions
+        .iter()
+        .map(|&instruction| match instruction {
+            Instruction::Ldc(index) => {
+                match constant_pool.and_then(|pool| pool.get(index as usize)) {
+                    Some(Constant::Integer(constant)) => Instruction::IConst(constant.value),
+                    Some(Constant::Long(constant)) => Instruction::LConst(constant.value),
+                    Some(Constant::Float(constant)) => Instruction::FConst(constant.value),
+                    Some(Constant::Double(constant)) => Instruction::DConst(constant.value),
+                    _ => instruction,
+                }
+            }
+            instruction => instruction,
+        })
+        .collect()
+}
+
+fn new_module() -> Option<JITModule> {
+    // Checked first, as the builder panics on hosts Cranelift does not support.
+    cranelift_native::builder().ok()?;
+    let builder = JITBuilder::with_flags(&[("opt_level", "speed")], default_libcall_names());
+    Some(JITModule::new(builder.ok()?))
+}
+
+/// Compilation totals, see [Vm::jit_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JitStats {
+    pub compiled_methods: usize,
+    /// Hot methods left to the interpreter, because compiled code would
+    /// deoptimize at their first instruction or did so too often, or because
+    /// compilation failed.
+    pub rejected_methods: usize,
+    /// Invocations of compiled code handed over to the interpreter.
+    pub deoptimizations: u64,
+    /// Compiled methods evicted from the code cache.
+    pub evicted_methods: usize,
+    /// Bytes of machine code in the code cache.
+    pub code_cache_size: usize,
+}
+
+enum MethodState {
+    /// Not compiled yet, with the hotness so far.
+    Interpreted(u32),
+    /// Waiting for compilation in the background, with the hotness growing
+    /// meanwhile.
+    Queued(Arc<AtomicU32>),
+    /// Compiled, with the deoptimizations and the instructions run by
+    /// compiled code since the code was last reviewed, and the clock of its
+    /// last invocation.
+    Compiled {
+        code: Arc<CompiledMethod>,
+        deoptimizations: u32,
+        instructions: u64,
+        last_used: u64,
+    },
+    Rejected,
+}
+
+/// Compiled code, each method having its own module so that its memory can
+/// be freed on its own once the code is discarded.
+pub(crate) struct CompiledMethod {
+    entry: Entry,
+    /// The frame states deoptimizing compiled code leaves behind.
+    analysis: Analysis,
+    stack_depth: usize,
+    size: usize,
+    module: Option<JITModule>,
+}
+
+impl CompiledMethod {
+    fn compile(
+        method: &RuntimeMethod,
+        instructions: &[Instruction],
+        analysis: Analysis,
+    ) -> Option<CompiledMethod> {
+        let mut module = new_module()?;
+        let (entry, size) = match compiler::compile(&mut module, instructions, &analysis) {
+            Ok(compiled) => compiled,
+            Err(error) => {
+                tracing::debug!(
+                    method = method.name.as_str(),
+                    error = %error,
+                    "compilation failed"
+                );
+                // SAFETY: none of the module's code was handed out.
+                unsafe { module.free_memory() };
+                return None;
+            }
+        };
+        Some(CompiledMethod {
+            entry,
+            stack_depth: analysis.stack_depth(),
+            analysis,
+            size,
+            module: Some(module),
+        })
+    }
+}
+
+// SAFETY: the module, which is not `Sync`, is only used when dropped.
+unsafe impl Sync for CompiledMethod {}
+
+impl Drop for CompiledMethod {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: compiled code only runs while its method is borrowed.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+// =============================================================================
+// EXECUTION
+// =============================================================================
+
+/// What compiled code shares with the VM. Compiled code reads and writes
+/// the frame through buffers of 64 bits per local and operand stack entry,
+/// leaving references to the frame itself.
+#[repr(C)]
+pub(crate) struct JitContext {
+    locals: *mut u64,
+    stack: *mut u64,
+    /// Instructions left before the next poll is due: the VM's next poll
+    /// minus the instructions executed.
+    fuel: i64,
+    /// Called by loops when a poll is due, see [poll_compiled].
+    poll: extern "C" fn(*mut JitContext, i64, i64) -> i64,
+    vm: *mut Vm,
+    /// The error of a failed poll.
+    error: Option<Unwind>,
+}
+
+impl JitContext {
+    const LOCALS: i32 = mem::offset_of!(JitContext, locals) as i32;
+    const STACK: i32 = mem::offset_of!(JitContext, stack) as i32;
+    const FUEL: i32 = mem::offset_of!(JitContext, fuel) as i32;
+    const POLL: i32 = mem::offset_of!(JitContext, poll) as i32;
+}
+
+impl Vm {
+    pub fn jit_stats(&self) -> JitStats {
+        self.jit
+            .as_ref()
+            .map_or_else(JitStats::default, |jit| jit.stats)
+    }
+
+    /// Discards the compiled code of the method replaced by a class
+    /// redefinition.
+    pub(crate) fn discard_compiled(&mut self, method: &Arc<RuntimeMethod>) {
+        if let Some(jit) = &mut self.jit {
+            jit.discard(method);
+        }
+    }
+
+    /// Runs the method of the current frame in compiled code if the frame was
+    /// just pushed and the method is hot. Returns the result if compiled code
+    /// ran the method to completion, or `None` if the interpreter has to run
+    /// the frame, deoptimized or not.
+    pub(crate) fn execute_compiled(&mut self) -> Result<Option<Option<Value>>, Unwind> {
+        let frame = self.frame();
+        if frame.ip != 0 || !frame.stack.is_empty() {
+            return Ok(None);
+        }
+        let method = frame.method.clone();
+        if self.coverage.is_some() {
+            return Ok(None);
+        }
+        if let Some(trace) = &self.trace {
+            if trace.traces(&self.class(method.class).name, &method.name) {
+                return Ok(None);
+            }
+        }
+        let constant_pool = self.classes[method.class.index()]
+            .source
+            .as_ref()
+            .map(|source| &source.class.constant_pool);
+        match self
+            .jit
+            .as_mut()
+            .and_then(|jit| jit.invoked(&method, constant_pool))
+        {
+            Some(compiled) => self.run_compiled(&method, &compiled),
+            None => Ok(None),
+        }
+    }
+
+    /// Counts a poll landing in the interpreted code of the current frame
+    /// towards the hotness of its method.
+    pub(crate) fn count_interpreted_poll(&mut self) {
+        let method = self.frame().method.clone();
+        if let Some(jit) = &mut self.jit {
+            jit.polled(&method);
+        }
+    }
+
+    fn run_compiled(
+        &mut self,
+        method: &Arc<RuntimeMethod>,
+        compiled: &CompiledMethod,
+    ) -> Result<Option<Option<Value>>, Unwind> {
+        let mut locals: Vec<u64> = self
+            .frame()
+            .locals
+            .iter()
+            .map(|value| to_raw(*value))
+            .collect();
+        let mut stack = vec![0; compiled.stack_depth.max(1)];
+        let mut context = JitContext {
+            locals: locals.as_mut_ptr(),
+            stack: stack.as_mut_ptr(),
+            fuel: self.next_poll.wrapping_sub(self.executed_instructions) as i64,
+            poll: poll_compiled,
+            vm: self,
+            error: None,
+        };
+        // SAFETY: the buffers have a slot for every local and operand stack
+        // entry of the method, and the VM is only used through the context
+        // until compiled code returns.
+        let status = unsafe { (compiled.entry)(&mut context) };
+
+        let executed = self.executed_instructions;
+        let error = context.error.take();
+        if error.is_none() {
+            self.executed_instructions = self.next_poll.wrapping_sub(context.fuel as u64);
+        }
+        if status == RETURNED {
+            let kind = method.parsed_descriptor.return_type.as_ref().map(Kind::of);
+            return Ok(Some(kind.map(|kind| from_raw(kind, stack[0]))));
+        }
+
+        let executed = self.executed_instructions.wrapping_sub(executed);
+        if let Some(jit) = &mut self.jit {
+            jit.deoptimized(method, executed);
+        }
+        let ip = status as usize;
+        let state = compiled.analysis.states[ip]
+            .as_ref()
+            .expect("Compiled code deoptimizes at reached instructions");
+        let frame = self.frame();
+        frame.ip = ip;
+        for (index, &kind) in state.locals.iter().enumerate() {
+            match kind {
+                Kind::Reference => {}
+                kind => frame.locals[index] = from_raw(kind, locals[index]),
+            }
+        }
+        frame.stack = state
+            .stack
+            .iter()
+            .zip(&stack)
+            .map(|(&kind, &raw)| from_raw(kind, raw))
+            .collect();
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Polls on behalf of compiled code about to branch back to the instruction
+/// at `ip`, with the fuel it has left. Returns the fuel until the next poll,
+/// or [POLL_FAILED] with the error stored in the context.
+extern "C" fn poll_compiled(context: *mut JitContext, ip: i64, fuel: i64) -> i64 {
+    // SAFETY: compiled code passes the context it runs with, whose VM is
+    // not in use until compiled code returns.
+    let context = unsafe { &mut *context };
+    let vm = unsafe { &mut *context.vm };
+
+    // Like the interpreter, poll having counted the target instruction,
+    // which the compiled block starting there accounts for again.
+    vm.executed_instructions = vm.next_poll.wrapping_sub(fuel as u64) + 1;
+    let frame = vm.frame();
+    frame.ip = ip as usize;
+    let pc = frame.pc();
+    let opcode = frame.method.code.as_ref().map_or(0, |code| code.code[pc]);
+    match vm.poll_at(opcode) {
+        Ok(()) => vm.next_poll.wrapping_sub(vm.executed_instructions) as i64 + 1,
+        Err(error) => {
+            context.error = Some(error);
+            POLL_FAILED
+        }
+    }
+}
+
+/// The 64 bits of a context slot holding the value.
+fn to_raw(value: Value) -> u64 {
+    match value {
+        Value::Int(value) => value as i64 as u64,
+        Value::Long(value) => value as u64,
+        Value::Float(value) => value.to_bits() as u64,
+        Value::Double(value) => value.to_bits(),
+        Value::Reference(_) | Value::ReturnAddress(_) | Value::Top => 0,
+    }
+}
+
+fn from_raw(kind: Kind, raw: u64) -> Value {
+    match kind {
+        Kind::Int => Value::Int(raw as i32),
+        Kind::Long => Value::Long(raw as i64),
+        Kind::Float => Value::Float(f32::from_bits(raw as u32)),
+        Kind::Double => Value::Double(f64::from_bits(raw)),
+        Kind::Reference | Kind::Top => Value::Top,
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::class::instruction::Instruction;
+use crate::vm::jit::analysis::Analysis;
+use crate::vm::jit::CompiledMethod;
+use crate::vm::runtime::RuntimeMethod;
+
+/// A hot method waiting to be compiled.
+pub(crate) struct Task {
+    pub(crate) method: Arc<RuntimeMethod>,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) analysis: Analysis,
+    /// The priority of the task, which keeps growing while the method runs
+    /// interpreted.
+    pub(crate) hotness: Arc<AtomicU32>,
+}
+
+/// The outcome of a task, `None` if compilation failed.
+pub(crate) type Compiled = (Arc<RuntimeMethod>, Option<CompiledMethod>);
+
+/// Compiles methods on a background thread, the hottest one first, while
+/// the VM keeps interpreting them.
+pub(crate) struct CompileQueue {
+    shared: Arc<Shared>,
+    compiled: Receiver<Compiled>,
+    compiler: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    tasks: Mutex<Tasks>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct Tasks {
+    pending: Vec<Task>,
+    closed: bool,
+}
+
+impl CompileQueue {
+    /// Starts the thread compiling the queued methods.
+    pub(crate) fn start() -> Self {
+        let shared = Arc::new(Shared {
+            tasks: Mutex::new(Tasks::default()),
+            available: Condvar::new(),
+        });
+        let (sender, compiled) = mpsc::channel();
+        let compiler = {
+            let shared = shared.clone();
+            thread::spawn(move || compile_queued(&shared, &sender))
+        };
+        CompileQueue {
+            shared,
+            compiled,
+            compiler: Some(compiler),
+        }
+    }
+
+    pub(crate) fn push(&self, task: Task) {
+        self.shared.tasks.lock().unwrap().pending.push(task);
+        self.shared.available.notify_one();
+    }
+
+    /// The methods compiled since last asked, without waiting.
+    pub(crate) fn compiled(&self) -> impl Iterator<Item = Compiled> + '_ {
+        self.compiled.try_iter()
+    }
+}
+
+impl Drop for CompileQueue {
+    /// Drops the pending tasks and waits for the running one, freeing the
+    /// code compiled but not taken yet along with the channel.
+    fn drop(&mut self) {
+        {
+            let mut tasks = self.shared.tasks.lock().unwrap();
+            tasks.closed = true;
+            tasks.pending.clear();
+        }
+        self.shared.available.notify_one();
+        if let Some(compiler) = self.compiler.take() {
+            let _ = compiler.join();
+        }
+    }
+}
+
+fn compile_queued(shared: &Shared, compiled: &Sender<Compiled>) {
+    loop {
+        let task = {
+            let mut tasks = shared.tasks.lock().unwrap();
+            loop {
+                if tasks.closed {
+                    return;
+                }
+                let hottest = (0..tasks.pending.len())
+                    .max_by_key(|&index| tasks.pending[index].hotness.load(Ordering::Relaxed));
+                match hottest {
+                    Some(index) => break tasks.pending.swap_remove(index),
+                    None => tasks = shared.available.wait(tasks).unwrap(),
+                }
+            }
+        };
+        let code = CompiledMethod::compile(&task.method, &task.instructions, task.analysis);
+        if compiled.send((task.method, code)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::class::attributes::{Attribute, ElementValue};
+use crate::class::{Class, ClassLoadingError, MethodAccessFlags};
+use crate::vm::loader::LoadedClass;
+use crate::vm::value::ObjectRef;
+use crate::vm::{JavaException, Vm, VmError};
+
+/// The annotations of JUnit 4 and of JUnit 5's Jupiter, by what they mark.
+const TEST: [&str; 2] = ["Lorg/junit/Test;", "Lorg/junit/jupiter/api/Test;"];
+const DISABLED: [&str; 2] = ["Lorg/junit/Ignore;", "Lorg/junit/jupiter/api/Disabled;"];
+const BEFORE_ALL: [&str; 2] = [
+    "Lorg/junit/BeforeClass;",
+    "Lorg/junit/jupiter/api/BeforeAll;",
+];
+const BEFORE_EACH: [&str; 2] = ["Lorg/junit/Before;", "Lorg/junit/jupiter/api/BeforeEach;"];
+const AFTER_EACH: [&str; 2] = ["Lorg/junit/After;", "Lorg/junit/jupiter/api/AfterEach;"];
+const AFTER_ALL: [&str; 2] = ["Lorg/junit/AfterClass;", "Lorg/junit/jupiter/api/AfterAll;"];
+
+// =============================================================================
+// DISCOVERY
+// =============================================================================
+
+/// The tests of a test class and the methods run around them, found by their
+/// JUnit 4 or Jupiter annotations in the class files. Only `void` methods
+/// without parameters take part.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestPlan {
+    /// In declaration order, the ones inherited first.
+    pub tests: Vec<TestMethod>,
+    /// The static methods run once before the tests.
+    pub before_all: Vec<String>,
+    pub before_each: Vec<String>,
+    pub after_each: Vec<String>,
+    /// The static methods run once after the tests.
+    pub after_all: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestMethod {
+    pub name: String,
+    /// Marked `@Ignore` or `@Disabled`.
+    pub disabled: bool,
+    /// The internal name of the exception JUnit 4's `@Test(expected = ...)`
+    /// requires the test to throw.
+    pub expected: Option<String>,
+}
+
+impl TestPlan {
+    /// Finds the annotated methods of a test class, given with its
+    /// superclasses from the class itself up. Like in JUnit the methods of
+    /// the superclasses are inherited unless overridden, their before
+    /// methods running first and their after methods last.
+    pub fn discover(hierarchy: &[&Class]) -> Result<TestPlan, ClassLoadingError> {
+        let mut plan = TestPlan::default();
+        for class in hierarchy.iter().rev() {
+            let pool = &class.constant_pool;
+            let mut after_each = Vec::new();
+            let mut after_all = Vec::new();
+            for method in &class.methods {
+                let name = pool.get_utf8(method.name_index)?;
+                if pool.get_utf8(method.descriptor_index)? != "()V" {
+                    continue;
+                }
+                // An override replaces the inherited method
+                plan.remove(name);
+
+                let mut test = None;
+                let mut disabled = false;
+                for (descriptor, expected) in annotations(class, &method.attributes)? {
+                    if TEST.contains(&descriptor) {
+                        test = Some(expected);
+                    } else if DISABLED.contains(&descriptor) {
+                        disabled = true;
+                    } else if BEFORE_EACH.contains(&descriptor) {
+                        plan.before_each.push(name.to_string());
+                    } else if AFTER_EACH.contains(&descriptor) {
+                        after_each.push(name.to_string());
+                    } else if method.access_flags.contains(MethodAccessFlags::STATIC) {
+                        if BEFORE_ALL.contains(&descriptor) {
+                            plan.before_all.push(name.to_string());
+                        } else if AFTER_ALL.contains(&descriptor) {
+                            after_all.push(name.to_string());
+                        }
+                    }
+                }
+                if let Some(expected) = test {
+                    plan.tests.push(TestMethod {
+                        name: name.to_string(),
+                        disabled,
+                        expected,
+                    });
+                }
+            }
+            plan.after_each.splice(0..0, after_each);
+            plan.after_all.splice(0..0, after_all);
+        }
+        Ok(plan)
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.tests.retain(|test| test.name != name);
+        for methods in [
+            &mut self.before_all,
+            &mut self.before_each,
+            &mut self.after_each,
+            &mut self.after_all,
+        ] {
+            methods.retain(|method| method != name);
+        }
+    }
+}
+
+/// The descriptors of the annotations, visible or not, each with the
+/// exception named by its `expected` element if any.
+fn annotations<'a>(
+    class: &'a Class,
+    attributes: &'a [Attribute],
+) -> Result<Vec<(&'a str, Option<String>)>, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let mut found = Vec::new();
+    for attribute in attributes {
+        let annotations = match attribute {
+            Attribute::RuntimeVisibleAnnotations(annotations)
+            | Attribute::RuntimeInvisibleAnnotations(annotations) => annotations,
+            _ => continue,
+        };
+        for annotation in annotations {
+            let mut expected = None;
+            for pair in &annotation.element_value_pairs {
+                if let ElementValue::Class(value) = &pair.value {
+                    if pool.get_utf8(pair.element_name_index)? == "expected" {
+                        let descriptor = pool.get_utf8(value.class_info_index)?;
+                        expected = Some(
+                            descriptor
+                                .trim_start_matches('L')
+                                .trim_end_matches(';')
+                                .to_string(),
+                        );
+                    }
+                }
+            }
+            found.push((pool.get_utf8(annotation.type_index)?, expected));
+        }
+    }
+    Ok(found)
+}
+
+// =============================================================================
+// RUNNING
+// =============================================================================
+
+/// The result of a test, JUnit telling failed assertions, which throw an
+/// `AssertionError`, from other exceptions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    Failed(TestFailure),
+    Errored(TestFailure),
+    /// The test is disabled.
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestFailure {
+    /// The exception and its message, or what the test failed to throw.
+    pub description: String,
+    /// Where the exception was thrown, with its causes.
+    pub stack_trace: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestResult {
+    /// The name of the test method, or of the before or after method
+    /// failing outside of any test.
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+}
+
+/// Runs the tests of the class, given by its internal name, the way JUnit
+/// does: each one on a new instance created by the constructor without
+/// parameters, between the before and after methods. An exception escaping
+/// a before-all method fails every enabled test.
+pub fn run_tests(vm: &mut Vm, class: &str) -> Result<Vec<TestResult>, VmError> {
+    let plan = test_plan(vm, class)?;
+    let mut results = Vec::new();
+
+    for method in &plan.before_all {
+        let started = Instant::now();
+        if let Some(exception) = thrown(vm.invoke_static(class, method, "()V", &[]))? {
+            let failure = failure(vm, &exception);
+            vm.release(exception.object);
+            results.extend(plan.tests.iter().map(|test| TestResult {
+                name: test.name.clone(),
+                outcome: if test.disabled {
+                    TestOutcome::Skipped
+                } else {
+                    TestOutcome::Errored(failure.clone())
+                },
+                duration: started.elapsed(),
+            }));
+            return Ok(results);
+        }
+    }
+
+    for test in &plan.tests {
+        let started = Instant::now();
+        let outcome = if test.disabled {
+            TestOutcome::Skipped
+        } else {
+            run_test(vm, class, &plan, test)?
+        };
+        results.push(TestResult {
+            name: test.name.clone(),
+            outcome,
+            duration: started.elapsed(),
+        });
+    }
+
+    for method in &plan.after_all {
+        let started = Instant::now();
+        if let Some(exception) = thrown(vm.invoke_static(class, method, "()V", &[]))? {
+            let outcome = outcome(vm, &exception);
+            vm.release(exception.object);
+            results.push(TestResult {
+                name: method.clone(),
+                outcome,
+                duration: started.elapsed(),
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// The plan of the class, discovered from the class files of the class and
+/// its superclasses.
+fn test_plan(vm: &mut Vm, class: &str) -> Result<TestPlan, VmError> {
+    let mut current = Some(vm.find_class(class)?);
+    let mut hierarchy: Vec<Arc<LoadedClass>> = Vec::new();
+    while let Some(class) = current {
+        let class = vm.class(class);
+        match &class.source {
+            Some(source) => hierarchy.push(source.clone()),
+            None => break,
+        }
+        current = class.super_class;
+    }
+    let classes: Vec<&Class> = hierarchy.iter().map(|loaded| &loaded.class).collect();
+    Ok(TestPlan::discover(&classes)?)
+}
+
+fn run_test(
+    vm: &mut Vm,
+    class: &str,
+    plan: &TestPlan,
+    test: &TestMethod,
+) -> Result<TestOutcome, VmError> {
+    let instance = match vm.new_object(class, "()V", &[]) {
+        Ok(instance) => instance,
+        Err(VmError::Exception(exception)) => {
+            let outcome = TestOutcome::Errored(failure(vm, &exception));
+            vm.release(exception.object);
+            return Ok(outcome);
+        }
+        Err(error) => return Err(error),
+    };
+
+    let mut before = None;
+    for method in &plan.before_each {
+        before = thrown(vm.invoke_method(instance, method, "()V", &[]))?;
+        if before.is_some() {
+            break;
+        }
+    }
+    let ran = match before {
+        Some(exception) => Err(exception),
+        None => Ok(thrown(vm.invoke_method(instance, &test.name, "()V", &[]))?),
+    };
+    // The after methods run even when the test failed, the first exception
+    // they throw failing a passed test
+    let mut after = Vec::new();
+    for method in &plan.after_each {
+        after.extend(thrown(vm.invoke_method(instance, method, "()V", &[]))?);
+    }
+    vm.release(instance);
+
+    let failed = match &ran {
+        Err(exception) => Some(outcome(vm, exception)),
+        Ok(Some(exception)) => match &test.expected {
+            None => Some(outcome(vm, exception)),
+            Some(expected) if is_instance(vm, exception.object, expected) => None,
+            Some(expected) => Some(TestOutcome::Errored(TestFailure {
+                description: format!(
+                    "Unexpected exception, expected<{}> but was<{}>",
+                    expected.replace('/', "."),
+                    exception.class_name
+                ),
+                stack_trace: vm.stack_trace(exception.object).ok(),
+            })),
+        },
+        Ok(None) => test.expected.as_ref().map(|expected| {
+            TestOutcome::Failed(TestFailure {
+                description: format!("Expected exception: {}", expected.replace('/', ".")),
+                stack_trace: None,
+            })
+        }),
+    };
+    let outcome = match (failed, after.first()) {
+        (Some(outcome), _) => outcome,
+        (None, Some(exception)) => outcome(vm, exception),
+        (None, None) => TestOutcome::Passed,
+    };
+
+    let thrown = match ran {
+        Err(exception) => Some(exception),
+        Ok(exception) => exception,
+    };
+    for exception in thrown.into_iter().chain(after) {
+        vm.release(exception.object);
+    }
+    Ok(outcome)
+}
+
+/// The exception escaping the Java code, telling it from the errors of the
+/// VM, which abort the run.
+fn thrown<T>(result: Result<T, VmError>) -> Result<Option<JavaException>, VmError> {
+    match result {
+        Ok(_) => Ok(None),
+        Err(VmError::Exception(exception)) => Ok(Some(exception)),
+        Err(error) => Err(error),
+    }
+}
+
+fn outcome(vm: &mut Vm, exception: &JavaException) -> TestOutcome {
+    let failure = failure(vm, exception);
+    if is_instance(vm, exception.object, "java/lang/AssertionError") {
+        TestOutcome::Failed(failure)
+    } else {
+        TestOutcome::Errored(failure)
+    }
+}
+
+fn failure(vm: &mut Vm, exception: &JavaException) -> TestFailure {
+    TestFailure {
+        description: exception.to_string(),
+        stack_trace: vm.stack_trace(exception.object).ok(),
+    }
+}
+
+/// Whether the object is an instance of the class, which it is not if the
+/// class cannot be loaded.
+fn is_instance(vm: &mut Vm, object: ObjectRef, class: &str) -> bool {
+    match vm.find_class(class) {
+        Ok(class) => vm.is_instance(object, class),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Bounds on the resources guest code may consume over the lifetime of a VM.
+/// Exceeding one aborts the running code with [`VmError::LimitExceeded`],
+/// without running exception handlers or shutdown hooks.
+///
+/// [`VmError::LimitExceeded`]: crate::vm::VmError::LimitExceeded
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionLimits {
+    max_instructions: Option<u64>,
+    max_duration: Option<Duration>,
+    max_heap_bytes: Option<usize>,
+}
+
+impl ExecutionLimits {
+    /// Limits the number of executed instructions.
+    pub fn max_instructions(mut self, instructions: u64) -> Self {
+        self.max_instructions = Some(instructions);
+        self
+    }
+
+    /// Limits the wall-clock time spent since the VM was built.
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Limits the estimated size of the heap.
+    pub fn max_heap_bytes(mut self, bytes: usize) -> Self {
+        self.max_heap_bytes = Some(bytes);
+        self
+    }
+
+    pub(crate) fn instructions(&self) -> Option<u64> {
+        self.max_instructions
+    }
+
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        self.max_duration
+    }
+
+    pub(crate) fn heap_bytes(&self) -> Option<usize> {
+        self.max_heap_bytes
+    }
+}
+
+/// The limit exceeded by guest code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Instructions(u64),
+    Duration(Duration),
+    HeapBytes(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Instructions(max) => write!(f, "instruction budget of {} exhausted", max),
+            Limit::Duration(max) => write!(f, "execution time limit of {:?} exceeded", max),
+            Limit::HeapBytes(max) => write!(f, "heap limit of {} bytes exceeded", max),
+        }
+    }
+}
@@ -0,0 +1,1517 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::task::Poll;
+
+use crate::class::attributes::Attribute;
+use crate::class::constant_pool::Constant;
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::instruction::{self, DecodedCode};
+use crate::class::{
+    Class, ClassAccessFlags, ClassLoadingError, FieldAccessFlags, MethodAccessFlags,
+};
+use crate::packaging::source::poll_reads;
+use crate::vm::archive::ArchivedLinks;
+use crate::vm::class_cache::{read_string, write_string, Cached};
+use crate::vm::heap::{ArrayData, HeapObject, NativeData, ObjectData};
+use crate::vm::inference::infer_frames;
+use crate::vm::loader::{LoadedClass, LoaderId};
+use crate::vm::natives::BuiltinClass;
+use crate::vm::runtime::{
+    ClassId, ClassKind, ExceptionHandler, InitState, MethodCode, ReferenceKind, RuntimeClass,
+    RuntimeField, RuntimeMethod,
+};
+use crate::vm::symbol::Symbol;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+// =============================================================================
+// LOADING
+// =============================================================================
+
+impl Vm {
+    /// Loads and links the class through the initiating loader, throwing
+    /// `NoClassDefFoundError` if no loader can provide it. Built-in classes are
+    /// always defined by the bootstrap loader, shadowing the JDK's own, while
+    /// the fallback ones are only defined when no loader provides the class.
+    pub(crate) fn load_class(
+        &mut self,
+        initiating: LoaderId,
+        name: &str,
+    ) -> Result<ClassId, Unwind> {
+        let symbol = self.symbols.intern(name);
+        if let Some(id) = self.loaded.get(&(initiating, symbol.clone())) {
+            return Ok(*id);
+        }
+
+        let id = if name.starts_with('[') {
+            self.define_array(initiating, name)?
+        } else if let Some(builtin) = self.natives.builtin(name).cloned() {
+            match self.loaded.get(&(LoaderId::BOOTSTRAP, symbol.clone())) {
+                Some(id) => *id,
+                None => self.define_builtin(builtin)?,
+            }
+        } else {
+            match self.read_class(initiating, name)? {
+                Ok(Some(loaded)) => {
+                    match self.loaded.get(&(loaded.defining_loader, symbol.clone())) {
+                        Some(id) => *id,
+                        None => self.define_loaded(loaded)?,
+                    }
+                }
+                Ok(None) => match self.natives.fallback(name).cloned() {
+                    Some(fallback) => match self.loaded.get(&(LoaderId::BOOTSTRAP, symbol.clone()))
+                    {
+                        Some(id) => *id,
+                        None => self.define_builtin(fallback)?,
+                    },
+                    None => {
+                        return Err(self
+                            .throw_new("java/lang/NoClassDefFoundError", Some(name.to_string())))
+                    }
+                },
+                Err(error) => {
+                    return Err(
+                        self.throw_new("java/lang/ClassFormatError", Some(error.to_string()))
+                    )
+                }
+            }
+        };
+
+        self.loaded.insert((initiating, symbol), id);
+        Ok(id)
+    }
+
+    /// Reads the class through the loaders. While an asynchronous class
+    /// source reads it, the started threads run, unless a class is being
+    /// linked as they could then see it half defined.
+    fn read_class(
+        &mut self,
+        initiating: LoaderId,
+        name: &str,
+    ) -> Result<Result<Option<Arc<LoadedClass>>, ClassLoadingError>, Unwind> {
+        while self.linking.is_empty() && !self.started_threads.is_empty() {
+            let loaders = &self.loaders;
+            match poll_reads(|| loaders.load_class(initiating, name)) {
+                Poll::Ready(result) => return Ok(result),
+                Poll::Pending => self.run_started_threads()?,
+            }
+        }
+        Ok(self.loaders.load_class(initiating, name))
+    }
+
+    /// Adds the class to the VM, recording it as defined by its loader.
+    fn register(&mut self, mut class: RuntimeClass) -> ClassId {
+        let id = ClassId(self.classes.len() as u32);
+        class.id = id;
+        class.reference = match class.name.as_str() {
+            "java/lang/ref/SoftReference" => Some(ReferenceKind::Soft),
+            "java/lang/ref/WeakReference" => Some(ReferenceKind::Weak),
+            "java/lang/ref/PhantomReference" => Some(ReferenceKind::Phantom),
+            _ => class
+                .super_class
+                .and_then(|super_class| self.class(super_class).reference),
+        };
+        self.loaded
+            .insert((class.defining_loader, class.name.clone()), id);
+        self.classes.push(class);
+        id
+    }
+
+    /// The superclass and superinterfaces, loaded through the defining loader
+    /// of the class.
+    fn load_supertypes(
+        &mut self,
+        loader: LoaderId,
+        super_class: Option<&str>,
+        interfaces: &[&str],
+    ) -> Result<(Option<ClassId>, Vec<ClassId>), Unwind> {
+        let super_class = match super_class {
+            Some(name) => Some(self.load_class(loader, name)?),
+            None => None,
+        };
+        let interfaces = interfaces
+            .iter()
+            .map(|name| self.load_class(loader, name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((super_class, interfaces))
+    }
+
+    /// Throws `IncompatibleClassChangeError` if the superclass is an
+    /// interface or final, or a superinterface is a class, and
+    /// `IllegalAccessError` if one of them is inaccessible to the class being
+    /// defined (JVMS 5.3.5).
+    fn check_supertypes(
+        &mut self,
+        name: &str,
+        loader: LoaderId,
+        super_class: Option<ClassId>,
+        interfaces: &[ClassId],
+    ) -> Result<(), Unwind> {
+        let java_name = name.replace('/', ".");
+        let accessible = |supertype: &RuntimeClass| {
+            (supertype.access_flags.contains(ClassAccessFlags::PUBLIC)
+                && self.modules.check_access(name, &supertype.name).is_ok())
+                || (supertype.defining_loader == loader
+                    && package_of(&supertype.name) == package_of(name))
+        };
+
+        let mut error = None;
+        if let Some(super_class) = super_class.map(|id| self.class(id)) {
+            let super_name = super_class.java_name();
+            if super_class.is_interface() {
+                error = Some((
+                    "java/lang/IncompatibleClassChangeError",
+                    format!(
+                        "class {} has interface {} as super class",
+                        java_name, super_name
+                    ),
+                ));
+            } else if super_class.access_flags.contains(ClassAccessFlags::FINAL) {
+                error = Some((
+                    "java/lang/IncompatibleClassChangeError",
+                    format!(
+                        "class {} cannot inherit from final class {}",
+                        java_name, super_name
+                    ),
+                ));
+            } else if !accessible(super_class) {
+                error = Some((
+                    "java/lang/IllegalAccessError",
+                    format!(
+                        "class {} cannot access its superclass {}",
+                        java_name, super_name
+                    ),
+                ));
+            }
+        }
+        for interface in interfaces.iter().map(|&id| self.class(id)) {
+            if error.is_some() {
+                break;
+            }
+            let interface_name = interface.java_name();
+            if !interface.is_interface() {
+                error = Some((
+                    "java/lang/IncompatibleClassChangeError",
+                    format!(
+                        "class {} can not implement {}, because it is not an interface",
+                        java_name, interface_name
+                    ),
+                ));
+            } else if !accessible(interface) {
+                error = Some((
+                    "java/lang/IllegalAccessError",
+                    format!(
+                        "class {} cannot access its superinterface {}",
+                        java_name, interface_name
+                    ),
+                ));
+            }
+        }
+
+        match error {
+            Some((class, message)) => Err(self.throw_new(class, Some(message))),
+            None => Ok(()),
+        }
+    }
+
+    fn define_loaded(&mut self, loaded: Arc<LoadedClass>) -> Result<ClassId, Unwind> {
+        let class = &loaded.class;
+        let constant_pool = &class.constant_pool;
+        let loader = loaded.defining_loader;
+
+        let super_class = class.super_class_name().map_err(VmError::from)?;
+        let interfaces = class
+            .interfaces
+            .iter()
+            .map(|interface| constant_pool.get_class_name(interface.interface_index))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(VmError::from)?;
+        // Loading a supertype which extends the class itself comes back here
+        let key = (loader, self.symbols.intern(&loaded.name));
+        if !self.linking.insert(key.clone()) {
+            let name = loaded.name.replace('/', ".");
+            return Err(self.throw_new("java/lang/ClassCircularityError", Some(name)));
+        }
+        let supertypes = self.load_supertypes(loader, super_class, &interfaces);
+        self.linking.remove(&key);
+        let (super_class, interfaces) = supertypes?;
+        self.check_supertypes(&loaded.name, loader, super_class, &interfaces)?;
+
+        let id = ClassId(self.classes.len() as u32);
+        let mut instance_fields = match super_class {
+            Some(super_class) => self.class(super_class).instance_fields.clone(),
+            None => Vec::new(),
+        };
+        let mut static_values = Vec::new();
+
+        let mut fields = Vec::with_capacity(class.fields.len());
+        for field in &class.fields {
+            let descriptor = constant_pool
+                .get_utf8(field.descriptor_index)
+                .map_err(VmError::from)?;
+            let field_type = FieldType::parse(descriptor).map_err(VmError::from)?;
+            let is_static = field.access_flags.contains(FieldAccessFlags::STATIC);
+            let values = if is_static {
+                &mut static_values
+            } else {
+                &mut instance_fields
+            };
+            values.push(Value::default_for(&field_type));
+
+            let constant_value = field
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::ConstantValue(value) if is_static => Some(value.const_value_index),
+                    _ => None,
+                });
+
+            fields.push(Arc::new(RuntimeField {
+                class: id,
+                name: self.symbols.intern(
+                    constant_pool
+                        .get_utf8(field.name_index)
+                        .map_err(VmError::from)?,
+                ),
+                descriptor: self.symbols.intern(descriptor),
+                field_type,
+                access_flags: field.access_flags,
+                slot: values.len() - 1,
+                constant_value,
+            }));
+        }
+
+        let mut methods = Vec::with_capacity(class.methods.len());
+        for method in &class.methods {
+            let name = constant_pool
+                .get_utf8(method.name_index)
+                .map_err(VmError::from)?;
+            let descriptor = constant_pool
+                .get_utf8(method.descriptor_index)
+                .map_err(VmError::from)?;
+            let native = if method.access_flags.contains(MethodAccessFlags::NATIVE) {
+                self.natives.lookup(&loaded.name, name, descriptor)
+            } else if loaded.defining_loader == LoaderId::BOOTSTRAP {
+                self.natives.intrinsic(&loaded.name, name, descriptor)
+            } else {
+                None
+            };
+            let permission = native.and_then(|_| self.natives.permission(&loaded.name, name));
+
+            methods.push(Arc::new(RuntimeMethod {
+                class: id,
+                name: self.symbols.intern(name),
+                descriptor: self.symbols.intern(descriptor),
+                parsed_descriptor: MethodDescriptor::parse(descriptor).map_err(VmError::from)?,
+                access_flags: method.access_flags,
+                code: self
+                    .method_code(&loaded, &method.attributes)
+                    .map_err(VmError::from)?,
+                native,
+                permission,
+            }));
+        }
+
+        if let Some(log) = &mut self.class_log {
+            log.loaded(&loaded).map_err(VmError::from)?;
+        }
+        let name = self.symbols.intern(&loaded.name);
+        let vtable = match &loaded.links {
+            Some(links) => self.link_vtable(id, &name, super_class, &interfaces, links),
+            None => HashMap::new(),
+        };
+        let id = self.register(RuntimeClass {
+            id,
+            name,
+            defining_loader: loader,
+            access_flags: class.access_flags,
+            kind: ClassKind::Instance,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            instance_fields,
+            static_values,
+            state: InitState::Linked,
+            source: Some(loaded.clone()),
+            mirror: None,
+            reference: None,
+            vtable,
+        });
+        self.fire_class_loaded(id);
+        Ok(id)
+    }
+
+    /// The vtable of a class being defined from its archived one, mapping the
+    /// declaring classes to the class and its supertypes.
+    fn link_vtable(
+        &mut self,
+        id: ClassId,
+        name: &Symbol,
+        super_class: Option<ClassId>,
+        interfaces: &[ClassId],
+        links: &ArchivedLinks,
+    ) -> HashMap<(Symbol, Symbol), ClassId> {
+        let mut hierarchy = vec![(name.clone(), id)];
+        for supertype in super_class.iter().chain(interfaces) {
+            for supertype in self.supertypes(*supertype) {
+                hierarchy.push((self.class(supertype).name.clone(), supertype));
+            }
+        }
+
+        let mut vtable = HashMap::with_capacity(links.vtable.len());
+        for (method, descriptor, declaring) in &links.vtable {
+            if let Some((_, class)) = hierarchy
+                .iter()
+                .find(|(name, _)| name == declaring.as_str())
+            {
+                let key = (self.symbols.intern(method), self.symbols.intern(descriptor));
+                vtable.insert(key, *class);
+            }
+        }
+        vtable
+    }
+
+    /// Extracts the `Code` attribute of a method, dereferencing the catch
+    /// types of its exception handlers.
+    pub(crate) fn method_code(
+        &mut self,
+        loaded: &LoadedClass,
+        attributes: &[Attribute],
+    ) -> Result<Option<MethodCode>, ClassLoadingError> {
+        let code = match attributes.iter().find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        }) {
+            Some(code) => code,
+            None => return Ok(None),
+        };
+
+        let exception_handlers = code
+            .exception_tables
+            .iter()
+            .map(|entry| {
+                let catch_type = match entry.catch_type {
+                    0 => None,
+                    index => Some(
+                        self.symbols
+                            .intern(loaded.class.constant_pool.get_class_name(index)?),
+                    ),
+                };
+                Ok(ExceptionHandler {
+                    start_pc: entry.start_pc,
+                    end_pc: entry.end_pc,
+                    handler_pc: entry.handler_pc,
+                    catch_type,
+                })
+            })
+            .collect::<Result<Vec<_>, ClassLoadingError>>()?;
+        let line_numbers = code
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::LineNumberTable(line_numbers) => Some(line_numbers),
+                _ => None,
+            })
+            .flatten()
+            .cloned()
+            .collect();
+
+        let DecodedCode {
+            mut instructions,
+            pcs: instruction_pcs,
+            switches,
+        } = instruction::decode(&code.code);
+        self.allocate_inline_caches(&mut instructions);
+        Ok(Some(MethodCode {
+            max_stack: code.max_stack,
+            max_locals: code.max_locals,
+            code: code.code.clone(),
+            instructions,
+            instruction_pcs,
+            switches,
+            exception_handlers,
+            line_numbers,
+        }))
+    }
+
+    fn define_builtin(&mut self, builtin: BuiltinClass) -> Result<ClassId, Unwind> {
+        let (super_class, interfaces) = self.load_supertypes(
+            LoaderId::BOOTSTRAP,
+            builtin.super_class,
+            &builtin.interfaces,
+        )?;
+
+        let id = ClassId(self.classes.len() as u32);
+        let mut instance_fields = match super_class {
+            Some(super_class) => self.class(super_class).instance_fields.clone(),
+            None => Vec::new(),
+        };
+        let mut static_values = Vec::new();
+
+        let mut fields = Vec::with_capacity(builtin.fields.len());
+        for field in &builtin.fields {
+            let field_type = FieldType::parse(field.descriptor).map_err(VmError::from)?;
+            let values = if field.access_flags.contains(FieldAccessFlags::STATIC) {
+                &mut static_values
+            } else {
+                &mut instance_fields
+            };
+            values.push(Value::default_for(&field_type));
+
+            fields.push(Arc::new(RuntimeField {
+                class: id,
+                name: self.symbols.intern(field.name),
+                descriptor: self.symbols.intern(field.descriptor),
+                field_type,
+                access_flags: field.access_flags,
+                slot: values.len() - 1,
+                constant_value: None,
+            }));
+        }
+
+        let mut methods = Vec::with_capacity(builtin.methods.len());
+        for method in &builtin.methods {
+            methods.push(Arc::new(RuntimeMethod {
+                class: id,
+                name: self.symbols.intern(method.name),
+                descriptor: self.symbols.intern(method.descriptor),
+                parsed_descriptor: MethodDescriptor::parse(method.descriptor)
+                    .map_err(VmError::from)?,
+                access_flags: method.access_flags,
+                code: None,
+                native: self
+                    .natives
+                    .lookup(builtin.name, method.name, method.descriptor),
+                permission: self.natives.permission(builtin.name, method.name),
+            }));
+        }
+
+        if let Some(log) = &mut self.class_log {
+            log.builtin(builtin.name).map_err(VmError::from)?;
+        }
+        let name = self.symbols.intern(builtin.name);
+        let id = self.register(RuntimeClass {
+            id,
+            name,
+            defining_loader: LoaderId::BOOTSTRAP,
+            access_flags: builtin.access_flags,
+            kind: ClassKind::Instance,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            instance_fields,
+            static_values,
+            state: InitState::Linked,
+            source: None,
+            mirror: None,
+            reference: None,
+            vtable: HashMap::new(),
+        });
+        self.fire_class_loaded(id);
+        Ok(id)
+    }
+
+    /// Creates an array class. Arrays of references are defined by the loader
+    /// of their element class, arrays of primitives by the bootstrap loader.
+    fn define_array(&mut self, initiating: LoaderId, name: &str) -> Result<ClassId, Unwind> {
+        let component = match FieldType::parse(&name[1..]) {
+            Ok(component) => component,
+            Err(error) => {
+                return Err(
+                    self.throw_new("java/lang/NoClassDefFoundError", Some(error.to_string()))
+                )
+            }
+        };
+        let loader = match &component {
+            FieldType::Object(element) => {
+                let element = self.load_class(initiating, element)?;
+                self.class(element).defining_loader
+            }
+            FieldType::Array(_) => {
+                let element = self.load_class(initiating, &name[1..])?;
+                self.class(element).defining_loader
+            }
+            _ => LoaderId::BOOTSTRAP,
+        };
+        let name = self.symbols.intern(name);
+        if let Some(id) = self.loaded.get(&(loader, name.clone())) {
+            return Ok(*id);
+        }
+
+        let (super_class, interfaces) = self.load_supertypes(
+            LoaderId::BOOTSTRAP,
+            Some("java/lang/Object"),
+            &["java/lang/Cloneable", "java/io/Serializable"],
+        )?;
+
+        Ok(self.register(RuntimeClass {
+            id: ClassId(0),
+            name,
+            defining_loader: loader,
+            access_flags: ClassAccessFlags::PUBLIC
+                | ClassAccessFlags::FINAL
+                | ClassAccessFlags::ABSTRACT,
+            kind: ClassKind::Array(component),
+            super_class,
+            interfaces,
+            fields: Vec::new(),
+            methods: Vec::new(),
+            instance_fields: Vec::new(),
+            static_values: Vec::new(),
+            state: InitState::Initialized,
+            source: None,
+            mirror: None,
+            reference: None,
+            vtable: HashMap::new(),
+        }))
+    }
+
+    /// The class of a primitive type, e.g. `int`, or of `void`.
+    pub(crate) fn primitive_class(&mut self, name: &str) -> Result<ClassId, Unwind> {
+        const PRIMITIVES: [&str; 9] = [
+            "boolean", "byte", "char", "short", "int", "long", "float", "double", "void",
+        ];
+        if !PRIMITIVES.contains(&name) {
+            return Err(self.throw_new(
+                "java/lang/IllegalArgumentException",
+                Some(format!("Not a primitive type: {}", name)),
+            ));
+        }
+        let name = self.symbols.intern(name);
+        if let Some(id) = self.loaded.get(&(LoaderId::BOOTSTRAP, name.clone())) {
+            return Ok(*id);
+        }
+
+        Ok(self.register(RuntimeClass {
+            id: ClassId(0),
+            name,
+            defining_loader: LoaderId::BOOTSTRAP,
+            access_flags: ClassAccessFlags::PUBLIC
+                | ClassAccessFlags::FINAL
+                | ClassAccessFlags::ABSTRACT,
+            kind: ClassKind::Primitive,
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            instance_fields: Vec::new(),
+            static_values: Vec::new(),
+            state: InitState::Initialized,
+            source: None,
+            mirror: None,
+            reference: None,
+            vtable: HashMap::new(),
+        }))
+    }
+
+    /// The `java.lang.Class` object of the class.
+    pub(crate) fn mirror(&mut self, class: ClassId) -> Result<ObjectRef, Unwind> {
+        if let Some(mirror) = self.class(class).mirror {
+            return Ok(mirror);
+        }
+
+        let class_class = self.load_class(LoaderId::BOOTSTRAP, "java/lang/Class")?;
+        let mirror = self.instantiate(class_class)?;
+        self.heap.get_mut(mirror).native = NativeData::Class(class);
+        self.class_mut(class).mirror = Some(mirror);
+        Ok(mirror)
+    }
+
+    /// Allocates an array of the given array class, e.g. `[I`.
+    pub(crate) fn allocate_array(
+        &mut self,
+        class: &str,
+        data: ArrayData,
+    ) -> Result<ObjectRef, Unwind> {
+        let class = self.load_class(LoaderId::BOOTSTRAP, class)?;
+        Ok(self.allocate(HeapObject {
+            class,
+            data: ObjectData::Array(data),
+            native: NativeData::None,
+            identity_hash: None,
+        }))
+    }
+}
+
+// =============================================================================
+// RESOLUTION
+// =============================================================================
+
+impl Vm {
+    /// The symbols of a member's name and descriptor, `None` if they were
+    /// never interned and so name no member of a loaded class.
+    pub(crate) fn member_symbols(&self, name: &str, descriptor: &str) -> Option<(Symbol, Symbol)> {
+        Some((self.symbols.get(name)?, self.symbols.get(descriptor)?))
+    }
+
+    /// Looks up a field in the class, its superinterfaces, then its
+    /// superclasses (JVMS 5.4.3.2).
+    pub(crate) fn resolve_field(
+        &self,
+        class: ClassId,
+        name: &Symbol,
+        descriptor: &Symbol,
+    ) -> Option<Arc<RuntimeField>> {
+        let runtime_class = self.class(class);
+        if let Some(field) = runtime_class.field(name, descriptor) {
+            return Some(field.clone());
+        }
+        for interface in &runtime_class.interfaces {
+            if let Some(field) = self.resolve_field(*interface, name, descriptor) {
+                return Some(field);
+            }
+        }
+
+        self.resolve_field(runtime_class.super_class?, name, descriptor)
+    }
+
+    /// Looks up a method in the class and its superclasses, then in its
+    /// superinterfaces (JVMS 5.4.3.3).
+    pub(crate) fn resolve_method(
+        &self,
+        class: ClassId,
+        name: &Symbol,
+        descriptor: &Symbol,
+    ) -> Option<Arc<RuntimeMethod>> {
+        let mut current = Some(class);
+        while let Some(id) = current {
+            if let Some(method) = self.class(id).method(name, descriptor) {
+                return Some(method.clone());
+            }
+            current = self.class(id).super_class;
+        }
+
+        self.find_interface_method(class, name, descriptor)
+    }
+
+    /// Selects the implementation invoked by `invokevirtual` and
+    /// `invokeinterface` on an instance of the class (JVMS 5.4.6), through
+    /// its vtable if it was linked from a class archive.
+    pub(crate) fn find_virtual(
+        &self,
+        class: ClassId,
+        name: &Symbol,
+        descriptor: &Symbol,
+    ) -> Option<Arc<RuntimeMethod>> {
+        let vtable = &self.class(class).vtable;
+        if let Some(&declaring) = vtable.get(&(name.clone(), descriptor.clone())) {
+            let method = self.class(declaring).method(name, descriptor);
+            if let Some(method) = method.filter(|method| !method.is_static()) {
+                return Some(method.clone());
+            }
+        }
+
+        let mut current = Some(class);
+        while let Some(id) = current {
+            if let Some(method) = self.class(id).method(name, descriptor) {
+                if !method.is_static() && !method.is_abstract() {
+                    return Some(method.clone());
+                }
+            }
+            current = self.class(id).super_class;
+        }
+
+        self.find_interface_method(class, name, descriptor)
+            .filter(|method| !method.is_abstract())
+    }
+
+    /// Finds the method in the superinterfaces of the class and its
+    /// superclasses, preferring default methods over abstract ones.
+    fn find_interface_method(
+        &self,
+        class: ClassId,
+        name: &Symbol,
+        descriptor: &Symbol,
+    ) -> Option<Arc<RuntimeMethod>> {
+        let mut pending = vec![class];
+        let mut found: Option<Arc<RuntimeMethod>> = None;
+        while let Some(id) = pending.pop() {
+            let runtime_class = self.class(id);
+            if id != class && runtime_class.is_interface() {
+                if let Some(method) = runtime_class.method(name, descriptor) {
+                    if !method.is_static() && !method.is_private() {
+                        if !method.is_abstract() {
+                            return Some(method.clone());
+                        }
+                        found.get_or_insert_with(|| method.clone());
+                    }
+                }
+            }
+            pending.extend(runtime_class.interfaces.iter().rev());
+            pending.extend(runtime_class.super_class);
+        }
+
+        found
+    }
+
+    /// The class followed by all its superclasses and superinterfaces, each
+    /// once.
+    pub(crate) fn supertypes(&self, class: ClassId) -> Vec<ClassId> {
+        let mut supertypes = Vec::new();
+        let mut pending = vec![class];
+        while let Some(id) = pending.pop() {
+            if !supertypes.contains(&id) {
+                supertypes.push(id);
+                let runtime_class = self.class(id);
+                pending.extend(runtime_class.interfaces.iter().rev());
+                pending.extend(runtime_class.super_class);
+            }
+        }
+        supertypes
+    }
+
+    /// The class declaring the member the reference of the class resolved to
+    /// when the class was archived, looked up through the loader of the
+    /// class owning the reference.
+    fn archived_resolution(
+        &mut self,
+        class: ClassId,
+        index: u16,
+        owner: ClassId,
+    ) -> Option<ClassId> {
+        let source = self.class(class).source.clone()?;
+        let resolved = &source.links.as_ref()?.resolved;
+        let position = resolved
+            .binary_search_by_key(&index, |(index, _)| *index)
+            .ok()?;
+        let declaring = self.symbols.intern(&resolved[position].1);
+        let loader = self.class(owner).defining_loader;
+        self.loaded.get(&(loader, declaring)).copied()
+    }
+
+    /// The loader resolving symbolic references made by the class.
+    fn loader_of(&self, class: ClassId) -> LoaderId {
+        self.class(class).defining_loader
+    }
+
+    pub(crate) fn constant_pool_of(&self, class: ClassId) -> Result<Arc<LoadedClass>, Unwind> {
+        self.class(class).source.clone().ok_or_else(|| {
+            Unwind::Error(VmError::Internal(format!(
+                "{} has no constant pool",
+                self.class(class).name
+            )))
+        })
+    }
+
+    /// Resolves a class constant of the class' constant pool.
+    pub(crate) fn resolve_class_ref(
+        &mut self,
+        class: ClassId,
+        index: u16,
+    ) -> Result<ClassId, Unwind> {
+        let source = self.constant_pool_of(class)?;
+        let name = source
+            .class
+            .constant_pool
+            .get_class_name(index)
+            .map_err(VmError::from)?;
+        let resolved = self.load_class(self.loader_of(class), name)?;
+        self.check_class_access(class, resolved)?;
+        Ok(resolved)
+    }
+
+    /// Dereferences a field or method reference to its class, name and
+    /// descriptor.
+    fn member_ref(
+        &mut self,
+        source: &LoadedClass,
+        index: u16,
+    ) -> Result<(u16, Symbol, Symbol), Unwind> {
+        let constant_pool = &source.class.constant_pool;
+        let reference = match constant_pool.get(index as usize) {
+            Some(Constant::Field(reference))
+            | Some(Constant::Method(reference))
+            | Some(Constant::InterfaceMethod(reference)) => reference,
+            _ => {
+                return Err(Unwind::Error(VmError::Internal(format!(
+                    "Constant #{} of {} is not a member reference",
+                    index, source.name
+                ))))
+            }
+        };
+        let name_and_type = match constant_pool.get(reference.name_and_type_index as usize) {
+            Some(Constant::NameAndType(name_and_type)) => name_and_type,
+            _ => {
+                return Err(Unwind::Error(VmError::Internal(format!(
+                    "Constant #{} of {} is not a name and type",
+                    reference.name_and_type_index, source.name
+                ))))
+            }
+        };
+
+        let name = constant_pool
+            .get_utf8(name_and_type.name_index)
+            .map_err(VmError::from)?;
+        let descriptor = constant_pool
+            .get_utf8(name_and_type.descriptor_index)
+            .map_err(VmError::from)?;
+        Ok((
+            reference.class_index,
+            self.symbols.intern(name),
+            self.symbols.intern(descriptor),
+        ))
+    }
+
+    /// Resolves a field reference of the class' constant pool.
+    pub(crate) fn resolve_field_ref(
+        &mut self,
+        class: ClassId,
+        index: u16,
+    ) -> Result<Arc<RuntimeField>, Unwind> {
+        if let Some(field) = self.field_cache.get(&(class, index)) {
+            return Ok(field.clone());
+        }
+
+        let source = self.constant_pool_of(class)?;
+        let (class_index, name, descriptor) = self.member_ref(&source, index)?;
+        let owner = self.resolve_class_ref(class, class_index)?;
+        let archived = self
+            .archived_resolution(class, index, owner)
+            .and_then(|declaring| self.class(declaring).field(&name, &descriptor).cloned());
+        let field = match archived.or_else(|| self.resolve_field(owner, &name, &descriptor)) {
+            Some(field) => field,
+            None => {
+                return Err(self.throw_new("java/lang/NoSuchFieldError", Some(name.to_string())))
+            }
+        };
+        let access = Access::of(field.access_flags.bits());
+        self.check_member_access(class, field.class, access, "field", &name)?;
+
+        self.field_cache.insert((class, index), field.clone());
+        Ok(field)
+    }
+
+    /// Resolves a method or interface method reference of the class' constant
+    /// pool.
+    pub(crate) fn resolve_method_ref(
+        &mut self,
+        class: ClassId,
+        index: u16,
+    ) -> Result<Arc<RuntimeMethod>, Unwind> {
+        if let Some(method) = self.method_cache.get(&(class, index)) {
+            return Ok(method.clone());
+        }
+
+        let source = self.constant_pool_of(class)?;
+        let (class_index, name, descriptor) = self.member_ref(&source, index)?;
+        let owner = self.resolve_class_ref(class, class_index)?;
+        // Methods of interfaces go through interface method references only
+        let interface_ref = matches!(
+            source.class.constant_pool.get(index as usize),
+            Some(Constant::InterfaceMethod(_))
+        );
+        if interface_ref != self.class(owner).is_interface() {
+            let message = format!(
+                "Found {} {}, but {} was expected",
+                if interface_ref { "class" } else { "interface" },
+                self.class(owner).java_name(),
+                if interface_ref { "interface" } else { "class" }
+            );
+            return Err(self.throw_new("java/lang/IncompatibleClassChangeError", Some(message)));
+        }
+        let archived = self
+            .archived_resolution(class, index, owner)
+            .and_then(|declaring| self.class(declaring).method(&name, &descriptor).cloned());
+        let method = match archived.or_else(|| self.resolve_method(owner, &name, &descriptor)) {
+            Some(method) => method,
+            None => {
+                let message = format!(
+                    "'{} {}.{}'",
+                    descriptor,
+                    self.class(owner).java_name(),
+                    name
+                );
+                return Err(self.throw_new("java/lang/NoSuchMethodError", Some(message)));
+            }
+        };
+        let access = Access::of(method.access_flags.bits());
+        self.check_member_access(class, method.class, access, "method", &name)?;
+
+        self.method_cache.insert((class, index), method.clone());
+        Ok(method)
+    }
+
+    /// Whether a value of class `from` can be assigned to a variable of class
+    /// `to` (JVMS 6.5 `checkcast`).
+    pub(crate) fn is_assignable(&self, from: ClassId, to: ClassId) -> bool {
+        if from == to {
+            return true;
+        }
+
+        let (from_class, to_class) = (self.class(from), self.class(to));
+        if let (ClassKind::Array(from_component), ClassKind::Array(to_component)) =
+            (&from_class.kind, &to_class.kind)
+        {
+            return match (from_component, to_component) {
+                (from_component, to_component) if from_component == to_component => true,
+                (from_component, to_component)
+                    if from_component.is_reference() && to_component.is_reference() =>
+                {
+                    match (
+                        self.array_component(from_class, from_component),
+                        self.array_component(to_class, to_component),
+                    ) {
+                        (Some(from), Some(to)) => self.is_assignable(from, to),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+        }
+
+        from_class
+            .interfaces
+            .iter()
+            .any(|interface| self.is_assignable(*interface, to))
+            || from_class
+                .super_class
+                .is_some_and(|super_class| self.is_assignable(super_class, to))
+    }
+
+    /// The already loaded class of the elements of a reference array.
+    pub(crate) fn array_component(
+        &self,
+        array: &RuntimeClass,
+        component: &FieldType,
+    ) -> Option<ClassId> {
+        let name = match component {
+            FieldType::Object(name) => self.symbols.get(name),
+            component => self.symbols.get(&component.to_string()),
+        }?;
+        self.loaded.get(&(array.defining_loader, name)).copied()
+    }
+
+    pub(crate) fn is_instance(&self, object: ObjectRef, class: ClassId) -> bool {
+        self.is_assignable(self.class_of(object), class)
+    }
+
+    /// The links to archive with the classes of the JDK's loaders: the
+    /// declaring classes of the references the VM resolved, and the
+    /// implementations of the virtual methods of each class.
+    pub(crate) fn archived_links(&self) -> HashMap<(LoaderId, String), ArchivedLinks> {
+        let mut links: HashMap<(LoaderId, String), ArchivedLinks> = HashMap::new();
+        for class in &self.classes {
+            let source = match &class.source {
+                Some(source) => source,
+                None => continue,
+            };
+            let loader = class.defining_loader;
+            let defined = match loader {
+                LoaderId::BOOTSTRAP | LoaderId::PLATFORM => {
+                    self.loaders.loader(loader).find_loaded(class.name.as_str())
+                }
+                _ => None,
+            };
+            // Redefined classes archive the class files they were loaded from.
+            if !defined.is_some_and(|defined| Arc::ptr_eq(&defined, source)) {
+                continue;
+            }
+
+            let mut archived = ArchivedLinks::default();
+            let resolved = self
+                .field_cache
+                .iter()
+                .map(|(key, field)| (key, field.class))
+                .chain(
+                    self.method_cache
+                        .iter()
+                        .map(|(key, method)| (key, method.class)),
+                );
+            for (&(owner, index), declaring) in resolved {
+                if owner == class.id {
+                    let declaring = self.class(declaring).name.to_string();
+                    archived.resolved.push((index, declaring));
+                }
+            }
+            archived.resolved.sort();
+
+            if !class.is_interface() {
+                for supertype in self.supertypes(class.id) {
+                    for method in &self.class(supertype).methods {
+                        if method.is_static()
+                            || method.is_private()
+                            || method.name.as_str().starts_with('<')
+                        {
+                            continue;
+                        }
+                        let implementation =
+                            self.find_virtual(class.id, &method.name, &method.descriptor);
+                        if let Some(implementation) = implementation {
+                            archived.vtable.push((
+                                method.name.to_string(),
+                                method.descriptor.to_string(),
+                                self.class(implementation.class).name.to_string(),
+                            ));
+                        }
+                    }
+                }
+                archived.vtable.sort();
+                archived.vtable.dedup();
+            }
+            links.insert((loader, class.name.to_string()), archived);
+        }
+        links
+    }
+}
+
+// =============================================================================
+// ACCESS CONTROL
+// =============================================================================
+
+/// The access a field or method declares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    Public,
+    Protected,
+    Package,
+    Private,
+}
+
+impl Access {
+    /// The access of the flags, which fields and methods store alike.
+    fn of(flags: u16) -> Access {
+        if flags & MethodAccessFlags::PUBLIC.bits() != 0 {
+            Access::Public
+        } else if flags & MethodAccessFlags::PRIVATE.bits() != 0 {
+            Access::Private
+        } else if flags & MethodAccessFlags::PROTECTED.bits() != 0 {
+            Access::Protected
+        } else {
+            Access::Package
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Access::Public => "public",
+            Access::Protected => "protected",
+            Access::Package => "package-private",
+            Access::Private => "private",
+        }
+    }
+}
+
+/// The package of the class, from its internal name.
+pub(crate) fn package_of(name: &str) -> &str {
+    name.rfind('/').map_or("", |end| &name[..end])
+}
+
+impl Vm {
+    /// Whether the classes are in the same runtime package: packages of the
+    /// same name, defined by the same loader (JVMS 5.3).
+    pub(crate) fn same_runtime_package(&self, class: ClassId, other: ClassId) -> bool {
+        let (class, other) = (self.class(class), self.class(other));
+        class.defining_loader == other.defining_loader
+            && package_of(&class.name) == package_of(&other.name)
+    }
+
+    /// Throws `IllegalAccessError` unless the class is in the runtime
+    /// package of the accessor, or public and, in a module, exported to the
+    /// module of the accessor reading it, arrays being as accessible as their
+    /// element type (JVMS 5.4.4).
+    fn check_class_access(&mut self, accessor: ClassId, class: ClassId) -> Result<(), Unwind> {
+        let mut element = class;
+        while let ClassKind::Array(component) = &self.class(element).kind {
+            match self.array_component(self.class(element), component) {
+                Some(component) => element = component,
+                None => return Ok(()),
+            }
+        }
+        let accessed = self.class(element);
+        if accessed.is_primitive() || self.same_runtime_package(accessor, element) {
+            return Ok(());
+        }
+
+        let message = if accessed.access_flags.contains(ClassAccessFlags::PUBLIC) {
+            match self
+                .modules
+                .check_access(&self.class(accessor).name, &accessed.name)
+            {
+                Ok(()) => return Ok(()),
+                Err(reason) => format!(
+                    "class {} cannot access class {} because {}",
+                    self.class(accessor).java_name(),
+                    accessed.java_name(),
+                    reason
+                ),
+            }
+        } else {
+            format!(
+                "failed to access class {} from class {}",
+                self.class(class).java_name(),
+                self.class(accessor).java_name()
+            )
+        };
+        Err(self.throw_new("java/lang/IllegalAccessError", Some(message)))
+    }
+
+    /// Throws `IllegalAccessError` unless the accessor may access a member of
+    /// the declaring class with the access (JVMS 5.4.4): protected ones from
+    /// subclasses and the runtime package, package-private ones from the
+    /// runtime package, and private ones from the nest.
+    fn check_member_access(
+        &mut self,
+        accessor: ClassId,
+        declaring: ClassId,
+        access: Access,
+        kind: &str,
+        name: &str,
+    ) -> Result<(), Unwind> {
+        if self.allows_member_access(accessor, declaring, access) {
+            return Ok(());
+        }
+
+        let message = format!(
+            "class {} tried to access {} {} {}.{}",
+            self.class(accessor).java_name(),
+            access.name(),
+            kind,
+            self.class(declaring).java_name(),
+            name
+        );
+        Err(self.throw_new("java/lang/IllegalAccessError", Some(message)))
+    }
+
+    /// Whether the accessor may access a member of the declaring class with
+    /// the access, see [Vm::check_member_access].
+    fn allows_member_access(
+        &mut self,
+        accessor: ClassId,
+        declaring: ClassId,
+        access: Access,
+    ) -> bool {
+        if accessor == declaring {
+            return true;
+        }
+        match access {
+            Access::Public => true,
+            Access::Protected => {
+                self.same_runtime_package(accessor, declaring)
+                    || self.is_subclass(accessor, declaring)
+            }
+            Access::Package => self.same_runtime_package(accessor, declaring),
+            Access::Private => self.nest_host(accessor) == self.nest_host(declaring),
+        }
+    }
+
+    /// Throws `IllegalAccessException` unless the caller may access, through
+    /// reflection, a member of the declaring class with the access flags:
+    /// the declaring class must be public or in the runtime package of the
+    /// caller, and the member accessible like from the caller's code.
+    pub(crate) fn check_reflective_access(
+        &mut self,
+        caller: ClassId,
+        declaring: ClassId,
+        flags: u16,
+    ) -> Result<(), Unwind> {
+        let public = self
+            .class(declaring)
+            .access_flags
+            .contains(ClassAccessFlags::PUBLIC);
+        let access = Access::of(flags);
+        if caller == declaring
+            || (public || self.same_runtime_package(caller, declaring))
+                && self.allows_member_access(caller, declaring, access)
+        {
+            return Ok(());
+        }
+
+        let message = format!(
+            "class {} cannot access a {} member of class {}",
+            self.class(caller).java_name(),
+            access.name(),
+            self.class(declaring).java_name()
+        );
+        Err(self.throw_new("java/lang/IllegalAccessException", Some(message)))
+    }
+
+    /// Throws `IncompatibleClassChangeError` when an instruction for static
+    /// members resolves to an instance one, or the reverse.
+    pub(crate) fn check_static(
+        &mut self,
+        is_static: bool,
+        expected: bool,
+        kind: &str,
+        owner: ClassId,
+        name: &str,
+    ) -> Result<(), Unwind> {
+        if is_static == expected {
+            return Ok(());
+        }
+        let message = format!(
+            "Expected {} {} {}.{}",
+            if expected { "static" } else { "non-static" },
+            kind,
+            self.class(owner).java_name(),
+            name
+        );
+        Err(self.throw_new("java/lang/IncompatibleClassChangeError", Some(message)))
+    }
+
+    /// Whether the class extends the other one, directly or not.
+    fn is_subclass(&self, class: ClassId, other: ClassId) -> bool {
+        let mut current = self.class(class).super_class;
+        while let Some(id) = current {
+            if id == other {
+                return true;
+            }
+            current = self.class(id).super_class;
+        }
+        false
+    }
+
+    /// The host of the class' nest: the class its `NestHost` attribute names,
+    /// if that one is in the same runtime package and lists the class among
+    /// its `NestMembers`, the class itself otherwise (JVMS 5.4.4).
+    fn nest_host(&mut self, class: ClassId) -> ClassId {
+        let host_name = self.class(class).source.as_ref().and_then(|source| {
+            let pool = &source.class.constant_pool;
+            source
+                .class
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::NestHost(host) => pool.get_class_name(host.host_class_index).ok(),
+                    _ => None,
+                })
+                .map(str::to_string)
+        });
+        let host = match host_name {
+            Some(host_name) => match self.load_class(self.loader_of(class), &host_name) {
+                Ok(host) => host,
+                Err(_) => return class,
+            },
+            None => return class,
+        };
+
+        let name: &str = &self.class(class).name;
+        let listed = self.class(host).source.as_ref().is_some_and(|source| {
+            let pool = &source.class.constant_pool;
+            source
+                .class
+                .attributes
+                .iter()
+                .any(|attribute| match attribute {
+                    Attribute::NestMembers(members) => members
+                        .iter()
+                        .any(|member| pool.get_class_name(member.class_index).ok() == Some(name)),
+                    _ => false,
+                })
+        });
+        if listed && self.same_runtime_package(class, host) {
+            host
+        } else {
+            class
+        }
+    }
+}
+
+// =============================================================================
+// VERIFICATION
+// =============================================================================
+
+/// Infers the types of the code of every method of the class, failing with
+/// the method and the instruction whose operands do not add up.
+pub fn verify(class: &Class) -> Result<(), String> {
+    let pool = &class.constant_pool;
+    for method in &class.methods {
+        let code = match method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            }) {
+            Some(code) => code,
+            None => continue,
+        };
+        if let Err(error) = infer_frames(class, method, code) {
+            return Err(format!(
+                "{}.{}{} at {}",
+                class.name().unwrap_or_default().replace('/', "."),
+                pool.get_utf8(method.name_index).unwrap_or_default(),
+                pool.get_utf8(method.descriptor_index).unwrap_or_default(),
+                error
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The outcome of [verify] for a class, as `bvm verify` caches it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verification {
+    /// The internal name of the class.
+    pub class: String,
+    pub error: Option<String>,
+}
+
+impl Verification {
+    pub fn new(class: &Class) -> Result<Verification, ClassLoadingError> {
+        Ok(Verification {
+            class: class.name()?.to_string(),
+            error: verify(class).err(),
+        })
+    }
+}
+
+impl Cached for Verification {
+    const KIND: &'static str = "verification-1";
+
+    fn write(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        write_string(output, &self.class)?;
+        // Failures always have a message
+        write_string(output, self.error.as_deref().unwrap_or_default())
+    }
+
+    fn read(input: &mut dyn io::Read) -> io::Result<Self> {
+        let class = read_string(input)?;
+        let error = Some(read_string(input)?).filter(|error| !error.is_empty());
+        Ok(Verification { class, error })
+    }
+}
+
+// =============================================================================
+// INITIALIZATION
+// =============================================================================
+
+impl Vm {
+    /// Initializes the class if it was not yet (JVMS 5.5): its superclass
+    /// first, then the constant static fields, then `<clinit>`.
+    pub(crate) fn initialize_class(&mut self, class: ClassId) -> Result<(), Unwind> {
+        match self.class(class).state {
+            // Recursive requests from the initializing thread succeed immediately
+            InitState::Initialized | InitState::Initializing => return Ok(()),
+            InitState::Erroneous => {
+                let message = format!(
+                    "Could not initialize class {}",
+                    self.class(class).java_name()
+                );
+                return Err(self.throw_new("java/lang/NoClassDefFoundError", Some(message)));
+            }
+            InitState::Linked => self.verify_class(class)?,
+        }
+
+        tracing::debug!(
+            class = self.class(class).name.as_str(),
+            "initializing class"
+        );
+        self.class_mut(class).state = InitState::Initializing;
+        let result = self.run_initializers(class);
+        self.class_mut(class).state = match result {
+            Ok(_) => InitState::Initialized,
+            Err(_) => InitState::Erroneous,
+        };
+
+        match result {
+            Err(Unwind::Throw(exception)) => {
+                tracing::debug!(
+                    class = self.class(class).name.as_str(),
+                    "class initialization failed"
+                );
+                Err(self.wrap_initializer_exception(exception))
+            }
+            result => result,
+        }
+    }
+
+    /// Throws `VerifyError` for a class of the application whose code mixes
+    /// up the types of its locals and operand stack, which the interpreter
+    /// takes on trust. Like HotSpot by default, the classes of the bootstrap
+    /// loader are not verified. A class failing verification stays linked,
+    /// so every attempt to initialize it fails the same way.
+    fn verify_class(&mut self, class: ClassId) -> Result<(), Unwind> {
+        let runtime_class = self.class(class);
+        let source = match &runtime_class.source {
+            Some(source) if runtime_class.defining_loader != LoaderId::BOOTSTRAP => source.clone(),
+            _ => return Ok(()),
+        };
+        match verify(&source.class) {
+            Ok(()) => Ok(()),
+            Err(message) => Err(self.throw_new("java/lang/VerifyError", Some(message))),
+        }
+    }
+
+    fn run_initializers(&mut self, class: ClassId) -> Result<(), Unwind> {
+        if !self.class(class).is_interface() {
+            if let Some(super_class) = self.class(class).super_class {
+                self.initialize_class(super_class)?;
+            }
+        }
+
+        let constant_fields: Vec<_> = self
+            .class(class)
+            .fields
+            .iter()
+            .filter_map(|field| Some((field.slot, field.constant_value?)))
+            .collect();
+        for (slot, index) in constant_fields {
+            let value = self.load_constant(class, index)?;
+            self.class_mut(class).static_values[slot] = value;
+        }
+
+        match self
+            .class(class)
+            .declared_method("<clinit>", "()V")
+            .cloned()
+        {
+            Some(initializer) => self.invoke(initializer, &[]).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Exceptions thrown by static initializers reach the code triggering the
+    /// initialization wrapped in `ExceptionInInitializerError`, unless they
+    /// are errors themselves.
+    fn wrap_initializer_exception(&mut self, exception: ObjectRef) -> Unwind {
+        let error = match self.load_class(LoaderId::BOOTSTRAP, "java/lang/Error") {
+            Ok(error) => error,
+            Err(unwind) => return unwind,
+        };
+        if self.is_instance(exception, error) {
+            return Unwind::Throw(exception);
+        }
+
+        match self.throw_new("java/lang/ExceptionInInitializerError", None) {
+            Unwind::Throw(wrapper) => {
+                self.set_field(wrapper, "cause", Value::Reference(Some(exception)));
+                Unwind::Throw(wrapper)
+            }
+            unwind => unwind,
+        }
+    }
+
+    /// Loads an `int`, `float`, `long`, `double` or `String` constant of the
+    /// class' constant pool as a value.
+    pub(crate) fn load_constant(&mut self, class: ClassId, index: u16) -> Result<Value, Unwind> {
+        let source = self.constant_pool_of(class)?;
+        let constant_pool = &source.class.constant_pool;
+        match constant_pool.get(index as usize) {
+            Some(Constant::Integer(value)) => Ok(Value::Int(value.value)),
+            Some(Constant::Float(value)) => Ok(Value::Float(value.value)),
+            Some(Constant::Long(value)) => Ok(Value::Long(value.value)),
+            Some(Constant::Double(value)) => Ok(Value::Double(value.value)),
+            Some(Constant::String(value)) => {
+                let string = constant_pool
+                    .get_utf8(value.string_index)
+                    .map_err(VmError::from)?;
+                let object = self.intern_string(string.encode_utf16().collect())?;
+                Ok(Value::Reference(Some(object)))
+            }
+            Some(Constant::Class(_)) => {
+                let loaded = self.resolve_class_ref(class, index)?;
+                Ok(Value::Reference(Some(self.mirror(loaded)?)))
+            }
+            constant => Err(Unwind::Error(VmError::Internal(format!(
+                "Cannot load constant #{} of {}: {:?}",
+                index,
+                self.class(class).name,
+                constant
+            )))),
+        }
+    }
+}
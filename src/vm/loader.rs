@@ -0,0 +1,593 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::class::{Class, ClassLoadingError, ParseLimits};
+use crate::packaging::classpath::ClassPath;
+use crate::packaging::services::ServiceProviders;
+use crate::vm::agent::ClassFileTransformer;
+use crate::vm::archive::{
+    self, ArchivedClass, ArchivedClassPath, ArchivedFile, ArchivedLinks, ClassArchive,
+    ClassSnapshot, LoaderSnapshot, ARCHIVED_LOADERS,
+};
+use crate::vm::optimizer::optimize_class;
+use crate::vm::registry::ClassRegistry;
+
+// =============================================================================
+// LOADED CLASS
+// =============================================================================
+
+/// Identifies a class loader of the VM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoaderId(usize);
+
+impl LoaderId {
+    pub const BOOTSTRAP: LoaderId = LoaderId(0);
+    pub const PLATFORM: LoaderId = LoaderId(1);
+    pub const APPLICATION: LoaderId = LoaderId(2);
+}
+
+impl fmt::Display for LoaderId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoaderId::BOOTSTRAP => write!(f, "bootstrap"),
+            LoaderId::PLATFORM => write!(f, "platform"),
+            LoaderId::APPLICATION => write!(f, "app"),
+            LoaderId(id) => write!(f, "loader#{}", id),
+        }
+    }
+}
+
+/// A class defined by one of the VM's class loaders.
+#[derive(Debug)]
+pub struct LoadedClass {
+    pub name: String,
+    /// The loader which defined the class (as opposed to the one that was
+    /// asked for it and delegated).
+    pub defining_loader: LoaderId,
+    /// The classpath entry the class was read from.
+    pub source: PathBuf,
+    /// Size of the class file in bytes.
+    pub size: usize,
+    /// Time it took to parse the class file.
+    pub parse_time: Duration,
+    /// Whether the class file was read from a [ClassArchive].
+    pub archived: bool,
+    /// The links of the class archived with it, if any.
+    pub links: Option<ArchivedLinks>,
+    pub class: Class,
+}
+
+impl LoadedClass {
+    /// The loader `Class.getClassLoader()` reports: `None` for classes defined
+    /// by the bootstrap loader, which is represented as `null` in Java.
+    pub fn class_loader(&self) -> Option<LoaderId> {
+        match self.defining_loader {
+            LoaderId::BOOTSTRAP => None,
+            loader => Some(loader),
+        }
+    }
+}
+
+// =============================================================================
+// CLASS LOADERS
+// =============================================================================
+
+/// A class loader with its own classpath and namespace of defined classes.
+pub struct ClassLoader {
+    pub id: LoaderId,
+    pub parent: Option<LoaderId>,
+    classes: ClassSource,
+    defined: Mutex<HashMap<String, Arc<LoadedClass>>>,
+    /// The providers declared on the loader's own classpath, scanned on
+    /// first use.
+    services: OnceLock<ServiceProviders>,
+}
+
+/// Where a loader finds the classes it defines.
+enum ClassSource {
+    Registry(ClassRegistry),
+    Archive(ArchivedClassPath),
+}
+
+impl ClassLoader {
+    fn new(id: LoaderId, parent: Option<LoaderId>, classes: ClassSource) -> Self {
+        ClassLoader {
+            id,
+            parent,
+            classes,
+            defined: Mutex::new(HashMap::new()),
+            services: OnceLock::new(),
+        }
+    }
+
+    /// The index of the loader's classpath, `None` for loaders looking their
+    /// classes up in a class archive.
+    pub fn registry(&self) -> Option<&ClassRegistry> {
+        match &self.classes {
+            ClassSource::Registry(registry) => Some(registry),
+            ClassSource::Archive(_) => None,
+        }
+    }
+
+    /// The classpath the loader finds its resources on, `None` for loaders
+    /// looking their classes up in a class archive, which provide none.
+    fn resource_path(&self) -> Option<&ClassPath> {
+        self.registry().map(ClassRegistry::class_path)
+    }
+
+    /// Returns the class if it was already defined by this loader.
+    pub fn find_loaded(&self, name: &str) -> Option<Arc<LoadedClass>> {
+        self.defined.lock().unwrap().get(name).cloned()
+    }
+
+    /// Reads, parses and defines the class from this loader's own classpath,
+    /// without delegating to the parent.
+    fn find_class(
+        &self,
+        name: &str,
+        parse: impl FnOnce(&[u8]) -> Result<Class, ClassLoadingError>,
+    ) -> Result<Option<Arc<LoadedClass>>, ClassLoadingError> {
+        let (source, bytes, archived, links) = match &self.classes {
+            ClassSource::Registry(registry) => {
+                let source = match registry.provider(name) {
+                    Some(entry) => entry.path().to_path_buf(),
+                    None => return Ok(None),
+                };
+                match registry.read_class(name)? {
+                    Some(bytes) => (source, Cow::Owned(bytes), false, None),
+                    None => return Ok(None),
+                }
+            }
+            ClassSource::Archive(archive) => match archive.read_class(name)? {
+                Some(found) => (found.source, found.bytes, found.archived, found.links),
+                None => return Ok(None),
+            },
+        };
+
+        let started = Instant::now();
+        let class = parse(&bytes)?;
+        let parse_time = started.elapsed();
+        if class.name()? != name {
+            return Err(ClassLoadingError::new(
+                format!("{} (wrong name: {})", name, class.name()?).as_str(),
+            ));
+        }
+
+        tracing::debug!(
+            class = name,
+            loader = %self.id,
+            source = %source.display(),
+            bytes = bytes.len(),
+            archived,
+            "defining class"
+        );
+        let mut defined = self.defined.lock().unwrap();
+        // Another thread might have defined it in the meantime; keep the first
+        let loaded = defined.entry(name.to_string()).or_insert_with(|| {
+            Arc::new(LoadedClass {
+                name: name.to_string(),
+                defining_loader: self.id,
+                source,
+                size: bytes.len(),
+                parse_time,
+                archived,
+                links,
+                class,
+            })
+        });
+
+        Ok(Some(loaded.clone()))
+    }
+
+    /// The index of the loader's classes, with the class files of the ones
+    /// it defined or which were archived already, to be archived.
+    fn snapshot(&self) -> io::Result<LoaderSnapshot<'_>> {
+        let defined = self.defined.lock().unwrap();
+        let mut snapshot = LoaderSnapshot {
+            files: Vec::new(),
+            classes: Vec::new(),
+        };
+        match &self.classes {
+            ClassSource::Registry(registry) => {
+                for entry in registry.class_path().entries() {
+                    snapshot.files.push(ArchivedFile::of(entry.path())?);
+                }
+                for name in registry.class_names() {
+                    let file = registry.provider_index(name).unwrap_or_default();
+                    let class = if defined.contains_key(name) {
+                        registry.read_class(name)?.map(Cow::Owned)
+                    } else {
+                        None
+                    };
+                    snapshot.classes.push(ClassSnapshot {
+                        name,
+                        file,
+                        class,
+                        links: None,
+                    });
+                }
+            }
+            ClassSource::Archive(archive) => {
+                snapshot.files = archive.files().to_vec();
+                // Keeping the classes archived by earlier runs
+                for ArchivedClass {
+                    name,
+                    file,
+                    class,
+                    links,
+                } in archive.classes()
+                {
+                    let class = match class {
+                        Some(class) => Some(Cow::Borrowed(class)),
+                        None if defined.contains_key(name) => {
+                            archive.read_class(name)?.map(|found| found.bytes)
+                        }
+                        None => None,
+                    };
+                    snapshot.classes.push(ClassSnapshot {
+                        name,
+                        file,
+                        class,
+                        links: links.map(Cow::Borrowed),
+                    });
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+/// The hierarchy of class loaders, following the parent-delegation model:
+///
+/// - the bootstrap loader, backed by the JDK image
+/// - the platform loader, delegating to the bootstrap loader
+/// - the application loader, backed by the user classpath and delegating to
+///   the platform loader
+pub struct ClassLoaders {
+    loaders: Vec<ClassLoader>,
+    limits: ParseLimits,
+    optimize: bool,
+    transformers: Vec<Arc<dyn ClassFileTransformer>>,
+}
+
+impl ClassLoaders {
+    pub fn new(
+        boot_class_path: ClassPath,
+        platform_class_path: ClassPath,
+        application_class_path: ClassPath,
+    ) -> std::io::Result<ClassLoaders> {
+        ClassLoaders::with_jdk_classes(
+            [
+                ClassSource::Registry(ClassRegistry::new(boot_class_path)?),
+                ClassSource::Registry(ClassRegistry::new(platform_class_path)?),
+            ],
+            application_class_path,
+        )
+    }
+
+    /// The loaders of the JDK's classes looking them up in the archive.
+    pub fn from_archive(
+        archive: ClassArchive,
+        application_class_path: ClassPath,
+    ) -> std::io::Result<ClassLoaders> {
+        let archive = Arc::new(archive);
+        ClassLoaders::with_jdk_classes(
+            [
+                ClassSource::Archive(ArchivedClassPath::new(archive.clone(), 0)),
+                ClassSource::Archive(ArchivedClassPath::new(archive, 1)),
+            ],
+            application_class_path,
+        )
+    }
+
+    fn with_jdk_classes(
+        [boot, platform]: [ClassSource; ARCHIVED_LOADERS],
+        application_class_path: ClassPath,
+    ) -> std::io::Result<ClassLoaders> {
+        let loaders = vec![
+            ClassLoader::new(LoaderId::BOOTSTRAP, None, boot),
+            ClassLoader::new(LoaderId::PLATFORM, Some(LoaderId::BOOTSTRAP), platform),
+            ClassLoader::new(
+                LoaderId::APPLICATION,
+                Some(LoaderId::PLATFORM),
+                ClassSource::Registry(ClassRegistry::new(application_class_path)?),
+            ),
+        ];
+
+        Ok(ClassLoaders {
+            loaders,
+            limits: ParseLimits::default(),
+            optimize: false,
+            transformers: Vec::new(),
+        })
+    }
+
+    /// Bounds the class files the loaders define.
+    pub fn with_parse_limits(mut self, limits: ParseLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Optimizes the classes the loaders define, see
+    /// [optimize_class](crate::vm::optimizer::optimize_class).
+    pub fn with_optimizer(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Passes the class files the loaders define through the transformers,
+    /// in order, before parsing them.
+    pub fn with_transformers(mut self, transformers: Vec<Arc<dyn ClassFileTransformer>>) -> Self {
+        self.transformers = transformers;
+        self
+    }
+
+    /// Parses the class file of the class with the internal name, about to be
+    /// defined by the loader, with the transformers, the limits and the
+    /// optimizer of the loaders, without defining it.
+    pub fn parse_class(
+        &self,
+        name: &str,
+        loader: LoaderId,
+        bytes: &[u8],
+    ) -> Result<Class, ClassLoadingError> {
+        let mut bytes = Cow::Borrowed(bytes);
+        for transformer in &self.transformers {
+            if let Some(transformed) = transformer.transform(name, loader, &bytes) {
+                tracing::debug!(class = name, loader = %loader, "transformed class");
+                bytes = Cow::Owned(transformed);
+            }
+        }
+        let mut class = Class::parse_bytes_with_limits(&bytes, &self.limits)?;
+        if self.optimize {
+            optimize_class(&mut class)?;
+        }
+        Ok(class)
+    }
+
+    pub fn loader(&self, id: LoaderId) -> &ClassLoader {
+        &self.loaders[id.0]
+    }
+
+    /// Loads a class through the given (initiating) loader: classes already
+    /// defined by it are returned as is, otherwise the parent is asked first,
+    /// and the loader only defines the class itself when no ancestor could.
+    pub fn load_class(
+        &self,
+        initiating: LoaderId,
+        name: &str,
+    ) -> Result<Option<Arc<LoadedClass>>, ClassLoadingError> {
+        let loader = self.loader(initiating);
+        if let Some(loaded) = loader.find_loaded(name) {
+            return Ok(Some(loaded));
+        }
+
+        if let Some(parent) = loader.parent {
+            if let Some(loaded) = self.load_class(parent, name)? {
+                return Ok(Some(loaded));
+            }
+        }
+
+        loader.find_class(name, |bytes| self.parse_class(name, initiating, bytes))
+    }
+
+    /// The URL of the resource the loader finds, asking its parents first
+    /// like `ClassLoader.getResource`.
+    pub fn resource_url(&self, loader: LoaderId, name: &str) -> Option<String> {
+        let loader = self.loader(loader);
+        let parent = loader
+            .parent
+            .and_then(|parent| self.resource_url(parent, name));
+        parent.or_else(|| loader.resource_path()?.resource_url(name))
+    }
+
+    /// The URLs of every copy of the resource the loader finds, its
+    /// ancestors' first, like `ClassLoader.getResources`.
+    pub fn resource_urls(&self, loader: LoaderId, name: &str) -> Vec<String> {
+        let loader = self.loader(loader);
+        let mut urls = match loader.parent {
+            Some(parent) => self.resource_urls(parent, name),
+            None => Vec::new(),
+        };
+        if let Some(class_path) = loader.resource_path() {
+            urls.extend(class_path.resource_urls(name));
+        }
+        urls
+    }
+
+    /// The providers of the service the loader finds, by binary name, its
+    /// ancestors' first like `ServiceLoader` enumerates them.
+    pub fn service_providers(&self, loader: LoaderId, service: &str) -> io::Result<Vec<String>> {
+        let mut providers = ServiceProviders::default();
+        let mut ancestors = Vec::new();
+        let mut next = Some(loader);
+        while let Some(id) = next {
+            ancestors.push(self.loader(id));
+            next = self.loader(id).parent;
+        }
+        for loader in ancestors.into_iter().rev() {
+            let class_path = match loader.resource_path() {
+                Some(class_path) => class_path,
+                None => continue,
+            };
+            let services = match loader.services.get() {
+                Some(services) => services,
+                None => {
+                    let services = ServiceProviders::scan(class_path)?;
+                    loader.services.get_or_init(|| services)
+                }
+            };
+            for provider in services.providers(service) {
+                providers.add(service, provider.clone());
+            }
+        }
+        Ok(providers.providers(service).to_vec())
+    }
+
+    /// Reads the resource at a URL of [ClassLoaders::resource_url], if one of
+    /// the loaders provides it.
+    pub fn resource_at(&self, url: &str) -> Option<Vec<u8>> {
+        self.loaders
+            .iter()
+            .filter_map(ClassLoader::resource_path)
+            .find_map(|class_path| class_path.resource_at(url))
+    }
+
+    /// Whether the loaders of the JDK's classes defined classes which are
+    /// missing from their class archive, if they have one.
+    pub fn defined_unarchived_classes(&self) -> bool {
+        self.loaders[..ARCHIVED_LOADERS].iter().any(|loader| {
+            let defined = loader.defined.lock().unwrap();
+            defined.values().any(|class| !class.archived)
+        })
+    }
+
+    /// Writes a class archive of the JDK's classes, holding the class files
+    /// defined so far with the links the VM resolved for them.
+    pub fn write_archive(
+        &self,
+        path: &Path,
+        links: impl Fn(LoaderId, &str) -> Option<ArchivedLinks>,
+    ) -> io::Result<()> {
+        let mut snapshots = Vec::with_capacity(ARCHIVED_LOADERS);
+        for loader in &self.loaders[..ARCHIVED_LOADERS] {
+            let mut snapshot = loader.snapshot()?;
+            for class in &mut snapshot.classes {
+                if let Some(links) = class.class.as_ref().and(links(loader.id, class.name)) {
+                    class.link(&links)?;
+                }
+            }
+            snapshots.push(snapshot);
+        }
+        archive::write(path, &snapshots)
+    }
+
+    /// All the classes defined so far, across every loader.
+    pub fn defined_classes(&self) -> Vec<Arc<LoadedClass>> {
+        self.loaders
+            .iter()
+            .flat_map(|loader| {
+                loader
+                    .defined
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// CLASS LOADER TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod class_loader_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{ClassLoaders, LoaderId};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::archive::ClassArchive;
+
+    fn class_path_with_hello(name: &str) -> ClassPath {
+        let root = std::env::temp_dir().join(format!("bvm-loader-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let hello_class = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden/Hello.class");
+        fs::copy(hello_class, root.join("Hello.class")).unwrap();
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(root).unwrap());
+        class_path
+    }
+
+    #[test]
+    fn test_application_loader_defines_classpath_classes() {
+        let loaders = ClassLoaders::new(
+            ClassPath::default(),
+            ClassPath::default(),
+            class_path_with_hello("app"),
+        )
+        .unwrap();
+
+        let hello = loaders
+            .load_class(LoaderId::APPLICATION, "Hello")
+            .unwrap()
+            .unwrap();
+        assert_eq!(hello.class_loader(), Some(LoaderId::APPLICATION));
+        assert!(loaders
+            .load_class(LoaderId::BOOTSTRAP, "Hello")
+            .unwrap()
+            .is_none());
+        assert!(loaders
+            .load_class(LoaderId::APPLICATION, "Missing")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_parent_is_asked_first() {
+        let loaders = ClassLoaders::new(
+            class_path_with_hello("boot"),
+            ClassPath::default(),
+            class_path_with_hello("shadowed"),
+        )
+        .unwrap();
+
+        let hello = loaders
+            .load_class(LoaderId::APPLICATION, "Hello")
+            .unwrap()
+            .unwrap();
+        assert_eq!(hello.defining_loader, LoaderId::BOOTSTRAP);
+        assert_eq!(hello.class_loader(), None);
+        assert!(loaders
+            .loader(LoaderId::APPLICATION)
+            .find_loaded("Hello")
+            .is_none());
+    }
+
+    #[test]
+    fn test_class_archive() {
+        let boot_class_path = class_path_with_hello("archived");
+        let boot_files = [boot_class_path.entries()[0].path().to_path_buf()];
+        let archive = boot_files[0].with_extension("bva");
+        let loaders =
+            ClassLoaders::new(boot_class_path, ClassPath::default(), ClassPath::default()).unwrap();
+        assert!(
+            !loaders
+                .load_class(LoaderId::APPLICATION, "Hello")
+                .unwrap()
+                .unwrap()
+                .archived
+        );
+        assert!(loaders.defined_unarchived_classes());
+        loaders.write_archive(&archive, |_, _| None).unwrap();
+
+        let stale = [PathBuf::from(env!("CARGO_MANIFEST_DIR"))];
+        assert!(ClassArchive::open(&archive, [&stale, &[]]).is_none());
+        let loaders = ClassLoaders::from_archive(
+            ClassArchive::open(&archive, [&boot_files, &[]]).unwrap(),
+            ClassPath::default(),
+        )
+        .unwrap();
+        let hello = loaders
+            .load_class(LoaderId::APPLICATION, "Hello")
+            .unwrap()
+            .unwrap();
+        assert!(hello.archived);
+        assert_eq!(hello.defining_loader, LoaderId::BOOTSTRAP);
+        assert_eq!(hello.source, boot_files[0]);
+        assert!(loaders
+            .load_class(LoaderId::APPLICATION, "Missing")
+            .unwrap()
+            .is_none());
+        assert!(!loaders.defined_unarchived_classes());
+    }
+}
@@ -0,0 +1,832 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::io;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::class::attributes::{Attribute, CodeAttribute};
+use crate::class::instruction::decode;
+use crate::class::{Class, ClassLoadingError};
+use crate::vm::cfg::ControlFlowGraph;
+use crate::vm::class_cache::{read_string, write_string, Cached};
+
+// =============================================================================
+// METRICS
+// =============================================================================
+
+/// Size and complexity measures of a method's code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MethodMetrics {
+    pub name: String,
+    pub descriptor: String,
+    pub instructions: usize,
+    /// Cyclomatic complexity: one more than the number of decisions, each
+    /// block deciding between its successors and exception handlers.
+    pub complexity: usize,
+    pub max_stack: u16,
+    pub max_locals: u16,
+    /// The most nested `try` ranges covering an instruction.
+    pub try_depth: usize,
+}
+
+impl MethodMetrics {
+    pub fn new(name: &str, descriptor: &str, code: &CodeAttribute) -> Self {
+        let decoded = decode(&code.code);
+        let graph = ControlFlowGraph::build(&decoded, &code.exception_tables);
+        let decisions: usize = graph
+            .blocks
+            .iter()
+            .map(|block| {
+                let successors = block.successors.len() + block.exception_successors.len();
+                successors.saturating_sub(1)
+            })
+            .sum();
+
+        // Handlers of the same try share its range
+        let mut ranges: Vec<(u16, u16)> = code
+            .exception_tables
+            .iter()
+            .map(|entry| (entry.start_pc, entry.end_pc))
+            .collect();
+        ranges.sort_unstable();
+        ranges.dedup();
+        let try_depth = decoded
+            .pcs
+            .iter()
+            .map(|&pc| {
+                ranges
+                    .iter()
+                    .filter(|&&(start, end)| (start as u32..end as u32).contains(&pc))
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+
+        MethodMetrics {
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            instructions: decoded.instructions.len(),
+            complexity: decisions + 1,
+            max_stack: code.max_stack,
+            max_locals: code.max_locals,
+            try_depth,
+        }
+    }
+}
+
+/// The metrics of the methods with code of a class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassMetrics {
+    pub name: String,
+    pub methods: Vec<MethodMetrics>,
+}
+
+impl ClassMetrics {
+    pub fn new(class: &Class) -> Result<Self, ClassLoadingError> {
+        let pool = &class.constant_pool;
+        let mut methods = Vec::new();
+        for method in &class.methods {
+            let code = method
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::Code(code) => Some(code),
+                    _ => None,
+                });
+            if let Some(code) = code {
+                methods.push(MethodMetrics::new(
+                    pool.get_utf8(method.name_index)?,
+                    pool.get_utf8(method.descriptor_index)?,
+                    code,
+                ));
+            }
+        }
+        Ok(ClassMetrics {
+            name: class.name()?.to_string(),
+            methods,
+        })
+    }
+
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary {
+            classes: 1,
+            ..Summary::default()
+        };
+        for method in &self.methods {
+            summary.methods += 1;
+            summary.instructions += method.instructions;
+            summary.complexity += method.complexity;
+            summary.max_complexity = summary.max_complexity.max(method.complexity);
+            summary.max_try_depth = summary.max_try_depth.max(method.try_depth);
+        }
+        summary
+    }
+}
+
+impl Cached for ClassMetrics {
+    const KIND: &'static str = "metrics-1";
+
+    fn write(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        write_string(output, &self.name)?;
+        output.write_u16::<BigEndian>(self.methods.len() as u16)?;
+        for method in &self.methods {
+            write_string(output, &method.name)?;
+            write_string(output, &method.descriptor)?;
+            output.write_u32::<BigEndian>(method.instructions as u32)?;
+            output.write_u32::<BigEndian>(method.complexity as u32)?;
+            output.write_u16::<BigEndian>(method.max_stack)?;
+            output.write_u16::<BigEndian>(method.max_locals)?;
+            output.write_u16::<BigEndian>(method.try_depth as u16)?;
+        }
+        Ok(())
+    }
+
+    fn read(input: &mut dyn io::Read) -> io::Result<Self> {
+        let name = read_string(input)?;
+        let count = input.read_u16::<BigEndian>()?;
+        let mut methods = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            methods.push(MethodMetrics {
+                name: read_string(input)?,
+                descriptor: read_string(input)?,
+                instructions: input.read_u32::<BigEndian>()? as usize,
+                complexity: input.read_u32::<BigEndian>()? as usize,
+                max_stack: input.read_u16::<BigEndian>()?,
+                max_locals: input.read_u16::<BigEndian>()?,
+                try_depth: input.read_u16::<BigEndian>()? as usize,
+            });
+        }
+        Ok(ClassMetrics { name, methods })
+    }
+}
+
+/// Totals and maxima over a set of classes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub classes: usize,
+    pub methods: usize,
+    pub instructions: usize,
+    /// The sum of the complexities of the methods.
+    pub complexity: usize,
+    pub max_complexity: usize,
+    pub max_try_depth: usize,
+}
+
+impl Summary {
+    pub fn add(&mut self, other: &Summary) {
+        self.classes += other.classes;
+        self.methods += other.methods;
+        self.instructions += other.instructions;
+        self.complexity += other.complexity;
+        self.max_complexity = self.max_complexity.max(other.max_complexity);
+        self.max_try_depth = self.max_try_depth.max(other.max_try_depth);
+    }
+
+    pub fn average_complexity(&self) -> f64 {
+        match self.methods {
+            0 => 0.0,
+            methods => self.complexity as f64 / methods as f64,
+        }
+    }
+}
+
+/// The metrics of the classes of a classpath entry, a directory or a jar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryMetrics {
+    pub path: String,
+    pub classes: Vec<ClassMetrics>,
+}
+
+impl EntryMetrics {
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for class in &self.classes {
+            summary.add(&class.summary());
+        }
+        summary
+    }
+}
+
+// =============================================================================
+// SIZES
+// =============================================================================
+
+/// The number of constants or attributes of a kind and the bytes they take.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Usage {
+    /// The kind of constant, like `Utf8` or `Methodref`, or the name of the
+    /// attribute.
+    pub kind: String,
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Sums usages by kind, listing the kinds taking the most bytes first.
+#[derive(Clone, Debug, Default)]
+struct UsageTotals(BTreeMap<String, (usize, usize)>);
+
+impl UsageTotals {
+    fn add(&mut self, kind: &str, count: usize, bytes: usize) {
+        let total = self.0.entry(kind.to_string()).or_default();
+        total.0 += count;
+        total.1 += bytes;
+    }
+
+    fn into_usages(self) -> Vec<Usage> {
+        let mut usages: Vec<Usage> = self
+            .0
+            .into_iter()
+            .map(|(kind, (count, bytes))| Usage { kind, count, bytes })
+            .collect();
+        usages.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.kind.cmp(&b.kind)));
+        usages
+    }
+}
+
+/// The size of a method in the class file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MethodSize {
+    pub name: String,
+    pub descriptor: String,
+    /// The bytes of the method, its attributes included.
+    pub bytes: usize,
+    /// The bytes of its bytecode alone.
+    pub code: usize,
+}
+
+/// Where the bytes of a class file go: its constant pool by kind of
+/// constant, its methods and its attributes by name, wherever they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassSizes {
+    pub name: String,
+    /// The size of the class file.
+    pub bytes: usize,
+    pub constants: Vec<Usage>,
+    pub methods: Vec<MethodSize>,
+    /// Those of the class, its fields, its methods and their code, the
+    /// `Code` attributes without the ones nested in them.
+    pub attributes: Vec<Usage>,
+}
+
+impl ClassSizes {
+    pub fn new(class: &Class) -> Result<Self, ClassLoadingError> {
+        let pool = &class.constant_pool;
+        let mut constants = UsageTotals::default();
+        for (_, constant) in pool.iter() {
+            constants.add(constant.kind(), 1, constant.size());
+        }
+
+        let mut attributes = UsageTotals::default();
+        let mut add_attributes = |list: &[Attribute]| -> Result<usize, ClassLoadingError> {
+            let mut total = 0;
+            for attribute in list {
+                let mut bytes = attribute.size(pool)?;
+                total += bytes;
+                if let Attribute::Code(code) = attribute {
+                    for nested in &code.attributes {
+                        let nested_bytes = nested.size(pool)?;
+                        attributes.add(nested.stored_name(pool)?, 1, nested_bytes);
+                        bytes -= nested_bytes;
+                    }
+                }
+                attributes.add(attribute.stored_name(pool)?, 1, bytes);
+            }
+            Ok(total)
+        };
+        add_attributes(&class.attributes)?;
+        for field in &class.fields {
+            add_attributes(&field.attributes)?;
+        }
+        let mut methods = Vec::new();
+        for method in &class.methods {
+            let code = method
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::Code(code) => Some(code.code.len()),
+                    _ => None,
+                });
+            methods.push(MethodSize {
+                name: pool.get_utf8(method.name_index)?.to_string(),
+                descriptor: pool.get_utf8(method.descriptor_index)?.to_string(),
+                // Its flags, name, descriptor and attribute count
+                bytes: 8 + add_attributes(&method.attributes)?,
+                code: code.unwrap_or(0),
+            });
+        }
+
+        Ok(ClassSizes {
+            name: class.name()?.to_string(),
+            bytes: class.to_bytes()?.len(),
+            constants: constants.into_usages(),
+            methods,
+            attributes: attributes.into_usages(),
+        })
+    }
+
+    pub fn summary(&self) -> SizeSummary {
+        let mut summary = SizeSummary::default();
+        summary.add(self);
+        summary
+    }
+}
+
+impl Cached for ClassSizes {
+    const KIND: &'static str = "sizes-1";
+
+    fn write(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        write_string(output, &self.name)?;
+        output.write_u32::<BigEndian>(self.bytes as u32)?;
+        write_usages(output, &self.constants)?;
+        output.write_u16::<BigEndian>(self.methods.len() as u16)?;
+        for method in &self.methods {
+            write_string(output, &method.name)?;
+            write_string(output, &method.descriptor)?;
+            output.write_u32::<BigEndian>(method.bytes as u32)?;
+            output.write_u32::<BigEndian>(method.code as u32)?;
+        }
+        write_usages(output, &self.attributes)
+    }
+
+    fn read(input: &mut dyn io::Read) -> io::Result<Self> {
+        let name = read_string(input)?;
+        let bytes = input.read_u32::<BigEndian>()? as usize;
+        let constants = read_usages(input)?;
+        let count = input.read_u16::<BigEndian>()?;
+        let mut methods = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            methods.push(MethodSize {
+                name: read_string(input)?,
+                descriptor: read_string(input)?,
+                bytes: input.read_u32::<BigEndian>()? as usize,
+                code: input.read_u32::<BigEndian>()? as usize,
+            });
+        }
+        Ok(ClassSizes {
+            name,
+            bytes,
+            constants,
+            methods,
+            attributes: read_usages(input)?,
+        })
+    }
+}
+
+fn write_usages(output: &mut dyn io::Write, usages: &[Usage]) -> io::Result<()> {
+    output.write_u16::<BigEndian>(usages.len() as u16)?;
+    for usage in usages {
+        write_string(output, &usage.kind)?;
+        output.write_u32::<BigEndian>(usage.count as u32)?;
+        output.write_u32::<BigEndian>(usage.bytes as u32)?;
+    }
+    Ok(())
+}
+
+fn read_usages(input: &mut dyn io::Read) -> io::Result<Vec<Usage>> {
+    let count = input.read_u16::<BigEndian>()?;
+    let mut usages = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        usages.push(Usage {
+            kind: read_string(input)?,
+            count: input.read_u32::<BigEndian>()? as usize,
+            bytes: input.read_u32::<BigEndian>()? as usize,
+        });
+    }
+    Ok(usages)
+}
+
+/// The sizes of a set of classes added up.
+#[derive(Clone, Debug, Default)]
+pub struct SizeSummary {
+    pub classes: usize,
+    pub bytes: usize,
+    /// The bytes of the bytecode of the methods.
+    pub code: usize,
+    constants: UsageTotals,
+    attributes: UsageTotals,
+}
+
+impl SizeSummary {
+    pub fn add(&mut self, class: &ClassSizes) {
+        self.classes += 1;
+        self.bytes += class.bytes;
+        self.code += class
+            .methods
+            .iter()
+            .map(|method| method.code)
+            .sum::<usize>();
+        for usage in &class.constants {
+            self.constants.add(&usage.kind, usage.count, usage.bytes);
+        }
+        for usage in &class.attributes {
+            self.attributes.add(&usage.kind, usage.count, usage.bytes);
+        }
+    }
+
+    pub fn merge(&mut self, other: &SizeSummary) {
+        self.classes += other.classes;
+        self.bytes += other.bytes;
+        self.code += other.code;
+        for (kind, (count, bytes)) in &other.constants.0 {
+            self.constants.add(kind, *count, *bytes);
+        }
+        for (kind, (count, bytes)) in &other.attributes.0 {
+            self.attributes.add(kind, *count, *bytes);
+        }
+    }
+
+    /// The bytes of the constant pools, without their counts.
+    pub fn pool_bytes(&self) -> usize {
+        self.constants.0.values().map(|(_, bytes)| bytes).sum()
+    }
+
+    pub fn constants(&self) -> Vec<Usage> {
+        self.constants.clone().into_usages()
+    }
+
+    pub fn attributes(&self) -> Vec<Usage> {
+        self.attributes.clone().into_usages()
+    }
+}
+
+/// The sizes of the classes of a classpath entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntrySizes {
+    pub path: String,
+    pub classes: Vec<ClassSizes>,
+}
+
+impl EntrySizes {
+    pub fn summary(&self) -> SizeSummary {
+        let mut summary = SizeSummary::default();
+        for class in &self.classes {
+            summary.add(class);
+        }
+        summary
+    }
+}
+
+// =============================================================================
+// OUTPUT
+// =============================================================================
+
+/// Writes the metrics as a table per class, each entry and the whole set
+/// followed by their summary.
+pub fn write_table<W: Write>(writer: &mut W, entries: &[EntryMetrics]) -> fmt::Result {
+    let mut total = Summary::default();
+    for entry in entries {
+        writeln!(writer, "{}", entry.path)?;
+        for class in &entry.classes {
+            writeln!(writer, "  {}", class.name)?;
+            writeln!(
+                writer,
+                "    {:>6} {:>4} {:>5} {:>6} {:>3}  method",
+                "instrs", "cc", "stack", "locals", "try"
+            )?;
+            for method in &class.methods {
+                writeln!(
+                    writer,
+                    "    {:>6} {:>4} {:>5} {:>6} {:>3}  {}{}",
+                    method.instructions,
+                    method.complexity,
+                    method.max_stack,
+                    method.max_locals,
+                    method.try_depth,
+                    method.name,
+                    method.descriptor
+                )?;
+            }
+        }
+        let summary = entry.summary();
+        write_summary(writer, "  ", &summary)?;
+        total.add(&summary);
+    }
+    if entries.len() > 1 {
+        writeln!(writer, "total")?;
+        write_summary(writer, "  ", &total)?;
+    }
+    Ok(())
+}
+
+fn write_summary<W: Write>(writer: &mut W, indent: &str, summary: &Summary) -> fmt::Result {
+    writeln!(
+        writer,
+        "{}{} classes, {} methods, {} instructions, complexity {:.2} average, {} max, try depth {} max",
+        indent,
+        summary.classes,
+        summary.methods,
+        summary.instructions,
+        summary.average_complexity(),
+        summary.max_complexity,
+        summary.max_try_depth
+    )
+}
+
+/// Writes the metrics as a JSON object with the `entries`, their classes
+/// and methods, and the `summary` of everything.
+pub fn write_json<W: Write>(writer: &mut W, entries: &[EntryMetrics]) -> fmt::Result {
+    let mut total = Summary::default();
+    write!(writer, "{{\"entries\":[")?;
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"path\":{},\"classes\":[",
+            json_string(&entry.path)
+        )?;
+        for (index, class) in entry.classes.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"name\":{},\"methods\":[",
+                json_string(&class.name)
+            )?;
+            for (index, method) in class.methods.iter().enumerate() {
+                if index > 0 {
+                    write!(writer, ",")?;
+                }
+                write!(
+                    writer,
+                    "{{\"name\":{},\"descriptor\":{},\"instructions\":{},\"complexity\":{},\
+                     \"max_stack\":{},\"max_locals\":{},\"try_depth\":{}}}",
+                    json_string(&method.name),
+                    json_string(&method.descriptor),
+                    method.instructions,
+                    method.complexity,
+                    method.max_stack,
+                    method.max_locals,
+                    method.try_depth
+                )?;
+            }
+            write!(writer, "],\"summary\":")?;
+            write_json_summary(writer, &class.summary())?;
+            write!(writer, "}}")?;
+        }
+        let summary = entry.summary();
+        write!(writer, "],\"summary\":")?;
+        write_json_summary(writer, &summary)?;
+        write!(writer, "}}")?;
+        total.add(&summary);
+    }
+    write!(writer, "],\"summary\":")?;
+    write_json_summary(writer, &total)?;
+    writeln!(writer, "}}")
+}
+
+fn write_json_summary<W: Write>(writer: &mut W, summary: &Summary) -> fmt::Result {
+    write!(
+        writer,
+        "{{\"classes\":{},\"methods\":{},\"instructions\":{},\"complexity\":{},\
+         \"average_complexity\":{:.2},\"max_complexity\":{},\"max_try_depth\":{}}}",
+        summary.classes,
+        summary.methods,
+        summary.instructions,
+        summary.complexity,
+        summary.average_complexity(),
+        summary.max_complexity,
+        summary.max_try_depth
+    )
+}
+
+/// Writes the sizes as tables per class, each entry and the whole set
+/// followed by their summary and their constants and attributes by kind.
+pub fn write_sizes_table<W: Write>(writer: &mut W, entries: &[EntrySizes]) -> fmt::Result {
+    let mut total = SizeSummary::default();
+    for entry in entries {
+        writeln!(writer, "{}", entry.path)?;
+        for class in &entry.classes {
+            let summary = class.summary();
+            writeln!(
+                writer,
+                "  {} ({} bytes, constant pool {} bytes)",
+                class.name,
+                class.bytes,
+                summary.pool_bytes()
+            )?;
+            write_usages_table(writer, "    ", "constant", &class.constants)?;
+            writeln!(writer, "    {:>6} {:>6}  method", "bytes", "code")?;
+            for method in &class.methods {
+                writeln!(
+                    writer,
+                    "    {:>6} {:>6}  {}{}",
+                    method.bytes, method.code, method.name, method.descriptor
+                )?;
+            }
+            write_usages_table(writer, "    ", "attribute", &class.attributes)?;
+        }
+        let summary = entry.summary();
+        write_size_summary(writer, &summary)?;
+        total.merge(&summary);
+    }
+    if entries.len() > 1 {
+        writeln!(writer, "total")?;
+        write_size_summary(writer, &total)?;
+    }
+    Ok(())
+}
+
+fn write_size_summary<W: Write>(writer: &mut W, summary: &SizeSummary) -> fmt::Result {
+    writeln!(
+        writer,
+        "  {} classes, {} bytes, constant pools {} bytes, code {} bytes",
+        summary.classes,
+        summary.bytes,
+        summary.pool_bytes(),
+        summary.code
+    )?;
+    write_usages_table(writer, "    ", "constant", &summary.constants())?;
+    write_usages_table(writer, "    ", "attribute", &summary.attributes())
+}
+
+fn write_usages_table<W: Write>(
+    writer: &mut W,
+    indent: &str,
+    title: &str,
+    usages: &[Usage],
+) -> fmt::Result {
+    writeln!(writer, "{}{:>6} {:>6}  {}", indent, "count", "bytes", title)?;
+    for usage in usages {
+        writeln!(
+            writer,
+            "{}{:>6} {:>6}  {}",
+            indent, usage.count, usage.bytes, usage.kind
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the sizes as a JSON object with the `entries`, their classes
+/// and methods, and the `summary` of everything.
+pub fn write_sizes_json<W: Write>(writer: &mut W, entries: &[EntrySizes]) -> fmt::Result {
+    let mut total = SizeSummary::default();
+    write!(writer, "{{\"entries\":[")?;
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"path\":{},\"classes\":[",
+            json_string(&entry.path)
+        )?;
+        for (index, class) in entry.classes.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"name\":{},\"bytes\":{},\"constants\":",
+                json_string(&class.name),
+                class.bytes
+            )?;
+            write_json_usages(writer, &class.constants)?;
+            write!(writer, ",\"methods\":[")?;
+            for (index, method) in class.methods.iter().enumerate() {
+                if index > 0 {
+                    write!(writer, ",")?;
+                }
+                write!(
+                    writer,
+                    "{{\"name\":{},\"descriptor\":{},\"bytes\":{},\"code\":{}}}",
+                    json_string(&method.name),
+                    json_string(&method.descriptor),
+                    method.bytes,
+                    method.code
+                )?;
+            }
+            write!(writer, "],\"attributes\":")?;
+            write_json_usages(writer, &class.attributes)?;
+            write!(writer, "}}")?;
+        }
+        let summary = entry.summary();
+        write!(writer, "],\"summary\":")?;
+        write_json_size_summary(writer, &summary)?;
+        write!(writer, "}}")?;
+        total.merge(&summary);
+    }
+    write!(writer, "],\"summary\":")?;
+    write_json_size_summary(writer, &total)?;
+    writeln!(writer, "}}")
+}
+
+fn write_json_size_summary<W: Write>(writer: &mut W, summary: &SizeSummary) -> fmt::Result {
+    write!(
+        writer,
+        "{{\"classes\":{},\"bytes\":{},\"pool_bytes\":{},\"code\":{},\"constants\":",
+        summary.classes,
+        summary.bytes,
+        summary.pool_bytes(),
+        summary.code
+    )?;
+    write_json_usages(writer, &summary.constants())?;
+    write!(writer, ",\"attributes\":")?;
+    write_json_usages(writer, &summary.attributes())?;
+    write!(writer, "}}")
+}
+
+fn write_json_usages<W: Write>(writer: &mut W, usages: &[Usage]) -> fmt::Result {
+    write!(writer, "[")?;
+    for (index, usage) in usages.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"kind\":{},\"count\":{},\"bytes\":{}}}",
+            json_string(&usage.kind),
+            usage.count,
+            usage.bytes
+        )?;
+    }
+    write!(writer, "]")
+}
+
+/// Quotes and escapes a JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod metrics_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{json_string, ClassMetrics, ClassSizes, Usage};
+    use crate::class::Class;
+    use crate::vm::class_cache::Cached;
+
+    #[test]
+    fn test_metrics() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+        let metrics = ClassMetrics::new(&class).unwrap();
+        let method = |name: &str| {
+            metrics
+                .methods
+                .iter()
+                .find(|method| method.name == name)
+                .unwrap()
+        };
+
+        let add = method("add");
+        assert_eq!((add.instructions, add.complexity, add.try_depth), (4, 1, 0));
+        let fibonacci = method("fibonacci");
+        assert_eq!((fibonacci.complexity, fibonacci.max_stack), (2, 4));
+        // try { return a / b; } catch (ArithmeticException e) { return 0; }
+        let safe_divide = method("safeDivide");
+        assert_eq!((safe_divide.complexity, safe_divide.try_depth), (2, 1));
+
+        let summary = metrics.summary();
+        assert_eq!(summary.methods, metrics.methods.len());
+        assert_eq!(summary.max_try_depth, 1);
+
+        assert_eq!(json_string("a\"b\\\n"), "\"a\\\"b\\\\\\n\"");
+    }
+
+    #[test]
+    fn test_sizes() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding/Calculator.class");
+        let bytes = fs::read(path).unwrap();
+        let sizes = ClassSizes::new(&Class::parse_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(sizes.bytes, bytes.len());
+
+        let usage = |usages: &[Usage], kind: &str| {
+            let usage = usages.iter().find(|usage| usage.kind == kind).unwrap();
+            (usage.count, usage.bytes)
+        };
+        assert_eq!(usage(&sizes.constants, "Double"), (1, 9));
+        assert_eq!(usage(&sizes.attributes, "SourceFile"), (1, 8));
+        assert_eq!(usage(&sizes.attributes, "Code").0, sizes.methods.len());
+        // The magic and versions, the pool, the flags, names and counts, the
+        // field, the methods and the source file
+        let summary = sizes.summary();
+        let methods: usize = sizes.methods.iter().map(|method| method.bytes).sum();
+        assert_eq!(8 + summary.pool_bytes() + 16 + 8 + methods + 8, sizes.bytes);
+        let add = sizes.methods.iter().find(|method| method.name == "add");
+        assert_eq!(add.map(|method| method.code), Some(4));
+
+        let mut cached = Vec::new();
+        sizes.write(&mut cached).unwrap();
+        assert_eq!(ClassSizes::read(&mut cached.as_slice()).unwrap(), sizes);
+    }
+}
@@ -0,0 +1,3613 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::class::descriptor::MethodDescriptor;
+use crate::class::{ClassLoadingError, ParseLimits};
+use crate::packaging::classpath::ClassPath;
+use crate::packaging::jdk::JdkImage;
+use crate::vm::agent::ClassFileTransformer;
+use crate::vm::archive::ClassArchive;
+use crate::vm::assertions::AssertionStatus;
+use crate::vm::call_site::CallSite;
+use crate::vm::clock::{Clock, SystemClock};
+use crate::vm::coverage::CodeCoverage;
+use crate::vm::events::VmEventListener;
+use crate::vm::gc::Collector;
+use crate::vm::heap::{ArrayData, Heap, NativeData, ObjectData};
+use crate::vm::inline_cache::InlineCache;
+#[cfg(feature = "jit")]
+use crate::vm::jit::JitCompiler;
+use crate::vm::limits::{ExecutionLimits, Limit};
+use crate::vm::loader::{ClassLoaders, LoaderId};
+use crate::vm::modules::ModuleGraph;
+use crate::vm::natives::{NativeFn, NativeRegistry};
+use crate::vm::policy::{Permission, VmPolicy};
+use crate::vm::profiler::MethodProfiler;
+use crate::vm::replay::{InteractionClock, InteractionLog};
+use crate::vm::runtime::{ClassId, RuntimeClass, RuntimeField, RuntimeMethod};
+use crate::vm::sampler::SamplingProfiler;
+use crate::vm::symbol::{Symbol, SymbolTable};
+use crate::vm::thread::{JavaThread, ThreadId};
+use crate::vm::trace::{BytecodeTrace, ClassLoadingLog, GcLog};
+use crate::vm::value::{JValue, ObjectRef, Value};
+
+pub mod agent;
+pub mod archive;
+pub mod assertions;
+#[cfg(feature = "tui")]
+pub mod browser;
+pub mod call_site;
+pub mod callgraph;
+pub mod cfg;
+pub mod class_cache;
+pub mod clock;
+pub mod compatibility;
+pub mod coverage;
+pub mod dataflow;
+pub mod deadcode;
+pub mod decompiler;
+pub mod diff;
+pub mod dry_link;
+pub mod events;
+pub mod fingerprint;
+pub mod gc;
+pub mod heap;
+pub mod inference;
+pub mod inline_cache;
+pub mod interpreter;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod junit;
+pub mod limits;
+pub mod linker;
+pub mod loader;
+pub mod metrics;
+pub mod modules;
+pub mod natives;
+pub mod optimizer;
+pub mod policy;
+pub mod profiler;
+pub mod redefinition;
+pub mod regions;
+pub mod registry;
+pub mod repl;
+pub mod replay;
+pub mod runtime;
+pub mod sampler;
+pub mod search;
+pub mod shrink;
+pub mod strip;
+pub mod symbol;
+pub mod thread;
+pub mod trace;
+pub mod value;
+
+// =============================================================================
+// ERRORS
+// =============================================================================
+
+/// A Java exception which escaped the code invoked by the embedder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JavaException {
+    /// Binary name of the exception class, e.g. `java.lang.ArithmeticException`.
+    pub class_name: String,
+    pub message: Option<String>,
+    /// The `Throwable` itself, still alive on the VM heap.
+    pub object: ObjectRef,
+    /// What failed, for the `LinkageError`s thrown while loading, linking
+    /// and initializing classes.
+    pub linkage_error: Option<LinkageError>,
+}
+
+impl fmt::Display for JavaException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.class_name, message),
+            None => write!(f, "{}", self.class_name),
+        }
+    }
+}
+
+/// The subclasses of `java.lang.LinkageError`, by the step of loading and
+/// linking which failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkageError {
+    /// A class, or one of its supertypes, was not found.
+    NoClassDefFound,
+    ClassFormat,
+    UnsupportedClassVersion,
+    /// A class is its own superclass or superinterface.
+    ClassCircularity,
+    Verify,
+    /// A class changed incompatibly with the classes referencing it, like a
+    /// superclass becoming an interface or a static field an instance one.
+    IncompatibleClassChange,
+    NoSuchField,
+    NoSuchMethod,
+    AbstractMethod,
+    Instantiation,
+    IllegalAccess,
+    UnsatisfiedLink,
+    ExceptionInInitializer,
+    /// Another subclass, thrown by Java code.
+    Other,
+}
+
+impl LinkageError {
+    /// The error thrown as an instance of the class, by its internal name.
+    fn of(class_name: &str) -> Option<LinkageError> {
+        let error = match class_name {
+            "java/lang/NoClassDefFoundError" => LinkageError::NoClassDefFound,
+            "java/lang/ClassFormatError" => LinkageError::ClassFormat,
+            "java/lang/UnsupportedClassVersionError" => LinkageError::UnsupportedClassVersion,
+            "java/lang/ClassCircularityError" => LinkageError::ClassCircularity,
+            "java/lang/VerifyError" => LinkageError::Verify,
+            "java/lang/IncompatibleClassChangeError" => LinkageError::IncompatibleClassChange,
+            "java/lang/NoSuchFieldError" => LinkageError::NoSuchField,
+            "java/lang/NoSuchMethodError" => LinkageError::NoSuchMethod,
+            "java/lang/AbstractMethodError" => LinkageError::AbstractMethod,
+            "java/lang/InstantiationError" => LinkageError::Instantiation,
+            "java/lang/IllegalAccessError" => LinkageError::IllegalAccess,
+            "java/lang/UnsatisfiedLinkError" => LinkageError::UnsatisfiedLink,
+            "java/lang/ExceptionInInitializerError" => LinkageError::ExceptionInInitializer,
+            "java/lang/LinkageError" => LinkageError::Other,
+            _ => return None,
+        };
+        Some(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum VmError {
+    /// The invoked Java code threw an exception.
+    Exception(JavaException),
+    /// The arguments passed by the embedder do not match the descriptor.
+    InvalidArguments(String),
+    NoSuchMethod(String),
+    Io(io::Error),
+    /// The guest called `System.exit` with the given status.
+    Exit(i32),
+    /// The guest was aborted for exceeding one of its execution limits.
+    LimitExceeded(Limit),
+    ClassLoading(ClassLoadingError),
+    /// The class cannot be redefined, see [Vm::redefine_class].
+    UnsupportedRedefinition(String),
+    /// The replayed run stopped following the recording of its host
+    /// interactions, see [InteractionLog].
+    ReplayDiverged(String),
+    /// The VM reached a state it cannot handle, like an unsupported
+    /// instruction or malformed bytecode.
+    Internal(String),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::Exception(exception) => write!(f, "Exception {}", exception),
+            VmError::InvalidArguments(message) => write!(f, "Invalid arguments: {}", message),
+            VmError::NoSuchMethod(method) => write!(f, "No such method: {}", method),
+            VmError::Io(error) => write!(f, "{}", error),
+            VmError::Exit(status) => write!(f, "VM exited with status {}", status),
+            VmError::LimitExceeded(limit) => write!(f, "Guest aborted: {}", limit),
+            VmError::ClassLoading(error) => write!(f, "{}", error),
+            VmError::UnsupportedRedefinition(message) => {
+                write!(f, "Unsupported class redefinition: {}", message)
+            }
+            VmError::ReplayDiverged(message) => write!(f, "Replay diverged: {}", message),
+            VmError::Internal(message) => write!(f, "Internal VM error: {}", message),
+        }
+    }
+}
+
+impl Error for VmError {}
+
+impl From<io::Error> for VmError {
+    fn from(err: io::Error) -> Self {
+        VmError::Io(err)
+    }
+}
+
+impl From<ClassLoadingError> for VmError {
+    fn from(err: ClassLoadingError) -> Self {
+        VmError::ClassLoading(err)
+    }
+}
+
+/// Why the execution of Java code completed abruptly.
+#[derive(Debug)]
+pub enum Unwind {
+    /// A `Throwable` was thrown and is looking for a handler.
+    Throw(ObjectRef),
+    /// The VM cannot continue executing the code.
+    Error(VmError),
+    /// `System.exit` was called: every frame is discarded without running
+    /// exception handlers or `finally` blocks.
+    Exit(i32),
+}
+
+impl From<VmError> for Unwind {
+    fn from(err: VmError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+// =============================================================================
+// BUILDER
+// =============================================================================
+
+/// The default size of the stack of a thread.
+const DEFAULT_STACK_SIZE: usize = 512 * 1024;
+
+/// Configures and boots a [Vm].
+pub struct VmBuilder {
+    class_path: ClassPath,
+    modules: ModuleGraph,
+    jdk: Option<JdkImage>,
+    class_archive: Option<PathBuf>,
+    natives: Vec<(String, String, String, NativeFn)>,
+    stdout: Box<dyn Write + Send>,
+    stderr: Box<dyn Write + Send>,
+    clock: Box<dyn Clock>,
+    policy: VmPolicy,
+    assertions: AssertionStatus,
+    limits: ExecutionLimits,
+    parse_limits: ParseLimits,
+    stack_size: usize,
+    optimize_bytecode: bool,
+    transformers: Vec<Arc<dyn ClassFileTransformer>>,
+    trace: Option<BytecodeTrace>,
+    class_log: Option<ClassLoadingLog>,
+    gc_log: Option<GcLog>,
+    profiler: Option<MethodProfiler>,
+    sampler: Option<SamplingProfiler>,
+    coverage: Option<CodeCoverage>,
+    interactions: Option<InteractionLog>,
+    listeners: Vec<Box<dyn VmEventListener>>,
+    thread_dump: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "jit")]
+    jit: Option<JitCompiler>,
+}
+
+impl VmBuilder {
+    /// The user classpath, served by the application class loader.
+    pub fn class_path(mut self, class_path: ClassPath) -> Self {
+        self.class_path = class_path;
+        self
+    }
+
+    /// The modules resolved from the module path, served by the application
+    /// class loader after the classpath, whose readability and exports the
+    /// access checks enforce.
+    pub fn modules(mut self, modules: ModuleGraph) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// The JDK backing the bootstrap and platform class loaders. Without one
+    /// only the VM's built-in core classes are available to them.
+    pub fn java_home<P: Into<PathBuf>>(mut self, java_home: P) -> Self {
+        self.jdk = Some(JdkImage::new(java_home.into()));
+        self
+    }
+
+    /// Defines the JDK's classes from the [ClassArchive] at the path. The
+    /// archive is written when the VM exits if it is missing, was written
+    /// for another JDK or lacks classes the run defined.
+    pub fn class_archive<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.class_archive = Some(path.into());
+        self
+    }
+
+    /// Registers the implementation of a native method, overriding the
+    /// built-in one with the same signature.
+    pub fn native(mut self, class: &str, name: &str, descriptor: &str, native: NativeFn) -> Self {
+        self.natives.push((
+            class.to_string(),
+            name.to_string(),
+            descriptor.to_string(),
+            native,
+        ));
+        self
+    }
+
+    /// Where `System.out` writes to, the process' standard output by default.
+    pub fn stdout<W: Write + Send + 'static>(mut self, stdout: W) -> Self {
+        self.stdout = Box::new(stdout);
+        self
+    }
+
+    /// Where `System.err` writes to, the process' standard error by default.
+    pub fn stderr<W: Write + Send + 'static>(mut self, stderr: W) -> Self {
+        self.stderr = Box::new(stderr);
+        self
+    }
+
+    /// The clock read by `System.currentTimeMillis` and `System.nanoTime`,
+    /// the host's by default.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// What guest code may do to the host, everything by default.
+    pub fn policy(mut self, policy: VmPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Which classes run their `assert` statements, none by default.
+    pub fn assertions(mut self, assertions: AssertionStatus) -> Self {
+        self.assertions = assertions;
+        self
+    }
+
+    /// Bounds on the resources guest code may consume, unlimited by default.
+    pub fn limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Bounds on the class files the VM parses, rejecting crafted classes
+    /// before they exhaust memory or the stack.
+    pub fn parse_limits(mut self, limits: ParseLimits) -> Self {
+        self.parse_limits = limits;
+        self
+    }
+
+    /// The size of the stack of every thread in bytes, like `-Xss`, 512 KiB
+    /// by default. Invoking a method whose frame does not fit in it throws
+    /// `StackOverflowError`.
+    pub fn stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = bytes;
+        self
+    }
+
+    /// Applies the peephole [optimizer](optimizer::optimize_class) to the
+    /// classes as they are defined, so that both the interpreter and the JIT
+    /// run the optimized code.
+    pub fn optimize_bytecode(mut self, optimize: bool) -> Self {
+        self.optimize_bytecode = optimize;
+        self
+    }
+
+    /// Registers a transformer rewriting the class files before they are
+    /// defined, e.g. by an instrumentation agent. Transformers run in the
+    /// order they were registered, before the optimizer.
+    pub fn transformer<T: ClassFileTransformer + 'static>(mut self, transformer: T) -> Self {
+        self.transformers.push(Arc::new(transformer));
+        self
+    }
+
+    /// Logs the instructions interpreted in the methods selected by the trace.
+    pub fn trace_bytecode(mut self, trace: BytecodeTrace) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Logs the classes as they are defined, with a summary when the VM
+    /// shuts down.
+    pub fn log_class_loading(mut self, log: ClassLoadingLog) -> Self {
+        self.class_log = Some(log);
+        self
+    }
+
+    /// Logs every garbage collection.
+    pub fn log_gc(mut self, log: GcLog) -> Self {
+        self.gc_log = Some(log);
+        self
+    }
+
+    /// Profiles the invoked methods, writing the profile when the VM shuts
+    /// down.
+    pub fn profile(mut self, profiler: MethodProfiler) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// Samples the Java stacks while guest code runs, writing the samples
+    /// when the VM shuts down.
+    pub fn sample(mut self, sampler: SamplingProfiler) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Records the readings of the host system by the natives, like the time,
+    /// or replays a recording of them.
+    pub fn interactions(mut self, log: InteractionLog) -> Self {
+        self.interactions = Some(log);
+        self
+    }
+
+    /// Collects the code coverage of the application classes, writing the
+    /// report when the VM shuts down.
+    pub fn coverage(mut self, coverage: CodeCoverage) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    /// Registers a listener for the events of the VM, called in registration
+    /// order.
+    pub fn listener<L: VmEventListener + 'static>(mut self, listener: L) -> Self {
+        self.listeners.push(Box::new(listener));
+        self
+    }
+
+    /// Writes a thread dump to the standard output of the VM whenever the
+    /// flag is raised, e.g. by a `SIGQUIT` handler. The flag is checked
+    /// between instructions, and cleared once the dump is written.
+    pub fn dump_threads_on(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.thread_dump = Some(flag);
+        self
+    }
+
+    /// Configures the compilation of hot methods to native code, enabled
+    /// with the default settings otherwise.
+    #[cfg(feature = "jit")]
+    pub fn jit(mut self, jit: JitCompiler) -> Self {
+        self.jit = Some(jit);
+        self
+    }
+
+    pub fn build(mut self) -> Result<Vm, VmError> {
+        for module in self.modules.modules() {
+            self.class_path.push(module.reference.open()?);
+        }
+        let (boot_files, platform_files) = match &self.jdk {
+            Some(jdk) => (
+                jdk.boot_class_path_files()?,
+                jdk.platform_class_path_files()?,
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        let archive = self
+            .class_archive
+            .as_deref()
+            .and_then(|path| ClassArchive::open(path, [&boot_files, &platform_files]));
+        let loaders = match archive {
+            Some(archive) => ClassLoaders::from_archive(archive, self.class_path)?,
+            None => ClassLoaders::new(
+                ClassPath::open_all(boot_files)?,
+                ClassPath::open_all(platform_files)?,
+                self.class_path,
+            )?,
+        }
+        .with_parse_limits(self.parse_limits)
+        .with_optimizer(self.optimize_bytecode)
+        .with_transformers(self.transformers);
+        if let Some(sampler) = &mut self.sampler {
+            sampler.start();
+        }
+
+        let mut natives = NativeRegistry::with_builtins();
+        for (class, name, descriptor, native) in self.natives {
+            natives.register(&class, &name, &descriptor, native);
+        }
+        let interactions = self.interactions.map(|log| Arc::new(Mutex::new(log)));
+        let clock: Box<dyn Clock> = match &interactions {
+            Some(log) => Box::new(InteractionClock::new(self.clock, log.clone())),
+            None => self.clock,
+        };
+
+        Ok(Vm {
+            loaders,
+            class_archive: self.class_archive,
+            classes: Vec::new(),
+            loaded: HashMap::new(),
+            linking: HashSet::new(),
+            symbols: SymbolTable::default(),
+            heap: Heap::default(),
+            natives,
+            interned_strings: HashMap::new(),
+            shutdown_hooks: Vec::new(),
+            main_thread: None,
+            loader_objects: HashMap::new(),
+            default_uncaught_handler: None,
+            started_threads: VecDeque::new(),
+            running_threads: Vec::new(),
+            suspended_stacks: Vec::new(),
+            thread_names: 0,
+            last_thread_id: ThreadId::MAIN,
+            random_seed: None,
+            field_cache: HashMap::new(),
+            method_cache: HashMap::new(),
+            call_sites: HashMap::new(),
+            inline_caches: Vec::new(),
+            obsolete_methods: Vec::new(),
+            thread: JavaThread::default(),
+            stack_size: self.stack_size,
+            stdout: self.stdout,
+            stderr: self.stderr,
+            clock,
+            policy: self.policy,
+            assertions: self.assertions,
+            modules: self.modules,
+            gc: Collector::new(self.gc_log, &self.limits),
+            invocations: 0,
+            limits: self.limits,
+            executed_instructions: 0,
+            next_poll: 0,
+            started: Instant::now(),
+            trace: self.trace,
+            class_log: self.class_log,
+            profiler: self.profiler,
+            sampler: self.sampler,
+            coverage: self.coverage,
+            interactions,
+            listeners: self.listeners,
+            thread_dump: self.thread_dump,
+            #[cfg(feature = "jit")]
+            jit: self.jit,
+        })
+    }
+}
+
+// =============================================================================
+// VM
+// =============================================================================
+
+/// A Java virtual machine instance.
+///
+/// Embedders configure one through [Vm::builder], then look up classes and
+/// invoke methods on them, passing and receiving [JValue]s:
+///
+/// ```no_run
+/// use bvm::packaging::classpath::ClassPath;
+/// use bvm::vm::value::JValue;
+/// use bvm::vm::Vm;
+///
+/// let mut vm = Vm::builder()
+///     .class_path(ClassPath::parse("classes").unwrap())
+///     .build()
+///     .unwrap();
+/// let sum = vm
+///     .invoke_static("com/example/Calculator", "add", "(II)I", &[JValue::Int(1), JValue::Int(2)])
+///     .unwrap();
+/// assert_eq!(sum, Some(JValue::Int(3)));
+/// ```
+///
+/// Objects returned to the embedder stay alive until passed to
+/// [Vm::release], the other ones are garbage collected once unreachable.
+pub struct Vm {
+    pub(crate) loaders: ClassLoaders,
+    /// Where the JDK's classes are archived, see [VmBuilder::class_archive].
+    pub(crate) class_archive: Option<PathBuf>,
+    pub(crate) classes: Vec<RuntimeClass>,
+    /// Classes by initiating (and defining) loader and internal name.
+    pub(crate) loaded: HashMap<(LoaderId, Symbol), ClassId>,
+    /// Classes whose supertypes are being loaded, by defining loader and
+    /// internal name, to detect circular inheritance.
+    pub(crate) linking: HashSet<(LoaderId, Symbol)>,
+    pub(crate) symbols: SymbolTable,
+    pub(crate) heap: Heap,
+    pub(crate) gc: Collector,
+    /// Interpreter loops and natives in progress, where only the outermost
+    /// loop is a safepoint of the collector.
+    pub(crate) invocations: usize,
+    pub(crate) natives: NativeRegistry,
+    pub(crate) interned_strings: HashMap<Arc<[u16]>, ObjectRef>,
+    /// The `Thread`s registered through `Runtime.addShutdownHook`.
+    pub(crate) shutdown_hooks: Vec<ObjectRef>,
+    /// The `Thread` of the guest's only thread, created on first use.
+    pub(crate) main_thread: Option<ObjectRef>,
+    /// The `ClassLoader`s of the loaders, created on first use.
+    pub(crate) loader_objects: HashMap<LoaderId, ObjectRef>,
+    /// The handler set by `Thread.setDefaultUncaughtExceptionHandler`.
+    pub(crate) default_uncaught_handler: Option<ObjectRef>,
+    /// Threads started but not run yet, in the order they started.
+    pub(crate) started_threads: VecDeque<ObjectRef>,
+    /// The started threads being run, the innermost one being the current
+    /// thread if any.
+    pub(crate) running_threads: Vec<ObjectRef>,
+    /// The stacks of the threads the running ones took over from, the last
+    /// one being the stack to resume when the current thread terminates.
+    pub(crate) suspended_stacks: Vec<JavaThread>,
+    /// Counter of the default `Thread-<n>` names.
+    pub(crate) thread_names: u32,
+    /// The id of the thread created last, the main thread having the first.
+    pub(crate) last_thread_id: ThreadId,
+    /// The seed of the generator behind `Math.random`, seeded from the clock
+    /// on first use.
+    pub(crate) random_seed: Option<i64>,
+    /// Fields, methods and `invokedynamic` call sites resolved from constant
+    /// pool entries, by the class owning the constant pool and the index of
+    /// the entry.
+    pub(crate) field_cache: HashMap<(ClassId, u16), Arc<RuntimeField>>,
+    pub(crate) method_cache: HashMap<(ClassId, u16), Arc<RuntimeMethod>>,
+    pub(crate) call_sites: HashMap<(ClassId, u16), Arc<CallSite>>,
+    /// The inline caches of the `invokevirtual` and `invokeinterface` call
+    /// sites, indexed by their instructions.
+    pub(crate) inline_caches: Vec<InlineCache>,
+    /// The methods replaced by class redefinitions, kept alive as the JIT
+    /// and the profiler identify methods by address.
+    pub(crate) obsolete_methods: Vec<Arc<RuntimeMethod>>,
+    pub(crate) thread: JavaThread,
+    /// The size of the stack of every thread, see [VmBuilder::stack_size].
+    pub(crate) stack_size: usize,
+    pub(crate) stdout: Box<dyn Write + Send>,
+    pub(crate) stderr: Box<dyn Write + Send>,
+    pub(crate) clock: Box<dyn Clock>,
+    pub(crate) policy: VmPolicy,
+    pub(crate) assertions: AssertionStatus,
+    /// The modules of the module path, see [VmBuilder::modules].
+    pub(crate) modules: ModuleGraph,
+    pub(crate) limits: ExecutionLimits,
+    pub(crate) executed_instructions: u64,
+    /// The executed instruction count at which the interpreter next polls,
+    /// see [Vm::request_poll].
+    pub(crate) next_poll: u64,
+    pub(crate) started: Instant,
+    pub(crate) trace: Option<BytecodeTrace>,
+    pub(crate) class_log: Option<ClassLoadingLog>,
+    pub(crate) profiler: Option<MethodProfiler>,
+    pub(crate) sampler: Option<SamplingProfiler>,
+    pub(crate) coverage: Option<CodeCoverage>,
+    /// The readings of the host system by the natives, shared with the
+    /// clock, see [VmBuilder::interactions].
+    pub(crate) interactions: Option<Arc<Mutex<InteractionLog>>>,
+    pub(crate) listeners: Vec<Box<dyn VmEventListener>>,
+    /// Raised to request a thread dump.
+    pub(crate) thread_dump: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "jit")]
+    pub(crate) jit: Option<JitCompiler>,
+}
+
+impl Vm {
+    pub fn builder() -> VmBuilder {
+        VmBuilder {
+            class_path: ClassPath::default(),
+            modules: ModuleGraph::default(),
+            jdk: None,
+            class_archive: None,
+            natives: Vec::new(),
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            clock: Box::new(SystemClock::new()),
+            policy: VmPolicy::default(),
+            assertions: AssertionStatus::default(),
+            limits: ExecutionLimits::default(),
+            parse_limits: ParseLimits::default(),
+            stack_size: DEFAULT_STACK_SIZE,
+            optimize_bytecode: false,
+            transformers: Vec::new(),
+            trace: None,
+            class_log: None,
+            gc_log: None,
+            profiler: None,
+            sampler: None,
+            coverage: None,
+            interactions: None,
+            listeners: Vec::new(),
+            thread_dump: None,
+            #[cfg(feature = "jit")]
+            jit: Some(JitCompiler::new()),
+        }
+    }
+
+    /// Number of instructions executed since the VM was built, interpreted or
+    /// compiled.
+    pub fn executed_instructions(&self) -> u64 {
+        self.executed_instructions
+    }
+
+    pub fn class(&self, id: ClassId) -> &RuntimeClass {
+        &self.classes[id.index()]
+    }
+
+    pub(crate) fn class_mut(&mut self, id: ClassId) -> &mut RuntimeClass {
+        &mut self.classes[id.index()]
+    }
+
+    /// The class of an object on the heap.
+    pub fn class_of(&self, object: ObjectRef) -> ClassId {
+        self.heap.get(object).class
+    }
+
+    /// Looks up a class by its internal name (e.g. `com/example/Main`) through
+    /// the application class loader, loading and linking it if needed.
+    pub fn find_class(&mut self, name: &str) -> Result<ClassId, VmError> {
+        let result = self.load_class(LoaderId::APPLICATION, name);
+        self.complete(result)
+    }
+
+    /// Invokes a static method, initializing its class first. Returns `None`
+    /// for `void` methods.
+    pub fn invoke_static(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+        arguments: &[JValue],
+    ) -> Result<Option<JValue>, VmError> {
+        let class = self.find_class(class)?;
+        let method = self
+            .member_symbols(name, descriptor)
+            .and_then(|(name, descriptor)| self.resolve_method(class, &name, &descriptor))
+            .filter(|method| method.is_static())
+            .ok_or_else(|| self.no_such_method(class, name, descriptor))?;
+        let arguments = Vm::check_arguments(&method.parsed_descriptor, None, arguments)?;
+
+        let result = self
+            .initialize_class(method.class)
+            .and_then(|_| self.invoke(method, &arguments));
+        let value = self.complete(result)?;
+        Ok(value.map(|value| self.export(value)))
+    }
+
+    /// Invokes an instance method on the object, selecting the implementation
+    /// by the object's class like `invokevirtual` does.
+    pub fn invoke_method(
+        &mut self,
+        receiver: ObjectRef,
+        name: &str,
+        descriptor: &str,
+        arguments: &[JValue],
+    ) -> Result<Option<JValue>, VmError> {
+        let class = self.class_of(receiver);
+        let method = self
+            .member_symbols(name, descriptor)
+            .and_then(|(name, descriptor)| self.find_virtual(class, &name, &descriptor))
+            .filter(|method| !method.is_static())
+            .ok_or_else(|| self.no_such_method(class, name, descriptor))?;
+        let arguments = Vm::check_arguments(&method.parsed_descriptor, Some(receiver), arguments)?;
+
+        let result = self.invoke(method, &arguments);
+        let value = self.complete(result)?;
+        Ok(value.map(|value| self.export(value)))
+    }
+
+    /// Allocates an instance of the class and runs the constructor matching
+    /// the descriptor on it.
+    pub fn new_object(
+        &mut self,
+        class: &str,
+        descriptor: &str,
+        arguments: &[JValue],
+    ) -> Result<ObjectRef, VmError> {
+        let class = self.find_class(class)?;
+        let constructor = self
+            .class(class)
+            .declared_method("<init>", descriptor)
+            .cloned()
+            .ok_or_else(|| self.no_such_method(class, "<init>", descriptor))?;
+
+        let result = self.initialize_class(class).and_then(|_| {
+            let object = self.instantiate(class)?;
+            let arguments =
+                Vm::check_arguments(&constructor.parsed_descriptor, Some(object), arguments)?;
+            self.invoke(constructor, &arguments)?;
+            Ok(object)
+        });
+        self.complete(result).map(|object| self.pin(object))
+    }
+
+    /// Creates an array of the given array class, e.g. `[I` or
+    /// `[Ljava/lang/String;`, with its elements set to their default values.
+    pub fn new_array(&mut self, class: &str, length: usize) -> Result<ObjectRef, VmError> {
+        let result = self
+            .load_class(LoaderId::APPLICATION, class)
+            .and_then(|class| self.create_array(class, length as i32));
+        self.complete(result).map(|object| self.pin(object))
+    }
+
+    /// Creates a `java.lang.String` with the given contents.
+    pub fn new_string(&mut self, value: &str) -> Result<ObjectRef, VmError> {
+        let result = self.create_string(value.encode_utf16().collect());
+        self.complete(result).map(|object| self.pin(object))
+    }
+
+    /// The stack trace of the `Throwable`, like an [escaped
+    /// exception](JavaException::object), and of its causes, formatted the
+    /// way `printStackTrace` prints it.
+    pub fn stack_trace(&mut self, throwable: ObjectRef) -> Result<String, VmError> {
+        let result = natives::lang::stack_trace(self, throwable);
+        self.complete(result)
+    }
+
+    /// Creates a `java.lang.String[]` holding the given strings, like the one
+    /// passed to `main`.
+    pub fn new_string_array(&mut self, values: &[&str]) -> Result<ObjectRef, VmError> {
+        let result = (|| {
+            let strings = values
+                .iter()
+                .map(|value| self.create_string(value.encode_utf16().collect()).map(Some))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.allocate_array("[Ljava/lang/String;", ArrayData::Reference(strings))
+        })();
+        self.complete(result).map(|object| self.pin(object))
+    }
+
+    /// The contents of a `java.lang.String`, or `None` if the object is not
+    /// a string. Unpaired surrogates are replaced.
+    pub fn string_value(&self, object: ObjectRef) -> Option<String> {
+        match &self.heap.get(object).native {
+            NativeData::String(chars) => Some(String::from_utf16_lossy(chars)),
+            _ => None,
+        }
+    }
+
+    /// Reads an instance field of the object by name, searching the class
+    /// hierarchy from the object's class upwards. A returned object is only
+    /// kept alive by the objects referencing it.
+    pub fn get_field(&self, object: ObjectRef, name: &str) -> Option<JValue> {
+        self.field(object, name).map(JValue::from)
+    }
+
+    /// The slot of the named instance field of the class or its superclasses.
+    pub(crate) fn field_slot(&self, class: ClassId, name: &str) -> Option<usize> {
+        let mut current = Some(class);
+        while let Some(id) = current {
+            let runtime_class = self.class(id);
+            if let Some(field) = runtime_class
+                .fields
+                .iter()
+                .find(|field| field.name == name && !field.is_static())
+            {
+                return Some(field.slot);
+            }
+            current = runtime_class.super_class;
+        }
+
+        None
+    }
+
+    pub(crate) fn field(&self, object: ObjectRef, name: &str) -> Option<Value> {
+        let slot = self.field_slot(self.class_of(object), name)?;
+        self.heap.get(object).fields().get(slot).copied()
+    }
+
+    pub(crate) fn set_field(&mut self, object: ObjectRef, name: &str, value: Value) {
+        if let Some(slot) = self.field_slot(self.class_of(object), name) {
+            if let ObjectData::Fields(fields) = &mut self.heap.get_mut(object).data {
+                fields[slot] = value;
+            }
+        }
+    }
+
+    pub(crate) fn static_field(&self, class: ClassId, name: &str) -> Option<Value> {
+        let field = self
+            .class(class)
+            .fields
+            .iter()
+            .find(|field| field.name == name && field.is_static())?;
+        self.class(class).static_values.get(field.slot).copied()
+    }
+
+    pub(crate) fn set_static_field(&mut self, class: ClassId, name: &str, value: Value) {
+        let slot = self
+            .class(class)
+            .fields
+            .iter()
+            .find(|field| field.name == name && field.is_static())
+            .map(|field| field.slot);
+        if let Some(slot) = slot {
+            self.class_mut(class).static_values[slot] = value;
+        }
+    }
+
+    /// Checks the embedder supplied arguments against the descriptor, and
+    /// prepends the receiver for instance methods.
+    fn check_arguments(
+        descriptor: &MethodDescriptor,
+        receiver: Option<ObjectRef>,
+        arguments: &[JValue],
+    ) -> Result<Vec<Value>, VmError> {
+        if arguments.len() != descriptor.parameters.len() {
+            return Err(VmError::InvalidArguments(format!(
+                "expected {} arguments for {}, got {}",
+                descriptor.parameters.len(),
+                descriptor,
+                arguments.len()
+            )));
+        }
+
+        let mut values = Vec::with_capacity(arguments.len() + 1);
+        values.extend(receiver.map(|receiver| Value::Reference(Some(receiver))));
+        for (argument, parameter) in arguments.iter().zip(&descriptor.parameters) {
+            if !argument.matches(parameter) {
+                return Err(VmError::InvalidArguments(format!(
+                    "{:?} cannot be passed as {}",
+                    argument, parameter
+                )));
+            }
+            values.push(Value::from(*argument));
+        }
+
+        Ok(values)
+    }
+
+    /// Throws `SecurityException` unless the policy permits the operation.
+    pub(crate) fn check_permission(&mut self, permission: Permission) -> Result<(), Unwind> {
+        if self.policy.permits(permission) {
+            return Ok(());
+        }
+
+        let message = format!("Access denied: {}", permission);
+        Err(self.throw_new("java/lang/SecurityException", Some(message)))
+    }
+
+    /// Aborts the guest if it exceeds one of its limits.
+    pub(crate) fn check_limits(&self) -> Result<(), Unwind> {
+        if let Some(max) = self.limits.instructions() {
+            if self.executed_instructions > max {
+                return Err(Unwind::Error(VmError::LimitExceeded(Limit::Instructions(
+                    max,
+                ))));
+            }
+        }
+        if let Some(max) = self.limits.duration() {
+            if self.started.elapsed() > max {
+                return Err(Unwind::Error(VmError::LimitExceeded(Limit::Duration(max))));
+            }
+        }
+        self.check_heap(0)
+    }
+
+    /// Aborts the guest if allocating `additional` bytes would exceed its
+    /// heap limit.
+    pub(crate) fn check_heap(&self, additional: usize) -> Result<(), Unwind> {
+        match self.limits.heap_bytes() {
+            Some(max) if self.heap.size().saturating_add(additional) > max => {
+                Err(Unwind::Error(VmError::LimitExceeded(Limit::HeapBytes(max))))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn no_such_method(&self, class: ClassId, name: &str, descriptor: &str) -> VmError {
+        VmError::NoSuchMethod(format!("{}.{}{}", self.class(class).name, name, descriptor))
+    }
+
+    /// Converts the outcome of running Java code into the embedder facing
+    /// result, describing escaped exceptions.
+    fn complete<T>(&mut self, result: Result<T, Unwind>) -> Result<T, VmError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(Unwind::Throw(exception)) => {
+                self.pin(exception);
+                Err(VmError::Exception(self.describe_exception(exception)))
+            }
+            Err(Unwind::Error(error)) => Err(error),
+            Err(Unwind::Exit(status)) => {
+                self.exit();
+                Err(VmError::Exit(status))
+            }
+        }
+    }
+
+    /// Shuts the VM down the way the end of the main thread does: runs the
+    /// started threads until the last non-daemon one terminates, then the
+    /// registered shutdown hooks, and flushes the standard streams.
+    pub fn shutdown(&mut self) -> Result<(), VmError> {
+        let result = self.run_non_daemon_threads();
+        self.complete(result)?;
+        self.exit();
+        self.flush().map_err(VmError::from)
+    }
+
+    /// Runs the shutdown hooks, then writes the class archive, the class
+    /// loading summary, the profiles and the coverage report, and flushes the
+    /// recorded host interactions.
+    fn exit(&mut self) {
+        self.run_shutdown_hooks();
+        if let Some(path) = self.class_archive.take() {
+            if self.loaders.defined_unarchived_classes() {
+                if let Err(error) = self.write_class_archive(&path) {
+                    tracing::warn!(archive = %path.display(), %error, "cannot write class archive");
+                }
+            }
+        }
+        if let Some(mut log) = self.class_log.take() {
+            let _ = log.summary();
+        }
+        if let Some(mut profiler) = self.profiler.take() {
+            let _ = profiler.write(|method| self.class(method.class).java_name());
+        }
+        if let Some(mut sampler) = self.sampler.take() {
+            let _ = sampler.write();
+        }
+        if let Some(mut coverage) = self.coverage.take() {
+            let _ = coverage.write(&self.classes);
+        }
+        if let Some(log) = &self.interactions {
+            let _ = log.lock().unwrap().finish();
+        }
+    }
+
+    /// Writes a [ClassArchive] of the JDK's classes, holding the ones defined
+    /// so far with the references and vtables the VM resolved for them.
+    pub fn write_class_archive(&self, path: &Path) -> io::Result<()> {
+        let links = self.archived_links();
+        self.loaders.write_archive(path, |loader, name| {
+            links.get(&(loader, name.to_string())).cloned()
+        })
+    }
+
+    /// Runs the shutdown hooks once, in registration order. Exceptions thrown
+    /// by a hook are reported and do not prevent the others from running.
+    fn run_shutdown_hooks(&mut self) {
+        // The hooks not run yet stay reachable while the others run
+        while !self.shutdown_hooks.is_empty() {
+            let hook = self.shutdown_hooks.remove(0);
+            if let Err(Unwind::Throw(exception)) = self.invoke_virtual(hook, "run", "()V", &[]) {
+                let _ = self.invoke_virtual(exception, "printStackTrace", "()V", &[]);
+            }
+        }
+    }
+
+    pub(crate) fn describe_exception(&self, exception: ObjectRef) -> JavaException {
+        let message = match self.get_field(exception, "detailMessage") {
+            Some(JValue::Object(message)) => self.string_value(message),
+            _ => None,
+        };
+
+        // The most specific error the class extends
+        let mut linkage_error = None;
+        let mut class = Some(self.class_of(exception));
+        while let (None, Some(id)) = (linkage_error, class) {
+            linkage_error = LinkageError::of(&self.class(id).name);
+            class = self.class(id).super_class;
+        }
+
+        JavaException {
+            class_name: self.class(self.class_of(exception)).java_name(),
+            message,
+            object: exception,
+            linkage_error,
+        }
+    }
+
+    /// Allocates an instance of the class with its fields set to their
+    /// default values, without running any constructor.
+    pub(crate) fn instantiate(&mut self, class: ClassId) -> Result<ObjectRef, Unwind> {
+        let fields = self.class(class).instance_fields.clone();
+        Ok(self.allocate(heap::HeapObject {
+            class,
+            data: ObjectData::Fields(fields),
+            native: NativeData::None,
+            identity_hash: None,
+        }))
+    }
+
+    /// Creates a string object from UTF-16 contents.
+    pub(crate) fn create_string(&mut self, chars: Vec<u16>) -> Result<ObjectRef, Unwind> {
+        let class = self.load_class(LoaderId::BOOTSTRAP, "java/lang/String")?;
+        let object = self.instantiate(class)?;
+        self.heap.get_mut(object).native = NativeData::String(chars.into());
+        Ok(object)
+    }
+
+    /// Returns the canonical string object for the contents, as
+    /// `String.intern()` and string literals do.
+    pub(crate) fn intern_string(&mut self, chars: Vec<u16>) -> Result<ObjectRef, Unwind> {
+        if let Some(object) = self.interned_strings.get(chars.as_slice()) {
+            return Ok(*object);
+        }
+
+        let object = self.create_string(chars)?;
+        if let NativeData::String(chars) = &self.heap.get(object).native {
+            self.interned_strings.insert(chars.clone(), object);
+        }
+        Ok(object)
+    }
+
+    /// Writes to one of the standard streams, as seen by guest code.
+    pub(crate) fn write_stream(
+        &mut self,
+        stream: heap::StandardStream,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        match stream {
+            heap::StandardStream::Out => self.stdout.write_all(bytes),
+            heap::StandardStream::Err => self.stderr.write_all(bytes),
+        }
+    }
+
+    /// Flushes the standard streams, e.g. before the VM is torn down.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        self.stderr.flush()
+    }
+}
+
+// =============================================================================
+// VM TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod vm_tests {
+    use std::fs;
+    use std::future;
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use super::{LinkageError, LoaderId, Unwind, Vm, VmError, VmPolicy};
+    use crate::class::attributes::Attribute;
+    use crate::class::constant_pool::ConstantPoolBuilder;
+    use crate::class::{Class, FieldAccessFlags};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+    use crate::packaging::modulepath::ModulePath;
+    use crate::packaging::source::{AsyncClassSource, BlockingSource, BoxFuture};
+    use crate::vm::assertions::AssertionStatus;
+    use crate::vm::clock::{Clock, VirtualClock};
+    use crate::vm::coverage::CodeCoverage;
+    use crate::vm::events::VmEventListener;
+    use crate::vm::junit::{self, TestOutcome, TestResult};
+    use crate::vm::limits::{ExecutionLimits, Limit};
+    use crate::vm::modules::ModuleGraph;
+    use crate::vm::profiler::{MethodProfiler, ProfileFormat};
+    use crate::vm::replay::InteractionLog;
+    use crate::vm::runtime::{RuntimeClass, RuntimeMethod};
+    use crate::vm::sampler::SamplingProfiler;
+    use crate::vm::thread::ThreadId;
+    use crate::vm::trace::{ClassLoadingLog, GcLog};
+    use crate::vm::value::{JValue, ObjectRef, Value};
+
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A clock advancing by one millisecond on every reading.
+    struct SteppingClock(Mutex<i64>);
+
+    impl Clock for SteppingClock {
+        fn current_time_millis(&self) -> i64 {
+            *self.0.lock().unwrap()
+        }
+
+        fn nano_time(&self) -> i64 {
+            let mut millis = self.0.lock().unwrap();
+            *millis += 1;
+            *millis * 1_000_000
+        }
+
+        fn sleep(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration.as_millis() as i64;
+        }
+    }
+
+    /// Records the events of the VM as text.
+    struct RecordingListener(Arc<Mutex<Vec<String>>>);
+
+    impl VmEventListener for RecordingListener {
+        fn class_loaded(&mut self, class: &RuntimeClass) {
+            self.0.lock().unwrap().push(format!("load {}", class.name));
+        }
+
+        fn method_entry(&mut self, _: &RuntimeClass, method: &RuntimeMethod) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("entry {}", method.name));
+        }
+
+        fn method_exit(&mut self, _: &RuntimeClass, method: &RuntimeMethod) {
+            self.0.lock().unwrap().push(format!("exit {}", method.name));
+        }
+
+        fn exception_thrown(
+            &mut self,
+            _: ObjectRef,
+            class: &RuntimeClass,
+            location: Option<(&RuntimeMethod, usize)>,
+        ) {
+            let (method, pc) = location.unwrap();
+            let event = format!("throw {} at {}:{}", class.name, method.name, pc);
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn exception_caught(
+            &mut self,
+            _: ObjectRef,
+            class: &RuntimeClass,
+            method: &RuntimeMethod,
+            _: usize,
+        ) {
+            let event = format!("catch {} in {}", class.name, method.name);
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    fn embedding_class_path() -> ClassPath {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(root).unwrap());
+        class_path
+    }
+
+    fn embedding_vm() -> Vm {
+        Vm::builder()
+            .class_path(embedding_class_path())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_invoke_static_methods() {
+        let mut vm = embedding_vm();
+
+        let sum = vm.invoke_static(
+            "Calculator",
+            "add",
+            "(II)I",
+            &[JValue::Int(40), JValue::Int(2)],
+        );
+        assert_eq!(sum.unwrap(), Some(JValue::Int(42)));
+        let fibonacci = vm.invoke_static("Calculator", "fibonacci", "(I)J", &[JValue::Int(20)]);
+        assert_eq!(fibonacci.unwrap(), Some(JValue::Long(6765)));
+        let half = vm.invoke_static("Calculator", "half", "(D)D", &[JValue::Double(5.0)]);
+        assert_eq!(half.unwrap(), Some(JValue::Double(2.5)));
+    }
+
+    #[test]
+    fn test_invoke_instance_method() {
+        let mut vm = embedding_vm();
+
+        let calculator = vm
+            .new_object("Calculator", "(I)V", &[JValue::Int(10)])
+            .unwrap();
+        let result = vm.invoke_method(calculator, "offset", "(I)I", &[JValue::Int(5)]);
+        assert_eq!(result.unwrap(), Some(JValue::Int(15)));
+    }
+
+    #[test]
+    fn test_symbols_are_shared() {
+        let mut vm = embedding_vm();
+
+        let (calculator, arithmetic) = (
+            vm.find_class("Calculator").unwrap(),
+            vm.find_class("Arithmetic").unwrap(),
+        );
+        let constructor = |vm: &Vm, class| {
+            let methods = &vm.class(class).methods;
+            methods
+                .iter()
+                .find(|method| method.name == "<init>")
+                .unwrap()
+                .clone()
+        };
+        let (first, second) = (constructor(&vm, calculator), constructor(&vm, arithmetic));
+        assert_eq!(first.name, second.name);
+        assert_eq!(vm.class(calculator).name, "Calculator");
+        assert_eq!(
+            vm.symbols.get("Calculator"),
+            Some(vm.class(calculator).name.clone())
+        );
+        assert!(vm.symbols.get("NotAName").is_none());
+    }
+
+    #[test]
+    fn test_strings_are_marshalled() {
+        let mut vm = embedding_vm();
+
+        let name = vm.new_string("bvm").unwrap();
+        let greeting = vm
+            .invoke_static(
+                "Calculator",
+                "greet",
+                "(Ljava/lang/String;)Ljava/lang/String;",
+                &[JValue::Object(name)],
+            )
+            .unwrap()
+            .and_then(|greeting| greeting.as_object())
+            .unwrap();
+        assert_eq!(vm.string_value(greeting).as_deref(), Some("Hello, bvm"));
+    }
+
+    #[test]
+    fn test_string_array_arguments() {
+        let mut vm = embedding_vm();
+
+        let arguments = vm.new_string_array(&["foo", "bar"]).unwrap();
+        let last = vm
+            .invoke_static(
+                "Echo",
+                "last",
+                "([Ljava/lang/String;)Ljava/lang/String;",
+                &[JValue::Object(arguments)],
+            )
+            .unwrap()
+            .and_then(|last| last.as_object())
+            .unwrap();
+        assert_eq!(vm.string_value(last).as_deref(), Some("bar"));
+    }
+
+    #[test]
+    fn test_exceptions_are_returned() {
+        let mut vm = embedding_vm();
+
+        let caught = vm.invoke_static(
+            "Calculator",
+            "safeDivide",
+            "(II)I",
+            &[JValue::Int(1), JValue::Int(0)],
+        );
+        assert_eq!(caught.unwrap(), Some(JValue::Int(0)));
+
+        match vm.invoke_static(
+            "Calculator",
+            "divide",
+            "(II)I",
+            &[JValue::Int(1), JValue::Int(0)],
+        ) {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.ArithmeticException");
+                assert_eq!(exception.message.as_deref(), Some("/ by zero"));
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_stack_overflow_is_thrown() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .stack_size(16 * 1024)
+            .build()
+            .unwrap();
+
+        let result = vm.invoke_static("Calculator", "fibonacci", "(I)J", &[JValue::Int(10)]);
+        assert_eq!(result.unwrap(), Some(JValue::Long(55)));
+        match vm.invoke_static("Calculator", "fibonacci", "(I)J", &[JValue::Int(1000)]) {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.StackOverflowError");
+            }
+            result => panic!("Expected a stack overflow, got {:?}", result),
+        }
+        // The stack unwound, leaving room for the next invocation
+        let result = vm.invoke_static("Calculator", "fibonacci", "(I)J", &[JValue::Int(10)]);
+        assert_eq!(result.unwrap(), Some(JValue::Long(55)));
+    }
+
+    fn redefined_class(version: &str) -> Vec<u8> {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/redefined");
+        fs::read(root.join(version).join("Reloaded.class")).unwrap()
+    }
+
+    fn greet(vm: &mut Vm) -> String {
+        let reloaded = vm.new_object("Reloaded", "()V", &[]).unwrap();
+        let greeting = vm
+            .invoke_method(reloaded, "greet", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .and_then(|greeting| greeting.as_object())
+            .unwrap();
+        vm.string_value(greeting).unwrap()
+    }
+
+    #[test]
+    fn test_redefine_class() {
+        let mut vm = embedding_vm();
+        let call = |vm: &mut Vm| vm.invoke_static("Reloaded", "call", "()I", &[]).unwrap();
+
+        assert_eq!(call(&mut vm), Some(JValue::Int(11)));
+        assert_eq!(greet(&mut vm), "Hello from version 1");
+        vm.redefine_class("Reloaded", &redefined_class("body"))
+            .unwrap();
+        assert_eq!(call(&mut vm), Some(JValue::Int(23)));
+        assert_eq!(greet(&mut vm), "HELLO from version 2");
+    }
+
+    #[test]
+    fn test_redefine_class_rejects_other_changes() {
+        let mut vm = embedding_vm();
+
+        match vm.redefine_class("Reloaded", &redefined_class("field")) {
+            Err(VmError::UnsupportedRedefinition(message)) => {
+                assert_eq!(message, "+ field Reloaded.calls:I");
+            }
+            result => panic!("Expected the redefinition to fail, got {:?}", result),
+        }
+        let result = vm.redefine_class("Calculator", &redefined_class("body"));
+        assert!(matches!(result, Err(VmError::UnsupportedRedefinition(_))));
+        let result = vm.redefine_class("java/lang/Object", &redefined_class("body"));
+        assert!(matches!(result, Err(VmError::UnsupportedRedefinition(_))));
+        assert_eq!(greet(&mut vm), "Hello from version 1");
+    }
+
+    #[test]
+    fn test_transformers_rewrite_defined_classes() {
+        let transformed = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transformed.clone();
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .transformer(move |name: &str, loader, _: &[u8]| {
+                recorded.lock().unwrap().push((name.to_string(), loader));
+                (name == "Reloaded").then(|| redefined_class("body"))
+            })
+            .build()
+            .unwrap();
+
+        let result = vm.invoke_static("Reloaded", "call", "()I", &[]).unwrap();
+        assert_eq!(result, Some(JValue::Int(23)));
+        assert_eq!(greet(&mut vm), "HELLO from version 2");
+        let transformed = transformed.lock().unwrap();
+        assert!(transformed.contains(&("Reloaded".to_string(), LoaderId::APPLICATION)));
+    }
+
+    #[test]
+    fn test_exit_runs_shutdown_hooks_only() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .stdout(SharedOutput(output.clone()))
+            .build()
+            .unwrap();
+
+        let result = vm.invoke_static("Shutdown", "exit", "(I)V", &[JValue::Int(3)]);
+        assert!(matches!(result, Err(VmError::Exit(3))));
+        assert_eq!(output.lock().unwrap().as_slice(), b"hook\n");
+    }
+
+    /// Serves `Remote` of `res/remote`, its reads pending once.
+    struct RemoteSource(PathBuf);
+
+    impl AsyncClassSource for RemoteSource {
+        fn location(&self) -> &Path {
+            &self.0
+        }
+
+        fn resource_names(&self) -> BoxFuture<io::Result<Vec<String>>> {
+            Box::pin(async { Ok(vec!["Remote.class".to_string()]) })
+        }
+
+        fn read_resource(&self, name: &str) -> BoxFuture<io::Result<Option<Vec<u8>>>> {
+            let path = self.0.join(name);
+            let mut polled = false;
+            Box::pin(async move {
+                future::poll_fn(|context| {
+                    if polled {
+                        return Poll::Ready(());
+                    }
+                    polled = true;
+                    context.waker().wake_by_ref();
+                    Poll::Pending
+                })
+                .await;
+                Ok(fs::read(path).ok())
+            })
+        }
+    }
+
+    #[test]
+    fn test_started_threads_run_while_reads_pend() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/remote");
+        let mut class_path = embedding_class_path();
+        let source = BlockingSource::new(RemoteSource(root));
+        class_path.push(ClassPathEntry::Source(Box::new(source)));
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+
+        let result = vm
+            .invoke_static("Pending", "load", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .and_then(|result| result.as_object())
+            .unwrap();
+        assert_eq!(vm.string_value(result).unwrap(), "loading thread remote ");
+    }
+
+    #[test]
+    fn test_uncaught_exception_handlers() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .stdout(SharedOutput(output.clone()))
+            .stderr(SharedOutput(errors.clone()))
+            .build()
+            .unwrap();
+
+        let result = vm
+            .invoke_static("Uncaught", "handlers", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .and_then(|result| result.as_object())
+            .unwrap();
+        assert_eq!(
+            vm.string_value(result).unwrap(),
+            "thread: own first\ndefault: other second\ntrue"
+        );
+        let printed = String::from_utf8(errors.lock().unwrap().clone()).unwrap();
+        assert!(printed.starts_with(
+            "Exception in thread \"unhandled\" java.lang.RuntimeException: printed\n"
+        ));
+        assert!(printed.ends_with(
+            "\nException: java.lang.IllegalStateException thrown from the \
+             UncaughtExceptionHandler in thread \"failing\"\n"
+        ));
+
+        // The exception escaping to the embedder goes to the handler of the
+        // main thread, the other threads running at shutdown
+        errors.lock().unwrap().clear();
+        let arguments = vm.new_string_array(&["handled"]).unwrap();
+        let result = vm.invoke_static(
+            "Uncaught",
+            "main",
+            "([Ljava/lang/String;)V",
+            &[JValue::Object(arguments)],
+        );
+        match result {
+            Err(VmError::Exception(exception)) => {
+                vm.dispatch_uncaught_exception(exception.object).unwrap()
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        }
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.ends_with("main: main in main\n"));
+        let printed = String::from_utf8(errors.lock().unwrap().clone()).unwrap();
+        assert!(printed
+            .starts_with("Exception in thread \"worker\" java.lang.RuntimeException: in thread\n"));
+    }
+
+    #[test]
+    fn test_dry_link() {
+        let mut vm = embedding_vm();
+
+        let report = vm.dry_link("DryLink").unwrap();
+        assert_eq!(report.classes, ["DryLink", "DryLinkTarget"]);
+        let errors: Vec<String> = report.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "DryLink: java.lang.NoSuchMethodError: '()I DryLinkTarget.removed'",
+                "DryLink: java.lang.NoSuchFieldError: gone",
+                "DryLink: java.lang.NoClassDefFoundError: DryLink$Missing",
+            ]
+        );
+
+        // The classes linked stay usable
+        let result = vm.invoke_static("DryLink", "present", "()I", &[]);
+        assert_eq!(result.unwrap(), Some(JValue::Int(2)));
+    }
+
+    #[test]
+    fn test_arguments_are_checked() {
+        let mut vm = embedding_vm();
+
+        let result = vm.invoke_static(
+            "Calculator",
+            "add",
+            "(II)I",
+            &[JValue::Long(1), JValue::Int(2)],
+        );
+        assert!(matches!(result, Err(VmError::InvalidArguments(_))));
+        let result = vm.invoke_static("Calculator", "missing", "()V", &[]);
+        assert!(matches!(result, Err(VmError::NoSuchMethod(_))));
+    }
+
+    #[test]
+    fn test_intrinsics() {
+        let mut vm = embedding_vm();
+
+        let call = |vm: &mut Vm, name: &str, descriptor: &str| {
+            vm.invoke_static("Intrinsics", name, descriptor, &[])
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(call(&mut vm, "shift", "()I"), JValue::Int(11234));
+        assert_eq!(call(&mut vm, "stableHash", "()Z"), JValue::Int(1));
+        for (name, expected) in [
+            (
+                "copyOutOfBounds",
+                "arraycopy: last source index 11 out of bounds for int[10]",
+            ),
+            (
+                "copyMismatch",
+                "arraycopy: type mismatch: can not copy int[] into java.lang.Object[]",
+            ),
+            ("className", "[Ljava.lang.String; Intrinsics"),
+        ] {
+            let string = call(&mut vm, name, "()Ljava/lang/String;");
+            let string = string.as_object().unwrap();
+            assert_eq!(vm.string_value(string).as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_platform_natives() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .clock(SteppingClock(Mutex::new(1_000)))
+            .build()
+            .unwrap();
+
+        let elapsed = vm.invoke_static("Platform", "elapsed", "()J", &[]);
+        assert_eq!(elapsed.unwrap(), Some(JValue::Long(1_000_000)));
+        let millis = vm.invoke_static("Platform", "millis", "()J", &[]);
+        assert_eq!(millis.unwrap(), Some(JValue::Long(1_002)));
+        let processors = vm.invoke_static("Platform", "processors", "()I", &[]);
+        assert!(processors.unwrap().and_then(|count| count.as_int()) >= Some(1));
+
+        let name = vm.new_string("BVM_SURELY_UNSET_VARIABLE").unwrap();
+        let value = vm.invoke_static(
+            "Platform",
+            "env",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            &[JValue::Object(name)],
+        );
+        assert_eq!(value.unwrap(), Some(JValue::Null));
+    }
+
+    #[test]
+    fn test_record_and_replay_interactions() {
+        let platform = |log: InteractionLog| {
+            let mut vm = Vm::builder()
+                .class_path(embedding_class_path())
+                .clock(SteppingClock(Mutex::new(1_000)))
+                .interactions(log)
+                .build()
+                .unwrap();
+            let millis = vm.invoke_static("Platform", "millis", "()J", &[]);
+            let name = vm.new_string("BVM_SURELY_UNSET_VARIABLE").unwrap();
+            let descriptor = "(Ljava/lang/String;)Ljava/lang/String;";
+            let value = vm.invoke_static("Platform", "env", descriptor, &[JValue::Object(name)]);
+            let value = value
+                .unwrap()
+                .and_then(|value| value.as_object())
+                .and_then(|value| vm.string_value(value));
+            let diverged = vm.invoke_static("Platform", "millis", "()J", &[]).is_err();
+            vm.shutdown().unwrap();
+            (millis.unwrap(), value, diverged)
+        };
+
+        let recording = Arc::new(Mutex::new(Vec::new()));
+        let recorded = platform(InteractionLog::record(SharedOutput(recording.clone())));
+        assert_eq!(recorded, (Some(JValue::Long(1_000)), None, false));
+        let recording = recording.lock().unwrap().clone();
+        let replayed = platform(InteractionLog::replay(recording.as_slice()).unwrap());
+        assert_eq!(replayed, recorded);
+
+        // The recording answers in place of the host, until it ends
+        let recording = b"currentTimeMillis\t\t42\ngetenv\tBVM_SURELY_UNSET_VARIABLE\tset\n";
+        let replayed = platform(InteractionLog::replay(recording.as_slice()).unwrap());
+        assert_eq!(
+            replayed,
+            (Some(JValue::Long(42)), Some("set".to_string()), true)
+        );
+    }
+
+    #[test]
+    fn test_runtime_memory() {
+        let memory = |vm: &mut Vm, name: &str| match vm.invoke_static("Platform", name, "()J", &[])
+        {
+            Ok(Some(JValue::Long(bytes))) => bytes,
+            other => panic!("Platform.{} returned {:?}", name, other),
+        };
+
+        let mut vm = embedding_vm();
+        let (free, total) = (
+            memory(&mut vm, "freeMemory"),
+            memory(&mut vm, "totalMemory"),
+        );
+        assert!(0 <= free && free <= total);
+        assert!(total as usize >= vm.heap_stats().used_bytes);
+        assert_eq!(memory(&mut vm, "maxMemory"), i64::MAX);
+
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .limits(ExecutionLimits::default().max_heap_bytes(64 << 20))
+            .build()
+            .unwrap();
+        let (free, total) = (
+            memory(&mut vm, "freeMemory"),
+            memory(&mut vm, "totalMemory"),
+        );
+        assert!(0 <= free && free <= total && total <= 64 << 20);
+        assert_eq!(memory(&mut vm, "maxMemory"), 64 << 20);
+    }
+
+    #[test]
+    fn test_charsets() {
+        let mut vm = embedding_vm();
+        let call = |vm: &mut Vm, name: &str, text: &str, charset: &str| {
+            let args = [
+                JValue::Object(vm.new_string(text).unwrap()),
+                JValue::Object(vm.new_string(charset).unwrap()),
+            ];
+            let descriptor = if name == "encodedLength" {
+                "(Ljava/lang/String;Ljava/lang/String;)I"
+            } else {
+                "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;"
+            };
+            match vm.invoke_static("Text", name, descriptor, &args).unwrap() {
+                Some(JValue::Object(text)) => vm.string_value(text).unwrap(),
+                Some(JValue::Int(length)) => length.to_string(),
+                other => panic!("Text.{} returned {:?}", name, other),
+            }
+        };
+
+        assert_eq!(call(&mut vm, "encodedLength", "aé€😀", "UTF-8"), "10");
+        assert_eq!(call(&mut vm, "encodedLength", "aé€😀", "latin1"), "4");
+        assert_eq!(call(&mut vm, "roundTrip", "aé€😀", "UTF-8"), "aé€😀");
+        assert_eq!(call(&mut vm, "roundTrip", "aé€😀", "ISO-8859-1"), "aé??");
+        assert_eq!(call(&mut vm, "roundTrip", "aé€😀", "US-ASCII"), "a???");
+
+        let decoded = vm.invoke_static("Text", "decodeRange", "()Ljava/lang/String;", &[]);
+        let decoded = decoded.unwrap().and_then(|text| text.as_object());
+        assert_eq!(
+            decoded.and_then(|text| vm.string_value(text)).as_deref(),
+            Some("éx\u{fffd}\u{fffd}y")
+        );
+        let default = vm.invoke_static("Text", "defaultCharset", "()Z", &[]);
+        assert_eq!(default.unwrap(), Some(JValue::Int(1)));
+        let unsupported = vm.invoke_static("Text", "unsupported", "()Ljava/lang/String;", &[]);
+        let unsupported = unsupported.unwrap().and_then(|text| text.as_object());
+        assert_eq!(
+            unsupported
+                .and_then(|text| vm.string_value(text))
+                .as_deref(),
+            Some("EBCDIC")
+        );
+    }
+
+    #[test]
+    fn test_zip_streams() {
+        let mut vm = embedding_vm();
+        let check = JValue::Object(vm.new_string("123456789").unwrap());
+        let crc = vm.invoke_static("Zip", "crc", "(Ljava/lang/String;)J", &[check]);
+        assert_eq!(crc.unwrap(), Some(JValue::Long(0xcbf4_3926)));
+
+        let text = "bvm inflates what it deflates, ".repeat(8);
+        for nowrap in [0, 1] {
+            let args = [
+                JValue::Object(vm.new_string(&text).unwrap()),
+                JValue::Int(nowrap),
+            ];
+            let descriptor = "(Ljava/lang/String;Z)Ljava/lang/String;";
+            let inflated = vm.invoke_static("Zip", "roundTrip", descriptor, &args);
+            let inflated = inflated.unwrap().and_then(|text| text.as_object());
+            assert_eq!(
+                inflated.and_then(|text| vm.string_value(text)),
+                Some(text.clone())
+            );
+        }
+
+        for (method, expected) in [
+            ("corrupt", "DataFormatException"),
+            ("closed", "Inflater has been closed"),
+        ] {
+            let message = vm.invoke_static("Zip", method, "()Ljava/lang/String;", &[]);
+            let message = message.unwrap().and_then(|text| text.as_object());
+            assert_eq!(
+                message.and_then(|text| vm.string_value(text)).as_deref(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_processes() {
+        let string = |vm: &mut Vm, value: Result<Option<JValue>, VmError>| {
+            let value = value.unwrap().and_then(|value| value.as_object());
+            value.and_then(|value| vm.string_value(value))
+        };
+
+        let mut vm = embedding_vm();
+        let descriptor = "(Ljava/lang/String;Z)Ljava/lang/String;";
+        let script = "echo out; echo err >&2; exit 3";
+        for (redirect, expected) in [(0, "out\n|err\n|3"), (1, "out\nerr\n||3")] {
+            let args = [
+                JValue::Object(vm.new_string(script).unwrap()),
+                JValue::Int(redirect),
+            ];
+            let output = vm.invoke_static("Processes", "shell", descriptor, &args);
+            assert_eq!(string(&mut vm, output).as_deref(), Some(expected));
+        }
+
+        let text = JValue::Object(vm.new_string("piped through").unwrap());
+        let descriptor = "(Ljava/lang/String;)Ljava/lang/String;";
+        let output = vm.invoke_static("Processes", "cat", descriptor, &[text]);
+        assert_eq!(
+            string(&mut vm, output).as_deref(),
+            Some("piped through|0|false")
+        );
+
+        let program = JValue::Object(vm.new_string("/bvm/surely/missing").unwrap());
+        let message = vm.invoke_static("Processes", "failure", descriptor, &[program]);
+        let message = string(&mut vm, message).unwrap();
+        assert!(message.starts_with("Cannot run program \"/bvm/surely/missing\""));
+
+        // Sandboxed guests may not start processes
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .policy(VmPolicy::sandboxed())
+            .build()
+            .unwrap();
+        let program = JValue::Object(vm.new_string("true").unwrap());
+        let message = vm.invoke_static("Processes", "failure", descriptor, &[program]);
+        assert_eq!(
+            string(&mut vm, message).as_deref(),
+            Some("Access denied: process execution")
+        );
+    }
+
+    #[test]
+    fn test_sandboxed_builtins() {
+        let denied = |result: Result<Option<JValue>, VmError>| match result {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.SecurityException");
+                exception.message.unwrap()
+            }
+            result => panic!("Expected a SecurityException, got {:?}", result),
+        };
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .policy(VmPolicy::sandboxed())
+            .build()
+            .unwrap();
+
+        let (echo, message) = (
+            vm.new_string("echo").unwrap(),
+            vm.new_string("denied").unwrap(),
+        );
+        let invoke = "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/Object;";
+        let args = [JValue::Object(echo), JValue::Object(message)];
+        let result = vm.invoke_static("Reflection", "invoke", invoke, &args);
+        assert_eq!(denied(result), "Access denied: reflective access");
+
+        // The reflection of java.lang.Class, listing members and creating
+        // instances, is denied as well
+        let label = JValue::Object(vm.new_string("label").unwrap());
+        let string = "(Ljava/lang/String;)Ljava/lang/String;";
+        let calls = [
+            ("className", string, vec![label]),
+            ("construct", string, vec![label]),
+            ("relabel", string, vec![label]),
+            ("declaredMethods", "()I", Vec::new()),
+            ("declaredConstructors", "()I", Vec::new()),
+            ("declaredFields", "()I", Vec::new()),
+        ];
+        for (name, descriptor, args) in calls {
+            let result = vm.invoke_static("Reflection", name, descriptor, &args);
+            assert_eq!(
+                denied(result),
+                "Access denied: reflective access",
+                "{}",
+                name
+            );
+        }
+
+        let name = JValue::Object(vm.new_string("PATH").unwrap());
+        let descriptor = "(Ljava/lang/String;)Ljava/lang/String;";
+        let result = vm.invoke_static("Platform", "env", descriptor, &[name]);
+        assert_eq!(denied(result), "Access denied: process execution");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_channels() {
+        let string = |vm: &mut Vm, value: Result<Option<JValue>, VmError>| {
+            let value = value.unwrap().and_then(|value| value.as_object());
+            value.and_then(|value| vm.string_value(value))
+        };
+        let file = std::env::temp_dir().join(format!("bvm-channels-{}", std::process::id()));
+        let file_name = file.to_str().unwrap();
+
+        let mut vm = embedding_vm();
+        let args = [
+            JValue::Object(vm.new_string(file_name).unwrap()),
+            JValue::Object(vm.new_string("through a channel").unwrap()),
+        ];
+        let descriptor = "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;";
+        let output = vm.invoke_static("Channels", "roundTrip", descriptor, &args);
+        assert_eq!(
+            string(&mut vm, output).as_deref(),
+            Some("through a channel|17|17|17|17|rough a channel")
+        );
+
+        let descriptor = "(Ljava/lang/String;)Ljava/lang/String;";
+        let path = [JValue::Object(vm.new_string(file_name).unwrap())];
+        let output = vm.invoke_static("Channels", "mapped", descriptor, &path);
+        assert_eq!(
+            string(&mut vm, output).as_deref(),
+            Some("THROUGH A CHANNEL---!|true|true|read-only")
+        );
+        assert_eq!(std::fs::read(&file).unwrap(), b"THROUGH A CHANNEL---!");
+
+        let output = vm.invoke_static("Channels", "closed", descriptor, &path);
+        assert_eq!(string(&mut vm, output).as_deref(), Some("closed|false"));
+        std::fs::remove_file(&file).unwrap();
+
+        let output = vm.invoke_static("Channels", "failure", descriptor, &path);
+        assert_eq!(
+            string(&mut vm, output),
+            Some(format!("java.nio.file.NoSuchFileException: {}", file_name))
+        );
+
+        // Sandboxed guests may only open the files they are permitted to
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .policy(VmPolicy::sandboxed())
+            .build()
+            .unwrap();
+        let path = [JValue::Object(vm.new_string(file_name).unwrap())];
+        let output = vm.invoke_static("Channels", "failure", descriptor, &path);
+        assert_eq!(
+            string(&mut vm, output),
+            Some(format!(
+                "java.lang.SecurityException: Access denied: read access to {}",
+                file_name
+            ))
+        );
+    }
+
+    #[test]
+    fn test_thread_locals() {
+        let mut vm = embedding_vm();
+        let values = vm.invoke_static("Locals", "values", "()Ljava/lang/String;", &[]);
+        let values = values.unwrap().and_then(|value| value.as_object()).unwrap();
+        assert_eq!(
+            vm.string_value(values).as_deref(),
+            Some("worker|1|parent+child/main|2|parent|null/0|changed|2")
+        );
+
+        // The values of a terminated thread are not kept alive by it
+        vm.collect_garbage().unwrap();
+        let collected = vm.invoke_static("Locals", "heldCollected", "()Z", &[]);
+        assert_eq!(collected.unwrap(), Some(JValue::Int(1)));
+    }
+
+    #[test]
+    fn test_class_values() {
+        let mut vm = embedding_vm();
+        let values = vm.invoke_static("ClassValues", "values", "()Ljava/lang/String;", &[]);
+        let values = values.unwrap().and_then(|value| value.as_object()).unwrap();
+        assert_eq!(
+            vm.string_value(values).as_deref(),
+            Some("java.lang.String#1|true|ClassValues#2|java.lang.String#3|3")
+        );
+
+        // The values go with their class value
+        vm.invoke_static("ClassValues", "drop", "()V", &[]).unwrap();
+        vm.collect_garbage().unwrap();
+        let collected = vm.invoke_static("ClassValues", "droppedCollected", "()Z", &[]);
+        assert_eq!(collected.unwrap(), Some(JValue::Int(1)));
+    }
+
+    #[test]
+    fn test_assertions() {
+        let outcomes = |assertions: AssertionStatus| {
+            let mut vm = Vm::builder()
+                .class_path(embedding_class_path())
+                .assertions(assertions)
+                .build()
+                .unwrap();
+            let mut outcomes = Vec::new();
+            for (name, arguments) in [
+                ("check", &[JValue::Int(-3)][..]),
+                ("check", &[JValue::Int(3)]),
+                ("nested", &[]),
+                ("primitive", &[]),
+            ] {
+                let descriptor = match arguments {
+                    [] => "()Ljava/lang/String;",
+                    _ => "(I)Ljava/lang/String;",
+                };
+                let result = vm.invoke_static("Assertions", name, descriptor, arguments);
+                let result = result.unwrap().and_then(|value| value.as_object()).unwrap();
+                outcomes.push(vm.string_value(result).unwrap());
+            }
+            outcomes.join("|")
+        };
+
+        let disabled = "passed|passed|passed|passed";
+        let enabled = "not positive: -3|passed|failed|42";
+        assert_eq!(outcomes(AssertionStatus::default()), disabled);
+        assert_eq!(outcomes(AssertionStatus::default().enable("")), enabled);
+        assert_eq!(outcomes(AssertionStatus::default().enable("...")), enabled);
+        // Nested classes follow their top-level class
+        assert_eq!(
+            outcomes(AssertionStatus::default().enable("Assertions")),
+            enabled
+        );
+        assert_eq!(
+            outcomes(AssertionStatus::default().enable("").disable("...")),
+            disabled
+        );
+        assert_eq!(
+            outcomes(AssertionStatus::default().enable("java.lang...")),
+            disabled
+        );
+    }
+
+    #[test]
+    fn test_junit_runner() {
+        // The stubs of the JUnit annotations, and the tests using them
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/junit");
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(root).unwrap());
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+        let summary = |results: &[TestResult]| {
+            results
+                .iter()
+                .map(|result| match &result.outcome {
+                    TestOutcome::Passed => format!("{} passed", result.name),
+                    TestOutcome::Failed(failure) => {
+                        format!("{} failed: {}", result.name, failure.description)
+                    }
+                    TestOutcome::Errored(failure) => {
+                        format!("{} errored: {}", result.name, failure.description)
+                    }
+                    TestOutcome::Skipped => format!("{} skipped", result.name),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let results = junit::run_tests(&mut vm, "JUnitSample").unwrap();
+        assert_eq!(
+            summary(&results),
+            [
+                "inherited passed",
+                "passes passed",
+                "fails failed: java.lang.AssertionError: expected:<3> but was:<2>",
+                "errors errored: java.lang.NullPointerException",
+                "divides passed",
+                "missesException failed: Expected exception: java.lang.ArithmeticException",
+                "throwsOther errored: Unexpected exception, \
+                 expected<java.lang.ArithmeticException> but was<java.lang.IllegalStateException>",
+                "ignored skipped",
+            ]
+        );
+        let stack_trace = match &results[2].outcome {
+            TestOutcome::Failed(failure) => failure.stack_trace.clone().unwrap(),
+            outcome => panic!("Expected a failure, got {:?}", outcome),
+        };
+        assert!(stack_trace.contains("\tat JUnitSample.fails(JUnitSample.java:"));
+
+        let log = vm.invoke_static("JUnitSample", "log", "()Ljava/lang/String;", &[]);
+        let log = log.unwrap().and_then(|value| value.as_object()).unwrap();
+        let around = |test: &str| format!("base-before;before;{}after;base-after;", test);
+        let expected = format!(
+            "class;{}{}done;",
+            around("inherited;"),
+            around("").repeat(6)
+        );
+        assert_eq!(vm.string_value(log), Some(expected));
+
+        let results = junit::run_tests(&mut vm, "JupiterSample").unwrap();
+        assert_eq!(
+            summary(&results),
+            [
+                "usesState passed",
+                "breaksState errored: java.lang.IllegalStateException: left broken",
+                "disabled skipped",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replayed_processors() {
+        let recording = b"availableProcessors\t\t3\n";
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .interactions(InteractionLog::replay(recording.as_slice()).unwrap())
+            .build()
+            .unwrap();
+        let processors = vm.invoke_static("Platform", "processors", "()I", &[]);
+        assert_eq!(processors.unwrap(), Some(JValue::Int(3)));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut vm = embedding_vm();
+        let collatz = vm.invoke_static("Arithmetic", "collatz", "(I)I", &[JValue::Int(1000)]);
+        assert_eq!(collatz.unwrap(), Some(JValue::Int(178)));
+        let fib = vm.invoke_static("Arithmetic", "fib", "(I)I", &[JValue::Int(15)]);
+        assert_eq!(fib.unwrap(), Some(JValue::Int(610)));
+    }
+
+    #[test]
+    fn test_legacy_subroutines() {
+        // Calculator.add as an old compiler would emit a finally block,
+        // without a StackMapTable: a += b, then twice a subroutine
+        // incrementing a
+        #[rustfmt::skip]
+        let code = vec![
+            0x1a, 0x1b, 0x60, 0x3b,
+            0xa8, 0x00, 0x0a,
+            0xc9, 0x00, 0x00, 0x00, 0x07,
+            0x1a, 0xac,
+            0x4d, 0x84, 0x00, 0x01, 0xa9, 0x02,
+        ];
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class =
+            Class::parse_bytes(&fs::read(root.join("Calculator.class")).unwrap()).unwrap();
+        class.major_version = 49;
+        class.minor_version = 0;
+        let add = class.constant_pool.find_utf8("add").unwrap();
+        let method = class
+            .methods
+            .iter_mut()
+            .find(|method| method.name_index == add)
+            .unwrap();
+        for attribute in &mut method.attributes {
+            if let Attribute::Code(attribute) = attribute {
+                attribute.max_stack = 2;
+                attribute.max_locals = 3;
+                attribute.code = code.clone();
+                attribute.exception_tables.clear();
+                attribute.attributes.clear();
+            }
+        }
+
+        let legacy = std::env::temp_dir().join(format!("bvm-legacy-{}", std::process::id()));
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("Calculator.class"), class.to_bytes().unwrap()).unwrap();
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&legacy).unwrap());
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+
+        let sum = vm.invoke_static(
+            "Calculator",
+            "add",
+            "(II)I",
+            &[JValue::Int(3), JValue::Int(4)],
+        );
+        assert_eq!(sum.unwrap(), Some(JValue::Int(9)));
+        let _ = fs::remove_dir_all(&legacy);
+    }
+
+    /// Invokes `Calculator.add` with its code replaced.
+    fn add_with_code(code: Vec<u8>) -> Result<Option<JValue>, VmError> {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .transformer(move |name: &str, _, bytes: &[u8]| {
+                if name != "Calculator" {
+                    return None;
+                }
+                let mut class = Class::parse_bytes(bytes).unwrap();
+                let add = class.constant_pool.find_utf8("add").unwrap();
+                let method = class
+                    .methods
+                    .iter_mut()
+                    .find(|method| method.name_index == add)
+                    .unwrap();
+                for attribute in &mut method.attributes {
+                    if let Attribute::Code(attribute) = attribute {
+                        attribute.code = code.clone();
+                        attribute.exception_tables.clear();
+                        attribute.attributes.clear();
+                    }
+                }
+                Some(class.to_bytes().unwrap())
+            })
+            .build()
+            .unwrap();
+
+        vm.invoke_static(
+            "Calculator",
+            "add",
+            "(II)I",
+            &[JValue::Int(3), JValue::Int(4)],
+        )
+    }
+
+    #[test]
+    fn test_malformed_code_fails_verification() {
+        // lconst_0, pop, iconst_0, ireturn popping half of a long, then code
+        // ending in a wide, and in an invokedynamic without its operands
+        let codes = [
+            vec![0x09, 0x57, 0x03, 0xac],
+            vec![0x03, 0x57, 0xc4],
+            vec![0x03, 0x57, 0xba, 0x00],
+        ];
+        for code in codes {
+            match add_with_code(code) {
+                Err(VmError::Exception(exception)) => {
+                    assert_eq!(exception.class_name, "java.lang.VerifyError");
+                    let message = exception.message.unwrap();
+                    assert!(message.starts_with("Calculator.add(II)I at"), "{}", message);
+                }
+                result => panic!("Expected a VerifyError, got {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_access_control() {
+        let mut vm = embedding_vm();
+        let nestmates = vm.invoke_static("Access", "nestmates", "()I", &[]);
+        assert_eq!(nestmates.unwrap(), Some(JValue::Int(49)));
+
+        // The field made private, the other one an instance field without
+        // its initializer, and the host no longer listing its nest members
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let read = |name: &str| {
+            Class::parse_bytes(&fs::read(root.join(format!("{}.class", name))).unwrap()).unwrap()
+        };
+        let mut target = read("Access$Target");
+        for field in &mut target.fields {
+            field.access_flags = match target.constant_pool.get_utf8(field.name_index).unwrap() {
+                "value" => FieldAccessFlags::PRIVATE,
+                _ => FieldAccessFlags::empty(),
+            };
+        }
+        let clinit = target.constant_pool.find_utf8("<clinit>").unwrap();
+        target.methods.retain(|method| method.name_index != clinit);
+        let mut host = read("Access");
+        host.attributes
+            .retain(|attribute| !matches!(attribute, Attribute::NestMembers(_)));
+
+        let changed = std::env::temp_dir().join(format!("bvm-access-{}", std::process::id()));
+        fs::create_dir_all(&changed).unwrap();
+        fs::write(changed.join("Access.class"), host.to_bytes().unwrap()).unwrap();
+        fs::write(
+            changed.join("Access$Target.class"),
+            target.to_bytes().unwrap(),
+        )
+        .unwrap();
+        fs::copy(
+            root.join("Access$Inner.class"),
+            changed.join("Access$Inner.class"),
+        )
+        .unwrap();
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&changed).unwrap());
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+
+        let mut error = |name: &str| match vm.invoke_static("Access", name, "()I", &[]) {
+            Err(VmError::Exception(exception)) => {
+                format!("{}: {}", exception.class_name, exception.message.unwrap())
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        };
+        assert_eq!(
+            error("nestmates"),
+            "java.lang.IllegalAccessError: class Access$Inner tried to access private field \
+             Access.secret"
+        );
+        assert_eq!(
+            error("value"),
+            "java.lang.IllegalAccessError: class Access tried to access private field \
+             Access$Target.value"
+        );
+        assert_eq!(
+            error("shared"),
+            "java.lang.IncompatibleClassChangeError: Expected static field Access$Target.shared"
+        );
+        let _ = fs::remove_dir_all(&changed);
+    }
+
+    #[test]
+    fn test_linkage_errors() {
+        // Copies of Calculator under other names, extending other classes
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let calculator = fs::read(root.join("Calculator.class")).unwrap();
+        let broken = std::env::temp_dir().join(format!("bvm-linkage-{}", std::process::id()));
+        fs::create_dir_all(&broken).unwrap();
+        for (name, super_class) in [
+            ("Circle", "Square"),
+            ("Square", "Circle"),
+            ("Orphan", "Missing"),
+            ("Runner", "java/lang/Runnable"),
+            ("Text", "java/lang/String"),
+        ] {
+            let mut class = Class::parse_bytes(&calculator).unwrap();
+            let mut pool = ConstantPoolBuilder::from_pool(&class.constant_pool);
+            class.this_class = pool.class(name).unwrap();
+            class.super_class = pool.class(super_class).unwrap();
+            class.constant_pool = pool.build();
+            let path = broken.join(format!("{}.class", name));
+            fs::write(path, class.to_bytes().unwrap()).unwrap();
+        }
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&broken).unwrap());
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+
+        let mut error = |name: &str| match vm.find_class(name) {
+            Err(VmError::Exception(exception)) => {
+                (exception.linkage_error.unwrap(), exception.to_string())
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        };
+        assert_eq!(
+            error("Circle"),
+            (
+                LinkageError::ClassCircularity,
+                "java.lang.ClassCircularityError: Circle".to_string()
+            )
+        );
+        assert_eq!(
+            error("Orphan"),
+            (
+                LinkageError::NoClassDefFound,
+                "java.lang.NoClassDefFoundError: Missing".to_string()
+            )
+        );
+        assert_eq!(
+            error("Runner").1,
+            "java.lang.IncompatibleClassChangeError: class Runner has interface \
+             java.lang.Runnable as super class"
+        );
+        assert_eq!(error("Text").0, LinkageError::IncompatibleClassChange);
+        let _ = fs::remove_dir_all(&broken);
+    }
+
+    #[test]
+    fn test_module_access() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/modules");
+        let module_path = ModulePath::parse(root.to_str().unwrap()).unwrap();
+        let graph = ModuleGraph::resolve(&module_path, &["app"]).unwrap();
+        let mut vm = Vm::builder().modules(graph).build().unwrap();
+
+        assert_eq!(
+            vm.invoke_static("app/Main", "run", "()I", &[]).unwrap(),
+            Some(JValue::Int(9))
+        );
+        match vm.invoke_static("app/Main", "peek", "()I", &[]) {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.IllegalAccessError")
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        }
+    }
+
+    /// VMs running `Numerics` interpreted, with the optimized bytecode and,
+    /// with the `jit` feature, compiled.
+    fn numerics_vms() -> Vec<Vm> {
+        #[cfg_attr(not(feature = "jit"), allow(unused_mut))]
+        let mut vms = vec![
+            embedding_vm(),
+            Vm::builder()
+                .class_path(embedding_class_path())
+                .optimize_bytecode(true)
+                .build()
+                .unwrap(),
+        ];
+        #[cfg(feature = "jit")]
+        vms.push(
+            Vm::builder()
+                .class_path(embedding_class_path())
+                .jit(
+                    crate::vm::jit::JitCompiler::new().mode(crate::vm::jit::CompilationMode::Eager),
+                )
+                .build()
+                .unwrap(),
+        );
+        vms
+    }
+
+    /// Results of the floating-point instructions and conversions, as the
+    /// reference JVM computes them, NaN standing for any NaN.
+    #[rustfmt::skip]
+    const NUMERICS: &[(&str, &str, &[JValue], JValue)] = &[
+            ("f2i", "(F)I", &[JValue::Float(f32::NAN)], JValue::Int(0)),
+            ("f2i", "(F)I", &[JValue::Float(f32::INFINITY)], JValue::Int(i32::MAX)),
+            ("f2i", "(F)I", &[JValue::Float(f32::NEG_INFINITY)], JValue::Int(i32::MIN)),
+            ("f2i", "(F)I", &[JValue::Float(3e9)], JValue::Int(i32::MAX)),
+            ("f2i", "(F)I", &[JValue::Float(-3e9)], JValue::Int(i32::MIN)),
+            ("f2i", "(F)I", &[JValue::Float(-2.5)], JValue::Int(-2)),
+            ("f2i", "(F)I", &[JValue::Float(-0.0)], JValue::Int(0)),
+            ("f2l", "(F)J", &[JValue::Float(f32::NAN)], JValue::Long(0)),
+            ("f2l", "(F)J", &[JValue::Float(1e19)], JValue::Long(i64::MAX)),
+            ("f2l", "(F)J", &[JValue::Float(-1e19)], JValue::Long(i64::MIN)),
+            ("f2l", "(F)J", &[JValue::Float(-1.5)], JValue::Long(-1)),
+            ("f2d", "(F)D", &[JValue::Float(0.1)], JValue::Double(0.10000000149011612)),
+            ("f2d", "(F)D", &[JValue::Float(f32::NAN)], JValue::Double(f64::NAN)),
+            ("d2i", "(D)I", &[JValue::Double(f64::NAN)], JValue::Int(0)),
+            ("d2i", "(D)I", &[JValue::Double(1e10)], JValue::Int(i32::MAX)),
+            ("d2i", "(D)I", &[JValue::Double(-1e10)], JValue::Int(i32::MIN)),
+            ("d2i", "(D)I", &[JValue::Double(-0.9)], JValue::Int(0)),
+            ("d2i", "(D)I", &[JValue::Double(2147483647.9)], JValue::Int(i32::MAX)),
+            ("d2l", "(D)J", &[JValue::Double(f64::NAN)], JValue::Long(0)),
+            ("d2l", "(D)J", &[JValue::Double(f64::INFINITY)], JValue::Long(i64::MAX)),
+            ("d2l", "(D)J", &[JValue::Double(f64::NEG_INFINITY)], JValue::Long(i64::MIN)),
+            ("d2l", "(D)J", &[JValue::Double(9.3e18)], JValue::Long(i64::MAX)),
+            ("d2l", "(D)J", &[JValue::Double(-9.3e18)], JValue::Long(i64::MIN)),
+            ("d2f", "(D)F", &[JValue::Double(1e40)], JValue::Float(f32::INFINITY)),
+            ("d2f", "(D)F", &[JValue::Double(-1e40)], JValue::Float(f32::NEG_INFINITY)),
+            ("d2f", "(D)F", &[JValue::Double(1e-50)], JValue::Float(0.0)),
+            ("d2f", "(D)F", &[JValue::Double(-1e-50)], JValue::Float(-0.0)),
+            ("d2f", "(D)F", &[JValue::Double(f64::NAN)], JValue::Float(f32::NAN)),
+            ("d2f", "(D)F", &[JValue::Double(16777217.0)], JValue::Float(16777216.0)),
+            ("d2f", "(D)F", &[JValue::Double(1.0000000596046448)], JValue::Float(1.0)),
+            ("l2f", "(J)F", &[JValue::Long(16777217)], JValue::Float(16777216.0)),
+            ("l2f", "(J)F", &[JValue::Long(i64::MAX)], JValue::Float(9.223372e18)),
+            ("l2f", "(J)F", &[JValue::Long(-1)], JValue::Float(-1.0)),
+            ("l2d", "(J)D", &[JValue::Long(9007199254740993)], JValue::Double(9007199254740992.0)),
+            ("l2d", "(J)D", &[JValue::Long(i64::MIN)], JValue::Double(-9.223372036854776e18)),
+            ("i2f", "(I)F", &[JValue::Int(16777217)], JValue::Float(16777216.0)),
+            ("i2f", "(I)F", &[JValue::Int(i32::MAX)], JValue::Float(2.1474836e09)),
+            ("i2b", "(I)I", &[JValue::Int(128)], JValue::Int(-128)),
+            ("i2b", "(I)I", &[JValue::Int(255)], JValue::Int(-1)),
+            ("i2b", "(I)I", &[JValue::Int(-129)], JValue::Int(127)),
+            ("i2c", "(I)I", &[JValue::Int(-1)], JValue::Int(65535)),
+            ("i2c", "(I)I", &[JValue::Int(65536)], JValue::Int(0)),
+            ("i2s", "(I)I", &[JValue::Int(32768)], JValue::Int(-32768)),
+            ("i2s", "(I)I", &[JValue::Int(65535)], JValue::Int(-1)),
+            ("less", "(FF)Z", &[JValue::Float(f32::NAN), JValue::Float(1.0)], JValue::Int(0)),
+            ("less", "(FF)Z", &[JValue::Float(1.0), JValue::Float(f32::NAN)], JValue::Int(0)),
+            ("less", "(FF)Z", &[JValue::Float(-0.0), JValue::Float(0.0)], JValue::Int(0)),
+            ("less", "(FF)Z", &[JValue::Float(1.0), JValue::Float(2.0)], JValue::Int(1)),
+            ("greater", "(FF)Z", &[JValue::Float(f32::NAN), JValue::Float(1.0)], JValue::Int(0)),
+            ("greater", "(FF)Z", &[JValue::Float(1.0), JValue::Float(f32::NAN)], JValue::Int(0)),
+            ("greater", "(FF)Z", &[JValue::Float(f32::INFINITY), JValue::Float(f32::MAX)], JValue::Int(1)),
+            ("less", "(DD)Z", &[JValue::Double(f64::NAN), JValue::Double(1.0)], JValue::Int(0)),
+            ("less", "(DD)Z", &[JValue::Double(1.0), JValue::Double(f64::NAN)], JValue::Int(0)),
+            ("greater", "(DD)Z", &[JValue::Double(f64::NAN), JValue::Double(1.0)], JValue::Int(0)),
+            ("greater", "(DD)Z", &[JValue::Double(1.0), JValue::Double(f64::NAN)], JValue::Int(0)),
+            ("greater", "(DD)Z", &[JValue::Double(0.0), JValue::Double(-0.0)], JValue::Int(0)),
+            ("equal", "(DD)Z", &[JValue::Double(-0.0), JValue::Double(0.0)], JValue::Int(1)),
+            ("equal", "(DD)Z", &[JValue::Double(f64::NAN), JValue::Double(f64::NAN)], JValue::Int(0)),
+            ("add", "(FF)F", &[JValue::Float(f32::MAX), JValue::Float(f32::MAX)], JValue::Float(f32::INFINITY)),
+            ("add", "(FF)F", &[JValue::Float(f32::INFINITY), JValue::Float(f32::NEG_INFINITY)], JValue::Float(f32::NAN)),
+            ("add", "(FF)F", &[JValue::Float(-0.0), JValue::Float(-0.0)], JValue::Float(-0.0)),
+            ("add", "(FF)F", &[JValue::Float(-0.0), JValue::Float(0.0)], JValue::Float(0.0)),
+            ("multiply", "(FF)F", &[JValue::Float(-0.0), JValue::Float(5.0)], JValue::Float(-0.0)),
+            ("multiply", "(FF)F", &[JValue::Float(f32::INFINITY), JValue::Float(0.0)], JValue::Float(f32::NAN)),
+            ("multiply", "(FF)F", &[JValue::Float(f32::from_bits(1)), JValue::Float(0.5)], JValue::Float(0.0)),
+            ("multiply", "(FF)F", &[JValue::Float(f32::from_bits(3)), JValue::Float(0.5)], JValue::Float(f32::from_bits(2))),
+            ("divide", "(FF)F", &[JValue::Float(1.0), JValue::Float(0.0)], JValue::Float(f32::INFINITY)),
+            ("divide", "(FF)F", &[JValue::Float(1.0), JValue::Float(-0.0)], JValue::Float(f32::NEG_INFINITY)),
+            ("divide", "(FF)F", &[JValue::Float(0.0), JValue::Float(0.0)], JValue::Float(f32::NAN)),
+            ("remainder", "(FF)F", &[JValue::Float(5.5), JValue::Float(2.0)], JValue::Float(1.5)),
+            ("remainder", "(FF)F", &[JValue::Float(-5.5), JValue::Float(2.0)], JValue::Float(-1.5)),
+            ("remainder", "(FF)F", &[JValue::Float(5.5), JValue::Float(-2.0)], JValue::Float(1.5)),
+            ("remainder", "(FF)F", &[JValue::Float(1.0), JValue::Float(0.0)], JValue::Float(f32::NAN)),
+            ("remainder", "(FF)F", &[JValue::Float(f32::INFINITY), JValue::Float(1.0)], JValue::Float(f32::NAN)),
+            ("remainder", "(FF)F", &[JValue::Float(1.0), JValue::Float(f32::INFINITY)], JValue::Float(1.0)),
+            ("remainder", "(FF)F", &[JValue::Float(-0.0), JValue::Float(1.0)], JValue::Float(-0.0)),
+            ("add", "(DD)D", &[JValue::Double(0.1), JValue::Double(0.2)], JValue::Double(0.30000000000000004)),
+            ("divide", "(DD)D", &[JValue::Double(-1.0), JValue::Double(0.0)], JValue::Double(f64::NEG_INFINITY)),
+            ("divide", "(DD)D", &[JValue::Double(0.0), JValue::Double(-0.0)], JValue::Double(f64::NAN)),
+            ("remainder", "(DD)D", &[JValue::Double(-7.5), JValue::Double(2.0)], JValue::Double(-1.5)),
+            ("remainder", "(DD)D", &[JValue::Double(1e300), JValue::Double(3.0)], JValue::Double(0.0)),
+            ("remainder", "(DD)D", &[JValue::Double(-0.0), JValue::Double(3.0)], JValue::Double(-0.0)),
+            ("remainder", "(DD)D", &[JValue::Double(3.0), JValue::Double(f64::NEG_INFINITY)], JValue::Double(3.0)),
+            ("negate", "(D)D", &[JValue::Double(0.0)], JValue::Double(-0.0)),
+            ("negate", "(D)D", &[JValue::Double(f64::NAN)], JValue::Double(f64::NAN)),
+    ];
+
+    #[test]
+    fn test_floating_point_semantics() {
+        let same = |a: JValue, b: JValue| match (a, b) {
+            (JValue::Float(a), JValue::Float(b)) => {
+                (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+            }
+            (JValue::Double(a), JValue::Double(b)) => {
+                (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+            }
+            (a, b) => a == b,
+        };
+        for vm in &mut numerics_vms() {
+            for &(name, descriptor, arguments, expected) in NUMERICS {
+                let result = vm
+                    .invoke_static("Numerics", name, descriptor, arguments)
+                    .unwrap()
+                    .unwrap();
+                assert!(
+                    same(result, expected),
+                    "Numerics.{}{} of {:?} is {:?} instead of {:?}",
+                    name,
+                    descriptor,
+                    arguments,
+                    result,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let divisions: &[(&str, &str, &[JValue], JValue)] = &[
+            (
+                "divide",
+                "(II)I",
+                &[JValue::Int(i32::MIN), JValue::Int(-1)],
+                JValue::Int(i32::MIN),
+            ),
+            (
+                "remainder",
+                "(II)I",
+                &[JValue::Int(i32::MIN), JValue::Int(-1)],
+                JValue::Int(0),
+            ),
+            (
+                "divide",
+                "(II)I",
+                &[JValue::Int(-7), JValue::Int(2)],
+                JValue::Int(-3),
+            ),
+            (
+                "remainder",
+                "(II)I",
+                &[JValue::Int(-7), JValue::Int(2)],
+                JValue::Int(-1),
+            ),
+            (
+                "remainder",
+                "(II)I",
+                &[JValue::Int(7), JValue::Int(-2)],
+                JValue::Int(1),
+            ),
+            (
+                "divide",
+                "(JJ)J",
+                &[JValue::Long(i64::MIN), JValue::Long(-1)],
+                JValue::Long(i64::MIN),
+            ),
+            (
+                "remainder",
+                "(JJ)J",
+                &[JValue::Long(i64::MIN), JValue::Long(-1)],
+                JValue::Long(0),
+            ),
+            (
+                "divide",
+                "(JJ)J",
+                &[JValue::Long(-7), JValue::Long(2)],
+                JValue::Long(-3),
+            ),
+            (
+                "remainder",
+                "(JJ)J",
+                &[JValue::Long(-7), JValue::Long(2)],
+                JValue::Long(-1),
+            ),
+        ];
+        let by_zero: &[(&str, &str, &[JValue])] = &[
+            ("divide", "(II)I", &[JValue::Int(1), JValue::Int(0)]),
+            (
+                "remainder",
+                "(II)I",
+                &[JValue::Int(i32::MIN), JValue::Int(0)],
+            ),
+            ("divide", "(JJ)J", &[JValue::Long(0), JValue::Long(0)]),
+            ("remainder", "(JJ)J", &[JValue::Long(1), JValue::Long(0)]),
+        ];
+
+        for vm in &mut numerics_vms() {
+            for &(name, descriptor, arguments, expected) in divisions {
+                let result = vm.invoke_static("Numerics", name, descriptor, arguments);
+                assert_eq!(result.unwrap(), Some(expected), "{} {:?}", name, arguments);
+            }
+            for &(name, descriptor, arguments) in by_zero {
+                match vm.invoke_static("Numerics", name, descriptor, arguments) {
+                    Err(VmError::Exception(exception)) => {
+                        assert_eq!(exception.class_name, "java.lang.ArithmeticException");
+                        assert_eq!(exception.message.as_deref(), Some("/ by zero"));
+                    }
+                    result => panic!("Expected an exception, got {:?}", result),
+                }
+            }
+        }
+    }
+
+    /// Results of the `Math` and `StrictMath` natives, as the reference JVM
+    /// computes them, NaN standing for any NaN.
+    #[rustfmt::skip]
+    const MATHS: &[(&str, &str, &[JValue], JValue)] = &[
+            ("sqrt", "(D)D", &[JValue::Double(2.0)], JValue::Double(std::f64::consts::SQRT_2)),
+            ("sqrt", "(D)D", &[JValue::Double(-1.0)], JValue::Double(f64::NAN)),
+            ("sqrt", "(D)D", &[JValue::Double(-0.0)], JValue::Double(-0.0)),
+            ("sin", "(D)D", &[JValue::Double(-0.0)], JValue::Double(-0.0)),
+            ("sin", "(D)D", &[JValue::Double(f64::INFINITY)], JValue::Double(f64::NAN)),
+            ("sin", "(D)D", &[JValue::Double(std::f64::consts::FRAC_PI_2)], JValue::Double(1.0)),
+            ("cos", "(D)D", &[JValue::Double(0.0)], JValue::Double(1.0)),
+            ("cos", "(D)D", &[JValue::Double(0.1)], JValue::Double(f64::from_bits(0x3fefd712f9a817c0))),
+            ("tan", "(D)D", &[JValue::Double(1.0)], JValue::Double(f64::from_bits(0x3ff8eb245cbee3a6))),
+            ("tan", "(D)D", &[JValue::Double(1e22)], JValue::Double(f64::from_bits(0xbffa0f79c1b6b258))),
+            ("log", "(D)D", &[JValue::Double(1e22)], JValue::Double(f64::from_bits(0x40495414621954fe))),
+            ("log", "(D)D", &[JValue::Double(-0.0)], JValue::Double(f64::NEG_INFINITY)),
+            ("cbrt", "(D)D", &[JValue::Double(27.0)], JValue::Double(3.0)),
+            ("cbrt", "(D)D", &[JValue::Double(2.0)], JValue::Double(f64::from_bits(0x3ff428a2f98d728b))),
+            ("floor", "(D)D", &[JValue::Double(-0.5)], JValue::Double(-1.0)),
+            ("floor", "(D)D", &[JValue::Double(-0.0)], JValue::Double(-0.0)),
+            ("ceil", "(D)D", &[JValue::Double(-0.5)], JValue::Double(-0.0)),
+            ("rint", "(D)D", &[JValue::Double(2.5)], JValue::Double(2.0)),
+            ("rint", "(D)D", &[JValue::Double(3.5)], JValue::Double(4.0)),
+            ("rint", "(D)D", &[JValue::Double(-2.5)], JValue::Double(-2.0)),
+            ("round", "(D)J", &[JValue::Double(0.49999999999999994)], JValue::Long(0)),
+            ("round", "(D)J", &[JValue::Double(-0.5)], JValue::Long(0)),
+            ("round", "(D)J", &[JValue::Double(-2.5)], JValue::Long(-2)),
+            ("round", "(D)J", &[JValue::Double(2.5)], JValue::Long(3)),
+            ("round", "(D)J", &[JValue::Double(f64::NAN)], JValue::Long(0)),
+            ("round", "(D)J", &[JValue::Double(1e20)], JValue::Long(i64::MAX)),
+            ("round", "(F)I", &[JValue::Float(0.5)], JValue::Int(1)),
+            ("round", "(F)I", &[JValue::Float(-1.5)], JValue::Int(-1)),
+            ("round", "(F)I", &[JValue::Float(f32::NEG_INFINITY)], JValue::Int(i32::MIN)),
+            ("pow", "(DD)D", &[JValue::Double(2.0), JValue::Double(10.0)], JValue::Double(1024.0)),
+            ("pow", "(DD)D", &[JValue::Double(1.0), JValue::Double(f64::NAN)], JValue::Double(f64::NAN)),
+            ("pow", "(DD)D", &[JValue::Double(f64::NAN), JValue::Double(0.0)], JValue::Double(1.0)),
+            ("pow", "(DD)D", &[JValue::Double(-1.0), JValue::Double(f64::INFINITY)], JValue::Double(f64::NAN)),
+            ("pow", "(DD)D", &[JValue::Double(-8.0), JValue::Double(1.0 / 3.0)], JValue::Double(f64::NAN)),
+            ("pow", "(DD)D", &[JValue::Double(-0.0), JValue::Double(-1.0)], JValue::Double(f64::NEG_INFINITY)),
+            ("strictPow", "(DD)D", &[JValue::Double(100.0), JValue::Double(0.37)], JValue::Double(f64::from_bits(0x4015fb4c6da959b0))),
+            ("max", "(DD)D", &[JValue::Double(-0.0), JValue::Double(0.0)], JValue::Double(0.0)),
+            ("max", "(DD)D", &[JValue::Double(0.0), JValue::Double(-0.0)], JValue::Double(0.0)),
+            ("max", "(DD)D", &[JValue::Double(1.0), JValue::Double(f64::NAN)], JValue::Double(f64::NAN)),
+            ("min", "(DD)D", &[JValue::Double(0.0), JValue::Double(-0.0)], JValue::Double(-0.0)),
+            ("min", "(DD)D", &[JValue::Double(-0.0), JValue::Double(0.0)], JValue::Double(-0.0)),
+            ("min", "(DD)D", &[JValue::Double(f64::NAN), JValue::Double(1.0)], JValue::Double(f64::NAN)),
+            ("signum", "(D)D", &[JValue::Double(-0.0)], JValue::Double(-0.0)),
+            ("signum", "(D)D", &[JValue::Double(-3.0)], JValue::Double(-1.0)),
+            ("signum", "(D)D", &[JValue::Double(f64::NAN)], JValue::Double(f64::NAN)),
+            ("remainder", "(DD)D", &[JValue::Double(5.0), JValue::Double(2.0)], JValue::Double(1.0)),
+            ("remainder", "(DD)D", &[JValue::Double(7.0), JValue::Double(2.0)], JValue::Double(-1.0)),
+            ("remainder", "(DD)D", &[JValue::Double(-5.0), JValue::Double(2.0)], JValue::Double(-1.0)),
+            ("remainder", "(DD)D", &[JValue::Double(-4.0), JValue::Double(2.0)], JValue::Double(-0.0)),
+            ("remainder", "(DD)D", &[JValue::Double(3.0), JValue::Double(f64::INFINITY)], JValue::Double(3.0)),
+            ("remainder", "(DD)D", &[JValue::Double(1.0), JValue::Double(0.0)], JValue::Double(f64::NAN)),
+            ("ulp", "(D)D", &[JValue::Double(1.0)], JValue::Double(f64::EPSILON)),
+            ("ulp", "(D)D", &[JValue::Double(0.0)], JValue::Double(5e-324)),
+            ("ulp", "(D)D", &[JValue::Double(f64::MAX)], JValue::Double(1.995_840_309_534_72e292)),
+            ("ulp", "(D)D", &[JValue::Double(f64::NEG_INFINITY)], JValue::Double(f64::INFINITY)),
+            ("scalb", "(DI)D", &[JValue::Double(1.5), JValue::Int(2)], JValue::Double(6.0)),
+            ("scalb", "(DI)D", &[JValue::Double(1.0), JValue::Int(1024)], JValue::Double(f64::INFINITY)),
+            ("scalb", "(DI)D", &[JValue::Double(1.0), JValue::Int(-1074)], JValue::Double(5e-324)),
+            ("scalb", "(DI)D", &[JValue::Double(1.0), JValue::Int(-1075)], JValue::Double(0.0)),
+            ("scalb", "(DI)D", &[JValue::Double(3.0), JValue::Int(-1075)], JValue::Double(1e-323)),
+            ("floorDiv", "(II)I", &[JValue::Int(-7), JValue::Int(2)], JValue::Int(-4)),
+            ("floorDiv", "(II)I", &[JValue::Int(7), JValue::Int(-2)], JValue::Int(-4)),
+            ("floorDiv", "(II)I", &[JValue::Int(i32::MIN), JValue::Int(-1)], JValue::Int(i32::MIN)),
+            ("floorMod", "(II)I", &[JValue::Int(-7), JValue::Int(2)], JValue::Int(1)),
+            ("floorMod", "(II)I", &[JValue::Int(7), JValue::Int(-2)], JValue::Int(-1)),
+            ("floorMod", "(II)I", &[JValue::Int(i32::MIN), JValue::Int(-1)], JValue::Int(0)),
+            ("floorDiv", "(JJ)J", &[JValue::Long(-7), JValue::Long(2)], JValue::Long(-4)),
+            ("floorDiv", "(JJ)J", &[JValue::Long(i64::MIN), JValue::Long(-1)], JValue::Long(i64::MIN)),
+            ("floorMod", "(JJ)J", &[JValue::Long(-7), JValue::Long(2)], JValue::Long(1)),
+            ("abs", "(I)I", &[JValue::Int(i32::MIN)], JValue::Int(i32::MIN)),
+            ("addExact", "(II)I", &[JValue::Int(1), JValue::Int(2)], JValue::Int(3)),
+            ("multiplyExact", "(JJ)J", &[JValue::Long(3_000_000_000), JValue::Long(3)], JValue::Long(9_000_000_000)),
+            ("toIntExact", "(J)I", &[JValue::Long(-5)], JValue::Int(-5)),
+            ("negateExact", "(I)I", &[JValue::Int(i32::MAX)], JValue::Int(-i32::MAX)),
+    ];
+
+    #[test]
+    fn test_math_natives() {
+        let same = |a: JValue, b: JValue| match (a, b) {
+            (JValue::Double(a), JValue::Double(b)) => {
+                (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+            }
+            (a, b) => a == b,
+        };
+        let overflows: &[(&str, &str, &[JValue], &str)] = &[
+            (
+                "addExact",
+                "(II)I",
+                &[JValue::Int(i32::MAX), JValue::Int(1)],
+                "integer overflow",
+            ),
+            (
+                "multiplyExact",
+                "(JJ)J",
+                &[JValue::Long(i64::MAX), JValue::Long(2)],
+                "long overflow",
+            ),
+            (
+                "toIntExact",
+                "(J)I",
+                &[JValue::Long(1 << 31)],
+                "integer overflow",
+            ),
+            (
+                "negateExact",
+                "(I)I",
+                &[JValue::Int(i32::MIN)],
+                "integer overflow",
+            ),
+            (
+                "floorDiv",
+                "(II)I",
+                &[JValue::Int(1), JValue::Int(0)],
+                "/ by zero",
+            ),
+            (
+                "floorMod",
+                "(JJ)J",
+                &[JValue::Long(1), JValue::Long(0)],
+                "/ by zero",
+            ),
+        ];
+
+        for vm in &mut numerics_vms() {
+            for &(name, descriptor, arguments, expected) in MATHS {
+                let result = vm
+                    .invoke_static("Maths", name, descriptor, arguments)
+                    .unwrap()
+                    .unwrap();
+                assert!(
+                    same(result, expected),
+                    "Maths.{}{} of {:?} is {:?} instead of {:?}",
+                    name,
+                    descriptor,
+                    arguments,
+                    result,
+                    expected
+                );
+            }
+            for &(name, descriptor, arguments, message) in overflows {
+                match vm.invoke_static("Maths", name, descriptor, arguments) {
+                    Err(VmError::Exception(exception)) => {
+                        assert_eq!(exception.class_name, "java.lang.ArithmeticException");
+                        assert_eq!(exception.message.as_deref(), Some(message));
+                    }
+                    result => panic!("Expected an exception, got {:?}", result),
+                }
+            }
+            let result = vm.invoke_static("Maths", "random", "()Z", &[]);
+            assert_eq!(result.unwrap(), Some(JValue::Int(1)));
+        }
+    }
+
+    #[test]
+    fn test_math_intrinsics() {
+        // A JDK whose java.lang.Math is Maths, whose sin calls Math.sin
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class = Class::parse_bytes(&fs::read(root.join("Maths.class")).unwrap()).unwrap();
+        let mut pool = ConstantPoolBuilder::from_pool(&class.constant_pool);
+        class.this_class = pool.class("java/lang/Math").unwrap();
+        class.constant_pool = pool.build();
+        let home = std::env::temp_dir().join(format!("bvm-intrinsics-{}", std::process::id()));
+        fs::create_dir_all(home.join("lib")).unwrap();
+        let mut jar = zip::ZipWriter::new(fs::File::create(home.join("lib/rt.jar")).unwrap());
+        jar.start_file("java/lang/Math.class", Default::default())
+            .unwrap();
+        jar.write_all(&class.to_bytes().unwrap()).unwrap();
+        jar.finish().unwrap();
+        let mut vm = Vm::builder().java_home(&home).build().unwrap();
+
+        // Shadowed by the VM instead of recursing
+        let sin = vm.invoke_static("java/lang/Math", "sin", "(D)D", &[JValue::Double(1e22)]);
+        assert_eq!(
+            sin.unwrap(),
+            Some(JValue::Double(f64::from_bits(0xbfeb453ab76bf397)))
+        );
+        // Run from the class file, as the VM has no Math.strictPow
+        let arguments = [JValue::Double(100.0), JValue::Double(0.37)];
+        let pow = vm.invoke_static("java/lang/Math", "strictPow", "(DD)D", &arguments);
+        assert_eq!(
+            pow.unwrap(),
+            Some(JValue::Double(f64::from_bits(0x4015fb4c6da959b0)))
+        );
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_class_archive_links() {
+        // A JDK holding the Dispatch classes, archived by a first run
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let home = std::env::temp_dir().join(format!("bvm-archive-links-{}", std::process::id()));
+        fs::create_dir_all(home.join("lib")).unwrap();
+        let mut jar = zip::ZipWriter::new(fs::File::create(home.join("lib/rt.jar")).unwrap());
+        for entry in fs::read_dir(&root).unwrap() {
+            let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+            if name.starts_with("Dispatch") && name.ends_with(".class") {
+                jar.start_file(name.as_str(), Default::default()).unwrap();
+                jar.write_all(&fs::read(root.join(&name)).unwrap()).unwrap();
+            }
+        }
+        jar.finish().unwrap();
+        let archive = home.join("classes.bvma");
+        let run = |vm: &mut Vm| {
+            let count = [JValue::Int(12)];
+            let polymorphic = vm.invoke_static("Dispatch", "polymorphic", "(I)I", &count);
+            let megamorphic = vm.invoke_static("Dispatch", "megamorphic", "(I)I", &count);
+            (polymorphic.unwrap(), megamorphic.unwrap())
+        };
+        let builder = || Vm::builder().java_home(&home).class_archive(&archive);
+        let mut vm = builder().build().unwrap();
+        let expected = run(&mut vm);
+        vm.shutdown().unwrap();
+
+        // Linked through the archived vtables and constant pool references
+        let mut vm = builder().build().unwrap();
+        assert_eq!(run(&mut vm), expected);
+        let square = vm.find_class("Dispatch$Square").unwrap();
+        let area = (vm.symbols.intern("area"), vm.symbols.intern("()I"));
+        assert_eq!(vm.class(square).vtable.get(&area), Some(&square));
+        let dispatch = vm.find_class("Dispatch").unwrap();
+        let links = vm.class(dispatch).source.as_ref().unwrap().links.as_ref();
+        assert!(!links.unwrap().resolved.is_empty());
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_resources() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let path = std::env::temp_dir().join(format!("bvm-resources-{}.jar", std::process::id()));
+        let mut jar = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        jar.start_file("Resources.class", Default::default())
+            .unwrap();
+        jar.write_all(&fs::read(root.join("Resources.class")).unwrap())
+            .unwrap();
+        jar.start_file("greeting.txt", Default::default()).unwrap();
+        jar.write_all(b"hello").unwrap();
+        jar.finish().unwrap();
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&path).unwrap());
+        let mut vm = Vm::builder().class_path(class_path).build().unwrap();
+        let mut call = |method: &str, name: &str| {
+            let name = JValue::Object(vm.new_string(name).unwrap());
+            let descriptor = "(Ljava/lang/String;)Ljava/lang/String;";
+            let result = vm
+                .invoke_static("Resources", method, descriptor, &[name])
+                .unwrap()
+                .and_then(|result| result.as_object());
+            result.and_then(|result| vm.string_value(result))
+        };
+
+        assert_eq!(call("fromClass", "greeting.txt").unwrap(), "hello");
+        assert_eq!(call("fromClass", "/greeting.txt").unwrap(), "hello");
+        assert_eq!(call("fromSystem", "greeting.txt").unwrap(), "hello");
+        assert_eq!(call("fromClass", "missing.txt"), None);
+        let url = format!("jar:file:{}!/greeting.txt hello", path.display());
+        assert_eq!(call("fromLoader", "greeting.txt").unwrap(), url);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reflection() {
+        let mut vm = embedding_vm();
+        let mut call = |name: &str, argument: Option<&str>| {
+            let (descriptor, arguments) = match argument {
+                Some(argument) => (
+                    "(Ljava/lang/String;)",
+                    vec![JValue::Object(vm.new_string(argument).unwrap())],
+                ),
+                None => ("()", Vec::new()),
+            };
+            let descriptor = format!("{}Ljava/lang/String;", descriptor);
+            let result = vm
+                .invoke_static("Reflection", name, &descriptor, &arguments)
+                .unwrap()
+                .and_then(|result| result.as_object());
+            result.and_then(|result| vm.string_value(result))
+        };
+
+        assert_eq!(call("className", Some("Calculator")).unwrap(), "Calculator");
+        assert_eq!(call("missing", Some("Missing")).unwrap(), "Missing");
+        assert_eq!(
+            call("missing", Some("java/lang/Object")).unwrap(),
+            "java/lang/Object"
+        );
+        assert_eq!(call("superclassName", None).unwrap(), "java.lang.Object");
+        assert_eq!(call("construct", Some("built")).unwrap(), "built");
+        assert_eq!(call("relabel", Some("changed")).unwrap(), "changed");
+        assert_eq!(call("failure", Some("boom")).unwrap(), "boom");
+        assert_eq!(
+            call("denied", Some("secret")).unwrap(),
+            "class Reflection cannot access a private member of class Hidden"
+        );
+        assert_eq!(
+            call("instantiated", None).unwrap(),
+            "class Reflection cannot access a private member of class Hidden"
+        );
+        assert_eq!(call("overridden", Some("secret")).unwrap(), "secret");
+
+        let members = |vm: &mut Vm, name: &str| vm.invoke_static("Reflection", name, "()I", &[]);
+        assert_eq!(
+            members(&mut vm, "declaredMethods").unwrap(),
+            Some(JValue::Int(16))
+        );
+        assert_eq!(
+            members(&mut vm, "declaredConstructors").unwrap(),
+            Some(JValue::Int(2))
+        );
+        assert_eq!(
+            members(&mut vm, "declaredFields").unwrap(),
+            Some(JValue::Int(2))
+        );
+
+        let invoke = "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/Object;";
+        let (echo, absent) = (
+            vm.new_string("echo").unwrap(),
+            vm.new_string("absent").unwrap(),
+        );
+        let message = vm.new_string("private").unwrap();
+        let result = vm.invoke_static(
+            "Reflection",
+            "invoke",
+            invoke,
+            &[JValue::Object(echo), JValue::Object(message)],
+        );
+        assert_eq!(result.unwrap(), Some(JValue::Object(message)));
+        match vm.invoke_static(
+            "Reflection",
+            "invoke",
+            invoke,
+            &[JValue::Object(absent), JValue::Null],
+        ) {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.NoSuchMethodException");
+                assert_eq!(
+                    exception.message.as_deref(),
+                    Some("Reflection.absent(java.lang.String)")
+                );
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let mut vm = embedding_vm();
+        let name = vm.new_string("n").unwrap();
+        let arguments = [
+            JValue::Object(name),
+            JValue::Int(3),
+            JValue::Int('m' as i32),
+            JValue::Double(0.5),
+            JValue::Null,
+        ];
+        let call = |vm: &mut Vm, method: &str, descriptor: &str, arguments: &[JValue]| {
+            let result = vm
+                .invoke_static("Concatenation", method, descriptor, arguments)
+                .unwrap()
+                .and_then(|result| result.as_object())
+                .unwrap();
+            vm.string_value(result).unwrap()
+        };
+
+        let describe = "(Ljava/lang/String;ICDLjava/lang/Object;)Ljava/lang/String;";
+        assert_eq!(
+            call(&mut vm, "describe", describe, &arguments),
+            "n: 3m at 0.5 \u{1} null!"
+        );
+        let constants = [JValue::Long(-7), JValue::Int(1)];
+        assert_eq!(
+            call(&mut vm, "constants", "(JZ)Ljava/lang/String;", &constants),
+            "-7true"
+        );
+        let repeat = [JValue::Int(3)];
+        assert_eq!(
+            call(&mut vm, "repeat", "(I)Ljava/lang/String;", &repeat),
+            "0,1,2,"
+        );
+        // Linked once per call site
+        assert_eq!(vm.call_sites.len(), 3);
+    }
+
+    #[test]
+    fn test_atomics() {
+        let mut vm = embedding_vm();
+        assert_eq!(
+            vm.invoke_static("Atomics", "counter", "()I", &[]).unwrap(),
+            Some(JValue::Int(100))
+        );
+        assert_eq!(
+            vm.invoke_static("Atomics", "wrapping", "()J", &[]).unwrap(),
+            Some(JValue::Long(i64::MIN))
+        );
+        assert_eq!(
+            vm.invoke_static("Atomics", "flag", "()Z", &[]).unwrap(),
+            Some(JValue::Int(1))
+        );
+
+        let (initial, next) = (vm.new_string("a").unwrap(), vm.new_string("b").unwrap());
+        let result = vm
+            .invoke_static(
+                "Atomics",
+                "reference",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+                &[JValue::Object(initial), JValue::Object(next)],
+            )
+            .unwrap()
+            .and_then(|result| result.as_object())
+            .unwrap();
+        assert_eq!(vm.string_value(result).as_deref(), Some("b,null"));
+    }
+
+    #[test]
+    fn test_interrupts() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .clock(SteppingClock(Mutex::new(1_000)))
+            .build()
+            .unwrap();
+        let string = |vm: &mut Vm, name: &str| {
+            let result = vm
+                .invoke_static("Interrupts", name, "()Ljava/lang/String;", &[])
+                .unwrap()
+                .and_then(|result| result.as_object())
+                .unwrap();
+            vm.string_value(result).unwrap()
+        };
+        assert_eq!(
+            string(&mut vm, "interrupted"),
+            "main true sleep interrupted false"
+        );
+        assert_eq!(
+            string(&mut vm, "waitUnowned"),
+            "current thread is not owner"
+        );
+
+        // Blocking for a timeout sleeps on the VM's clock
+        let sleep = vm.invoke_static("Interrupts", "sleep", "(J)J", &[JValue::Long(5)]);
+        assert_eq!(sleep.unwrap(), Some(JValue::Long(5)));
+        let wait = vm.invoke_static("Interrupts", "waitOwned", "()J", &[]);
+        assert_eq!(wait.unwrap(), Some(JValue::Long(5)));
+        let park = vm.invoke_static("Interrupts", "park", "()J", &[]);
+        assert_eq!(park.unwrap(), Some(JValue::Long(3)));
+
+        match vm.invoke_static("Interrupts", "sleep", "(J)J", &[JValue::Long(-1)]) {
+            Err(VmError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.IllegalArgumentException")
+            }
+            result => panic!("Expected an exception, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_virtual_time() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .clock(VirtualClock::new(1_000))
+            .build()
+            .unwrap();
+        let sleep = vm.invoke_static("Interrupts", "sleep", "(J)J", &[JValue::Long(60_000)]);
+        assert_eq!(sleep.unwrap(), Some(JValue::Long(60_000)));
+        let millis = vm.invoke_static("Platform", "millis", "()J", &[]);
+        assert_eq!(millis.unwrap(), Some(JValue::Long(61_000)));
+        let yields = vm.invoke_static("Interrupts", "yields", "()J", &[]);
+        assert_eq!(yields.unwrap(), Some(JValue::Long(0)));
+    }
+
+    #[test]
+    fn test_inline_caches() {
+        let mut vm = embedding_vm();
+        let mut invoke = |name: &str, count: i32| {
+            vm.invoke_static("Dispatch", name, "(I)I", &[JValue::Int(count)])
+                .unwrap()
+        };
+        assert_eq!(invoke("monomorphic", 10), Some(JValue::Int(90)));
+        assert_eq!(invoke("polymorphic", 30), Some(JValue::Int(140)));
+        assert_eq!(invoke("megamorphic", 60), Some(JValue::Int(180)));
+
+        let mut sites: Vec<(usize, bool)> = vm
+            .inline_caches
+            .iter()
+            .filter(|cache| {
+                cache
+                    .resolved
+                    .as_ref()
+                    .is_some_and(|method| method.name == "area")
+            })
+            .map(|cache| (cache.targets.len(), cache.is_megamorphic()))
+            .collect();
+        sites.sort();
+        assert_eq!(sites, vec![(1, false), (4, true)]);
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_jit() {
+        use crate::vm::jit::{CompilationMode, JitCompiler};
+
+        let jit_vm = |mode: CompilationMode, limits: ExecutionLimits| {
+            Vm::builder()
+                .class_path(embedding_class_path())
+                .limits(limits)
+                .jit(JitCompiler::new().mode(mode))
+                .build()
+                .unwrap()
+        };
+        let mut interpreter = jit_vm(CompilationMode::Interpreted, ExecutionLimits::default());
+        let mut compiler = jit_vm(CompilationMode::Eager, ExecutionLimits::default());
+        let calls: &[(&str, &str, &[JValue])] = &[
+            ("checksum", "(I)J", &[JValue::Int(5000)]),
+            ("harmonic", "(I)D", &[JValue::Int(5000)]),
+            (
+                "compare",
+                "(DD)I",
+                &[JValue::Double(1.0), JValue::Double(2.0)],
+            ),
+            (
+                "compare",
+                "(DD)I",
+                &[JValue::Double(f64::NAN), JValue::Double(2.0)],
+            ),
+            ("divide", "(II)I", &[JValue::Int(i32::MIN), JValue::Int(-1)]),
+            ("divide", "(II)I", &[JValue::Int(-7), JValue::Int(2)]),
+            ("sumAndCall", "(I)I", &[JValue::Int(100)]),
+        ];
+        for (name, descriptor, arguments) in calls {
+            let expected = interpreter.invoke_static("Compiled", name, descriptor, arguments);
+            let compiled = compiler.invoke_static("Compiled", name, descriptor, arguments);
+            assert_eq!(
+                compiled.unwrap(),
+                expected.unwrap(),
+                "{}{}",
+                name,
+                descriptor
+            );
+        }
+        let divided = compiler.invoke_static(
+            "Compiled",
+            "divide",
+            "(II)I",
+            &[JValue::Int(1), JValue::Int(0)],
+        );
+        assert!(matches!(divided, Err(VmError::Exception(exception))
+            if exception.class_name == "java.lang.ArithmeticException"));
+
+        let stats = compiler.jit_stats();
+        // Including `Arithmetic.fib`, called by `sumAndCall`.
+        assert_eq!(stats.compiled_methods, 6);
+        // `sumAndCall` and the division by zero.
+        assert_eq!(stats.deoptimizations, 2);
+        assert_eq!(interpreter.jit_stats().compiled_methods, 0);
+
+        // Compiled loops poll like interpreted ones.
+        let mut vm = jit_vm(
+            CompilationMode::Eager,
+            ExecutionLimits::default().max_instructions(10_000),
+        );
+        let sum = vm.invoke_static("Limits", "sum", "(I)I", &[JValue::Int(100)]);
+        assert_eq!(sum.unwrap(), Some(JValue::Int(4950)));
+        let result = vm.invoke_static("Limits", "spin", "()V", &[]);
+        assert!(matches!(
+            result,
+            Err(VmError::LimitExceeded(Limit::Instructions(10_000)))
+        ));
+        assert_eq!(vm.executed_instructions(), 10_001);
+        assert_eq!(vm.jit_stats().compiled_methods, 2);
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_jit_policy() {
+        use std::time::Instant;
+
+        use crate::vm::jit::{CompilationMode, JitCompiler, JitStats};
+
+        let jit_vm = |jit: JitCompiler| {
+            Vm::builder()
+                .class_path(embedding_class_path())
+                .jit(jit)
+                .build()
+                .unwrap()
+        };
+        let checksum = |vm: &mut Vm, n: i32| {
+            vm.invoke_static("Compiled", "checksum", "(I)J", &[JValue::Int(n)])
+                .unwrap()
+        };
+        let harmonic = |vm: &mut Vm| {
+            vm.invoke_static("Compiled", "harmonic", "(I)D", &[JValue::Int(100)])
+                .unwrap()
+        };
+
+        // Hot methods keep running interpreted while compiled in the background.
+        let mut vm = jit_vm(JitCompiler::new().threshold(10));
+        let expected = checksum(&mut vm, 100);
+        let started = Instant::now();
+        while vm.jit_stats().compiled_methods == 0 {
+            assert!(started.elapsed() < Duration::from_secs(10), "Not compiled");
+            assert_eq!(checksum(&mut vm, 100), expected);
+        }
+
+        // Loops make their method hot, compiled on its next invocation.
+        let mut vm = jit_vm(JitCompiler::new().threshold(100).background(false));
+        checksum(&mut vm, 5000);
+        assert_eq!(vm.jit_stats().compiled_methods, 0);
+        checksum(&mut vm, 5000);
+        assert_eq!(vm.jit_stats().compiled_methods, 1);
+
+        // The code cache evicts the least recently invoked methods.
+        let mut vm = jit_vm(JitCompiler::new().mode(CompilationMode::Eager));
+        checksum(&mut vm, 100);
+        let checksum_size = vm.jit_stats().code_cache_size;
+        harmonic(&mut vm);
+        let harmonic_size = vm.jit_stats().code_cache_size - checksum_size;
+
+        let mut vm = jit_vm(
+            JitCompiler::new()
+                .mode(CompilationMode::Eager)
+                .code_cache(checksum_size.max(harmonic_size)),
+        );
+        assert_eq!(checksum(&mut vm, 100), expected);
+        harmonic(&mut vm);
+        assert_eq!(checksum(&mut vm, 100), expected);
+        let stats = JitStats {
+            compiled_methods: 3,
+            evicted_methods: 2,
+            code_cache_size: checksum_size,
+            ..JitStats::default()
+        };
+        assert_eq!(vm.jit_stats(), stats);
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let limited_vm = |limits: ExecutionLimits| {
+            Vm::builder()
+                .class_path(embedding_class_path())
+                .limits(limits)
+                .build()
+                .unwrap()
+        };
+
+        let mut vm = limited_vm(ExecutionLimits::default().max_instructions(10_000));
+        let sum = vm.invoke_static("Limits", "sum", "(I)I", &[JValue::Int(100)]);
+        assert_eq!(sum.unwrap(), Some(JValue::Int(4950)));
+        let result = vm.invoke_static("Limits", "spin", "()V", &[]);
+        assert!(matches!(
+            result,
+            Err(VmError::LimitExceeded(Limit::Instructions(10_000)))
+        ));
+        assert_eq!(vm.executed_instructions(), 10_001);
+
+        let mut vm = limited_vm(ExecutionLimits::default().max_duration(Duration::from_millis(20)));
+        let result = vm.invoke_static("Limits", "spin", "()V", &[]);
+        assert!(matches!(
+            result,
+            Err(VmError::LimitExceeded(Limit::Duration(_)))
+        ));
+        // Also while sleeping
+        let mut vm = limited_vm(ExecutionLimits::default().max_duration(Duration::from_millis(20)));
+        let sleep = [JValue::Long(60_000)];
+        let result = vm.invoke_static("Interrupts", "sleep", "(J)J", &sleep);
+        assert!(matches!(
+            result,
+            Err(VmError::LimitExceeded(Limit::Duration(_)))
+        ));
+
+        let mut vm = limited_vm(ExecutionLimits::default().max_heap_bytes(1 << 20));
+        let result = vm.invoke_static("Limits", "hoard", "()I", &[]);
+        assert!(matches!(
+            result,
+            Err(VmError::LimitExceeded(Limit::HeapBytes(_)))
+        ));
+    }
+
+    #[test]
+    fn test_class_loading_log() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .log_class_loading(ClassLoadingLog::new().output(SharedOutput(output.clone())))
+            .build()
+            .unwrap();
+
+        vm.find_class("Echo").unwrap();
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("[Loaded java.lang.Object from builtin]\n"));
+        assert!(output.contains("[Loaded Echo from "));
+        let summary = output.lines().last().unwrap();
+        assert!(summary.starts_with("[Loaded 1 classes, 581 bytes, parsed in "));
+    }
+
+    #[test]
+    fn test_profiler() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let profiler = MethodProfiler::new()
+            .format(ProfileFormat::Collapsed)
+            .output(SharedOutput(output.clone()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .profile(profiler)
+            .build()
+            .unwrap();
+
+        for _ in 0..3 {
+            vm.invoke_static("Limits", "sum", "(I)I", &[JValue::Int(10)])
+                .unwrap();
+        }
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.lines().any(|line| line.starts_with("Limits.sum ")));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let coverage = CodeCoverage::new().output(SharedOutput(output.clone()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .coverage(coverage)
+            .build()
+            .unwrap();
+
+        vm.invoke_static("Limits", "sum", "(I)I", &[JValue::Int(10)])
+            .unwrap();
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&"SF:Limits.java"));
+        assert!(lines.contains(&"FNDA:1,Limits.sum(I)I"));
+        assert!(lines.contains(&"FNDA:0,Limits.spin()V"));
+        // The body of the loop
+        assert!(lines.contains(&"DA:18,10"));
+    }
+
+    #[test]
+    fn test_sampling_profiler() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let sampler = SamplingProfiler::new()
+            .interval(Duration::from_micros(100))
+            .opcodes(true)
+            .output(SharedOutput(output.clone()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .sample(sampler)
+            .build()
+            .unwrap();
+
+        vm.invoke_static("Limits", "sum", "(I)I", &[JValue::Int(200_000)])
+            .unwrap();
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.lines().any(|line| line.starts_with("Limits.sum;[")));
+    }
+
+    #[test]
+    fn test_sampling_profiler_threads() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let sampler = SamplingProfiler::new()
+            .interval(Duration::from_micros(100))
+            .output(SharedOutput(output.clone()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .sample(sampler)
+            .build()
+            .unwrap();
+
+        vm.invoke_static("Sampled", "joined", "()J", &[]).unwrap();
+        vm.invoke_static("Sampled", "sleep", "(J)V", &[JValue::Long(20)])
+            .unwrap();
+        vm.shutdown().unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        let sampled = |stack: &str| output.lines().any(|line| line.starts_with(stack));
+        // The spinning thread, and the main thread waiting for it
+        assert!(sampled(
+            "java.lang.Thread.run[native];Sampled$Spinner.run;Sampled.spin "
+        ));
+        assert!(sampled("Sampled.joined;java.lang.Thread.join[native] "));
+        assert!(sampled("Sampled.sleep;java.lang.Thread.sleep[native] "));
+    }
+
+    #[test]
+    fn test_event_listener() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .listener(RecordingListener(events.clone()))
+            .build()
+            .unwrap();
+
+        vm.invoke_static(
+            "Calculator",
+            "safeDivide",
+            "(II)I",
+            &[JValue::Int(1), JValue::Int(0)],
+        )
+        .unwrap();
+        let events = events.lock().unwrap();
+        assert!(events.contains(&"load Calculator".to_string()));
+        assert!(events.contains(&"load java/lang/ArithmeticException".to_string()));
+        let start = events.iter().position(|event| event == "entry safeDivide");
+        let events: Vec<&String> = events[start.unwrap()..]
+            .iter()
+            .filter(|event| !event.starts_with("load "))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "entry safeDivide",
+                "throw java/lang/ArithmeticException at safeDivide:2",
+                "catch java/lang/ArithmeticException in safeDivide",
+                "exit safeDivide",
+            ]
+        );
+    }
+
+    fn dump(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+        let dump = vm.thread_dump();
+        let dump = vm.create_string(dump.encode_utf16().collect())?;
+        Ok(Some(Value::Reference(Some(dump))))
+    }
+
+    #[test]
+    fn test_thread_dump() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .native("Monitors", "dump", "()Ljava/lang/String;", dump)
+            .build()
+            .unwrap();
+
+        let lock = vm.new_object("java/lang/Object", "()V", &[]).unwrap();
+        let dump = vm
+            .invoke_static(
+                "Monitors",
+                "locked",
+                "(Ljava/lang/Object;)Ljava/lang/String;",
+                &[JValue::Object(lock)],
+            )
+            .unwrap();
+        let dump = match dump {
+            Some(JValue::Object(dump)) => vm.string_value(dump).unwrap(),
+            other => panic!("Unexpected result {:?}", other),
+        };
+        let frames: Vec<&str> = dump.lines().skip(4).collect();
+        assert_eq!(frames[0], "\tat Monitors.inner(Monitors.java:11)");
+        assert!(frames[1].ends_with("(a Monitors)"));
+        assert_eq!(frames[2], "\tat Monitors.locked(Monitors.java:6)");
+        assert!(frames[3].ends_with("(a java.lang.Object)"));
+        assert!(frames[4].ends_with("(a java.lang.Class for Monitors)"));
+        assert!(vm.thread_dump().ends_with("RUNNABLE\n\n"));
+    }
+
+    fn walk(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+        let thread = vm.current_thread_id();
+        let frames: Vec<String> = vm
+            .walk_stack(thread)
+            .unwrap()
+            .map(|frame| {
+                let locals = frame.locals();
+                let receiver = locals
+                    .first()
+                    .copied()
+                    .flatten()
+                    .and_then(|local| local.as_object());
+                format!(
+                    "{}.{} bci {} line {:?} locals {} receiver {}",
+                    frame.class().name,
+                    frame.method().name,
+                    frame.bci(),
+                    frame.line_number(),
+                    locals.len(),
+                    receiver.map_or("none".to_string(), |object| vm
+                        .class(vm.class_of(object))
+                        .java_name()),
+                )
+            })
+            .collect();
+        let frames = vm.create_string(frames.join("\n").encode_utf16().collect())?;
+        Ok(Some(Value::Reference(Some(frames))))
+    }
+
+    #[test]
+    fn test_walk_stack() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .native("Monitors", "dump", "()Ljava/lang/String;", walk)
+            .build()
+            .unwrap();
+
+        let lock = vm.new_object("java/lang/Object", "()V", &[]).unwrap();
+        let frames = vm
+            .invoke_static(
+                "Monitors",
+                "locked",
+                "(Ljava/lang/Object;)Ljava/lang/String;",
+                &[JValue::Object(lock)],
+            )
+            .unwrap();
+        let frames = match frames {
+            Some(JValue::Object(frames)) => vm.string_value(frames).unwrap(),
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(
+            frames.lines().collect::<Vec<_>>(),
+            [
+                "Monitors.inner bci 0 line Some(11) locals 1 receiver Monitors",
+                "Monitors.locked bci 11 line Some(6) locals 3 receiver java.lang.Object",
+            ]
+        );
+        assert_eq!(vm.threads(), [ThreadId::MAIN]);
+        assert_eq!(vm.walk_stack(ThreadId::MAIN).unwrap().count(), 0);
+        assert!(vm.walk_stack(ThreadId(2)).is_none());
+    }
+
+    #[test]
+    fn test_garbage_collection() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .log_gc(GcLog::new().output(SharedOutput(output.clone())))
+            .build()
+            .unwrap();
+
+        let length = vm.invoke_static("Garbage", "churn", "(I)I", &[JValue::Int(10_000)]);
+        assert_eq!(length.unwrap(), Some(JValue::Int(256)));
+        let stats = vm.heap_stats();
+        assert!(stats.collections > 0);
+        assert!(stats.collected_bytes > 0);
+        assert!(stats.allocated_objects > 10_000);
+        assert_eq!(
+            stats.used_objects as u64,
+            stats.allocated_objects - stats.collected_objects
+        );
+        assert!(stats.used_bytes < 8 << 20);
+
+        let kept = vm.new_string("kept").unwrap();
+        vm.invoke_static("Garbage", "collect", "()V", &[]).unwrap();
+        assert_eq!(vm.string_value(kept).unwrap(), "kept");
+        let used = vm.heap_stats().used_objects;
+        vm.release(kept);
+        vm.collect_garbage().unwrap();
+        assert_eq!(vm.heap_stats().used_objects, used - 1);
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.starts_with("[GC (Heap Threshold) "));
+        assert!(output.contains("[GC (System.gc()) "));
+        assert!(output.contains("[GC (Embedder Request) "));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut vm = embedding_vm();
+        let mut call = |name: &str, descriptor: &str| {
+            vm.invoke_static("Cloning", name, descriptor, &[])
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(call("point", "()I"), JValue::Int(72));
+        assert_eq!(call("array", "()I"), JValue::Int(14));
+        let message = call("plain", "()Ljava/lang/String;");
+        let message = vm.string_value(message.as_object().unwrap()).unwrap();
+        assert_eq!(message, "Cloning$Plain");
+    }
+
+    #[test]
+    fn test_identity_hashes() {
+        let mut vm = embedding_vm();
+        let kept = vm.new_string("kept").unwrap();
+        let dropped = vm.new_string("dropped").unwrap();
+        let kept_hash = vm.heap.identity_hash(kept);
+        let dropped_hash = vm.heap.identity_hash(dropped);
+        assert_ne!(kept_hash, dropped_hash);
+
+        vm.release(dropped);
+        vm.collect_garbage().unwrap();
+        assert_eq!(vm.heap.identity_hash(kept), kept_hash);
+        // The slot of the collected string is reused, under another hash
+        let reused = vm.new_string("reused").unwrap();
+        assert_eq!(reused, dropped);
+        assert_ne!(vm.heap.identity_hash(reused), dropped_hash);
+
+        // Hashes are drawn in the same sequence in every run
+        let mut other = embedding_vm();
+        let object = other.new_string("kept").unwrap();
+        assert_eq!(other.heap.identity_hash(object), kept_hash);
+    }
+
+    #[test]
+    fn test_references() {
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .build()
+            .unwrap();
+
+        vm.invoke_static("References", "create", "()V", &[])
+            .unwrap();
+        let cleared = vm.invoke_static("References", "cleared", "()I", &[]);
+        assert_eq!(cleared.unwrap(), Some(JValue::Int(0)));
+
+        vm.collect_garbage().unwrap();
+        let cleared = vm.invoke_static("References", "cleared", "()I", &[]);
+        assert_eq!(cleared.unwrap(), Some(JValue::Int(1 | 8)));
+        let enqueued = vm.invoke_static("References", "enqueued", "()I", &[]);
+        assert_eq!(enqueued.unwrap(), Some(JValue::Int(2)));
+
+        // Soft references are cleared once the heap gets close to its limit
+        let used = vm.heap_stats().used_bytes;
+        let mut vm = Vm::builder()
+            .class_path(embedding_class_path())
+            .limits(ExecutionLimits::default().max_heap_bytes(used + used / 4))
+            .build()
+            .unwrap();
+        vm.invoke_static("References", "create", "()V", &[])
+            .unwrap();
+        vm.collect_garbage().unwrap();
+        let cleared = vm.invoke_static("References", "cleared", "()I", &[]);
+        assert_eq!(cleared.unwrap(), Some(JValue::Int(1 | 4 | 8)));
+    }
+}
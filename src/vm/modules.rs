@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use crate::class::module::ModuleDescriptor;
+use crate::packaging::modulepath::{ModulePath, ModuleReference};
+use crate::vm::linker::package_of;
+
+/// The root standing for every module of the module path, as in
+/// `--add-modules ALL-MODULE-PATH`.
+pub const ALL_MODULE_PATH: &str = "ALL-MODULE-PATH";
+
+// =============================================================================
+// ERRORS
+// =============================================================================
+
+/// Why the modules cannot be resolved, like `java.lang.module` reports with
+/// a `FindException` or `ResolutionException`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionError {
+    /// A root module, or one required by a resolved module, is not on the
+    /// module path.
+    NotFound {
+        module: String,
+        required_by: Option<String>,
+    },
+    /// Two resolved modules hold the same package.
+    SplitPackage {
+        package: String,
+        modules: (String, String),
+    },
+    /// The modules require each other.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolutionError::NotFound {
+                module,
+                required_by: Some(required_by),
+            } => write!(
+                f,
+                "Module {} not found, required by {}",
+                module, required_by
+            ),
+            ResolutionError::NotFound { module, .. } => write!(f, "Module {} not found", module),
+            ResolutionError::SplitPackage { package, modules } => write!(
+                f,
+                "Package {} in both module {} and module {}",
+                package.replace('/', "."),
+                modules.0,
+                modules.1
+            ),
+            ResolutionError::Cycle(modules) => {
+                write!(f, "Cycle detected: {}", modules.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for ResolutionError {}
+
+// =============================================================================
+// MODULE GRAPH
+// =============================================================================
+
+/// A module of the graph, with the modules it reads.
+#[derive(Debug)]
+pub struct ResolvedModule {
+    pub reference: ModuleReference,
+    /// The modules the module reads: those it requires, and those they
+    /// require transitively.
+    pub reads: BTreeSet<String>,
+}
+
+impl ResolvedModule {
+    pub fn descriptor(&self) -> &ModuleDescriptor {
+        &self.reference.descriptor
+    }
+}
+
+/// The modules of the module path resolved from the roots, with the
+/// readability between them, deciding which classes of a module other
+/// modules can access.
+///
+/// Modules the graph does not hold, like those of the JDK, are not checked:
+/// the classes of the classpath, the unnamed module, and of the JDK read
+/// every module, and can be read by every module.
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    modules: BTreeMap<String, ResolvedModule>,
+    /// The module of each package of the resolved modules.
+    packages: HashMap<String, String>,
+}
+
+impl ModuleGraph {
+    /// Resolves the root modules and, transitively, the modules they
+    /// require, from the module path. Modules of the JDK, named `java.*` and
+    /// `jdk.*`, are required without being resolved, as are the modules
+    /// required only at compile time (`requires static`) which nothing else
+    /// requires. Resolving an automatic module resolves every automatic
+    /// module of the module path, like the reference JVM does.
+    pub fn resolve(
+        module_path: &ModulePath,
+        roots: &[&str],
+    ) -> Result<ModuleGraph, ResolutionError> {
+        let mut found: BTreeMap<String, &ModuleReference> = BTreeMap::new();
+        let mut pending: VecDeque<(String, Option<String>)> = VecDeque::new();
+        for &root in roots {
+            if root == ALL_MODULE_PATH {
+                pending.extend(
+                    module_path
+                        .modules()
+                        .iter()
+                        .map(|module| (module.descriptor.name.clone(), None)),
+                );
+            } else {
+                pending.push_back((root.to_string(), None));
+            }
+        }
+
+        let mut automatic_added = false;
+        while let Some((name, required_by)) = pending.pop_front() {
+            if found.contains_key(&name) || is_system_module(&name) {
+                continue;
+            }
+            let module = module_path
+                .find(&name)
+                .ok_or_else(|| ResolutionError::NotFound {
+                    module: name.clone(),
+                    required_by: required_by.clone(),
+                })?;
+            for requires in &module.descriptor.requires {
+                if !requires.static_phase {
+                    pending.push_back((requires.name.clone(), Some(name.clone())));
+                }
+            }
+            if module.descriptor.automatic && !automatic_added {
+                automatic_added = true;
+                pending.extend(
+                    module_path
+                        .modules()
+                        .iter()
+                        .filter(|module| module.descriptor.automatic)
+                        .map(|module| (module.descriptor.name.clone(), Some(name.clone()))),
+                );
+            }
+            found.insert(name, module);
+        }
+
+        check_cycles(&found)?;
+        let mut packages: HashMap<String, String> = HashMap::new();
+        for (name, module) in &found {
+            for package in &module.descriptor.packages {
+                if let Some(other) = packages.insert(package.clone(), name.clone()) {
+                    return Err(ResolutionError::SplitPackage {
+                        package: package.clone(),
+                        modules: (other, name.clone()),
+                    });
+                }
+            }
+        }
+
+        let mut modules = BTreeMap::new();
+        for (name, module) in &found {
+            let reads = readability(&found, &module.descriptor);
+            let reference = (*module).clone();
+            modules.insert(name.clone(), ResolvedModule { reference, reads });
+        }
+        Ok(ModuleGraph { modules, packages })
+    }
+
+    /// The resolved modules, in name order.
+    pub fn modules(&self) -> impl Iterator<Item = &ResolvedModule> {
+        self.modules.values()
+    }
+
+    pub fn module(&self, name: &str) -> Option<&ResolvedModule> {
+        self.modules.get(name)
+    }
+
+    /// The module of the class, by its internal name, `None` for classes of
+    /// other modules.
+    pub fn module_of(&self, class: &str) -> Option<&str> {
+        self.packages.get(package_of(class)).map(String::as_str)
+    }
+
+    /// Whether the first module reads the second, `None` standing for a
+    /// module outside the graph, which reads and is read by every module.
+    pub fn reads(&self, from: Option<&str>, to: Option<&str>) -> bool {
+        match (from, to) {
+            (Some(from), Some(to)) if from != to => self
+                .modules
+                .get(from)
+                .is_some_and(|module| module.reads.contains(to)),
+            _ => true,
+        }
+    }
+
+    /// Whether the module of the package exports it to the module, `None`
+    /// standing for a module outside the graph.
+    pub fn is_exported(&self, package: &str, to: Option<&str>) -> bool {
+        match self.packages.get(package) {
+            Some(module) if Some(module.as_str()) != to => {
+                self.modules[module].descriptor().exports_to(package, to)
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the module of the package opens it to deep reflection by the
+    /// module, `None` standing for a module outside the graph. The hook of
+    /// `setAccessible` checks.
+    pub fn is_open(&self, package: &str, to: Option<&str>) -> bool {
+        match self.packages.get(package) {
+            Some(module) if Some(module.as_str()) != to => {
+                self.modules[module].descriptor().opens_to(package, to)
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks that the module of the accessor reads the module of the
+    /// accessed class and that it exports the package of the class to it,
+    /// the module part of the access control of public classes, explaining
+    /// why not otherwise.
+    pub fn check_access(&self, accessor: &str, accessed: &str) -> Result<(), String> {
+        let (from, to) = (self.module_of(accessor), self.module_of(accessed));
+        let describe = |module: Option<&str>| match module {
+            Some(module) => format!("module {}", module),
+            None => "the unnamed module".to_string(),
+        };
+        if !self.reads(from, to) {
+            return Err(format!("{} does not read {}", describe(from), describe(to)));
+        }
+        let package = package_of(accessed);
+        if !self.is_exported(package, from) {
+            return Err(format!(
+                "{} does not export {} to {}",
+                describe(to),
+                package.replace('/', "."),
+                describe(from)
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn is_system_module(name: &str) -> bool {
+    name.starts_with("java.") || name.starts_with("jdk.")
+}
+
+/// The modules the module reads: the modules it requires, and those they
+/// require transitively (JLS 7.7.1). An automatic module reads every module,
+/// and reading it implies reading the other automatic modules.
+fn readability(
+    found: &BTreeMap<String, &ModuleReference>,
+    descriptor: &ModuleDescriptor,
+) -> BTreeSet<String> {
+    if descriptor.automatic {
+        return found
+            .keys()
+            .filter(|&name| name != &descriptor.name)
+            .cloned()
+            .collect();
+    }
+
+    let mut reads = BTreeSet::new();
+    let mut pending: Vec<&str> = descriptor
+        .requires
+        .iter()
+        .map(|requires| requires.name.as_str())
+        .collect();
+    while let Some(name) = pending.pop() {
+        let module = match found.get(name) {
+            Some(module) => module,
+            None => continue,
+        };
+        if !reads.insert(name.to_string()) {
+            continue;
+        }
+        if module.descriptor.automatic {
+            pending.extend(
+                found
+                    .values()
+                    .filter(|other| other.descriptor.automatic)
+                    .map(|other| other.descriptor.name.as_str()),
+            );
+        }
+        pending.extend(
+            module
+                .descriptor
+                .requires
+                .iter()
+                .filter(|requires| requires.transitive)
+                .map(|requires| requires.name.as_str()),
+        );
+    }
+    reads.remove(&descriptor.name);
+    reads
+}
+
+/// Fails if the explicit modules require each other, by depth first search.
+fn check_cycles(found: &BTreeMap<String, &ModuleReference>) -> Result<(), ResolutionError> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        name: &'a str,
+        found: &'a BTreeMap<String, &ModuleReference>,
+        states: &mut HashMap<&'a str, State>,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<(), ResolutionError> {
+        match states.get(name) {
+            Some(State::Done) => return Ok(()),
+            Some(State::Visiting) => {
+                let start = stack.iter().position(|&module| module == name).unwrap_or(0);
+                let mut cycle: Vec<String> = stack[start..].iter().map(|m| m.to_string()).collect();
+                cycle.push(name.to_string());
+                return Err(ResolutionError::Cycle(cycle));
+            }
+            None => {}
+        }
+        let module = match found.get(name) {
+            Some(module) if !module.descriptor.automatic => module,
+            _ => return Ok(()),
+        };
+        states.insert(name, State::Visiting);
+        stack.push(name);
+        for requires in &module.descriptor.requires {
+            visit(&requires.name, found, states, stack)?;
+        }
+        stack.pop();
+        states.insert(name, State::Done);
+        Ok(())
+    }
+
+    let mut states = HashMap::new();
+    for name in found.keys() {
+        visit(name, found, &mut states, &mut Vec::new())?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// MODULE GRAPH TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod module_graph_tests {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use super::{ModuleGraph, ResolutionError, ALL_MODULE_PATH};
+    use crate::class::module::{Exports, ModuleDescriptor, Requires};
+    use crate::packaging::modulepath::{ModulePath, ModuleReference};
+
+    fn module(name: &str, requires: &[(&str, bool)], exports: &[&str]) -> ModuleReference {
+        let packages: BTreeSet<String> = exports
+            .iter()
+            .map(|package| package.to_string())
+            .chain([format!("{}/internal", name)])
+            .collect();
+        let mut descriptor = ModuleDescriptor::automatic(name, packages);
+        descriptor.automatic = false;
+        descriptor.open = false;
+        descriptor.requires = requires
+            .iter()
+            .map(|&(name, transitive)| Requires {
+                name: name.to_string(),
+                transitive,
+                static_phase: false,
+                version: None,
+            })
+            .collect();
+        descriptor.exports = exports
+            .iter()
+            .map(|package| Exports {
+                package: package.to_string(),
+                targets: Vec::new(),
+            })
+            .collect();
+        ModuleReference {
+            descriptor,
+            location: PathBuf::from(name),
+        }
+    }
+
+    fn module_path(modules: Vec<ModuleReference>) -> ModulePath {
+        let mut module_path = ModulePath::default();
+        for module in modules {
+            module_path.push(module);
+        }
+        module_path
+    }
+
+    #[test]
+    fn test_transitive_readability() {
+        let module_path = module_path(vec![
+            module("app", &[("lib", false), ("java.base", false)], &[]),
+            module("lib", &[("api", true), ("impl", false)], &["lib"]),
+            module("api", &[], &["api"]),
+            module("impl", &[], &["impl"]),
+            module("unused", &[], &["unused"]),
+        ]);
+        let graph = ModuleGraph::resolve(&module_path, &["app"]).unwrap();
+        let names: Vec<_> = graph
+            .modules()
+            .map(|m| m.descriptor().name.as_str())
+            .collect();
+        assert_eq!(names, ["api", "app", "impl", "lib"]);
+
+        assert!(graph.reads(Some("app"), Some("lib")));
+        assert!(graph.reads(Some("app"), Some("api")));
+        assert!(!graph.reads(Some("app"), Some("impl")));
+        assert!(graph.reads(None, Some("impl")));
+
+        assert_eq!(graph.check_access("app/internal/Main", "api/Api"), Ok(()));
+        assert_eq!(
+            graph
+                .check_access("app/internal/Main", "impl/Impl")
+                .unwrap_err(),
+            "module app does not read module impl"
+        );
+        assert_eq!(
+            graph
+                .check_access("app/internal/Main", "lib/internal/Hidden")
+                .unwrap_err(),
+            "module lib does not export lib.internal to module app"
+        );
+        assert_eq!(
+            graph
+                .check_access("Main", "lib/internal/Hidden")
+                .unwrap_err(),
+            "module lib does not export lib.internal to the unnamed module"
+        );
+        assert_eq!(graph.check_access("Main", "java/lang/Object"), Ok(()));
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let missing = module_path(vec![module("app", &[("lib", false)], &[])]);
+        assert_eq!(
+            ModuleGraph::resolve(&missing, &["app"]).unwrap_err(),
+            ResolutionError::NotFound {
+                module: "lib".to_string(),
+                required_by: Some("app".to_string()),
+            }
+        );
+
+        let split = module_path(vec![
+            module("a", &[("b", false)], &["shared"]),
+            module("b", &[], &["shared"]),
+        ]);
+        assert!(matches!(
+            ModuleGraph::resolve(&split, &["a"]),
+            Err(ResolutionError::SplitPackage { .. })
+        ));
+
+        let cycle = module_path(vec![
+            module("a", &[("b", false)], &[]),
+            module("b", &[("a", false)], &[]),
+        ]);
+        assert_eq!(
+            ModuleGraph::resolve(&cycle, &[ALL_MODULE_PATH])
+                .unwrap_err()
+                .to_string(),
+            "Cycle detected: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn test_automatic_modules_read_everything() {
+        let mut automatic = module("plain", &[], &["plain"]);
+        automatic.descriptor.automatic = true;
+        let mut other = module("other", &[], &["other"]);
+        other.descriptor.automatic = true;
+        let module_path = module_path(vec![
+            module("app", &[("plain", false)], &[]),
+            automatic,
+            other,
+        ]);
+        let graph = ModuleGraph::resolve(&module_path, &["app"]).unwrap();
+        assert!(graph.module("other").is_some());
+        assert!(graph.reads(Some("app"), Some("other")));
+        assert!(graph.reads(Some("plain"), Some("app")));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::vm::heap::NativeData;
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::lang::{chars, exception};
+use crate::vm::natives::{non_null, BuiltinClass};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.nio.charset`.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        charset(),
+        standard_charsets(),
+        exception(
+            "java/nio/charset/UnsupportedCharsetException",
+            "java/lang/IllegalArgumentException",
+        ),
+    ]
+}
+
+// =============================================================================
+// ENCODINGS
+// =============================================================================
+
+/// The charsets the VM encodes and decodes text in, without the machinery of
+/// the JDK's providers. Characters a charset cannot represent are encoded as
+/// `?`, and bytes which do not decode become U+FFFD, like the JDK does by
+/// default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Ascii,
+}
+
+impl Encoding {
+    /// The encoding of a charset name or alias, ignoring case.
+    pub fn for_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_uppercase().as_str() {
+            "UTF-8" | "UTF8" | "UNICODE-1-1-UTF-8" => Some(Encoding::Utf8),
+            "ISO-8859-1" | "ISO8859-1" | "ISO8859_1" | "ISO_8859_1" | "ISO_8859-1" | "8859_1"
+            | "LATIN1" | "L1" | "CP819" | "IBM819" => Some(Encoding::Latin1),
+            "US-ASCII" | "ASCII" | "US" | "ISO646-US" | "646" | "CP367" | "IBM367" => {
+                Some(Encoding::Ascii)
+            }
+            _ => None,
+        }
+    }
+
+    /// The canonical name, as returned by `Charset.name()`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Ascii => "US-ASCII",
+        }
+    }
+
+    /// The field of `StandardCharsets` holding the charset.
+    fn field(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF_8",
+            Encoding::Latin1 => "ISO_8859_1",
+            Encoding::Ascii => "US_ASCII",
+        }
+    }
+
+    pub fn encode(self, chars: &[u16]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(chars.len());
+        for char in std::char::decode_utf16(chars.iter().copied()) {
+            match (self, char) {
+                (Encoding::Utf8, Ok(char)) => {
+                    bytes.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes())
+                }
+                (Encoding::Latin1, Ok(char)) if (char as u32) < 0x100 => bytes.push(char as u8),
+                (Encoding::Ascii, Ok(char)) if char.is_ascii() => bytes.push(char as u8),
+                _ => bytes.push(b'?'),
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Vec<u16> {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).encode_utf16().collect(),
+            Encoding::Latin1 => bytes.iter().map(|byte| *byte as u16).collect(),
+            Encoding::Ascii => bytes
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii() {
+                        *byte as u16
+                    } else {
+                        0xfffd
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The encoding of a `Charset` argument, throwing `NullPointerException` for
+/// `null`.
+pub(crate) fn encoding(vm: &mut Vm, value: Value) -> Result<Encoding, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).native {
+        NativeData::Charset(encoding) => Ok(encoding),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a charset",
+            object
+        )))),
+    }
+}
+
+/// The encoding of a charset name argument, throwing `exception` if the VM
+/// does not support it.
+pub(crate) fn named_encoding(
+    vm: &mut Vm,
+    value: Value,
+    exception: &str,
+) -> Result<Encoding, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, value)?);
+    match Encoding::for_name(&name) {
+        Some(encoding) => Ok(encoding),
+        None => Err(vm.throw_new(exception, Some(name))),
+    }
+}
+
+// =============================================================================
+// CHARSET
+// =============================================================================
+
+fn charset() -> BuiltinClass {
+    BuiltinClass::new("java/nio/charset/Charset", "java/lang/Object")
+        .static_method(
+            "forName",
+            "(Ljava/lang/String;)Ljava/nio/charset/Charset;",
+            charset_for_name,
+        )
+        .static_method(
+            "defaultCharset",
+            "()Ljava/nio/charset/Charset;",
+            charset_default_charset,
+        )
+        .static_method("isSupported", "(Ljava/lang/String;)Z", charset_is_supported)
+        .method("name", "()Ljava/lang/String;", charset_name)
+        .method("displayName", "()Ljava/lang/String;", charset_name)
+        .method("toString", "()Ljava/lang/String;", charset_name)
+}
+
+/// The single `Charset` object of the encoding, so that charsets compare
+/// equal by identity.
+fn charset_object(vm: &mut Vm, encoding: Encoding) -> Result<ObjectRef, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/charset/StandardCharsets")?;
+    vm.initialize_class(class)?;
+    match vm.static_field(class, encoding.field()) {
+        Some(Value::Reference(Some(charset))) => Ok(charset),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "The {} charset is not initialized",
+            encoding.name()
+        )))),
+    }
+}
+
+fn charset_for_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let encoding = named_encoding(vm, args[0], "java/nio/charset/UnsupportedCharsetException")?;
+    let charset = charset_object(vm, encoding)?;
+    Ok(Some(Value::Reference(Some(charset))))
+}
+
+/// UTF-8, like the JDK since 18, regardless of the host's locale.
+fn charset_default_charset(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let charset = charset_object(vm, Encoding::Utf8)?;
+    Ok(Some(Value::Reference(Some(charset))))
+}
+
+fn charset_is_supported(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    Ok(Some(Value::Int(Encoding::for_name(&name).is_some() as i32)))
+}
+
+fn charset_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = encoding(vm, args[0])?.name();
+    let object = vm.intern_string(name.encode_utf16().collect())?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+// =============================================================================
+// STANDARD CHARSETS
+// =============================================================================
+
+fn standard_charsets() -> BuiltinClass {
+    BuiltinClass::new("java/nio/charset/StandardCharsets", "java/lang/Object")
+        .static_field("UTF_8", "Ljava/nio/charset/Charset;")
+        .static_field("ISO_8859_1", "Ljava/nio/charset/Charset;")
+        .static_field("US_ASCII", "Ljava/nio/charset/Charset;")
+        .static_method("<clinit>", "()V", standard_charsets_clinit)
+}
+
+fn standard_charsets_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let standard = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/charset/StandardCharsets")?;
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/charset/Charset")?;
+    for encoding in [Encoding::Utf8, Encoding::Latin1, Encoding::Ascii] {
+        let charset = vm.instantiate(class)?;
+        vm.heap.get_mut(charset).native = NativeData::Charset(encoding);
+        vm.set_static_field(standard, encoding.field(), Value::Reference(Some(charset)));
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod charset_tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u16> {
+        text.encode_utf16().collect()
+    }
+
+    #[test]
+    fn test_for_name() {
+        assert_eq!(Encoding::for_name("utf-8"), Some(Encoding::Utf8));
+        assert_eq!(Encoding::for_name("Latin1"), Some(Encoding::Latin1));
+        assert_eq!(Encoding::for_name("ASCII"), Some(Encoding::Ascii));
+        assert_eq!(Encoding::for_name("UTF-16"), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let text = utf16("aé€😀");
+        assert_eq!(Encoding::Utf8.encode(&text), "aé€😀".as_bytes());
+        assert_eq!(Encoding::Latin1.encode(&text), b"a\xe9??");
+        assert_eq!(Encoding::Ascii.encode(&text), b"a???");
+        // An unpaired surrogate has no encoding
+        assert_eq!(Encoding::Utf8.encode(&[0x61, 0xd800, 0x62]), b"a?b");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Encoding::Utf8.decode("aé€😀".as_bytes()), utf16("aé€😀"));
+        assert_eq!(
+            Encoding::Utf8.decode(b"a\xffb\xe2\x82"),
+            utf16("a\u{fffd}b\u{fffd}")
+        );
+        assert_eq!(Encoding::Latin1.decode(b"a\xe9"), utf16("aé"));
+        assert_eq!(Encoding::Ascii.decode(b"a\xe9"), utf16("a\u{fffd}"));
+    }
+}
@@ -0,0 +1,777 @@
+use crate::vm::heap::ObjectData;
+use std::time::Duration;
+
+use crate::vm::natives::lang::{current_thread, mirrored_class, object_chars, primitive_to_string};
+use crate::vm::natives::reflect::reflected_field;
+use crate::vm::natives::{long, non_null, BuiltinClass, NativeFn};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.util.concurrent.atomic`. The VM runs a
+/// single guest thread at a time and natives hold it exclusively, so every
+/// read-modify-write of a `value` field is atomic as is.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        atomic_integer(),
+        atomic_long(),
+        atomic(
+            BuiltinClass::new(
+                "java/util/concurrent/atomic/AtomicBoolean",
+                "java/lang/Object",
+            )
+            .implements("java/io/Serializable")
+            .field("value", "Z"),
+            &AtomicDescriptors {
+                value: "(Z)V",
+                get: "()Z",
+                get_and_set: "(Z)Z",
+                compare_and_set: "(ZZ)Z",
+                compare_and_exchange: "(ZZ)Z",
+            },
+        )
+        .method("toString", "()Ljava/lang/String;", atomic_boolean_to_string),
+        atomic_reference(),
+        lock_support(),
+    ]
+}
+
+/// Natives of `jdk.internal.misc.Unsafe`, which the JDK's own concurrency
+/// utilities build on, by class, name and descriptor.
+pub fn natives() -> Vec<(&'static str, &'static str, &'static str, NativeFn)> {
+    const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+    vec![
+        (UNSAFE, "registerNatives", "()V", unsafe_nop),
+        (UNSAFE, "fullFence", "()V", unsafe_nop),
+        (UNSAFE, "loadFence", "()V", unsafe_nop),
+        (UNSAFE, "storeFence", "()V", unsafe_nop),
+        (UNSAFE, "park", "(ZJ)V", unsafe_park),
+        (UNSAFE, "unpark", "(Ljava/lang/Object;)V", unsafe_unpark),
+        (
+            UNSAFE,
+            "arrayBaseOffset0",
+            "(Ljava/lang/Class;)I",
+            unsafe_array_base_offset,
+        ),
+        (
+            UNSAFE,
+            "arrayIndexScale0",
+            "(Ljava/lang/Class;)I",
+            unsafe_array_index_scale,
+        ),
+        (
+            UNSAFE,
+            "objectFieldOffset0",
+            "(Ljava/lang/reflect/Field;)J",
+            unsafe_object_field_offset,
+        ),
+        (
+            UNSAFE,
+            "objectFieldOffset1",
+            "(Ljava/lang/Class;Ljava/lang/String;)J",
+            unsafe_object_field_offset_by_name,
+        ),
+        (
+            UNSAFE,
+            "staticFieldOffset0",
+            "(Ljava/lang/reflect/Field;)J",
+            unsafe_static_field_offset,
+        ),
+        (
+            UNSAFE,
+            "staticFieldBase0",
+            "(Ljava/lang/reflect/Field;)Ljava/lang/Object;",
+            unsafe_static_field_base,
+        ),
+        (UNSAFE, "getInt", "(Ljava/lang/Object;J)I", unsafe_get),
+        (
+            UNSAFE,
+            "getIntVolatile",
+            "(Ljava/lang/Object;J)I",
+            unsafe_get,
+        ),
+        (UNSAFE, "getLong", "(Ljava/lang/Object;J)J", unsafe_get),
+        (
+            UNSAFE,
+            "getLongVolatile",
+            "(Ljava/lang/Object;J)J",
+            unsafe_get,
+        ),
+        (
+            UNSAFE,
+            "getReference",
+            "(Ljava/lang/Object;J)Ljava/lang/Object;",
+            unsafe_get,
+        ),
+        (
+            UNSAFE,
+            "getReferenceVolatile",
+            "(Ljava/lang/Object;J)Ljava/lang/Object;",
+            unsafe_get,
+        ),
+        (UNSAFE, "putInt", "(Ljava/lang/Object;JI)V", unsafe_put),
+        (
+            UNSAFE,
+            "putIntVolatile",
+            "(Ljava/lang/Object;JI)V",
+            unsafe_put,
+        ),
+        (UNSAFE, "putLong", "(Ljava/lang/Object;JJ)V", unsafe_put),
+        (
+            UNSAFE,
+            "putLongVolatile",
+            "(Ljava/lang/Object;JJ)V",
+            unsafe_put,
+        ),
+        (
+            UNSAFE,
+            "putReference",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            unsafe_put,
+        ),
+        (
+            UNSAFE,
+            "putReferenceVolatile",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            unsafe_put,
+        ),
+        (
+            UNSAFE,
+            "compareAndSetInt",
+            "(Ljava/lang/Object;JII)Z",
+            unsafe_compare_and_set,
+        ),
+        (
+            UNSAFE,
+            "compareAndSetLong",
+            "(Ljava/lang/Object;JJJ)Z",
+            unsafe_compare_and_set,
+        ),
+        (
+            UNSAFE,
+            "compareAndSetReference",
+            "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Z",
+            unsafe_compare_and_set,
+        ),
+        (
+            UNSAFE,
+            "compareAndExchangeInt",
+            "(Ljava/lang/Object;JII)I",
+            unsafe_compare_and_exchange,
+        ),
+        (
+            UNSAFE,
+            "compareAndExchangeLong",
+            "(Ljava/lang/Object;JJJ)J",
+            unsafe_compare_and_exchange,
+        ),
+        (
+            UNSAFE,
+            "compareAndExchangeReference",
+            "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+            unsafe_compare_and_exchange,
+        ),
+    ]
+}
+
+// =============================================================================
+// ATOMICS
+// =============================================================================
+
+/// Names of the accesses differing only in their memory ordering, which
+/// does not matter to a single guest thread.
+const GETS: [&str; 4] = ["get", "getPlain", "getOpaque", "getAcquire"];
+const SETS: [&str; 5] = ["set", "lazySet", "setPlain", "setOpaque", "setRelease"];
+const COMPARE_AND_SETS: [&str; 6] = [
+    "compareAndSet",
+    "weakCompareAndSet",
+    "weakCompareAndSetPlain",
+    "weakCompareAndSetVolatile",
+    "weakCompareAndSetAcquire",
+    "weakCompareAndSetRelease",
+];
+const COMPARE_AND_EXCHANGES: [&str; 3] = [
+    "compareAndExchange",
+    "compareAndExchangeAcquire",
+    "compareAndExchangeRelease",
+];
+
+/// Descriptors of the accesses of an atomic by the type of its value.
+struct AtomicDescriptors {
+    /// The constructor and the setters.
+    value: &'static str,
+    get: &'static str,
+    get_and_set: &'static str,
+    compare_and_set: &'static str,
+    compare_and_exchange: &'static str,
+}
+
+/// Adds the accesses every atomic has to the class declaring its `value`.
+fn atomic(mut class: BuiltinClass, descriptors: &AtomicDescriptors) -> BuiltinClass {
+    class = class
+        .method("<init>", "()V", atomic_init)
+        .method("<init>", descriptors.value, atomic_init)
+        .method("getAndSet", descriptors.get_and_set, atomic_get_and_set);
+    for name in GETS {
+        class = class.method(name, descriptors.get, atomic_get);
+    }
+    for name in SETS {
+        class = class.method(name, descriptors.value, atomic_set);
+    }
+    for name in COMPARE_AND_SETS {
+        class = class.method(name, descriptors.compare_and_set, atomic_compare_and_set);
+    }
+    for name in COMPARE_AND_EXCHANGES {
+        class = class.method(
+            name,
+            descriptors.compare_and_exchange,
+            atomic_compare_and_exchange,
+        );
+    }
+    class
+}
+
+/// Adds the conversions of `Number`.
+fn number(class: BuiltinClass) -> BuiltinClass {
+    class
+        .method("intValue", "()I", atomic_int_value)
+        .method("longValue", "()J", atomic_long_value)
+        .method("floatValue", "()F", atomic_float_value)
+        .method("doubleValue", "()D", atomic_double_value)
+        .method("toString", "()Ljava/lang/String;", atomic_to_string)
+}
+
+fn atomic_integer() -> BuiltinClass {
+    let class = BuiltinClass::new(
+        "java/util/concurrent/atomic/AtomicInteger",
+        "java/lang/Number",
+    )
+    .field("value", "I");
+    let class = atomic(
+        class,
+        &AtomicDescriptors {
+            value: "(I)V",
+            get: "()I",
+            get_and_set: "(I)I",
+            compare_and_set: "(II)Z",
+            compare_and_exchange: "(II)I",
+        },
+    );
+    number(class)
+        .method("getAndIncrement", "()I", atomic_get_and_increment)
+        .method("getAndDecrement", "()I", atomic_get_and_decrement)
+        .method("incrementAndGet", "()I", atomic_increment_and_get)
+        .method("decrementAndGet", "()I", atomic_decrement_and_get)
+        .method("getAndAdd", "(I)I", atomic_get_and_add)
+        .method("addAndGet", "(I)I", atomic_add_and_get)
+        .method(
+            "getAndUpdate",
+            "(Ljava/util/function/IntUnaryOperator;)I",
+            atomic_get_and_update,
+        )
+        .method(
+            "updateAndGet",
+            "(Ljava/util/function/IntUnaryOperator;)I",
+            atomic_update_and_get,
+        )
+        .method(
+            "getAndAccumulate",
+            "(ILjava/util/function/IntBinaryOperator;)I",
+            atomic_get_and_accumulate,
+        )
+        .method(
+            "accumulateAndGet",
+            "(ILjava/util/function/IntBinaryOperator;)I",
+            atomic_accumulate_and_get,
+        )
+}
+
+fn atomic_long() -> BuiltinClass {
+    let class = BuiltinClass::new("java/util/concurrent/atomic/AtomicLong", "java/lang/Number")
+        .field("value", "J");
+    let class = atomic(
+        class,
+        &AtomicDescriptors {
+            value: "(J)V",
+            get: "()J",
+            get_and_set: "(J)J",
+            compare_and_set: "(JJ)Z",
+            compare_and_exchange: "(JJ)J",
+        },
+    );
+    number(class)
+        .method("getAndIncrement", "()J", atomic_get_and_increment)
+        .method("getAndDecrement", "()J", atomic_get_and_decrement)
+        .method("incrementAndGet", "()J", atomic_increment_and_get)
+        .method("decrementAndGet", "()J", atomic_decrement_and_get)
+        .method("getAndAdd", "(J)J", atomic_get_and_add)
+        .method("addAndGet", "(J)J", atomic_add_and_get)
+        .method(
+            "getAndUpdate",
+            "(Ljava/util/function/LongUnaryOperator;)J",
+            atomic_get_and_update,
+        )
+        .method(
+            "updateAndGet",
+            "(Ljava/util/function/LongUnaryOperator;)J",
+            atomic_update_and_get,
+        )
+        .method(
+            "getAndAccumulate",
+            "(JLjava/util/function/LongBinaryOperator;)J",
+            atomic_get_and_accumulate,
+        )
+        .method(
+            "accumulateAndGet",
+            "(JLjava/util/function/LongBinaryOperator;)J",
+            atomic_accumulate_and_get,
+        )
+}
+
+fn atomic_reference() -> BuiltinClass {
+    let class = BuiltinClass::new(
+        "java/util/concurrent/atomic/AtomicReference",
+        "java/lang/Object",
+    )
+    .implements("java/io/Serializable")
+    .field("value", "Ljava/lang/Object;");
+    atomic(
+        class,
+        &AtomicDescriptors {
+            value: "(Ljava/lang/Object;)V",
+            get: "()Ljava/lang/Object;",
+            get_and_set: "(Ljava/lang/Object;)Ljava/lang/Object;",
+            compare_and_set: "(Ljava/lang/Object;Ljava/lang/Object;)Z",
+            compare_and_exchange: "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        },
+    )
+    .method(
+        "getAndUpdate",
+        "(Ljava/util/function/UnaryOperator;)Ljava/lang/Object;",
+        atomic_get_and_update,
+    )
+    .method(
+        "updateAndGet",
+        "(Ljava/util/function/UnaryOperator;)Ljava/lang/Object;",
+        atomic_update_and_get,
+    )
+    .method(
+        "getAndAccumulate",
+        "(Ljava/lang/Object;Ljava/util/function/BinaryOperator;)Ljava/lang/Object;",
+        atomic_get_and_accumulate,
+    )
+    .method(
+        "accumulateAndGet",
+        "(Ljava/lang/Object;Ljava/util/function/BinaryOperator;)Ljava/lang/Object;",
+        atomic_accumulate_and_get,
+    )
+    .method("toString", "()Ljava/lang/String;", atomic_to_string)
+}
+
+fn load(vm: &mut Vm, atomic: Value) -> Result<Value, Unwind> {
+    let this = non_null(vm, atomic)?;
+    Ok(vm.field(this, "value").unwrap_or(Value::NULL))
+}
+
+fn store(vm: &mut Vm, atomic: Value, value: Value) -> Result<(), Unwind> {
+    let this = non_null(vm, atomic)?;
+    vm.set_field(this, "value", value);
+    Ok(())
+}
+
+/// Adds the delta to an `int` or `long` value, wrapping around on overflow.
+fn add(value: Value, delta: i64) -> Value {
+    match value {
+        Value::Int(value) => Value::Int(value.wrapping_add(delta as i32)),
+        Value::Long(value) => Value::Long(value.wrapping_add(delta)),
+        value => panic!("Cannot add to {:?}", value),
+    }
+}
+
+fn delta(value: Value) -> i64 {
+    match value {
+        Value::Int(value) => value as i64,
+        value => long(value),
+    }
+}
+
+/// Applies the functional interface of the update methods to the current
+/// value, and the operand for the accumulating ones.
+fn apply(vm: &mut Vm, function: Value, arguments: &[Value]) -> Result<Value, Unwind> {
+    let function = non_null(vm, function)?;
+    let (name, descriptor) = match (arguments[0], arguments.len()) {
+        (Value::Int(_), 1) => ("applyAsInt", "(I)I"),
+        (Value::Int(_), _) => ("applyAsInt", "(II)I"),
+        (Value::Long(_), 1) => ("applyAsLong", "(J)J"),
+        (Value::Long(_), _) => ("applyAsLong", "(JJ)J"),
+        (_, 1) => ("apply", "(Ljava/lang/Object;)Ljava/lang/Object;"),
+        (_, _) => (
+            "apply",
+            "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        ),
+    };
+    vm.invoke_virtual(function, name, descriptor, arguments)?
+        .ok_or_else(|| {
+            Unwind::Error(VmError::Internal(format!(
+                "{}{} returned no value",
+                name, descriptor
+            )))
+        })
+}
+
+fn atomic_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    if let Some(&value) = args.get(1) {
+        store(vm, args[0], value)?;
+    }
+    Ok(None)
+}
+
+fn atomic_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(load(vm, args[0])?))
+}
+
+fn atomic_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    store(vm, args[0], args[1])?;
+    Ok(None)
+}
+
+fn atomic_get_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    store(vm, args[0], args[1])?;
+    Ok(Some(previous))
+}
+
+/// References compare by identity, like the `==` of Java.
+fn atomic_compare_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let witness = atomic_compare_and_exchange(vm, args)?;
+    Ok(Some(Value::Int((witness == Some(args[1])) as i32)))
+}
+
+fn atomic_compare_and_exchange(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let witness = load(vm, args[0])?;
+    if witness == args[1] {
+        store(vm, args[0], args[2])?;
+    }
+    Ok(Some(witness))
+}
+
+fn atomic_get_and_increment(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    atomic_get_and_add(vm, &[args[0], Value::Long(1)])
+}
+
+fn atomic_get_and_decrement(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    atomic_get_and_add(vm, &[args[0], Value::Long(-1)])
+}
+
+fn atomic_increment_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    atomic_add_and_get(vm, &[args[0], Value::Long(1)])
+}
+
+fn atomic_decrement_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    atomic_add_and_get(vm, &[args[0], Value::Long(-1)])
+}
+
+fn atomic_get_and_add(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    store(vm, args[0], add(previous, delta(args[1])))?;
+    Ok(Some(previous))
+}
+
+fn atomic_add_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = add(load(vm, args[0])?, delta(args[1]));
+    store(vm, args[0], value)?;
+    Ok(Some(value))
+}
+
+fn atomic_get_and_update(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    let value = apply(vm, args[1], &[previous])?;
+    store(vm, args[0], value)?;
+    Ok(Some(previous))
+}
+
+fn atomic_update_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    let value = apply(vm, args[1], &[previous])?;
+    store(vm, args[0], value)?;
+    Ok(Some(value))
+}
+
+fn atomic_get_and_accumulate(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    let value = apply(vm, args[2], &[previous, args[1]])?;
+    store(vm, args[0], value)?;
+    Ok(Some(previous))
+}
+
+fn atomic_accumulate_and_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let previous = load(vm, args[0])?;
+    let value = apply(vm, args[2], &[previous, args[1]])?;
+    store(vm, args[0], value)?;
+    Ok(Some(value))
+}
+
+fn atomic_int_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(delta(load(vm, args[0])?) as i32)))
+}
+
+fn atomic_long_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(delta(load(vm, args[0])?))))
+}
+
+fn atomic_float_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Float(delta(load(vm, args[0])?) as f32)))
+}
+
+fn atomic_double_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Double(delta(load(vm, args[0])?) as f64)))
+}
+
+fn atomic_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = match load(vm, args[0])? {
+        value @ Value::Reference(_) => object_chars(vm, value)?,
+        value => primitive_to_string(value).encode_utf16().collect(),
+    };
+    Ok(Some(Value::Reference(Some(vm.create_string(chars)?))))
+}
+
+fn atomic_boolean_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = load(vm, args[0])? != Value::Int(0);
+    let string = vm.create_string(value.to_string().encode_utf16().collect())?;
+    Ok(Some(Value::Reference(Some(string))))
+}
+
+// =============================================================================
+// LOCK SUPPORT
+// =============================================================================
+
+/// `LockSupport`, whose blockers are not recorded.
+fn lock_support() -> BuiltinClass {
+    BuiltinClass::new("java/util/concurrent/locks/LockSupport", "java/lang/Object")
+        .static_method("park", "()V", lock_support_park)
+        .static_method("park", "(Ljava/lang/Object;)V", lock_support_park)
+        .static_method("parkNanos", "(J)V", lock_support_park_nanos)
+        .static_method(
+            "parkNanos",
+            "(Ljava/lang/Object;J)V",
+            lock_support_park_nanos,
+        )
+        .static_method("parkUntil", "(J)V", lock_support_park_until)
+        .static_method(
+            "parkUntil",
+            "(Ljava/lang/Object;J)V",
+            lock_support_park_until,
+        )
+        .static_method("unpark", "(Ljava/lang/Thread;)V", lock_support_unpark)
+}
+
+/// Parks the current thread unless its permit is available, consuming it,
+/// or it is interrupted. Parked, it lets the started threads run, and then
+/// stays parked for the timeout unless they unparked it. Without a timeout
+/// it returns at once, as no other thread could unpark it later, which is
+/// one of the spurious returns `park` allows.
+fn park(vm: &mut Vm, timeout: Option<Duration>) -> Result<(), Unwind> {
+    let thread = current_thread(vm)?;
+    let available = |vm: &mut Vm| {
+        let permit = vm.field(thread, "permit") == Some(Value::Int(1));
+        vm.set_field(thread, "permit", Value::Int(0));
+        permit || vm.field(thread, "interrupted") == Some(Value::Int(1))
+    };
+    if available(vm) {
+        return Ok(());
+    }
+
+    vm.run_started_threads()?;
+    match (available(vm), timeout) {
+        (false, Some(timeout)) => vm.sleep(timeout),
+        _ => Ok(()),
+    }
+}
+
+/// Makes the permit of the thread available.
+fn unpark(vm: &mut Vm, thread: Value) {
+    if let Value::Reference(Some(thread)) = thread {
+        vm.set_field(thread, "permit", Value::Int(1));
+    }
+}
+
+/// The duration until the deadline in milliseconds since the epoch, `None`
+/// if it passed.
+fn until(vm: &mut Vm, deadline: i64) -> Option<Duration> {
+    let remaining = deadline.saturating_sub(vm.clock.current_time_millis());
+    Some(Duration::from_millis(remaining as u64)).filter(|_| remaining > 0)
+}
+
+fn lock_support_park(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    park(vm, None)?;
+    Ok(None)
+}
+
+/// Returns at once for a timeout which is not positive.
+fn lock_support_park_nanos(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let nanos = long(args[args.len() - 1]);
+    if nanos > 0 {
+        park(vm, Some(Duration::from_nanos(nanos as u64)))?;
+    }
+    Ok(None)
+}
+
+fn lock_support_park_until(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    if let Some(timeout) = until(vm, long(args[args.len() - 1])) {
+        park(vm, Some(timeout))?;
+    }
+    Ok(None)
+}
+
+fn lock_support_unpark(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    unpark(vm, args[0]);
+    Ok(None)
+}
+
+// =============================================================================
+// UNSAFE
+// =============================================================================
+
+/// The offset of a field is its slot and the one of an array element its
+/// index, so arrays start at 0 and their elements are 1 apart whatever their
+/// type. Static fields are at their slot of the class, with this bit set.
+const STATIC_OFFSET: i64 = 1 << 32;
+
+fn unsafe_nop(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(None)
+}
+
+fn unsafe_array_base_offset(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(0)))
+}
+
+fn unsafe_array_index_scale(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(1)))
+}
+
+fn unsafe_object_field_offset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[1])?;
+    Ok(Some(Value::Long(field.slot as i64)))
+}
+
+fn unsafe_object_field_offset_by_name(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[1])?;
+    non_null(vm, args[2])?;
+    let name = String::from_utf16_lossy(&object_chars(vm, args[2])?);
+    match vm.field_slot(class, &name) {
+        Some(slot) => Ok(Some(Value::Long(slot as i64))),
+        None => Err(vm.throw_new("java/lang/InternalError", Some(name))),
+    }
+}
+
+fn unsafe_static_field_offset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[1])?;
+    Ok(Some(Value::Long(field.slot as i64 | STATIC_OFFSET)))
+}
+
+fn unsafe_static_field_base(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[1])?;
+    Ok(Some(Value::Reference(Some(vm.mirror(field.class)?))))
+}
+
+fn invalid_offset(base: ObjectRef, offset: i64) -> Unwind {
+    Unwind::Error(VmError::Internal(format!(
+        "Invalid offset {} into {}",
+        offset, base
+    )))
+}
+
+/// Reads the field or element at the offset into the base object.
+fn read(vm: &mut Vm, base: Value, offset: i64) -> Result<Value, Unwind> {
+    let object = non_null(vm, base)?;
+    let index = (offset & !STATIC_OFFSET) as usize;
+    let value = if offset & STATIC_OFFSET != 0 {
+        let class = mirrored_class(vm, base)?;
+        vm.class(class).static_values.get(index).copied()
+    } else {
+        match &vm.heap.get(object).data {
+            ObjectData::Fields(fields) => fields.get(index).copied(),
+            ObjectData::Array(array) => array.get(index),
+        }
+    };
+    value.ok_or_else(|| invalid_offset(object, offset))
+}
+
+/// Writes the field or element at the offset into the base object.
+fn write(vm: &mut Vm, base: Value, offset: i64, value: Value) -> Result<(), Unwind> {
+    let object = non_null(vm, base)?;
+    let index = (offset & !STATIC_OFFSET) as usize;
+    let written = if offset & STATIC_OFFSET != 0 {
+        let class = mirrored_class(vm, base)?;
+        match vm.class_mut(class).static_values.get_mut(index) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    } else {
+        match &mut vm.heap.get_mut(object).data {
+            ObjectData::Fields(fields) => match fields.get_mut(index) {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                }
+                None => false,
+            },
+            ObjectData::Array(array) => array.set(index, value),
+        }
+    };
+    if written {
+        Ok(())
+    } else {
+        Err(invalid_offset(object, offset))
+    }
+}
+
+fn unsafe_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(read(vm, args[1], long(args[2]))?))
+}
+
+fn unsafe_put(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[1], long(args[2]), args[3])?;
+    Ok(None)
+}
+
+fn unsafe_compare_and_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let witness = unsafe_compare_and_exchange(vm, args)?;
+    Ok(Some(Value::Int((witness == Some(args[3])) as i32)))
+}
+
+fn unsafe_compare_and_exchange(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let offset = long(args[2]);
+    let witness = read(vm, args[1], offset)?;
+    if witness == args[3] {
+        write(vm, args[1], offset, args[4])?;
+    }
+    Ok(Some(witness))
+}
+
+/// `park(isAbsolute, time)`: until the deadline in milliseconds since the
+/// epoch, or for the nanoseconds, 0 being no timeout.
+fn unsafe_park(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (absolute, time) = (args[1] == Value::Int(1), long(args[2]));
+    let timeout = match (absolute, time) {
+        (false, 0) => None,
+        (false, nanos) if nanos > 0 => Some(Duration::from_nanos(nanos as u64)),
+        (true, deadline) => match until(vm, deadline) {
+            Some(timeout) => Some(timeout),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    park(vm, timeout)?;
+    Ok(None)
+}
+
+fn unsafe_unpark(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    unpark(vm, args[1]);
+    Ok(None)
+}
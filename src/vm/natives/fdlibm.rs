@@ -0,0 +1,1811 @@
+//! A port of fdlibm 5.3, whose results `StrictMath` specifies bit for bit:
+//! the functions the JDK implements its natives with and those it ports to
+//! Java. They follow the C sources closely, operating on the high and low
+//! words of the doubles like them, raising NaN as `(x - x) / (x - x)`; the
+//! comments and the constants, digit for digit, are theirs.
+
+#![allow(clippy::approx_constant, clippy::eq_op, clippy::excessive_precision)]
+
+// =============================================================================
+// WORDS
+// =============================================================================
+
+fn hi(x: f64) -> i32 {
+    (x.to_bits() >> 32) as i32
+}
+
+fn lo(x: f64) -> u32 {
+    x.to_bits() as u32
+}
+
+fn from_words(hi: i32, lo: u32) -> f64 {
+    f64::from_bits(((hi as u32 as u64) << 32) | lo as u64)
+}
+
+fn with_hi(x: f64, hi: i32) -> f64 {
+    from_words(hi, lo(x))
+}
+
+fn with_lo(x: f64, lo: u32) -> f64 {
+    from_words(hi(x), lo)
+}
+
+const TWO54: f64 = 1.80143985094819840000e+16;
+const TWOM54: f64 = 5.55111512312578270212e-17;
+const HUGE: f64 = 1.0e+300;
+const TINY: f64 = 1.0e-300;
+
+/// x * 2^n, computed by exponent manipulation.
+fn scalbn(x: f64, n: i32) -> f64 {
+    let mut hx = hi(x);
+    let lx = lo(x);
+    let mut k = (hx & 0x7ff00000) >> 20;
+    let mut x = x;
+    if k == 0 {
+        // 0 or subnormal x
+        if (lx | (hx as u32 & 0x7fffffff)) == 0 {
+            return x;
+        }
+        x *= TWO54;
+        hx = hi(x);
+        k = ((hx & 0x7ff00000) >> 20) - 54;
+        if n < -50000 {
+            return TINY * x;
+        }
+    }
+    if k == 0x7ff {
+        // NaN or Inf
+        return x + x;
+    }
+    let k = k as i64 + n as i64;
+    if k > 0x7fe {
+        return HUGE * HUGE.copysign(x);
+    }
+    if k > 0 {
+        return with_hi(x, (hx & 0x800fffffu32 as i32) | ((k as i32) << 20));
+    }
+    if k <= -54 {
+        if n > 50000 {
+            return HUGE * HUGE.copysign(x);
+        }
+        return TINY * TINY.copysign(x);
+    }
+    let x = with_hi(x, (hx & 0x800fffffu32 as i32) | (((k + 54) as i32) << 20));
+    x * TWOM54
+}
+
+// =============================================================================
+// ARGUMENT REDUCTION
+// =============================================================================
+
+/// The bits of 2/pi, 24 per element.
+#[rustfmt::skip]
+const TWO_OVER_PI: [i32; 66] = [
+    0xA2F983, 0x6E4E44, 0x1529FC, 0x2757D1, 0xF534DD, 0xC0DB62,
+    0x95993C, 0x439041, 0xFE5163, 0xABDEBB, 0xC561B7, 0x246E3A,
+    0x424DD2, 0xE00649, 0x2EEA09, 0xD1921C, 0xFE1DEB, 0x1CB129,
+    0xA73EE8, 0x8235F5, 0x2EBB44, 0x84E99C, 0x7026B4, 0x5F7E41,
+    0x3991D6, 0x398353, 0x39F49C, 0x845F8B, 0xBDF928, 0x3B1FF8,
+    0x97FFDE, 0x05980F, 0xEF2F11, 0x8B5A0A, 0x6D1F6D, 0x367ECF,
+    0x27CB09, 0xB74F46, 0x3F669E, 0x5FEA2D, 0x7527BA, 0xC7EBE5,
+    0xF17B3D, 0x0739F7, 0x8A5292, 0xEA6BFB, 0x5FB11F, 0x8D5D08,
+    0x560330, 0x46FC7B, 0x6BABF0, 0xCFBC20, 0x9AF436, 0x1DA9E3,
+    0x91615E, 0xE61B08, 0x659985, 0x5F14A0, 0x68408D, 0xFFD880,
+    0x4D7327, 0x310606, 0x1556CA, 0x73A8C9, 0x60E27B, 0xC08C6B,
+];
+
+/// The high words of n * pi/2, for n from 1 to 32.
+#[rustfmt::skip]
+const NPIO2_HW: [i32; 32] = [
+    0x3FF921FB, 0x400921FB, 0x4012D97C, 0x401921FB, 0x401F6A7A, 0x4022D97C,
+    0x4025FDBB, 0x402921FB, 0x402C463A, 0x402F6A7A, 0x4031475C, 0x4032D97C,
+    0x40346B9C, 0x4035FDBB, 0x40378FDB, 0x403921FB, 0x403AB41B, 0x403C463A,
+    0x403DD85A, 0x403F6A7A, 0x40407E4C, 0x4041475C, 0x4042106C, 0x4042D97C,
+    0x4043A28C, 0x40446B9C, 0x404534AC, 0x4045FDBB, 0x4046C6CB, 0x40478FDB,
+    0x404858EB, 0x404921FB,
+];
+
+const TWO24: f64 = 1.67772160000000000000e+07;
+const TWON24: f64 = 5.96046447753906250000e-08;
+const INVPIO2: f64 = 6.36619772367581382433e-01;
+const PIO2_1: f64 = 1.57079632673412561417e+00;
+const PIO2_1T: f64 = 6.07710050650619224932e-11;
+const PIO2_2: f64 = 6.07710050630396597660e-11;
+const PIO2_2T: f64 = 2.02226624879595063154e-21;
+const PIO2_3: f64 = 2.02226624871116645580e-21;
+const PIO2_3T: f64 = 8.47842766036889956997e-32;
+
+/// Returns the remainder of x rem pi/2 in y[0] + y[1], and the quadrant.
+fn rem_pio2(x: f64, y: &mut [f64; 2]) -> i32 {
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff;
+    if ix <= 0x3fe921fb {
+        // |x| ~<= pi/4, no need for reduction
+        y[0] = x;
+        y[1] = 0.0;
+        return 0;
+    }
+    if ix < 0x4002d97c {
+        // |x| < 3pi/4, special case with n=+-1
+        return if hx > 0 {
+            let mut z = x - PIO2_1;
+            if ix != 0x3ff921fb {
+                // 33+53 bit pi is good enough
+                y[0] = z - PIO2_1T;
+                y[1] = (z - y[0]) - PIO2_1T;
+            } else {
+                // near pi/2, use 33+33+53 bit pi
+                z -= PIO2_2;
+                y[0] = z - PIO2_2T;
+                y[1] = (z - y[0]) - PIO2_2T;
+            }
+            1
+        } else {
+            let mut z = x + PIO2_1;
+            if ix != 0x3ff921fb {
+                y[0] = z + PIO2_1T;
+                y[1] = (z - y[0]) + PIO2_1T;
+            } else {
+                z += PIO2_2;
+                y[0] = z + PIO2_2T;
+                y[1] = (z - y[0]) + PIO2_2T;
+            }
+            -1
+        };
+    }
+    if ix <= 0x413921fb {
+        // |x| ~<= 2^19*(pi/2), medium size
+        let mut t = x.abs();
+        let n = (t * INVPIO2 + 0.5) as i32;
+        let fn_ = n as f64;
+        let mut r = t - fn_ * PIO2_1;
+        let mut w = fn_ * PIO2_1T; // 1st round good to 85 bit
+        if n < 32 && ix != NPIO2_HW[n as usize - 1] {
+            y[0] = r - w; // quick check no cancellation
+        } else {
+            let j = ix >> 20;
+            y[0] = r - w;
+            let i = j - ((hi(y[0]) >> 20) & 0x7ff);
+            if i > 16 {
+                // 2nd iteration needed, good to 118
+                t = r;
+                w = fn_ * PIO2_2;
+                r = t - w;
+                w = fn_ * PIO2_2T - ((t - r) - w);
+                y[0] = r - w;
+                let i = j - ((hi(y[0]) >> 20) & 0x7ff);
+                if i > 49 {
+                    // 3rd iteration need, 151 bits acc
+                    t = r;
+                    w = fn_ * PIO2_3;
+                    r = t - w;
+                    w = fn_ * PIO2_3T - ((t - r) - w);
+                    y[0] = r - w;
+                }
+            }
+        }
+        y[1] = (r - y[0]) - w;
+        if hx < 0 {
+            y[0] = -y[0];
+            y[1] = -y[1];
+            return -n;
+        }
+        return n;
+    }
+
+    // all other (large) arguments
+    if ix >= 0x7ff00000 {
+        // x is inf or NaN
+        y[0] = x - x;
+        y[1] = y[0];
+        return 0;
+    }
+    // set z = scalbn(|x|,ilogb(x)-23)
+    let e0 = (ix >> 20) - 1046; // e0 = ilogb(z)-23;
+    let mut z = from_words(ix - (e0 << 20), lo(x));
+    let mut tx = [0.0; 3];
+    for value in tx.iter_mut().take(2) {
+        *value = (z as i32) as f64;
+        z = (z - *value) * TWO24;
+    }
+    tx[2] = z;
+    let mut nx = 3;
+    while tx[nx - 1] == 0.0 {
+        nx -= 1; // skip zero term
+    }
+    let n = kernel_rem_pio2(&tx[..nx], y, e0);
+    if hx < 0 {
+        y[0] = -y[0];
+        y[1] = -y[1];
+        return -n;
+    }
+    n
+}
+
+/// The 24-bit chunks of pi/2.
+#[rustfmt::skip]
+const PIO2: [f64; 8] = [
+    1.57079625129699707031e+00,
+    7.54978941586159635335e-08,
+    5.39030252995776476554e-15,
+    3.28200341580791294123e-22,
+    1.27065575308067607349e-29,
+    1.22933308981111328932e-36,
+    2.73370053816464559624e-44,
+    2.16741683877804819444e-51,
+];
+
+/// `__kernel_rem_pio2` for double precision (prec 2), reducing the 24-bit
+/// chunks x of a large argument scaled by 2^-e0.
+fn kernel_rem_pio2(x: &[f64], y: &mut [f64; 2], e0: i32) -> i32 {
+    let jk = 4usize; // init_jk[2]
+    let jp = jk;
+    let mut iq = [0i32; 20];
+    let mut f = [0.0f64; 20];
+    let mut fq = [0.0f64; 20];
+    let mut q = [0.0f64; 20];
+
+    // determine jx,jv,q0, note that 3>q0
+    let jx = x.len() - 1;
+    let jv = ((e0 - 3) / 24).max(0);
+    let mut q0 = e0 - 24 * (jv + 1);
+
+    // set up f[0] to f[jx+jk] where f[jx+jk] = ipio2[jv+jk]
+    let m = jx + jk;
+    for (j, value) in (jv - jx as i32..).zip(f.iter_mut().take(m + 1)) {
+        *value = if j < 0 {
+            0.0
+        } else {
+            TWO_OVER_PI[j as usize] as f64
+        };
+    }
+
+    // compute q[0],q[1],...q[jk]
+    for i in 0..=jk {
+        let mut fw = 0.0;
+        for j in 0..=jx {
+            fw += x[j] * f[jx + i - j];
+        }
+        q[i] = fw;
+    }
+
+    let mut jz = jk;
+    let (mut z, mut n, mut ih);
+    loop {
+        // distill q[] into iq[] reversingly
+        z = q[jz];
+        let mut i = 0;
+        let mut j = jz;
+        while j > 0 {
+            let fw = ((TWON24 * z) as i32) as f64;
+            iq[i] = (z - TWO24 * fw) as i32;
+            z = q[j - 1] + fw;
+            i += 1;
+            j -= 1;
+        }
+
+        // compute n
+        z = scalbn(z, q0); // actual value of z
+        z -= 8.0 * (z * 0.125).floor(); // trim off integer >= 8
+        n = z as i32;
+        z -= n as f64;
+        ih = 0;
+        if q0 > 0 {
+            // need iq[jz-1] to determine n
+            let i = iq[jz - 1] >> (24 - q0);
+            n += i;
+            iq[jz - 1] -= i << (24 - q0);
+            ih = iq[jz - 1] >> (23 - q0);
+        } else if q0 == 0 {
+            ih = iq[jz - 1] >> 23;
+        } else if z >= 0.5 {
+            ih = 2;
+        }
+
+        if ih > 0 {
+            // q > 0.5
+            n += 1;
+            let mut carry = 0;
+            for value in iq.iter_mut().take(jz) {
+                // compute 1-q
+                let j = *value;
+                if carry == 0 {
+                    if j != 0 {
+                        carry = 1;
+                        *value = 0x1000000 - j;
+                    }
+                } else {
+                    *value = 0xffffff - j;
+                }
+            }
+            if q0 > 0 {
+                // rare case: chance is 1 in 12
+                match q0 {
+                    1 => iq[jz - 1] &= 0x7fffff,
+                    2 => iq[jz - 1] &= 0x3fffff,
+                    _ => {}
+                }
+            }
+            if ih == 2 {
+                z = 1.0 - z;
+                if carry != 0 {
+                    z -= scalbn(1.0, q0);
+                }
+            }
+        }
+
+        // check if recomputation is needed
+        if z == 0.0 {
+            let mut j = 0;
+            for i in (jk..jz).rev() {
+                j |= iq[i];
+            }
+            if j == 0 {
+                // need recomputation
+                let mut k = 1;
+                while iq[jk - k] == 0 {
+                    k += 1; // k = no. of terms needed
+                }
+                for i in jz + 1..=jz + k {
+                    // add q[jz+1] to q[jz+k]
+                    f[jx + i] = TWO_OVER_PI[jv as usize + i] as f64;
+                    let mut fw = 0.0;
+                    for j in 0..=jx {
+                        fw += x[j] * f[jx + i - j];
+                    }
+                    q[i] = fw;
+                }
+                jz += k;
+                continue;
+            }
+        }
+        break;
+    }
+
+    // chop off zero terms
+    if z == 0.0 {
+        jz -= 1;
+        q0 -= 24;
+        while iq[jz] == 0 {
+            jz -= 1;
+            q0 -= 24;
+        }
+    } else {
+        // break z into 24-bit if necessary
+        z = scalbn(z, -q0);
+        if z >= TWO24 {
+            let fw = ((TWON24 * z) as i32) as f64;
+            iq[jz] = (z - TWO24 * fw) as i32;
+            jz += 1;
+            q0 += 24;
+            iq[jz] = fw as i32;
+        } else {
+            iq[jz] = z as i32;
+        }
+    }
+
+    // convert integer "bit" chunk to floating-point value
+    let mut fw = scalbn(1.0, q0);
+    for i in (0..=jz).rev() {
+        q[i] = fw * iq[i] as f64;
+        fw *= TWON24;
+    }
+
+    // compute PIo2[0,...,jp]*q[jz,...,0]
+    for i in (0..=jz).rev() {
+        let mut fw = 0.0;
+        let mut k = 0;
+        while k <= jp && k <= jz - i {
+            fw += PIO2[k] * q[i + k];
+            k += 1;
+        }
+        fq[jz - i] = fw;
+    }
+
+    // compress fq[] into y[]
+    let mut fw = 0.0;
+    for i in (0..=jz).rev() {
+        fw += fq[i];
+    }
+    y[0] = if ih == 0 { fw } else { -fw };
+    fw = fq[0] - fw;
+    for value in fq.iter().take(jz + 1).skip(1) {
+        fw += value;
+    }
+    y[1] = if ih == 0 { fw } else { -fw };
+    n & 7
+}
+
+// =============================================================================
+// TRIGONOMETRIC FUNCTIONS
+// =============================================================================
+
+const S1: f64 = -1.66666666666666324348e-01;
+const S2: f64 = 8.33333333332248946124e-03;
+const S3: f64 = -1.98412698298579493134e-04;
+const S4: f64 = 2.75573137070700676789e-06;
+const S5: f64 = -2.50507602534068634195e-08;
+const S6: f64 = 1.58969099521155010221e-10;
+
+/// sin(x + y) on [-pi/4, pi/4], y being the tail of x; iy is 0 if y is 0.
+fn kernel_sin(x: f64, y: f64, iy: i32) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+    if ix < 0x3e400000 && x as i32 == 0 {
+        // |x| < 2**-27, generate inexact
+        return x;
+    }
+    let z = x * x;
+    let v = z * x;
+    let r = S2 + z * (S3 + z * (S4 + z * (S5 + z * S6)));
+    if iy == 0 {
+        x + v * (S1 + z * r)
+    } else {
+        x - ((z * (0.5 * y - v * r) - y) - v * S1)
+    }
+}
+
+const C1: f64 = 4.16666666666666019037e-02;
+const C2: f64 = -1.38888888888741095749e-03;
+const C3: f64 = 2.48015872894767294178e-05;
+const C4: f64 = -2.75573143513906633035e-07;
+const C5: f64 = 2.08757232129817482790e-09;
+const C6: f64 = -1.13596475577881948265e-11;
+
+/// cos(x + y) on [-pi/4, pi/4], y being the tail of x.
+fn kernel_cos(x: f64, y: f64) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+    if ix < 0x3e400000 && x as i32 == 0 {
+        // if x < 2**27, generate inexact
+        return 1.0;
+    }
+    let z = x * x;
+    let r = z * (C1 + z * (C2 + z * (C3 + z * (C4 + z * (C5 + z * C6)))));
+    if ix < 0x3FD33333 {
+        // if |x| < 0.3
+        1.0 - (0.5 * z - (z * r - x * y))
+    } else {
+        let qx = if ix > 0x3fe90000 {
+            // x > 0.78125
+            0.28125
+        } else {
+            from_words(ix - 0x00200000, 0) // x/4
+        };
+        let hz = 0.5 * z - qx;
+        let a = 1.0 - qx;
+        a - (hz - (z * r - x * y))
+    }
+}
+
+#[rustfmt::skip]
+const T: [f64; 13] = [
+    3.33333333333334091986e-01,
+    1.33333333333201242699e-01,
+    5.39682539762260521377e-02,
+    2.18694882948595424599e-02,
+    8.86323982359930005737e-03,
+    3.59207910759131235356e-03,
+    1.45620945432529025516e-03,
+    5.88041240820264096874e-04,
+    2.46463134818469906812e-04,
+    7.81794442939557092300e-05,
+    7.14072491382608190305e-05,
+    -1.85586374855275456654e-05,
+    2.59073051863633712884e-05,
+];
+const PIO4: f64 = 7.85398163397448278999e-01;
+const PIO4LO: f64 = 3.06161699786838301793e-17;
+
+/// tan(x + y) on [-pi/4, pi/4] if iy is 1, -1/tan(x + y) if it is -1.
+fn kernel_tan(x: f64, y: f64, iy: i32) -> f64 {
+    let (mut x, mut y) = (x, y);
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff; // high word of |x|
+    if ix < 0x3e300000 && x as i32 == 0 {
+        // x < 2**-28, generate inexact
+        if ((ix as u32 | lo(x)) | (iy + 1) as u32) == 0 {
+            return 1.0 / x.abs();
+        } else if iy == 1 {
+            return x;
+        } else {
+            // compute -1 / (x+y) carefully
+            let w = x + y;
+            let z = with_lo(w, 0);
+            let v = y - (z - x);
+            let a = -1.0 / w;
+            let t = with_lo(a, 0);
+            let s = 1.0 + t * z;
+            return t + a * (s + t * v);
+        }
+    }
+    if ix >= 0x3FE59428 {
+        // |x| >= 0.6744
+        if hx < 0 {
+            x = -x;
+            y = -y;
+        }
+        let z = PIO4 - x;
+        let w = PIO4LO - y;
+        x = z + w;
+        y = 0.0;
+    }
+    let z = x * x;
+    let w = z * z;
+    // Break x^5*(T[1]+x^2*T[2]+...) into
+    // x^5(T[1]+x^4*T[3]+...+x^20*T[11]) +
+    // x^5(x^2*(T[2]+x^4*T[4]+...+x^22*[T12]))
+    let mut r = T[1] + w * (T[3] + w * (T[5] + w * (T[7] + w * (T[9] + w * T[11]))));
+    let v = z * (T[2] + w * (T[4] + w * (T[6] + w * (T[8] + w * (T[10] + w * T[12])))));
+    let s = z * x;
+    r = y + z * (s * (r + v) + y);
+    r += T[0] * s;
+    let w = x + r;
+    if ix >= 0x3FE59428 {
+        let v = iy as f64;
+        return (1 - ((hx >> 30) & 2)) as f64 * (v - 2.0 * (x - (w * w / (w + v) - r)));
+    }
+    if iy == 1 {
+        w
+    } else {
+        // compute -1.0 / (x+r) accurately
+        let z = with_lo(w, 0);
+        let v = r - (z - x); // z+v = r+x
+        let a = -1.0 / w;
+        let t = with_lo(a, 0);
+        let s = 1.0 + t * z;
+        t + a * (s + t * v)
+    }
+}
+
+pub fn sin(x: f64) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+    if ix <= 0x3fe921fb {
+        return kernel_sin(x, 0.0, 0);
+    }
+    if ix >= 0x7ff00000 {
+        // sin(Inf or NaN) is NaN
+        return x - x;
+    }
+    let mut y = [0.0; 2];
+    match rem_pio2(x, &mut y) & 3 {
+        0 => kernel_sin(y[0], y[1], 1),
+        1 => kernel_cos(y[0], y[1]),
+        2 => -kernel_sin(y[0], y[1], 1),
+        _ => -kernel_cos(y[0], y[1]),
+    }
+}
+
+pub fn cos(x: f64) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+    if ix <= 0x3fe921fb {
+        return kernel_cos(x, 0.0);
+    }
+    if ix >= 0x7ff00000 {
+        // cos(Inf or NaN) is NaN
+        return x - x;
+    }
+    let mut y = [0.0; 2];
+    match rem_pio2(x, &mut y) & 3 {
+        0 => kernel_cos(y[0], y[1]),
+        1 => -kernel_sin(y[0], y[1], 1),
+        2 => -kernel_cos(y[0], y[1]),
+        _ => kernel_sin(y[0], y[1], 1),
+    }
+}
+
+pub fn tan(x: f64) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+    if ix <= 0x3fe921fb {
+        return kernel_tan(x, 0.0, 1);
+    }
+    if ix >= 0x7ff00000 {
+        // tan(Inf or NaN) is NaN
+        return x - x;
+    }
+    let mut y = [0.0; 2];
+    let n = rem_pio2(x, &mut y);
+    // 1 -- n even, -1 -- n odd
+    kernel_tan(y[0], y[1], 1 - ((n & 1) << 1))
+}
+
+// =============================================================================
+// INVERSE TRIGONOMETRIC FUNCTIONS
+// =============================================================================
+
+const PI: f64 = 3.14159265358979311600e+00;
+const PIO2_HI: f64 = 1.57079632679489655800e+00;
+const PIO2_LO: f64 = 6.12323399573676603587e-17;
+const PIO4_HI: f64 = 7.85398163397448278999e-01;
+const PS0: f64 = 1.66666666666666657415e-01;
+const PS1: f64 = -3.25565818622400915405e-01;
+const PS2: f64 = 2.01212532134862925881e-01;
+const PS3: f64 = -4.00555345006794114027e-02;
+const PS4: f64 = 7.91534994289814532176e-04;
+const PS5: f64 = 3.47933107596021167570e-05;
+const QS1: f64 = -2.40339491173441421878e+00;
+const QS2: f64 = 2.02094576023350569471e+00;
+const QS3: f64 = -6.88283971605453293030e-01;
+const QS4: f64 = 7.70381505559019352791e-02;
+
+/// The rational approximation of (asin(x) - x) / x^3 both functions share.
+fn asin_ratio(t: f64) -> (f64, f64) {
+    let p = t * (PS0 + t * (PS1 + t * (PS2 + t * (PS3 + t * (PS4 + t * PS5)))));
+    let q = 1.0 + t * (QS1 + t * (QS2 + t * (QS3 + t * QS4)));
+    (p, q)
+}
+
+pub fn asin(x: f64) -> f64 {
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff;
+    if ix >= 0x3ff00000 {
+        // |x|>= 1
+        if ((ix - 0x3ff00000) as u32 | lo(x)) == 0 {
+            // asin(1)=+-pi/2 with inexact
+            return x * PIO2_HI + x * PIO2_LO;
+        }
+        return (x - x) / (x - x); // asin(|x|>1) is NaN
+    } else if ix < 0x3fe00000 {
+        // |x|<0.5
+        if ix < 0x3e400000 {
+            // if |x| < 2**-27, return x with inexact if x!=0
+            return x;
+        }
+        let t = x * x;
+        let (p, q) = asin_ratio(t);
+        let w = p / q;
+        return x + x * w;
+    }
+    // 1> |x|>= 0.5
+    let w = 1.0 - x.abs();
+    let t = w * 0.5;
+    let (p, q) = asin_ratio(t);
+    let s = t.sqrt();
+    let t = if ix >= 0x3FEF3333 {
+        // if |x| > 0.975
+        let w = p / q;
+        PIO2_HI - (2.0 * (s + s * w) - PIO2_LO)
+    } else {
+        let w = with_lo(s, 0);
+        let c = (t - w * w) / (s + w);
+        let r = p / q;
+        let p = 2.0 * s * r - (PIO2_LO - 2.0 * c);
+        let q = PIO4_HI - 2.0 * w;
+        PIO4_HI - (p - q)
+    };
+    if hx > 0 {
+        t
+    } else {
+        -t
+    }
+}
+
+pub fn acos(x: f64) -> f64 {
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff;
+    if ix >= 0x3ff00000 {
+        // |x| >= 1
+        if ((ix - 0x3ff00000) as u32 | lo(x)) == 0 {
+            // |x|==1
+            return if hx > 0 {
+                0.0 // acos(1) = 0
+            } else {
+                PI + 2.0 * PIO2_LO // acos(-1)= pi
+            };
+        }
+        return (x - x) / (x - x); // acos(|x|>1) is NaN
+    }
+    if ix < 0x3fe00000 {
+        // |x| < 0.5
+        if ix <= 0x3c600000 {
+            return PIO2_HI + PIO2_LO; // if|x|<2**-57
+        }
+        let z = x * x;
+        let (p, q) = asin_ratio(z);
+        let r = p / q;
+        PIO2_HI - (x - (PIO2_LO - x * r))
+    } else if hx < 0 {
+        // x < -0.5
+        let z = (1.0 + x) * 0.5;
+        let (p, q) = asin_ratio(z);
+        let s = z.sqrt();
+        let r = p / q;
+        let w = r * s - PIO2_LO;
+        PI - 2.0 * (s + w)
+    } else {
+        // x > 0.5
+        let z = (1.0 - x) * 0.5;
+        let s = z.sqrt();
+        let df = with_lo(s, 0);
+        let c = (z - df * df) / (s + df);
+        let (p, q) = asin_ratio(z);
+        let r = p / q;
+        let w = r * s + c;
+        2.0 * (df + w)
+    }
+}
+
+#[rustfmt::skip]
+const ATANHI: [f64; 4] = [
+    4.63647609000806093515e-01, // atan(0.5)hi
+    7.85398163397448278999e-01, // atan(1.0)hi
+    9.82793723247329054082e-01, // atan(1.5)hi
+    1.57079632679489655800e+00, // atan(inf)hi
+];
+
+#[rustfmt::skip]
+const ATANLO: [f64; 4] = [
+    2.26987774529616870924e-17, // atan(0.5)lo
+    3.06161699786838301793e-17, // atan(1.0)lo
+    1.39033110312309984516e-17, // atan(1.5)lo
+    6.12323399573676603587e-17, // atan(inf)lo
+];
+
+#[rustfmt::skip]
+const AT: [f64; 11] = [
+    3.33333333333329318027e-01,
+    -1.99999999998764832476e-01,
+    1.42857142725034663711e-01,
+    -1.11111104054623557880e-01,
+    9.09088713343650656196e-02,
+    -7.69187620504482999495e-02,
+    6.66107313738753120669e-02,
+    -5.83357013379057348645e-02,
+    4.97687799461593236017e-02,
+    -3.65315727442169155270e-02,
+    1.62858201153657823623e-02,
+];
+
+pub fn atan(x: f64) -> f64 {
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff;
+    let mut x = x;
+    let id: i32;
+    if ix >= 0x44100000 {
+        // if |x| >= 2^66
+        if ix > 0x7ff00000 || (ix == 0x7ff00000 && lo(x) != 0) {
+            return x + x; // NaN
+        }
+        return if hx > 0 {
+            ATANHI[3] + ATANLO[3]
+        } else {
+            -ATANHI[3] - ATANLO[3]
+        };
+    }
+    if ix < 0x3fdc0000 {
+        // |x| < 0.4375
+        if ix < 0x3e200000 {
+            // |x| < 2^-29, raise inexact
+            return x;
+        }
+        id = -1;
+    } else {
+        x = x.abs();
+        if ix < 0x3ff30000 {
+            // |x| < 1.1875
+            if ix < 0x3fe60000 {
+                // 7/16 <=|x|<11/16
+                id = 0;
+                x = (2.0 * x - 1.0) / (2.0 + x);
+            } else {
+                // 11/16<=|x|< 19/16
+                id = 1;
+                x = (x - 1.0) / (x + 1.0);
+            }
+        } else if ix < 0x40038000 {
+            // |x| < 2.4375
+            id = 2;
+            x = (x - 1.5) / (1.0 + 1.5 * x);
+        } else {
+            // 2.4375 <= |x| < 2^66
+            id = 3;
+            x = -1.0 / x;
+        }
+    }
+    // end of argument reduction
+    let z = x * x;
+    let w = z * z;
+    // break sum from i=0 to 10 aT[i]z**(i+1) into odd and even poly
+    let s1 = z * (AT[0] + w * (AT[2] + w * (AT[4] + w * (AT[6] + w * (AT[8] + w * AT[10])))));
+    let s2 = w * (AT[1] + w * (AT[3] + w * (AT[5] + w * (AT[7] + w * AT[9]))));
+    if id < 0 {
+        return x - x * (s1 + s2);
+    }
+    let id = id as usize;
+    let z = ATANHI[id] - ((x * (s1 + s2) - ATANLO[id]) - x);
+    if hx < 0 {
+        -z
+    } else {
+        z
+    }
+}
+
+const PI_O_4: f64 = 7.8539816339744827900E-01;
+const PI_O_2: f64 = 1.5707963267948965580E+00;
+const PI_LO: f64 = 1.2246467991473531772E-16;
+
+pub fn atan2(y: f64, x: f64) -> f64 {
+    let hx = hi(x);
+    let ix = hx & 0x7fffffff;
+    let lx = lo(x);
+    let hy = hi(y);
+    let iy = hy & 0x7fffffff;
+    let ly = lo(y);
+    if (ix as u32 | ((lx | lx.wrapping_neg()) >> 31)) > 0x7ff00000
+        || (iy as u32 | ((ly | ly.wrapping_neg()) >> 31)) > 0x7ff00000
+    {
+        // x or y is NaN
+        return x + y;
+    }
+    if (hx.wrapping_sub(0x3ff00000) as u32 | lx) == 0 {
+        return atan(y); // x=1.0
+    }
+    let m = ((hy >> 31) & 1) | ((hx >> 30) & 2); // 2*sign(x)+sign(y)
+
+    // when y = 0
+    if (iy as u32 | ly) == 0 {
+        match m {
+            0 | 1 => return y,      // atan(+-0,+anything)=+-0
+            2 => return PI + TINY,  // atan(+0,-anything) = pi
+            _ => return -PI - TINY, // atan(-0,-anything) =-pi
+        }
+    }
+    // when x = 0
+    if (ix as u32 | lx) == 0 {
+        return if hy < 0 {
+            -PI_O_2 - TINY
+        } else {
+            PI_O_2 + TINY
+        };
+    }
+
+    // when x is INF
+    if ix == 0x7ff00000 {
+        if iy == 0x7ff00000 {
+            return match m {
+                0 => PI_O_4 + TINY,        // atan(+INF,+INF)
+                1 => -PI_O_4 - TINY,       // atan(-INF,+INF)
+                2 => 3.0 * PI_O_4 + TINY,  // atan(+INF,-INF)
+                _ => -3.0 * PI_O_4 - TINY, // atan(-INF,-INF)
+            };
+        } else {
+            return match m {
+                0 => 0.0,        // atan(+...,+INF)
+                1 => -0.0,       // atan(-...,+INF)
+                2 => PI + TINY,  // atan(+...,-INF)
+                _ => -PI - TINY, // atan(-...,-INF)
+            };
+        }
+    }
+    // when y is INF
+    if iy == 0x7ff00000 {
+        return if hy < 0 {
+            -PI_O_2 - TINY
+        } else {
+            PI_O_2 + TINY
+        };
+    }
+
+    // compute y/x
+    let k = (iy - ix) >> 20;
+    let z = if k > 60 {
+        PI_O_2 + 0.5 * PI_LO // |y/x| >  2**60
+    } else if hx < 0 && k < -60 {
+        0.0 // |y|/x < -2**60
+    } else {
+        atan((y / x).abs()) // safe to do y/x
+    };
+    match m {
+        0 => z,                                        // atan(+,+)
+        1 => with_hi(z, hi(z) ^ 0x80000000u32 as i32), // atan(-,+)
+        2 => PI - (z - PI_LO),                         // atan(+,-)
+        _ => (z - PI_LO) - PI,                         // atan(-,-)
+    }
+}
+
+// =============================================================================
+// EXPONENTIALS AND LOGARITHMS
+// =============================================================================
+
+const LN2_HI: f64 = 6.93147180369123816490e-01;
+const LN2_LO: f64 = 1.90821492927058770002e-10;
+const INVLN2: f64 = 1.44269504088896338700e+00;
+const O_THRESHOLD: f64 = 7.09782712893383973096e+02;
+const U_THRESHOLD: f64 = -7.45133219101941108420e+02;
+const TWOM1000: f64 = 9.33263618503218878990e-302;
+const P1: f64 = 1.66666666666666019037e-01;
+const P2: f64 = -2.77777777770155933842e-03;
+const P3: f64 = 6.61375632143793436117e-05;
+const P4: f64 = -1.65339022054652515390e-06;
+const P5: f64 = 4.13813679705723846039e-08;
+
+pub fn exp(x: f64) -> f64 {
+    let hx = hi(x) as u32;
+    let xsb = ((hx >> 31) & 1) as usize; // sign bit of x
+    let hx = hx & 0x7fffffff; // high word of |x|
+    let (ln2hi, ln2lo, half) = ([LN2_HI, -LN2_HI], [LN2_LO, -LN2_LO], [0.5, -0.5]);
+
+    // filter out non-finite argument
+    if hx >= 0x40862E42 {
+        // if |x|>=709.78...
+        if hx >= 0x7ff00000 {
+            if ((hx & 0xfffff) | lo(x)) != 0 {
+                return x + x; // NaN
+            }
+            // exp(+-inf)={inf,0}
+            return if xsb == 0 { x } else { 0.0 };
+        }
+        if x > O_THRESHOLD {
+            return HUGE * HUGE; // overflow
+        }
+        if x < U_THRESHOLD {
+            return TWOM1000 * TWOM1000; // underflow
+        }
+    }
+
+    // argument reduction
+    let (mut x, mut hi_part, mut lo_part, mut k) = (x, 0.0, 0.0, 0i32);
+    if hx > 0x3fd62e42 {
+        // if  |x| > 0.5 ln2
+        if hx < 0x3FF0A2B2 {
+            // and |x| < 1.5 ln2
+            hi_part = x - ln2hi[xsb];
+            lo_part = ln2lo[xsb];
+            k = 1 - xsb as i32 - xsb as i32;
+        } else {
+            k = (INVLN2 * x + half[xsb]) as i32;
+            let t = k as f64;
+            hi_part = x - t * ln2hi[0]; // t*ln2HI is exact here
+            lo_part = t * ln2lo[0];
+        }
+        x = hi_part - lo_part;
+    } else if hx < 0x3e300000 {
+        // when |x|<2**-28, trigger inexact
+        return 1.0 + x;
+    }
+
+    // x is now in primary range
+    let t = x * x;
+    let c = x - t * (P1 + t * (P2 + t * (P3 + t * (P4 + t * P5))));
+    if k == 0 {
+        return 1.0 - ((x * c) / (c - 2.0) - x);
+    }
+    let y = 1.0 - ((lo_part - (x * c) / (2.0 - c)) - hi_part);
+    if k >= -1021 {
+        with_hi(y, hi(y).wrapping_add(k << 20)) // add k to y's exponent
+    } else {
+        with_hi(y, hi(y).wrapping_add((k + 1000) << 20)) * TWOM1000
+    }
+}
+
+const Q1: f64 = -3.33333333333331316428e-02;
+const Q2: f64 = 1.58730158725481460165e-03;
+const Q3: f64 = -7.93650757867487942473e-05;
+const Q4: f64 = 4.00821782732936239552e-06;
+const Q5: f64 = -2.01099218183624371326e-07;
+
+pub fn expm1(x: f64) -> f64 {
+    let hx = hi(x) as u32;
+    let xsb = hx & 0x80000000; // sign bit of x
+    let hx = hx & 0x7fffffff; // high word of |x|
+    let mut x = x;
+
+    // filter out huge and non-finite argument
+    if hx >= 0x4043687A {
+        // if |x|>=56*ln2
+        if hx >= 0x40862E42 {
+            // if |x|>=709.78...
+            if hx >= 0x7ff00000 {
+                if ((hx & 0xfffff) | lo(x)) != 0 {
+                    return x + x; // NaN
+                }
+                // exp(+-inf)={inf,-1}
+                return if xsb == 0 { x } else { -1.0 };
+            }
+            if x > O_THRESHOLD {
+                return HUGE * HUGE; // overflow
+            }
+        }
+        if xsb != 0 && x + TINY < 0.0 {
+            // x < -56*ln2, return -1.0 with inexact
+            return TINY - 1.0;
+        }
+    }
+
+    // argument reduction
+    let (k, c);
+    if hx > 0x3fd62e42 {
+        // if  |x| > 0.5 ln2
+        let (hi_part, lo_part);
+        if hx < 0x3FF0A2B2 {
+            // and |x| < 1.5 ln2
+            if xsb == 0 {
+                hi_part = x - LN2_HI;
+                lo_part = LN2_LO;
+                k = 1;
+            } else {
+                hi_part = x + LN2_HI;
+                lo_part = -LN2_LO;
+                k = -1;
+            }
+        } else {
+            k = (INVLN2 * x + if xsb == 0 { 0.5 } else { -0.5 }) as i32;
+            let t = k as f64;
+            hi_part = x - t * LN2_HI; // t*ln2_hi is exact here
+            lo_part = t * LN2_LO;
+        }
+        x = hi_part - lo_part;
+        c = (hi_part - x) - lo_part;
+    } else if hx < 0x3c900000 {
+        // when |x|<2**-54, return x
+        let t = HUGE + x; // return x with inexact flags when x!=0
+        return x - (t - (HUGE + x));
+    } else {
+        k = 0;
+        c = 0.0;
+    }
+
+    // x is now in primary range
+    let hfx = 0.5 * x;
+    let hxs = x * hfx;
+    let r1 = 1.0 + hxs * (Q1 + hxs * (Q2 + hxs * (Q3 + hxs * (Q4 + hxs * Q5))));
+    let t = 3.0 - r1 * hfx;
+    let mut e = hxs * ((r1 - t) / (6.0 - x * t));
+    if k == 0 {
+        return x - (x * e - hxs); // c is 0
+    }
+    e = x * (e - c) - c;
+    e -= hxs;
+    if k == -1 {
+        return 0.5 * (x - e) - 0.5;
+    }
+    if k == 1 {
+        return if x < -0.25 {
+            -2.0 * (e - (x + 0.5))
+        } else {
+            1.0 + 2.0 * (x - e)
+        };
+    }
+    if k <= -2 || k > 56 {
+        // suffice to return exp(x)-1
+        let y = 1.0 - (e - x);
+        let y = with_hi(y, hi(y).wrapping_add(k << 20)); // add k to y's exponent
+        return y - 1.0;
+    }
+    if k < 20 {
+        let t = from_words(0x3ff00000 - (0x200000 >> k), 0); // t=1-2^-k
+        let y = t - (e - x);
+        with_hi(y, hi(y).wrapping_add(k << 20))
+    } else {
+        let t = from_words((0x3ff - k) << 20, 0); // 2^-k
+        let y = x - (e + t);
+        let y = y + 1.0;
+        with_hi(y, hi(y).wrapping_add(k << 20))
+    }
+}
+
+const LG1: f64 = 6.666666666666735130e-01;
+const LG2: f64 = 3.999999999940941908e-01;
+const LG3: f64 = 2.857142874366239149e-01;
+const LG4: f64 = 2.222219843214978396e-01;
+const LG5: f64 = 1.818357216161805012e-01;
+const LG6: f64 = 1.531383769920937332e-01;
+const LG7: f64 = 1.479819860511658591e-01;
+
+pub fn log(x: f64) -> f64 {
+    let mut hx = hi(x);
+    let lx = lo(x);
+    let mut x = x;
+
+    let mut k = 0;
+    if hx < 0x00100000 {
+        // x < 2**-1022
+        if ((hx & 0x7fffffff) as u32 | lx) == 0 {
+            return -TWO54 / 0.0; // log(+-0)=-inf
+        }
+        if hx < 0 {
+            return (x - x) / 0.0; // log(-#) = NaN
+        }
+        k -= 54;
+        x *= TWO54; // subnormal number, scale up x
+        hx = hi(x);
+    }
+    if hx >= 0x7ff00000 {
+        return x + x;
+    }
+    k += (hx >> 20) - 1023;
+    hx &= 0x000fffff;
+    let i = (hx + 0x95f64) & 0x100000;
+    x = with_hi(x, hx | (i ^ 0x3ff00000)); // normalize x or x/2
+    k += i >> 20;
+    let f = x - 1.0;
+    if (0x000fffff & (2 + hx)) < 3 {
+        // |f| < 2**-20
+        if f == 0.0 {
+            if k == 0 {
+                return 0.0;
+            }
+            let dk = k as f64;
+            return dk * LN2_HI + dk * LN2_LO;
+        }
+        let r = f * f * (0.5 - 0.33333333333333333 * f);
+        if k == 0 {
+            return f - r;
+        }
+        let dk = k as f64;
+        return dk * LN2_HI - ((r - dk * LN2_LO) - f);
+    }
+    let s = f / (2.0 + f);
+    let dk = k as f64;
+    let z = s * s;
+    let mut i = hx - 0x6147a;
+    let w = z * z;
+    let j = 0x6b851 - hx;
+    let t1 = w * (LG2 + w * (LG4 + w * LG6));
+    let t2 = z * (LG1 + w * (LG3 + w * (LG5 + w * LG7)));
+    i |= j;
+    let r = t2 + t1;
+    if i > 0 {
+        let hfsq = 0.5 * f * f;
+        if k == 0 {
+            f - (hfsq - s * (hfsq + r))
+        } else {
+            dk * LN2_HI - ((hfsq - (s * (hfsq + r) + dk * LN2_LO)) - f)
+        }
+    } else if k == 0 {
+        f - s * (f - r)
+    } else {
+        dk * LN2_HI - ((s * (f - r) - dk * LN2_LO) - f)
+    }
+}
+
+const IVLN10: f64 = 4.34294481903251816668e-01;
+const LOG10_2HI: f64 = 3.01029995663611771306e-01;
+const LOG10_2LO: f64 = 3.69423907715893078616e-13;
+
+pub fn log10(x: f64) -> f64 {
+    let mut hx = hi(x);
+    let lx = lo(x);
+    let mut x = x;
+
+    let mut k = 0;
+    if hx < 0x00100000 {
+        // x < 2**-1022
+        if ((hx & 0x7fffffff) as u32 | lx) == 0 {
+            return -TWO54 / 0.0; // log(+-0)=-inf
+        }
+        if hx < 0 {
+            return (x - x) / 0.0; // log(-#) = NaN
+        }
+        k -= 54;
+        x *= TWO54; // subnormal number, scale up x
+        hx = hi(x);
+    }
+    if hx >= 0x7ff00000 {
+        return x + x;
+    }
+    k += (hx >> 20) - 1023;
+    let i = ((k as u32 & 0x80000000) >> 31) as i32;
+    hx = (hx & 0x000fffff) | ((0x3ff - i) << 20);
+    let y = (k + i) as f64;
+    x = with_hi(x, hx);
+    let z = y * LOG10_2LO + IVLN10 * log(x);
+    z + y * LOG10_2HI
+}
+
+const LP1: f64 = 6.666666666666735130e-01;
+const LP2: f64 = 3.999999999940941908e-01;
+const LP3: f64 = 2.857142874366239149e-01;
+const LP4: f64 = 2.222219843214978396e-01;
+const LP5: f64 = 1.818357216161805012e-01;
+const LP6: f64 = 1.531383769920937332e-01;
+const LP7: f64 = 1.479819860511658591e-01;
+
+pub fn log1p(x: f64) -> f64 {
+    let hx = hi(x);
+    let ax = hx & 0x7fffffff;
+
+    let mut k = 1;
+    let (mut f, mut hu, mut c) = (0.0, 0, 0.0);
+    if hx < 0x3FDA827A {
+        // x < 0.41422
+        if ax >= 0x3ff00000 {
+            // x <= -1.0
+            if x == -1.0 {
+                return -TWO54 / 0.0; // log1p(-1)=+inf
+            }
+            return (x - x) / (x - x); // log1p(x<-1)=NaN
+        }
+        if ax < 0x3e200000 {
+            // |x| < 2**-29
+            if TWO54 + x > 0.0 && ax < 0x3c900000 {
+                // |x| < 2**-54
+                return x;
+            }
+            return x - x * x * 0.5;
+        }
+        if hx > 0 || hx <= 0xbfd2bec3u32 as i32 {
+            // -0.2929<x<0.41422
+            k = 0;
+            f = x;
+            hu = 1;
+        }
+    }
+    if hx >= 0x7ff00000 {
+        return x + x;
+    }
+    if k != 0 {
+        let mut u;
+        if hx < 0x43400000 {
+            u = 1.0 + x;
+            hu = hi(u);
+            k = (hu >> 20) - 1023;
+            // correction term
+            c = if k > 0 { 1.0 - (u - x) } else { x - (u - 1.0) };
+            c /= u;
+        } else {
+            u = x;
+            hu = hi(u);
+            k = (hu >> 20) - 1023;
+            c = 0.0;
+        }
+        hu &= 0x000fffff;
+        if hu < 0x6a09e {
+            u = with_hi(u, hu | 0x3ff00000); // normalize u
+        } else {
+            k += 1;
+            u = with_hi(u, hu | 0x3fe00000); // normalize u/2
+            hu = (0x00100000 - hu) >> 2;
+        }
+        f = u - 1.0;
+    }
+    let hfsq = 0.5 * f * f;
+    let dk = k as f64;
+    if hu == 0 {
+        // |f| < 2**-20
+        if f == 0.0 {
+            if k == 0 {
+                return 0.0;
+            }
+            c += dk * LN2_LO;
+            return dk * LN2_HI + c;
+        }
+        let r = hfsq * (1.0 - 0.66666666666666666 * f);
+        if k == 0 {
+            return f - r;
+        }
+        return dk * LN2_HI - ((r - (dk * LN2_LO + c)) - f);
+    }
+    let s = f / (2.0 + f);
+    let z = s * s;
+    let r = z * (LP1 + z * (LP2 + z * (LP3 + z * (LP4 + z * (LP5 + z * (LP6 + z * LP7))))));
+    if k == 0 {
+        f - (hfsq - s * (hfsq + r))
+    } else {
+        dk * LN2_HI - ((hfsq - (s * (hfsq + r) + (dk * LN2_LO + c))) - f)
+    }
+}
+
+// =============================================================================
+// HYPERBOLIC FUNCTIONS
+// =============================================================================
+
+const SHUGE: f64 = 1.0e307;
+
+pub fn sinh(x: f64) -> f64 {
+    let jx = hi(x);
+    let ix = jx & 0x7fffffff;
+
+    // x is INF or NaN
+    if ix >= 0x7ff00000 {
+        return x + x;
+    }
+
+    let h = if jx < 0 { -0.5 } else { 0.5 };
+    // |x| in [0,22], return sign(x)*0.5*(E+E/(E+1)))
+    if ix < 0x40360000 {
+        // |x|<22
+        if ix < 0x3e300000 && SHUGE + x > 1.0 {
+            // |x|<2**-28, sinh(tiny) = tiny with inexact
+            return x;
+        }
+        let t = expm1(x.abs());
+        if ix < 0x3ff00000 {
+            return h * (2.0 * t - t * t / (t + 1.0));
+        }
+        return h * (t + t / (t + 1.0));
+    }
+
+    // |x| in [22, log(maxdouble)] return 0.5*exp(|x|)
+    if ix < 0x40862E42 {
+        return h * exp(x.abs());
+    }
+
+    // |x| in [log(maxdouble), overflowthresold]
+    let lx = lo(x);
+    if ix < 0x408633CE || (ix == 0x408633ce && lx <= 0x8fb9f87d) {
+        let w = exp(0.5 * x.abs());
+        let t = h * w;
+        return t * w;
+    }
+
+    // |x| > overflowthresold, sinh(x) overflow
+    x * SHUGE
+}
+
+pub fn cosh(x: f64) -> f64 {
+    let ix = hi(x) & 0x7fffffff;
+
+    // x is INF or NaN
+    if ix >= 0x7ff00000 {
+        return x * x;
+    }
+
+    // |x| in [0,0.5*ln2], return 1+expm1(|x|)^2/(2*exp(|x|))
+    if ix < 0x3fd62e43 {
+        let t = expm1(x.abs());
+        let w = 1.0 + t;
+        if ix < 0x3c800000 {
+            return w; // cosh(tiny) = 1
+        }
+        return 1.0 + (t * t) / (w + w);
+    }
+
+    // |x| in [0.5*ln2,22], return (exp(|x|)+1/exp(|x|)/2;
+    if ix < 0x40360000 {
+        let t = exp(x.abs());
+        return 0.5 * t + 0.5 / t;
+    }
+
+    // |x| in [22, log(maxdouble)] return half*exp(|x|)
+    if ix < 0x40862E42 {
+        return 0.5 * exp(x.abs());
+    }
+
+    // |x| in [log(maxdouble), overflowthresold]
+    let lx = lo(x);
+    if ix < 0x408633CE || (ix == 0x408633ce && lx <= 0x8fb9f87d) {
+        let w = exp(0.5 * x.abs());
+        let t = 0.5 * w;
+        return t * w;
+    }
+
+    // |x| > overflowthresold, cosh(x) overflow
+    HUGE * HUGE
+}
+
+pub fn tanh(x: f64) -> f64 {
+    let jx = hi(x);
+    let ix = jx & 0x7fffffff;
+
+    // x is INF or NaN
+    if ix >= 0x7ff00000 {
+        return if jx >= 0 {
+            1.0 / x + 1.0 // tanh(+-inf)=+-1
+        } else {
+            1.0 / x - 1.0 // tanh(NaN) = NaN
+        };
+    }
+
+    let z = if ix < 0x40360000 {
+        // |x|<22
+        if ix < 0x3c800000 {
+            // |x|<2**-55, tanh(small) = small
+            return x * (1.0 + x);
+        }
+        if ix >= 0x3ff00000 {
+            // |x|>=1
+            let t = expm1(2.0 * x.abs());
+            1.0 - 2.0 / (t + 2.0)
+        } else {
+            let t = expm1(-2.0 * x.abs());
+            -t / (t + 2.0)
+        }
+    } else {
+        // |x| > 22, return +-1
+        1.0 - TINY // raised inexact flag
+    };
+    if jx >= 0 {
+        z
+    } else {
+        -z
+    }
+}
+
+// =============================================================================
+// POWERS AND ROOTS
+// =============================================================================
+
+const B1: i32 = 715094163; // B1 = (682-0.03306235651)*2**20
+const B2: i32 = 696219795; // B2 = (664-0.03306235651)*2**20
+const CBRT_C: f64 = 5.42857142857142815906e-01; // 19/35
+const CBRT_D: f64 = -7.05306122448979611050e-01; // -864/1225
+const CBRT_E: f64 = 1.41428571428571436819e+00; // 99/70
+const CBRT_F: f64 = 1.60714285714285720630e+00; // 45/28
+const CBRT_G: f64 = 3.57142857142857150787e-01; // 5/14
+
+pub fn cbrt(x: f64) -> f64 {
+    let hx = hi(x);
+    let sign = hx & 0x80000000u32 as i32; // sign= sign(x)
+    let hx = hx ^ sign;
+    if hx >= 0x7ff00000 {
+        return x + x; // cbrt(NaN,INF) is itself
+    }
+    if (hx as u32 | lo(x)) == 0 {
+        return x; // cbrt(0) is itself
+    }
+    let x = with_hi(x, hx); // x <- |x|
+
+    // rough cbrt to 5 bits
+    let mut t = if hx < 0x00100000 {
+        // subnormal number
+        let t = from_words(0x43500000, 0) * x; // set t= 2**54
+        from_words(hi(t) / 3 + B2, 0)
+    } else {
+        from_words(hx / 3 + B1, 0)
+    };
+
+    // new cbrt to 23 bits, may be implemented in single precision
+    let r = t * t / x;
+    let s = CBRT_C + r * t;
+    t *= CBRT_G + CBRT_F / (s + CBRT_E + CBRT_D / s);
+
+    // chopped to 20 bits and make it larger than cbrt(x)
+    t = from_words(hi(t) + 1, 0);
+
+    // one step newton iteration to 53 bits with error less than 0.667 ulps
+    let s = t * t; // t*t is exact
+    let r = x / s;
+    let w = t + t;
+    let r = (r - t) / (w + r); // r-s is exact
+    t += t * r;
+
+    // restore the sign bit
+    with_hi(t, hi(t) | sign)
+}
+
+pub fn hypot(x: f64, y: f64) -> f64 {
+    let mut ha = hi(x) & 0x7fffffff; // high word of  x
+    let mut hb = hi(y) & 0x7fffffff; // high word of  y
+    let (mut a, mut b) = if hb > ha {
+        std::mem::swap(&mut ha, &mut hb);
+        (y, x)
+    } else {
+        (x, y)
+    };
+    a = with_hi(a, ha); // a <- |a|
+    b = with_hi(b, hb); // b <- |b|
+    if ha - hb > 0x3c00000 {
+        return a + b; // x/y > 2**60
+    }
+    let mut k = 0;
+    if ha > 0x5f300000 {
+        // a>2**500
+        if ha >= 0x7ff00000 {
+            // Inf or NaN
+            let mut w = a + b; // for sNaN
+            if ((ha & 0xfffff) as u32 | lo(a)) == 0 {
+                w = a;
+            }
+            if ((hb ^ 0x7ff00000) as u32 | lo(b)) == 0 {
+                w = b;
+            }
+            return w;
+        }
+        // scale a and b by 2**-600
+        ha -= 0x25800000;
+        hb -= 0x25800000;
+        k += 600;
+        a = with_hi(a, ha);
+        b = with_hi(b, hb);
+    }
+    if hb < 0x20b00000 {
+        // b < 2**-500
+        if hb <= 0x000fffff {
+            // subnormal b or 0
+            if (hb as u32 | lo(b)) == 0 {
+                return a;
+            }
+            let t1 = from_words(0x7fd00000, 0); // t1=2^1022
+            b *= t1;
+            a *= t1;
+            k -= 1022;
+        } else {
+            // scale a and b by 2^600
+            ha += 0x25800000; // a *= 2^600
+            hb += 0x25800000; // b *= 2^600
+            k -= 600;
+            a = with_hi(a, ha);
+            b = with_hi(b, hb);
+        }
+    }
+
+    // medium size a and b
+    let mut w = a - b;
+    if w > b {
+        let t1 = from_words(ha, 0);
+        let t2 = a - t1;
+        w = (t1 * t1 - (b * (-b) - t2 * (a + t1))).sqrt();
+    } else {
+        a += a;
+        let y1 = from_words(hb, 0);
+        let y2 = b - y1;
+        let t1 = from_words(ha + 0x00100000, 0);
+        let t2 = a - t1;
+        w = (t1 * y1 - (w * (-w) - (t1 * y2 + t2 * b))).sqrt();
+    }
+    if k != 0 {
+        from_words(0x3ff00000 + (k << 20), 0) * w
+    } else {
+        w
+    }
+}
+
+const BP: [f64; 2] = [1.0, 1.5];
+const DP_H: [f64; 2] = [0.0, 5.84962487220764160156e-01];
+const DP_L: [f64; 2] = [0.0, 1.35003920212974897128e-08];
+const TWO53: f64 = 9007199254740992.0;
+// poly coefs for (3/2)*(log(x)-2s-2/3*s**3
+const L1: f64 = 5.99999999999994648725e-01;
+const L2: f64 = 4.28571428578550184252e-01;
+const L3: f64 = 3.33333329818377432918e-01;
+const L4: f64 = 2.72728123808534006489e-01;
+const L5: f64 = 2.30660745775561754067e-01;
+const L6: f64 = 2.06975017800338417784e-01;
+const LN2: f64 = 6.93147180559945286227e-01; // lg2
+const LN2_TOP: f64 = 6.93147182464599609375e-01; // lg2_h
+const LN2_TAIL: f64 = -1.90465429995776804525e-09; // lg2_l
+const OVT: f64 = 8.0085662595372944372e-17; // -(1024-log2(ovfl+.5ulp))
+const CP: f64 = 9.61796693925975554329e-01; // 2/(3ln2)
+const CP_H: f64 = 9.61796700954437255859e-01; // (float)cp
+const CP_L: f64 = -7.02846165095275826516e-09; // tail of cp_h
+const IVLN2: f64 = 1.44269504088896338700e+00; // 1/ln2
+const IVLN2_H: f64 = 1.44269502162933349609e+00; // 24b 1/ln2
+const IVLN2_L: f64 = 1.92596299112661746887e-08; // 1/ln2 tail
+
+pub fn pow(x: f64, y: f64) -> f64 {
+    let (hx, lx) = (hi(x), lo(x));
+    let (hy, ly) = (hi(y), lo(y));
+    let mut ix = hx & 0x7fffffff;
+    let iy = hy & 0x7fffffff;
+
+    // y==zero: x**0 = 1
+    if (iy as u32 | ly) == 0 {
+        return 1.0;
+    }
+
+    // +-NaN return x+y
+    if ix > 0x7ff00000
+        || (ix == 0x7ff00000 && lx != 0)
+        || iy > 0x7ff00000
+        || (iy == 0x7ff00000 && ly != 0)
+    {
+        return x + y;
+    }
+
+    // determine if y is an odd int when x < 0
+    // yisint = 0 ... y is not an integer
+    // yisint = 1 ... y is an odd int
+    // yisint = 2 ... y is an even int
+    let mut yisint = 0;
+    if hx < 0 {
+        if iy >= 0x43400000 {
+            yisint = 2; // even integer y
+        } else if iy >= 0x3ff00000 {
+            let k = (iy >> 20) - 0x3ff; // exponent
+            if k > 20 {
+                let j = ly >> (52 - k);
+                if (j << (52 - k)) == ly {
+                    yisint = 2 - (j & 1) as i32;
+                }
+            } else if ly == 0 {
+                let j = iy >> (20 - k);
+                if (j << (20 - k)) == iy {
+                    yisint = 2 - (j & 1);
+                }
+            }
+        }
+    }
+
+    // special value of y
+    if ly == 0 {
+        if iy == 0x7ff00000 {
+            // y is +-inf
+            return if ((ix - 0x3ff00000) as u32 | lx) == 0 {
+                y - y // inf**+-1 is NaN
+            } else if ix >= 0x3ff00000 {
+                // (|x|>1)**+-inf = inf,0
+                if hy >= 0 {
+                    y
+                } else {
+                    0.0
+                }
+            } else if hy < 0 {
+                // (|x|<1)**-,+inf = inf,0
+                -y
+            } else {
+                0.0
+            };
+        }
+        if iy == 0x3ff00000 {
+            // y is  +-1
+            return if hy < 0 { 1.0 / x } else { x };
+        }
+        if hy == 0x40000000 {
+            return x * x; // y is  2
+        }
+        if hy == 0x3fe00000 && hx >= 0 {
+            // y is  0.5, x >= +0
+            return x.sqrt();
+        }
+    }
+
+    let mut ax = x.abs();
+    // special value of x
+    if lx == 0 && (ix == 0x7ff00000 || ix == 0 || ix == 0x3ff00000) {
+        let mut z = ax; // x is +-0,+-inf,+-1
+        if hy < 0 {
+            z = 1.0 / z; // z = (1/|x|)
+        }
+        if hx < 0 {
+            if ((ix - 0x3ff00000) | yisint) == 0 {
+                z = (z - z) / (z - z); // (-1)**non-int is NaN
+            } else if yisint == 1 {
+                z = -z; // (x<0)**odd = -(|x|**odd)
+            }
+        }
+        return z;
+    }
+
+    let mut n = (hx >> 31) + 1;
+
+    // (x<0)**(non-int) is NaN
+    if (n | yisint) == 0 {
+        return (x - x) / (x - x);
+    }
+
+    let mut s = 1.0; // s (sign of result -ve**odd) = -1 else = 1
+    if (n | (yisint - 1)) == 0 {
+        s = -1.0; // (-ve)**(odd int)
+    }
+
+    let (t1, t2);
+    if iy > 0x41e00000 {
+        // |y| is huge: if |y| > 2**31
+        if iy > 0x43f00000 {
+            // if |y| > 2**64, must o/uflow
+            if ix <= 0x3fefffff {
+                return if hy < 0 { HUGE * HUGE } else { TINY * TINY };
+            }
+            if ix >= 0x3ff00000 {
+                return if hy > 0 { HUGE * HUGE } else { TINY * TINY };
+            }
+        }
+        // over/underflow if x is not close to one
+        if ix < 0x3fefffff {
+            return if hy < 0 {
+                s * HUGE * HUGE
+            } else {
+                s * TINY * TINY
+            };
+        }
+        if ix > 0x3ff00000 {
+            return if hy > 0 {
+                s * HUGE * HUGE
+            } else {
+                s * TINY * TINY
+            };
+        }
+        // now |1-x| is tiny <= 2**-20, suffice to compute
+        // log(x) by x-x^2/2+x^3/3-x^4/4
+        let t = ax - 1.0; // t has 20 trailing zeros
+        let w = (t * t) * (0.5 - t * (0.3333333333333333333333 - t * 0.25));
+        let u = IVLN2_H * t; // ivln2_h has 21 sig. bits
+        let v = t * IVLN2_L - w * IVLN2;
+        t1 = with_lo(u + v, 0);
+        t2 = v - (t1 - u);
+    } else {
+        n = 0;
+        // take care subnormal number
+        if ix < 0x00100000 {
+            ax *= TWO53;
+            n -= 53;
+            ix = hi(ax);
+        }
+        n += (ix >> 20) - 0x3ff;
+        let j = ix & 0x000fffff;
+        // determine interval
+        ix = j | 0x3ff00000; // normalize ix
+        let k = if j <= 0x3988E {
+            0 // |x|<sqrt(3/2)
+        } else if j < 0xBB67A {
+            1 // |x|<sqrt(3)
+        } else {
+            n += 1;
+            ix -= 0x00100000;
+            0
+        };
+        ax = with_hi(ax, ix);
+
+        // compute ss = s_h+s_l = (x-1)/(x+1) or (x-1.5)/(x+1.5)
+        let u = ax - BP[k]; // bp[0]=1.0, bp[1]=1.5
+        let v = 1.0 / (ax + BP[k]);
+        let ss = u * v;
+        let s_h = with_lo(ss, 0);
+        // t_h=ax+bp[k] High
+        let t_h = from_words(
+            ((ix >> 1) | 0x20000000) + 0x00080000 + ((k as i32) << 18),
+            0,
+        );
+        let t_l = ax - (t_h - BP[k]);
+        let s_l = v * ((u - s_h * t_h) - s_h * t_l);
+        // compute log(ax)
+        let s2 = ss * ss;
+        let mut r = s2 * s2 * (L1 + s2 * (L2 + s2 * (L3 + s2 * (L4 + s2 * (L5 + s2 * L6)))));
+        r += s_l * (s_h + ss);
+        let s2 = s_h * s_h;
+        let t_h = with_lo(3.0 + s2 + r, 0);
+        let t_l = r - ((t_h - 3.0) - s2);
+        // u+v = ss*(1+...)
+        let u = s_h * t_h;
+        let v = s_l * t_h + t_l * ss;
+        // 2/(3log2)*(ss+...)
+        let p_h = with_lo(u + v, 0);
+        let p_l = v - (p_h - u);
+        let z_h = CP_H * p_h; // cp_h+cp_l = 2/(3*log2)
+        let z_l = CP_L * p_h + p_l * CP + DP_L[k];
+        // log2(ax) = (ss+..)*2/(3*log2) = n + dp_h + z_h + z_l
+        let t = n as f64;
+        t1 = with_lo(((z_h + z_l) + DP_H[k]) + t, 0);
+        t2 = z_l - (((t1 - t) - DP_H[k]) - z_h);
+    }
+
+    // split up y into y1+y2 and compute (y1+y2)*(t1+t2)
+    let y1 = with_lo(y, 0);
+    let p_l = (y - y1) * t1 + y * t2;
+    let mut p_h = y1 * t1;
+    let z = p_l + p_h;
+    let j = hi(z);
+    let i = lo(z);
+    if j >= 0x40900000 {
+        // z >= 1024
+        if ((j - 0x40900000) as u32 | i) != 0 || p_l + OVT > z - p_h {
+            return s * HUGE * HUGE; // overflow
+        }
+    } else if (j & 0x7fffffff) >= 0x4090cc00 {
+        // z <= -1075
+        if ((j as u32).wrapping_sub(0xc090cc00) | i) != 0 || p_l <= z - p_h {
+            return s * TINY * TINY; // underflow
+        }
+    }
+
+    // compute 2**(p_h+p_l)
+    let i = j & 0x7fffffff;
+    let mut k = (i >> 20) - 0x3ff;
+    let mut n = 0;
+    if i > 0x3fe00000 {
+        // if |z| > 0.5, set n = [z+0.5]
+        n = j + (0x00100000 >> (k + 1));
+        k = ((n & 0x7fffffff) >> 20) - 0x3ff; // new k for n
+        let t = from_words(n & !(0x000fffff >> k), 0);
+        n = ((n & 0x000fffff) | 0x00100000) >> (20 - k);
+        if j < 0 {
+            n = -n;
+        }
+        p_h -= t;
+    }
+    let t = with_lo(p_l + p_h, 0);
+    let u = t * LN2_TOP;
+    let v = (p_l - (t - p_h)) * LN2 + t * LN2_TAIL;
+    let mut z = u + v;
+    let w = v - (z - u);
+    let t = z * z;
+    let t1 = z - t * (P1 + t * (P2 + t * (P3 + t * (P4 + t * P5))));
+    let r = (z * t1) / (t1 - 2.0) - (w + z * w);
+    z = 1.0 - (r - z);
+    let j = hi(z).wrapping_add(n << 20);
+    if (j >> 20) <= 0 {
+        z = scalbn(z, n); // subnormal output
+    } else {
+        z = with_hi(z, j);
+    }
+    s * z
+}
@@ -0,0 +1,436 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::class::ClassAccessFlags;
+use crate::vm::heap::{ArrayData, NativeData, StandardStream};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::lang::{exception, object_init, object_to_string, primitive_to_string};
+use crate::vm::natives::{byte_array, int, new_byte_array, non_null, BuiltinClass};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.io`.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        BuiltinClass::interface("java/io/Serializable"),
+        BuiltinClass::interface("java/io/Closeable").abstract_method("close", "()V"),
+        BuiltinClass::interface("java/io/Flushable").abstract_method("flush", "()V"),
+        input_stream(),
+        output_stream(),
+        print_stream(),
+        exception("java/io/IOException", "java/lang/Exception"),
+        exception(
+            "java/io/UnsupportedEncodingException",
+            "java/io/IOException",
+        ),
+    ]
+}
+
+// =============================================================================
+// PIPES
+// =============================================================================
+
+/// The host end of a stream of bytes behind an `InputStream` or an
+/// `OutputStream`, like a pipe to a child process.
+pub enum Pipe {
+    Input(Box<dyn Read + Send>),
+    Output(Box<dyn Write + Send>),
+}
+
+impl fmt::Debug for Pipe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pipe::Input(_) => write!(f, "Pipe::Input"),
+            Pipe::Output(_) => write!(f, "Pipe::Output"),
+        }
+    }
+}
+
+/// Creates an `InputStream` or an `OutputStream` reading or writing the
+/// pipe.
+pub(crate) fn new_pipe_stream(vm: &mut Vm, pipe: Pipe) -> Result<ObjectRef, Unwind> {
+    let class = match pipe {
+        Pipe::Input(_) => "java/io/InputStream",
+        Pipe::Output(_) => "java/io/OutputStream",
+    };
+    let class = vm.load_class(LoaderId::BOOTSTRAP, class)?;
+    let object = vm.instantiate(class)?;
+    vm.heap.get_mut(object).native = NativeData::Pipe(Arc::new(Mutex::new(pipe)));
+    Ok(object)
+}
+
+/// The pipe of a stream, `None` for a closed stream or one implemented in
+/// Java.
+fn pipe(vm: &mut Vm, this: Value) -> Result<Option<Arc<Mutex<Pipe>>>, Unwind> {
+    let this = non_null(vm, this)?;
+    match &vm.heap.get(this).native {
+        NativeData::Pipe(pipe) => Ok(Some(pipe.clone())),
+        _ => Ok(None),
+    }
+}
+
+/// Throws an `IOException` for the error of the host.
+pub(crate) fn io_exception(vm: &mut Vm, error: io::Error) -> Unwind {
+    vm.throw_new("java/io/IOException", Some(error.to_string()))
+}
+
+fn stream_closed(vm: &mut Vm) -> Unwind {
+    vm.throw_new("java/io/IOException", Some("Stream closed".to_string()))
+}
+
+/// The range of a `(byte[] b, int off, int len)` argument triple starting at
+/// `args[index]`, or all of `b` without the offset and length, throwing
+/// `IndexOutOfBoundsException` unless it is within the array.
+pub(crate) fn byte_range(
+    vm: &mut Vm,
+    args: &[Value],
+    index: usize,
+) -> Result<(ObjectRef, usize, usize), Unwind> {
+    let array = non_null(vm, args[index])?;
+    let size = vm.heap.get(array).array().map_or(0, ArrayData::len);
+    let (offset, length) = match args.get(index + 1..index + 3) {
+        Some([offset, length]) => (int(*offset), int(*length)),
+        _ => return Ok((array, 0, size)),
+    };
+    if offset < 0 || length < 0 || offset as usize + length as usize > size {
+        let message = format!(
+            "Range [{}, {} + {}) out of bounds for length {}",
+            offset, offset, length, size
+        );
+        return Err(vm.throw_new("java/lang/IndexOutOfBoundsException", Some(message)));
+    }
+    Ok((array, offset as usize, length as usize))
+}
+
+fn set_byte(vm: &mut Vm, array: ObjectRef, index: usize, byte: u8) {
+    if let Some(ArrayData::Byte(values)) = vm.heap.get_mut(array).array_mut() {
+        values[index] = byte as i8;
+    }
+}
+
+// =============================================================================
+// INPUT STREAM
+// =============================================================================
+
+/// `java.io.InputStream`, reading a pipe of the host when created by the VM.
+/// The bulk reads of subclasses defined in Java go through their `read()`,
+/// like the JDK's.
+fn input_stream() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/io/InputStream", "java/lang/Object")
+        .implements("java/io/Closeable")
+        .method("<init>", "()V", object_init)
+        .method("read", "()I", input_read)
+        .method("read", "([B)I", input_read_bytes)
+        .method("read", "([BII)I", input_read_bytes)
+        .method("readAllBytes", "()[B", input_read_all_bytes)
+        .method("available", "()I", input_available)
+        .method("close", "()V", stream_close);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+/// Reads from the pipe into the buffer, returning 0 at the end of the
+/// stream.
+fn read_pipe(vm: &mut Vm, pipe: &Mutex<Pipe>, buffer: &mut [u8]) -> Result<usize, Unwind> {
+    let result = match &mut *pipe.lock().unwrap() {
+        Pipe::Input(input) => input.read(buffer),
+        Pipe::Output(_) => Ok(0),
+    };
+    result.map_err(|error| io_exception(vm, error))
+}
+
+fn input_read(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let pipe = match pipe(vm, args[0])? {
+        Some(pipe) => pipe,
+        None => return Err(stream_closed(vm)),
+    };
+    let mut byte = [0];
+    match read_pipe(vm, &pipe, &mut byte)? {
+        0 => Ok(Some(Value::Int(-1))),
+        _ => Ok(Some(Value::Int(byte[0] as i32))),
+    }
+}
+
+/// Reads up to the length of the range, blocking until at least one byte is
+/// available, and returns the number of bytes read or -1 at the end of the
+/// stream.
+fn input_read_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (array, offset, length) = byte_range(vm, args, 1)?;
+    if length == 0 {
+        return Ok(Some(Value::Int(0)));
+    }
+
+    let this = non_null(vm, args[0])?;
+    let pipe = match pipe(vm, args[0])? {
+        Some(pipe) => pipe,
+        None => {
+            let mut read = 0;
+            while read < length {
+                match vm.invoke_virtual(this, "read", "()I", &[])?.map(int) {
+                    Some(byte) if byte >= 0 => set_byte(vm, array, offset + read, byte as u8),
+                    _ => break,
+                }
+                read += 1;
+            }
+            return Ok(Some(Value::Int(if read == 0 { -1 } else { read as i32 })));
+        }
+    };
+
+    let mut buffer = vec![0; length];
+    let read = read_pipe(vm, &pipe, &mut buffer)?;
+    if read == 0 {
+        return Ok(Some(Value::Int(-1)));
+    }
+    for (index, byte) in buffer[..read].iter().enumerate() {
+        set_byte(vm, array, offset + index, *byte);
+    }
+    Ok(Some(Value::Int(read as i32)))
+}
+
+fn input_read_all_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let mut bytes = Vec::new();
+    match pipe(vm, args[0])? {
+        Some(pipe) => {
+            let result = match &mut *pipe.lock().unwrap() {
+                Pipe::Input(input) => input.read_to_end(&mut bytes),
+                Pipe::Output(_) => Ok(0),
+            };
+            result.map_err(|error| io_exception(vm, error))?;
+        }
+        None => {
+            while let Some(byte) = vm.invoke_virtual(this, "read", "()I", &[])?.map(int) {
+                if byte < 0 {
+                    break;
+                }
+                bytes.push(byte as u8);
+            }
+        }
+    }
+    new_byte_array(vm, bytes)
+}
+
+/// Always 0, as pipes cannot tell without blocking.
+fn input_available(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(0)))
+}
+
+/// Closes the pipe of the stream, if any.
+fn stream_close(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if let NativeData::Pipe(_) = vm.heap.get(this).native {
+        vm.heap.get_mut(this).native = NativeData::None;
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// OUTPUT STREAM
+// =============================================================================
+
+/// `java.io.OutputStream`, writing a pipe of the host when created by the
+/// VM. The bulk writes of subclasses defined in Java go through their
+/// `write(int)`, like the JDK's.
+fn output_stream() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/io/OutputStream", "java/lang/Object")
+        .implements("java/io/Closeable")
+        .implements("java/io/Flushable")
+        .method("<init>", "()V", object_init)
+        .method("write", "(I)V", output_write)
+        .method("write", "([B)V", output_write_bytes)
+        .method("write", "([BII)V", output_write_bytes)
+        .method("flush", "()V", output_flush)
+        .method("close", "()V", stream_close);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn write_pipe(vm: &mut Vm, pipe: &Mutex<Pipe>, bytes: &[u8]) -> Result<(), Unwind> {
+    let result = match &mut *pipe.lock().unwrap() {
+        Pipe::Output(output) => output.write_all(bytes),
+        Pipe::Input(_) => Ok(()),
+    };
+    result.map_err(|error| io_exception(vm, error))
+}
+
+fn output_write(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match pipe(vm, args[0])? {
+        Some(pipe) => write_pipe(vm, &pipe, &[int(args[1]) as u8])?,
+        None => return Err(stream_closed(vm)),
+    }
+    Ok(None)
+}
+
+fn output_write_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let (array, offset, length) = byte_range(vm, args, 1)?;
+    let bytes = byte_array(vm, Value::Reference(Some(array)))?;
+    let bytes = &bytes[offset..offset + length];
+    match pipe(vm, args[0])? {
+        Some(pipe) => write_pipe(vm, &pipe, bytes)?,
+        None => {
+            for byte in bytes {
+                vm.invoke_virtual(this, "write", "(I)V", &[Value::Int(*byte as i8 as i32)])?;
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn output_flush(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    if let Some(pipe) = pipe(vm, args[0])? {
+        let result = match &mut *pipe.lock().unwrap() {
+            Pipe::Output(output) => output.flush(),
+            Pipe::Input(_) => Ok(()),
+        };
+        result.map_err(|error| io_exception(vm, error))?;
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// PRINT STREAM
+// =============================================================================
+
+fn print_stream() -> BuiltinClass {
+    BuiltinClass::new("java/io/PrintStream", "java/lang/Object")
+        .method("print", "(Z)V", print_boolean)
+        .method("print", "(C)V", print_char)
+        .method("print", "(I)V", print_primitive)
+        .method("print", "(J)V", print_primitive)
+        .method("print", "(F)V", print_primitive)
+        .method("print", "(D)V", print_primitive)
+        .method("print", "([C)V", print_chars)
+        .method("print", "(Ljava/lang/String;)V", print_object)
+        .method("print", "(Ljava/lang/Object;)V", print_object)
+        .method("println", "()V", println)
+        .method("println", "(Z)V", println_boolean)
+        .method("println", "(C)V", println_char)
+        .method("println", "(I)V", println_primitive)
+        .method("println", "(J)V", println_primitive)
+        .method("println", "(F)V", println_primitive)
+        .method("println", "(D)V", println_primitive)
+        .method("println", "([C)V", println_chars)
+        .method("println", "(Ljava/lang/String;)V", println_object)
+        .method("println", "(Ljava/lang/Object;)V", println_object)
+        .method("write", "(I)V", write_byte)
+        .method("flush", "()V", flush)
+}
+
+/// Creates a `PrintStream` writing to one of the VM's standard streams.
+pub(crate) fn new_print_stream(vm: &mut Vm, stream: StandardStream) -> Result<ObjectRef, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/io/PrintStream")?;
+    let object = vm.instantiate(class)?;
+    vm.heap.get_mut(object).native = NativeData::Stream(stream);
+    Ok(object)
+}
+
+fn write(vm: &mut Vm, this: Value, bytes: &[u8]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, this)?;
+    let stream = match vm.heap.get(this).native {
+        NativeData::Stream(stream) => stream,
+        _ => {
+            return Err(Unwind::Error(VmError::Internal(format!(
+                "{} is not a standard stream",
+                this
+            ))))
+        }
+    };
+
+    vm.write_stream(stream, bytes)
+        .map_err(|error| Unwind::Error(error.into()))?;
+    Ok(None)
+}
+
+fn boolean_string(value: Value) -> &'static str {
+    if int(value) != 0 {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+fn char_string(value: Value) -> String {
+    String::from_utf16_lossy(&[int(value) as u16])
+}
+
+fn chars_string(vm: &mut Vm, value: Value) -> Result<String, Unwind> {
+    let array = non_null(vm, value)?;
+    match vm.heap.get(array).array() {
+        Some(ArrayData::Char(chars)) => Ok(String::from_utf16_lossy(chars)),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a char array",
+            array
+        )))),
+    }
+}
+
+fn print_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[0], boolean_string(args[1]).as_bytes())
+}
+
+fn print_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[0], char_string(args[1]).as_bytes())
+}
+
+fn print_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[0], primitive_to_string(args[1]).as_bytes())
+}
+
+fn print_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = chars_string(vm, args[1])?;
+    write(vm, args[0], string.as_bytes())
+}
+
+fn print_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = object_to_string(vm, args[1])?;
+    write(vm, args[0], string.as_bytes())
+}
+
+fn println(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[0], b"\n")
+}
+
+fn println_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(
+        vm,
+        args[0],
+        format!("{}\n", boolean_string(args[1])).as_bytes(),
+    )
+}
+
+fn println_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(
+        vm,
+        args[0],
+        format!("{}\n", char_string(args[1])).as_bytes(),
+    )
+}
+
+fn println_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(
+        vm,
+        args[0],
+        format!("{}\n", primitive_to_string(args[1])).as_bytes(),
+    )
+}
+
+fn println_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = chars_string(vm, args[1])?;
+    write(vm, args[0], format!("{}\n", string).as_bytes())
+}
+
+fn println_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = object_to_string(vm, args[1])?;
+    write(vm, args[0], format!("{}\n", string).as_bytes())
+}
+
+fn write_byte(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    write(vm, args[0], &[int(args[1]) as u8])
+}
+
+fn flush(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    vm.flush().map_err(|error| Unwind::Error(error.into()))?;
+    Ok(None)
+}
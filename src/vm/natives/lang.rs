@@ -0,0 +1,2878 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::class::descriptor::FieldType;
+use crate::class::{ClassAccessFlags, FieldAccessFlags};
+use crate::vm::heap::{ArrayData, HeapObject, NativeData, ObjectData, StandardStream};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::charset::{encoding, named_encoding, Encoding};
+use crate::vm::natives::io::{new_pipe_stream, Pipe};
+use crate::vm::natives::net::new_url;
+use crate::vm::natives::process::{runtime_exec, runtime_exec_array};
+use crate::vm::natives::reflect::{
+    class_get_declared_constructor, class_get_declared_constructors, class_get_declared_field,
+    class_get_declared_fields, class_get_declared_method, class_get_declared_methods,
+    class_new_instance,
+};
+use crate::vm::natives::util::new_array_iterator;
+use crate::vm::natives::{byte_array, int, long, new_byte_array, non_null, BuiltinClass, NativeFn};
+use crate::vm::runtime::{ClassId, ClassKind};
+use crate::vm::thread::ThreadStatus;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.lang`.
+pub fn classes() -> Vec<BuiltinClass> {
+    let mut classes = vec![
+        object(),
+        class(),
+        class_loader(),
+        enumeration(),
+        number(),
+        string(),
+        string_builder(),
+        system(),
+        runtime(),
+        thread(),
+        BuiltinClass::interface("java/lang/Thread$UncaughtExceptionHandler").abstract_method(
+            "uncaughtException",
+            "(Ljava/lang/Thread;Ljava/lang/Throwable;)V",
+        ),
+        thread_local(),
+        supplied_thread_local(),
+        inheritable_thread_local(),
+        class_value(),
+        throwable(),
+        BuiltinClass::interface("java/lang/Cloneable"),
+        BuiltinClass::interface("java/lang/Runnable").abstract_method("run", "()V"),
+        BuiltinClass::interface("java/lang/Comparable")
+            .abstract_method("compareTo", "(Ljava/lang/Object;)I"),
+        BuiltinClass::interface("java/lang/CharSequence")
+            .abstract_method("length", "()I")
+            .abstract_method("charAt", "(I)C")
+            .abstract_method("toString", "()Ljava/lang/String;"),
+        // Of `java.util.function`, for `ThreadLocal.withInitial`
+        BuiltinClass::interface("java/util/function/Supplier")
+            .abstract_method("get", "()Ljava/lang/Object;"),
+    ];
+
+    for (name, super_class) in EXCEPTIONS {
+        classes.push(exception(name, super_class));
+    }
+    classes.push(
+        exception(
+            "java/lang/ExceptionInInitializerError",
+            "java/lang/LinkageError",
+        )
+        .method(
+            "getException",
+            "()Ljava/lang/Throwable;",
+            throwable_get_cause,
+        ),
+    );
+    classes.push(assertion_error());
+
+    classes
+}
+
+/// Natives of JDK classes which are not built into the VM, by class, name
+/// and descriptor.
+pub fn natives() -> Vec<(&'static str, &'static str, &'static str, NativeFn)> {
+    vec![
+        (
+            "java/lang/Float",
+            "floatToRawIntBits",
+            "(F)I",
+            float_to_raw_int_bits,
+        ),
+        (
+            "java/lang/Float",
+            "intBitsToFloat",
+            "(I)F",
+            int_bits_to_float,
+        ),
+        (
+            "java/lang/Double",
+            "doubleToRawLongBits",
+            "(D)J",
+            double_to_raw_long_bits,
+        ),
+        (
+            "java/lang/Double",
+            "longBitsToDouble",
+            "(J)D",
+            long_bits_to_double,
+        ),
+    ]
+}
+
+// =============================================================================
+// OBJECT
+// =============================================================================
+
+fn object() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/Object", "")
+        .method("<init>", "()V", object_init)
+        .method("equals", "(Ljava/lang/Object;)Z", object_equals)
+        .method("hashCode", "()I", object_hash_code)
+        .method("getClass", "()Ljava/lang/Class;", object_get_class)
+        .method("clone", "()Ljava/lang/Object;", object_clone)
+        .method("toString", "()Ljava/lang/String;", object_to_string_native)
+        .method("wait", "()V", object_wait)
+        .method("wait", "(J)V", object_wait)
+        .method("wait", "(JI)V", object_wait)
+        .method("notify", "()V", object_notify)
+        .method("notifyAll", "()V", object_notify);
+    class.super_class = None;
+    class
+}
+
+pub(crate) fn object_init(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(None)
+}
+
+/// Throws `IllegalMonitorStateException` unless a frame of the thread entered
+/// the monitor of the object.
+fn check_monitor_owner(vm: &mut Vm, object: ObjectRef) -> Result<(), Unwind> {
+    let owner = vm
+        .thread
+        .frames
+        .iter()
+        .any(|frame| frame.monitors.contains(&object));
+    if owner {
+        return Ok(());
+    }
+
+    Err(vm.throw_new(
+        "java/lang/IllegalMonitorStateException",
+        Some("current thread is not owner".to_string()),
+    ))
+}
+
+fn object_wait(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let timeout = timeout(vm, &args[1..])?;
+    check_monitor_owner(vm, this)?;
+    block(vm, timeout, None)?;
+    Ok(None)
+}
+
+/// With a single thread, nothing waits to be notified.
+fn object_notify(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    check_monitor_owner(vm, this)?;
+    Ok(None)
+}
+
+fn object_equals(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int((args[0] == args[1]) as i32)))
+}
+
+fn object_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Int(vm.heap.identity_hash(this))))
+}
+
+fn object_get_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let mirror = vm.mirror(vm.class_of(this))?;
+    Ok(Some(Value::Reference(Some(mirror))))
+}
+
+/// A shallow copy of arrays and of instances of `Cloneable` classes, with
+/// an identity of its own.
+fn object_clone(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let object = vm.heap.get(this);
+    // Arrays are always cloneable, copying only their elements
+    let copy = match object.array() {
+        Some(array) => HeapObject {
+            class: object.class,
+            data: ObjectData::Array(array.clone()),
+            native: NativeData::None,
+            identity_hash: None,
+        },
+        None => {
+            let class = object.class;
+            let cloneable = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Cloneable")?;
+            if !vm.is_assignable(class, cloneable) {
+                let message = vm.class(class).java_name();
+                return Err(vm.throw_new("java/lang/CloneNotSupportedException", Some(message)));
+            }
+            HeapObject {
+                identity_hash: None,
+                ..vm.heap.get(this).clone()
+            }
+        }
+    };
+    Ok(Some(Value::Reference(Some(vm.allocate(copy)))))
+}
+
+/// `getClass().getName() + "@" + Integer.toHexString(hashCode())`
+fn object_to_string_native(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let hash = match vm.invoke_virtual(this, "hashCode", "()I", &[])? {
+        Some(Value::Int(hash)) => hash,
+        value => panic!("Expected an int hash code, got {:?}", value),
+    };
+    let name = vm.class(vm.class_of(this)).java_name();
+    new_string(
+        vm,
+        format!("{}@{:x}", name, hash as u32)
+            .encode_utf16()
+            .collect(),
+    )
+}
+
+// =============================================================================
+// CLASS
+// =============================================================================
+
+fn class() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/Class", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .method("getName", "()Ljava/lang/String;", class_get_name)
+        .method("toString", "()Ljava/lang/String;", class_to_string)
+        .method("isInterface", "()Z", class_is_interface)
+        .method("isArray", "()Z", class_is_array)
+        .method("isPrimitive", "()Z", class_is_primitive)
+        .method("isEnum", "()Z", class_is_enum)
+        .method(
+            "getEnumConstants",
+            "()[Ljava/lang/Object;",
+            class_get_enum_constants,
+        )
+        .method("getModifiers", "()I", class_get_modifiers)
+        .method(
+            "desiredAssertionStatus",
+            "()Z",
+            class_desired_assertion_status,
+        )
+        .method("getSuperclass", "()Ljava/lang/Class;", class_get_superclass)
+        .method(
+            "getInterfaces",
+            "()[Ljava/lang/Class;",
+            class_get_interfaces,
+        )
+        .method(
+            "getDeclaredMethods",
+            "()[Ljava/lang/reflect/Method;",
+            class_get_declared_methods,
+        )
+        .method(
+            "getDeclaredMethod",
+            "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;",
+            class_get_declared_method,
+        )
+        .method(
+            "getDeclaredConstructors",
+            "()[Ljava/lang/reflect/Constructor;",
+            class_get_declared_constructors,
+        )
+        .method(
+            "getDeclaredConstructor",
+            "([Ljava/lang/Class;)Ljava/lang/reflect/Constructor;",
+            class_get_declared_constructor,
+        )
+        .method(
+            "getDeclaredFields",
+            "()[Ljava/lang/reflect/Field;",
+            class_get_declared_fields,
+        )
+        .method(
+            "getDeclaredField",
+            "(Ljava/lang/String;)Ljava/lang/reflect/Field;",
+            class_get_declared_field,
+        )
+        .method("newInstance", "()Ljava/lang/Object;", class_new_instance)
+        .method(
+            "getClassLoader",
+            "()Ljava/lang/ClassLoader;",
+            class_get_class_loader,
+        )
+        .method(
+            "getResource",
+            "(Ljava/lang/String;)Ljava/net/URL;",
+            class_get_resource,
+        )
+        .method(
+            "getResourceAsStream",
+            "(Ljava/lang/String;)Ljava/io/InputStream;",
+            class_get_resource_as_stream,
+        )
+        .static_method(
+            "forName",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            class_for_name,
+        )
+        .static_method(
+            "getPrimitiveClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            class_get_primitive_class,
+        );
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// The class represented by a `java.lang.Class` argument.
+pub(crate) fn mirrored_class(vm: &mut Vm, value: Value) -> Result<ClassId, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).native {
+        NativeData::Class(class) => Ok(class),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a class mirror",
+            object
+        )))),
+    }
+}
+
+fn class_get_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let name = vm.class(class).java_name();
+    let name = vm.intern_string(name.encode_utf16().collect())?;
+    Ok(Some(Value::Reference(Some(name))))
+}
+
+fn class_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let class = vm.class(class);
+    let string = if class.is_primitive() {
+        class.java_name()
+    } else if class.is_interface() {
+        format!("interface {}", class.java_name())
+    } else {
+        format!("class {}", class.java_name())
+    };
+    new_string(vm, string.encode_utf16().collect())
+}
+
+fn class_is_interface(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    Ok(Some(Value::Int(vm.class(class).is_interface() as i32)))
+}
+
+fn class_is_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    Ok(Some(Value::Int(vm.class(class).is_array() as i32)))
+}
+
+fn class_is_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    Ok(Some(Value::Int(vm.class(class).is_primitive() as i32)))
+}
+
+fn class_get_primitive_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    let class = vm.primitive_class(&name)?;
+    let mirror = vm.mirror(class)?;
+    Ok(Some(Value::Reference(Some(mirror))))
+}
+
+/// `ACC_SUPER` is not a modifier of the language.
+fn class_get_modifiers(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let flags = vm.class(class).access_flags - ClassAccessFlags::SUPER;
+    Ok(Some(Value::Int(flags.bits() as i32)))
+}
+
+/// Whether the VM's [AssertionStatus](crate::vm::assertions::AssertionStatus)
+/// enables the assertions of the class.
+fn class_desired_assertion_status(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let class = vm.class(class);
+    let bootstrap = class.defining_loader == LoaderId::BOOTSTRAP;
+    let enabled = vm.assertions.desired(&class.name, bootstrap);
+    Ok(Some(Value::Int(enabled as i32)))
+}
+
+fn class_get_superclass(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let runtime_class = vm.class(class);
+    match runtime_class.super_class {
+        Some(super_class) if !runtime_class.is_interface() => {
+            Ok(Some(Value::Reference(Some(vm.mirror(super_class)?))))
+        }
+        _ => Ok(Some(Value::NULL)),
+    }
+}
+
+fn class_get_interfaces(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let mut mirrors = Vec::new();
+    for interface in vm.class(class).interfaces.clone() {
+        mirrors.push(Some(vm.mirror(interface)?));
+    }
+
+    let array = vm.allocate_array("[Ljava/lang/Class;", ArrayData::Reference(mirrors))?;
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+/// Loads and initializes the class of the binary name through the loader of
+/// the calling class, throwing `ClassNotFoundException` if it is not found.
+fn class_for_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let java_name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    let loader = match vm.thread.frames.last() {
+        Some(frame) => vm.class(frame.class).defining_loader,
+        None => LoaderId::BOOTSTRAP,
+    };
+
+    let name = java_name.replace('.', "/");
+    let class = if java_name.contains('/') || java_name.is_empty() {
+        None
+    } else {
+        match vm.load_class(loader, &name) {
+            Ok(class) => Some(class),
+            Err(Unwind::Throw(error)) if is_missing(vm, error, &name) => None,
+            Err(unwind) => return Err(unwind),
+        }
+    };
+    let class = match class {
+        Some(class) => class,
+        None => return Err(vm.throw_new("java/lang/ClassNotFoundException", Some(java_name))),
+    };
+
+    vm.initialize_class(class)?;
+    Ok(Some(Value::Reference(Some(vm.mirror(class)?))))
+}
+
+/// Whether the throwable reports that the class itself was not found, not
+/// one it depends on.
+fn is_missing(vm: &Vm, throwable: ObjectRef, name: &str) -> bool {
+    if vm.class(vm.class_of(throwable)).name != *"java/lang/NoClassDefFoundError" {
+        return false;
+    }
+    match vm.field(throwable, "detailMessage") {
+        Some(Value::Reference(Some(message))) => match &vm.heap.get(message).native {
+            NativeData::String(chars) => String::from_utf16_lossy(chars) == name,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn class_get_class_loader(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let loader = vm.class(class).defining_loader;
+    Ok(Some(loader_object(vm, loader)?))
+}
+
+fn class_get_resource(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let name = class_resource_name(vm, class, args[1])?;
+    resource(vm, vm.class(class).defining_loader, &name)
+}
+
+fn class_get_resource_as_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let name = class_resource_name(vm, class, args[1])?;
+    resource_as_stream(vm, vm.class(class).defining_loader, &name)
+}
+
+/// The name of a resource relative to the package of the class, unless it
+/// starts with `/`.
+fn class_resource_name(vm: &mut Vm, class: ClassId, name: Value) -> Result<String, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, name)?);
+    if let Some(absolute) = name.strip_prefix('/') {
+        return Ok(absolute.to_string());
+    }
+    match vm.class(class).name.rfind('/') {
+        Some(end) => Ok(format!("{}/{}", &vm.class(class).name[..end], name)),
+        None => Ok(name),
+    }
+}
+
+// =============================================================================
+// CLASS LOADER
+// =============================================================================
+
+/// `java.lang.ClassLoader`, standing for the loaders of the VM. Subclasses
+/// defined by the guest delegate to the application loader, as they cannot
+/// define classes of their own.
+fn class_loader() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/ClassLoader", "java/lang/Object")
+        .method("<init>", "()V", object_init)
+        .method(
+            "getParent",
+            "()Ljava/lang/ClassLoader;",
+            class_loader_get_parent,
+        )
+        .method(
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            class_loader_load_class,
+        )
+        .method(
+            "getResource",
+            "(Ljava/lang/String;)Ljava/net/URL;",
+            class_loader_get_resource,
+        )
+        .method(
+            "getResourceAsStream",
+            "(Ljava/lang/String;)Ljava/io/InputStream;",
+            class_loader_get_resource_as_stream,
+        )
+        .method(
+            "getResources",
+            "(Ljava/lang/String;)Ljava/util/Enumeration;",
+            class_loader_get_resources,
+        )
+        .static_method(
+            "getSystemClassLoader",
+            "()Ljava/lang/ClassLoader;",
+            class_loader_get_system_class_loader,
+        )
+        .static_method(
+            "getSystemResource",
+            "(Ljava/lang/String;)Ljava/net/URL;",
+            class_loader_get_system_resource,
+        )
+        .static_method(
+            "getSystemResources",
+            "(Ljava/lang/String;)Ljava/util/Enumeration;",
+            class_loader_get_system_resources,
+        )
+        .static_method(
+            "getSystemResourceAsStream",
+            "(Ljava/lang/String;)Ljava/io/InputStream;",
+            class_loader_get_system_resource_as_stream,
+        );
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+/// The `ClassLoader` of the loader, `null` for the bootstrap loader.
+pub(crate) fn loader_object(vm: &mut Vm, loader: LoaderId) -> Result<Value, Unwind> {
+    if loader == LoaderId::BOOTSTRAP {
+        return Ok(Value::NULL);
+    }
+    if let Some(object) = vm.loader_objects.get(&loader) {
+        return Ok(Value::Reference(Some(*object)));
+    }
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/ClassLoader")?;
+    let object = vm.instantiate(class)?;
+    vm.heap.get_mut(object).native = NativeData::Loader(loader);
+    vm.loader_objects.insert(loader, object);
+    Ok(Value::Reference(Some(object)))
+}
+
+/// The loader a `ClassLoader` argument stands for.
+pub(crate) fn object_loader(vm: &mut Vm, value: Value) -> Result<LoaderId, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).native {
+        NativeData::Loader(loader) => Ok(loader),
+        _ => Ok(LoaderId::APPLICATION),
+    }
+}
+
+fn class_loader_get_parent(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let loader = object_loader(vm, args[0])?;
+    match vm.loaders.loader(loader).parent {
+        Some(parent) => Ok(Some(loader_object(vm, parent)?)),
+        None => Ok(Some(Value::NULL)),
+    }
+}
+
+fn class_loader_load_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let loader = object_loader(vm, args[0])?;
+    let java_name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    let name = java_name.replace('.', "/");
+    let class = if java_name.contains('/') || java_name.is_empty() {
+        None
+    } else {
+        match vm.load_class(loader, &name) {
+            Ok(class) => Some(class),
+            Err(Unwind::Throw(error)) if is_missing(vm, error, &name) => None,
+            Err(unwind) => return Err(unwind),
+        }
+    };
+    match class {
+        Some(class) => Ok(Some(Value::Reference(Some(vm.mirror(class)?)))),
+        None => Err(vm.throw_new("java/lang/ClassNotFoundException", Some(java_name))),
+    }
+}
+
+fn class_loader_get_resource(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let loader = object_loader(vm, args[0])?;
+    let name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    resource(vm, loader, &name)
+}
+
+fn class_loader_get_resource_as_stream(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let loader = object_loader(vm, args[0])?;
+    let name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    resource_as_stream(vm, loader, &name)
+}
+
+fn class_loader_get_resources(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let loader = object_loader(vm, args[0])?;
+    let name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    resources(vm, loader, &name)
+}
+
+fn class_loader_get_system_class_loader(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(loader_object(vm, LoaderId::APPLICATION)?))
+}
+
+fn class_loader_get_system_resource(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    resource(vm, LoaderId::APPLICATION, &name)
+}
+
+fn class_loader_get_system_resources(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    resources(vm, LoaderId::APPLICATION, &name)
+}
+
+fn class_loader_get_system_resource_as_stream(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    resource_as_stream(vm, LoaderId::APPLICATION, &name)
+}
+
+/// The `URL` of the resource the loader finds, `null` if none does.
+fn resource(vm: &mut Vm, loader: LoaderId, name: &str) -> Result<Option<Value>, Unwind> {
+    match vm.loaders.resource_url(loader, name) {
+        Some(url) => Ok(Some(Value::Reference(Some(new_url(vm, &url)?)))),
+        None => Ok(Some(Value::NULL)),
+    }
+}
+
+/// An `Enumeration` of the `URL`s of every copy of the resource the loader
+/// finds, like the provider-configuration files `ServiceLoader` reads.
+fn resources(vm: &mut Vm, loader: LoaderId, name: &str) -> Result<Option<Value>, Unwind> {
+    let mut urls = Vec::new();
+    for url in vm.loaders.resource_urls(loader, name) {
+        urls.push(Some(new_url(vm, &url)?));
+    }
+    Ok(Some(Value::Reference(Some(new_array_iterator(vm, urls)?))))
+}
+
+/// An `InputStream` reading the resource the loader finds, `null` if none
+/// does.
+fn resource_as_stream(vm: &mut Vm, loader: LoaderId, name: &str) -> Result<Option<Value>, Unwind> {
+    let bytes = vm
+        .loaders
+        .resource_url(loader, name)
+        .and_then(|url| vm.loaders.resource_at(&url));
+    match bytes {
+        Some(bytes) => {
+            let stream = new_pipe_stream(vm, Pipe::Input(Box::new(Cursor::new(bytes))))?;
+            Ok(Some(Value::Reference(Some(stream))))
+        }
+        None => Ok(Some(Value::NULL)),
+    }
+}
+
+// =============================================================================
+// ENUM
+// =============================================================================
+
+/// The superclass of enums, whose `values()` and `valueOf(String)` javac
+/// generates from their `$VALUES` array and [enum_value_of].
+fn enumeration() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/Enum", "java/lang/Object")
+        .implements("java/lang/Comparable")
+        .implements("java/io/Serializable")
+        .field("name", "Ljava/lang/String;")
+        .field("ordinal", "I")
+        .method("<init>", "(Ljava/lang/String;I)V", enum_init)
+        .method("name", "()Ljava/lang/String;", enum_name)
+        .method("toString", "()Ljava/lang/String;", enum_name)
+        .method("ordinal", "()I", enum_ordinal)
+        .method("compareTo", "(Ljava/lang/Enum;)I", enum_compare_to)
+        .method("compareTo", "(Ljava/lang/Object;)I", enum_compare_to)
+        .method(
+            "getDeclaringClass",
+            "()Ljava/lang/Class;",
+            enum_get_declaring_class,
+        )
+        .static_method(
+            "valueOf",
+            "(Ljava/lang/Class;Ljava/lang/String;)Ljava/lang/Enum;",
+            enum_value_of,
+        );
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn enum_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "name", args[1]);
+    vm.set_field(this, "ordinal", args[2]);
+    Ok(None)
+}
+
+fn enum_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "name"))
+}
+
+fn enum_ordinal(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "ordinal"))
+}
+
+/// The enum class of a constant, which is the superclass of the classes
+/// javac generates for constants with a body.
+fn declaring_enum(vm: &Vm, constant: ObjectRef) -> ClassId {
+    let class = vm.class_of(constant);
+    match vm.class(class).super_class {
+        Some(super_class) if vm.class(super_class).name != *"java/lang/Enum" => super_class,
+        _ => class,
+    }
+}
+
+fn enum_compare_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let other = non_null(vm, args[1])?;
+    if declaring_enum(vm, this) != declaring_enum(vm, other) {
+        let message = format!(
+            "class {} cannot be cast to class {}",
+            vm.class(vm.class_of(other)).java_name(),
+            vm.class(declaring_enum(vm, this)).java_name()
+        );
+        return Err(vm.throw_new("java/lang/ClassCastException", Some(message)));
+    }
+
+    let ordinal = |object| vm.field(object, "ordinal").map_or(0, int);
+    Ok(Some(Value::Int(ordinal(this) - ordinal(other))))
+}
+
+fn enum_get_declaring_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let mirror = vm.mirror(declaring_enum(vm, this))?;
+    Ok(Some(Value::Reference(Some(mirror))))
+}
+
+/// Whether the class is an enum, not the class of a constant with a body.
+fn is_enum(vm: &Vm, class: ClassId) -> bool {
+    let class = vm.class(class);
+    class.access_flags.contains(ClassAccessFlags::ENUM)
+        && class
+            .super_class
+            .is_some_and(|super_class| vm.class(super_class).name == *"java/lang/Enum")
+}
+
+/// The constants of an initialized enum class in declaration order, read
+/// from the static fields javac marks as enum constants.
+fn enum_constants(vm: &mut Vm, class: ClassId) -> Result<Vec<Value>, Unwind> {
+    vm.initialize_class(class)?;
+    let runtime_class = vm.class(class);
+    Ok(runtime_class
+        .fields
+        .iter()
+        .filter(|field| field.is_static() && field.access_flags.contains(FieldAccessFlags::ENUM))
+        .map(|field| runtime_class.static_values[field.slot])
+        .collect())
+}
+
+fn class_is_enum(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    Ok(Some(Value::Int(is_enum(vm, class) as i32)))
+}
+
+/// A new array of the constants of an enum class, `null` for other classes.
+fn class_get_enum_constants(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    if !is_enum(vm, class) {
+        return Ok(Some(Value::NULL));
+    }
+
+    let constants = enum_constants(vm, class)?
+        .into_iter()
+        .map(|constant| match constant {
+            Value::Reference(object) => object,
+            _ => None,
+        })
+        .collect();
+    let runtime_class = vm.class(class);
+    let array_name = format!("[L{};", runtime_class.name);
+    let array_class = vm.load_class(runtime_class.defining_loader, &array_name)?;
+    let array = vm.allocate(HeapObject {
+        class: array_class,
+        data: ObjectData::Array(ArrayData::Reference(constants)),
+        native: NativeData::None,
+        identity_hash: None,
+    });
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+/// The constant of the enum class with the name, as `valueOf(String)` of
+/// every enum returns it.
+fn enum_value_of(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    if args[1] == Value::NULL {
+        return Err(vm.throw_new(
+            "java/lang/NullPointerException",
+            Some("Name is null".to_string()),
+        ));
+    }
+    let name = chars(vm, args[1])?;
+    if !is_enum(vm, class) {
+        let message = format!("{} is not an enum type", vm.class(class).java_name());
+        return Err(vm.throw_new("java/lang/IllegalArgumentException", Some(message)));
+    }
+
+    for constant in enum_constants(vm, class)? {
+        if let Value::Reference(Some(object)) = constant {
+            let constant_name = vm.field(object, "name").unwrap_or(Value::NULL);
+            if *chars(vm, constant_name)? == *name {
+                return Ok(Some(constant));
+            }
+        }
+    }
+
+    let message = format!(
+        "No enum constant {}.{}",
+        vm.class(class).java_name().replace('$', "."),
+        String::from_utf16_lossy(&name)
+    );
+    Err(vm.throw_new("java/lang/IllegalArgumentException", Some(message)))
+}
+
+// =============================================================================
+// FLOAT AND DOUBLE
+// =============================================================================
+
+fn float_to_raw_int_bits(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match args[0] {
+        Value::Float(value) => Ok(Some(Value::Int(value.to_bits() as i32))),
+        value => panic!("Expected a float, got {:?}", value),
+    }
+}
+
+fn int_bits_to_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Float(f32::from_bits(int(args[0]) as u32))))
+}
+
+fn double_to_raw_long_bits(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match args[0] {
+        Value::Double(value) => Ok(Some(Value::Long(value.to_bits() as i64))),
+        value => panic!("Expected a double, got {:?}", value),
+    }
+}
+
+fn long_bits_to_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match args[0] {
+        Value::Long(value) => Ok(Some(Value::Double(f64::from_bits(value as u64)))),
+        value => panic!("Expected a long, got {:?}", value),
+    }
+}
+
+// =============================================================================
+// NUMBER
+// =============================================================================
+
+fn number() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/Number", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .method("<init>", "()V", object_init)
+        .abstract_method("intValue", "()I")
+        .abstract_method("longValue", "()J")
+        .abstract_method("floatValue", "()F")
+        .abstract_method("doubleValue", "()D")
+        .method("byteValue", "()B", number_byte_value)
+        .method("shortValue", "()S", number_short_value);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn number_byte_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let value = vm.invoke_virtual(this, "intValue", "()I", &[])?;
+    Ok(value.map(|value| Value::Int(int(value) as i8 as i32)))
+}
+
+fn number_short_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let value = vm.invoke_virtual(this, "intValue", "()I", &[])?;
+    Ok(value.map(|value| Value::Int(int(value) as i16 as i32)))
+}
+
+// =============================================================================
+// STRING
+// =============================================================================
+
+fn string() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/String", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .implements("java/lang/Comparable")
+        .implements("java/lang/CharSequence")
+        .method("<init>", "()V", string_init)
+        .method("<init>", "(Ljava/lang/String;)V", string_init_string)
+        .method("<init>", "([C)V", string_init_chars)
+        .method("<init>", "([CII)V", string_init_chars_range)
+        .method("<init>", "([B)V", string_init_bytes)
+        .method("<init>", "([BII)V", string_init_bytes)
+        .method("<init>", "([BLjava/lang/String;)V", string_init_bytes)
+        .method("<init>", "([BIILjava/lang/String;)V", string_init_bytes)
+        .method(
+            "<init>",
+            "([BLjava/nio/charset/Charset;)V",
+            string_init_bytes,
+        )
+        .method(
+            "<init>",
+            "([BIILjava/nio/charset/Charset;)V",
+            string_init_bytes,
+        )
+        .method("length", "()I", string_length)
+        .method("isEmpty", "()Z", string_is_empty)
+        .method("charAt", "(I)C", string_char_at)
+        .method("equals", "(Ljava/lang/Object;)Z", string_equals)
+        .method("hashCode", "()I", string_hash_code)
+        .method("toString", "()Ljava/lang/String;", string_to_string)
+        .method("intern", "()Ljava/lang/String;", string_intern)
+        .method(
+            "concat",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            string_concat,
+        )
+        .method("compareTo", "(Ljava/lang/String;)I", string_compare_to)
+        .method("compareTo", "(Ljava/lang/Object;)I", string_compare_to)
+        .method("toCharArray", "()[C", string_to_char_array)
+        .method("getBytes", "()[B", string_get_bytes)
+        .method("getBytes", "(Ljava/lang/String;)[B", string_get_bytes)
+        .method(
+            "getBytes",
+            "(Ljava/nio/charset/Charset;)[B",
+            string_get_bytes,
+        )
+        .method("substring", "(I)Ljava/lang/String;", string_substring)
+        .method("substring", "(II)Ljava/lang/String;", string_substring)
+        .method("indexOf", "(I)I", string_index_of_char)
+        .method("indexOf", "(Ljava/lang/String;)I", string_index_of_string)
+        .method("contains", "(Ljava/lang/CharSequence;)Z", string_contains)
+        .method("startsWith", "(Ljava/lang/String;)Z", string_starts_with)
+        .method("endsWith", "(Ljava/lang/String;)Z", string_ends_with)
+        .method("trim", "()Ljava/lang/String;", string_trim)
+        .method("toUpperCase", "()Ljava/lang/String;", string_to_upper_case)
+        .method("toLowerCase", "()Ljava/lang/String;", string_to_lower_case)
+        .method("replace", "(CC)Ljava/lang/String;", string_replace)
+        .static_method("valueOf", "(Z)Ljava/lang/String;", string_value_of_boolean)
+        .static_method("valueOf", "(C)Ljava/lang/String;", string_value_of_char)
+        .static_method(
+            "valueOf",
+            "(I)Ljava/lang/String;",
+            string_value_of_primitive,
+        )
+        .static_method(
+            "valueOf",
+            "(J)Ljava/lang/String;",
+            string_value_of_primitive,
+        )
+        .static_method(
+            "valueOf",
+            "(F)Ljava/lang/String;",
+            string_value_of_primitive,
+        )
+        .static_method(
+            "valueOf",
+            "(D)Ljava/lang/String;",
+            string_value_of_primitive,
+        )
+        .static_method(
+            "valueOf",
+            "(Ljava/lang/Object;)Ljava/lang/String;",
+            string_value_of_object,
+        )
+        .static_method("valueOf", "([C)Ljava/lang/String;", string_value_of_chars);
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// The UTF-16 contents of a string argument, throwing `NullPointerException`
+/// for `null`.
+pub(crate) fn chars(vm: &mut Vm, value: Value) -> Result<Arc<[u16]>, Unwind> {
+    let object = non_null(vm, value)?;
+    match &vm.heap.get(object).native {
+        NativeData::String(chars) => Ok(chars.clone()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a string",
+            object
+        )))),
+    }
+}
+
+fn set_chars(vm: &mut Vm, value: Value, chars: Vec<u16>) -> Result<Option<Value>, Unwind> {
+    let object = non_null(vm, value)?;
+    vm.heap.get_mut(object).native = NativeData::String(chars.into());
+    Ok(None)
+}
+
+fn new_string(vm: &mut Vm, chars: Vec<u16>) -> Result<Option<Value>, Unwind> {
+    let object = vm.create_string(chars)?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+fn char_array(vm: &mut Vm, value: Value) -> Result<Vec<u16>, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).array() {
+        Some(ArrayData::Char(chars)) => Ok(chars.clone()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a char array",
+            object
+        )))),
+    }
+}
+
+fn string_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    set_chars(vm, args[0], Vec::new())
+}
+
+fn string_init_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[1])?;
+    set_chars(vm, args[0], chars.to_vec())
+}
+
+fn string_init_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = char_array(vm, args[1])?;
+    set_chars(vm, args[0], chars)
+}
+
+fn string_init_chars_range(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = char_array(vm, args[1])?;
+    let (offset, count) = (int(args[2]), int(args[3]));
+    if offset < 0 || count < 0 || offset as usize + count as usize > chars.len() {
+        return Err(vm.throw_new(
+            "java/lang/StringIndexOutOfBoundsException",
+            Some(format!(
+                "offset {}, count {}, length {}",
+                offset,
+                count,
+                chars.len()
+            )),
+        ));
+    }
+
+    let range = offset as usize..(offset + count) as usize;
+    set_chars(vm, args[0], chars[range].to_vec())
+}
+
+/// The encoding named by the optional charset argument of a `String`
+/// method, a charset name or a `Charset`, UTF-8 without one.
+fn string_encoding(vm: &mut Vm, value: Option<Value>) -> Result<Encoding, Unwind> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(Encoding::Utf8),
+    };
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).native {
+        NativeData::String(_) => named_encoding(vm, value, "java/io/UnsupportedEncodingException"),
+        _ => encoding(vm, value),
+    }
+}
+
+/// Decodes the bytes, or the range of them given by an offset and a count,
+/// in the optional charset.
+fn string_init_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let bytes = byte_array(vm, args[1])?;
+    let (range, charset) = match args.len() {
+        2 | 3 => (0..bytes.len(), args.get(2)),
+        _ => {
+            let (offset, count) = (int(args[2]), int(args[3]));
+            if offset < 0 || count < 0 || offset as usize + count as usize > bytes.len() {
+                return Err(vm.throw_new(
+                    "java/lang/StringIndexOutOfBoundsException",
+                    Some(format!(
+                        "offset {}, count {}, length {}",
+                        offset,
+                        count,
+                        bytes.len()
+                    )),
+                ));
+            }
+            (offset as usize..(offset + count) as usize, args.get(4))
+        }
+    };
+
+    let encoding = string_encoding(vm, charset.copied())?;
+    set_chars(vm, args[0], encoding.decode(&bytes[range]))
+}
+
+fn string_get_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let encoding = string_encoding(vm, args.get(1).copied())?;
+    new_byte_array(vm, encoding.encode(&chars))
+}
+
+fn string_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(chars(vm, args[0])?.len() as i32)))
+}
+
+fn string_is_empty(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(chars(vm, args[0])?.is_empty() as i32)))
+}
+
+fn string_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let index = int(args[1]);
+    match chars.get(index as usize) {
+        Some(char) if index >= 0 => Ok(Some(Value::Int(*char as i32))),
+        _ => Err(vm.throw_new(
+            "java/lang/StringIndexOutOfBoundsException",
+            Some(format!(
+                "Index {} out of bounds for length {}",
+                index,
+                chars.len()
+            )),
+        )),
+    }
+}
+
+fn string_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = chars(vm, args[0])?;
+    let equal = match args[1] {
+        Value::Reference(Some(other)) => match &vm.heap.get(other).native {
+            NativeData::String(other) => *other == this,
+            _ => false,
+        },
+        _ => false,
+    };
+
+    Ok(Some(Value::Int(equal as i32)))
+}
+
+fn string_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let hash = chars(vm, args[0])?.iter().fold(0i32, |hash, char| {
+        hash.wrapping_mul(31).wrapping_add(*char as i32)
+    });
+    Ok(Some(Value::Int(hash)))
+}
+
+fn string_to_string(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(args[0]))
+}
+
+fn string_intern(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let object = vm.intern_string(chars.to_vec())?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+fn string_concat(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    if other.is_empty() {
+        return Ok(Some(args[0]));
+    }
+    new_string(vm, [&this[..], &other[..]].concat())
+}
+
+fn string_compare_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    let difference = this
+        .iter()
+        .zip(other.iter())
+        .find(|(a, b)| a != b)
+        .map(|(a, b)| *a as i32 - *b as i32)
+        .unwrap_or(this.len() as i32 - other.len() as i32);
+    Ok(Some(Value::Int(difference)))
+}
+
+fn string_to_char_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let array = vm.allocate_array("[C", ArrayData::Char(chars.to_vec()))?;
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+fn string_substring(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let begin = int(args[1]);
+    let end = args.get(2).map_or(chars.len() as i32, |end| int(*end));
+    if begin < 0 || end > chars.len() as i32 || begin > end {
+        return Err(vm.throw_new(
+            "java/lang/StringIndexOutOfBoundsException",
+            Some(format!(
+                "begin {}, end {}, length {}",
+                begin,
+                end,
+                chars.len()
+            )),
+        ));
+    }
+
+    new_string(vm, chars[begin as usize..end as usize].to_vec())
+}
+
+fn index_of(haystack: &[u16], needle: &[u16]) -> i32 {
+    if needle.is_empty() {
+        return 0;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map_or(-1, |index| index as i32)
+}
+
+fn string_index_of_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let needle: Vec<u16> = char::from_u32(int(args[1]) as u32)
+        .map(|char| char.encode_utf16(&mut [0; 2]).to_vec())
+        .unwrap_or_default();
+    let index = if needle.is_empty() {
+        -1
+    } else {
+        index_of(&chars, &needle)
+    };
+    Ok(Some(Value::Int(index)))
+}
+
+fn string_index_of_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    Ok(Some(Value::Int(index_of(&this, &other))))
+}
+
+fn string_contains(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    Ok(Some(Value::Int((index_of(&this, &other) >= 0) as i32)))
+}
+
+fn string_starts_with(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    Ok(Some(Value::Int(this.starts_with(&other) as i32)))
+}
+
+fn string_ends_with(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, other) = (chars(vm, args[0])?, chars(vm, args[1])?);
+    Ok(Some(Value::Int(this.ends_with(&other) as i32)))
+}
+
+fn string_trim(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let begin = chars.iter().position(|char| *char > 0x20);
+    let end = chars.iter().rposition(|char| *char > 0x20);
+    match (begin, end) {
+        (Some(0), Some(end)) if end + 1 == chars.len() => Ok(Some(args[0])),
+        (Some(begin), Some(end)) => new_string(vm, chars[begin..=end].to_vec()),
+        _ => new_string(vm, Vec::new()),
+    }
+}
+
+fn map_string(vm: &mut Vm, value: Value, map: fn(&str) -> String) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, value)?;
+    let mapped = map(&String::from_utf16_lossy(&chars));
+    new_string(vm, mapped.encode_utf16().collect())
+}
+
+fn string_to_upper_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    map_string(vm, args[0], str::to_uppercase)
+}
+
+fn string_to_lower_case(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    map_string(vm, args[0], str::to_lowercase)
+}
+
+fn string_replace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = chars(vm, args[0])?;
+    let (old, new) = (int(args[1]) as u16, int(args[2]) as u16);
+    if !chars.contains(&old) {
+        return Ok(Some(args[0]));
+    }
+
+    let replaced = chars
+        .iter()
+        .map(|char| if *char == old { new } else { *char })
+        .collect();
+    new_string(vm, replaced)
+}
+
+fn string_value_of_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = if int(args[0]) != 0 { "true" } else { "false" };
+    new_string(vm, string.encode_utf16().collect())
+}
+
+fn string_value_of_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    new_string(vm, primitive_to_string(args[0]).encode_utf16().collect())
+}
+
+fn string_value_of_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = object_to_string(vm, args[0])?;
+    new_string(vm, string.encode_utf16().collect())
+}
+
+fn string_value_of_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    new_string(vm, vec![int(args[0]) as u16])
+}
+
+fn string_value_of_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = char_array(vm, args[0])?;
+    new_string(vm, chars)
+}
+
+/// Formats an `int`, `long`, `float` or `double` value the way the
+/// `toString` of its wrapper class does.
+pub(crate) fn primitive_to_string(value: Value) -> String {
+    match value {
+        Value::Int(value) => value.to_string(),
+        Value::Long(value) => value.to_string(),
+        Value::Float(value) => float_to_string(value),
+        Value::Double(value) => double_to_string(value),
+        value => panic!("Expected a primitive, got {:?}", value),
+    }
+}
+
+/// The result of `String.valueOf(Object)`: `"null"`, or the `toString()` of
+/// the object.
+pub(crate) fn object_to_string(vm: &mut Vm, value: Value) -> Result<String, Unwind> {
+    Ok(String::from_utf16_lossy(&object_chars(vm, value)?))
+}
+
+/// The UTF-16 contents of [object_to_string].
+pub(crate) fn object_chars(vm: &mut Vm, value: Value) -> Result<Vec<u16>, Unwind> {
+    let object = match value {
+        Value::Reference(Some(object)) => object,
+        _ => return Ok("null".encode_utf16().collect()),
+    };
+    if let NativeData::String(chars) = &vm.heap.get(object).native {
+        return Ok(chars.to_vec());
+    }
+
+    let string = vm.invoke_virtual(object, "toString", "()Ljava/lang/String;", &[])?;
+    match string {
+        Some(Value::Reference(Some(string))) => {
+            Ok(chars(vm, Value::Reference(Some(string)))?.to_vec())
+        }
+        _ => Ok("null".encode_utf16().collect()),
+    }
+}
+
+/// The UTF-16 contents of `String.valueOf` of a value of the type.
+pub(crate) fn value_chars(
+    vm: &mut Vm,
+    value: Value,
+    field_type: &FieldType,
+) -> Result<Vec<u16>, Unwind> {
+    let string = match (field_type, value) {
+        (FieldType::Boolean, Value::Int(value)) => (value != 0).to_string(),
+        (FieldType::Char, Value::Int(value)) => return Ok(vec![value as u16]),
+        (field_type, value) if field_type.is_reference() => return object_chars(vm, value),
+        (_, value) => primitive_to_string(value),
+    };
+    Ok(string.encode_utf16().collect())
+}
+
+/// Formats a `double` the way `Double.toString` does.
+pub fn double_to_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    let magnitude = value.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        java_decimal(format!("{}", value))
+    } else {
+        java_scientific(format!("{:e}", value))
+    }
+}
+
+/// Formats a `float` the way `Float.toString` does.
+pub fn float_to_string(value: f32) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    let magnitude = value.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        java_decimal(format!("{}", value))
+    } else {
+        java_scientific(format!("{:e}", value))
+    }
+}
+
+fn java_decimal(formatted: String) -> String {
+    if formatted.contains('.') {
+        formatted
+    } else {
+        formatted + ".0"
+    }
+}
+
+fn java_scientific(formatted: String) -> String {
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    if mantissa.contains('.') {
+        format!("{}E{}", mantissa, exponent)
+    } else {
+        format!("{}.0E{}", mantissa, exponent)
+    }
+}
+
+// =============================================================================
+// STRING BUILDER
+// =============================================================================
+
+fn string_builder() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/StringBuilder", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .implements("java/lang/CharSequence")
+        .method("<init>", "()V", builder_init)
+        .method("<init>", "(I)V", builder_init_capacity)
+        .method("<init>", "(Ljava/lang/String;)V", builder_init_string)
+        .method("<init>", "(Ljava/lang/CharSequence;)V", builder_init_string)
+        .method(
+            "append",
+            "(Ljava/lang/String;)Ljava/lang/StringBuilder;",
+            builder_append_object,
+        )
+        .method(
+            "append",
+            "(Ljava/lang/Object;)Ljava/lang/StringBuilder;",
+            builder_append_object,
+        )
+        .method(
+            "append",
+            "(Ljava/lang/CharSequence;)Ljava/lang/StringBuilder;",
+            builder_append_object,
+        )
+        .method(
+            "append",
+            "([C)Ljava/lang/StringBuilder;",
+            builder_append_chars,
+        )
+        .method(
+            "append",
+            "(Z)Ljava/lang/StringBuilder;",
+            builder_append_boolean,
+        )
+        .method(
+            "append",
+            "(C)Ljava/lang/StringBuilder;",
+            builder_append_char,
+        )
+        .method(
+            "append",
+            "(I)Ljava/lang/StringBuilder;",
+            builder_append_primitive,
+        )
+        .method(
+            "append",
+            "(J)Ljava/lang/StringBuilder;",
+            builder_append_primitive,
+        )
+        .method(
+            "append",
+            "(F)Ljava/lang/StringBuilder;",
+            builder_append_primitive,
+        )
+        .method(
+            "append",
+            "(D)Ljava/lang/StringBuilder;",
+            builder_append_primitive,
+        )
+        .method(
+            "insert",
+            "(ILjava/lang/String;)Ljava/lang/StringBuilder;",
+            builder_insert,
+        )
+        .method(
+            "deleteCharAt",
+            "(I)Ljava/lang/StringBuilder;",
+            builder_delete_char_at,
+        )
+        .method("reverse", "()Ljava/lang/StringBuilder;", builder_reverse)
+        .method("length", "()I", builder_length)
+        .method("charAt", "(I)C", builder_char_at)
+        .method("setCharAt", "(IC)V", builder_set_char_at)
+        .method("setLength", "(I)V", builder_set_length)
+        .method("toString", "()Ljava/lang/String;", builder_to_string);
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// Applies the function to the contents of a builder argument.
+fn with_builder<T>(
+    vm: &mut Vm,
+    value: Value,
+    function: impl FnOnce(&mut Vec<u16>) -> T,
+) -> Result<T, Unwind> {
+    let object = non_null(vm, value)?;
+    match &mut vm.heap.get_mut(object).native {
+        NativeData::Builder(chars) => Ok(function(chars)),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a string builder",
+            object
+        )))),
+    }
+}
+
+/// Appends to the builder, returning it for chaining.
+fn append(vm: &mut Vm, builder: Value, chars: &[u16]) -> Result<Option<Value>, Unwind> {
+    with_builder(vm, builder, |builder| builder.extend_from_slice(chars))?;
+    Ok(Some(builder))
+}
+
+/// Throws `StringIndexOutOfBoundsException` unless the index is below the
+/// length of the builder.
+fn check_builder_index(vm: &mut Vm, builder: Value, index: i32) -> Result<(), Unwind> {
+    let length = with_builder(vm, builder, |builder| builder.len())?;
+    if index >= 0 && (index as usize) < length {
+        return Ok(());
+    }
+
+    Err(vm.throw_new(
+        "java/lang/StringIndexOutOfBoundsException",
+        Some(format!("index {},length {}", index, length)),
+    ))
+}
+
+fn builder_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.heap.get_mut(this).native = NativeData::Builder(Vec::new());
+    Ok(None)
+}
+
+/// The capacity is only a hint, as the contents grow as needed.
+fn builder_init_capacity(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let capacity = int(args[1]);
+    if capacity < 0 {
+        return Err(vm.throw_new(
+            "java/lang/NegativeArraySizeException",
+            Some(capacity.to_string()),
+        ));
+    }
+    builder_init(vm, args)
+}
+
+fn builder_init_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    non_null(vm, args[1])?;
+    let chars = object_chars(vm, args[1])?;
+    let this = non_null(vm, args[0])?;
+    vm.heap.get_mut(this).native = NativeData::Builder(chars);
+    Ok(None)
+}
+
+fn builder_append_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = object_chars(vm, args[1])?;
+    append(vm, args[0], &chars)
+}
+
+fn builder_append_chars(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = char_array(vm, args[1])?;
+    append(vm, args[0], &chars)
+}
+
+fn builder_append_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = value_chars(vm, args[1], &FieldType::Boolean)?;
+    append(vm, args[0], &chars)
+}
+
+fn builder_append_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    append(vm, args[0], &[int(args[1]) as u16])
+}
+
+fn builder_append_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let string = primitive_to_string(args[1]);
+    append(vm, args[0], &string.encode_utf16().collect::<Vec<_>>())
+}
+
+fn builder_insert(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let offset = int(args[1]);
+    let chars = object_chars(vm, args[2])?;
+    let length = with_builder(vm, args[0], |builder| builder.len())?;
+    if offset < 0 || offset as usize > length {
+        return Err(vm.throw_new(
+            "java/lang/StringIndexOutOfBoundsException",
+            Some(format!("offset {}, length {}", offset, length)),
+        ));
+    }
+
+    with_builder(vm, args[0], |builder| {
+        builder.splice(offset as usize..offset as usize, chars);
+    })?;
+    Ok(Some(args[0]))
+}
+
+fn builder_delete_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let index = int(args[1]);
+    check_builder_index(vm, args[0], index)?;
+    with_builder(vm, args[0], |builder| builder.remove(index as usize))?;
+    Ok(Some(args[0]))
+}
+
+/// Reverses the characters, keeping the surrogate pairs in order.
+fn builder_reverse(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    with_builder(vm, args[0], |builder| {
+        builder.reverse();
+        let mut index = 0;
+        while index + 1 < builder.len() {
+            let (low, high) = (builder[index], builder[index + 1]);
+            if (0xdc00..0xe000).contains(&low) && (0xd800..0xdc00).contains(&high) {
+                builder.swap(index, index + 1);
+                index += 2;
+            } else {
+                index += 1;
+            }
+        }
+    })?;
+    Ok(Some(args[0]))
+}
+
+fn builder_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let length = with_builder(vm, args[0], |builder| builder.len())?;
+    Ok(Some(Value::Int(length as i32)))
+}
+
+fn builder_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let index = int(args[1]);
+    check_builder_index(vm, args[0], index)?;
+    let char = with_builder(vm, args[0], |builder| builder[index as usize])?;
+    Ok(Some(Value::Int(char as i32)))
+}
+
+fn builder_set_char_at(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let index = int(args[1]);
+    check_builder_index(vm, args[0], index)?;
+    with_builder(vm, args[0], |builder| {
+        builder[index as usize] = int(args[2]) as u16
+    })?;
+    Ok(None)
+}
+
+/// Truncates the contents, or pads them with `'\0'`.
+fn builder_set_length(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let length = int(args[1]);
+    if length < 0 {
+        return Err(vm.throw_new(
+            "java/lang/StringIndexOutOfBoundsException",
+            Some(format!("String index out of range: {}", length)),
+        ));
+    }
+    with_builder(vm, args[0], |builder| builder.resize(length as usize, 0))?;
+    Ok(None)
+}
+
+fn builder_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let chars = with_builder(vm, args[0], |builder| builder.clone())?;
+    new_string(vm, chars)
+}
+
+// =============================================================================
+// SYSTEM
+// =============================================================================
+
+fn system() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/System", "java/lang/Object")
+        .static_field("out", "Ljava/io/PrintStream;")
+        .static_field("err", "Ljava/io/PrintStream;")
+        .static_method("<clinit>", "()V", system_clinit)
+        .static_method("exit", "(I)V", system_exit)
+        .static_method("gc", "()V", system_gc)
+        .static_method(
+            "arraycopy",
+            "(Ljava/lang/Object;ILjava/lang/Object;II)V",
+            system_arraycopy,
+        )
+        .static_method(
+            "identityHashCode",
+            "(Ljava/lang/Object;)I",
+            system_identity_hash_code,
+        )
+        .static_method("currentTimeMillis", "()J", system_current_time_millis)
+        .static_method("nanoTime", "()J", system_nano_time)
+        .static_method(
+            "getenv",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            system_getenv,
+        )
+        .static_method(
+            "lineSeparator",
+            "()Ljava/lang/String;",
+            system_line_separator,
+        );
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+fn system_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let system = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/System")?;
+    let out = super::io::new_print_stream(vm, StandardStream::Out)?;
+    let err = super::io::new_print_stream(vm, StandardStream::Err)?;
+    vm.set_static_field(system, "out", Value::Reference(Some(out)));
+    vm.set_static_field(system, "err", Value::Reference(Some(err)));
+    Ok(None)
+}
+
+fn system_exit(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Err(Unwind::Exit(int(args[0])))
+}
+
+/// Collects at the next safepoint, once the calling native returned.
+fn system_gc(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    vm.request_gc();
+    Ok(None)
+}
+
+fn system_identity_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let hash = match args[0] {
+        Value::Reference(Some(object)) => vm.heap.identity_hash(object),
+        _ => 0,
+    };
+    Ok(Some(Value::Int(hash)))
+}
+
+/// The Java source name of a type, e.g. `int` or `java.lang.String[]`.
+pub(crate) fn type_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Byte => "byte".to_string(),
+        FieldType::Char => "char".to_string(),
+        FieldType::Double => "double".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Int => "int".to_string(),
+        FieldType::Long => "long".to_string(),
+        FieldType::Short => "short".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Object(name) => name.replace('/', "."),
+        FieldType::Array(component) => format!("{}[]", type_name(component)),
+    }
+}
+
+/// Copies elements between arrays with the checks and messages of HotSpot.
+fn system_arraycopy(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let source = non_null(vm, args[0])?;
+    let destination = non_null(vm, args[2])?;
+    let (source_position, destination_position, length) =
+        (int(args[1]), int(args[3]), int(args[4]));
+
+    let component = |vm: &Vm, array: ObjectRef| match &vm.class(vm.class_of(array)).kind {
+        ClassKind::Array(component) => Some(component.clone()),
+        _ => None,
+    };
+    let source_component = match component(vm, source) {
+        Some(component) => component,
+        None => {
+            let message = format!(
+                "arraycopy: source type {} is not an array",
+                vm.class(vm.class_of(source)).java_name()
+            );
+            return Err(vm.throw_new("java/lang/ArrayStoreException", Some(message)));
+        }
+    };
+    let destination_component = match component(vm, destination) {
+        Some(component) => component,
+        None => {
+            let message = format!(
+                "arraycopy: destination type {} is not an array",
+                vm.class(vm.class_of(destination)).java_name()
+            );
+            return Err(vm.throw_new("java/lang/ArrayStoreException", Some(message)));
+        }
+    };
+    let references = source_component.is_reference() && destination_component.is_reference();
+    if !references && source_component != destination_component {
+        let message = format!(
+            "arraycopy: type mismatch: can not copy {}[] into {}[]",
+            type_name(&source_component),
+            type_name(&destination_component)
+        );
+        return Err(vm.throw_new("java/lang/ArrayStoreException", Some(message)));
+    }
+
+    let describe = |component: &FieldType, length: usize| {
+        if component.is_reference() {
+            format!("object array[{}]", length)
+        } else {
+            format!("{}[{}]", type_name(component), length)
+        }
+    };
+    let source_length = vm.heap.get(source).array().map_or(0, ArrayData::len);
+    let destination_length = vm.heap.get(destination).array().map_or(0, ArrayData::len);
+    let message = if source_position < 0 {
+        Some(format!(
+            "arraycopy: source index {} out of bounds for {}",
+            source_position,
+            describe(&source_component, source_length)
+        ))
+    } else if destination_position < 0 {
+        Some(format!(
+            "arraycopy: destination index {} out of bounds for {}",
+            destination_position,
+            describe(&destination_component, destination_length)
+        ))
+    } else if length < 0 {
+        Some(format!("arraycopy: length {} is negative", length))
+    } else if source_position as usize + length as usize > source_length {
+        Some(format!(
+            "arraycopy: last source index {} out of bounds for {}",
+            source_position as usize + length as usize,
+            describe(&source_component, source_length)
+        ))
+    } else if destination_position as usize + length as usize > destination_length {
+        Some(format!(
+            "arraycopy: last destination index {} out of bounds for {}",
+            destination_position as usize + length as usize,
+            describe(&destination_component, destination_length)
+        ))
+    } else {
+        None
+    };
+    if let Some(message) = message {
+        return Err(vm.throw_new("java/lang/ArrayIndexOutOfBoundsException", Some(message)));
+    }
+
+    // Read everything first, so that overlapping copies within one array work
+    let (source_position, destination_position) =
+        (source_position as usize, destination_position as usize);
+    let values: Vec<Value> = {
+        let array = vm.heap.get(source).array().expect("Checked to be an array");
+        (0..length as usize)
+            .map(|index| {
+                array
+                    .get(source_position + index)
+                    .expect("Checked to be in bounds")
+            })
+            .collect()
+    };
+
+    // Elements of reference arrays are only checked when the component types differ
+    let element_class = if references && source_component != destination_component {
+        let destination_class = vm.class(vm.class_of(destination));
+        vm.array_component(destination_class, &destination_component)
+    } else {
+        None
+    };
+    for (index, value) in values.into_iter().enumerate() {
+        if let (Some(element_class), Value::Reference(Some(element))) = (element_class, value) {
+            if !vm.is_instance(element, element_class) {
+                let message = format!(
+                    "arraycopy: element type {} cannot be stored into {}",
+                    vm.class(vm.class_of(element)).java_name(),
+                    describe(&destination_component, destination_length)
+                );
+                return Err(vm.throw_new("java/lang/ArrayStoreException", Some(message)));
+            }
+        }
+        if let Some(array) = vm.heap.get_mut(destination).array_mut() {
+            array.set(destination_position + index, value);
+        }
+    }
+
+    Ok(None)
+}
+
+fn system_current_time_millis(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(vm.clock.current_time_millis())))
+}
+
+fn system_nano_time(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(vm.clock.nano_time())))
+}
+
+/// The value of a variable of the host's environment, or `null` if it is not
+/// set or not valid Unicode.
+fn system_getenv(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let name = String::from_utf16_lossy(&chars(vm, args[0])?);
+    match vm.host_interaction("getenv", &name, |_| std::env::var(&name).ok()) {
+        Some(value) => new_string(vm, value.encode_utf16().collect()),
+        None => Ok(Some(Value::NULL)),
+    }
+}
+
+fn system_line_separator(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let object = vm.intern_string("\n".encode_utf16().collect())?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+// =============================================================================
+// RUNTIME
+// =============================================================================
+
+fn runtime() -> BuiltinClass {
+    BuiltinClass::new("java/lang/Runtime", "java/lang/Object")
+        .static_field("currentRuntime", "Ljava/lang/Runtime;")
+        .static_method("<clinit>", "()V", runtime_clinit)
+        .static_method("getRuntime", "()Ljava/lang/Runtime;", runtime_get_runtime)
+        .method("exit", "(I)V", runtime_exit)
+        .method("halt", "(I)V", runtime_halt)
+        .method("availableProcessors", "()I", runtime_available_processors)
+        .method(
+            "exec",
+            "(Ljava/lang/String;)Ljava/lang/Process;",
+            runtime_exec,
+        )
+        .method(
+            "exec",
+            "([Ljava/lang/String;)Ljava/lang/Process;",
+            runtime_exec_array,
+        )
+        .method("freeMemory", "()J", runtime_free_memory)
+        .method("totalMemory", "()J", runtime_total_memory)
+        .method("maxMemory", "()J", runtime_max_memory)
+        .method("gc", "()V", system_gc)
+        .method(
+            "addShutdownHook",
+            "(Ljava/lang/Thread;)V",
+            runtime_add_shutdown_hook,
+        )
+        .method(
+            "removeShutdownHook",
+            "(Ljava/lang/Thread;)Z",
+            runtime_remove_shutdown_hook,
+        )
+}
+
+fn runtime_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let runtime = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Runtime")?;
+    let current = vm.instantiate(runtime)?;
+    vm.set_static_field(runtime, "currentRuntime", Value::Reference(Some(current)));
+    Ok(None)
+}
+
+fn runtime_get_runtime(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let runtime = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Runtime")?;
+    Ok(vm.static_field(runtime, "currentRuntime"))
+}
+
+fn runtime_exit(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    Err(Unwind::Exit(int(args[1])))
+}
+
+/// Exits without running the shutdown hooks.
+fn runtime_halt(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    vm.shutdown_hooks.clear();
+    Err(Unwind::Exit(int(args[1])))
+}
+
+/// The processors of the host, recorded and replayed like the other readings
+/// of the host, as thread pools size themselves from it.
+fn runtime_available_processors(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let processors = vm.host_interaction("availableProcessors", "", |_| {
+        let processors = std::thread::available_parallelism().map_or(1, |count| count.get());
+        Some(processors.to_string())
+    });
+    let processors = processors.and_then(|count| count.parse::<i32>().ok());
+    Ok(Some(Value::Int(processors.unwrap_or(1).max(1))))
+}
+
+fn runtime_free_memory(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let free = vm.total_memory().saturating_sub(vm.heap_stats().used_bytes);
+    Ok(Some(Value::Long(free as i64)))
+}
+
+fn runtime_total_memory(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(vm.total_memory() as i64)))
+}
+
+/// `Long.MAX_VALUE` without a heap limit, like the JDK without `-Xmx`.
+fn runtime_max_memory(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let max = vm.max_memory().map_or(i64::MAX, |max| max as i64);
+    Ok(Some(Value::Long(max)))
+}
+
+fn runtime_add_shutdown_hook(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let hook = non_null(vm, args[1])?;
+    if vm.shutdown_hooks.contains(&hook) {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("Hook previously registered".to_string()),
+        ));
+    }
+
+    vm.shutdown_hooks.push(hook);
+    Ok(None)
+}
+
+fn runtime_remove_shutdown_hook(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let hook = non_null(vm, args[1])?;
+    let registered = vm.shutdown_hooks.len();
+    vm.shutdown_hooks.retain(|registered| *registered != hook);
+    Ok(Some(Value::Int(
+        (vm.shutdown_hooks.len() != registered) as i32,
+    )))
+}
+
+// =============================================================================
+// THREAD
+// =============================================================================
+
+fn thread() -> BuiltinClass {
+    BuiltinClass::new("java/lang/Thread", "java/lang/Object")
+        .implements("java/lang/Runnable")
+        .field("target", "Ljava/lang/Runnable;")
+        .field("name", "Ljava/lang/String;")
+        .field("interrupted", "Z")
+        .field("permit", "Z")
+        .field("daemon", "Z")
+        .field("status", "I")
+        .field("tid", "J")
+        .field(
+            "uncaughtExceptionHandler",
+            "Ljava/lang/Thread$UncaughtExceptionHandler;",
+        )
+        .method("<init>", "()V", thread_init)
+        .method("<init>", "(Ljava/lang/Runnable;)V", thread_init)
+        .method(
+            "<init>",
+            "(Ljava/lang/Runnable;Ljava/lang/String;)V",
+            thread_init,
+        )
+        .method("<init>", "(Ljava/lang/String;)V", thread_init_with_name)
+        .method("getName", "()Ljava/lang/String;", thread_get_name)
+        .method("getId", "()J", thread_get_id)
+        .method("run", "()V", thread_run)
+        .static_method(
+            "currentThread",
+            "()Ljava/lang/Thread;",
+            thread_current_thread,
+        )
+        .method("start", "()V", thread_start)
+        .method("isAlive", "()Z", thread_is_alive)
+        .method("setDaemon", "(Z)V", thread_set_daemon)
+        .method("isDaemon", "()Z", thread_is_daemon)
+        .method("interrupt", "()V", thread_interrupt)
+        .method("isInterrupted", "()Z", thread_is_interrupted)
+        .static_method("interrupted", "()Z", thread_interrupted)
+        .static_method("sleep", "(J)V", thread_sleep)
+        .static_method("sleep", "(JI)V", thread_sleep)
+        .static_method("yield", "()V", thread_yield)
+        .static_method("onSpinWait", "()V", thread_on_spin_wait)
+        .method("join", "()V", thread_join)
+        .method("join", "(J)V", thread_join)
+        .method("join", "(JI)V", thread_join)
+        .method(
+            "setUncaughtExceptionHandler",
+            "(Ljava/lang/Thread$UncaughtExceptionHandler;)V",
+            thread_set_uncaught_exception_handler,
+        )
+        .method(
+            "getUncaughtExceptionHandler",
+            "()Ljava/lang/Thread$UncaughtExceptionHandler;",
+            thread_get_uncaught_exception_handler,
+        )
+        .static_method(
+            "setDefaultUncaughtExceptionHandler",
+            "(Ljava/lang/Thread$UncaughtExceptionHandler;)V",
+            thread_set_default_uncaught_exception_handler,
+        )
+        .static_method(
+            "getDefaultUncaughtExceptionHandler",
+            "()Ljava/lang/Thread$UncaughtExceptionHandler;",
+            thread_get_default_uncaught_exception_handler,
+        )
+}
+
+fn thread_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if let Some(target) = args.get(1) {
+        vm.set_field(this, "target", *target);
+    }
+    let name = match args.get(2) {
+        Some(name) => *name,
+        None => {
+            let name = format!("Thread-{}", vm.thread_names);
+            vm.thread_names += 1;
+            Value::Reference(Some(vm.create_string(name.encode_utf16().collect())?))
+        }
+    };
+    vm.set_field(this, "name", name);
+    vm.last_thread_id.0 += 1;
+    vm.set_field(this, "tid", Value::Long(vm.last_thread_id.0));
+    let current = current_thread(vm)?;
+    let daemon = vm.field(current, "daemon").unwrap_or(Value::Int(0));
+    vm.set_field(this, "daemon", daemon);
+    inherit_thread_locals(vm, this)?;
+    Ok(None)
+}
+
+fn thread_init_with_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    thread_init(vm, &[args[0], Value::NULL, args[1]])
+}
+
+fn thread_get_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "name"))
+}
+
+fn thread_get_id(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Long(vm.thread_id(this).0)))
+}
+
+fn thread_run(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    match vm.field(this, "target") {
+        Some(Value::Reference(Some(target))) => vm.invoke_virtual(target, "run", "()V", &[]),
+        _ => Ok(None),
+    }
+}
+
+/// The `Thread` being run, or the one of the main thread, named `main`.
+pub(crate) fn current_thread(vm: &mut Vm) -> Result<ObjectRef, Unwind> {
+    if let Some(&thread) = vm.running_threads.last() {
+        return Ok(thread);
+    }
+    if let Some(thread) = vm.main_thread {
+        return Ok(thread);
+    }
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Thread")?;
+    let thread = vm.instantiate(class)?;
+    vm.main_thread = Some(thread);
+    let name = vm.intern_string("main".encode_utf16().collect())?;
+    vm.set_field(thread, "name", Value::Reference(Some(name)));
+    Ok(thread)
+}
+
+/// Clears the interrupt of the current thread, returning whether it was
+/// interrupted.
+pub(crate) fn take_interrupt(vm: &mut Vm) -> Result<bool, Unwind> {
+    let thread = current_thread(vm)?;
+    let interrupted = vm.field(thread, "interrupted") == Some(Value::Int(1));
+    vm.set_field(thread, "interrupted", Value::Int(0));
+    Ok(interrupted)
+}
+
+/// Blocks the current thread at an interruptible point: throws
+/// `InterruptedException` if it was interrupted, otherwise lets the started
+/// threads run and sleeps for the timeout. Without one it returns once they
+/// ran, as no other thread could ever wake it up, which is one of the
+/// spurious wakeups Java allows.
+pub(crate) fn block(
+    vm: &mut Vm,
+    timeout: Option<Duration>,
+    message: Option<&str>,
+) -> Result<(), Unwind> {
+    if take_interrupt(vm)? {
+        let message = message.map(String::from);
+        return Err(vm.throw_new("java/lang/InterruptedException", message));
+    }
+    vm.run_started_threads()?;
+    match timeout {
+        Some(timeout) => vm.sleep(timeout),
+        None => Ok(()),
+    }
+}
+
+/// The timeout of the `(millis)` and `(millis, nanos)` arguments of the
+/// blocking methods, `None` for 0 meaning to wait forever.
+fn timeout(vm: &mut Vm, args: &[Value]) -> Result<Option<Duration>, Unwind> {
+    let millis = args.first().map_or(0, |&millis| long(millis));
+    let nanos = args.get(1).map_or(0, |&nanos| int(nanos));
+    if millis < 0 {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("timeout value is negative".to_string()),
+        ));
+    }
+    if !(0..=999_999).contains(&nanos) {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("nanosecond timeout value out of range".to_string()),
+        ));
+    }
+
+    let timeout = Duration::from_millis(millis as u64) + Duration::from_nanos(nanos as u64);
+    Ok(Some(timeout).filter(|timeout| !timeout.is_zero()))
+}
+
+fn thread_current_thread(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Reference(Some(current_thread(vm)?))))
+}
+
+fn thread_start(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if vm.thread_status(this) != ThreadStatus::New || vm.main_thread == Some(this) {
+        return Err(vm.throw_new("java/lang/IllegalThreadStateException", None));
+    }
+    vm.start_thread(this);
+    Ok(None)
+}
+
+fn is_alive(vm: &Vm, thread: ObjectRef) -> bool {
+    match vm.thread_status(thread) {
+        ThreadStatus::Started | ThreadStatus::Running => true,
+        ThreadStatus::New => vm.main_thread == Some(thread),
+        ThreadStatus::Terminated => false,
+    }
+}
+
+fn thread_is_alive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Int(is_alive(vm, this) as i32)))
+}
+
+fn thread_set_daemon(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if is_alive(vm, this) {
+        return Err(vm.throw_new("java/lang/IllegalThreadStateException", None));
+    }
+    vm.set_field(this, "daemon", args[1]);
+    Ok(None)
+}
+
+fn thread_is_daemon(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "daemon"))
+}
+
+fn thread_interrupt(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "interrupted", Value::Int(1));
+    Ok(None)
+}
+
+fn thread_is_interrupted(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "interrupted"))
+}
+
+fn thread_interrupted(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(take_interrupt(vm)? as i32)))
+}
+
+fn thread_sleep(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let timeout = timeout(vm, args)?;
+    block(
+        vm,
+        Some(timeout.unwrap_or_default()),
+        Some("sleep interrupted"),
+    )?;
+    Ok(None)
+}
+
+fn thread_yield(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    vm.yield_now()?;
+    Ok(None)
+}
+
+fn thread_on_spin_wait(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    std::hint::spin_loop();
+    Ok(None)
+}
+
+fn thread_set_uncaught_exception_handler(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "uncaughtExceptionHandler", args[1]);
+    Ok(None)
+}
+
+/// The handler of the thread, or null as there are no thread groups to
+/// stand in for a missing one.
+fn thread_get_uncaught_exception_handler(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "uncaughtExceptionHandler"))
+}
+
+fn thread_set_default_uncaught_exception_handler(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    vm.default_uncaught_handler = match args[0] {
+        Value::Reference(handler) => handler,
+        _ => None,
+    };
+    Ok(None)
+}
+
+fn thread_get_default_uncaught_exception_handler(
+    vm: &mut Vm,
+    _: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Reference(vm.default_uncaught_handler)))
+}
+
+/// Runs a started thread to completion, and otherwise returns at once
+/// unless a thread waits for itself to terminate.
+fn thread_join(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let timeout = timeout(vm, &args[1..])?;
+    if is_alive(vm, this) {
+        if take_interrupt(vm)? {
+            return Err(vm.throw_new("java/lang/InterruptedException", None));
+        }
+        if vm.thread_status(this) == ThreadStatus::Started {
+            vm.run_thread(this)?;
+        } else {
+            block(vm, timeout, None)?;
+        }
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// THREAD LOCAL
+// =============================================================================
+
+/// `java.lang.ThreadLocal`, whose values are kept in a map of each `Thread`
+/// keyed by the identity of the `ThreadLocal`. Unlike the JDK's, the map
+/// holds on to its keys until the thread terminates.
+fn thread_local() -> BuiltinClass {
+    BuiltinClass::new("java/lang/ThreadLocal", "java/lang/Object")
+        .method("<init>", "()V", object_init)
+        .method(
+            "initialValue",
+            "()Ljava/lang/Object;",
+            thread_local_initial_value,
+        )
+        .method("get", "()Ljava/lang/Object;", thread_local_get)
+        .method("set", "(Ljava/lang/Object;)V", thread_local_set)
+        .method("remove", "()V", thread_local_remove)
+        .static_method(
+            "withInitial",
+            "(Ljava/util/function/Supplier;)Ljava/lang/ThreadLocal;",
+            thread_local_with_initial,
+        )
+}
+
+/// The `ThreadLocal` of `ThreadLocal.withInitial`, initialized by its
+/// supplier.
+fn supplied_thread_local() -> BuiltinClass {
+    let mut class = BuiltinClass::new(
+        "java/lang/ThreadLocal$SuppliedThreadLocal",
+        "java/lang/ThreadLocal",
+    )
+    .field("supplier", "Ljava/util/function/Supplier;")
+    .method(
+        "initialValue",
+        "()Ljava/lang/Object;",
+        supplied_thread_local_initial_value,
+    );
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// `java.lang.InheritableThreadLocal`, whose values new threads take over
+/// from the thread creating them.
+fn inheritable_thread_local() -> BuiltinClass {
+    BuiltinClass::new("java/lang/InheritableThreadLocal", "java/lang/ThreadLocal")
+        .method("<init>", "()V", object_init)
+        .method(
+            "childValue",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+            inheritable_thread_local_child_value,
+        )
+}
+
+/// The value of the thread-local in the thread, `None` if it has none.
+fn thread_local_value(vm: &Vm, thread: ObjectRef, local: ObjectRef) -> Option<Value> {
+    match &vm.heap.get(thread).native {
+        NativeData::ThreadLocals(values) => values.get(&local).copied(),
+        _ => None,
+    }
+}
+
+/// Sets or, for `None`, removes the value of the thread-local in the
+/// thread.
+fn set_thread_local_value(vm: &mut Vm, thread: ObjectRef, local: ObjectRef, value: Option<Value>) {
+    let native = &mut vm.heap.get_mut(thread).native;
+    if !matches!(native, NativeData::ThreadLocals(_)) {
+        *native = NativeData::ThreadLocals(HashMap::new());
+    }
+    if let NativeData::ThreadLocals(values) = native {
+        match value {
+            Some(value) => values.insert(local, value),
+            None => values.remove(&local),
+        };
+    }
+}
+
+/// Gives the new thread the values of the current thread's inheritable
+/// thread-locals, as their `childValue` derives them.
+fn inherit_thread_locals(vm: &mut Vm, thread: ObjectRef) -> Result<(), Unwind> {
+    let parent = current_thread(vm)?;
+    let values = match &vm.heap.get(parent).native {
+        NativeData::ThreadLocals(values) => values.clone(),
+        _ => return Ok(()),
+    };
+    let inheritable = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/InheritableThreadLocal")?;
+    for (local, value) in values {
+        if vm.is_instance(local, inheritable) {
+            let descriptor = "(Ljava/lang/Object;)Ljava/lang/Object;";
+            let value = vm.invoke_virtual(local, "childValue", descriptor, &[value])?;
+            set_thread_local_value(vm, thread, local, value);
+        }
+    }
+    Ok(())
+}
+
+fn thread_local_initial_value(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::NULL))
+}
+
+/// The value in the current thread, set to the `initialValue()` on first
+/// use or after a removal.
+fn thread_local_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let thread = current_thread(vm)?;
+    if let Some(value) = thread_local_value(vm, thread, this) {
+        return Ok(Some(value));
+    }
+
+    let value = vm
+        .invoke_virtual(this, "initialValue", "()Ljava/lang/Object;", &[])?
+        .unwrap_or(Value::NULL);
+    set_thread_local_value(vm, thread, this, Some(value));
+    Ok(Some(value))
+}
+
+fn thread_local_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let thread = current_thread(vm)?;
+    set_thread_local_value(vm, thread, this, Some(args[1]));
+    Ok(None)
+}
+
+fn thread_local_remove(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let thread = current_thread(vm)?;
+    set_thread_local_value(vm, thread, this, None);
+    Ok(None)
+}
+
+fn thread_local_with_initial(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    non_null(vm, args[0])?;
+    let class = vm.load_class(
+        LoaderId::BOOTSTRAP,
+        "java/lang/ThreadLocal$SuppliedThreadLocal",
+    )?;
+    let local = vm.instantiate(class)?;
+    vm.set_field(local, "supplier", args[0]);
+    Ok(Some(Value::Reference(Some(local))))
+}
+
+fn supplied_thread_local_initial_value(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let supplier = vm.field(this, "supplier").unwrap_or(Value::NULL);
+    let supplier = non_null(vm, supplier)?;
+    vm.invoke_virtual(supplier, "get", "()Ljava/lang/Object;", &[])
+}
+
+fn inheritable_thread_local_child_value(
+    _: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    Ok(Some(args[1]))
+}
+
+// =============================================================================
+// CLASS VALUE
+// =============================================================================
+
+/// `java.lang.ClassValue`, keeping the values it computed in a map of its
+/// own by class. As classes are never unloaded, the values are collected
+/// along with the `ClassValue`.
+fn class_value() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/ClassValue", "java/lang/Object")
+        .method("<init>", "()V", object_init)
+        .abstract_method("computeValue", "(Ljava/lang/Class;)Ljava/lang/Object;")
+        .method(
+            "get",
+            "(Ljava/lang/Class;)Ljava/lang/Object;",
+            class_value_get,
+        )
+        .method("remove", "(Ljava/lang/Class;)V", class_value_remove);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn class_value_of(vm: &Vm, class_value: ObjectRef, class: ClassId) -> Option<Value> {
+    match &vm.heap.get(class_value).native {
+        NativeData::ClassValues(values) => values.get(&class).copied(),
+        _ => None,
+    }
+}
+
+/// The value for the class, computed by `computeValue` on first use or
+/// after a removal. A value stored while computing it, by a nested `get`,
+/// wins over the computed one.
+fn class_value_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let class = mirrored_class(vm, args[1])?;
+    if let Some(value) = class_value_of(vm, this, class) {
+        return Ok(Some(value));
+    }
+
+    let descriptor = "(Ljava/lang/Class;)Ljava/lang/Object;";
+    let value = vm
+        .invoke_virtual(this, "computeValue", descriptor, &[args[1]])?
+        .unwrap_or(Value::NULL);
+    let native = &mut vm.heap.get_mut(this).native;
+    if !matches!(native, NativeData::ClassValues(_)) {
+        *native = NativeData::ClassValues(HashMap::new());
+    }
+    match native {
+        NativeData::ClassValues(values) => Ok(Some(*values.entry(class).or_insert(value))),
+        _ => Ok(Some(value)),
+    }
+}
+
+fn class_value_remove(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let class = mirrored_class(vm, args[1])?;
+    if let NativeData::ClassValues(values) = &mut vm.heap.get_mut(this).native {
+        values.remove(&class);
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// THROWABLE
+// =============================================================================
+
+/// Exception classes without behaviour of their own, with their superclass.
+static EXCEPTIONS: [(&str, &str); 44] = [
+    ("java/lang/Exception", "java/lang/Throwable"),
+    ("java/lang/Error", "java/lang/Throwable"),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    (
+        "java/lang/ArithmeticException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IndexOutOfBoundsException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArrayIndexOutOfBoundsException",
+        "java/lang/IndexOutOfBoundsException",
+    ),
+    (
+        "java/lang/StringIndexOutOfBoundsException",
+        "java/lang/IndexOutOfBoundsException",
+    ),
+    (
+        "java/lang/NullPointerException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    (
+        "java/lang/ArrayStoreException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NegativeArraySizeException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalArgumentException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NumberFormatException",
+        "java/lang/IllegalArgumentException",
+    ),
+    (
+        "java/lang/IllegalStateException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalMonitorStateException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalThreadStateException",
+        "java/lang/IllegalArgumentException",
+    ),
+    (
+        "java/lang/UnsupportedOperationException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/SecurityException", "java/lang/RuntimeException"),
+    (
+        "java/lang/CloneNotSupportedException",
+        "java/lang/Exception",
+    ),
+    ("java/lang/InterruptedException", "java/lang/Exception"),
+    (
+        "java/lang/ReflectiveOperationException",
+        "java/lang/Exception",
+    ),
+    (
+        "java/lang/ClassNotFoundException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/InstantiationException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/IllegalAccessException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/NoSuchFieldException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/NoSuchMethodException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/ClassFormatError", "java/lang/LinkageError"),
+    (
+        "java/lang/UnsupportedClassVersionError",
+        "java/lang/ClassFormatError",
+    ),
+    ("java/lang/ClassCircularityError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    ("java/lang/VerifyError", "java/lang/LinkageError"),
+    (
+        "java/lang/IncompatibleClassChangeError",
+        "java/lang/LinkageError",
+    ),
+    (
+        "java/lang/NoSuchFieldError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/NoSuchMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/AbstractMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/InstantiationError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/IllegalAccessError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    ("java/lang/InternalError", "java/lang/VirtualMachineError"),
+    (
+        "java/lang/OutOfMemoryError",
+        "java/lang/VirtualMachineError",
+    ),
+    (
+        "java/lang/StackOverflowError",
+        "java/lang/VirtualMachineError",
+    ),
+    ("java/lang/BootstrapMethodError", "java/lang/LinkageError"),
+];
+
+fn throwable() -> BuiltinClass {
+    exception("java/lang/Throwable", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .field("detailMessage", "Ljava/lang/String;")
+        .field("cause", "Ljava/lang/Throwable;")
+        .method("getMessage", "()Ljava/lang/String;", throwable_get_message)
+        .method(
+            "getLocalizedMessage",
+            "()Ljava/lang/String;",
+            throwable_get_localized_message,
+        )
+        .method("getCause", "()Ljava/lang/Throwable;", throwable_get_cause)
+        .method(
+            "initCause",
+            "(Ljava/lang/Throwable;)Ljava/lang/Throwable;",
+            throwable_init_cause,
+        )
+        .method(
+            "fillInStackTrace",
+            "()Ljava/lang/Throwable;",
+            throwable_fill_in_stack_trace,
+        )
+        .method("toString", "()Ljava/lang/String;", throwable_to_string)
+        .method("printStackTrace", "()V", throwable_print_stack_trace)
+}
+
+/// A `Throwable` subclass with the four standard constructors.
+pub(crate) fn exception(name: &'static str, super_class: &'static str) -> BuiltinClass {
+    BuiltinClass::new(name, super_class)
+        .method("<init>", "()V", throwable_init)
+        .method("<init>", "(Ljava/lang/String;)V", throwable_init)
+        .method(
+            "<init>",
+            "(Ljava/lang/String;Ljava/lang/Throwable;)V",
+            throwable_init,
+        )
+        .method(
+            "<init>",
+            "(Ljava/lang/Throwable;)V",
+            throwable_init_with_cause,
+        )
+}
+
+/// `AssertionError`, with the constructors taking the detail of a failed
+/// `assert` statement besides the standard ones.
+fn assertion_error() -> BuiltinClass {
+    exception("java/lang/AssertionError", "java/lang/Error")
+        .method(
+            "<init>",
+            "(Ljava/lang/Object;)V",
+            assertion_error_init_object,
+        )
+        .method("<init>", "(Z)V", assertion_error_init_boolean)
+        .method("<init>", "(C)V", assertion_error_init_char)
+        .method("<init>", "(I)V", assertion_error_init_primitive)
+        .method("<init>", "(J)V", assertion_error_init_primitive)
+        .method("<init>", "(F)V", assertion_error_init_primitive)
+        .method("<init>", "(D)V", assertion_error_init_primitive)
+}
+
+/// Converts the detail to the message, which a `Throwable` detail is also the
+/// cause of.
+fn assertion_error_init_object(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let message = string_value_of_object(vm, &args[1..])?;
+    let throwable = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Throwable")?;
+    let cause = match args[1] {
+        Value::Reference(Some(detail)) if vm.is_instance(detail, throwable) => args[1],
+        _ => Value::NULL,
+    };
+    throwable_init(vm, &[args[0], message.unwrap_or(Value::NULL), cause])
+}
+
+fn assertion_error_init_boolean(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let message = string_value_of_boolean(vm, &args[1..])?;
+    throwable_init(vm, &[args[0], message.unwrap_or(Value::NULL)])
+}
+
+fn assertion_error_init_char(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let message = string_value_of_char(vm, &args[1..])?;
+    throwable_init(vm, &[args[0], message.unwrap_or(Value::NULL)])
+}
+
+fn assertion_error_init_primitive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let message = string_value_of_primitive(vm, &args[1..])?;
+    throwable_init(vm, &[args[0], message.unwrap_or(Value::NULL)])
+}
+
+fn throwable_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if let Some(message) = args.get(1) {
+        vm.set_field(this, "detailMessage", *message);
+    }
+    if let Some(cause) = args.get(2) {
+        vm.set_field(this, "cause", *cause);
+    }
+    vm.fill_in_stack_trace(this);
+    Ok(None)
+}
+
+fn throwable_init_with_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let message = match args[1] {
+        Value::Reference(Some(cause)) => {
+            vm.invoke_virtual(cause, "toString", "()Ljava/lang/String;", &[])?
+        }
+        _ => None,
+    };
+    vm.set_field(this, "detailMessage", message.unwrap_or(Value::NULL));
+    vm.set_field(this, "cause", args[1]);
+    vm.fill_in_stack_trace(this);
+    Ok(None)
+}
+
+fn throwable_get_message(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "detailMessage"))
+}
+
+fn throwable_get_localized_message(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.invoke_virtual(this, "getMessage", "()Ljava/lang/String;", &[])
+}
+
+fn throwable_get_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "cause"))
+}
+
+fn throwable_init_cause(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if args[1] == args[0] {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("Self-causation not permitted".to_string()),
+        ));
+    }
+    if vm.field(this, "cause") != Some(Value::NULL) {
+        return Err(vm.throw_new(
+            "java/lang/IllegalStateException",
+            Some("Can't overwrite cause".to_string()),
+        ));
+    }
+
+    vm.set_field(this, "cause", args[1]);
+    Ok(Some(args[0]))
+}
+
+fn throwable_fill_in_stack_trace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.fill_in_stack_trace(this);
+    Ok(Some(args[0]))
+}
+
+fn throwable_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let string = throwable_description(vm, this)?;
+    new_string(vm, string.encode_utf16().collect())
+}
+
+fn throwable_print_stack_trace(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let output = stack_trace(vm, this)?;
+    vm.write_stream(StandardStream::Err, output.as_bytes())
+        .map_err(|error| Unwind::Error(error.into()))?;
+    Ok(None)
+}
+
+/// The stack trace of the throwable and its causes, as `printStackTrace`
+/// prints it.
+pub(crate) fn stack_trace(vm: &mut Vm, this: ObjectRef) -> Result<String, Unwind> {
+    let mut output = String::new();
+    let mut current = Some(this);
+    while let Some(throwable) = current {
+        if throwable != this {
+            output.push_str("Caused by: ");
+        }
+        let description = vm.invoke_virtual(throwable, "toString", "()Ljava/lang/String;", &[])?;
+        output.push_str(&object_to_string(vm, description.unwrap_or(Value::NULL))?);
+        output.push('\n');
+        if let NativeData::Backtrace(backtrace) = &vm.heap.get(throwable).native {
+            for element in backtrace {
+                output.push_str(&format!("\tat {}\n", element));
+            }
+        }
+
+        current = match vm.field(throwable, "cause") {
+            Some(Value::Reference(Some(cause))) if cause != throwable => Some(cause),
+            _ => None,
+        };
+    }
+    Ok(output)
+}
+
+/// The `toString()` of a throwable: its class name, followed by its
+/// localized message if it has one.
+pub(crate) fn throwable_description(vm: &mut Vm, throwable: ObjectRef) -> Result<String, Unwind> {
+    let name = vm.class(vm.class_of(throwable)).java_name();
+    let message = vm.invoke_virtual(
+        throwable,
+        "getLocalizedMessage",
+        "()Ljava/lang/String;",
+        &[],
+    )?;
+    match message {
+        Some(Value::Reference(Some(message))) => {
+            let message = chars(vm, Value::Reference(Some(message)))?;
+            Ok(format!("{}: {}", name, String::from_utf16_lossy(&message)))
+        }
+        _ => Ok(name),
+    }
+}
@@ -0,0 +1,821 @@
+use std::convert::TryFrom;
+
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::{double, fdlibm, float, int, long, BuiltinClass, NativeFn};
+use crate::vm::value::Value;
+use crate::vm::{Unwind, Vm};
+
+type Native = (&'static str, &'static str, &'static str, NativeFn);
+
+/// The natives of `java.lang.StrictMath`. Its transcendental functions are
+/// specified as fdlibm's results, which [fdlibm] reproduces bit for bit; the
+/// others are correctly rounded.
+pub fn natives() -> Vec<Native> {
+    let mut natives = transcendentals("java/lang/StrictMath");
+    natives.push(("java/lang/StrictMath", "sqrt", "(D)D", sqrt));
+    natives.push((
+        "java/lang/StrictMath",
+        "IEEEremainder",
+        "(DD)D",
+        ieee_remainder,
+    ));
+    natives
+}
+
+/// The methods of `java.lang.Math` and `java.lang.StrictMath` the VM runs
+/// instead of interpreting their bytecode, all giving exactly the results of
+/// the JDK's: the correctly rounded and integer operations, `Math`'s
+/// transcendental functions, which delegate to `StrictMath`'s, and the
+/// fdlibm functions the JDK ports to Java. Members of newer JDKs run from
+/// their class files.
+pub fn intrinsics() -> Vec<Native> {
+    let mut intrinsics = transcendentals("java/lang/Math");
+    for class in ["java/lang/Math", "java/lang/StrictMath"] {
+        intrinsics.extend(powers(class));
+        intrinsics.extend(exact(class));
+    }
+    intrinsics
+}
+
+/// The `java.lang.Math` and `java.lang.StrictMath` the VM defines when no
+/// class path provides the JDK's, with the methods of JDK 17's.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        math("java/lang/Math", math_clinit),
+        math("java/lang/StrictMath", strict_math_clinit),
+    ]
+}
+
+fn math(name: &'static str, clinit: NativeFn) -> BuiltinClass {
+    let methods = transcendentals(name)
+        .into_iter()
+        .chain(powers(name))
+        .chain(exact(name));
+    methods.fold(
+        BuiltinClass::new(name, "java/lang/Object")
+            .static_field("E", "D")
+            .static_field("PI", "D")
+            .static_method("<clinit>", "()V", clinit),
+        |class, (_, method, descriptor, native)| class.static_method(method, descriptor, native),
+    )
+}
+
+fn math_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    set_constants(vm, "java/lang/Math")
+}
+
+fn strict_math_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    set_constants(vm, "java/lang/StrictMath")
+}
+
+fn set_constants(vm: &mut Vm, name: &str) -> Result<Option<Value>, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, name)?;
+    vm.set_static_field(class, "E", Value::Double(std::f64::consts::E));
+    vm.set_static_field(class, "PI", Value::Double(std::f64::consts::PI));
+    Ok(None)
+}
+
+fn transcendentals(class: &'static str) -> Vec<Native> {
+    vec![
+        (class, "sin", "(D)D", sin),
+        (class, "cos", "(D)D", cos),
+        (class, "tan", "(D)D", tan),
+        (class, "asin", "(D)D", asin),
+        (class, "acos", "(D)D", acos),
+        (class, "atan", "(D)D", atan),
+        (class, "atan2", "(DD)D", atan2),
+        (class, "sinh", "(D)D", sinh),
+        (class, "cosh", "(D)D", cosh),
+        (class, "tanh", "(D)D", tanh),
+        (class, "expm1", "(D)D", expm1),
+        (class, "log", "(D)D", log),
+        (class, "log10", "(D)D", log10),
+        (class, "log1p", "(D)D", log1p),
+    ]
+}
+
+fn powers(class: &'static str) -> Vec<Native> {
+    vec![
+        (class, "exp", "(D)D", exp),
+        (class, "pow", "(DD)D", pow),
+        (class, "cbrt", "(D)D", cbrt),
+        (class, "hypot", "(DD)D", hypot),
+    ]
+}
+
+fn exact(class: &'static str) -> Vec<Native> {
+    vec![
+        (class, "toRadians", "(D)D", to_radians),
+        (class, "toDegrees", "(D)D", to_degrees),
+        (class, "sqrt", "(D)D", sqrt),
+        (class, "IEEEremainder", "(DD)D", ieee_remainder),
+        (class, "ceil", "(D)D", ceil),
+        (class, "floor", "(D)D", floor),
+        (class, "rint", "(D)D", rint),
+        (class, "round", "(F)I", round_float),
+        (class, "round", "(D)J", round_double),
+        (class, "random", "()D", random),
+        (class, "fma", "(FFF)F", fma_float),
+        (class, "fma", "(DDD)D", fma_double),
+        (class, "abs", "(I)I", abs_int),
+        (class, "abs", "(J)J", abs_long),
+        (class, "abs", "(F)F", abs_float),
+        (class, "abs", "(D)D", abs_double),
+        (class, "absExact", "(I)I", abs_exact_int),
+        (class, "absExact", "(J)J", abs_exact_long),
+        (class, "max", "(II)I", max_int),
+        (class, "max", "(JJ)J", max_long),
+        (class, "max", "(FF)F", max_float),
+        (class, "max", "(DD)D", max_double),
+        (class, "min", "(II)I", min_int),
+        (class, "min", "(JJ)J", min_long),
+        (class, "min", "(FF)F", min_float),
+        (class, "min", "(DD)D", min_double),
+        (class, "signum", "(F)F", signum_float),
+        (class, "signum", "(D)D", signum_double),
+        (class, "copySign", "(FF)F", copy_sign_float),
+        (class, "copySign", "(DD)D", copy_sign_double),
+        (class, "ulp", "(F)F", ulp_float),
+        (class, "ulp", "(D)D", ulp_double),
+        (class, "getExponent", "(F)I", get_exponent_float),
+        (class, "getExponent", "(D)I", get_exponent_double),
+        (class, "nextUp", "(F)F", next_up_float),
+        (class, "nextUp", "(D)D", next_up_double),
+        (class, "nextDown", "(F)F", next_down_float),
+        (class, "nextDown", "(D)D", next_down_double),
+        (class, "nextAfter", "(FD)F", next_after_float),
+        (class, "nextAfter", "(DD)D", next_after_double),
+        (class, "scalb", "(FI)F", scalb_float),
+        (class, "scalb", "(DI)D", scalb_double),
+        (class, "addExact", "(II)I", add_exact_int),
+        (class, "addExact", "(JJ)J", add_exact_long),
+        (class, "subtractExact", "(II)I", subtract_exact_int),
+        (class, "subtractExact", "(JJ)J", subtract_exact_long),
+        (class, "multiplyExact", "(II)I", multiply_exact_int),
+        (class, "multiplyExact", "(JI)J", multiply_exact_long),
+        (class, "multiplyExact", "(JJ)J", multiply_exact_long),
+        (class, "multiplyFull", "(II)J", multiply_full),
+        (class, "multiplyHigh", "(JJ)J", multiply_high),
+        (class, "incrementExact", "(I)I", increment_exact_int),
+        (class, "incrementExact", "(J)J", increment_exact_long),
+        (class, "decrementExact", "(I)I", decrement_exact_int),
+        (class, "decrementExact", "(J)J", decrement_exact_long),
+        (class, "negateExact", "(I)I", negate_exact_int),
+        (class, "negateExact", "(J)J", negate_exact_long),
+        (class, "toIntExact", "(J)I", to_int_exact),
+        (class, "floorDiv", "(II)I", floor_div_int),
+        (class, "floorDiv", "(JI)J", floor_div_long),
+        (class, "floorDiv", "(JJ)J", floor_div_long),
+        (class, "floorMod", "(II)I", floor_mod_int),
+        (class, "floorMod", "(JI)I", floor_mod_long_int),
+        (class, "floorMod", "(JJ)J", floor_mod_long),
+    ]
+}
+
+/// The `long` argument at `index`, widening an `int` for the overloads taking
+/// a `long` and an `int`.
+fn widened(args: &[Value], index: usize) -> i64 {
+    match args[index] {
+        Value::Int(value) => value as i64,
+        value => long(value),
+    }
+}
+
+fn overflow(vm: &mut Vm, type_name: &str) -> Unwind {
+    vm.throw_new(
+        "java/lang/ArithmeticException",
+        Some(format!("{} overflow", type_name)),
+    )
+}
+
+fn by_zero(vm: &mut Vm) -> Unwind {
+    vm.throw_new("java/lang/ArithmeticException", Some("/ by zero".into()))
+}
+
+fn returning_int(value: i32) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(value)))
+}
+
+fn returning_long(value: i64) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(value)))
+}
+
+fn returning_float(value: f32) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Float(value)))
+}
+
+fn returning_double(value: f64) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Double(value)))
+}
+
+// =============================================================================
+// TRANSCENDENTAL FUNCTIONS
+// =============================================================================
+
+fn sin(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::sin(double(args[0])))
+}
+
+fn cos(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::cos(double(args[0])))
+}
+
+fn tan(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::tan(double(args[0])))
+}
+
+fn asin(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::asin(double(args[0])))
+}
+
+fn acos(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::acos(double(args[0])))
+}
+
+fn atan(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::atan(double(args[0])))
+}
+
+fn atan2(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::atan2(double(args[0]), double(args[1])))
+}
+
+fn sinh(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::sinh(double(args[0])))
+}
+
+fn cosh(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::cosh(double(args[0])))
+}
+
+fn tanh(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::tanh(double(args[0])))
+}
+
+fn exp(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::exp(double(args[0])))
+}
+
+fn expm1(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::expm1(double(args[0])))
+}
+
+fn log(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::log(double(args[0])))
+}
+
+fn log10(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::log10(double(args[0])))
+}
+
+fn log1p(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::log1p(double(args[0])))
+}
+
+fn pow(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::pow(double(args[0]), double(args[1])))
+}
+
+fn cbrt(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::cbrt(double(args[0])))
+}
+
+fn hypot(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(fdlibm::hypot(double(args[0]), double(args[1])))
+}
+
+/// Multiplies by the rounded ratio like the JDK does, which differs in the
+/// last bit from Rust's `to_radians` for some angles.
+fn to_radians(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]) * 0.017453292519943295)
+}
+
+fn to_degrees(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]) * 57.29577951308232)
+}
+
+// =============================================================================
+// ROUNDING AND REMAINDERS
+// =============================================================================
+
+fn sqrt(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).sqrt())
+}
+
+fn ceil(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).ceil())
+}
+
+fn floor(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).floor())
+}
+
+fn rint(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).round_ties_even())
+}
+
+/// Rounds half up, taking the fraction from the floor so that the largest
+/// double below one half still rounds to zero. The cast saturates and maps
+/// NaN to zero, as Java requires.
+fn round_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = float(args[0]);
+    let floor = value.floor();
+    let rounded = if value - floor >= 0.5 {
+        floor + 1.0
+    } else {
+        floor
+    };
+    returning_int(rounded as i32)
+}
+
+fn round_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = double(args[0]);
+    let floor = value.floor();
+    let rounded = if value - floor >= 0.5 {
+        floor + 1.0
+    } else {
+        floor
+    };
+    returning_long(rounded as i64)
+}
+
+/// The remainder of the division rounding the quotient to the nearest even
+/// integer, computed like fdlibm's `__ieee754_remainder`.
+fn ieee_remainder(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (double(args[0]), double(args[1]));
+    if dividend.is_nan() || divisor.is_nan() || dividend.is_infinite() || divisor == 0.0 {
+        return returning_double(f64::NAN);
+    }
+    if divisor.is_infinite() {
+        return returning_double(dividend);
+    }
+
+    let negative = dividend.is_sign_negative();
+    let divisor = divisor.abs();
+    // Reduce to below twice the divisor, which keeps the parity of the
+    // quotient.
+    let mut remainder = if divisor <= f64::MAX / 2.0 {
+        dividend % (divisor + divisor)
+    } else {
+        dividend
+    };
+    if remainder.abs() == divisor {
+        return returning_double(if negative { -0.0 } else { 0.0 });
+    }
+    remainder = remainder.abs();
+    if divisor < 2.0 * f64::MIN_POSITIVE {
+        if remainder + remainder > divisor {
+            remainder -= divisor;
+            if remainder + remainder >= divisor {
+                remainder -= divisor;
+            }
+        }
+    } else {
+        let half = 0.5 * divisor;
+        if remainder > half {
+            remainder -= divisor;
+            if remainder >= half {
+                remainder -= divisor;
+            }
+        }
+    }
+    returning_double(if negative { -remainder } else { remainder })
+}
+
+fn fma_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_float(float(args[0]).mul_add(float(args[1]), float(args[2])))
+}
+
+fn fma_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).mul_add(double(args[1]), double(args[2])))
+}
+
+// =============================================================================
+// RANDOM
+// =============================================================================
+
+/// The multiplier, increment and mask of `java.util.Random`'s linear
+/// congruential generator.
+const RANDOM_MULTIPLIER: i64 = 0x5_DEEC_E66D;
+const RANDOM_ADDEND: i64 = 0xB;
+const RANDOM_MASK: i64 = (1 << 48) - 1;
+
+/// Draws from a generator behaving like the `java.util.Random` the JDK
+/// creates for `Math.random`, seeded from the VM's clock so that runs on a
+/// virtual clock repeat their numbers.
+fn random(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let mut seed = match vm.random_seed {
+        Some(seed) => seed,
+        None => {
+            let uniquifier = 8682522807148012i64.wrapping_mul(1181783497276652981);
+            (uniquifier ^ vm.clock.nano_time() ^ RANDOM_MULTIPLIER) & RANDOM_MASK
+        }
+    };
+    let mut next = |bits: u32| {
+        seed = (seed
+            .wrapping_mul(RANDOM_MULTIPLIER)
+            .wrapping_add(RANDOM_ADDEND))
+            & RANDOM_MASK;
+        seed >> (48 - bits)
+    };
+    let high = next(26);
+    let low = next(27);
+    vm.random_seed = Some(seed);
+    returning_double(((high << 27) + low) as f64 * (1.0 / (1i64 << 53) as f64))
+}
+
+// =============================================================================
+// SIGNS, EXTREMES AND NEIGHBOURS
+// =============================================================================
+
+fn abs_int(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_int(int(args[0]).wrapping_abs())
+}
+
+fn abs_long(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_long(long(args[0]).wrapping_abs())
+}
+
+fn abs_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_float(float(args[0]).abs())
+}
+
+fn abs_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).abs())
+}
+
+fn abs_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_abs() {
+        Some(value) => returning_int(value),
+        None => Err(vm.throw_new(
+            "java/lang/ArithmeticException",
+            Some("Overflow to represent absolute value of Integer.MIN_VALUE".into()),
+        )),
+    }
+}
+
+fn abs_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_abs() {
+        Some(value) => returning_long(value),
+        None => Err(vm.throw_new(
+            "java/lang/ArithmeticException",
+            Some("Overflow to represent absolute value of Long.MIN_VALUE".into()),
+        )),
+    }
+}
+
+fn max_int(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_int(int(args[0]).max(int(args[1])))
+}
+
+fn max_long(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_long(long(args[0]).max(long(args[1])))
+}
+
+fn min_int(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_int(int(args[0]).min(int(args[1])))
+}
+
+fn min_long(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_long(long(args[0]).min(long(args[1])))
+}
+
+/// The larger value, NaN if either is and positive zero over negative zero,
+/// where Rust's `max` would ignore NaN and pick either zero.
+fn max_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (a, b) = (float(args[0]), float(args[1]));
+    returning_float(match (a, b) {
+        _ if a.is_nan() || b.is_nan() => f32::NAN,
+        _ if a == 0.0 && b == 0.0 => f32::from_bits(a.to_bits() & b.to_bits()),
+        _ if a >= b => a,
+        _ => b,
+    })
+}
+
+fn max_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (a, b) = (double(args[0]), double(args[1]));
+    returning_double(match (a, b) {
+        _ if a.is_nan() || b.is_nan() => f64::NAN,
+        _ if a == 0.0 && b == 0.0 => f64::from_bits(a.to_bits() & b.to_bits()),
+        _ if a >= b => a,
+        _ => b,
+    })
+}
+
+/// The smaller value, NaN if either is and negative zero under positive
+/// zero.
+fn min_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (a, b) = (float(args[0]), float(args[1]));
+    returning_float(match (a, b) {
+        _ if a.is_nan() || b.is_nan() => f32::NAN,
+        _ if a == 0.0 && b == 0.0 => f32::from_bits(a.to_bits() | b.to_bits()),
+        _ if a <= b => a,
+        _ => b,
+    })
+}
+
+fn min_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (a, b) = (double(args[0]), double(args[1]));
+    returning_double(match (a, b) {
+        _ if a.is_nan() || b.is_nan() => f64::NAN,
+        _ if a == 0.0 && b == 0.0 => f64::from_bits(a.to_bits() | b.to_bits()),
+        _ if a <= b => a,
+        _ => b,
+    })
+}
+
+/// One with the sign of the argument, which is returned as is when zero or
+/// NaN.
+fn signum_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = float(args[0]);
+    returning_float(if value == 0.0 || value.is_nan() {
+        value
+    } else {
+        1.0f32.copysign(value)
+    })
+}
+
+fn signum_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = double(args[0]);
+    returning_double(if value == 0.0 || value.is_nan() {
+        value
+    } else {
+        1.0f64.copysign(value)
+    })
+}
+
+fn copy_sign_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_float(float(args[0]).copysign(float(args[1])))
+}
+
+fn copy_sign_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).copysign(double(args[1])))
+}
+
+/// The distance to the next larger magnitude, the one below for the largest
+/// finite value.
+fn ulp_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = float(args[0]).abs();
+    returning_float(match value {
+        _ if !value.is_finite() => value,
+        f32::MAX => value - value.next_down(),
+        _ => value.next_up() - value,
+    })
+}
+
+fn ulp_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = double(args[0]).abs();
+    returning_double(match value {
+        _ if !value.is_finite() => value,
+        f64::MAX => value - value.next_down(),
+        _ => value.next_up() - value,
+    })
+}
+
+/// The unbiased exponent, one above the largest for infinities and NaN and
+/// one below the smallest for zeros and subnormals.
+fn get_exponent_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_int(((float(args[0]).to_bits() >> 23) & 0xff) as i32 - 127)
+}
+
+fn get_exponent_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_int(((double(args[0]).to_bits() >> 52) & 0x7ff) as i32 - 1023)
+}
+
+fn next_up_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_float(float(args[0]).next_up())
+}
+
+fn next_up_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).next_up())
+}
+
+fn next_down_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_float(float(args[0]).next_down())
+}
+
+fn next_down_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_double(double(args[0]).next_down())
+}
+
+/// The neighbour of `start` towards `direction`, which is returned when the
+/// two are equal.
+fn next_after_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (start, direction) = (float(args[0]), double(args[1]));
+    returning_float(match (start as f64).partial_cmp(&direction) {
+        Some(std::cmp::Ordering::Greater) => start.next_down(),
+        Some(std::cmp::Ordering::Less) => start.next_up(),
+        Some(std::cmp::Ordering::Equal) => direction as f32,
+        None => f32::NAN,
+    })
+}
+
+fn next_after_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (start, direction) = (double(args[0]), double(args[1]));
+    returning_double(match start.partial_cmp(&direction) {
+        Some(std::cmp::Ordering::Greater) => start.next_down(),
+        Some(std::cmp::Ordering::Less) => start.next_up(),
+        Some(std::cmp::Ordering::Equal) => direction,
+        None => f64::NAN,
+    })
+}
+
+/// Two to the power, for powers within the normal exponents.
+fn power_of_two(exponent: i32) -> f64 {
+    f64::from_bits(((exponent + 1023) as u64) << 52)
+}
+
+/// `value * 2^scale`, scaling in the same steps as the JDK so that results
+/// rounded into the subnormals agree.
+fn scalb_double(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    // Beyond it any finite non-zero value overflows or underflows.
+    const MAX_SCALE: i32 = 1023 + 1022 + 53 + 1;
+    let (mut value, scale) = (double(args[0]), int(args[1]));
+    let (mut scale, increment, step) = if scale < 0 {
+        (scale.max(-MAX_SCALE), -512, power_of_two(-512))
+    } else {
+        (scale.min(MAX_SCALE), 512, power_of_two(512))
+    };
+    // The remainder of the scale by 512, with the sign of the scale.
+    let bias = ((scale >> 8) as u32 >> 23) as i32;
+    let adjustment = ((scale + bias) & 511) - bias;
+    value *= power_of_two(adjustment);
+    scale -= adjustment;
+    while scale != 0 {
+        value *= step;
+        scale -= increment;
+    }
+    returning_double(value)
+}
+
+fn scalb_float(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    const MAX_SCALE: i32 = 127 + 126 + 24 + 1;
+    let scale = int(args[1]).clamp(-MAX_SCALE, MAX_SCALE);
+    returning_float((float(args[0]) as f64 * power_of_two(scale)) as f32)
+}
+
+// =============================================================================
+// EXACT AND FLOORED INTEGER ARITHMETIC
+// =============================================================================
+
+fn add_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_add(int(args[1])) {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn add_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_add(long(args[1])) {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn subtract_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_sub(int(args[1])) {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn subtract_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_sub(long(args[1])) {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn multiply_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_mul(int(args[1])) {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn multiply_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_mul(widened(args, 1)) {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn multiply_full(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_long(int(args[0]) as i64 * int(args[1]) as i64)
+}
+
+fn multiply_high(_: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    returning_long(((long(args[0]) as i128 * long(args[1]) as i128) >> 64) as i64)
+}
+
+fn increment_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_add(1) {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn increment_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_add(1) {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn decrement_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_sub(1) {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn decrement_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_sub(1) {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn negate_exact_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match int(args[0]).checked_neg() {
+        Some(value) => returning_int(value),
+        None => Err(overflow(vm, "integer")),
+    }
+}
+
+fn negate_exact_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match long(args[0]).checked_neg() {
+        Some(value) => returning_long(value),
+        None => Err(overflow(vm, "long")),
+    }
+}
+
+fn to_int_exact(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    match i32::try_from(long(args[0])) {
+        Ok(value) => returning_int(value),
+        Err(_) => Err(overflow(vm, "integer")),
+    }
+}
+
+/// The quotient rounded towards negative infinity, wrapping like `idiv` for
+/// the minimum value divided by minus one.
+fn floor_div(dividend: i64, divisor: i64) -> i64 {
+    let quotient = dividend.wrapping_div(divisor);
+    if (dividend ^ divisor) < 0 && quotient.wrapping_mul(divisor) != dividend {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+/// The remainder with the sign of the divisor.
+fn floor_mod(dividend: i64, divisor: i64) -> i64 {
+    let remainder = dividend.wrapping_rem(divisor);
+    if (remainder ^ divisor) < 0 && remainder != 0 {
+        remainder + divisor
+    } else {
+        remainder
+    }
+}
+
+fn floor_div_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (int(args[0]), int(args[1]));
+    if divisor == 0 {
+        return Err(by_zero(vm));
+    }
+    // Computed on longs, where only `i32::MIN / -1` exceeds the range and
+    // wraps back as `idiv` does.
+    returning_int(floor_div(dividend as i64, divisor as i64) as i32)
+}
+
+fn floor_div_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (long(args[0]), widened(args, 1));
+    if divisor == 0 {
+        return Err(by_zero(vm));
+    }
+    returning_long(floor_div(dividend, divisor))
+}
+
+fn floor_mod_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (int(args[0]), int(args[1]));
+    if divisor == 0 {
+        return Err(by_zero(vm));
+    }
+    returning_int(floor_mod(dividend as i64, divisor as i64) as i32)
+}
+
+fn floor_mod_long_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (long(args[0]), int(args[1]) as i64);
+    if divisor == 0 {
+        return Err(by_zero(vm));
+    }
+    returning_int(floor_mod(dividend, divisor) as i32)
+}
+
+fn floor_mod_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (dividend, divisor) = (long(args[0]), long(args[1]));
+    if divisor == 0 {
+        return Err(by_zero(vm));
+    }
+    returning_long(floor_mod(dividend, divisor))
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use crate::class::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::vm::heap::ArrayData;
+use crate::vm::policy::Permission;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+pub mod charset;
+pub mod concurrent;
+mod fdlibm;
+pub mod io;
+pub mod lang;
+pub mod math;
+pub mod net;
+pub mod nio;
+pub mod process;
+pub mod reference;
+pub mod reflect;
+pub mod util;
+pub mod zip;
+
+// =============================================================================
+// NATIVE METHODS
+// =============================================================================
+
+/// The Rust implementation of a Java method. Receives the receiver (for
+/// instance methods) followed by the arguments, and returns `None` for `void`
+/// methods.
+pub type NativeFn = fn(&mut Vm, &[Value]) -> Result<Option<Value>, Unwind>;
+
+/// Native method implementations by declaring class, name and descriptor,
+/// together with the classes built into the VM, those it falls back to when
+/// the class path lacks them, and the intrinsics replacing the bytecode of
+/// JDK methods.
+pub struct NativeRegistry {
+    methods: HashMap<(String, String, String), NativeFn>,
+    intrinsics: HashMap<(String, String, String), NativeFn>,
+    builtins: HashMap<&'static str, BuiltinClass>,
+    fallbacks: HashMap<&'static str, BuiltinClass>,
+}
+
+impl NativeRegistry {
+    /// A registry knowing the VM's built-in classes.
+    pub fn with_builtins() -> NativeRegistry {
+        let mut builtins = HashMap::new();
+        for class in lang::classes()
+            .into_iter()
+            .chain(reference::classes())
+            .chain(reflect::classes())
+            .chain(concurrent::classes())
+            .chain(io::classes())
+            .chain(charset::classes())
+            .chain(nio::classes())
+            .chain(zip::classes())
+            .chain(process::classes())
+            .chain(util::classes())
+        {
+            builtins.insert(class.name, class);
+        }
+        let fallbacks = math::classes()
+            .into_iter()
+            .chain(net::classes())
+            .chain(util::fallbacks())
+            .map(|class| (class.name, class))
+            .collect();
+
+        let mut registry = NativeRegistry {
+            methods: HashMap::new(),
+            intrinsics: HashMap::new(),
+            builtins,
+            fallbacks,
+        };
+        for (class, name, descriptor, native) in lang::natives()
+            .into_iter()
+            .chain(concurrent::natives())
+            .chain(math::natives())
+        {
+            registry.register(class, name, descriptor, native);
+        }
+        for (class, name, descriptor, intrinsic) in math::intrinsics() {
+            registry.intrinsics.insert(
+                (class.to_string(), name.to_string(), descriptor.to_string()),
+                intrinsic,
+            );
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, class: &str, name: &str, descriptor: &str, native: NativeFn) {
+        self.methods.insert(
+            (class.to_string(), name.to_string(), descriptor.to_string()),
+            native,
+        );
+    }
+
+    /// The registered implementation of the method; for built-in classes
+    /// falling back to the VM's own one.
+    pub fn lookup(&self, class: &str, name: &str, descriptor: &str) -> Option<NativeFn> {
+        let key = (class.to_string(), name.to_string(), descriptor.to_string());
+        self.methods.get(&key).copied().or_else(|| {
+            self.builtins
+                .get(class)
+                .or_else(|| self.fallbacks.get(class))?
+                .methods
+                .iter()
+                .find(|method| method.name == name && method.descriptor == descriptor)?
+                .native
+        })
+    }
+
+    /// The permission the policy must grant before the native or built-in
+    /// method of the class runs, for those reaching the host system.
+    pub fn permission(&self, class: &str, name: &str) -> Option<Permission<'static>> {
+        Permission::for_native(class, name)
+    }
+
+    /// The implementation running in place of the bytecode of a method of a
+    /// class the bootstrap loader defined, if the VM has one.
+    pub fn intrinsic(&self, class: &str, name: &str, descriptor: &str) -> Option<NativeFn> {
+        let key = (class.to_string(), name.to_string(), descriptor.to_string());
+        self.intrinsics.get(&key).copied()
+    }
+
+    pub fn builtin(&self, name: &str) -> Option<&BuiltinClass> {
+        self.builtins.get(name)
+    }
+
+    /// The built-in class defined in place of one no class path provides.
+    pub fn fallback(&self, name: &str) -> Option<&BuiltinClass> {
+        self.fallbacks.get(name)
+    }
+}
+
+// =============================================================================
+// BUILT-IN CLASSES
+// =============================================================================
+
+/// A class implemented by the VM itself instead of being loaded from a class
+/// file, like the core of `java.lang`.
+#[derive(Clone)]
+pub struct BuiltinClass {
+    pub name: &'static str,
+    pub super_class: Option<&'static str>,
+    pub interfaces: Vec<&'static str>,
+    pub access_flags: ClassAccessFlags,
+    pub fields: Vec<BuiltinField>,
+    pub methods: Vec<BuiltinMethod>,
+}
+
+#[derive(Clone)]
+pub struct BuiltinField {
+    pub name: &'static str,
+    pub descriptor: &'static str,
+    pub access_flags: FieldAccessFlags,
+}
+
+#[derive(Clone)]
+pub struct BuiltinMethod {
+    pub name: &'static str,
+    pub descriptor: &'static str,
+    pub access_flags: MethodAccessFlags,
+    /// `None` for abstract methods.
+    pub native: Option<NativeFn>,
+}
+
+impl BuiltinClass {
+    pub fn new(name: &'static str, super_class: &'static str) -> BuiltinClass {
+        BuiltinClass {
+            name,
+            super_class: Some(super_class),
+            interfaces: Vec::new(),
+            access_flags: ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER,
+            fields: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn interface(name: &'static str) -> BuiltinClass {
+        BuiltinClass {
+            name,
+            super_class: Some("java/lang/Object"),
+            interfaces: Vec::new(),
+            access_flags: ClassAccessFlags::PUBLIC
+                | ClassAccessFlags::INTERFACE
+                | ClassAccessFlags::ABSTRACT,
+            fields: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn implements(mut self, interface: &'static str) -> Self {
+        self.interfaces.push(interface);
+        self
+    }
+
+    pub fn field(mut self, name: &'static str, descriptor: &'static str) -> Self {
+        self.fields.push(BuiltinField {
+            name,
+            descriptor,
+            access_flags: FieldAccessFlags::PRIVATE,
+        });
+        self
+    }
+
+    pub fn static_field(mut self, name: &'static str, descriptor: &'static str) -> Self {
+        self.fields.push(BuiltinField {
+            name,
+            descriptor,
+            access_flags: FieldAccessFlags::PUBLIC
+                | FieldAccessFlags::STATIC
+                | FieldAccessFlags::FINAL,
+        });
+        self
+    }
+
+    /// A constant of a built-in enum, flagged like the fields javac generates
+    /// for them so that `Class.getEnumConstants()` finds it.
+    pub fn enum_constant(mut self, name: &'static str, descriptor: &'static str) -> Self {
+        self.fields.push(BuiltinField {
+            name,
+            descriptor,
+            access_flags: FieldAccessFlags::PUBLIC
+                | FieldAccessFlags::STATIC
+                | FieldAccessFlags::FINAL
+                | FieldAccessFlags::ENUM,
+        });
+        self
+    }
+
+    pub fn method(
+        mut self,
+        name: &'static str,
+        descriptor: &'static str,
+        native: NativeFn,
+    ) -> Self {
+        self.methods.push(BuiltinMethod {
+            name,
+            descriptor,
+            access_flags: MethodAccessFlags::PUBLIC,
+            native: Some(native),
+        });
+        self
+    }
+
+    pub fn static_method(
+        mut self,
+        name: &'static str,
+        descriptor: &'static str,
+        native: NativeFn,
+    ) -> Self {
+        self.methods.push(BuiltinMethod {
+            name,
+            descriptor,
+            access_flags: MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC,
+            native: Some(native),
+        });
+        self
+    }
+
+    pub fn abstract_method(mut self, name: &'static str, descriptor: &'static str) -> Self {
+        self.methods.push(BuiltinMethod {
+            name,
+            descriptor,
+            access_flags: MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT,
+            native: None,
+        });
+        self
+    }
+}
+
+// =============================================================================
+// HELPERS
+// =============================================================================
+
+/// The object reference of an argument, throwing `NullPointerException` for
+/// `null`.
+pub(crate) fn non_null(vm: &mut Vm, value: Value) -> Result<ObjectRef, Unwind> {
+    match value {
+        Value::Reference(Some(object)) => Ok(object),
+        _ => Err(vm.throw_new("java/lang/NullPointerException", None)),
+    }
+}
+
+pub(crate) fn int(value: Value) -> i32 {
+    match value {
+        Value::Int(value) => value,
+        value => panic!("Expected an int, got {:?}", value),
+    }
+}
+
+pub(crate) fn long(value: Value) -> i64 {
+    match value {
+        Value::Long(value) => value,
+        value => panic!("Expected a long, got {:?}", value),
+    }
+}
+
+pub(crate) fn float(value: Value) -> f32 {
+    match value {
+        Value::Float(value) => value,
+        value => panic!("Expected a float, got {:?}", value),
+    }
+}
+
+pub(crate) fn double(value: Value) -> f64 {
+    match value {
+        Value::Double(value) => value,
+        value => panic!("Expected a double, got {:?}", value),
+    }
+}
+
+/// The contents of a `byte[]` argument, throwing `NullPointerException` for
+/// `null`.
+pub(crate) fn byte_array(vm: &mut Vm, value: Value) -> Result<Vec<u8>, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.heap.get(object).array() {
+        Some(ArrayData::Byte(bytes)) => Ok(bytes.iter().map(|byte| *byte as u8).collect()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a byte array",
+            object
+        )))),
+    }
+}
+
+pub(crate) fn new_byte_array(vm: &mut Vm, bytes: Vec<u8>) -> Result<Option<Value>, Unwind> {
+    let bytes = bytes.into_iter().map(|byte| byte as i8).collect();
+    let array = vm.allocate_array("[B", ArrayData::Byte(bytes))?;
+    Ok(Some(Value::Reference(Some(array))))
+}
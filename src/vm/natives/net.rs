@@ -0,0 +1,84 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::io::{io_exception, new_pipe_stream, Pipe};
+use crate::vm::natives::lang::{chars, object_to_string};
+use crate::vm::natives::{non_null, BuiltinClass};
+use crate::vm::policy::Permission;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm};
+
+/// The classes of `java.net` the VM defines when no class path provides the
+/// JDK's.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![url()]
+}
+
+// =============================================================================
+// URL
+// =============================================================================
+
+/// `java.net.URL`, only parsed as far as `openStream` needs: it opens the
+/// resources of the class path by the URLs `ClassLoader.getResource` gives
+/// and `file:` URLs.
+fn url() -> BuiltinClass {
+    BuiltinClass::new("java/net/URL", "java/lang/Object")
+        .implements("java/io/Serializable")
+        .field("spec", "Ljava/lang/String;")
+        .method("<init>", "(Ljava/lang/String;)V", url_init)
+        .method("toString", "()Ljava/lang/String;", url_to_string)
+        .method("toExternalForm", "()Ljava/lang/String;", url_to_string)
+        .method("openStream", "()Ljava/io/InputStream;", url_open_stream)
+}
+
+/// Creates a `java.net.URL` of the specification, through the constructor
+/// of the JDK's when it has one.
+pub(crate) fn new_url(vm: &mut Vm, spec: &str) -> Result<ObjectRef, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/net/URL")?;
+    vm.initialize_class(class)?;
+    let url = vm.instantiate(class)?;
+    let spec = vm.create_string(spec.encode_utf16().collect())?;
+    vm.invoke_virtual(
+        url,
+        "<init>",
+        "(Ljava/lang/String;)V",
+        &[Value::Reference(Some(spec))],
+    )?;
+    Ok(url)
+}
+
+fn url_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    non_null(vm, args[1])?;
+    vm.set_field(this, "spec", args[1]);
+    Ok(None)
+}
+
+fn url_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "spec"))
+}
+
+fn url_open_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let spec = vm.field(this, "spec").unwrap_or(Value::NULL);
+    let spec = String::from_utf16_lossy(&chars(vm, spec)?);
+
+    let bytes = match vm.loaders.resource_at(&spec) {
+        Some(bytes) => bytes,
+        None => match spec.strip_prefix("file:") {
+            Some(path) => {
+                vm.check_permission(Permission::Read(Path::new(path)))?;
+                fs::read(path).map_err(|error| io_exception(vm, error))?
+            }
+            None => {
+                let message = format!("cannot open {}", object_to_string(vm, args[0])?);
+                return Err(vm.throw_new("java/io/IOException", Some(message)));
+            }
+        },
+    };
+    let stream = new_pipe_stream(vm, Pipe::Input(Box::new(Cursor::new(bytes))))?;
+    Ok(Some(Value::Reference(Some(stream))))
+}
@@ -0,0 +1,1462 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::class::ClassAccessFlags;
+use crate::vm::heap::{ArrayData, NativeData};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::io::byte_range;
+use crate::vm::natives::lang::{chars, exception};
+use crate::vm::natives::{int, long, non_null, BuiltinClass};
+use crate::vm::policy::Permission;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.nio`, `java.nio.file` and
+/// `java.nio.channels` for reading and writing files through channels.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        buffer(),
+        byte_buffer(),
+        mapped_byte_buffer(),
+        exception(
+            "java/nio/BufferUnderflowException",
+            "java/lang/RuntimeException",
+        ),
+        exception(
+            "java/nio/BufferOverflowException",
+            "java/lang/RuntimeException",
+        ),
+        exception(
+            "java/nio/ReadOnlyBufferException",
+            "java/lang/UnsupportedOperationException",
+        ),
+        exception(
+            "java/nio/InvalidMarkException",
+            "java/lang/IllegalStateException",
+        ),
+        path(),
+        host_path(),
+        BuiltinClass::new("java/nio/file/Paths", "java/lang/Object").static_method(
+            "get",
+            "(Ljava/lang/String;[Ljava/lang/String;)Ljava/nio/file/Path;",
+            paths_get,
+        ),
+        BuiltinClass::interface("java/nio/file/OpenOption"),
+        standard_open_option(),
+        exception("java/nio/file/FileSystemException", "java/io/IOException"),
+        exception(
+            "java/nio/file/NoSuchFileException",
+            "java/nio/file/FileSystemException",
+        ),
+        exception(
+            "java/nio/file/FileAlreadyExistsException",
+            "java/nio/file/FileSystemException",
+        ),
+        exception(
+            "java/nio/file/AccessDeniedException",
+            "java/nio/file/FileSystemException",
+        ),
+        BuiltinClass::interface("java/nio/channels/Channel")
+            .implements("java/io/Closeable")
+            .abstract_method("isOpen", "()Z"),
+        file_channel(),
+        map_mode(),
+        exception(
+            "java/nio/channels/ClosedChannelException",
+            "java/io/IOException",
+        ),
+        exception(
+            "java/nio/channels/NonReadableChannelException",
+            "java/lang/IllegalStateException",
+        ),
+        exception(
+            "java/nio/channels/NonWritableChannelException",
+            "java/lang/IllegalStateException",
+        ),
+    ]
+}
+
+fn int_field(vm: &Vm, object: ObjectRef, name: &str) -> i32 {
+    vm.field(object, name).map_or(0, int)
+}
+
+fn set_int_field(vm: &mut Vm, object: ObjectRef, name: &str, value: i32) {
+    vm.set_field(object, name, Value::Int(value));
+}
+
+fn illegal_argument(vm: &mut Vm, message: &str) -> Unwind {
+    vm.throw_new(
+        "java/lang/IllegalArgumentException",
+        Some(message.to_string()),
+    )
+}
+
+// =============================================================================
+// MAPPINGS
+// =============================================================================
+
+/// A region of a file mapped into memory, backing a `MappedByteBuffer`.
+/// Buffers check their bounds and whether they are read-only before
+/// accessing it.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct Mapping {
+    /// The start of the mapped pages, `null` for an empty region.
+    pointer: *mut libc::c_void,
+    /// The length of the mapped pages.
+    mapped: usize,
+    /// The start of the region within the first page.
+    offset: usize,
+    length: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    fn of(file: &File, mode: MapMode, position: u64, length: usize) -> io::Result<Mapping> {
+        use std::os::unix::io::AsRawFd;
+
+        if length == 0 {
+            return Ok(Mapping {
+                pointer: std::ptr::null_mut(),
+                mapped: 0,
+                offset: 0,
+                length,
+            });
+        }
+        // SAFETY: querying the page size has no preconditions.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let offset = (position % page) as usize;
+        let (protection, flags) = match mode {
+            MapMode::ReadOnly => (libc::PROT_READ, libc::MAP_SHARED),
+            MapMode::ReadWrite => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED),
+            MapMode::Private => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE),
+        };
+        // SAFETY: a fresh mapping of the open file at a page-aligned offset,
+        // which nothing else references yet.
+        let pointer = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                offset + length,
+                protection,
+                flags,
+                file.as_raw_fd(),
+                (position - offset as u64) as libc::off_t,
+            )
+        };
+        if pointer == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping {
+            pointer,
+            mapped: offset + length,
+            offset,
+            length,
+        })
+    }
+
+    fn read(&self, index: usize, bytes: &mut [u8]) {
+        assert!(index + bytes.len() <= self.length);
+        if bytes.is_empty() {
+            return;
+        }
+        // SAFETY: the region is readable and within the mapping.
+        unsafe {
+            let source = (self.pointer as *const u8).add(self.offset + index);
+            std::ptr::copy_nonoverlapping(source, bytes.as_mut_ptr(), bytes.len());
+        }
+    }
+
+    fn write(&self, index: usize, bytes: &[u8]) {
+        assert!(index + bytes.len() <= self.length);
+        if bytes.is_empty() {
+            return;
+        }
+        // SAFETY: the region is within the mapping, which buffers only write
+        // to if it is writable.
+        unsafe {
+            let target = (self.pointer as *mut u8).add(self.offset + index);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), target, bytes.len());
+        }
+    }
+
+    /// Writes the changes to the region back to the file.
+    fn force(&self) -> io::Result<()> {
+        if self.mapped == 0 {
+            return Ok(());
+        }
+        // SAFETY: the pages are mapped.
+        match unsafe { libc::msync(self.pointer, self.mapped, libc::MS_SYNC) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.mapped != 0 {
+            // SAFETY: the buffer owning the mapping is gone.
+            unsafe { libc::munmap(self.pointer, self.mapped) };
+        }
+    }
+}
+
+// SAFETY: the mapping belongs to a single buffer, and guest threads access
+// the heap one at a time.
+#[cfg(unix)]
+unsafe impl Send for Mapping {}
+#[cfg(unix)]
+unsafe impl Sync for Mapping {}
+
+/// The region of the file read into memory, where mapping it is not
+/// supported. Changes are not written back.
+#[cfg(not(unix))]
+#[derive(Debug)]
+pub struct Mapping {
+    bytes: Mutex<Vec<u8>>,
+    length: usize,
+}
+
+#[cfg(not(unix))]
+impl Mapping {
+    fn of(mut file: &File, _: MapMode, position: u64, length: usize) -> io::Result<Mapping> {
+        let mut bytes = vec![0; length];
+        let current = file.stream_position()?;
+        file.seek(SeekFrom::Start(position))?;
+        let result = file.read_exact(&mut bytes);
+        file.seek(SeekFrom::Start(current))?;
+        result?;
+        Ok(Mapping {
+            bytes: Mutex::new(bytes),
+            length,
+        })
+    }
+
+    fn read(&self, index: usize, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.bytes.lock().unwrap()[index..index + bytes.len()]);
+    }
+
+    fn write(&self, index: usize, bytes: &[u8]) {
+        self.bytes.lock().unwrap()[index..index + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn force(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The modes of `FileChannel.MapMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MapMode {
+    ReadOnly,
+    ReadWrite,
+    Private,
+}
+
+impl MapMode {
+    const ALL: [MapMode; 3] = [MapMode::ReadOnly, MapMode::ReadWrite, MapMode::Private];
+
+    fn name(self) -> &'static str {
+        match self {
+            MapMode::ReadOnly => "READ_ONLY",
+            MapMode::ReadWrite => "READ_WRITE",
+            MapMode::Private => "PRIVATE",
+        }
+    }
+}
+
+// =============================================================================
+// BUFFER
+// =============================================================================
+
+/// `java.nio.Buffer`, keeping the indices of its subclasses in fields.
+fn buffer() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/Buffer", "java/lang/Object")
+        .field("mark", "I")
+        .field("position", "I")
+        .field("limit", "I")
+        .field("capacity", "I")
+        .method("capacity", "()I", buffer_capacity)
+        .method("position", "()I", buffer_position)
+        .method("position", "(I)Ljava/nio/Buffer;", buffer_set_position)
+        .method("limit", "()I", buffer_limit)
+        .method("limit", "(I)Ljava/nio/Buffer;", buffer_set_limit)
+        .method("mark", "()Ljava/nio/Buffer;", buffer_mark)
+        .method("reset", "()Ljava/nio/Buffer;", buffer_reset)
+        .method("clear", "()Ljava/nio/Buffer;", buffer_clear)
+        .method("flip", "()Ljava/nio/Buffer;", buffer_flip)
+        .method("rewind", "()Ljava/nio/Buffer;", buffer_rewind)
+        .method("remaining", "()I", buffer_remaining)
+        .method("hasRemaining", "()Z", buffer_has_remaining);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn buffer_capacity(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "capacity"))
+}
+
+fn buffer_position(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "position"))
+}
+
+fn buffer_set_position(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let (position, limit) = (int(args[1]), int_field(vm, this, "limit"));
+    if position < 0 || position > limit {
+        let message = format!("newPosition > limit: ({} > {})", position, limit);
+        return Err(illegal_argument(vm, &message));
+    }
+    if int_field(vm, this, "mark") > position {
+        set_int_field(vm, this, "mark", -1);
+    }
+    set_int_field(vm, this, "position", position);
+    Ok(Some(args[0]))
+}
+
+fn buffer_limit(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "limit"))
+}
+
+fn buffer_set_limit(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let (limit, capacity) = (int(args[1]), int_field(vm, this, "capacity"));
+    if limit < 0 || limit > capacity {
+        let message = format!("newLimit > capacity: ({} > {})", limit, capacity);
+        return Err(illegal_argument(vm, &message));
+    }
+    if int_field(vm, this, "position") > limit {
+        set_int_field(vm, this, "position", limit);
+    }
+    if int_field(vm, this, "mark") > limit {
+        set_int_field(vm, this, "mark", -1);
+    }
+    set_int_field(vm, this, "limit", limit);
+    Ok(Some(args[0]))
+}
+
+fn buffer_mark(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let position = int_field(vm, this, "position");
+    set_int_field(vm, this, "mark", position);
+    Ok(Some(args[0]))
+}
+
+fn buffer_reset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let mark = int_field(vm, this, "mark");
+    if mark < 0 {
+        return Err(vm.throw_new("java/nio/InvalidMarkException", None));
+    }
+    set_int_field(vm, this, "position", mark);
+    Ok(Some(args[0]))
+}
+
+fn buffer_clear(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let capacity = int_field(vm, this, "capacity");
+    set_int_field(vm, this, "position", 0);
+    set_int_field(vm, this, "limit", capacity);
+    set_int_field(vm, this, "mark", -1);
+    Ok(Some(args[0]))
+}
+
+fn buffer_flip(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let position = int_field(vm, this, "position");
+    set_int_field(vm, this, "limit", position);
+    set_int_field(vm, this, "position", 0);
+    set_int_field(vm, this, "mark", -1);
+    Ok(Some(args[0]))
+}
+
+fn buffer_rewind(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    set_int_field(vm, this, "position", 0);
+    set_int_field(vm, this, "mark", -1);
+    Ok(Some(args[0]))
+}
+
+fn remaining(vm: &Vm, buffer: ObjectRef) -> usize {
+    (int_field(vm, buffer, "limit") - int_field(vm, buffer, "position")).max(0) as usize
+}
+
+fn buffer_remaining(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Int(remaining(vm, this) as i32)))
+}
+
+fn buffer_has_remaining(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Int((remaining(vm, this) > 0) as i32)))
+}
+
+// =============================================================================
+// BYTE BUFFER
+// =============================================================================
+
+/// `java.nio.ByteBuffer`, big-endian, over a `byte[]` or a mapped region of
+/// a file. Direct buffers are allocated on the heap like the others.
+fn byte_buffer() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/ByteBuffer", "java/nio/Buffer")
+        .field("hb", "[B")
+        .field("readOnly", "Z")
+        .static_method("allocate", "(I)Ljava/nio/ByteBuffer;", byte_buffer_allocate)
+        .static_method(
+            "allocateDirect",
+            "(I)Ljava/nio/ByteBuffer;",
+            byte_buffer_allocate,
+        )
+        .static_method("wrap", "([B)Ljava/nio/ByteBuffer;", byte_buffer_wrap)
+        .static_method("wrap", "([BII)Ljava/nio/ByteBuffer;", byte_buffer_wrap)
+        // The covariant overrides of Java 9 and later
+        .method("position", "(I)Ljava/nio/ByteBuffer;", buffer_set_position)
+        .method("limit", "(I)Ljava/nio/ByteBuffer;", buffer_set_limit)
+        .method("mark", "()Ljava/nio/ByteBuffer;", buffer_mark)
+        .method("reset", "()Ljava/nio/ByteBuffer;", buffer_reset)
+        .method("clear", "()Ljava/nio/ByteBuffer;", buffer_clear)
+        .method("flip", "()Ljava/nio/ByteBuffer;", buffer_flip)
+        .method("rewind", "()Ljava/nio/ByteBuffer;", buffer_rewind)
+        .method("compact", "()Ljava/nio/ByteBuffer;", byte_buffer_compact)
+        .method("get", "()B", byte_buffer_get)
+        .method("get", "(I)B", byte_buffer_get)
+        .method("get", "([B)Ljava/nio/ByteBuffer;", byte_buffer_get_bytes)
+        .method("get", "([BII)Ljava/nio/ByteBuffer;", byte_buffer_get_bytes)
+        .method("getInt", "()I", byte_buffer_get_int)
+        .method("getInt", "(I)I", byte_buffer_get_int)
+        .method("getLong", "()J", byte_buffer_get_long)
+        .method("getLong", "(I)J", byte_buffer_get_long)
+        .method("put", "(B)Ljava/nio/ByteBuffer;", byte_buffer_put)
+        .method("put", "(IB)Ljava/nio/ByteBuffer;", byte_buffer_put)
+        .method("put", "([B)Ljava/nio/ByteBuffer;", byte_buffer_put_bytes)
+        .method("put", "([BII)Ljava/nio/ByteBuffer;", byte_buffer_put_bytes)
+        .method(
+            "put",
+            "(Ljava/nio/ByteBuffer;)Ljava/nio/ByteBuffer;",
+            byte_buffer_put_buffer,
+        )
+        .method("putInt", "(I)Ljava/nio/ByteBuffer;", byte_buffer_put_int)
+        .method("putInt", "(II)Ljava/nio/ByteBuffer;", byte_buffer_put_int)
+        .method("putLong", "(J)Ljava/nio/ByteBuffer;", byte_buffer_put_long)
+        .method("putLong", "(IJ)Ljava/nio/ByteBuffer;", byte_buffer_put_long)
+        .method("hasArray", "()Z", byte_buffer_has_array)
+        .method("array", "()[B", byte_buffer_array)
+        .method("arrayOffset", "()I", byte_buffer_array_offset)
+        .method("isDirect", "()Z", byte_buffer_is_direct)
+        .method("isReadOnly", "()Z", byte_buffer_is_read_only);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+/// Creates a buffer of the class over all of the `byte[]` or the mapping.
+fn new_buffer(
+    vm: &mut Vm,
+    class: &str,
+    bytes: Result<ObjectRef, Arc<Mapping>>,
+    read_only: bool,
+) -> Result<ObjectRef, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, class)?;
+    let buffer = vm.instantiate(class)?;
+    let capacity = match bytes {
+        Ok(array) => {
+            vm.set_field(buffer, "hb", Value::Reference(Some(array)));
+            vm.heap.get(array).array().map_or(0, ArrayData::len)
+        }
+        Err(mapping) => {
+            let capacity = mapping.length;
+            vm.heap.get_mut(buffer).native = NativeData::Mapping(mapping);
+            capacity
+        }
+    };
+    set_int_field(vm, buffer, "mark", -1);
+    set_int_field(vm, buffer, "limit", capacity as i32);
+    set_int_field(vm, buffer, "capacity", capacity as i32);
+    vm.set_field(buffer, "readOnly", Value::Int(read_only as i32));
+    Ok(buffer)
+}
+
+fn byte_buffer_allocate(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let capacity = int(args[0]);
+    if capacity < 0 {
+        let message = format!("capacity < 0: ({} < 0)", capacity);
+        return Err(illegal_argument(vm, &message));
+    }
+    let array = vm.allocate_array("[B", ArrayData::Byte(vec![0; capacity as usize]))?;
+    let buffer = new_buffer(vm, "java/nio/ByteBuffer", Ok(array), false)?;
+    Ok(Some(Value::Reference(Some(buffer))))
+}
+
+/// A buffer over the array, positioned at the range of it if one is given.
+fn byte_buffer_wrap(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (array, offset, length) = byte_range(vm, args, 0)?;
+    let buffer = new_buffer(vm, "java/nio/ByteBuffer", Ok(array), false)?;
+    set_int_field(vm, buffer, "position", offset as i32);
+    set_int_field(vm, buffer, "limit", (offset + length) as i32);
+    Ok(Some(Value::Reference(Some(buffer))))
+}
+
+fn is_read_only(vm: &Vm, buffer: ObjectRef) -> bool {
+    vm.field(buffer, "readOnly")
+        .is_some_and(|read_only| int(read_only) != 0)
+}
+
+/// Reads the bytes of the buffer at the index, which is checked to be within
+/// its limit.
+fn load(vm: &Vm, buffer: ObjectRef, index: usize, bytes: &mut [u8]) {
+    if let NativeData::Mapping(mapping) = &vm.heap.get(buffer).native {
+        return mapping.read(index, bytes);
+    }
+    if let Some(Value::Reference(Some(array))) = vm.field(buffer, "hb") {
+        if let Some(ArrayData::Byte(values)) = vm.heap.get(array).array() {
+            for (byte, value) in bytes.iter_mut().zip(&values[index..]) {
+                *byte = *value as u8;
+            }
+        }
+    }
+}
+
+/// Writes the bytes to the buffer at the index, which is checked to be
+/// within its limit and the buffer not to be read-only.
+fn store(vm: &mut Vm, buffer: ObjectRef, index: usize, bytes: &[u8]) {
+    if let NativeData::Mapping(mapping) = &vm.heap.get(buffer).native {
+        return mapping.write(index, bytes);
+    }
+    if let Some(Value::Reference(Some(array))) = vm.field(buffer, "hb") {
+        write_array(vm, array, index, bytes);
+    }
+}
+
+fn write_array(vm: &mut Vm, array: ObjectRef, index: usize, bytes: &[u8]) {
+    if let Some(ArrayData::Byte(values)) = vm.heap.get_mut(array).array_mut() {
+        for (value, byte) in values[index..].iter_mut().zip(bytes) {
+            *value = *byte as i8;
+        }
+    }
+}
+
+/// The index of `length` bytes to get or put, at the absolute index if one
+/// is given, otherwise at the position, which is moved past them.
+fn element_index(
+    vm: &mut Vm,
+    buffer: ObjectRef,
+    index: Option<Value>,
+    length: usize,
+    put: bool,
+) -> Result<usize, Unwind> {
+    if put && is_read_only(vm, buffer) {
+        return Err(vm.throw_new("java/nio/ReadOnlyBufferException", None));
+    }
+
+    let limit = int_field(vm, buffer, "limit");
+    match index.map(int) {
+        Some(index) if index < 0 || index as usize + length > limit as usize => {
+            let message = format!("Index {} out of bounds for length {}", index, limit);
+            Err(vm.throw_new("java/lang/IndexOutOfBoundsException", Some(message)))
+        }
+        Some(index) => Ok(index as usize),
+        None if remaining(vm, buffer) < length && put => {
+            Err(vm.throw_new("java/nio/BufferOverflowException", None))
+        }
+        None if remaining(vm, buffer) < length => {
+            Err(vm.throw_new("java/nio/BufferUnderflowException", None))
+        }
+        None => {
+            let position = int_field(vm, buffer, "position");
+            set_int_field(vm, buffer, "position", position + length as i32);
+            Ok(position as usize)
+        }
+    }
+}
+
+/// Reads `N` bytes for a relative get method, or an absolute one taking the
+/// index.
+fn get<const N: usize>(vm: &mut Vm, args: &[Value]) -> Result<[u8; N], Unwind> {
+    let this = non_null(vm, args[0])?;
+    let index = element_index(vm, this, args.get(1).copied(), N, false)?;
+    let mut bytes = [0; N];
+    load(vm, this, index, &mut bytes);
+    Ok(bytes)
+}
+
+/// Writes the bytes for a relative put method, or an absolute one taking
+/// the index before the value.
+fn put(vm: &mut Vm, args: &[Value], bytes: &[u8]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let index = match args {
+        [_, index, _] => Some(*index),
+        _ => None,
+    };
+    let index = element_index(vm, this, index, bytes.len(), true)?;
+    store(vm, this, index, bytes);
+    Ok(Some(args[0]))
+}
+
+fn byte_buffer_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let [byte] = get::<1>(vm, args)?;
+    Ok(Some(Value::Int(byte as i8 as i32)))
+}
+
+fn byte_buffer_get_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let bytes = get::<4>(vm, args)?;
+    Ok(Some(Value::Int(i32::from_be_bytes(bytes))))
+}
+
+fn byte_buffer_get_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let bytes = get::<8>(vm, args)?;
+    Ok(Some(Value::Long(i64::from_be_bytes(bytes))))
+}
+
+fn byte_buffer_put(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = int(args[args.len() - 1]);
+    put(vm, args, &[value as u8])
+}
+
+fn byte_buffer_put_int(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = int(args[args.len() - 1]);
+    put(vm, args, &value.to_be_bytes())
+}
+
+fn byte_buffer_put_long(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = long(args[args.len() - 1]);
+    put(vm, args, &value.to_be_bytes())
+}
+
+/// Transfers bytes of the buffer to the range of the array, all or nothing.
+fn byte_buffer_get_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let (array, offset, length) = byte_range(vm, args, 1)?;
+    let index = element_index(vm, this, None, length, false)?;
+    let mut bytes = vec![0; length];
+    load(vm, this, index, &mut bytes);
+    write_array(vm, array, offset, &bytes);
+    Ok(Some(args[0]))
+}
+
+fn byte_buffer_put_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let (array, offset, length) = byte_range(vm, args, 1)?;
+    let bytes: Vec<u8> = match vm.heap.get(array).array() {
+        Some(ArrayData::Byte(values)) => values[offset..offset + length]
+            .iter()
+            .map(|value| *value as u8)
+            .collect(),
+        _ => Vec::new(),
+    };
+    let index = element_index(vm, this, None, length, true)?;
+    store(vm, this, index, &bytes);
+    Ok(Some(args[0]))
+}
+
+/// Transfers the remaining bytes of another buffer to this one.
+fn byte_buffer_put_buffer(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let source = non_null(vm, args[1])?;
+    if source == this {
+        return Err(illegal_argument(vm, "The source buffer is this buffer"));
+    }
+    let length = remaining(vm, source);
+    if is_read_only(vm, this) {
+        return Err(vm.throw_new("java/nio/ReadOnlyBufferException", None));
+    }
+    if remaining(vm, this) < length {
+        return Err(vm.throw_new("java/nio/BufferOverflowException", None));
+    }
+
+    let mut bytes = vec![0; length];
+    let index = element_index(vm, source, None, length, false)?;
+    load(vm, source, index, &mut bytes);
+    let index = element_index(vm, this, None, length, true)?;
+    store(vm, this, index, &bytes);
+    Ok(Some(args[0]))
+}
+
+/// Moves the remaining bytes to the start of the buffer, positioned after
+/// them for further puts.
+fn byte_buffer_compact(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    if is_read_only(vm, this) {
+        return Err(vm.throw_new("java/nio/ReadOnlyBufferException", None));
+    }
+    let position = int_field(vm, this, "position") as usize;
+    let mut bytes = vec![0; remaining(vm, this)];
+    load(vm, this, position, &mut bytes);
+    store(vm, this, 0, &bytes);
+
+    let capacity = int_field(vm, this, "capacity");
+    set_int_field(vm, this, "position", bytes.len() as i32);
+    set_int_field(vm, this, "limit", capacity);
+    set_int_field(vm, this, "mark", -1);
+    Ok(Some(args[0]))
+}
+
+fn byte_buffer_has_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let has_array = vm.field(this, "hb") != Some(Value::NULL) && !is_read_only(vm, this);
+    Ok(Some(Value::Int(has_array as i32)))
+}
+
+fn byte_buffer_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    match vm.field(this, "hb") {
+        _ if is_read_only(vm, this) => Err(vm.throw_new("java/nio/ReadOnlyBufferException", None)),
+        Some(Value::Reference(Some(array))) => Ok(Some(Value::Reference(Some(array)))),
+        _ => Err(vm.throw_new("java/lang/UnsupportedOperationException", None)),
+    }
+}
+
+fn byte_buffer_array_offset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    byte_buffer_array(vm, args)?;
+    Ok(Some(Value::Int(0)))
+}
+
+/// Whether the buffer is mapped, the only buffers outside of the heap.
+fn byte_buffer_is_direct(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let direct = matches!(vm.heap.get(this).native, NativeData::Mapping(_));
+    Ok(Some(Value::Int(direct as i32)))
+}
+
+fn byte_buffer_is_read_only(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(Some(Value::Int(is_read_only(vm, this) as i32)))
+}
+
+// =============================================================================
+// MAPPED BYTE BUFFER
+// =============================================================================
+
+/// `java.nio.MappedByteBuffer`, the buffers `FileChannel.map` returns. The
+/// region is unmapped when the buffer is collected.
+fn mapped_byte_buffer() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/MappedByteBuffer", "java/nio/ByteBuffer")
+        .method("force", "()Ljava/nio/MappedByteBuffer;", mapped_force)
+        .method("load", "()Ljava/nio/MappedByteBuffer;", mapped_load)
+        .method("isLoaded", "()Z", mapped_is_loaded);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn mapping(vm: &mut Vm, this: Value) -> Result<Arc<Mapping>, Unwind> {
+    let this = non_null(vm, this)?;
+    match &vm.heap.get(this).native {
+        NativeData::Mapping(mapping) => Ok(mapping.clone()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a mapped buffer",
+            this
+        )))),
+    }
+}
+
+/// Writes the changes back to the file, which a private mapping never does.
+fn mapped_force(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let mapping = mapping(vm, args[0])?;
+    if let Err(error) = mapping.force() {
+        return Err(file_exception(vm, error, None));
+    }
+    Ok(Some(args[0]))
+}
+
+/// Does nothing, the host pages the file in as it is accessed.
+fn mapped_load(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    mapping(vm, args[0])?;
+    Ok(Some(args[0]))
+}
+
+fn mapped_is_loaded(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    mapping(vm, args[0])?;
+    Ok(Some(Value::Int(1)))
+}
+
+// =============================================================================
+// PATHS
+// =============================================================================
+
+fn path() -> BuiltinClass {
+    BuiltinClass::interface("java/nio/file/Path")
+        .abstract_method("getFileName", "()Ljava/nio/file/Path;")
+        .abstract_method("getParent", "()Ljava/nio/file/Path;")
+        .abstract_method("isAbsolute", "()Z")
+        .abstract_method("toAbsolutePath", "()Ljava/nio/file/Path;")
+        .abstract_method("resolve", "(Ljava/lang/String;)Ljava/nio/file/Path;")
+        .abstract_method("resolve", "(Ljava/nio/file/Path;)Ljava/nio/file/Path;")
+        .abstract_method("toString", "()Ljava/lang/String;")
+}
+
+/// The `Path` of the host's filesystem, holding the path as a string.
+fn host_path() -> BuiltinClass {
+    let mut class = BuiltinClass::new("sun/nio/fs/HostPath", "java/lang/Object")
+        .implements("java/nio/file/Path")
+        .field("path", "Ljava/lang/String;")
+        .method("getFileName", "()Ljava/nio/file/Path;", path_get_file_name)
+        .method("getParent", "()Ljava/nio/file/Path;", path_get_parent)
+        .method("isAbsolute", "()Z", path_is_absolute)
+        .method(
+            "toAbsolutePath",
+            "()Ljava/nio/file/Path;",
+            path_to_absolute_path,
+        )
+        .method(
+            "resolve",
+            "(Ljava/lang/String;)Ljava/nio/file/Path;",
+            path_resolve,
+        )
+        .method(
+            "resolve",
+            "(Ljava/nio/file/Path;)Ljava/nio/file/Path;",
+            path_resolve,
+        )
+        .method("equals", "(Ljava/lang/Object;)Z", path_equals)
+        .method("hashCode", "()I", path_hash_code)
+        .method("toString", "()Ljava/lang/String;", path_to_string);
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// The host path of a `Path` argument, throwing `NullPointerException` for
+/// `null`.
+fn host_path_of(vm: &mut Vm, value: Value) -> Result<PathBuf, Unwind> {
+    let object = non_null(vm, value)?;
+    match vm.field(object, "path") {
+        Some(path) => Ok(PathBuf::from(String::from_utf16_lossy(&chars(vm, path)?))),
+        None => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a path of the host",
+            object
+        )))),
+    }
+}
+
+/// Creates a `Path` of the string, dropping redundant separators like the
+/// JDK does.
+fn new_path(vm: &mut Vm, path: &str) -> Result<Option<Value>, Unwind> {
+    let mut normalized = String::with_capacity(path.len());
+    for char in path.chars() {
+        if char != '/' || !normalized.ends_with('/') {
+            normalized.push(char);
+        }
+    }
+    if normalized.len() > 1 && normalized.ends_with('/') {
+        normalized.pop();
+    }
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "sun/nio/fs/HostPath")?;
+    let object = vm.instantiate(class)?;
+    let string = vm.create_string(normalized.encode_utf16().collect())?;
+    vm.set_field(object, "path", Value::Reference(Some(string)));
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+/// Joins the strings into a path, skipping empty ones.
+fn paths_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let mut path = String::from_utf16_lossy(&chars(vm, args[0])?);
+    let more = non_null(vm, args[1])?;
+    let more = match vm.heap.get(more).array() {
+        Some(ArrayData::Reference(more)) => more.clone(),
+        _ => Vec::new(),
+    };
+    for part in more {
+        let part = String::from_utf16_lossy(&chars(vm, Value::Reference(part))?);
+        if !part.is_empty() {
+            path.push('/');
+            path.push_str(&part);
+        }
+    }
+    new_path(vm, &path)
+}
+
+/// The last name of the path, `null` for a root.
+fn path_get_file_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    match path.components().next_back() {
+        None => new_path(vm, ""),
+        Some(Component::RootDir) | Some(Component::Prefix(_)) => Ok(Some(Value::NULL)),
+        Some(name) => new_path(vm, &name.as_os_str().to_string_lossy()),
+    }
+}
+
+/// The path without its last name, `null` if it has a single one.
+fn path_get_parent(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => new_path(vm, &parent.to_string_lossy()),
+        _ => Ok(Some(Value::NULL)),
+    }
+}
+
+fn path_is_absolute(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    Ok(Some(Value::Int(path.is_absolute() as i32)))
+}
+
+/// The path resolved against the working directory of the host, which a
+/// replayed run reads from the recording.
+fn path_to_absolute_path(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    if path.is_absolute() {
+        return Ok(Some(args[0]));
+    }
+
+    let directory = vm.host_interaction("currentDirectory", "", |_| {
+        env::current_dir()
+            .ok()
+            .map(|directory| directory.to_string_lossy().into_owned())
+    });
+    match directory {
+        Some(directory) => new_path(vm, &Path::new(&directory).join(path).to_string_lossy()),
+        None => Err(vm.throw_new(
+            "java/io/IOError",
+            Some("The working directory is not available".to_string()),
+        )),
+    }
+}
+
+/// The other path or string against this path, which is the other path
+/// itself if it is absolute.
+fn path_resolve(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    let other = non_null(vm, args[1])?;
+    let other = match vm.field(other, "path") {
+        Some(_) => host_path_of(vm, args[1])?,
+        None => PathBuf::from(String::from_utf16_lossy(&chars(vm, args[1])?)),
+    };
+    if other.as_os_str().is_empty() {
+        return Ok(Some(args[0]));
+    }
+    new_path(vm, &path.join(other).to_string_lossy())
+}
+
+fn path_equals(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let equal = match args[1] {
+        Value::Reference(Some(other)) if vm.class_of(other) == vm.class_of(this) => {
+            host_path_of(vm, args[0])? == host_path_of(vm, args[1])?
+        }
+        _ => false,
+    };
+    Ok(Some(Value::Int(equal as i32)))
+}
+
+/// The hash code of the path string, as `String.hashCode()` computes it.
+fn path_hash_code(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let path = vm.field(this, "path").unwrap_or(Value::NULL);
+    let hash = chars(vm, path)?.iter().fold(0i32, |hash, char| {
+        hash.wrapping_mul(31).wrapping_add(*char as i32)
+    });
+    Ok(Some(Value::Int(hash)))
+}
+
+fn path_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "path"))
+}
+
+// =============================================================================
+// OPEN OPTIONS
+// =============================================================================
+
+const OPEN_OPTIONS: [&str; 10] = [
+    "READ",
+    "WRITE",
+    "APPEND",
+    "TRUNCATE_EXISTING",
+    "CREATE",
+    "CREATE_NEW",
+    "DELETE_ON_CLOSE",
+    "SPARSE",
+    "SYNC",
+    "DSYNC",
+];
+
+fn standard_open_option() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/file/StandardOpenOption", "java/lang/Enum")
+        .implements("java/nio/file/OpenOption")
+        .static_method("<clinit>", "()V", standard_open_option_clinit)
+        .static_method(
+            "values",
+            "()[Ljava/nio/file/StandardOpenOption;",
+            standard_open_option_values,
+        );
+    for name in OPEN_OPTIONS {
+        class = class.enum_constant(name, "Ljava/nio/file/StandardOpenOption;");
+    }
+    class.access_flags |= ClassAccessFlags::FINAL | ClassAccessFlags::ENUM;
+    class
+}
+
+fn standard_open_option_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/file/StandardOpenOption")?;
+    for (ordinal, name) in OPEN_OPTIONS.iter().copied().enumerate() {
+        let option = vm.instantiate(class)?;
+        let string = vm.intern_string(name.encode_utf16().collect())?;
+        vm.set_field(option, "name", Value::Reference(Some(string)));
+        set_int_field(vm, option, "ordinal", ordinal as i32);
+        vm.set_static_field(class, name, Value::Reference(Some(option)));
+    }
+    Ok(None)
+}
+
+fn standard_open_option_values(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/file/StandardOpenOption")?;
+    vm.initialize_class(class)?;
+    let options = OPEN_OPTIONS
+        .iter()
+        .map(|name| match vm.static_field(class, name) {
+            Some(Value::Reference(option)) => option,
+            _ => None,
+        })
+        .collect();
+    let array = vm.allocate_array(
+        "[Ljava/nio/file/StandardOpenOption;",
+        ArrayData::Reference(options),
+    )?;
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+/// The names of the options of an `OpenOption[]` argument, which have to be
+/// enum constants.
+fn option_names(vm: &mut Vm, value: Value) -> Result<Vec<String>, Unwind> {
+    let array = non_null(vm, value)?;
+    let options = match vm.heap.get(array).array() {
+        Some(ArrayData::Reference(options)) => options.clone(),
+        _ => Vec::new(),
+    };
+
+    let mut names = Vec::with_capacity(options.len());
+    for option in options {
+        let option = non_null(vm, Value::Reference(option))?;
+        match vm.field(option, "name") {
+            Some(name) => names.push(String::from_utf16_lossy(&chars(vm, name)?)),
+            None => {
+                let message = Some("Unsupported option".to_string());
+                return Err(vm.throw_new("java/lang/UnsupportedOperationException", message));
+            }
+        }
+    }
+    Ok(names)
+}
+
+// =============================================================================
+// FILE CHANNEL
+// =============================================================================
+
+/// The open file of a `FileChannel` and what it was opened for.
+#[derive(Debug)]
+pub struct Channel {
+    /// `None` once the channel is closed.
+    file: Option<File>,
+    path: PathBuf,
+    readable: bool,
+    writable: bool,
+    delete_on_close: bool,
+}
+
+/// What a channel operation needs the file to be opened for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Any,
+    Read,
+    Write,
+}
+
+/// `java.nio.channels.FileChannel`, reading and writing a file of the host
+/// at the channel's position or at a given one.
+fn file_channel() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/channels/FileChannel", "java/lang/Object")
+        .implements("java/nio/channels/Channel")
+        .static_method(
+            "open",
+            "(Ljava/nio/file/Path;[Ljava/nio/file/OpenOption;)Ljava/nio/channels/FileChannel;",
+            channel_open,
+        )
+        .method("read", "(Ljava/nio/ByteBuffer;)I", channel_read)
+        .method("read", "(Ljava/nio/ByteBuffer;J)I", channel_read)
+        .method("write", "(Ljava/nio/ByteBuffer;)I", channel_write)
+        .method("write", "(Ljava/nio/ByteBuffer;J)I", channel_write)
+        .method("position", "()J", channel_position)
+        .method(
+            "position",
+            "(J)Ljava/nio/channels/FileChannel;",
+            channel_set_position,
+        )
+        .method("size", "()J", channel_size)
+        .method(
+            "truncate",
+            "(J)Ljava/nio/channels/FileChannel;",
+            channel_truncate,
+        )
+        .method("force", "(Z)V", channel_force)
+        .method(
+            "map",
+            "(Ljava/nio/channels/FileChannel$MapMode;JJ)Ljava/nio/MappedByteBuffer;",
+            channel_map,
+        )
+        .method("isOpen", "()Z", channel_is_open)
+        .method("close", "()V", channel_close);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+/// Throws the `java.nio.file` exception for an error of the host accessing
+/// the path, or an `IOException` for other errors.
+fn file_exception(vm: &mut Vm, error: io::Error, path: Option<&Path>) -> Unwind {
+    let exception = match (error.kind(), path) {
+        (io::ErrorKind::NotFound, Some(_)) => "java/nio/file/NoSuchFileException",
+        (io::ErrorKind::AlreadyExists, Some(_)) => "java/nio/file/FileAlreadyExistsException",
+        (io::ErrorKind::PermissionDenied, Some(_)) => "java/nio/file/AccessDeniedException",
+        _ => return vm.throw_new("java/io/IOException", Some(error.to_string())),
+    };
+    let path = path.map(|path| path.to_string_lossy().into_owned());
+    vm.throw_new(exception, path)
+}
+
+/// Opens the file at the path for reading, unless the options say to write
+/// or append to it, like the JDK. The policy of the VM has to permit the
+/// access.
+fn channel_open(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let path = host_path_of(vm, args[0])?;
+    let options = option_names(vm, args[1])?;
+    let option = |name: &str| options.iter().any(|option| option == name);
+
+    let append = option("APPEND");
+    let writable = option("WRITE") || append;
+    let readable = option("READ") || !writable;
+    if readable && append {
+        return Err(illegal_argument(vm, "READ + APPEND not allowed"));
+    }
+    if append && option("TRUNCATE_EXISTING") {
+        return Err(illegal_argument(
+            vm,
+            "APPEND + TRUNCATE_EXISTING not allowed",
+        ));
+    }
+    let delete_on_close = option("DELETE_ON_CLOSE");
+    if readable {
+        vm.check_permission(Permission::Read(&path))?;
+    }
+    if writable || delete_on_close {
+        vm.check_permission(Permission::Write(&path))?;
+    }
+
+    let mut open = OpenOptions::new();
+    open.read(readable).write(writable).append(append);
+    if writable {
+        open.truncate(option("TRUNCATE_EXISTING"))
+            .create(option("CREATE"))
+            .create_new(option("CREATE_NEW"));
+    }
+    let file = match open.open(&path) {
+        Ok(file) => file,
+        Err(error) => return Err(file_exception(vm, error, Some(&path))),
+    };
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/channels/FileChannel")?;
+    let channel = vm.instantiate(class)?;
+    vm.heap.get_mut(channel).native = NativeData::Channel(Arc::new(Mutex::new(Channel {
+        file: Some(file),
+        path,
+        readable,
+        writable,
+        delete_on_close,
+    })));
+    Ok(Some(Value::Reference(Some(channel))))
+}
+
+fn channel(vm: &mut Vm, this: Value) -> Result<Arc<Mutex<Channel>>, Unwind> {
+    let this = non_null(vm, this)?;
+    match &vm.heap.get(this).native {
+        NativeData::Channel(channel) => Ok(channel.clone()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a file channel",
+            this
+        )))),
+    }
+}
+
+/// The file of the channel, throwing if it was not opened for the access or
+/// is closed.
+fn open_file<'a>(
+    vm: &mut Vm,
+    channel: &'a mut Channel,
+    access: Access,
+) -> Result<&'a mut File, Unwind> {
+    if access == Access::Read && !channel.readable {
+        return Err(vm.throw_new("java/nio/channels/NonReadableChannelException", None));
+    }
+    if access == Access::Write && !channel.writable {
+        return Err(vm.throw_new("java/nio/channels/NonWritableChannelException", None));
+    }
+    match &mut channel.file {
+        Some(file) => Ok(file),
+        None => Err(vm.throw_new("java/nio/channels/ClosedChannelException", None)),
+    }
+}
+
+/// The position argument of a positional read or write, `None` for the
+/// ones at the channel's position.
+fn explicit_position(vm: &mut Vm, args: &[Value]) -> Result<Option<u64>, Unwind> {
+    match args.get(2).map(|position| long(*position)) {
+        Some(position) if position < 0 => Err(illegal_argument(vm, "Negative position")),
+        position => Ok(position.map(|position| position as u64)),
+    }
+}
+
+/// Performs the operation at the position if one is given, leaving the
+/// channel's position where it was.
+fn at_position<T>(
+    file: &mut File,
+    position: Option<u64>,
+    operation: impl FnOnce(&mut File) -> io::Result<T>,
+) -> io::Result<T> {
+    let position = match position {
+        Some(position) => position,
+        None => return operation(file),
+    };
+    let current = file.stream_position()?;
+    file.seek(SeekFrom::Start(position))?;
+    let result = operation(file);
+    file.seek(SeekFrom::Start(current))?;
+    result
+}
+
+/// Reads into the remaining bytes of the buffer, returning -1 at the end of
+/// the file.
+fn channel_read(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let buffer = non_null(vm, args[1])?;
+    let position = explicit_position(vm, args)?;
+    if is_read_only(vm, buffer) {
+        return Err(illegal_argument(vm, "Read-only buffer"));
+    }
+
+    let mut bytes = vec![0; remaining(vm, buffer)];
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Read)?;
+    let read = match at_position(file, position, |file| file.read(&mut bytes)) {
+        Ok(read) => read,
+        Err(error) => return Err(file_exception(vm, error, None)),
+    };
+    if read == 0 && !bytes.is_empty() {
+        return Ok(Some(Value::Int(-1)));
+    }
+
+    let index = element_index(vm, buffer, None, read, true)?;
+    store(vm, buffer, index, &bytes[..read]);
+    Ok(Some(Value::Int(read as i32)))
+}
+
+/// Writes all the remaining bytes of the buffer.
+fn channel_write(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let buffer = non_null(vm, args[1])?;
+    let position = explicit_position(vm, args)?;
+
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Write)?;
+    let mut bytes = vec![0; remaining(vm, buffer)];
+    let index = int_field(vm, buffer, "position") as usize;
+    load(vm, buffer, index, &mut bytes);
+    if let Err(error) = at_position(file, position, |file| file.write_all(&bytes)) {
+        return Err(file_exception(vm, error, None));
+    }
+    element_index(vm, buffer, None, bytes.len(), false)?;
+    Ok(Some(Value::Int(bytes.len() as i32)))
+}
+
+fn channel_position(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Any)?;
+    match file.stream_position() {
+        Ok(position) => Ok(Some(Value::Long(position as i64))),
+        Err(error) => Err(file_exception(vm, error, None)),
+    }
+}
+
+/// Moves the position, past the end of the file if need be, where writing
+/// extends the file.
+fn channel_set_position(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let position = long(args[1]);
+    if position < 0 {
+        return Err(illegal_argument(vm, "Negative position"));
+    }
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Any)?;
+    if let Err(error) = file.seek(SeekFrom::Start(position as u64)) {
+        return Err(file_exception(vm, error, None));
+    }
+    Ok(Some(args[0]))
+}
+
+fn channel_size(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Any)?;
+    match file.metadata() {
+        Ok(metadata) => Ok(Some(Value::Long(metadata.len() as i64))),
+        Err(error) => Err(file_exception(vm, error, None)),
+    }
+}
+
+/// Cuts the file to the size if it is larger, moving the position back to
+/// its end if it was past it.
+fn channel_truncate(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let size = long(args[1]);
+    if size < 0 {
+        return Err(illegal_argument(vm, "Negative size"));
+    }
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Write)?;
+    let truncate = |file: &mut File| -> io::Result<()> {
+        let size = size as u64;
+        if size < file.metadata()?.len() {
+            file.set_len(size)?;
+        }
+        if file.stream_position()? > size {
+            file.seek(SeekFrom::Start(size))?;
+        }
+        Ok(())
+    };
+    if let Err(error) = truncate(file) {
+        return Err(file_exception(vm, error, None));
+    }
+    Ok(Some(args[0]))
+}
+
+/// Writes the file through to the storage device, with its metadata if
+/// asked to.
+fn channel_force(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let mut channel = channel.lock().unwrap();
+    let file = open_file(vm, &mut channel, Access::Any)?;
+    let result = match int(args[1]) {
+        0 => file.sync_data(),
+        _ => file.sync_all(),
+    };
+    match result {
+        Ok(()) => Ok(None),
+        Err(error) => Err(file_exception(vm, error, None)),
+    }
+}
+
+/// Maps the region of the file into memory, extending the file to its end
+/// if the channel is writable. Mapping for writing needs the channel to be
+/// opened for both reading and writing.
+fn channel_map(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let mode = non_null(vm, args[1])?;
+    let (position, size) = (long(args[2]), long(args[3]));
+    let mode = match vm.field(mode, "name") {
+        Some(name) => String::from_utf16_lossy(&chars(vm, name)?),
+        None => String::new(),
+    };
+    let mode = match MapMode::ALL
+        .iter()
+        .copied()
+        .find(|known| known.name() == mode)
+    {
+        Some(mode) => mode,
+        None => return Err(vm.throw_new("java/lang/UnsupportedOperationException", None)),
+    };
+    if position < 0 {
+        return Err(illegal_argument(vm, "Negative position"));
+    }
+    if size < 0 {
+        return Err(illegal_argument(vm, "Negative size"));
+    }
+    if size > i32::MAX as i64 {
+        return Err(illegal_argument(vm, "Size exceeds Integer.MAX_VALUE"));
+    }
+
+    let mut channel = channel.lock().unwrap();
+    let writable = channel.writable;
+    if mode != MapMode::ReadOnly {
+        open_file(vm, &mut channel, Access::Write)?;
+    }
+    let file = open_file(vm, &mut channel, Access::Read)?;
+    let file_size = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(error) => return Err(file_exception(vm, error, None)),
+    };
+    let end = (position + size) as u64;
+    if file_size < end {
+        if !writable {
+            let message = "Channel not open for writing - cannot extend file to required size";
+            return Err(vm.throw_new("java/io/IOException", Some(message.to_string())));
+        }
+        if let Err(error) = file.set_len(end) {
+            return Err(file_exception(vm, error, None));
+        }
+    }
+    let mapping = match Mapping::of(file, mode, position as u64, size as usize) {
+        Ok(mapping) => mapping,
+        Err(error) => return Err(file_exception(vm, error, None)),
+    };
+    drop(channel);
+
+    let buffer = new_buffer(
+        vm,
+        "java/nio/MappedByteBuffer",
+        Err(Arc::new(mapping)),
+        mode == MapMode::ReadOnly,
+    )?;
+    Ok(Some(Value::Reference(Some(buffer))))
+}
+
+fn channel_is_open(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let open = channel.lock().unwrap().file.is_some();
+    Ok(Some(Value::Int(open as i32)))
+}
+
+/// Closes the file, deleting it if it was opened with `DELETE_ON_CLOSE`.
+/// Buffers mapped from it stay valid.
+fn channel_close(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let channel = channel(vm, args[0])?;
+    let mut channel = channel.lock().unwrap();
+    if channel.file.take().is_some() && channel.delete_on_close {
+        if let Err(error) = fs::remove_file(&channel.path) {
+            return Err(file_exception(vm, error, Some(&channel.path)));
+        }
+    }
+    Ok(None)
+}
+
+// =============================================================================
+// MAP MODES
+// =============================================================================
+
+fn map_mode() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/nio/channels/FileChannel$MapMode", "java/lang/Object")
+        .field("name", "Ljava/lang/String;")
+        .static_method("<clinit>", "()V", map_mode_clinit)
+        .method("toString", "()Ljava/lang/String;", map_mode_to_string);
+    for mode in MapMode::ALL {
+        class = class.static_field(mode.name(), "Ljava/nio/channels/FileChannel$MapMode;");
+    }
+    class
+}
+
+fn map_mode_clinit(vm: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/nio/channels/FileChannel$MapMode")?;
+    for mode in MapMode::ALL {
+        let object = vm.instantiate(class)?;
+        let name = vm.intern_string(mode.name().encode_utf16().collect())?;
+        vm.set_field(object, "name", Value::Reference(Some(name)));
+        vm.set_static_field(class, mode.name(), Value::Reference(Some(object)));
+    }
+    Ok(None)
+}
+
+fn map_mode_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "name"))
+}
@@ -0,0 +1,355 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+
+use crate::vm::heap::{ArrayData, NativeData};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::io::{io_exception, new_pipe_stream, Pipe};
+use crate::vm::natives::lang::chars;
+use crate::vm::natives::{int, non_null, BuiltinClass};
+use crate::vm::policy::Permission;
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.lang` for host processes.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![process_builder(), process()]
+}
+
+// =============================================================================
+// PROCESS BUILDER
+// =============================================================================
+
+fn process_builder() -> BuiltinClass {
+    BuiltinClass::new("java/lang/ProcessBuilder", "java/lang/Object")
+        .field("command", "[Ljava/lang/String;")
+        .field("redirectErrorStream", "Z")
+        .field("inheritIO", "Z")
+        .method("<init>", "([Ljava/lang/String;)V", builder_command)
+        .method(
+            "command",
+            "([Ljava/lang/String;)Ljava/lang/ProcessBuilder;",
+            builder_command,
+        )
+        .method("redirectErrorStream", "()Z", builder_redirects_error_stream)
+        .method(
+            "redirectErrorStream",
+            "(Z)Ljava/lang/ProcessBuilder;",
+            builder_redirect_error_stream,
+        )
+        .method(
+            "inheritIO",
+            "()Ljava/lang/ProcessBuilder;",
+            builder_inherit_io,
+        )
+        .method("start", "()Ljava/lang/Process;", builder_start)
+}
+
+fn builder_command(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    non_null(vm, args[1])?;
+    vm.set_field(this, "command", args[1]);
+    Ok(Some(args[0]))
+}
+
+fn builder_redirects_error_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "redirectErrorStream"))
+}
+
+fn builder_redirect_error_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "redirectErrorStream", args[1]);
+    Ok(Some(args[0]))
+}
+
+fn builder_inherit_io(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "inheritIO", Value::Int(1));
+    Ok(Some(args[0]))
+}
+
+fn builder_start(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let command = vm.field(this, "command").unwrap_or(Value::NULL);
+    let command = strings(vm, command)?;
+    if command.is_empty() {
+        return Err(vm.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            Some("Index 0 out of bounds for length 0".to_string()),
+        ));
+    }
+
+    let flag = |vm: &Vm, name| vm.field(this, name).is_some_and(|value| int(value) != 0);
+    let options = Options {
+        redirect_error_stream: flag(vm, "redirectErrorStream"),
+        inherit_io: flag(vm, "inheritIO"),
+    };
+    let process = start(vm, &command, options)?;
+    Ok(Some(Value::Reference(Some(process))))
+}
+
+/// The contents of a `String[]` argument, throwing `NullPointerException`
+/// for `null` or a `null` element.
+fn strings(vm: &mut Vm, value: Value) -> Result<Vec<String>, Unwind> {
+    let array = non_null(vm, value)?;
+    let elements = match vm.heap.get(array).array() {
+        Some(ArrayData::Reference(elements)) => elements.clone(),
+        _ => {
+            return Err(Unwind::Error(VmError::Internal(format!(
+                "{} is not a string array",
+                array
+            ))))
+        }
+    };
+    let mut strings = Vec::with_capacity(elements.len());
+    for element in elements {
+        let chars = chars(vm, Value::Reference(element))?;
+        strings.push(String::from_utf16_lossy(&chars));
+    }
+    Ok(strings)
+}
+
+// =============================================================================
+// PROCESS
+// =============================================================================
+
+/// How a process started by the guest is connected to the VM.
+#[derive(Clone, Copy, Debug, Default)]
+struct Options {
+    /// Merges the standard error of the process into its standard output.
+    redirect_error_stream: bool,
+    /// Connects the process to the standard streams of the host instead of
+    /// pipes, bypassing the VM's own `System.out` and `System.err`.
+    inherit_io: bool,
+}
+
+fn process() -> BuiltinClass {
+    BuiltinClass::new("java/lang/Process", "java/lang/Object")
+        .field("stdin", "Ljava/io/OutputStream;")
+        .field("stdout", "Ljava/io/InputStream;")
+        .field("stderr", "Ljava/io/InputStream;")
+        .method(
+            "getOutputStream",
+            "()Ljava/io/OutputStream;",
+            process_get_output_stream,
+        )
+        .method(
+            "getInputStream",
+            "()Ljava/io/InputStream;",
+            process_get_input_stream,
+        )
+        .method(
+            "getErrorStream",
+            "()Ljava/io/InputStream;",
+            process_get_error_stream,
+        )
+        .method("waitFor", "()I", process_wait_for)
+        .method("exitValue", "()I", process_exit_value)
+        .method("isAlive", "()Z", process_is_alive)
+        .method("destroy", "()V", process_destroy)
+        .method(
+            "destroyForcibly",
+            "()Ljava/lang/Process;",
+            process_destroy_forcibly,
+        )
+        .method("pid", "()J", process_pid)
+}
+
+/// Starts a host process, if the policy of the VM permits it, and creates
+/// the `Process` controlling it.
+fn start(vm: &mut Vm, command: &[String], options: Options) -> Result<ObjectRef, Unwind> {
+    vm.check_permission(Permission::Process)?;
+
+    let spawn = || -> io::Result<(Child, Option<io::PipeReader>)> {
+        let mut process = Command::new(&command[0]);
+        process.args(&command[1..]);
+        if options.inherit_io {
+            return Ok((process.spawn()?, None));
+        }
+
+        process.stdin(Stdio::piped());
+        if !options.redirect_error_stream {
+            process.stdout(Stdio::piped()).stderr(Stdio::piped());
+            return Ok((process.spawn()?, None));
+        }
+        let (reader, writer) = io::pipe()?;
+        process.stdout(writer.try_clone()?).stderr(writer);
+        let child = process.spawn()?;
+        // The command holds on to the writing ends, which have to be closed
+        // for the output to end with the process
+        drop(process);
+        Ok((child, Some(reader)))
+    };
+    let (mut child, merged) = match spawn() {
+        Ok(spawned) => spawned,
+        Err(error) => {
+            let message = format!("Cannot run program \"{}\": {}", command[0], error);
+            return Err(vm.throw_new("java/io/IOException", Some(message)));
+        }
+    };
+
+    let stdin: Pipe = match child.stdin.take() {
+        Some(stdin) => Pipe::Output(Box::new(stdin)),
+        None => Pipe::Output(Box::new(io::sink())),
+    };
+    let stdout: Pipe = match (child.stdout.take(), merged) {
+        (Some(stdout), _) => Pipe::Input(Box::new(stdout)),
+        (None, Some(merged)) => Pipe::Input(Box::new(merged)),
+        (None, None) => Pipe::Input(Box::new(io::empty())),
+    };
+    let stderr: Pipe = match child.stderr.take() {
+        Some(stderr) => Pipe::Input(Box::new(stderr)),
+        None => Pipe::Input(Box::new(io::empty())),
+    };
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/lang/Process")?;
+    let process = vm.instantiate(class)?;
+    vm.heap.get_mut(process).native = NativeData::Process(Arc::new(Mutex::new(child)));
+    for (field, pipe) in [("stdin", stdin), ("stdout", stdout), ("stderr", stderr)] {
+        let stream = new_pipe_stream(vm, pipe)?;
+        vm.set_field(process, field, Value::Reference(Some(stream)));
+    }
+    Ok(process)
+}
+
+fn child(vm: &mut Vm, this: Value) -> Result<Arc<Mutex<Child>>, Unwind> {
+    let this = non_null(vm, this)?;
+    match &vm.heap.get(this).native {
+        NativeData::Process(child) => Ok(child.clone()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a process",
+            this
+        )))),
+    }
+}
+
+/// The exit value of a process, 128 plus the number of the signal for one
+/// killed by a signal, like the JDK reports it.
+fn exit_value(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+fn process_get_output_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "stdin"))
+}
+
+fn process_get_input_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "stdout"))
+}
+
+fn process_get_error_stream(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "stderr"))
+}
+
+/// Blocks the VM until the process exits, returning its exit value.
+fn process_wait_for(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let status = child.lock().unwrap().wait();
+    match status {
+        Ok(status) => Ok(Some(Value::Int(exit_value(status)))),
+        Err(error) => Err(Unwind::Error(VmError::Io(error))),
+    }
+}
+
+/// The exit value, throwing `IllegalThreadStateException` while the process
+/// is running.
+fn process_exit_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let status = child.lock().unwrap().try_wait();
+    match status {
+        Ok(Some(status)) => Ok(Some(Value::Int(exit_value(status)))),
+        Ok(None) => Err(vm.throw_new(
+            "java/lang/IllegalThreadStateException",
+            Some("process hasn't exited".to_string()),
+        )),
+        Err(error) => Err(Unwind::Error(VmError::Io(error))),
+    }
+}
+
+fn process_is_alive(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let status = child.lock().unwrap().try_wait();
+    match status {
+        Ok(status) => Ok(Some(Value::Int(status.is_none() as i32))),
+        Err(error) => Err(Unwind::Error(VmError::Io(error))),
+    }
+}
+
+/// Asks the process to terminate, sending `SIGTERM` on Unix.
+fn process_destroy(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let mut child = child.lock().unwrap();
+    if let Ok(None) = child.try_wait() {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+        #[cfg(not(unix))]
+        let _ = child.kill();
+    }
+    Ok(None)
+}
+
+fn process_destroy_forcibly(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let result = child.lock().unwrap().kill();
+    // Killing a process which already exited fails, and is no error here
+    if let Err(error) = result {
+        if error.kind() != io::ErrorKind::InvalidInput {
+            return Err(io_exception(vm, error));
+        }
+    }
+    Ok(Some(args[0]))
+}
+
+fn process_pid(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let child = child(vm, args[0])?;
+    let pid = child.lock().unwrap().id();
+    Ok(Some(Value::Long(pid as i64)))
+}
+
+// =============================================================================
+// RUNTIME
+// =============================================================================
+
+/// `Runtime.exec(String)`, splitting the command at whitespace like the JDK's
+/// `StringTokenizer`.
+pub(crate) fn runtime_exec(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let command = String::from_utf16_lossy(&chars(vm, args[1])?);
+    let command: Vec<String> = command
+        .split([' ', '\t', '\n', '\r', '\x0c'])
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect();
+    if command.is_empty() {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("Empty command".to_string()),
+        ));
+    }
+
+    let process = start(vm, &command, Options::default())?;
+    Ok(Some(Value::Reference(Some(process))))
+}
+
+/// `Runtime.exec(String[])`.
+pub(crate) fn runtime_exec_array(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let command = strings(vm, args[1])?;
+    if command.is_empty() {
+        return Err(vm.throw_new("java/lang/IndexOutOfBoundsException", None));
+    }
+
+    let process = start(vm, &command, Options::default())?;
+    Ok(Some(Value::Reference(Some(process))))
+}
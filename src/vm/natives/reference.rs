@@ -0,0 +1,179 @@
+use crate::class::ClassAccessFlags;
+use crate::vm::natives::{non_null, BuiltinClass};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm};
+
+/// Slots of the fields of `Reference`, which declares them before any of
+/// its subclasses.
+pub(crate) const REFERENT_SLOT: usize = 0;
+pub(crate) const QUEUE_SLOT: usize = 1;
+pub(crate) const NEXT_SLOT: usize = 2;
+/// Slot of the head of a `ReferenceQueue`.
+pub(crate) const HEAD_SLOT: usize = 0;
+
+/// The built-in classes of `java.lang.ref`, whose referents the garbage
+/// collector clears.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        reference(),
+        BuiltinClass::new("java/lang/ref/SoftReference", "java/lang/ref/Reference")
+            .method("<init>", "(Ljava/lang/Object;)V", reference_init)
+            .method(
+                "<init>",
+                "(Ljava/lang/Object;Ljava/lang/ref/ReferenceQueue;)V",
+                reference_init,
+            ),
+        BuiltinClass::new("java/lang/ref/WeakReference", "java/lang/ref/Reference")
+            .method("<init>", "(Ljava/lang/Object;)V", reference_init)
+            .method(
+                "<init>",
+                "(Ljava/lang/Object;Ljava/lang/ref/ReferenceQueue;)V",
+                reference_init,
+            ),
+        BuiltinClass::new("java/lang/ref/PhantomReference", "java/lang/ref/Reference")
+            .method(
+                "<init>",
+                "(Ljava/lang/Object;Ljava/lang/ref/ReferenceQueue;)V",
+                reference_init,
+            )
+            .method("get", "()Ljava/lang/Object;", phantom_reference_get),
+        reference_queue(),
+    ]
+}
+
+// =============================================================================
+// REFERENCE
+// =============================================================================
+
+/// A reference is active until enqueued, when `next` links it into its queue
+/// (the last one linking to itself), and inactive once removed from the queue
+/// or if it has none, when `queue` is `null`.
+fn reference() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/lang/ref/Reference", "java/lang/Object")
+        .field("referent", "Ljava/lang/Object;")
+        .field("queue", "Ljava/lang/ref/ReferenceQueue;")
+        .field("next", "Ljava/lang/ref/Reference;")
+        .method("get", "()Ljava/lang/Object;", reference_get)
+        .method("refersTo", "(Ljava/lang/Object;)Z", reference_refers_to)
+        .method("clear", "()V", reference_clear)
+        .method("isEnqueued", "()Z", reference_is_enqueued)
+        .method("enqueue", "()Z", reference_enqueue)
+        .static_method(
+            "reachabilityFence",
+            "(Ljava/lang/Object;)V",
+            reference_reachability_fence,
+        );
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn reference_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "referent", args[1]);
+    vm.set_field(this, "queue", args.get(2).copied().unwrap_or(Value::NULL));
+    Ok(None)
+}
+
+fn reference_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "referent"))
+}
+
+fn phantom_reference_get(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::NULL))
+}
+
+fn reference_refers_to(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let refers = vm.field(this, "referent") == Some(args[1]);
+    Ok(Some(Value::Int(refers as i32)))
+}
+
+fn reference_clear(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "referent", Value::NULL);
+    Ok(None)
+}
+
+fn reference_is_enqueued(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let enqueued =
+        vm.field(this, "next") != Some(Value::NULL) && vm.field(this, "queue") != Some(Value::NULL);
+    Ok(Some(Value::Int(enqueued as i32)))
+}
+
+/// Clears the reference and adds it to its queue.
+fn reference_enqueue(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "referent", Value::NULL);
+    Ok(Some(Value::Int(enqueue(vm, this) as i32)))
+}
+
+/// Nothing is collected while a native runs.
+fn reference_reachability_fence(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(None)
+}
+
+/// Adds an active reference to its queue, returning whether it was.
+pub(crate) fn enqueue(vm: &mut Vm, reference: ObjectRef) -> bool {
+    let fields = vm.heap.get(reference).fields();
+    let queue = match (fields[QUEUE_SLOT], fields[NEXT_SLOT]) {
+        (Value::Reference(Some(queue)), Value::NULL) => queue,
+        _ => return false,
+    };
+
+    let next = match vm.heap.get(queue).fields()[HEAD_SLOT] {
+        Value::NULL => Value::Reference(Some(reference)),
+        head => head,
+    };
+    vm.heap.get_mut(reference).fields_mut()[NEXT_SLOT] = next;
+    vm.heap.get_mut(queue).fields_mut()[HEAD_SLOT] = Value::Reference(Some(reference));
+    true
+}
+
+// =============================================================================
+// REFERENCE QUEUE
+// =============================================================================
+
+/// Queues are filled by the garbage collector itself. Guest code runs on a
+/// single thread, which no other thread could enqueue a reference for while
+/// it waits, so removing with or without a timeout only polls the queue.
+fn reference_queue() -> BuiltinClass {
+    BuiltinClass::new("java/lang/ref/ReferenceQueue", "java/lang/Object")
+        .field("head", "Ljava/lang/ref/Reference;")
+        .method("<init>", "()V", reference_queue_init)
+        .method("poll", "()Ljava/lang/ref/Reference;", reference_queue_poll)
+        .method(
+            "remove",
+            "()Ljava/lang/ref/Reference;",
+            reference_queue_poll,
+        )
+        .method(
+            "remove",
+            "(J)Ljava/lang/ref/Reference;",
+            reference_queue_poll,
+        )
+}
+
+fn reference_queue_init(_: &mut Vm, _: &[Value]) -> Result<Option<Value>, Unwind> {
+    Ok(None)
+}
+
+fn reference_queue_poll(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let head = match vm.heap.get(this).fields()[HEAD_SLOT] {
+        Value::Reference(Some(head)) => head,
+        _ => return Ok(Some(Value::NULL)),
+    };
+
+    let next = vm.heap.get(head).fields()[NEXT_SLOT];
+    let rest = if next == Value::Reference(Some(head)) {
+        Value::NULL
+    } else {
+        next
+    };
+    vm.heap.get_mut(this).fields_mut()[HEAD_SLOT] = rest;
+    vm.heap.get_mut(head).fields_mut()[QUEUE_SLOT] = Value::NULL;
+    vm.heap.get_mut(head).fields_mut()[NEXT_SLOT] = Value::Reference(Some(head));
+    Ok(Some(Value::Reference(Some(head))))
+}
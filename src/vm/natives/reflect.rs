@@ -0,0 +1,785 @@
+use std::sync::Arc;
+
+use crate::class::descriptor::FieldType;
+use crate::class::{ClassAccessFlags, FieldAccessFlags};
+use crate::vm::heap::ArrayData;
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::lang::{chars, exception, mirrored_class, type_name};
+use crate::vm::natives::{int, non_null, BuiltinClass};
+use crate::vm::runtime::{ClassId, RuntimeField, RuntimeMethod};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.lang.reflect`. Like the JDK's own, the
+/// reflective objects refer to their member by the mirror of the declaring
+/// class and the index of the member among the ones it declares.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        BuiltinClass::interface("java/lang/reflect/Member")
+            .abstract_method("getDeclaringClass", "()Ljava/lang/Class;")
+            .abstract_method("getName", "()Ljava/lang/String;")
+            .abstract_method("getModifiers", "()I"),
+        accessible_object(),
+        executable(),
+        method(),
+        constructor(),
+        field(),
+        exception(
+            "java/lang/reflect/InvocationTargetException",
+            "java/lang/ReflectiveOperationException",
+        )
+        .method(
+            "getTargetException",
+            "()Ljava/lang/Throwable;",
+            invocation_target_exception_get_target,
+        ),
+    ]
+}
+
+// =============================================================================
+// CLASS MEMBERS
+// =============================================================================
+
+fn is_initializer(method: &RuntimeMethod) -> bool {
+    method.name == "<init>" || method.name == "<clinit>"
+}
+
+/// The reflective object of the declared method at the index, a
+/// `Constructor` for instance initializers.
+fn new_method(vm: &mut Vm, class: ClassId, slot: usize) -> Result<ObjectRef, Unwind> {
+    let method = vm.class(class).methods[slot].clone();
+    let reflect_class = if method.name == "<init>" {
+        "java/lang/reflect/Constructor"
+    } else {
+        "java/lang/reflect/Method"
+    };
+    new_member(vm, reflect_class, class, slot, &method.name)
+}
+
+fn new_member(
+    vm: &mut Vm,
+    reflect_class: &str,
+    class: ClassId,
+    slot: usize,
+    name: &str,
+) -> Result<ObjectRef, Unwind> {
+    let reflect_class = vm.load_class(LoaderId::BOOTSTRAP, reflect_class)?;
+    let member = vm.instantiate(reflect_class)?;
+    let mirror = vm.mirror(class)?;
+    let name = vm.intern_string(name.encode_utf16().collect())?;
+    vm.set_field(member, "clazz", Value::Reference(Some(mirror)));
+    vm.set_field(member, "slot", Value::Int(slot as i32));
+    vm.set_field(member, "name", Value::Reference(Some(name)));
+    Ok(member)
+}
+
+/// The declaring class and index of the member a reflective object refers to.
+fn member_slot(vm: &mut Vm, value: Value) -> Result<(ClassId, usize), Unwind> {
+    let this = non_null(vm, value)?;
+    let mirror = vm.field(this, "clazz").unwrap_or(Value::NULL);
+    let class = mirrored_class(vm, mirror)?;
+    let slot = vm.field(this, "slot").map_or(0, int) as usize;
+    Ok((class, slot))
+}
+
+fn reflected_method(vm: &mut Vm, value: Value) -> Result<Arc<RuntimeMethod>, Unwind> {
+    let (class, slot) = member_slot(vm, value)?;
+    Ok(vm.class(class).methods[slot].clone())
+}
+
+pub(crate) fn reflected_field(vm: &mut Vm, value: Value) -> Result<Arc<RuntimeField>, Unwind> {
+    let (class, slot) = member_slot(vm, value)?;
+    Ok(vm.class(class).fields[slot].clone())
+}
+
+/// An array of the reflective objects of the declared members at the
+/// indices.
+fn member_array(
+    vm: &mut Vm,
+    array_class: &str,
+    class: ClassId,
+    slots: Vec<usize>,
+    new: fn(&mut Vm, ClassId, usize) -> Result<ObjectRef, Unwind>,
+) -> Result<Option<Value>, Unwind> {
+    let mut members = Vec::with_capacity(slots.len());
+    for slot in slots {
+        members.push(Some(new(vm, class, slot)?));
+    }
+    let array = vm.allocate_array(array_class, ArrayData::Reference(members))?;
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+/// The indices of the declared methods matching the filter.
+fn method_slots(vm: &Vm, class: ClassId, filter: impl Fn(&RuntimeMethod) -> bool) -> Vec<usize> {
+    let methods = &vm.class(class).methods;
+    (0..methods.len())
+        .filter(|slot| filter(&methods[*slot]))
+        .collect()
+}
+
+pub(crate) fn class_get_declared_methods(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let slots = method_slots(vm, class, |method| !is_initializer(method));
+    member_array(vm, "[Ljava/lang/reflect/Method;", class, slots, new_method)
+}
+
+pub(crate) fn class_get_declared_constructors(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let slots = method_slots(vm, class, |method| method.name == "<init>");
+    member_array(
+        vm,
+        "[Ljava/lang/reflect/Constructor;",
+        class,
+        slots,
+        new_method,
+    )
+}
+
+pub(crate) fn class_get_declared_fields(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let slots = (0..vm.class(class).fields.len()).collect();
+    member_array(vm, "[Ljava/lang/reflect/Field;", class, slots, new_field)
+}
+
+pub(crate) fn class_get_declared_method(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    let slot = find_method(vm, class, &name, args[2])?;
+    Ok(Some(Value::Reference(Some(new_method(vm, class, slot)?))))
+}
+
+pub(crate) fn class_get_declared_constructor(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let slot = find_method(vm, class, "<init>", args[1])?;
+    Ok(Some(Value::Reference(Some(new_method(vm, class, slot)?))))
+}
+
+pub(crate) fn class_get_declared_field(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    let name = String::from_utf16_lossy(&chars(vm, args[1])?);
+    match vm
+        .class(class)
+        .fields
+        .iter()
+        .position(|field| field.name == *name)
+    {
+        Some(slot) => Ok(Some(Value::Reference(Some(new_field(vm, class, slot)?)))),
+        None => Err(vm.throw_new("java/lang/NoSuchFieldException", Some(name))),
+    }
+}
+
+/// The index of the declared method with the name and the parameter types of
+/// a `Class[]`, throwing `NoSuchMethodException` if there is none.
+fn find_method(
+    vm: &mut Vm,
+    class: ClassId,
+    name: &str,
+    parameter_types: Value,
+) -> Result<usize, Unwind> {
+    let mut parameters = Vec::new();
+    for mirror in objects(vm, parameter_types)? {
+        parameters.push(mirrored_class(vm, mirror)?);
+    }
+
+    let loader = vm.class(class).defining_loader;
+    for slot in method_slots(vm, class, |method| method.name == *name) {
+        let method = vm.class(class).methods[slot].clone();
+        if method.parsed_descriptor.parameters.len() != parameters.len() {
+            continue;
+        }
+        let mut matches = true;
+        for (parameter, expected) in method.parsed_descriptor.parameters.iter().zip(&parameters) {
+            matches &= type_class(vm, loader, Some(parameter))? == *expected;
+        }
+        if matches {
+            return Ok(slot);
+        }
+    }
+
+    let names: Vec<String> = parameters
+        .iter()
+        .map(|parameter| vm.class(*parameter).java_name())
+        .collect();
+    let message = format!(
+        "{}.{}({})",
+        vm.class(class).java_name(),
+        name,
+        names.join(", ")
+    );
+    Err(vm.throw_new("java/lang/NoSuchMethodException", Some(message)))
+}
+
+/// Creates an instance of the class with its nullary constructor, letting
+/// the exceptions of the constructor propagate unwrapped, after checking
+/// that the calling class may access the constructor.
+pub(crate) fn class_new_instance(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let class = mirrored_class(vm, args[0])?;
+    new_instance(vm, class, true)
+}
+
+/// Creates an instance of the class with its nullary constructor, checking
+/// the access of the calling class to it when asked to.
+pub(crate) fn new_instance(
+    vm: &mut Vm,
+    class: ClassId,
+    checked: bool,
+) -> Result<Option<Value>, Unwind> {
+    let constructor = vm
+        .class(class)
+        .methods
+        .iter()
+        .find(|method| method.name == "<init>" && method.descriptor == "()V")
+        .cloned();
+    let constructor = match constructor {
+        Some(constructor) => constructor,
+        None => {
+            let message = vm.class(class).java_name();
+            return Err(vm.throw_new("java/lang/InstantiationException", Some(message)));
+        }
+    };
+    if checked {
+        if let Some(frame) = vm.thread.frames.last() {
+            let caller = frame.class;
+            vm.check_reflective_access(caller, class, constructor.access_flags.bits())?;
+        }
+    }
+
+    let object = allocate_instance(vm, class)?;
+    vm.invoke(constructor, &[Value::Reference(Some(object))])?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+/// Initializes the class and allocates an instance of it, throwing
+/// `InstantiationException` for abstract classes, interfaces, arrays and
+/// primitive types.
+fn allocate_instance(vm: &mut Vm, class: ClassId) -> Result<ObjectRef, Unwind> {
+    let runtime_class = vm.class(class);
+    if runtime_class
+        .access_flags
+        .contains(ClassAccessFlags::ABSTRACT)
+    {
+        let message = runtime_class.java_name();
+        return Err(vm.throw_new("java/lang/InstantiationException", Some(message)));
+    }
+
+    vm.initialize_class(class)?;
+    vm.instantiate(class)
+}
+
+// =============================================================================
+// TYPES
+// =============================================================================
+
+/// The class of a type used by a class of the loader; `None` is `void`.
+fn type_class(
+    vm: &mut Vm,
+    loader: LoaderId,
+    field_type: Option<&FieldType>,
+) -> Result<ClassId, Unwind> {
+    match field_type {
+        None => vm.primitive_class("void"),
+        Some(FieldType::Object(name)) => vm.load_class(loader, name),
+        Some(array @ FieldType::Array(_)) => vm.load_class(loader, &array.to_string()),
+        Some(primitive) => vm.primitive_class(&type_name(primitive)),
+    }
+}
+
+fn type_mirror(
+    vm: &mut Vm,
+    loader: LoaderId,
+    field_type: Option<&FieldType>,
+) -> Result<Option<Value>, Unwind> {
+    let class = type_class(vm, loader, field_type)?;
+    Ok(Some(Value::Reference(Some(vm.mirror(class)?))))
+}
+
+/// The elements of an `Object[]` argument, `null` being an empty array.
+fn objects(vm: &mut Vm, value: Value) -> Result<Vec<Value>, Unwind> {
+    let array = match value {
+        Value::Reference(Some(array)) => array,
+        _ => return Ok(Vec::new()),
+    };
+    match vm.heap.get(array).array() {
+        Some(ArrayData::Reference(elements)) => Ok(elements
+            .iter()
+            .map(|element| Value::Reference(*element))
+            .collect()),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a reference array",
+            array
+        )))),
+    }
+}
+
+/// The wrapper class of a primitive type.
+fn wrapper(field_type: &FieldType) -> Option<&'static str> {
+    Some(match field_type {
+        FieldType::Boolean => "java/lang/Boolean",
+        FieldType::Byte => "java/lang/Byte",
+        FieldType::Char => "java/lang/Character",
+        FieldType::Short => "java/lang/Short",
+        FieldType::Int => "java/lang/Integer",
+        FieldType::Long => "java/lang/Long",
+        FieldType::Float => "java/lang/Float",
+        FieldType::Double => "java/lang/Double",
+        _ => return None,
+    })
+}
+
+/// The primitive type a wrapper class boxes.
+fn unwrapped(class: &str) -> Option<FieldType> {
+    Some(match class {
+        "java/lang/Boolean" => FieldType::Boolean,
+        "java/lang/Byte" => FieldType::Byte,
+        "java/lang/Character" => FieldType::Char,
+        "java/lang/Short" => FieldType::Short,
+        "java/lang/Integer" => FieldType::Int,
+        "java/lang/Long" => FieldType::Long,
+        "java/lang/Float" => FieldType::Float,
+        "java/lang/Double" => FieldType::Double,
+        _ => return None,
+    })
+}
+
+/// Boxes a value of a primitive type into a new wrapper object, leaving
+/// references as they are.
+fn box_value(vm: &mut Vm, value: Value, field_type: &FieldType) -> Result<Value, Unwind> {
+    let wrapper = match wrapper(field_type) {
+        Some(wrapper) => wrapper,
+        None => return Ok(value),
+    };
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, wrapper)?;
+    vm.initialize_class(class)?;
+    let object = vm.instantiate(class)?;
+    vm.set_field(object, "value", value);
+    Ok(Value::Reference(Some(object)))
+}
+
+/// Converts an argument to the type, unboxing and widening primitives (JLS
+/// 5.3), and throws `IllegalArgumentException` if it cannot be.
+fn unbox(
+    vm: &mut Vm,
+    value: Value,
+    field_type: &FieldType,
+    loader: LoaderId,
+) -> Result<Value, Unwind> {
+    let converted = match value {
+        Value::Reference(None) if field_type.is_reference() => Some(value),
+        Value::Reference(Some(object)) if field_type.is_reference() => {
+            let class = type_class(vm, loader, Some(field_type))?;
+            Some(value).filter(|_| vm.is_instance(object, class))
+        }
+        Value::Reference(Some(object)) => match unwrapped(&vm.class(vm.class_of(object)).name) {
+            Some(primitive) => vm
+                .field(object, "value")
+                .and_then(|value| widen(value, &primitive, field_type)),
+            None => None,
+        },
+        _ => None,
+    };
+
+    match converted {
+        Some(value) => Ok(value),
+        None => Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("argument type mismatch".to_string()),
+        )),
+    }
+}
+
+/// Applies a widening primitive conversion (JLS 5.1.2), or the identity one.
+fn widen(value: Value, from: &FieldType, to: &FieldType) -> Option<Value> {
+    use FieldType::*;
+
+    let widens = from == to
+        || match from {
+            Byte => matches!(to, Short | Int | Long | Float | Double),
+            Short | Char => matches!(to, Int | Long | Float | Double),
+            Int => matches!(to, Long | Float | Double),
+            Long => matches!(to, Float | Double),
+            Float => matches!(to, Double),
+            _ => false,
+        };
+    if !widens {
+        return None;
+    }
+
+    Some(match (value, to) {
+        (Value::Int(value), Long) => Value::Long(value as i64),
+        (Value::Int(value), Float) => Value::Float(value as f32),
+        (Value::Int(value), Double) => Value::Double(value as f64),
+        (Value::Long(value), Float) => Value::Float(value as f32),
+        (Value::Long(value), Double) => Value::Double(value as f64),
+        (Value::Float(value), Double) => Value::Double(value as f64),
+        (value, _) => value,
+    })
+}
+
+// =============================================================================
+// ACCESSIBLE OBJECT
+// =============================================================================
+
+/// Suppressing the access checks lets reflection reach the members the
+/// caller could not access, and set final instance fields.
+fn accessible_object() -> BuiltinClass {
+    BuiltinClass::new("java/lang/reflect/AccessibleObject", "java/lang/Object")
+        .field("override", "Z")
+        .method("setAccessible", "(Z)V", accessible_object_set_accessible)
+        .method("isAccessible", "()Z", accessible_object_is_accessible)
+}
+
+fn accessible_object_set_accessible(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.set_field(this, "override", args[1]);
+    Ok(None)
+}
+
+fn accessible_object_is_accessible(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "override"))
+}
+
+/// Throws `IllegalAccessException` unless the access checks of the reflected
+/// member were suppressed, or the calling class may access it.
+fn check_access(vm: &mut Vm, this: Value, class: ClassId, flags: u16) -> Result<(), Unwind> {
+    let this = non_null(vm, this)?;
+    if vm.field(this, "override") == Some(Value::Int(1)) {
+        return Ok(());
+    }
+    match vm.thread.frames.last() {
+        Some(frame) => {
+            let caller = frame.class;
+            vm.check_reflective_access(caller, class, flags)
+        }
+        None => Ok(()),
+    }
+}
+
+fn member_get_declaring_class(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "clazz"))
+}
+
+fn member_get_name(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "name"))
+}
+
+// =============================================================================
+// METHOD AND CONSTRUCTOR
+// =============================================================================
+
+fn executable() -> BuiltinClass {
+    let mut class = BuiltinClass::new(
+        "java/lang/reflect/Executable",
+        "java/lang/reflect/AccessibleObject",
+    )
+    .implements("java/lang/reflect/Member")
+    .field("clazz", "Ljava/lang/Class;")
+    .field("slot", "I")
+    .field("name", "Ljava/lang/String;")
+    .method(
+        "getDeclaringClass",
+        "()Ljava/lang/Class;",
+        member_get_declaring_class,
+    )
+    .method("getName", "()Ljava/lang/String;", member_get_name)
+    .method("getModifiers", "()I", executable_get_modifiers)
+    .method(
+        "getParameterTypes",
+        "()[Ljava/lang/Class;",
+        executable_get_parameter_types,
+    )
+    .method("getParameterCount", "()I", executable_get_parameter_count);
+    class.access_flags |= ClassAccessFlags::ABSTRACT;
+    class
+}
+
+fn method() -> BuiltinClass {
+    BuiltinClass::new("java/lang/reflect/Method", "java/lang/reflect/Executable")
+        .method(
+            "invoke",
+            "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+            method_invoke,
+        )
+        .method(
+            "getReturnType",
+            "()Ljava/lang/Class;",
+            method_get_return_type,
+        )
+}
+
+fn constructor() -> BuiltinClass {
+    BuiltinClass::new(
+        "java/lang/reflect/Constructor",
+        "java/lang/reflect/Executable",
+    )
+    .method(
+        "newInstance",
+        "([Ljava/lang/Object;)Ljava/lang/Object;",
+        constructor_new_instance,
+    )
+}
+
+fn executable_get_modifiers(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let method = reflected_method(vm, args[0])?;
+    Ok(Some(Value::Int(method.access_flags.bits() as i32)))
+}
+
+fn executable_get_parameter_types(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let method = reflected_method(vm, args[0])?;
+    let loader = vm.class(method.class).defining_loader;
+    let mut mirrors = Vec::with_capacity(method.parsed_descriptor.parameters.len());
+    for parameter in &method.parsed_descriptor.parameters {
+        let class = type_class(vm, loader, Some(parameter))?;
+        mirrors.push(Some(vm.mirror(class)?));
+    }
+
+    let array = vm.allocate_array("[Ljava/lang/Class;", ArrayData::Reference(mirrors))?;
+    Ok(Some(Value::Reference(Some(array))))
+}
+
+fn executable_get_parameter_count(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let method = reflected_method(vm, args[0])?;
+    let count = method.parsed_descriptor.parameters.len();
+    Ok(Some(Value::Int(count as i32)))
+}
+
+fn method_get_return_type(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let method = reflected_method(vm, args[0])?;
+    let loader = vm.class(method.class).defining_loader;
+    type_mirror(vm, loader, method.parsed_descriptor.return_type.as_ref())
+}
+
+/// Invokes the method, selecting instance methods by the class of the
+/// receiver, and boxes its result (`null` for `void` methods). The caller
+/// must be able to access the method, unless its checks were suppressed.
+fn method_invoke(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let method = reflected_method(vm, args[0])?;
+    check_access(vm, args[0], method.class, method.access_flags.bits())?;
+    let arguments = objects(vm, args[2])?;
+
+    let mut values = Vec::with_capacity(arguments.len() + 1);
+    let target = if method.is_static() {
+        vm.initialize_class(method.class)?;
+        method.clone()
+    } else {
+        let receiver = non_null(vm, args[1])?;
+        if !vm.is_instance(receiver, method.class) {
+            return Err(vm.throw_new(
+                "java/lang/IllegalArgumentException",
+                Some("object is not an instance of declaring class".to_string()),
+            ));
+        }
+        values.push(args[1]);
+        if method.is_private() {
+            method.clone()
+        } else {
+            let class = vm.class_of(receiver);
+            vm.find_virtual(class, &method.name, &method.descriptor)
+                .unwrap_or_else(|| method.clone())
+        }
+    };
+    convert_arguments(vm, &method, arguments, &mut values)?;
+
+    let result = invoke_target(vm, target, &values)?;
+    match (&method.parsed_descriptor.return_type, result) {
+        (Some(return_type), Some(result)) => Ok(Some(box_value(vm, result, return_type)?)),
+        _ => Ok(Some(Value::NULL)),
+    }
+}
+
+fn constructor_new_instance(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let constructor = reflected_method(vm, args[0])?;
+    check_access(
+        vm,
+        args[0],
+        constructor.class,
+        constructor.access_flags.bits(),
+    )?;
+    let arguments = objects(vm, args[1])?;
+
+    let object = allocate_instance(vm, constructor.class)?;
+    let mut values = vec![Value::Reference(Some(object))];
+    convert_arguments(vm, &constructor, arguments, &mut values)?;
+    invoke_target(vm, constructor, &values)?;
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+/// Converts the arguments to the parameter types of the method, appending
+/// them to the values it is invoked with.
+fn convert_arguments(
+    vm: &mut Vm,
+    method: &RuntimeMethod,
+    arguments: Vec<Value>,
+    values: &mut Vec<Value>,
+) -> Result<(), Unwind> {
+    let parameters = &method.parsed_descriptor.parameters;
+    if arguments.len() != parameters.len() {
+        return Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("wrong number of arguments".to_string()),
+        ));
+    }
+
+    let loader = vm.class(method.class).defining_loader;
+    for (argument, parameter) in arguments.into_iter().zip(parameters) {
+        values.push(unbox(vm, argument, parameter, loader)?);
+    }
+    Ok(())
+}
+
+/// Invokes the method, wrapping the exceptions it throws into an
+/// `InvocationTargetException`.
+fn invoke_target(
+    vm: &mut Vm,
+    method: Arc<RuntimeMethod>,
+    values: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    match vm.invoke(method, values) {
+        Err(Unwind::Throw(target)) => {
+            let class = vm.load_class(
+                LoaderId::BOOTSTRAP,
+                "java/lang/reflect/InvocationTargetException",
+            )?;
+            vm.initialize_class(class)?;
+            let exception = vm.instantiate(class)?;
+            vm.set_field(exception, "cause", Value::Reference(Some(target)));
+            vm.fill_in_stack_trace(exception);
+            vm.fire_exception_thrown(exception);
+            Err(Unwind::Throw(exception))
+        }
+        result => result,
+    }
+}
+
+fn invocation_target_exception_get_target(
+    vm: &mut Vm,
+    args: &[Value],
+) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    Ok(vm.field(this, "cause"))
+}
+
+// =============================================================================
+// FIELD
+// =============================================================================
+
+fn field() -> BuiltinClass {
+    BuiltinClass::new(
+        "java/lang/reflect/Field",
+        "java/lang/reflect/AccessibleObject",
+    )
+    .implements("java/lang/reflect/Member")
+    .field("clazz", "Ljava/lang/Class;")
+    .field("slot", "I")
+    .field("name", "Ljava/lang/String;")
+    .method(
+        "getDeclaringClass",
+        "()Ljava/lang/Class;",
+        member_get_declaring_class,
+    )
+    .method("getName", "()Ljava/lang/String;", member_get_name)
+    .method("getModifiers", "()I", field_get_modifiers)
+    .method("getType", "()Ljava/lang/Class;", field_get_type)
+    .method("get", "(Ljava/lang/Object;)Ljava/lang/Object;", field_get)
+    .method("set", "(Ljava/lang/Object;Ljava/lang/Object;)V", field_set)
+}
+
+fn new_field(vm: &mut Vm, class: ClassId, slot: usize) -> Result<ObjectRef, Unwind> {
+    let name = vm.class(class).fields[slot].name.clone();
+    new_member(vm, "java/lang/reflect/Field", class, slot, &name)
+}
+
+fn field_get_modifiers(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[0])?;
+    Ok(Some(Value::Int(field.access_flags.bits() as i32)))
+}
+
+fn field_get_type(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[0])?;
+    let loader = vm.class(field.class).defining_loader;
+    type_mirror(vm, loader, Some(&field.field_type))
+}
+
+/// The object whose instance field is accessed, throwing
+/// `IllegalArgumentException` unless it is an instance of the declaring
+/// class.
+fn field_receiver(vm: &mut Vm, field: &RuntimeField, value: Value) -> Result<ObjectRef, Unwind> {
+    let object = non_null(vm, value)?;
+    if vm.is_instance(object, field.class) {
+        return Ok(object);
+    }
+
+    let message = format!(
+        "Can not access {} field {}.{} on {}",
+        type_name(&field.field_type),
+        vm.class(field.class).java_name(),
+        field.name,
+        vm.class(vm.class_of(object)).java_name()
+    );
+    Err(vm.throw_new("java/lang/IllegalArgumentException", Some(message)))
+}
+
+fn field_get(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[0])?;
+    check_access(vm, args[0], field.class, field.access_flags.bits())?;
+    let value = if field.is_static() {
+        vm.initialize_class(field.class)?;
+        vm.class(field.class).static_values[field.slot]
+    } else {
+        let object = field_receiver(vm, &field, args[1])?;
+        vm.heap.get(object).fields()[field.slot]
+    };
+
+    Ok(Some(box_value(vm, value, &field.field_type)?))
+}
+
+/// Sets the field to the unboxed value. Final fields can only be set if they
+/// are instance fields whose access checks were suppressed.
+fn field_set(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let field = reflected_field(vm, args[0])?;
+    check_access(vm, args[0], field.class, field.access_flags.bits())?;
+    let this = non_null(vm, args[0])?;
+    let overridden = vm.field(this, "override") == Some(Value::Int(1));
+    if field.access_flags.contains(FieldAccessFlags::FINAL) && (field.is_static() || !overridden) {
+        let message = format!(
+            "Can not set final {} field {}.{}",
+            type_name(&field.field_type),
+            vm.class(field.class).java_name(),
+            field.name
+        );
+        return Err(vm.throw_new("java/lang/IllegalAccessException", Some(message)));
+    }
+
+    let loader = vm.class(field.class).defining_loader;
+    if field.is_static() {
+        let value = unbox(vm, args[2], &field.field_type, loader)?;
+        vm.initialize_class(field.class)?;
+        vm.class_mut(field.class).static_values[field.slot] = value;
+    } else {
+        let object = field_receiver(vm, &field, args[1])?;
+        let value = unbox(vm, args[2], &field.field_type, loader)?;
+        vm.heap.get_mut(object).fields_mut()[field.slot] = value;
+    }
+    Ok(None)
+}
@@ -0,0 +1,195 @@
+use crate::class::ClassAccessFlags;
+use crate::vm::heap::{ArrayData, ObjectData};
+use crate::vm::loader::LoaderId;
+use crate::vm::natives::lang::{exception, loader_object, mirrored_class, object_loader};
+use crate::vm::natives::reflect::new_instance;
+use crate::vm::natives::{int, non_null, BuiltinClass};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm};
+
+/// The built-in classes of `java.util`.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![array_iterator()]
+}
+
+/// The classes of `java.util` the VM defines when no class path provides the
+/// JDK's.
+pub fn fallbacks() -> Vec<BuiltinClass> {
+    vec![
+        // Of `java.lang`, for iterating a `ServiceLoader` with for-each
+        BuiltinClass::interface("java/lang/Iterable")
+            .abstract_method("iterator", "()Ljava/util/Iterator;"),
+        BuiltinClass::interface("java/util/Iterator")
+            .abstract_method("hasNext", "()Z")
+            .abstract_method("next", "()Ljava/lang/Object;"),
+        BuiltinClass::interface("java/util/Enumeration")
+            .abstract_method("hasMoreElements", "()Z")
+            .abstract_method("nextElement", "()Ljava/lang/Object;"),
+        service_loader(),
+        exception("java/util/ServiceConfigurationError", "java/lang/Error"),
+        exception(
+            "java/util/NoSuchElementException",
+            "java/lang/RuntimeException",
+        ),
+    ]
+}
+
+// =============================================================================
+// ARRAY ITERATOR
+// =============================================================================
+
+/// The `Iterator` and `Enumeration` of the elements the natives collect,
+/// like the URLs of `ClassLoader.getResources`.
+fn array_iterator() -> BuiltinClass {
+    let mut class = BuiltinClass::new("sun/misc/ArrayIterator", "java/lang/Object")
+        .implements("java/util/Iterator")
+        .implements("java/util/Enumeration")
+        .field("elements", "[Ljava/lang/Object;")
+        .field("next", "I")
+        .method("hasNext", "()Z", array_iterator_has_next)
+        .method("next", "()Ljava/lang/Object;", array_iterator_next)
+        .method("hasMoreElements", "()Z", array_iterator_has_next)
+        .method("nextElement", "()Ljava/lang/Object;", array_iterator_next);
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// Creates an iterator over the elements.
+pub(crate) fn new_array_iterator(
+    vm: &mut Vm,
+    elements: Vec<Option<ObjectRef>>,
+) -> Result<ObjectRef, Unwind> {
+    let elements = vm.allocate_array("[Ljava/lang/Object;", ArrayData::Reference(elements))?;
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "sun/misc/ArrayIterator")?;
+    let object = vm.instantiate(class)?;
+    vm.set_field(object, "elements", Value::Reference(Some(elements)));
+    Ok(object)
+}
+
+/// The elements of the iterator and the index of the next one.
+fn iterator_state(
+    vm: &mut Vm,
+    this: Value,
+) -> Result<(ObjectRef, Vec<Option<ObjectRef>>, usize), Unwind> {
+    let this = non_null(vm, this)?;
+    let elements = match vm.field(this, "elements") {
+        Some(Value::Reference(Some(elements))) => match &vm.heap.get(elements).data {
+            ObjectData::Array(ArrayData::Reference(elements)) => elements.clone(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+    let next = int(vm.field(this, "next").unwrap_or(Value::Int(0))) as usize;
+    Ok((this, elements, next))
+}
+
+fn array_iterator_has_next(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (_, elements, next) = iterator_state(vm, args[0])?;
+    Ok(Some(Value::Int((next < elements.len()) as i32)))
+}
+
+fn array_iterator_next(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (this, elements, next) = iterator_state(vm, args[0])?;
+    match elements.get(next) {
+        Some(element) => {
+            vm.set_field(this, "next", Value::Int(next as i32 + 1));
+            Ok(Some(Value::Reference(*element)))
+        }
+        None => Err(vm.throw_new("java/util/NoSuchElementException", None)),
+    }
+}
+
+// =============================================================================
+// SERVICE LOADER
+// =============================================================================
+
+/// `java.util.ServiceLoader`, instantiating the providers the loaders
+/// declare in `META-INF/services` when iterated instead of lazily.
+fn service_loader() -> BuiltinClass {
+    let mut class = BuiltinClass::new("java/util/ServiceLoader", "java/lang/Object")
+        .implements("java/lang/Iterable")
+        .field("service", "Ljava/lang/Class;")
+        .field("loader", "Ljava/lang/ClassLoader;")
+        .static_method(
+            "load",
+            "(Ljava/lang/Class;)Ljava/util/ServiceLoader;",
+            service_loader_load,
+        )
+        .static_method(
+            "load",
+            "(Ljava/lang/Class;Ljava/lang/ClassLoader;)Ljava/util/ServiceLoader;",
+            service_loader_load,
+        )
+        .method(
+            "iterator",
+            "()Ljava/util/Iterator;",
+            service_loader_iterator,
+        )
+        .method("toString", "()Ljava/lang/String;", service_loader_to_string);
+    class.access_flags |= ClassAccessFlags::FINAL;
+    class
+}
+
+/// Looks the providers up through the loader, the application loader when
+/// it is missing or `null`.
+fn service_loader_load(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    non_null(vm, args[0])?;
+    let loader = match args.get(1) {
+        Some(Value::Reference(Some(loader))) => Value::Reference(Some(*loader)),
+        _ => loader_object(vm, LoaderId::APPLICATION)?,
+    };
+
+    let class = vm.load_class(LoaderId::BOOTSTRAP, "java/util/ServiceLoader")?;
+    let object = vm.instantiate(class)?;
+    vm.set_field(object, "service", args[0]);
+    vm.set_field(object, "loader", loader);
+    Ok(Some(Value::Reference(Some(object))))
+}
+
+fn service_loader_iterator(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let service = mirrored_class(vm, vm.field(this, "service").unwrap_or(Value::NULL))?;
+    let loader = object_loader(vm, vm.field(this, "loader").unwrap_or(Value::NULL))?;
+    let name = vm.class(service).java_name();
+
+    let providers = match vm.loaders.service_providers(loader, &name) {
+        Ok(providers) => providers,
+        Err(error) => return Err(configuration_error(vm, &name, &error.to_string())),
+    };
+    let mut instances = Vec::with_capacity(providers.len());
+    for provider in providers {
+        let class = match vm.load_class(loader, &provider.replace('.', "/")) {
+            Ok(class) => class,
+            Err(Unwind::Throw(_)) => {
+                let message = format!("Provider {} not found", provider);
+                return Err(configuration_error(vm, &name, &message));
+            }
+            Err(unwind) => return Err(unwind),
+        };
+        if !vm.is_assignable(class, service) {
+            let message = format!("Provider {} not a subtype", provider);
+            return Err(configuration_error(vm, &name, &message));
+        }
+        match new_instance(vm, class, false)? {
+            Some(Value::Reference(instance)) => instances.push(instance),
+            _ => unreachable!("newInstance returns an object"),
+        }
+    }
+
+    let iterator = new_array_iterator(vm, instances)?;
+    Ok(Some(Value::Reference(Some(iterator))))
+}
+
+fn service_loader_to_string(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let service = mirrored_class(vm, vm.field(this, "service").unwrap_or(Value::NULL))?;
+    let string = format!("java.util.ServiceLoader[{}]", vm.class(service).java_name());
+    let string = vm.create_string(string.encode_utf16().collect())?;
+    Ok(Some(Value::Reference(Some(string))))
+}
+
+/// Throws a `ServiceConfigurationError` about the service.
+fn configuration_error(vm: &mut Vm, service: &str, message: &str) -> Unwind {
+    let message = format!("{}: {}", service, message);
+    vm.throw_new("java/util/ServiceConfigurationError", Some(message))
+}
@@ -0,0 +1,512 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::vm::heap::{ArrayData, NativeData};
+use crate::vm::natives::lang::exception;
+use crate::vm::natives::{byte_array, int, non_null, BuiltinClass};
+use crate::vm::value::{ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The built-in classes of `java.util.zip`.
+pub fn classes() -> Vec<BuiltinClass> {
+    vec![
+        BuiltinClass::interface("java/util/zip/Checksum")
+            .abstract_method("update", "(I)V")
+            .abstract_method("update", "([BII)V")
+            .abstract_method("getValue", "()J")
+            .abstract_method("reset", "()V"),
+        crc32(),
+        inflater(),
+        deflater(),
+        exception("java/util/zip/DataFormatException", "java/lang/Exception"),
+    ]
+}
+
+/// The bytes of `array` between `offset` and `offset + length`, throwing
+/// `ArrayIndexOutOfBoundsException` unless they are within the array.
+fn checked_range(
+    vm: &mut Vm,
+    array: ObjectRef,
+    offset: Value,
+    length: Value,
+) -> Result<Range<usize>, Unwind> {
+    let size = vm.heap.get(array).array().map_or(0, ArrayData::len);
+    let (offset, length) = (int(offset), int(length));
+    if offset < 0 || length < 0 || offset as usize + length as usize > size {
+        return Err(vm.throw_new("java/lang/ArrayIndexOutOfBoundsException", None));
+    }
+    Ok(offset as usize..offset as usize + length as usize)
+}
+
+/// The range of a `(byte[] b, int off, int len)` argument triple starting at
+/// `args[index]`, or all of `b` without the offset and length.
+fn byte_range(
+    vm: &mut Vm,
+    args: &[Value],
+    index: usize,
+) -> Result<(ObjectRef, Range<usize>), Unwind> {
+    let array = non_null(vm, args[index])?;
+    match args.get(index + 1..index + 3) {
+        Some([offset, length]) => Ok((array, checked_range(vm, array, *offset, *length)?)),
+        _ => {
+            let length = vm.heap.get(array).array().map_or(0, ArrayData::len);
+            Ok((array, 0..length))
+        }
+    }
+}
+
+/// Copies the bytes into the array from `offset` on.
+fn write_bytes(vm: &mut Vm, array: ObjectRef, offset: usize, bytes: &[u8]) {
+    if let Some(ArrayData::Byte(values)) = vm.heap.get_mut(array).array_mut() {
+        for (value, byte) in values[offset..].iter_mut().zip(bytes) {
+            *value = *byte as i8;
+        }
+    }
+}
+
+// =============================================================================
+// CRC32
+// =============================================================================
+
+fn crc32() -> BuiltinClass {
+    BuiltinClass::new("java/util/zip/CRC32", "java/lang/Object")
+        .implements("java/util/zip/Checksum")
+        .method("<init>", "()V", crc32_reset)
+        .method("update", "(I)V", crc32_update_byte)
+        .method("update", "([B)V", crc32_update_bytes)
+        .method("update", "([BII)V", crc32_update_bytes)
+        .method("getValue", "()J", crc32_get_value)
+        .method("reset", "()V", crc32_reset)
+}
+
+/// Applies `function` to the checksum of a `CRC32`.
+fn with_checksum<T>(
+    vm: &mut Vm,
+    this: Value,
+    function: impl FnOnce(&mut crc32fast::Hasher) -> T,
+) -> Result<T, Unwind> {
+    let this = non_null(vm, this)?;
+    match &mut vm.heap.get_mut(this).native {
+        NativeData::Checksum(hasher) => Ok(function(hasher)),
+        _ => Err(Unwind::Error(VmError::Internal(format!(
+            "{} is not a checksum",
+            this
+        )))),
+    }
+}
+
+fn crc32_reset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.heap.get_mut(this).native = NativeData::Checksum(crc32fast::Hasher::new());
+    Ok(None)
+}
+
+fn crc32_update_byte(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let byte = int(args[1]) as u8;
+    with_checksum(vm, args[0], |hasher| hasher.update(&[byte]))?;
+    Ok(None)
+}
+
+fn crc32_update_bytes(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let (array, range) = byte_range(vm, args, 1)?;
+    let bytes = byte_array(vm, Value::Reference(Some(array)))?;
+    with_checksum(vm, args[0], |hasher| hasher.update(&bytes[range]))?;
+    Ok(None)
+}
+
+fn crc32_get_value(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let value = with_checksum(vm, args[0], |hasher| hasher.clone().finalize())?;
+    Ok(Some(Value::Long(value as i64)))
+}
+
+// =============================================================================
+// INFLATER
+// =============================================================================
+
+/// The state of a `java.util.zip.Inflater`, holding on to a copy of the
+/// input set last until it is consumed.
+#[derive(Debug)]
+pub struct Inflater {
+    stream: Decompress,
+    zlib_header: bool,
+    input: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+fn inflater() -> BuiltinClass {
+    BuiltinClass::new("java/util/zip/Inflater", "java/lang/Object")
+        .method("<init>", "()V", inflater_init)
+        .method("<init>", "(Z)V", inflater_init)
+        .method("setInput", "([B)V", inflater_set_input)
+        .method("setInput", "([BII)V", inflater_set_input)
+        .method("inflate", "([B)I", inflater_inflate)
+        .method("inflate", "([BII)I", inflater_inflate)
+        .method("needsInput", "()Z", inflater_needs_input)
+        .method("needsDictionary", "()Z", inflater_needs_dictionary)
+        .method("finished", "()Z", inflater_finished)
+        .method("getRemaining", "()I", inflater_get_remaining)
+        .method("getTotalIn", "()I", inflater_get_total_in)
+        .method("getTotalOut", "()I", inflater_get_total_out)
+        .method("getBytesRead", "()J", inflater_get_bytes_read)
+        .method("getBytesWritten", "()J", inflater_get_bytes_written)
+        .method("reset", "()V", inflater_reset)
+        .method("end", "()V", zip_end)
+}
+
+/// The state of an `Inflater` or `Deflater`, throwing `NullPointerException`
+/// once it has been ended like the JDK does.
+fn stream_state<T>(
+    vm: &mut Vm,
+    this: Value,
+    name: &str,
+    state: impl FnOnce(&NativeData) -> Option<Arc<Mutex<T>>>,
+) -> Result<Arc<Mutex<T>>, Unwind> {
+    let object = non_null(vm, this)?;
+    match state(&vm.heap.get(object).native) {
+        Some(state) => Ok(state),
+        None => Err(vm.throw_new(
+            "java/lang/NullPointerException",
+            Some(format!("{} has been closed", name)),
+        )),
+    }
+}
+
+fn inflater_state(vm: &mut Vm, this: Value) -> Result<Arc<Mutex<Inflater>>, Unwind> {
+    stream_state(vm, this, "Inflater", |native| match native {
+        NativeData::Inflater(state) => Some(state.clone()),
+        _ => None,
+    })
+}
+
+/// Frees the state of an `Inflater` or `Deflater`, which fail from then on.
+fn zip_end(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    vm.heap.get_mut(this).native = NativeData::None;
+    Ok(None)
+}
+
+/// Creates an inflater for zlib streams, or for raw deflate streams as found
+/// in zip files if `nowrap` is set.
+fn inflater_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let zlib_header = args.get(1).is_none_or(|nowrap| int(*nowrap) == 0);
+    vm.heap.get_mut(this).native = NativeData::Inflater(Arc::new(Mutex::new(Inflater {
+        stream: Decompress::new(zlib_header),
+        zlib_header,
+        input: Vec::new(),
+        position: 0,
+        finished: false,
+    })));
+    Ok(None)
+}
+
+fn inflater_set_input(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let (array, range) = byte_range(vm, args, 1)?;
+    let bytes = byte_array(vm, Value::Reference(Some(array)))?;
+    let mut state = state.lock().unwrap();
+    state.input = bytes[range].to_vec();
+    state.position = 0;
+    Ok(None)
+}
+
+/// Inflates as much of the input as fits the output range, returning the
+/// number of bytes written and throwing `DataFormatException` for corrupt
+/// input.
+fn inflater_inflate(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let (array, range) = byte_range(vm, args, 1)?;
+    let mut output = vec![0; range.len()];
+    let result = {
+        let mut state = state.lock().unwrap();
+        let state = &mut *state;
+        let (total_in, total_out) = (state.stream.total_in(), state.stream.total_out());
+        let status = state.stream.decompress(
+            &state.input[state.position..],
+            &mut output,
+            FlushDecompress::None,
+        );
+        state.position += (state.stream.total_in() - total_in) as usize;
+        let written = (state.stream.total_out() - total_out) as usize;
+        if let Ok(Status::StreamEnd) = status {
+            state.finished = true;
+        }
+        status.map(|_| written)
+    };
+
+    match result {
+        Ok(written) => {
+            write_bytes(vm, array, range.start, &output[..written]);
+            Ok(Some(Value::Int(written as i32)))
+        }
+        Err(error) => {
+            let message = error
+                .message()
+                .unwrap_or("invalid deflate stream")
+                .to_string();
+            Err(vm.throw_new("java/util/zip/DataFormatException", Some(message)))
+        }
+    }
+}
+
+fn inflater_needs_input(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let state = state.lock().unwrap();
+    Ok(Some(Value::Int(
+        (state.position >= state.input.len()) as i32,
+    )))
+}
+
+/// Always false, as preset dictionaries are not supported.
+fn inflater_needs_dictionary(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    inflater_state(vm, args[0])?;
+    Ok(Some(Value::Int(0)))
+}
+
+fn inflater_finished(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let finished = state.lock().unwrap().finished;
+    Ok(Some(Value::Int(finished as i32)))
+}
+
+fn inflater_get_remaining(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let state = state.lock().unwrap();
+    Ok(Some(Value::Int(
+        (state.input.len() - state.position) as i32,
+    )))
+}
+
+fn inflater_get_total_in(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_in();
+    Ok(Some(Value::Int(total as i32)))
+}
+
+fn inflater_get_total_out(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_out();
+    Ok(Some(Value::Int(total as i32)))
+}
+
+fn inflater_get_bytes_read(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_in();
+    Ok(Some(Value::Long(total as i64)))
+}
+
+fn inflater_get_bytes_written(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_out();
+    Ok(Some(Value::Long(total as i64)))
+}
+
+fn inflater_reset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = inflater_state(vm, args[0])?;
+    let mut state = state.lock().unwrap();
+    let zlib_header = state.zlib_header;
+    state.stream.reset(zlib_header);
+    state.input.clear();
+    state.position = 0;
+    state.finished = false;
+    Ok(None)
+}
+
+// =============================================================================
+// DEFLATER
+// =============================================================================
+
+/// The state of a `java.util.zip.Deflater`. The compression level can only
+/// change before the first input is compressed, as the Rust backend cannot
+/// switch levels within a stream.
+#[derive(Debug)]
+pub struct Deflater {
+    stream: Compress,
+    level: Compression,
+    zlib_header: bool,
+    input: Vec<u8>,
+    position: usize,
+    finish: bool,
+    finished: bool,
+}
+
+fn deflater() -> BuiltinClass {
+    BuiltinClass::new("java/util/zip/Deflater", "java/lang/Object")
+        .method("<init>", "()V", deflater_init)
+        .method("<init>", "(I)V", deflater_init)
+        .method("<init>", "(IZ)V", deflater_init)
+        .method("setInput", "([B)V", deflater_set_input)
+        .method("setInput", "([BII)V", deflater_set_input)
+        .method("setLevel", "(I)V", deflater_set_level)
+        .method("finish", "()V", deflater_finish)
+        .method("deflate", "([B)I", deflater_deflate)
+        .method("deflate", "([BII)I", deflater_deflate)
+        .method("deflate", "([BIII)I", deflater_deflate)
+        .method("needsInput", "()Z", deflater_needs_input)
+        .method("finished", "()Z", deflater_finished)
+        .method("getTotalIn", "()I", deflater_get_total_in)
+        .method("getTotalOut", "()I", deflater_get_total_out)
+        .method("getBytesRead", "()J", deflater_get_bytes_read)
+        .method("getBytesWritten", "()J", deflater_get_bytes_written)
+        .method("reset", "()V", deflater_reset)
+        .method("end", "()V", zip_end)
+}
+
+fn deflater_state(vm: &mut Vm, this: Value) -> Result<Arc<Mutex<Deflater>>, Unwind> {
+    stream_state(vm, this, "Deflater", |native| match native {
+        NativeData::Deflater(state) => Some(state.clone()),
+        _ => None,
+    })
+}
+
+/// The compression of a `Deflater` level, throwing
+/// `IllegalArgumentException` outside of -1 (the default) to 9.
+fn compression(vm: &mut Vm, level: Option<&Value>) -> Result<Compression, Unwind> {
+    match level.map_or(-1, |level| int(*level)) {
+        -1 => Ok(Compression::default()),
+        level @ 0..=9 => Ok(Compression::new(level as u32)),
+        _ => Err(vm.throw_new(
+            "java/lang/IllegalArgumentException",
+            Some("invalid compression level".to_string()),
+        )),
+    }
+}
+
+/// Creates a deflater writing zlib streams, or raw deflate streams as found
+/// in zip files if `nowrap` is set.
+fn deflater_init(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let this = non_null(vm, args[0])?;
+    let level = compression(vm, args.get(1))?;
+    let zlib_header = args.get(2).is_none_or(|nowrap| int(*nowrap) == 0);
+    vm.heap.get_mut(this).native = NativeData::Deflater(Arc::new(Mutex::new(Deflater {
+        stream: Compress::new(level, zlib_header),
+        level,
+        zlib_header,
+        input: Vec::new(),
+        position: 0,
+        finish: false,
+        finished: false,
+    })));
+    Ok(None)
+}
+
+fn deflater_set_input(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let (array, range) = byte_range(vm, args, 1)?;
+    let bytes = byte_array(vm, Value::Reference(Some(array)))?;
+    let mut state = state.lock().unwrap();
+    state.input = bytes[range].to_vec();
+    state.position = 0;
+    Ok(None)
+}
+
+fn deflater_set_level(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let level = compression(vm, args.get(1))?;
+    let mut state = state.lock().unwrap();
+    if state.stream.total_in() == 0 && state.level != level {
+        state.stream = Compress::new(level, state.zlib_header);
+        state.level = level;
+    }
+    Ok(None)
+}
+
+fn deflater_finish(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    state.lock().unwrap().finish = true;
+    Ok(None)
+}
+
+/// Compresses as much of the input as fits the output range, returning the
+/// number of bytes written. The flush mode is `NO_FLUSH` (0), `SYNC_FLUSH`
+/// (2) or `FULL_FLUSH` (3), and ending the stream once `finish()` was called.
+fn deflater_deflate(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let (array, range) = byte_range(vm, args, 1)?;
+    let flush = match args.get(4).map(|flush| int(*flush)) {
+        None | Some(0) => FlushCompress::None,
+        Some(2) => FlushCompress::Sync,
+        Some(3) => FlushCompress::Full,
+        Some(_) => {
+            return Err(vm.throw_new(
+                "java/lang/IllegalArgumentException",
+                Some("Illegal flush mode".to_string()),
+            ))
+        }
+    };
+
+    let mut output = vec![0; range.len()];
+    let written = {
+        let mut state = state.lock().unwrap();
+        let state = &mut *state;
+        let flush = if state.finish {
+            FlushCompress::Finish
+        } else {
+            flush
+        };
+        let (total_in, total_out) = (state.stream.total_in(), state.stream.total_out());
+        let status = state
+            .stream
+            .compress(&state.input[state.position..], &mut output, flush);
+        state.position += (state.stream.total_in() - total_in) as usize;
+        if let Ok(Status::StreamEnd) = status {
+            state.finished = true;
+        }
+        (state.stream.total_out() - total_out) as usize
+    };
+
+    write_bytes(vm, array, range.start, &output[..written]);
+    Ok(Some(Value::Int(written as i32)))
+}
+
+fn deflater_needs_input(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let state = state.lock().unwrap();
+    Ok(Some(Value::Int(
+        (state.position >= state.input.len()) as i32,
+    )))
+}
+
+fn deflater_finished(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let finished = state.lock().unwrap().finished;
+    Ok(Some(Value::Int(finished as i32)))
+}
+
+fn deflater_get_total_in(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_in();
+    Ok(Some(Value::Int(total as i32)))
+}
+
+fn deflater_get_total_out(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_out();
+    Ok(Some(Value::Int(total as i32)))
+}
+
+fn deflater_get_bytes_read(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_in();
+    Ok(Some(Value::Long(total as i64)))
+}
+
+fn deflater_get_bytes_written(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let total = state.lock().unwrap().stream.total_out();
+    Ok(Some(Value::Long(total as i64)))
+}
+
+fn deflater_reset(vm: &mut Vm, args: &[Value]) -> Result<Option<Value>, Unwind> {
+    let state = deflater_state(vm, args[0])?;
+    let mut state = state.lock().unwrap();
+    state.stream.reset();
+    state.input.clear();
+    state.position = 0;
+    state.finish = false;
+    state.finished = false;
+    Ok(None)
+}
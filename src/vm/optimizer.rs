@@ -0,0 +1,1137 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::class::attributes::{
+    Attribute, CodeAttribute, FullFrame, LineNumberTableAttribute, ObjectVariableInfo,
+    StackMapTableAttribute, UninitializedVariableInfo, VerificationType,
+};
+use crate::class::constant_pool::{Constant, ConstantPool, ConstantPoolBuilder};
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::instruction::{decode, Instruction, Switch};
+use crate::class::{Class, ClassLoadingError, MethodAccessFlags};
+
+/// Rounds of passes run at most on a method, each enabling the next.
+const MAX_ROUNDS: usize = 8;
+
+// =============================================================================
+// STATISTICS
+// =============================================================================
+
+/// What the optimizer changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimizationStats {
+    /// Methods whose code changed.
+    pub methods: usize,
+    /// Arithmetic on constants replaced by its result.
+    pub folded_constants: usize,
+    /// Stores overwritten before being read, and values pushed only to be
+    /// popped, removed.
+    pub dead_stores: usize,
+    /// Branches to a `goto` sent to its target, and `goto`s to the next
+    /// instruction removed.
+    pub collapsed_jumps: usize,
+    /// Instructions no path from the entry or a used handler reaches.
+    pub unreachable_instructions: usize,
+}
+
+impl OptimizationStats {
+    pub fn add(&mut self, other: &OptimizationStats) {
+        self.methods += other.methods;
+        self.folded_constants += other.folded_constants;
+        self.dead_stores += other.dead_stores;
+        self.collapsed_jumps += other.collapsed_jumps;
+        self.unreachable_instructions += other.unreachable_instructions;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.folded_constants == 0
+            && self.dead_stores == 0
+            && self.collapsed_jumps == 0
+            && self.unreachable_instructions == 0
+    }
+}
+
+impl fmt::Display for OptimizationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} methods changed: {} constants folded, {} dead stores removed, \
+             {} jumps collapsed, {} unreachable instructions removed",
+            self.methods,
+            self.folded_constants,
+            self.dead_stores,
+            self.collapsed_jumps,
+            self.unreachable_instructions
+        )
+    }
+}
+
+// =============================================================================
+// OPTIMIZER
+// =============================================================================
+
+/// Applies peephole optimizations to the code of every method of the class:
+///
+/// - arithmetic on constants is folded into the constant it results in
+/// - stores overwritten in the same block before being read become pops,
+///   and pushes followed by a pop are removed
+/// - branches to a `goto` go to its target instead, and `goto`s to the next
+///   instruction are removed
+/// - unreachable instructions are removed
+///
+/// The exception table, line numbers, local variables and stack map frames
+/// follow the instructions they refer to, so the class stays valid and
+/// debuggable. Constants folded into values needing an `ldc` are added to
+/// the constant pool. Methods using subroutines, or code attributes the
+/// optimizer does not know, are left as they are.
+pub fn optimize_class(class: &mut Class) -> Result<OptimizationStats, ClassLoadingError> {
+    let mut pool = ConstantPoolBuilder::from_pool(&class.constant_pool);
+    let count = pool.count();
+    let this = MethodContext {
+        class_name: class.name()?.to_string(),
+        class_index: class.this_class,
+        is_init: false,
+        is_static: false,
+        descriptor: MethodDescriptor {
+            parameters: Vec::new(),
+            return_type: None,
+        },
+    };
+
+    let mut stats = OptimizationStats::default();
+    for method in &mut class.methods {
+        let context = MethodContext {
+            is_init: class.constant_pool.get_utf8(method.name_index)? == "<init>",
+            is_static: method.access_flags.contains(MethodAccessFlags::STATIC),
+            descriptor: MethodDescriptor::parse(
+                class.constant_pool.get_utf8(method.descriptor_index)?,
+            )?,
+            ..this.clone()
+        };
+        for attribute in &mut method.attributes {
+            if let Attribute::Code(code) = attribute {
+                if let Some(method_stats) =
+                    optimize_code(code, &class.constant_pool, &mut pool, &context)?
+                {
+                    stats.add(&method_stats);
+                }
+            }
+        }
+    }
+    if pool.count() != count {
+        class.constant_pool = pool.build();
+    }
+    Ok(stats)
+}
+
+/// The method whose code is optimized, giving the locals its stack map
+/// frames start from.
+#[derive(Clone)]
+struct MethodContext {
+    class_name: String,
+    class_index: u16,
+    is_init: bool,
+    is_static: bool,
+    descriptor: MethodDescriptor,
+}
+
+/// Optimizes the code in place, returning what changed, or `None` when the
+/// code is left as it is.
+fn optimize_code(
+    code: &mut CodeAttribute,
+    constant_pool: &ConstantPool,
+    pool: &mut ConstantPoolBuilder,
+    context: &MethodContext,
+) -> Result<Option<OptimizationStats>, ClassLoadingError> {
+    let supported = code.attributes.iter().all(|attribute| {
+        matches!(
+            attribute,
+            Attribute::StackMapTable(_)
+                | Attribute::LineNumberTable(_)
+                | Attribute::LocalVariableTable(_)
+                | Attribute::LocalVariableTypeTable(_)
+        )
+    });
+    let mut method = match Method::decode(code, constant_pool) {
+        Some(method) if supported => method,
+        _ => return Ok(None),
+    };
+
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = method.thread_jumps();
+        changed |= method.remove_unreachable();
+        changed |= method.fold_constants(pool);
+        changed |= method.remove_dead_stores(constant_pool);
+        if !changed {
+            break;
+        }
+    }
+    if method.stats.is_empty() {
+        return Ok(None);
+    }
+
+    let (bytes, new_pcs) = match method.encode() {
+        Some(encoded) => encoded,
+        None => return Ok(None),
+    };
+    let index_of = |pc: u16| method.index_of(pc as u32);
+    let new_pc = |pc: u16| index_of(pc).map(|index| new_pcs[index]);
+
+    // Frames are decoded before anything changes, since they need the
+    // original pcs
+    let mut frames = None;
+    for attribute in &code.attributes {
+        if let Attribute::StackMapTable(table) = attribute {
+            let initial = initial_locals(context, pool)?;
+            frames = match method.remap_frames(table, initial, &new_pcs) {
+                Some(frames) => Some(frames),
+                None => return Ok(None),
+            };
+        }
+    }
+
+    let mut exception_table = Vec::new();
+    for mut entry in code.exception_tables.drain(..) {
+        let handler = index_of(entry.handler_pc).unwrap_or(method.ops.len());
+        let (start, end, handler_pc) = match (
+            new_pc(entry.start_pc),
+            new_pc(entry.end_pc),
+            new_pc(entry.handler_pc),
+        ) {
+            (Some(start), Some(end), Some(handler_pc)) => (start, end, handler_pc),
+            _ => continue,
+        };
+        if start < end && handler < method.ops.len() && !method.ops[handler].removed {
+            entry.start_pc = start;
+            entry.end_pc = end;
+            entry.handler_pc = handler_pc;
+            exception_table.push(entry);
+        }
+    }
+    code.exception_tables = exception_table;
+
+    let length = new_pcs[method.ops.len()];
+    for attribute in &mut code.attributes {
+        match attribute {
+            Attribute::StackMapTable(table) => {
+                *table = frames.take().unwrap_or_default();
+            }
+            Attribute::LineNumberTable(lines) => {
+                // Lines starting at removed instructions start at the next
+                // one, unless it starts a line of its own
+                let mut remapped: Vec<(u16, u16, u16)> = lines
+                    .iter()
+                    .filter_map(|line| {
+                        Some((new_pc(line.start_pc)?, line.start_pc, line.line_number))
+                    })
+                    .filter(|&(pc, _, _)| pc < length)
+                    .collect();
+                remapped.sort_unstable();
+                remapped.dedup_by(|next, previous| {
+                    if next.0 == previous.0 {
+                        *previous = *next;
+                    }
+                    next.0 == previous.0
+                });
+                lines.truncate(0);
+                lines.extend(remapped.into_iter().map(|(start_pc, _, line_number)| {
+                    LineNumberTableAttribute {
+                        start_pc,
+                        line_number,
+                    }
+                }));
+            }
+            Attribute::LocalVariableTable(variables) => {
+                variables.retain_mut(|variable| {
+                    remap_range(&mut variable.start_pc, &mut variable.length, new_pc)
+                });
+            }
+            Attribute::LocalVariableTypeTable(variables) => {
+                variables.retain_mut(|variable| {
+                    remap_range(&mut variable.start_pc, &mut variable.length, new_pc)
+                });
+            }
+            _ => {}
+        }
+    }
+    code.code = bytes;
+
+    let mut stats = method.stats;
+    stats.methods = 1;
+    Ok(Some(stats))
+}
+
+/// Moves the range of pcs to the instructions it covered, `false` when none
+/// is left.
+fn remap_range(start_pc: &mut u16, length: &mut u16, new_pc: impl Fn(u16) -> Option<u16>) -> bool {
+    let end_pc = match u16::try_from(*start_pc as u32 + *length as u32) {
+        Ok(end_pc) => end_pc,
+        Err(_) => return false,
+    };
+    match (new_pc(*start_pc), new_pc(end_pc)) {
+        (Some(start), Some(end)) if start < end => {
+            *start_pc = start;
+            *length = end - start;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The locals of the implicit first frame of the method, from its
+/// descriptor.
+fn initial_locals(
+    context: &MethodContext,
+    pool: &mut ConstantPoolBuilder,
+) -> Result<Vec<VerificationType>, ClassLoadingError> {
+    let mut locals = Vec::new();
+    if !context.is_static {
+        locals.push(
+            if context.is_init && context.class_name != "java/lang/Object" {
+                VerificationType::UninitializedThis
+            } else {
+                object(context.class_index)
+            },
+        );
+    }
+    for parameter in &context.descriptor.parameters {
+        locals.push(match parameter {
+            FieldType::Float => VerificationType::Float,
+            FieldType::Long => VerificationType::Long,
+            FieldType::Double => VerificationType::Double,
+            FieldType::Object(name) => object(pool.class(name)?),
+            FieldType::Array(_) => object(pool.class(&parameter.to_string())?),
+            _ => VerificationType::Integer,
+        });
+    }
+    Ok(locals)
+}
+
+fn object(constant_index: u16) -> VerificationType {
+    VerificationType::Object(ObjectVariableInfo { constant_index })
+}
+
+// =============================================================================
+// METHOD
+// =============================================================================
+
+/// A constant pushed by an instruction, which arithmetic can fold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Int(i32),
+    Long(i64),
+}
+
+/// An instruction of the code being optimized.
+struct Op {
+    /// The instruction as read, whose branch offsets are rewritten when
+    /// encoded.
+    bytes: Vec<u8>,
+    /// The decoded instruction, its targets being indices of [Method::ops].
+    instruction: Instruction,
+    constant: Option<Value>,
+    /// Removed instructions keep their index, branches to them going to the
+    /// next instruction left.
+    removed: bool,
+}
+
+impl Op {
+    /// The opcode, past a `wide` prefix.
+    fn opcode(&self) -> u8 {
+        match self.bytes[0] {
+            0xc4 => self.bytes[1],
+            opcode => opcode,
+        }
+    }
+
+    /// Whether a load or store is of a `long` or `double`, taking two slots.
+    fn is_wide_local(&self) -> bool {
+        matches!(
+            self.opcode(),
+            0x16 | 0x18 | 0x1e..=0x21 | 0x26..=0x29 | 0x37 | 0x39 | 0x3f..=0x42 | 0x47..=0x4a
+        )
+    }
+
+    /// The local variable slots the instruction reads or writes.
+    fn slots(&self) -> Option<(u16, u16)> {
+        match self.instruction {
+            Instruction::Load(slot) | Instruction::Store(slot) => {
+                Some((slot, slot + if self.is_wide_local() { 2 } else { 1 }))
+            }
+            Instruction::IInc(slot, _) => Some((slot, slot + 1)),
+            _ => None,
+        }
+    }
+}
+
+/// An exception table entry, its pcs turned into indices of [Method::ops].
+struct Handler {
+    start: usize,
+    end: usize,
+    handler: usize,
+}
+
+struct Method {
+    ops: Vec<Op>,
+    pcs: Vec<u32>,
+    length: u32,
+    switches: Vec<Switch>,
+    handlers: Vec<Handler>,
+    stats: OptimizationStats,
+}
+
+impl Method {
+    /// Decodes the code, `None` if it holds subroutines or instructions the
+    /// optimizer cannot move.
+    fn decode(code: &CodeAttribute, pool: &ConstantPool) -> Option<Method> {
+        let decoded = decode(&code.code);
+        let mut ops = Vec::with_capacity(decoded.instructions.len());
+        for (index, &instruction) in decoded.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Jsr(_) | Instruction::Ret(_) => return None,
+                // invokedynamic is moved as it is, anything else unsupported
+                // might be a branch
+                Instruction::Unsupported(opcode) if opcode != 0xba => return None,
+                _ => {}
+            }
+            let start = decoded.pcs[index] as usize;
+            let end = decoded
+                .pcs
+                .get(index + 1)
+                .map_or(code.code.len(), |&pc| pc as usize);
+            let constant = match instruction {
+                Instruction::IConst(value) => Some(Value::Int(value)),
+                Instruction::LConst(value) => Some(Value::Long(value)),
+                Instruction::Ldc(index) => match pool.get(index as usize) {
+                    Some(Constant::Integer(integer)) => Some(Value::Int(integer.value)),
+                    Some(Constant::Long(long)) => Some(Value::Long(long.value)),
+                    _ => None,
+                },
+                _ => None,
+            };
+            ops.push(Op {
+                bytes: code.code[start..end].to_vec(),
+                instruction,
+                constant,
+                removed: false,
+            });
+        }
+
+        let mut method = Method {
+            ops,
+            pcs: decoded.pcs,
+            length: code.code.len() as u32,
+            switches: decoded.switches,
+            handlers: Vec::new(),
+            stats: OptimizationStats::default(),
+        };
+        for entry in &code.exception_tables {
+            let handler = Handler {
+                start: method.index_of(entry.start_pc as u32)?,
+                end: method.index_of(entry.end_pc as u32)?,
+                handler: method.index_of(entry.handler_pc as u32)?,
+            };
+            if handler.handler >= method.ops.len() {
+                return None;
+            }
+            method.handlers.push(handler);
+        }
+        Some(method)
+    }
+
+    /// The index of the instruction at the original pc, or the number of
+    /// instructions for the end of the code.
+    fn index_of(&self, pc: u32) -> Option<usize> {
+        if pc == self.length {
+            return Some(self.ops.len());
+        }
+        self.pcs.binary_search(&pc).ok()
+    }
+
+    /// The first instruction left at or after the index.
+    fn resolve(&self, mut index: usize) -> usize {
+        while index < self.ops.len() && self.ops[index].removed {
+            index += 1;
+        }
+        index
+    }
+
+    fn next(&self, index: usize) -> usize {
+        self.resolve(index + 1)
+    }
+
+    /// The instructions entered other than by falling through to them.
+    fn entries(&self) -> Vec<bool> {
+        let mut entries = vec![false; self.ops.len() + 1];
+        for op in self.ops.iter().filter(|op| !op.removed) {
+            for target in self.targets(op.instruction) {
+                entries[self.resolve(target)] = true;
+            }
+        }
+        for handler in &self.handlers {
+            entries[self.resolve(handler.handler)] = true;
+        }
+        entries
+    }
+
+    /// The targets of a branch or switch.
+    fn targets(&self, instruction: Instruction) -> Vec<usize> {
+        match instruction {
+            Instruction::If(_, target)
+            | Instruction::IfICmp(_, target)
+            | Instruction::IfACmpEq(target)
+            | Instruction::IfACmpNe(target)
+            | Instruction::IfNull(target)
+            | Instruction::IfNonNull(target)
+            | Instruction::Goto(target) => vec![target as usize],
+            Instruction::Switch(switch) => {
+                let switch = &self.switches[switch as usize];
+                std::iter::once(switch.default)
+                    .chain(switch.cases.iter().map(|&(_, target)| target))
+                    .map(|target| target as usize)
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Passes ------------------------------------------------------------------
+
+    /// Sends branches to a `goto` to its final target, and removes `goto`s
+    /// to the instruction they would fall through to.
+    fn thread_jumps(&mut self) -> bool {
+        let final_target = |method: &Method, mut target: usize| {
+            target = method.resolve(target);
+            for _ in 0..method.ops.len() {
+                match method.ops.get(target).map(|op| op.instruction) {
+                    Some(Instruction::Goto(next)) if method.resolve(next as usize) != target => {
+                        target = method.resolve(next as usize)
+                    }
+                    _ => break,
+                }
+            }
+            target
+        };
+
+        let mut changed = false;
+        for index in 0..self.ops.len() {
+            if self.ops[index].removed {
+                continue;
+            }
+            let retarget = |target: u32| {
+                let threaded = final_target(self, target as usize);
+                (threaded != self.resolve(target as usize), threaded as u32)
+            };
+            let (collapsed, instruction) = match self.ops[index].instruction {
+                Instruction::If(condition, target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::If(condition, target))
+                }
+                Instruction::IfICmp(condition, target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::IfICmp(condition, target))
+                }
+                Instruction::IfACmpEq(target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::IfACmpEq(target))
+                }
+                Instruction::IfACmpNe(target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::IfACmpNe(target))
+                }
+                Instruction::IfNull(target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::IfNull(target))
+                }
+                Instruction::IfNonNull(target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::IfNonNull(target))
+                }
+                Instruction::Goto(target) => {
+                    let (collapsed, target) = retarget(target);
+                    (collapsed, Instruction::Goto(target))
+                }
+                Instruction::Switch(switch) => {
+                    let mut collapsed = false;
+                    let mut table = self.switches[switch as usize].clone();
+                    for target in std::iter::once(&mut table.default)
+                        .chain(table.cases.iter_mut().map(|(_, target)| target))
+                    {
+                        let (threaded, new_target) = retarget(*target);
+                        collapsed |= threaded;
+                        *target = new_target;
+                    }
+                    self.switches[switch as usize] = table;
+                    (collapsed, Instruction::Switch(switch))
+                }
+                _ => continue,
+            };
+            self.ops[index].instruction = instruction;
+            if collapsed {
+                self.stats.collapsed_jumps += 1;
+                changed = true;
+            }
+
+            if let Instruction::Goto(target) = instruction {
+                if self.resolve(target as usize) == self.next(index) {
+                    self.ops[index].removed = true;
+                    self.stats.collapsed_jumps += 1;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Removes the instructions reached neither from the entry, nor from the
+    /// handlers of reached instructions.
+    fn remove_unreachable(&mut self) -> bool {
+        let count = self.ops.len();
+        let mut reached = vec![false; count];
+        let mut pending = vec![self.resolve(0)];
+        while let Some(index) = pending.pop() {
+            if index >= count || reached[index] {
+                continue;
+            }
+            reached[index] = true;
+            let instruction = self.ops[index].instruction;
+            let falls_through = !matches!(
+                instruction,
+                Instruction::Goto(_)
+                    | Instruction::Return
+                    | Instruction::ReturnValue
+                    | Instruction::AThrow
+                    | Instruction::Switch(_)
+            );
+            if falls_through {
+                pending.push(self.next(index));
+            }
+            for target in self.targets(instruction) {
+                pending.push(self.resolve(target));
+            }
+            for handler in &self.handlers {
+                if (handler.start..handler.end).contains(&index) {
+                    pending.push(self.resolve(handler.handler));
+                }
+            }
+        }
+
+        let mut changed = false;
+        for (op, reached) in self.ops.iter_mut().zip(reached) {
+            if !op.removed && !reached {
+                op.removed = true;
+                self.stats.unreachable_instructions += 1;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Replaces arithmetic on constants with the constant it results in.
+    fn fold_constants(&mut self, pool: &mut ConstantPoolBuilder) -> bool {
+        let entries = self.entries();
+        let mut changed = false;
+        let mut index = self.resolve(0);
+        while index < self.ops.len() {
+            let first = match self.ops[index].constant {
+                Some(value) => value,
+                None => {
+                    index = self.next(index);
+                    continue;
+                }
+            };
+            let second = self.next(index);
+            let third = self.next(second);
+            let op = |index: usize| self.ops.get(index).filter(|_| !entries[index]);
+
+            let folded = match op(second).map(|op| (op.constant, op.instruction)) {
+                Some((None, instruction)) => {
+                    unary(first, instruction).map(|value| (value, vec![second]))
+                }
+                Some((Some(value), _)) => op(third)
+                    .and_then(|op| binary(first, value, op.instruction))
+                    .map(|value| (value, vec![second, third])),
+                None => None,
+            };
+            let replacement = folded
+                .and_then(|(value, removed)| Some((push_constant(value, pool)?, value, removed)));
+            match replacement {
+                Some(((bytes, instruction), value, removed)) => {
+                    self.ops[index] = Op {
+                        bytes,
+                        instruction,
+                        constant: Some(value),
+                        removed: false,
+                    };
+                    for removed in removed {
+                        self.ops[removed].removed = true;
+                    }
+                    self.stats.folded_constants += 1;
+                    changed = true;
+                }
+                None => index = self.next(index),
+            }
+        }
+        changed
+    }
+
+    /// Turns stores overwritten later in their block, before being read,
+    /// into pops, and removes values pushed only to be popped.
+    fn remove_dead_stores(&mut self, pool: &ConstantPool) -> bool {
+        let entries = self.entries();
+        let mut changed = false;
+
+        let mut index = self.resolve(0);
+        while index < self.ops.len() {
+            if matches!(self.ops[index].instruction, Instruction::Store(_))
+                && self.is_overwritten(index, &entries)
+            {
+                let wide = self.ops[index].is_wide_local();
+                self.ops[index] = Op {
+                    bytes: vec![if wide { 0x58 } else { 0x57 }],
+                    instruction: if wide {
+                        Instruction::Pop2
+                    } else {
+                        Instruction::Pop
+                    },
+                    constant: None,
+                    removed: false,
+                };
+                self.stats.dead_stores += 1;
+                changed = true;
+            }
+            index = self.next(index);
+        }
+
+        let mut index = self.resolve(0);
+        while index < self.ops.len() {
+            let next = self.next(index);
+            let popped = match self.ops.get(next) {
+                Some(op) if !entries[next] => match op.instruction {
+                    Instruction::Pop => Some(1),
+                    Instruction::Pop2 => Some(2),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if popped.is_some() && pushed(&self.ops[index], pool) == popped {
+                self.ops[index].removed = true;
+                self.ops[next].removed = true;
+                self.stats.dead_stores += 1;
+                changed = true;
+                index = self.next(next);
+            } else {
+                index = next;
+            }
+        }
+        changed
+    }
+
+    /// Whether the store's locals are all written again in its block, before
+    /// being read and before any exception might be caught.
+    fn is_overwritten(&self, store: usize, entries: &[bool]) -> bool {
+        let (start, end) = match self.ops[store].slots() {
+            Some(slots) => slots,
+            None => return false,
+        };
+        let wide = self.ops[store].is_wide_local();
+        let mut index = self.next(store);
+        while index < self.ops.len() && !entries[index] {
+            let op = &self.ops[index];
+            if let Some((other_start, other_end)) = op.slots() {
+                let overlaps = other_start < end && start < other_end;
+                if matches!(op.instruction, Instruction::Store(_))
+                    && other_start == start
+                    && op.is_wide_local() == wide
+                {
+                    return !self
+                        .handlers
+                        .iter()
+                        .any(|handler| handler.start <= index && store < handler.end);
+                }
+                if overlaps {
+                    return false;
+                }
+            }
+            if !self.targets(op.instruction).is_empty()
+                || matches!(
+                    op.instruction,
+                    Instruction::Return | Instruction::ReturnValue | Instruction::AThrow
+                )
+            {
+                return false;
+            }
+            index = self.next(index);
+        }
+        false
+    }
+
+    // Encoding ----------------------------------------------------------------
+
+    /// The bytecode of the instructions left, with the new pc of every
+    /// instruction, removed ones having the pc of the next one left, and of
+    /// the end of the code. `None` if a branch no longer fits its offset.
+    fn encode(&self) -> Option<(Vec<u8>, Vec<u16>)> {
+        let mut new_pcs = Vec::with_capacity(self.ops.len() + 1);
+        let mut pc = 0usize;
+        for op in &self.ops {
+            new_pcs.push(pc);
+            if !op.removed {
+                pc += match op.instruction {
+                    Instruction::Switch(switch) => {
+                        let switch = &self.switches[switch as usize];
+                        let entries = if op.bytes[0] == 0xaa {
+                            12 + 4 * switch.cases.len()
+                        } else {
+                            8 + 8 * switch.cases.len()
+                        };
+                        (pc + 4) / 4 * 4 - pc + entries
+                    }
+                    _ => op.bytes.len(),
+                };
+            }
+        }
+        new_pcs.push(pc);
+        let new_pcs = new_pcs
+            .into_iter()
+            .map(|pc| u16::try_from(pc).ok())
+            .collect::<Option<Vec<u16>>>()?;
+
+        let mut code = Vec::with_capacity(pc);
+        for (index, op) in self.ops.iter().enumerate() {
+            if op.removed {
+                continue;
+            }
+            let offset =
+                |target: u32| new_pcs[self.resolve(target as usize)] as i32 - new_pcs[index] as i32;
+            match op.instruction {
+                Instruction::If(_, target)
+                | Instruction::IfICmp(_, target)
+                | Instruction::IfACmpEq(target)
+                | Instruction::IfACmpNe(target)
+                | Instruction::IfNull(target)
+                | Instruction::IfNonNull(target)
+                | Instruction::Goto(target) => {
+                    code.push(op.bytes[0]);
+                    if op.bytes[0] == 0xc8 {
+                        code.extend_from_slice(&offset(target).to_be_bytes());
+                    } else {
+                        code.extend_from_slice(&i16::try_from(offset(target)).ok()?.to_be_bytes());
+                    }
+                }
+                Instruction::Switch(switch) => {
+                    let switch = &self.switches[switch as usize];
+                    code.push(op.bytes[0]);
+                    while code.len() % 4 != 0 {
+                        code.push(0);
+                    }
+                    code.extend_from_slice(&offset(switch.default).to_be_bytes());
+                    if op.bytes[0] == 0xaa {
+                        let low = switch.cases.first().map_or(0, |&(key, _)| key);
+                        let high = switch.cases.last().map_or(-1, |&(key, _)| key);
+                        code.extend_from_slice(&low.to_be_bytes());
+                        code.extend_from_slice(&high.to_be_bytes());
+                        for &(_, target) in &switch.cases {
+                            code.extend_from_slice(&offset(target).to_be_bytes());
+                        }
+                    } else {
+                        code.extend_from_slice(&(switch.cases.len() as i32).to_be_bytes());
+                        for &(key, target) in &switch.cases {
+                            code.extend_from_slice(&key.to_be_bytes());
+                            code.extend_from_slice(&offset(target).to_be_bytes());
+                        }
+                    }
+                }
+                _ => code.extend_from_slice(&op.bytes),
+            }
+        }
+        Some((code, new_pcs))
+    }
+
+    /// Decodes the frames, moves those of removed instructions to the next
+    /// instruction left and encodes them again, at their new pcs, as full
+    /// frames.
+    fn remap_frames(
+        &self,
+        table: &[StackMapTableAttribute],
+        mut locals: Vec<VerificationType>,
+        new_pcs: &[u16],
+    ) -> Option<Vec<StackMapTableAttribute>> {
+        let remap = |types: &[VerificationType]| {
+            types
+                .iter()
+                .map(|verification_type| match verification_type {
+                    VerificationType::Uninitialized(info) => {
+                        let index = self.index_of(info.offset as u32)?;
+                        Some(VerificationType::Uninitialized(UninitializedVariableInfo {
+                            offset: new_pcs[index],
+                        }))
+                    }
+                    other => Some(other.clone()),
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+
+        let mut positioned: Vec<(u16, Vec<VerificationType>, Vec<VerificationType>)> = Vec::new();
+        let mut pc: Option<u32> = None;
+        for frame in table {
+            let (offset_delta, stack) = match frame {
+                StackMapTableAttribute::Same(frame) => (frame.offset_delta as u16, Vec::new()),
+                StackMapTableAttribute::SameLocalsOneStackItem(frame) => {
+                    (frame.offset_delta as u16, vec![frame.stack.clone()])
+                }
+                StackMapTableAttribute::SameLocalsOneStackItemExtended(frame) => {
+                    (frame.offset_delta, vec![frame.stack.clone()])
+                }
+                StackMapTableAttribute::Chop(frame) => {
+                    let chopped = locals.len().checked_sub(frame.chopped as usize)?;
+                    locals.truncate(chopped);
+                    (frame.offset_delta, Vec::new())
+                }
+                StackMapTableAttribute::SameExtended(frame) => (frame.offset_delta, Vec::new()),
+                StackMapTableAttribute::Append(frame) => {
+                    locals.extend(frame.locals.iter().cloned());
+                    (frame.offset_delta, Vec::new())
+                }
+                StackMapTableAttribute::Full(frame) => {
+                    locals = frame.locals.clone();
+                    (frame.offset_delta, frame.stack.clone())
+                }
+            };
+            let current = match pc {
+                Some(pc) => pc + offset_delta as u32 + 1,
+                None => offset_delta as u32,
+            };
+            pc = Some(current);
+
+            // The frame of a removed instruction holds for the next one left,
+            // unless that one has a frame of its own
+            let index = self.resolve(self.index_of(current)?);
+            if index >= self.ops.len() {
+                continue;
+            }
+            let frame = (new_pcs[index], remap(&locals)?, remap(&stack)?);
+            match positioned.last_mut() {
+                Some(last) if last.0 == frame.0 => *last = frame,
+                _ => positioned.push(frame),
+            }
+        }
+
+        let mut frames = Vec::with_capacity(positioned.len());
+        let mut previous_pc: Option<u16> = None;
+        for (new_pc, locals, stack) in positioned {
+            let offset_delta = match previous_pc {
+                Some(previous) => new_pc - previous - 1,
+                None => new_pc,
+            };
+            previous_pc = Some(new_pc);
+            frames.push(StackMapTableAttribute::Full(FullFrame {
+                offset_delta,
+                locals,
+                stack,
+            }));
+        }
+        Some(frames)
+    }
+}
+
+// =============================================================================
+// FOLDING
+// =============================================================================
+
+fn unary(value: Value, instruction: Instruction) -> Option<Value> {
+    match (value, instruction) {
+        (Value::Int(a), Instruction::INeg) => Some(Value::Int(a.wrapping_neg())),
+        (Value::Int(a), Instruction::I2L) => Some(Value::Long(a as i64)),
+        (Value::Long(a), Instruction::LNeg) => Some(Value::Long(a.wrapping_neg())),
+        (Value::Long(a), Instruction::L2I) => Some(Value::Int(a as i32)),
+        _ => None,
+    }
+}
+
+/// The result of the arithmetic, `None` for divisions by zero, which throw.
+fn binary(first: Value, second: Value, instruction: Instruction) -> Option<Value> {
+    let value = match (first, second, instruction) {
+        (Value::Int(a), Value::Int(b), instruction) => Value::Int(match instruction {
+            Instruction::IAdd => a.wrapping_add(b),
+            Instruction::ISub => a.wrapping_sub(b),
+            Instruction::IMul => a.wrapping_mul(b),
+            Instruction::IDiv if b != 0 => a.wrapping_div(b),
+            Instruction::IRem if b != 0 => a.wrapping_rem(b),
+            Instruction::IAnd => a & b,
+            Instruction::IOr => a | b,
+            Instruction::IXor => a ^ b,
+            Instruction::IShl => a.wrapping_shl(b as u32),
+            Instruction::IShr => a.wrapping_shr(b as u32),
+            Instruction::IUShr => (a as u32).wrapping_shr(b as u32) as i32,
+            _ => return None,
+        }),
+        (Value::Long(a), Value::Int(b), instruction) => Value::Long(match instruction {
+            Instruction::LShl => a.wrapping_shl(b as u32),
+            Instruction::LShr => a.wrapping_shr(b as u32),
+            Instruction::LUShr => (a as u64).wrapping_shr(b as u32) as i64,
+            _ => return None,
+        }),
+        (Value::Long(a), Value::Long(b), instruction) => Value::Long(match instruction {
+            Instruction::LAdd => a.wrapping_add(b),
+            Instruction::LSub => a.wrapping_sub(b),
+            Instruction::LMul => a.wrapping_mul(b),
+            Instruction::LDiv if b != 0 => a.wrapping_div(b),
+            Instruction::LRem if b != 0 => a.wrapping_rem(b),
+            Instruction::LAnd => a & b,
+            Instruction::LOr => a | b,
+            Instruction::LXor => a ^ b,
+            _ => return None,
+        }),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The shortest instruction pushing the constant, `None` if it needs a
+/// constant the pool has no room for.
+fn push_constant(value: Value, pool: &mut ConstantPoolBuilder) -> Option<(Vec<u8>, Instruction)> {
+    let pushed = match value {
+        Value::Int(value @ -1..=5) => (vec![(0x03 + value) as u8], Instruction::IConst(value)),
+        Value::Int(value) if i8::try_from(value).is_ok() => {
+            (vec![0x10, value as i8 as u8], Instruction::IConst(value))
+        }
+        Value::Int(value) if i16::try_from(value).is_ok() => {
+            let [high, low] = (value as i16).to_be_bytes();
+            (vec![0x11, high, low], Instruction::IConst(value))
+        }
+        Value::Int(value) => {
+            let index = pool.integer(value).ok()?;
+            match u8::try_from(index) {
+                Ok(index) => (vec![0x12, index], Instruction::Ldc(index as u16)),
+                Err(_) => {
+                    let [high, low] = index.to_be_bytes();
+                    (vec![0x13, high, low], Instruction::Ldc(index))
+                }
+            }
+        }
+        Value::Long(value @ 0..=1) => (vec![0x09 + value as u8], Instruction::LConst(value)),
+        Value::Long(value) => {
+            let index = pool.long(value).ok()?;
+            let [high, low] = index.to_be_bytes();
+            (vec![0x14, high, low], Instruction::Ldc(index))
+        }
+    };
+    Some(pushed)
+}
+
+/// The slots of the value the instruction pushes without side effects, if
+/// it does.
+fn pushed(op: &Op, pool: &ConstantPool) -> Option<u8> {
+    match op.instruction {
+        Instruction::AConstNull
+        | Instruction::IConst(_)
+        | Instruction::FConst(_)
+        | Instruction::Dup => Some(1),
+        Instruction::LConst(_) | Instruction::DConst(_) => Some(2),
+        Instruction::Load(_) => Some(if op.is_wide_local() { 2 } else { 1 }),
+        Instruction::Ldc(_) if op.constant.is_some() => {
+            Some(if op.bytes[0] == 0x14 { 2 } else { 1 })
+        }
+        Instruction::Ldc(index) => match pool.get(index as usize) {
+            Some(Constant::Float(_)) | Some(Constant::String(_)) => Some(1),
+            Some(Constant::Double(_)) => Some(2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod optimizer_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::optimize_class;
+    use crate::class::attributes::{Attribute, LineNumberTableAttribute};
+    use crate::class::Class;
+    use crate::vm::inference::infer_frames;
+
+    #[test]
+    fn test_optimize() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class =
+            Class::parse_bytes(&fs::read(root.join("Calculator.class")).unwrap()).unwrap();
+        let add = class
+            .methods
+            .iter()
+            .position(|method| class.constant_pool.get_utf8(method.name_index).unwrap() == "add")
+            .unwrap();
+        for attribute in &mut class.methods[add].attributes {
+            if let Attribute::Code(code) = attribute {
+                code.max_locals = 3;
+                code.code = vec![
+                    0x1a, // 0: iload_0
+                    0x3d, // 1: istore_2, overwritten at 6
+                    0x05, // 2: iconst_2
+                    0x10, 0x28, // 3: bipush 40
+                    0x68, // 5: imul
+                    0x3d, // 6: istore_2
+                    0xa7, 0x00, 0x04, // 7: goto 11
+                    0x00, // 10: nop, unreachable
+                    0xa7, 0x00, 0x03, // 11: goto 14
+                    0x1c, // 14: iload_2
+                    0xac, // 15: ireturn
+                ];
+                code.attributes = vec![Attribute::LineNumberTable(vec![
+                    LineNumberTableAttribute {
+                        start_pc: 0,
+                        line_number: 1,
+                    },
+                    LineNumberTableAttribute {
+                        start_pc: 14,
+                        line_number: 2,
+                    },
+                ])];
+            }
+        }
+
+        let stats = optimize_class(&mut class).unwrap();
+        assert_eq!(stats.methods, 1);
+        assert_eq!(stats.folded_constants, 1);
+        assert_eq!(stats.dead_stores, 2);
+        assert_eq!(stats.collapsed_jumps, 3);
+        assert_eq!(stats.unreachable_instructions, 1);
+
+        let class = Class::parse_bytes(&class.to_bytes().unwrap()).unwrap();
+        let method = &class.methods[add];
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            })
+            .unwrap();
+        // bipush 80, istore_2, iload_2, ireturn
+        assert_eq!(code.code, vec![0x10, 80, 0x3d, 0x1c, 0xac]);
+        match &code.attributes[0] {
+            Attribute::LineNumberTable(lines) => {
+                let lines: Vec<(u16, u16)> = lines
+                    .iter()
+                    .map(|line| (line.start_pc, line.line_number))
+                    .collect();
+                assert_eq!(lines, vec![(0, 1), (3, 2)]);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+
+        // The other methods, with their stack map frames, still type check
+        for method in &class.methods {
+            for attribute in &method.attributes {
+                if let Attribute::Code(code) = attribute {
+                    infer_frames(&class, method, code).unwrap();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::env;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+// =============================================================================
+// PERMISSIONS
+// =============================================================================
+
+/// An operation of guest code which is subject to the [`VmPolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission<'a> {
+    /// Reading the file or listing the directory at the path.
+    Read(&'a Path),
+    /// Creating, modifying or deleting the file or directory at the path.
+    Write(&'a Path),
+    /// Accessing the filesystem where the path is not known, e.g. through
+    /// the JDK's own file natives.
+    FileSystem,
+    /// Opening sockets or resolving host names.
+    Network,
+    /// Starting or controlling host processes, or reading the environment.
+    Process,
+    /// Inspecting or invoking members through `java.lang.reflect`.
+    Reflection,
+}
+
+impl<'a> Permission<'a> {
+    /// The permission required by a native or built-in method: the one of
+    /// the method itself, else the one of its class.
+    pub fn for_native(class: &str, name: &str) -> Option<Permission<'static>> {
+        const METHODS: [(&str, &str, Permission); 9] = [
+            ("java/lang/System", "getenv", Permission::Process),
+            ("java/lang/Class", "forName", Permission::Reflection),
+            ("java/lang/Class", "newInstance", Permission::Reflection),
+            (
+                "java/lang/Class",
+                "getDeclaredMethods",
+                Permission::Reflection,
+            ),
+            (
+                "java/lang/Class",
+                "getDeclaredMethod",
+                Permission::Reflection,
+            ),
+            (
+                "java/lang/Class",
+                "getDeclaredConstructors",
+                Permission::Reflection,
+            ),
+            (
+                "java/lang/Class",
+                "getDeclaredConstructor",
+                Permission::Reflection,
+            ),
+            (
+                "java/lang/Class",
+                "getDeclaredFields",
+                Permission::Reflection,
+            ),
+            (
+                "java/lang/Class",
+                "getDeclaredField",
+                Permission::Reflection,
+            ),
+        ];
+
+        METHODS
+            .iter()
+            .find(|(owner, method, _)| *owner == class && *method == name)
+            .map(|(_, _, permission)| *permission)
+            .or_else(|| Permission::for_native_class(class))
+    }
+
+    /// The permission required by the natives of a JDK class, for the
+    /// packages whose natives reach the host system.
+    pub fn for_native_class(class: &str) -> Option<Permission<'static>> {
+        const FILE_SYSTEM: [&str; 6] = [
+            "java/io/File",
+            "java/io/RandomAccessFile",
+            "java/io/UnixFileSystem",
+            "java/io/WinNTFileSystem",
+            "sun/nio/fs/",
+            "sun/nio/ch/FileChannelImpl",
+        ];
+        const NETWORK: [&str; 3] = ["java/net/", "sun/net/", "sun/nio/ch/"];
+        const PROCESS: [&str; 3] = [
+            "java/lang/ProcessImpl",
+            "java/lang/ProcessHandleImpl",
+            "java/lang/ProcessEnvironment",
+        ];
+        const REFLECTION: [&str; 3] = [
+            "java/lang/reflect/",
+            "jdk/internal/reflect/",
+            "sun/reflect/",
+        ];
+
+        let matches = |prefixes: &[&str]| prefixes.iter().any(|prefix| class.starts_with(prefix));
+        if matches(&FILE_SYSTEM) {
+            Some(Permission::FileSystem)
+        } else if matches(&NETWORK) {
+            Some(Permission::Network)
+        } else if matches(&PROCESS) {
+            Some(Permission::Process)
+        } else if matches(&REFLECTION) {
+            Some(Permission::Reflection)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> fmt::Display for Permission<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Permission::Read(path) => write!(f, "read access to {}", path.display()),
+            Permission::Write(path) => write!(f, "write access to {}", path.display()),
+            Permission::FileSystem => write!(f, "filesystem access"),
+            Permission::Network => write!(f, "network access"),
+            Permission::Process => write!(f, "process execution"),
+            Permission::Reflection => write!(f, "reflective access"),
+        }
+    }
+}
+
+// =============================================================================
+// POLICY
+// =============================================================================
+
+/// What guest code may do to the host through the natives of the VM.
+///
+/// The default policy allows everything. Untrusted bytecode should run under
+/// [`VmPolicy::sandboxed`], opening up only what it needs:
+///
+/// ```
+/// use bvm::vm::policy::VmPolicy;
+///
+/// let policy = VmPolicy::sandboxed()
+///     .allow_read("/srv/data")
+///     .allow_reflection(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct VmPolicy {
+    /// Directories readable with their contents, or `None` if unrestricted.
+    readable: Option<Vec<PathBuf>>,
+    /// Directories writable with their contents, or `None` if unrestricted.
+    writable: Option<Vec<PathBuf>>,
+    network: bool,
+    processes: bool,
+    reflection: bool,
+}
+
+impl Default for VmPolicy {
+    fn default() -> Self {
+        VmPolicy::permissive()
+    }
+}
+
+impl VmPolicy {
+    /// A policy allowing everything.
+    pub fn permissive() -> Self {
+        VmPolicy {
+            readable: None,
+            writable: None,
+            network: true,
+            processes: true,
+            reflection: true,
+        }
+    }
+
+    /// A policy denying everything.
+    pub fn sandboxed() -> Self {
+        VmPolicy {
+            readable: Some(Vec::new()),
+            writable: Some(Vec::new()),
+            network: false,
+            processes: false,
+            reflection: false,
+        }
+    }
+
+    /// Allows reading the path, and everything below it if it is a directory.
+    pub fn allow_read<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        if let Some(readable) = &mut self.readable {
+            readable.push(normalize(&path.into()));
+        }
+        self
+    }
+
+    /// Allows reading and writing the path, and everything below it if it is
+    /// a directory.
+    pub fn allow_write<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = normalize(&path.into());
+        if let Some(readable) = &mut self.readable {
+            readable.push(path.clone());
+        }
+        if let Some(writable) = &mut self.writable {
+            writable.push(path);
+        }
+        self
+    }
+
+    pub fn allow_network(mut self, allow: bool) -> Self {
+        self.network = allow;
+        self
+    }
+
+    pub fn allow_processes(mut self, allow: bool) -> Self {
+        self.processes = allow;
+        self
+    }
+
+    pub fn allow_reflection(mut self, allow: bool) -> Self {
+        self.reflection = allow;
+        self
+    }
+
+    pub fn permits(&self, permission: Permission) -> bool {
+        let contains = |roots: &Option<Vec<PathBuf>>, path: &Path| match roots {
+            Some(roots) => {
+                let path = resolve(path);
+                roots.iter().any(|root| path.starts_with(resolve(root)))
+            }
+            None => true,
+        };
+
+        match permission {
+            Permission::Read(path) => contains(&self.readable, path),
+            Permission::Write(path) => contains(&self.writable, path),
+            Permission::FileSystem => self.readable.is_none() && self.writable.is_none(),
+            Permission::Network => self.network,
+            Permission::Process => self.processes,
+            Permission::Reflection => self.reflection,
+        }
+    }
+}
+
+/// Makes the path absolute and resolves its `.` and `..` components without
+/// touching the filesystem, so that `..` cannot escape an allowed directory.
+fn normalize(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Normalizes the path and resolves the symbolic links of its longest
+/// existing ancestor, so that a link within an allowed directory cannot
+/// escape it. The components which do not exist yet are kept as they are.
+fn resolve(path: &Path) -> PathBuf {
+    let path = normalize(path);
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return match rest.as_os_str().is_empty() {
+                true => resolved,
+                false => resolved.join(rest),
+            };
+        }
+    }
+    path
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod policy_tests {
+    use std::path::Path;
+
+    use super::{Permission, VmPolicy};
+
+    #[test]
+    fn test_sandboxed_paths() {
+        let policy = VmPolicy::sandboxed()
+            .allow_read("/srv/data")
+            .allow_write("/tmp/out");
+
+        assert!(policy.permits(Permission::Read(Path::new("/srv/data/a.txt"))));
+        assert!(policy.permits(Permission::Read(Path::new("/tmp/out/b.txt"))));
+        assert!(policy.permits(Permission::Write(Path::new("/tmp/out/./b.txt"))));
+        assert!(!policy.permits(Permission::Write(Path::new("/srv/data/a.txt"))));
+        assert!(!policy.permits(Permission::Read(Path::new("/srv/data/../../etc/passwd"))));
+        assert!(!policy.permits(Permission::Read(Path::new("/srv/database"))));
+        assert!(!policy.permits(Permission::FileSystem));
+        assert!(!policy.permits(Permission::Network));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symbolic_links() {
+        let root = std::env::temp_dir().join(format!("bvm-policy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("allowed")).unwrap();
+        std::fs::create_dir_all(root.join("secret")).unwrap();
+        std::os::unix::fs::symlink(root.join("secret"), root.join("allowed/link")).unwrap();
+        let policy = VmPolicy::sandboxed().allow_read(root.join("allowed"));
+
+        assert!(policy.permits(Permission::Read(&root.join("allowed/file.txt"))));
+        assert!(policy.permits(Permission::Read(&root.join("allowed/new/file.txt"))));
+        assert!(!policy.permits(Permission::Read(&root.join("allowed/link/file.txt"))));
+        assert!(!policy.permits(Permission::Read(&root.join("allowed/link"))));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_native_classes() {
+        assert_eq!(
+            Permission::for_native_class("java/net/Inet4AddressImpl"),
+            Some(Permission::Network)
+        );
+        assert_eq!(
+            Permission::for_native_class("java/io/UnixFileSystem"),
+            Some(Permission::FileSystem)
+        );
+        assert_eq!(Permission::for_native_class("java/lang/Float"), None);
+        assert_eq!(
+            Permission::for_native("java/lang/System", "getenv"),
+            Some(Permission::Process)
+        );
+        assert_eq!(
+            Permission::for_native("java/lang/reflect/Method", "invoke"),
+            Some(Permission::Reflection)
+        );
+        assert_eq!(
+            Permission::for_native("java/lang/Class", "getDeclaredField"),
+            Some(Permission::Reflection)
+        );
+        assert_eq!(Permission::for_native("java/lang/Class", "getName"), None);
+        assert_eq!(Permission::for_native("java/lang/System", "nanoTime"), None);
+    }
+}
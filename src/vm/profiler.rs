@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::vm::runtime::RuntimeMethod;
+
+/// How the profile is written when the VM exits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// A table of the methods sorted by self time.
+    Report,
+    /// One line per distinct stack with its self time in microseconds, the
+    /// input format of `flamegraph.pl` and `inferno`.
+    Collapsed,
+}
+
+/// Identifies a method by the address of its runtime representation, which
+/// the VM keeps alive as long as its class.
+type MethodKey = usize;
+
+fn key(method: &Arc<RuntimeMethod>) -> MethodKey {
+    Arc::as_ptr(method) as MethodKey
+}
+
+struct MethodStats {
+    method: Arc<RuntimeMethod>,
+    calls: u64,
+    /// Time spent in the method itself, excluding its callees.
+    self_time: Duration,
+    /// Time spent in the outermost invocations of the method, including its
+    /// callees.
+    total_time: Duration,
+}
+
+/// An invocation in progress.
+struct Activation {
+    method: Arc<RuntimeMethod>,
+    /// The depth of the interpreter frame of the invocation, `None` for
+    /// native methods which have no frame.
+    frame: Option<usize>,
+    started: Instant,
+    /// Time spent in the callees so far.
+    children: Duration,
+}
+
+/// Counts the invocations of every method and measures the time spent in
+/// them, written as a report or as collapsed stacks when the VM exits.
+pub struct MethodProfiler {
+    format: ProfileFormat,
+    output: Box<dyn Write + Send>,
+    methods: HashMap<MethodKey, MethodStats>,
+    activations: Vec<Activation>,
+    /// Self time by the stack of methods it was spent in, outermost first.
+    stacks: HashMap<Vec<MethodKey>, Duration>,
+}
+
+impl MethodProfiler {
+    /// A profiler writing a report to standard error.
+    pub fn new() -> Self {
+        MethodProfiler {
+            format: ProfileFormat::Report,
+            output: Box::new(io::stderr()),
+            methods: HashMap::new(),
+            activations: Vec::new(),
+            stacks: HashMap::new(),
+        }
+    }
+
+    pub fn format(mut self, format: ProfileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Records the start of an invocation.
+    pub(crate) fn enter(&mut self, method: &Arc<RuntimeMethod>, frame: Option<usize>) {
+        self.methods
+            .entry(key(method))
+            .or_insert_with(|| MethodStats {
+                method: method.clone(),
+                calls: 0,
+                self_time: Duration::ZERO,
+                total_time: Duration::ZERO,
+            })
+            .calls += 1;
+        self.activations.push(Activation {
+            method: method.clone(),
+            frame,
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    /// Records the end of the innermost invocation.
+    pub(crate) fn leave(&mut self) {
+        let activation = match self.activations.pop() {
+            Some(activation) => activation,
+            None => return,
+        };
+
+        let method = key(&activation.method);
+        let total = activation.started.elapsed();
+        let own = total.saturating_sub(activation.children);
+        let recursive = self
+            .activations
+            .iter()
+            .any(|outer| key(&outer.method) == method);
+        if let Some(stats) = self.methods.get_mut(&method) {
+            stats.self_time += own;
+            if !recursive {
+                stats.total_time += total;
+            }
+        }
+        if let Some(caller) = self.activations.last_mut() {
+            caller.children += total;
+        }
+
+        if self.format == ProfileFormat::Collapsed {
+            let mut stack: Vec<MethodKey> = self
+                .activations
+                .iter()
+                .map(|activation| key(&activation.method))
+                .collect();
+            stack.push(method);
+            *self.stacks.entry(stack).or_default() += own;
+        }
+    }
+
+    /// Records the end of the invocations whose frames were popped, leaving
+    /// `depth` frames on the stack.
+    pub(crate) fn leave_frames_above(&mut self, depth: usize) {
+        while let Some(Some(frame)) = self.activations.last().map(|activation| activation.frame) {
+            if frame <= depth {
+                break;
+            }
+            self.leave();
+        }
+    }
+
+    /// Writes the profile, with `class_name` naming the class of a method.
+    pub(crate) fn write(
+        &mut self,
+        class_name: impl Fn(&RuntimeMethod) -> String,
+    ) -> io::Result<()> {
+        while !self.activations.is_empty() {
+            self.leave();
+        }
+
+        match self.format {
+            ProfileFormat::Report => self.write_report(class_name)?,
+            ProfileFormat::Collapsed => self.write_collapsed(class_name)?,
+        }
+        self.output.flush()
+    }
+
+    fn write_report(&mut self, class_name: impl Fn(&RuntimeMethod) -> String) -> io::Result<()> {
+        let mut methods: Vec<&MethodStats> = self.methods.values().collect();
+        methods.sort_by(|a, b| b.self_time.cmp(&a.self_time).then(b.calls.cmp(&a.calls)));
+
+        writeln!(
+            self.output,
+            "{:>12} {:>12} {:>10}  method",
+            "self ms", "total ms", "calls"
+        )?;
+        for stats in methods {
+            writeln!(
+                self.output,
+                "{:>12.3} {:>12.3} {:>10}  {}.{}{}",
+                stats.self_time.as_secs_f64() * 1000.0,
+                stats.total_time.as_secs_f64() * 1000.0,
+                stats.calls,
+                class_name(&stats.method),
+                stats.method.name,
+                stats.method.descriptor
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Frames are named without descriptors, which could contain the `;`
+    /// separating them.
+    fn write_collapsed(&mut self, class_name: impl Fn(&RuntimeMethod) -> String) -> io::Result<()> {
+        let mut lines: Vec<(String, u128)> = self
+            .stacks
+            .iter()
+            .map(|(stack, time)| {
+                let frames: Vec<String> = stack
+                    .iter()
+                    .map(|method| {
+                        let method = &self.methods[method].method;
+                        format!("{}.{}", class_name(method), method.name)
+                    })
+                    .collect();
+                (frames.join(";"), time.as_micros())
+            })
+            .collect();
+        lines.sort();
+
+        for (stack, micros) in lines {
+            writeln!(self.output, "{} {}", stack, micros)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for MethodProfiler {
+    fn default() -> Self {
+        MethodProfiler::new()
+    }
+}
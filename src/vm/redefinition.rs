@@ -0,0 +1,136 @@
+use std::mem;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::class::ClassLoadingError;
+use crate::vm::diff::{diff_classes, DifferenceKind};
+use crate::vm::linker::verify;
+use crate::vm::loader::{LoadedClass, LoaderId};
+use crate::vm::runtime::{ClassId, RuntimeMethod};
+use crate::vm::{Vm, VmError};
+
+impl Vm {
+    /// Replaces the code of the methods of a loaded class with the one of
+    /// the class file, like a debugger's hot code replace. Only method bodies
+    /// may change: the class is rejected with
+    /// [VmError::UnsupportedRedefinition] if it adds, removes or changes the
+    /// signature or flags of a member, or changes its supertypes. New code
+    /// failing verification is rejected with [VmError::ClassLoading].
+    ///
+    /// Invocations from then on run the new code, and the constant pool
+    /// entries the old code resolved are resolved again. The static fields
+    /// keep their values and the class is not initialized again. The class
+    /// file passes through the transformers, like when the class was loaded.
+    /// Classes with a method running on a stack cannot be redefined, as its
+    /// frame would resolve the new constant pool from the old code.
+    pub fn redefine_class(&mut self, name: &str, bytes: &[u8]) -> Result<(), VmError> {
+        let id = self.find_class(name)?;
+        let old = match &self.class(id).source {
+            Some(source) => source.clone(),
+            None => {
+                let message = format!("{} is built into the VM", name);
+                return Err(VmError::UnsupportedRedefinition(message));
+            }
+        };
+        let started = Instant::now();
+        let class = self.loaders.parse_class(name, old.defining_loader, bytes)?;
+        let parse_time = started.elapsed();
+        if class.name()? != name {
+            let message = format!("{} (wrong name: {})", name, class.name()?);
+            return Err(VmError::UnsupportedRedefinition(message));
+        }
+        let changes: Vec<String> = diff_classes(&old.class, &class, false)?
+            .into_iter()
+            .filter(|difference| {
+                difference.kind != DifferenceKind::Changed || difference.details != ["code changed"]
+            })
+            .map(|difference| difference.to_string())
+            .collect();
+        if !changes.is_empty() {
+            return Err(VmError::UnsupportedRedefinition(changes.join("\n")));
+        }
+        if old.defining_loader != LoaderId::BOOTSTRAP {
+            verify(&class).map_err(|message| ClassLoadingError::new(&message))?;
+        }
+        if self.is_running(id) {
+            let message = format!("a method of {} is running", name);
+            return Err(VmError::UnsupportedRedefinition(message));
+        }
+
+        let loaded = Arc::new(LoadedClass {
+            name: old.name.clone(),
+            defining_loader: old.defining_loader,
+            source: old.source.clone(),
+            size: bytes.len(),
+            parse_time,
+            archived: false,
+            links: None,
+            class,
+        });
+        // The methods keep their order, which reflection relies on
+        let mut methods = Vec::with_capacity(self.class(id).methods.len());
+        for method in self.class(id).methods.clone() {
+            let attributes = loaded.class.methods.iter().find_map(|new| {
+                let pool = &loaded.class.constant_pool;
+                let name = pool.get_utf8(new.name_index).ok()?;
+                let descriptor = pool.get_utf8(new.descriptor_index).ok()?;
+                (name == method.name.as_str() && descriptor == method.descriptor.as_str())
+                    .then_some(&new.attributes)
+            });
+            let code = match attributes {
+                Some(attributes) => self.method_code(&loaded, attributes)?,
+                None => None,
+            };
+            methods.push(Arc::new(RuntimeMethod {
+                class: id,
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                parsed_descriptor: method.parsed_descriptor.clone(),
+                access_flags: method.access_flags,
+                code,
+                native: method.native,
+                permission: method.permission,
+            }));
+        }
+
+        let class = self.class_mut(id);
+        class.source = Some(loaded);
+        let obsolete = mem::replace(&mut class.methods, methods);
+        self.invalidate_resolutions(id);
+        for method in obsolete {
+            #[cfg(feature = "jit")]
+            self.discard_compiled(&method);
+            self.obsolete_methods.push(method);
+        }
+        tracing::debug!(class = name, "redefined class");
+        Ok(())
+    }
+
+    /// Whether a frame of any thread runs a method of the class.
+    fn is_running(&self, class: ClassId) -> bool {
+        self.suspended_stacks
+            .iter()
+            .chain([&self.thread])
+            .flat_map(|stack| &stack.frames)
+            .any(|frame| frame.method.class == class)
+    }
+
+    /// Forgets the constant pool entries of the class resolved so far, and
+    /// the resolved methods and inline caches referencing its methods.
+    fn invalidate_resolutions(&mut self, class: ClassId) {
+        self.field_cache.retain(|&(owner, _), _| owner != class);
+        self.call_sites.retain(|&(owner, _), _| owner != class);
+        self.method_cache
+            .retain(|&(owner, _), method| owner != class && method.class != class);
+        for cache in &mut self.inline_caches {
+            if cache
+                .resolved
+                .as_ref()
+                .is_some_and(|resolved| resolved.class == class)
+            {
+                cache.resolved = None;
+            }
+            cache.targets.retain(|(_, target)| target.class != class);
+        }
+    }
+}
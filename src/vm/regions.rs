@@ -0,0 +1,353 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use crate::class::attributes::ExceptionTableAttribute;
+use crate::class::instruction::DecodedCode;
+use crate::vm::cfg::ControlFlowGraph;
+
+// =============================================================================
+// TRY REGIONS
+// =============================================================================
+
+/// A `catch` clause of a try statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatchClause {
+    /// Constant pool indices of the classes caught, several for a
+    /// multi-catch.
+    pub catch_types: Vec<u16>,
+    /// Indices of the instructions of the handler.
+    pub handler: Range<usize>,
+}
+
+/// A try statement rebuilt from the exception table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryRegion {
+    /// Indices of the instructions of the try block. Compilers inline the
+    /// finally block on the exits of the body, which may leave these copies
+    /// inside it.
+    pub body: Range<usize>,
+    pub catches: Vec<CatchClause>,
+    /// The handler of anything thrown in the body or the catch clauses,
+    /// running the finally block, or leaving the monitor of a synchronized
+    /// one, before throwing it again.
+    pub finally: Option<Range<usize>>,
+    /// The try statements in the body and the handlers, in bytecode order.
+    pub nested: Vec<TryRegion>,
+}
+
+impl TryRegion {
+    /// The instructions of the whole statement, from the body to the end of
+    /// the last handler.
+    pub fn extent(&self) -> Range<usize> {
+        let end = self
+            .catches
+            .iter()
+            .map(|clause| clause.handler.end)
+            .chain(self.finally.as_ref().map(|finally| finally.end))
+            .fold(self.body.end, usize::max);
+        self.body.start..end
+    }
+
+    /// Writes the statement and the nested ones, one clause per line, with
+    /// the pcs of the bounds of their instructions.
+    pub fn write<W: Write>(
+        &self,
+        writer: &mut W,
+        indent: usize,
+        pc: &impl Fn(usize) -> u32,
+        catch_type: &impl Fn(u16) -> String,
+    ) -> fmt::Result {
+        let range = |range: &Range<usize>| format!("{}-{}", pc(range.start), pc(range.end));
+        let padding = " ".repeat(indent);
+        writeln!(writer, "{}try {}", padding, range(&self.body))?;
+        for clause in &self.catches {
+            let types: Vec<String> = clause
+                .catch_types
+                .iter()
+                .map(|&index| catch_type(index))
+                .collect();
+            writeln!(
+                writer,
+                "{}catch {} {}",
+                padding,
+                types.join(" | "),
+                range(&clause.handler)
+            )?;
+        }
+        if let Some(finally) = &self.finally {
+            writeln!(writer, "{}finally {}", padding, range(finally))?;
+        }
+        for nested in &self.nested {
+            nested.write(writer, indent + 2, pc, catch_type)?;
+        }
+        Ok(())
+    }
+}
+
+/// The handlers of the exception table, entries sharing a handler merged.
+struct Handler {
+    start: usize,
+    /// Sorted, adjacent ranges joined.
+    ranges: Vec<Range<usize>>,
+    catch_types: Vec<u16>,
+}
+
+/// Rebuilds the try statements of a method from its flat exception table,
+/// nesting them by the instructions they span.
+///
+/// Entries with the same handler make up a clause, `catch` clauses covering
+/// the same instructions a statement, and a handler of any exception covering
+/// the start of a statement and its `catch` clauses its `finally` clause. A
+/// handler spans the instructions it dominates, up to the first one it does
+/// not.
+pub fn try_regions(
+    code: &DecodedCode,
+    exception_table: &[ExceptionTableAttribute],
+) -> Vec<TryRegion> {
+    let count = code.instructions.len();
+    let index_of = |pc: u16| code.pcs.partition_point(|&start| start < pc as u32);
+
+    let mut catches: Vec<Handler> = Vec::new();
+    let mut finallies: Vec<Handler> = Vec::new();
+    for entry in exception_table {
+        let (start, end, handler) = (
+            index_of(entry.start_pc),
+            index_of(entry.end_pc),
+            index_of(entry.handler_pc),
+        );
+        if start >= end || handler >= count {
+            continue;
+        }
+        let handlers = if entry.catch_type == 0 {
+            &mut finallies
+        } else {
+            &mut catches
+        };
+        let position = match handlers.iter().position(|other| other.start == handler) {
+            Some(position) => position,
+            None => {
+                handlers.push(Handler {
+                    start: handler,
+                    ranges: Vec::new(),
+                    catch_types: Vec::new(),
+                });
+                handlers.len() - 1
+            }
+        };
+        let handler = &mut handlers[position];
+        if !handler.ranges.contains(&(start..end)) {
+            handler.ranges.push(start..end);
+        }
+        if !handler.catch_types.contains(&entry.catch_type) {
+            handler.catch_types.push(entry.catch_type);
+        }
+    }
+    for handler in catches.iter_mut().chain(&mut finallies) {
+        handler.ranges.sort_by_key(|range| (range.start, range.end));
+        let mut joined: Vec<Range<usize>> = Vec::new();
+        for range in handler.ranges.drain(..) {
+            match joined.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => joined.push(range),
+            }
+        }
+        handler.ranges = joined;
+    }
+
+    let graph = ControlFlowGraph::build(code, exception_table);
+    let dominators = graph.dominators();
+    let dominates = |dominator: usize, mut block: usize| loop {
+        if block == dominator {
+            return true;
+        }
+        match dominators[block] {
+            Some(next) if next != block => block = next,
+            _ => return false,
+        }
+    };
+    let handler_extent = |start: usize| {
+        let first = match graph.block_of(start) {
+            Some(block) => block,
+            None => return start..start,
+        };
+        let last = (first..graph.blocks.len())
+            .take_while(|&block| dominates(first, block))
+            .last()
+            .unwrap_or(first);
+        start..graph.blocks[last].instructions.end
+    };
+
+    // Catch clauses covering the same ranges belong to one statement
+    let mut regions: Vec<(Vec<Range<usize>>, TryRegion)> = Vec::new();
+    for handler in &catches {
+        let clause = CatchClause {
+            catch_types: handler.catch_types.clone(),
+            handler: handler_extent(handler.start),
+        };
+        match regions
+            .iter_mut()
+            .find(|(ranges, _)| *ranges == handler.ranges)
+        {
+            Some((_, region)) => region.catches.push(clause),
+            None => {
+                let body = handler.ranges[0].start..handler.ranges[handler.ranges.len() - 1].end;
+                let region = TryRegion {
+                    body,
+                    catches: vec![clause],
+                    finally: None,
+                    nested: Vec::new(),
+                };
+                regions.push((handler.ranges.clone(), region));
+            }
+        }
+    }
+    for handler in &finallies {
+        let covers = |index: usize| handler.ranges.iter().any(|range| range.contains(&index));
+        let start = handler.ranges[0].start;
+        // Of nested statements starting together, the one whose catch
+        // clauses come last, right before the finally clause
+        let statement = regions
+            .iter_mut()
+            .filter(|(_, region)| {
+                region.finally.is_none()
+                    && region.body.start == start
+                    && region.catches.iter().all(|clause| {
+                        clause.handler.start < handler.start && covers(clause.handler.start)
+                    })
+            })
+            .max_by_key(|(_, region)| region.catches.last().map(|clause| clause.handler.start));
+        let finally = Some(handler_extent(handler.start));
+        match statement {
+            Some((_, region)) => region.finally = finally,
+            None => {
+                // Leaving out the ranges covering the handler itself
+                let end = handler
+                    .ranges
+                    .iter()
+                    .filter(|range| range.start < handler.start)
+                    .map(|range| range.end)
+                    .max()
+                    .unwrap_or(handler.ranges[0].end);
+                let region = TryRegion {
+                    body: start..end,
+                    catches: Vec::new(),
+                    finally,
+                    nested: Vec::new(),
+                };
+                regions.push((handler.ranges.clone(), region));
+            }
+        }
+    }
+
+    let mut regions: Vec<TryRegion> = regions.into_iter().map(|(_, region)| region).collect();
+    regions.sort_by_key(|region| {
+        let extent = region.extent();
+        (extent.start, usize::MAX - extent.end)
+    });
+    let mut roots = Vec::new();
+    for region in regions {
+        nest(&mut roots, region);
+    }
+    roots
+}
+
+/// Adds the region to the innermost one spanning it, or to the list.
+fn nest(regions: &mut Vec<TryRegion>, region: TryRegion) {
+    let extent = region.extent();
+    let parent = regions.iter_mut().rev().find(|parent| {
+        let outer = parent.extent();
+        outer.start <= extent.start && extent.end <= outer.end
+    });
+    match parent {
+        Some(parent) => nest(&mut parent.nested, region),
+        None => regions.push(region),
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod regions_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{try_regions, CatchClause, TryRegion};
+    use crate::class::attributes::{Attribute, CodeAttribute};
+    use crate::class::instruction::decode;
+    use crate::class::Class;
+
+    fn code<'a>(class: &'a Class, name: &str) -> &'a CodeAttribute {
+        let name_index = class.constant_pool.find_utf8(name).unwrap();
+        class
+            .methods
+            .iter()
+            .find(|method| method.name_index == name_index)
+            .and_then(|method| {
+                method
+                    .attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::Code(code) => Some(code),
+                        _ => None,
+                    })
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_try_regions() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden/Exceptions.class");
+        let class = Class::parse_bytes(&fs::read(path).unwrap()).unwrap();
+
+        // try { return a / b; }
+        // catch (ArithmeticException e) { ...; return 0; }
+        // finally { ... }
+        let divide = code(&class, "divide");
+        let decoded = decode(&divide.code);
+        let regions = try_regions(&decoded, &divide.exception_tables);
+        let index_of = |pc: u32| decoded.pcs.iter().position(|&start| start == pc).unwrap();
+        assert_eq!(
+            regions,
+            vec![TryRegion {
+                body: 0..index_of(4),
+                catches: vec![CatchClause {
+                    catch_types: vec![divide.exception_tables[0].catch_type],
+                    handler: index_of(14)..index_of(37),
+                }],
+                finally: Some(index_of(37)..decoded.instructions.len()),
+                nested: Vec::new(),
+            }]
+        );
+
+        let mut text = String::new();
+        regions[0]
+            .write(
+                &mut text,
+                2,
+                &|index| {
+                    decoded
+                        .pcs
+                        .get(index)
+                        .copied()
+                        .unwrap_or(divide.code.len() as u32)
+                },
+                &|catch_type| class.constant_pool.describe(catch_type),
+            )
+            .unwrap();
+        assert!(text.starts_with("  try 0-4\n  catch "));
+        assert!(text.ends_with(" 14-37\n  finally 37-50\n"));
+
+        // Three statements one after the other
+        let main = code(&class, "main");
+        let regions = try_regions(&decode(&main.code), &main.exception_tables);
+        assert_eq!(regions.len(), 3);
+        assert!(regions
+            .iter()
+            .all(|region| region.catches.len() == 1 && region.nested.is_empty()));
+        assert!(regions
+            .windows(2)
+            .all(|pair| pair[0].extent().end <= pair[1].body.start));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+
+use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+
+// =============================================================================
+// DIAGNOSTICS
+// =============================================================================
+
+/// A class provided by more than one classpath entry. The first provider is
+/// the one the registry resolves to, the rest are shadowed.
+#[derive(Debug)]
+pub struct DuplicateClass<'a> {
+    pub name: &'a str,
+    pub providers: Vec<&'a Path>,
+}
+
+/// A package whose classes are spread across more than one classpath entry.
+#[derive(Debug)]
+pub struct SplitPackage<'a> {
+    pub package: &'a str,
+    pub providers: Vec<&'a Path>,
+}
+
+/// Returns the package part of an internal class name, e.g. `java/lang` for
+/// `java/lang/Object`, or an empty string for the unnamed package.
+pub fn package_of(class_name: &str) -> &str {
+    match class_name.rfind('/') {
+        Some(index) => &class_name[..index],
+        None => "",
+    }
+}
+
+// =============================================================================
+// CLASS REGISTRY
+// =============================================================================
+
+/// Index of every class available on a [ClassPath].
+///
+/// Resolution is deterministic: when multiple entries provide the same class,
+/// the entry appearing first on the classpath wins, the same way the reference
+/// launcher behaves.
+pub struct ClassRegistry {
+    class_path: ClassPath,
+    /// Indices of the entries providing each class, in classpath order.
+    providers: BTreeMap<String, Vec<usize>>,
+}
+
+impl ClassRegistry {
+    pub fn new(class_path: ClassPath) -> io::Result<ClassRegistry> {
+        let mut providers: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for index in 0..class_path.entries().len() {
+            for name in class_path.class_names(index)? {
+                providers.entry(name).or_default().push(index);
+            }
+        }
+
+        Ok(ClassRegistry {
+            class_path,
+            providers,
+        })
+    }
+
+    pub fn class_path(&self) -> &ClassPath {
+        &self.class_path
+    }
+
+    /// Internal names of all the classes available, in sorted order.
+    pub fn class_names(&self) -> impl Iterator<Item = &str> {
+        self.providers.keys().map(String::as_str)
+    }
+
+    /// The classpath entry the class resolves to, if any provides it.
+    pub fn provider(&self, name: &str) -> Option<&ClassPathEntry> {
+        let index = *self.providers.get(name)?.first()?;
+        Some(&self.class_path.entries()[index])
+    }
+
+    /// The index of the classpath entry the class resolves to, if any
+    /// provides it.
+    pub fn provider_index(&self, name: &str) -> Option<usize> {
+        self.providers.get(name)?.first().copied()
+    }
+
+    /// Paths of every classpath entry providing the class, the one it
+    /// resolves to first.
+    pub fn providers(&self, name: &str) -> Vec<&Path> {
+        match self.providers.get(name) {
+            Some(providers) => self.entry_paths(providers.iter()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Reads the bytes of the class from the entry it resolves to.
+    pub fn read_class(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match self.provider(name) {
+            Some(entry) => entry.read_class(name),
+            None => Ok(None),
+        }
+    }
+
+    /// Classes provided by more than one classpath entry, sorted by name.
+    pub fn duplicate_classes(&self) -> Vec<DuplicateClass<'_>> {
+        self.providers
+            .iter()
+            .filter(|(_, providers)| providers.len() > 1)
+            .map(|(name, providers)| DuplicateClass {
+                name,
+                providers: self.entry_paths(providers.iter()),
+            })
+            .collect()
+    }
+
+    /// Named packages with classes coming from more than one classpath entry,
+    /// sorted by package name.
+    pub fn split_packages(&self) -> Vec<SplitPackage<'_>> {
+        let mut packages: BTreeMap<&str, BTreeSet<usize>> = BTreeMap::new();
+        for (name, providers) in &self.providers {
+            let package = package_of(name);
+            if !package.is_empty() {
+                packages
+                    .entry(package)
+                    .or_default()
+                    .extend(providers.iter().copied());
+            }
+        }
+
+        packages
+            .into_iter()
+            .filter(|(_, providers)| providers.len() > 1)
+            .map(|(package, providers)| SplitPackage {
+                package,
+                providers: self.entry_paths(providers.iter()),
+            })
+            .collect()
+    }
+
+    fn entry_paths<'a, I: Iterator<Item = &'a usize>>(&self, indices: I) -> Vec<&Path> {
+        indices
+            .map(|index| self.class_path.entries()[*index].path())
+            .collect()
+    }
+}
+
+// ============================================================================
+// CLASS REGISTRY TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod class_registry_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::ClassRegistry;
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+
+    fn class_dir(name: &str, classes: &[(&str, &[u8])]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("bvm-registry-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (class, bytes) in classes {
+            let path = root.join(format!("{}.class", class));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, bytes).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_first_entry_wins() {
+        let first = class_dir("first", &[("a/A", b"first"), ("a/B", b"b")]);
+        let second = class_dir("second", &[("a/A", b"second"), ("c/C", b"c")]);
+
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(&first).unwrap());
+        class_path.push(ClassPathEntry::open(&second).unwrap());
+        let registry = ClassRegistry::new(class_path).unwrap();
+
+        assert_eq!(registry.read_class("a/A").unwrap().unwrap(), b"first");
+        assert_eq!(registry.provider("c/C").unwrap().path(), second);
+
+        let duplicates = registry.duplicate_classes();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "a/A");
+        assert_eq!(duplicates[0].providers, vec![&first, &second]);
+
+        let split_packages = registry.split_packages();
+        assert_eq!(split_packages.len(), 1);
+        assert_eq!(split_packages[0].package, "a");
+    }
+}
@@ -0,0 +1,525 @@
+use std::fmt::Write;
+
+use crate::class::descriptor::FieldType;
+use crate::class::instruction;
+use crate::class::MethodAccessFlags;
+use crate::vm::natives::lang::type_name;
+use crate::vm::runtime::{ClassId, RuntimeMethod};
+use crate::vm::value::{JValue, ObjectRef, Value};
+use crate::vm::Vm;
+
+const HELP: &str = "\
+load <class>                  loads and links a class
+methods <class>               lists the methods of a class
+disasm <class> [method]       prints the bytecode of the methods, optionally
+                              only the ones with the name or name+descriptor
+call <class>.<method>[descriptor] [argument...]
+                              calls a static method, the arguments being
+                              numbers, 'c' chars, true, false, null,
+                              \"strings\" or $n results
+inspect <$n | class>          prints the fields of a result, or the static
+                              fields of a class
+heap                          prints heap and collection statistics
+gc                            collects garbage, then prints the statistics
+help                          prints this help
+quit                          leaves the shell";
+
+const COMMANDS: [&str; 10] = [
+    "load", "methods", "disasm", "call", "inspect", "heap", "gc", "help", "quit", "exit",
+];
+
+/// How many elements of an array [Repl] prints.
+const MAX_ELEMENTS: usize = 32;
+
+/// An interactive shell driving a VM, for inspecting classes and calling
+/// into them while developing the VM, see [Repl::execute] for the commands.
+/// The values returned by calls are kept as `$1`, `$2`, ... to be inspected
+/// or passed to later calls.
+pub struct Repl {
+    vm: Vm,
+    results: Vec<JValue>,
+}
+
+impl Repl {
+    pub fn new(vm: Vm) -> Self {
+        Repl {
+            vm,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn vm(&mut self) -> &mut Vm {
+        &mut self.vm
+    }
+
+    /// The VM, e.g. to [shut it down](Vm::shutdown) when leaving the shell.
+    pub fn into_vm(self) -> Vm {
+        self.vm
+    }
+
+    /// Runs a command line, returning what it prints, or `None` for `quit`.
+    /// Run `help` for the commands; class names can be internal or binary
+    /// names, e.g. `java/lang/String` or `java.lang.String`.
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        let words = split_words(line)?;
+        let (command, arguments) = match words.split_first() {
+            Some((command, arguments)) => (command.as_str(), arguments),
+            None => return Ok(Some(String::new())),
+        };
+        let output = match (command, arguments) {
+            ("quit" | "exit", []) => return Ok(None),
+            ("help", []) => format!("{}\n", HELP),
+            ("load", [class]) => self.load(class)?,
+            ("methods", [class]) => self.methods(class)?,
+            ("disasm", [class]) => self.disassemble(class, None)?,
+            ("disasm", [class, method]) => self.disassemble(class, Some(method))?,
+            ("call", [target, arguments @ ..]) => self.call(target, arguments)?,
+            ("inspect", [value]) => self.inspect(value)?,
+            ("heap", []) => self.heap(),
+            ("gc", []) => {
+                self.vm
+                    .collect_garbage()
+                    .map_err(|error| error.to_string())?;
+                self.heap()
+            }
+            _ if COMMANDS.contains(&command) => {
+                return Err(format!("Wrong arguments for {}, see help", command));
+            }
+            _ => return Err(format!("Unknown command {}, see help", command)),
+        };
+        Ok(Some(output))
+    }
+
+    fn find_class(&mut self, name: &str) -> Result<ClassId, String> {
+        self.vm
+            .find_class(&name.replace('.', "/"))
+            .map_err(|error| error.to_string())
+    }
+
+    fn load(&mut self, name: &str) -> Result<String, String> {
+        let class = self.find_class(name)?;
+        let class = self.vm.class(class);
+        let source = match &class.source {
+            Some(source) => source.source.display().to_string(),
+            None => "builtin".to_string(),
+        };
+        Ok(format!(
+            "{} from {}, {} methods, {} fields\n",
+            class.java_name(),
+            source,
+            class.methods.len(),
+            class.fields.len()
+        ))
+    }
+
+    fn methods(&mut self, name: &str) -> Result<String, String> {
+        let class = self.find_class(name)?;
+        let class = self.vm.class(class);
+        let mut output = String::new();
+        for method in &class.methods {
+            writeln!(output, "{}", signature(method)).unwrap();
+        }
+        Ok(output)
+    }
+
+    fn disassemble(&mut self, name: &str, method_name: Option<&str>) -> Result<String, String> {
+        let class = self.find_class(name)?;
+        let class = self.vm.class(class);
+        let pool = match &class.source {
+            Some(source) => &source.class.constant_pool,
+            None => return Err(format!("{} is built into the VM", class.java_name())),
+        };
+        let mut output = String::new();
+        let methods = class.methods.iter().filter(|method| {
+            method_name.is_none_or(|name| {
+                method.name == name || format!("{}{}", method.name, method.descriptor) == name
+            })
+        });
+        for method in methods {
+            writeln!(output, "{}", signature(method)).unwrap();
+            let code = match &method.code {
+                Some(code) => code,
+                None => continue,
+            };
+            let decoded = instruction::decode(&code.code);
+            for (index, pc) in decoded.pcs.iter().enumerate() {
+                let instruction = instruction::disassemble(&code.code, &decoded, index, pool);
+                writeln!(output, "  {:>4}: {}", pc, instruction).unwrap();
+            }
+        }
+        if output.is_empty() {
+            return Err(format!(
+                "No method {} in {}",
+                method_name.unwrap_or(""),
+                name
+            ));
+        }
+        Ok(output)
+    }
+
+    fn call(&mut self, target: &str, arguments: &[String]) -> Result<String, String> {
+        let (target, descriptor) = match target.find('(') {
+            Some(index) => (&target[..index], Some(&target[index..])),
+            None => (target, None),
+        };
+        let (class_name, method_name) = target
+            .rsplit_once('.')
+            .ok_or_else(|| format!("Expected <class>.<method>, got {}", target))?;
+        let class_name = class_name.replace('.', "/");
+        let class = self.find_class(&class_name)?;
+
+        let candidates: Vec<_> = self
+            .vm
+            .class(class)
+            .methods
+            .iter()
+            .filter(|method| method.is_static() && method.name == method_name)
+            .filter(|method| descriptor.is_none_or(|descriptor| method.descriptor == descriptor))
+            .filter(|method| method.parsed_descriptor.parameters.len() == arguments.len())
+            .cloned()
+            .collect();
+        let method = match candidates.as_slice() {
+            [method] => method,
+            [] => {
+                return Err(format!(
+                    "No static method {}{} with {} parameters in {}",
+                    method_name,
+                    descriptor.unwrap_or(""),
+                    arguments.len(),
+                    class_name
+                ));
+            }
+            _ => {
+                let overloads: Vec<String> =
+                    candidates.iter().map(|method| signature(method)).collect();
+                return Err(format!(
+                    "Ambiguous call, add the descriptor of one of:\n  {}",
+                    overloads.join("\n  ")
+                ));
+            }
+        };
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for (argument, parameter) in arguments.iter().zip(&method.parsed_descriptor.parameters) {
+            values.push(self.literal(argument, parameter)?);
+        }
+        let result = self
+            .vm
+            .invoke_static(&class_name, &method.name, &method.descriptor, &values)
+            .map_err(|error| error.to_string())?;
+        Ok(match result {
+            Some(value) => {
+                self.results.push(value);
+                format!("${} = {}\n", self.results.len(), self.describe(value))
+            }
+            None => String::new(),
+        })
+    }
+
+    /// Reads a literal argument passed as the parameter type.
+    fn literal(&mut self, literal: &str, parameter: &FieldType) -> Result<JValue, String> {
+        let invalid = || format!("{} is not a valid {}", literal, type_name(parameter));
+        let value = match parameter {
+            _ if literal.starts_with('$') => self.result(literal)?,
+            FieldType::Boolean => match literal {
+                "true" => JValue::Int(1),
+                "false" => JValue::Int(0),
+                _ => return Err(invalid()),
+            },
+            FieldType::Char => {
+                let mut chars = literal
+                    .strip_prefix('\'')
+                    .and_then(|rest| rest.strip_suffix('\''))
+                    .ok_or_else(invalid)?
+                    .encode_utf16();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => JValue::Int(c as i32),
+                    _ => return Err(invalid()),
+                }
+            }
+            FieldType::Byte | FieldType::Short | FieldType::Int => {
+                JValue::Int(literal.parse().map_err(|_| invalid())?)
+            }
+            FieldType::Long => {
+                let digits = literal.strip_suffix(['l', 'L']).unwrap_or(literal);
+                JValue::Long(digits.parse().map_err(|_| invalid())?)
+            }
+            FieldType::Float => {
+                let digits = literal.strip_suffix(['f', 'F']).unwrap_or(literal);
+                JValue::Float(digits.parse().map_err(|_| invalid())?)
+            }
+            FieldType::Double => {
+                let digits = literal.strip_suffix(['d', 'D']).unwrap_or(literal);
+                JValue::Double(digits.parse().map_err(|_| invalid())?)
+            }
+            _ if literal == "null" => JValue::Null,
+            _ => match literal.strip_prefix('"') {
+                Some(string) => {
+                    let string = self
+                        .vm
+                        .new_string(string)
+                        .map_err(|error| error.to_string())?;
+                    JValue::Object(string)
+                }
+                None => return Err(invalid()),
+            },
+        };
+        Ok(value)
+    }
+
+    /// The result named `$n`.
+    fn result(&self, name: &str) -> Result<JValue, String> {
+        name.strip_prefix('$')
+            .and_then(|number| number.parse::<usize>().ok())
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| self.results.get(index).copied())
+            .ok_or_else(|| format!("No result {}", name))
+    }
+
+    fn inspect(&mut self, name: &str) -> Result<String, String> {
+        if !name.starts_with('$') {
+            return self.inspect_class(name);
+        }
+        let object = match self.result(name)? {
+            JValue::Object(object) => object,
+            value => return Ok(format!("{}\n", self.describe(value))),
+        };
+
+        let mut output = format!("{}\n", self.describe(JValue::Object(object)));
+        let heap_object = self.vm.heap.get(object);
+        if let Some(array) = heap_object.array() {
+            for index in 0..array.len().min(MAX_ELEMENTS) {
+                if let Some(element) = array.get(index) {
+                    writeln!(output, "  [{}] = {}", index, self.describe_value(element)).unwrap();
+                }
+            }
+            if array.len() > MAX_ELEMENTS {
+                writeln!(output, "  ... {} more", array.len() - MAX_ELEMENTS).unwrap();
+            }
+            return Ok(output);
+        }
+        // The fields of the superclasses first, like they are laid out
+        let mut classes = Vec::new();
+        let mut current = Some(heap_object.class);
+        while let Some(class) = current {
+            classes.push(class);
+            current = self.vm.class(class).super_class;
+        }
+        for class in classes.into_iter().rev() {
+            for field in &self.vm.class(class).fields {
+                if field.is_static() {
+                    continue;
+                }
+                if let Some(&value) = heap_object.fields().get(field.slot) {
+                    let value = self.describe_value(value);
+                    writeln!(output, "  {} {} = {}", field.descriptor, field.name, value).unwrap();
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    fn inspect_class(&mut self, name: &str) -> Result<String, String> {
+        let class = self.find_class(name)?;
+        let runtime_class = self.vm.class(class);
+        let mut output = format!(
+            "{} ({:?})\n",
+            runtime_class.java_name(),
+            runtime_class.state
+        );
+        for field in runtime_class
+            .fields
+            .iter()
+            .filter(|field| field.is_static())
+        {
+            if let Some(&value) = runtime_class.static_values.get(field.slot) {
+                let value = self.describe_value(value);
+                writeln!(
+                    output,
+                    "  static {} {} = {}",
+                    field.descriptor, field.name, value
+                )
+                .unwrap();
+            }
+        }
+        Ok(output)
+    }
+
+    fn heap(&self) -> String {
+        let stats = self.vm.heap_stats();
+        format!(
+            "heap: {} objects, {} bytes ({} live after the last collection)\n\
+             allocated: {} objects, {} bytes\n\
+             collections: {}, freed {} objects, {} bytes in {:.3} ms\n",
+            stats.used_objects,
+            stats.used_bytes,
+            stats.live_bytes,
+            stats.allocated_objects,
+            stats.allocated_bytes,
+            stats.collections,
+            stats.collected_objects,
+            stats.collected_bytes,
+            stats.gc_time.as_secs_f64() * 1000.0
+        )
+    }
+
+    fn describe_value(&self, value: Value) -> String {
+        self.describe(JValue::from(value))
+    }
+
+    /// A value as printed by the shell: strings quoted, other objects by
+    /// class and reference.
+    fn describe(&self, value: JValue) -> String {
+        match value {
+            JValue::Int(value) => value.to_string(),
+            JValue::Long(value) => format!("{}L", value),
+            JValue::Float(value) => format!("{}f", value),
+            JValue::Double(value) => format!("{}d", value),
+            JValue::Null => "null".to_string(),
+            JValue::Object(object) => self.describe_object(object),
+        }
+    }
+
+    fn describe_object(&self, object: ObjectRef) -> String {
+        if let Some(string) = self.vm.string_value(object) {
+            return format!("{:?}", string);
+        }
+        let class = self.vm.class(self.vm.class_of(object));
+        match self.vm.heap.get(object).array() {
+            Some(array) => {
+                let component = class.java_name();
+                let component = component.trim_start_matches('[');
+                format!("{}[{}]{}", array_component(component), array.len(), object)
+            }
+            None => format!("{}{}", class.java_name(), object),
+        }
+    }
+}
+
+/// The Java name of the component of an array class name without its `[`.
+fn array_component(component: &str) -> String {
+    match FieldType::parse(component) {
+        Ok(field_type) => type_name(&field_type),
+        Err(_) => component.to_string(),
+    }
+}
+
+fn signature(method: &RuntimeMethod) -> String {
+    let mut signature = String::new();
+    if method.is_static() {
+        signature.push_str("static ");
+    }
+    if method.access_flags.contains(MethodAccessFlags::NATIVE) || method.native.is_some() {
+        signature.push_str("native ");
+    }
+    write!(signature, "{}{}", method.name, method.descriptor).unwrap();
+    signature
+}
+
+/// Splits a command line on whitespace, keeping double-quoted strings
+/// together with their opening quote, so that `"a b"` stays `"a b` and
+/// `\"` and `\\` escape the characters inside of them.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut word = c.to_string();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => word.extend(chars.next()),
+                    Some(c) => word.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod repl_tests {
+    use std::path::PathBuf;
+
+    use super::{split_words, Repl};
+    use crate::packaging::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::Vm;
+
+    fn repl() -> Repl {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut class_path = ClassPath::default();
+        class_path.push(ClassPathEntry::open(root).unwrap());
+        Repl::new(Vm::builder().class_path(class_path).build().unwrap())
+    }
+
+    fn execute(repl: &mut Repl, line: &str) -> String {
+        repl.execute(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_split_words() {
+        let words = split_words(r#"call  A.b 1 "two words" "\"quoted\"""#).unwrap();
+        assert_eq!(words, ["call", "A.b", "1", "\"two words", "\"\"quoted\""]);
+        assert!(split_words(r#"call A.b "open"#).is_err());
+    }
+
+    #[test]
+    fn test_call_and_inspect() {
+        let mut repl = repl();
+
+        assert_eq!(execute(&mut repl, "call Calculator.add 2 3"), "$1 = 5\n");
+        assert_eq!(
+            execute(&mut repl, "call Calculator.fibonacci(I)J 10"),
+            "$2 = 55L\n"
+        );
+        assert_eq!(
+            execute(
+                &mut repl,
+                r#"call Concatenation.describe "bolts" $1 'x' 0.5 null"#
+            ),
+            "$3 = \"bolts: 5x at 0.5 \\u{1} null!\"\n"
+        );
+        assert_eq!(execute(&mut repl, "inspect $2"), "55L\n");
+        assert!(execute(&mut repl, "inspect Calculator").starts_with("Calculator (Initialized)\n"));
+        assert!(execute(&mut repl, "heap").starts_with("heap: "));
+        assert_eq!(repl.execute("quit"), Ok(None));
+    }
+
+    #[test]
+    fn test_methods_and_disassembly() {
+        let mut repl = repl();
+
+        assert!(execute(&mut repl, "methods Calculator").contains("static add(II)I\n"));
+        assert_eq!(
+            execute(&mut repl, "disasm Calculator add"),
+            "static add(II)I\n     0: iload_0\n     1: iload_1\n     2: iadd\n     3: ireturn\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut repl = repl();
+
+        for (line, error) in [
+            ("bogus", "Unknown command bogus, see help"),
+            ("load", "Wrong arguments for load, see help"),
+            (
+                "call Calculator.add 1",
+                "No static method add with 1 parameters in Calculator",
+            ),
+            ("call Calculator.add 1 two", "two is not a valid int"),
+            ("inspect $4", "No result $4"),
+        ] {
+            assert_eq!(repl.execute(line), Err(error.to_string()), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::vm::clock::Clock;
+use crate::vm::{Unwind, Vm, VmError};
+
+/// A reading of the host system by a native method, like the time or a
+/// variable of the environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interaction {
+    /// What was read, e.g. `getenv`.
+    pub kind: String,
+    /// What the native asked for, e.g. the name of the variable.
+    pub input: String,
+    /// What the host answered, `None` for nothing, like an unset variable.
+    pub output: Option<String>,
+}
+
+impl Interaction {
+    /// Reads an interaction written by [Interaction::write].
+    fn parse(line: &str) -> Option<Interaction> {
+        let mut fields = line.split('\t').map(unescape);
+        Some(Interaction {
+            kind: fields.next()?,
+            input: fields.next()?,
+            output: fields.next(),
+        })
+    }
+
+    /// Writes the interaction as a line of tab-separated fields, the output
+    /// left out when there is none.
+    fn write(&self, output: &mut dyn Write) -> io::Result<()> {
+        write!(output, "{}\t{}", escape(&self.kind), escape(&self.input))?;
+        if let Some(answer) = &self.output {
+            write!(output, "\t{}", escape(answer))?;
+        }
+        writeln!(output)
+    }
+}
+
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+enum Mode {
+    Record(Box<dyn Write + Send>),
+    Replay(VecDeque<Interaction>),
+}
+
+/// The readings of the host system by the natives of a run, written to a
+/// trace file while recording, and answered from it while replaying instead
+/// of reaching the host, so that a failure of the guest can be reproduced
+/// on another machine. The clock of the VM and `System.getenv` are
+/// recorded, and natives registered by embedders can take part through
+/// [Vm::host_interaction].
+///
+/// A replayed run must ask for the same readings in the same order as the
+/// recorded one, otherwise it fails with [VmError::ReplayDiverged].
+pub struct InteractionLog {
+    mode: Mode,
+    /// Why the replayed run stopped following the recording, reported when
+    /// the native asking for the reading returns.
+    divergence: Option<String>,
+}
+
+impl InteractionLog {
+    /// A log recording the interactions to the output.
+    pub fn record<W: Write + Send + 'static>(output: W) -> Self {
+        InteractionLog {
+            mode: Mode::Record(Box::new(output)),
+            divergence: None,
+        }
+    }
+
+    /// A log replaying the interactions recorded to the input.
+    pub fn replay<R: BufRead>(input: R) -> io::Result<Self> {
+        let mut interactions = VecDeque::new();
+        for (number, line) in input.lines().enumerate() {
+            match Interaction::parse(&line?) {
+                Some(interaction) => interactions.push_back(interaction),
+                None => {
+                    let message = format!("malformed interaction on line {}", number + 1);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+            }
+        }
+        Ok(InteractionLog::replay_interactions(interactions))
+    }
+
+    /// A log replaying the interactions, in order.
+    pub fn replay_interactions(interactions: impl IntoIterator<Item = Interaction>) -> Self {
+        InteractionLog {
+            mode: Mode::Replay(interactions.into_iter().collect()),
+            divergence: None,
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    /// The recorded answer to the reading when replaying, `None` when it is
+    /// to be performed on the host.
+    fn replayed(&mut self, kind: &str, input: &str) -> Option<Option<String>> {
+        let interactions = match &mut self.mode {
+            Mode::Replay(interactions) => interactions,
+            Mode::Record(_) => return None,
+        };
+        if self.divergence.is_some() {
+            return None;
+        }
+        let divergence = match interactions.pop_front() {
+            Some(next) if next.kind == kind && next.input == input => return Some(next.output),
+            Some(next) => format!(
+                "the guest read {}({}) instead of {}({})",
+                kind, input, next.kind, next.input
+            ),
+            None => format!(
+                "the guest read {}({}) after the recording ended",
+                kind, input
+            ),
+        };
+        tracing::warn!(%divergence, "replay diverged");
+        self.divergence = Some(divergence);
+        None
+    }
+
+    fn recorded(&mut self, interaction: Interaction) {
+        if let Mode::Record(output) = &mut self.mode {
+            if let Err(error) = interaction.write(output) {
+                tracing::warn!(%error, "cannot record host interaction");
+            }
+        }
+    }
+
+    /// Performs the reading unless it is replayed.
+    fn interact(
+        log: &Mutex<InteractionLog>,
+        kind: &str,
+        input: &str,
+        perform: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        if let Some(output) = log.lock().unwrap().replayed(kind, input) {
+            return output;
+        }
+        let output = perform();
+        log.lock().unwrap().recorded(Interaction {
+            kind: kind.to_string(),
+            input: input.to_string(),
+            output: output.clone(),
+        });
+        output
+    }
+
+    /// Flushes the recording, warning about the interactions a replayed run
+    /// did not read.
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        match &mut self.mode {
+            Mode::Record(output) => output.flush(),
+            Mode::Replay(interactions) => {
+                if !interactions.is_empty() && self.divergence.is_none() {
+                    tracing::warn!(
+                        left = interactions.len(),
+                        "replay ended before the recording"
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The clock of a VM recording or replaying its interactions. Replayed, the
+/// guest does not actually sleep, as the time it reads is recorded.
+pub(crate) struct InteractionClock {
+    clock: Box<dyn Clock>,
+    log: Arc<Mutex<InteractionLog>>,
+}
+
+impl InteractionClock {
+    pub(crate) fn new(clock: Box<dyn Clock>, log: Arc<Mutex<InteractionLog>>) -> Self {
+        InteractionClock { clock, log }
+    }
+
+    fn read(&self, kind: &str, read: impl Fn() -> i64) -> i64 {
+        let output = InteractionLog::interact(&self.log, kind, "", || Some(read().to_string()));
+        match output.and_then(|output| output.parse().ok()) {
+            Some(time) => time,
+            None => {
+                let mut log = self.log.lock().unwrap();
+                if log.divergence.is_none() {
+                    log.divergence = Some(format!("the recorded {} is not a time", kind));
+                }
+                read()
+            }
+        }
+    }
+}
+
+impl Clock for InteractionClock {
+    fn current_time_millis(&self) -> i64 {
+        self.read("currentTimeMillis", || self.clock.current_time_millis())
+    }
+
+    fn nano_time(&self) -> i64 {
+        self.read("nanoTime", || self.clock.nano_time())
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if !self.log.lock().unwrap().is_replaying() {
+            self.clock.sleep(duration);
+        }
+    }
+}
+
+impl Vm {
+    /// Reads the host system for a native method, like a variable of the
+    /// environment, recording the reading when the VM records its
+    /// interactions and answering it from the recording when it replays
+    /// them, without calling `perform`. The kind and input identify the
+    /// reading, which a replayed run must ask for in the recorded order.
+    pub fn host_interaction(
+        &mut self,
+        kind: &str,
+        input: &str,
+        perform: impl FnOnce(&mut Vm) -> Option<String>,
+    ) -> Option<String> {
+        match self.interactions.clone() {
+            Some(log) => InteractionLog::interact(&log, kind, input, || perform(self)),
+            None => perform(self),
+        }
+    }
+
+    /// Fails with [VmError::ReplayDiverged] once a replayed run stopped
+    /// following the recording.
+    pub(crate) fn check_replay(&self) -> Result<(), Unwind> {
+        let log = match &self.interactions {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        match log.lock().unwrap().divergence.clone() {
+            Some(divergence) => Err(VmError::ReplayDiverged(divergence).into()),
+            None => Ok(()),
+        }
+    }
+}
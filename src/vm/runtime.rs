@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::class::attributes::{Attribute, LineNumberTableAttribute};
+use crate::class::descriptor::{FieldType, MethodDescriptor};
+use crate::class::instruction::{Instruction, Switch};
+use crate::class::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::vm::loader::{LoadedClass, LoaderId};
+use crate::vm::natives::NativeFn;
+use crate::vm::policy::Permission;
+use crate::vm::symbol::Symbol;
+use crate::vm::value::{ObjectRef, Value};
+
+// =============================================================================
+// CLASSES
+// =============================================================================
+
+/// Identifies a class linked into the VM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClassId(pub(crate) u32);
+
+impl ClassId {
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Initialization state of a class (JVMS 5.5).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InitState {
+    Linked,
+    Initializing,
+    Initialized,
+    Erroneous,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassKind {
+    Instance,
+    /// An array class with the given component type.
+    Array(FieldType),
+    /// The class of a primitive type or `void`, which only exists for its
+    /// `java.lang.Class` object.
+    Primitive,
+}
+
+/// How strongly a `java.lang.ref.Reference` holds its referent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Soft,
+    Weak,
+    Phantom,
+}
+
+/// A class as represented by the running VM: its resolved hierarchy, field
+/// layout, methods and static state.
+pub struct RuntimeClass {
+    pub id: ClassId,
+    /// Internal name, e.g. `java/lang/Object` or `[I`.
+    pub name: Symbol,
+    pub defining_loader: LoaderId,
+    pub access_flags: ClassAccessFlags,
+    pub kind: ClassKind,
+    pub super_class: Option<ClassId>,
+    pub interfaces: Vec<ClassId>,
+    /// Fields declared by the class itself.
+    pub fields: Vec<Arc<RuntimeField>>,
+    /// Methods declared by the class itself.
+    pub methods: Vec<Arc<RuntimeMethod>>,
+    /// Default values of the instance field slots, including the inherited
+    /// ones, used as the template of new instances.
+    pub instance_fields: Vec<Value>,
+    pub static_values: Vec<Value>,
+    pub state: InitState,
+    /// The class file the class was defined from; `None` for built-in and
+    /// array classes.
+    pub source: Option<Arc<LoadedClass>>,
+    /// The `java.lang.Class` object representing the class, created on first
+    /// use.
+    pub mirror: Option<ObjectRef>,
+    /// The strength of the references, for subclasses of
+    /// `java.lang.ref.Reference`.
+    pub reference: Option<ReferenceKind>,
+    /// The class declaring the implementation of each virtual method, by
+    /// name and descriptor, for the classes linked from a class archive.
+    pub(crate) vtable: HashMap<(Symbol, Symbol), ClassId>,
+}
+
+impl RuntimeClass {
+    pub fn is_interface(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::INTERFACE)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.kind, ClassKind::Array(_))
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        self.kind == ClassKind::Primitive
+    }
+
+    /// The binary name as returned by `Class.getName()`, e.g. `java.lang.Object`.
+    pub fn java_name(&self) -> String {
+        self.name.replace('/', ".")
+    }
+
+    pub fn declared_method(&self, name: &str, descriptor: &str) -> Option<&Arc<RuntimeMethod>> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+    }
+
+    pub fn declared_field(&self, name: &str, descriptor: &str) -> Option<&Arc<RuntimeField>> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name && field.descriptor == descriptor)
+    }
+
+    /// The declared method, comparing the interned name and descriptor by
+    /// identity.
+    pub(crate) fn method(&self, name: &Symbol, descriptor: &Symbol) -> Option<&Arc<RuntimeMethod>> {
+        self.methods
+            .iter()
+            .find(|method| method.name == *name && method.descriptor == *descriptor)
+    }
+
+    /// The declared field, comparing the interned name and descriptor by
+    /// identity.
+    pub(crate) fn field(&self, name: &Symbol, descriptor: &Symbol) -> Option<&Arc<RuntimeField>> {
+        self.fields
+            .iter()
+            .find(|field| field.name == *name && field.descriptor == *descriptor)
+    }
+
+    /// The name of the source file, from the `SourceFile` attribute.
+    pub fn source_file(&self) -> Option<String> {
+        let source = self.source.as_ref()?;
+        source
+            .class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::SourceFile(source_file) => source
+                    .class
+                    .constant_pool
+                    .get_utf8(source_file.sourcefile_index)
+                    .ok()
+                    .map(String::from),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Debug for RuntimeClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RuntimeClass")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("defining_loader", &self.defining_loader)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+// =============================================================================
+// FIELDS
+// =============================================================================
+
+#[derive(Debug)]
+pub struct RuntimeField {
+    pub class: ClassId,
+    pub name: Symbol,
+    pub descriptor: Symbol,
+    pub field_type: FieldType,
+    pub access_flags: FieldAccessFlags,
+    /// Index into the object's fields, or into the class' static values for
+    /// static fields.
+    pub slot: usize,
+    /// Constant pool index of the `ConstantValue` attribute of static fields.
+    pub constant_value: Option<u16>,
+}
+
+impl RuntimeField {
+    pub fn is_static(&self) -> bool {
+        self.access_flags.contains(FieldAccessFlags::STATIC)
+    }
+}
+
+// =============================================================================
+// METHODS
+// =============================================================================
+
+/// An entry of a method's exception table, with the catch type dereferenced.
+#[derive(Clone, Debug)]
+pub struct ExceptionHandler {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    /// Internal name of the caught class, or `None` for `finally` handlers.
+    pub catch_type: Option<Symbol>,
+}
+
+/// The bytecode of a non-abstract, non-native method.
+#[derive(Debug)]
+pub struct MethodCode {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: Vec<u8>,
+    /// The decoded instructions executed by the interpreter.
+    pub instructions: Vec<Instruction>,
+    /// The pc of every instruction.
+    pub instruction_pcs: Vec<u32>,
+    /// The jump tables of the switches.
+    pub switches: Vec<Switch>,
+    pub exception_handlers: Vec<ExceptionHandler>,
+    pub line_numbers: Vec<LineNumberTableAttribute>,
+}
+
+impl MethodCode {
+    /// The index of the instruction at `pc`.
+    pub fn instruction_index(&self, pc: usize) -> Option<usize> {
+        let pc = u32::try_from(pc).ok()?;
+        self.instruction_pcs.binary_search(&pc).ok()
+    }
+
+    /// The source line of the instruction at `pc`, if the method has a
+    /// `LineNumberTable`.
+    pub fn line_number(&self, pc: usize) -> Option<u16> {
+        self.line_numbers
+            .iter()
+            .filter(|entry| entry.start_pc as usize <= pc)
+            .max_by_key(|entry| entry.start_pc)
+            .map(|entry| entry.line_number)
+    }
+}
+
+pub struct RuntimeMethod {
+    pub class: ClassId,
+    pub name: Symbol,
+    pub descriptor: Symbol,
+    pub parsed_descriptor: MethodDescriptor,
+    pub access_flags: MethodAccessFlags,
+    pub code: Option<MethodCode>,
+    /// The Rust implementation of native and built-in methods.
+    pub native: Option<NativeFn>,
+    /// What the policy of the VM must permit before the native runs.
+    pub permission: Option<Permission<'static>>,
+}
+
+impl RuntimeMethod {
+    pub fn is_static(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::STATIC)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::ABSTRACT)
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::PRIVATE)
+    }
+
+    /// Number of arguments popped from the operand stack on invocation,
+    /// including the receiver of instance methods.
+    pub fn argument_count(&self) -> usize {
+        self.parsed_descriptor.parameters.len() + if self.is_static() { 0 } else { 1 }
+    }
+}
+
+impl fmt::Debug for RuntimeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RuntimeMethod")
+            .field("class", &self.class)
+            .field("name", &self.name)
+            .field("descriptor", &self.descriptor)
+            .finish()
+    }
+}
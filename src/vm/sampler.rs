@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Samples the Java stacks at a fixed interval and writes how often each
+/// distinct stack was seen as collapsed stacks, the input format of
+/// `flamegraph.pl` and `inferno`.
+///
+/// A background thread requests the samples, which the interpreter takes at
+/// the next instruction boundary, where its stacks are consistent, or a
+/// sleeping thread after its next slice. Each sample records the stack of
+/// every live thread, the native methods being called showing as frames
+/// suffixed with `[native]`.
+pub struct SamplingProfiler {
+    interval: Duration,
+    opcodes: bool,
+    output: Box<dyn Write + Send>,
+    /// Number of samples by collapsed stack.
+    samples: HashMap<String, u64>,
+    requested: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    sampler: Option<JoinHandle<()>>,
+}
+
+impl SamplingProfiler {
+    /// A profiler sampling every millisecond, writing to standard error.
+    pub fn new() -> Self {
+        SamplingProfiler {
+            interval: Duration::from_millis(1),
+            opcodes: false,
+            output: Box::new(io::stderr()),
+            samples: HashMap::new(),
+            requested: Arc::new(AtomicBool::new(false)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            sampler: None,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Adds the executed instruction as the innermost frame of the samples,
+    /// showing where the interpreter itself spends its time.
+    pub fn opcodes(mut self, opcodes: bool) -> Self {
+        self.opcodes = opcodes;
+        self
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Starts the thread requesting the samples.
+    pub(crate) fn start(&mut self) {
+        let (requested, stopped) = (self.requested.clone(), self.stopped.clone());
+        let interval = self.interval;
+        self.sampler = Some(thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                requested.store(true, Ordering::Relaxed);
+            }
+        }));
+    }
+
+    fn stop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(sampler) = self.sampler.take() {
+            let _ = sampler.join();
+        }
+    }
+
+    /// Whether a sample is due, clearing the request.
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.load(Ordering::Relaxed) && self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    pub(crate) fn includes_opcodes(&self) -> bool {
+        self.opcodes
+    }
+
+    /// Records a sample of the frames, outermost first.
+    pub(crate) fn record(&mut self, frames: Vec<String>) {
+        *self.samples.entry(frames.join(";")).or_default() += 1;
+    }
+
+    /// Stops sampling and writes the collected samples.
+    pub(crate) fn write(&mut self) -> io::Result<()> {
+        self.stop();
+
+        let mut samples: Vec<(&String, &u64)> = self.samples.iter().collect();
+        samples.sort();
+        for (stack, count) in samples {
+            writeln!(self.output, "{} {}", stack, count)?;
+        }
+        self.output.flush()
+    }
+}
+
+impl Default for SamplingProfiler {
+    fn default() -> Self {
+        SamplingProfiler::new()
+    }
+}
+
+impl Drop for SamplingProfiler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
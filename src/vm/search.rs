@@ -0,0 +1,245 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+
+use crate::class::attributes::Attribute;
+use crate::class::constant_pool::Constant;
+use crate::class::instruction::{decode, Instruction};
+use crate::class::{Class, ClassLoadingError};
+
+// =============================================================================
+// PATTERNS
+// =============================================================================
+
+/// Matches internal names of classes, e.g. `java/lang/String`, or of members
+/// with their class, e.g. `java/lang/System.out`.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    /// A pattern matching whole names, where `*` stands for any run of
+    /// characters and `?` for any one. Dots of binary names like
+    /// `java.util.*` are read as the slashes of internal names, except for
+    /// the last one of a member pattern like `java.lang.System#out`.
+    pub fn glob(glob: &str) -> Result<Self, String> {
+        let glob = match glob.split_once('#') {
+            Some((class, member)) => format!("{}.{}", class.replace('.', "/"), member),
+            None => glob.replace('.', "/"),
+        };
+        let mut regex = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        Pattern::regex(&regex)
+    }
+
+    /// A pattern matching names containing a match of the regular
+    /// expression, anchored with `^` and `$` to match whole names.
+    pub fn regex(regex: &str) -> Result<Self, String> {
+        let regex = Regex::new(regex).map_err(|error| error.to_string())?;
+        Ok(Pattern { regex })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+// =============================================================================
+// QUERIES
+// =============================================================================
+
+/// The classes of the set extending or implementing a class matching the
+/// pattern, directly or not, with the supertypes they match, sorted by
+/// class.
+pub fn find_subtypes<'a>(
+    classes: impl IntoIterator<Item = &'a Class>,
+    pattern: &Pattern,
+) -> Result<BTreeMap<String, Vec<String>>, ClassLoadingError> {
+    let mut parents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for class in classes {
+        let supertypes = parents.entry(class.name()?).or_default();
+        supertypes.extend(class.super_class_name()?);
+        for interface in &class.interfaces {
+            supertypes.push(
+                class
+                    .constant_pool
+                    .get_class_name(interface.interface_index)?,
+            );
+        }
+    }
+
+    let mut subtypes = BTreeMap::new();
+    for &class in parents.keys() {
+        let mut supertypes = BTreeSet::new();
+        let mut pending = parents[class].clone();
+        while let Some(supertype) = pending.pop() {
+            if supertypes.insert(supertype) {
+                if let Some(grandparents) = parents.get(supertype) {
+                    pending.extend(grandparents);
+                }
+            }
+        }
+        let matching: Vec<String> = supertypes
+            .into_iter()
+            .filter(|supertype| pattern.matches(supertype))
+            .map(String::from)
+            .collect();
+        if !matching.is_empty() {
+            subtypes.insert(class.to_string(), matching);
+        }
+    }
+    Ok(subtypes)
+}
+
+/// An instruction referring to a field or a method.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Reference {
+    pub class: String,
+    /// Name and descriptor of the method holding the instruction.
+    pub method: String,
+    pub pc: u32,
+    /// The member referred to, e.g. `java/lang/System.out:Ljava/io/PrintStream;`.
+    pub member: String,
+}
+
+/// The instructions of the classes accessing a field, or calling a method,
+/// whose class and name, like `java/lang/System.out`, match the pattern.
+/// Only the code of classes whose constant pool refers to a matching member
+/// is scanned.
+pub fn find_references<'a>(
+    classes: impl IntoIterator<Item = &'a Class>,
+    pattern: &Pattern,
+) -> Result<Vec<Reference>, ClassLoadingError> {
+    let mut references = Vec::new();
+    for class in classes {
+        let pool = &class.constant_pool;
+        let matching = |index: u16| -> Result<Option<String>, ClassLoadingError> {
+            let (owner, name, descriptor) = pool.get_member(index)?;
+            Ok(pattern
+                .matches(&format!("{}.{}", owner, name))
+                .then(|| format!("{}.{}:{}", owner, name, descriptor)))
+        };
+        let mut refers = false;
+        for (index, constant) in pool.iter() {
+            if let Constant::Field(_) | Constant::Method(_) | Constant::InterfaceMethod(_) =
+                constant
+            {
+                refers |= matching(index as u16)?.is_some();
+            }
+        }
+        if !refers {
+            continue;
+        }
+
+        let class_name = class.name()?;
+        for method in &class.methods {
+            let code = method
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::Code(code) => Some(code),
+                    _ => None,
+                });
+            let code = match code {
+                Some(code) => decode(&code.code),
+                None => continue,
+            };
+            for (&instruction, &pc) in code.instructions.iter().zip(&code.pcs) {
+                let index = match instruction {
+                    Instruction::GetStatic(index)
+                    | Instruction::PutStatic(index)
+                    | Instruction::GetField(index)
+                    | Instruction::PutField(index)
+                    | Instruction::InvokeStatic(index)
+                    | Instruction::InvokeSpecial(index)
+                    | Instruction::InvokeVirtual(index, _)
+                    | Instruction::InvokeInterface(index, _) => index,
+                    _ => continue,
+                };
+                if let Some(member) = matching(index)? {
+                    references.push(Reference {
+                        class: class_name.to_string(),
+                        method: format!(
+                            "{}{}",
+                            pool.get_utf8(method.name_index)?,
+                            pool.get_utf8(method.descriptor_index)?
+                        ),
+                        pc,
+                        member,
+                    });
+                }
+            }
+        }
+    }
+    references.sort();
+    Ok(references)
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod search_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{find_references, find_subtypes, Pattern};
+    use crate::class::Class;
+
+    #[test]
+    fn test_search() {
+        let glob = Pattern::glob("java.util.*List").unwrap();
+        assert!(glob.matches("java/util/ArrayList"));
+        assert!(!glob.matches("java/util/ArrayList$Itr"));
+        assert!(Pattern::glob("java.lang.System#out")
+            .unwrap()
+            .matches("java/lang/System.out"));
+        assert!(Pattern::regex("Array")
+            .unwrap()
+            .matches("java/util/ArrayList"));
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden");
+        let classes: Vec<Class> = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "class")
+            })
+            .map(|path| Class::parse_bytes(&fs::read(path).unwrap()).unwrap())
+            .collect();
+
+        // Square and Circle implement Shape through Named
+        let subtypes = find_subtypes(&classes, &Pattern::glob("Objects$Shape").unwrap()).unwrap();
+        let names: Vec<&String> = subtypes.keys().collect();
+        assert_eq!(
+            names,
+            vec!["Objects$Circle", "Objects$Named", "Objects$Square"]
+        );
+
+        let references =
+            find_references(&classes, &Pattern::glob("Objects#counter").unwrap()).unwrap();
+        let sites: Vec<(&str, u32)> = references
+            .iter()
+            .map(|reference| (reference.method.as_str(), reference.pc))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                ("<clinit>()V", 2),
+                ("main([Ljava/lang/String;)V", 89),
+                ("main([Ljava/lang/String;)V", 94),
+                ("main([Ljava/lang/String;)V", 100),
+            ]
+        );
+    }
+}
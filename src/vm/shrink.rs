@@ -0,0 +1,330 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::class::{Class, ClassLoadingError};
+use crate::packaging::services::{parse_provider_configuration, SERVICES_DIRECTORY};
+use crate::vm::callgraph::{CallGraph, MethodRef};
+use crate::vm::deadcode::{find_dead_code, KeepRules};
+use crate::vm::strip::{strip_class, Stripped};
+
+// =============================================================================
+// SHRINKING
+// =============================================================================
+
+#[derive(Clone, Debug, Default)]
+pub struct ShrinkOptions {
+    /// The entry points: classes, whose `main` method is kept, or methods
+    /// like `com.example.Main#run`, every method of the name being kept.
+    /// The `Main-Class` of the manifest if empty.
+    pub entries: Vec<String>,
+    /// The classes and methods used by reflection, besides the providers of
+    /// the `META-INF/services` files and the `reflect-config.json` files
+    /// of the jar.
+    pub keep: KeepRules,
+    pub strip: Stripped,
+}
+
+/// What shrinking removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShrinkStats {
+    pub classes: usize,
+    pub removed_classes: usize,
+    pub removed_methods: usize,
+    pub stripped_attributes: usize,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+}
+
+impl fmt::Display for ShrinkStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} classes and {} methods removed, {} attributes stripped: \
+             {} bytes down to {}",
+            self.removed_classes,
+            self.classes,
+            self.removed_methods,
+            self.stripped_attributes,
+            self.input_bytes,
+            self.output_bytes
+        )
+    }
+}
+
+/// The jar without the classes and methods the entry points never use,
+/// found by [find_dead_code], and without the stripped attributes.
+///
+/// The other entries are copied as they are, except for the signatures of
+/// a signed jar, which the changed classes invalidate. The classes of the
+/// `META-INF/versions` of a multi-release jar are copied too, and so are
+/// the constant pools of the shrunk classes.
+pub fn shrink_jar<R: Read + Seek, W: Write + Seek>(
+    mut input: R,
+    output: W,
+    options: &ShrinkOptions,
+) -> io::Result<ShrinkStats> {
+    let mut stats = ShrinkStats {
+        input_bytes: input.seek(SeekFrom::End(0))?,
+        ..ShrinkStats::default()
+    };
+    let mut archive = ZipArchive::new(input)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let mut bytes = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut bytes)?;
+        entries.push((file.name().to_string(), file.is_dir(), bytes));
+    }
+
+    let mut classes = Vec::new();
+    let mut keep = options.keep.clone();
+    let mut main_class = None;
+    for (name, _, bytes) in &entries {
+        if is_shrunk_class(name) {
+            let class = Class::parse_bytes(bytes)
+                .map_err(|error| invalid_data(format!("Cannot parse {}: {}", name, error)))?;
+            classes.push(class);
+        } else if name.starts_with(SERVICES_DIRECTORY) {
+            for provider in parse_provider_configuration(bytes) {
+                keep.classes.insert(provider.replace('.', "/"));
+            }
+        } else if name.ends_with("reflect-config.json") {
+            keep.parse_reflection_config(&String::from_utf8_lossy(bytes));
+        } else if name == "META-INF/MANIFEST.MF" {
+            main_class = manifest_main_class(&String::from_utf8_lossy(bytes));
+        }
+    }
+    stats.classes = classes.len();
+
+    let graph = CallGraph::build(&classes).map_err(class_error)?;
+    let mut requested = options.entries.clone();
+    if requested.is_empty() {
+        requested.extend(main_class);
+    }
+    if requested.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No entry point given, and the manifest has no Main-Class",
+        ));
+    }
+    let entry_points = entry_points(&graph, &requested)?;
+    let dead = find_dead_code(&classes, &graph, &entry_points, &keep).map_err(class_error)?;
+    let dead_classes: BTreeSet<&str> = dead.classes.iter().map(String::as_str).collect();
+    let dead_methods: BTreeSet<&MethodRef> = dead.methods.iter().collect();
+
+    let mut shrunk = Vec::new();
+    for mut class in classes {
+        let name = class.name().map_err(class_error)?.to_string();
+        if dead_classes.contains(name.as_str()) {
+            stats.removed_classes += 1;
+            continue;
+        }
+        let pool = &class.constant_pool;
+        let mut methods = Vec::with_capacity(class.methods.len());
+        for method in class.methods.drain(..) {
+            let method_ref = MethodRef::new(
+                &name,
+                pool.get_utf8(method.name_index).map_err(class_error)?,
+                pool.get_utf8(method.descriptor_index)
+                    .map_err(class_error)?,
+            );
+            if dead_methods.contains(&method_ref) {
+                stats.removed_methods += 1;
+            } else {
+                methods.push(method);
+            }
+        }
+        class.methods = methods;
+        stats.stripped_attributes += strip_class(&mut class, options.strip).map_err(class_error)?;
+        shrunk.push((format!("{}.class", name), class.to_bytes()?));
+    }
+
+    let mut writer = ZipWriter::new(output);
+    let file_options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, is_dir, bytes) in entries {
+        if is_dir {
+            writer.add_directory(name, file_options)?;
+            continue;
+        }
+        if is_signature(&name) {
+            continue;
+        }
+        let bytes = if is_shrunk_class(&name) {
+            match shrunk.iter().position(|(shrunk, _)| *shrunk == name) {
+                Some(position) => shrunk.swap_remove(position).1,
+                None => continue,
+            }
+        } else {
+            bytes
+        };
+        writer.start_file(name, file_options)?;
+        writer.write_all(&bytes)?;
+    }
+    stats.output_bytes = writer.finish()?.seek(SeekFrom::End(0))?;
+    Ok(stats)
+}
+
+/// Whether the entry is a class taking part in shrinking, unlike the
+/// module descriptor and the classes of other Java versions.
+fn is_shrunk_class(name: &str) -> bool {
+    name.ends_with(".class")
+        && !name.ends_with("module-info.class")
+        && !name.starts_with("META-INF/")
+}
+
+/// Whether the entry signs the jar, like `META-INF/SIGNER.SF`.
+fn is_signature(name: &str) -> bool {
+    match name.strip_prefix("META-INF/") {
+        Some(file) if !file.contains('/') => [".SF", ".RSA", ".DSA", ".EC"]
+            .iter()
+            .any(|extension| file.to_ascii_uppercase().ends_with(extension)),
+        _ => false,
+    }
+}
+
+fn manifest_main_class(manifest: &str) -> Option<String> {
+    manifest
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "Main-Class").then(|| value.trim().to_string())
+        })
+}
+
+/// The methods of the entry points, failing for one naming no method.
+fn entry_points(graph: &CallGraph, entries: &[String]) -> io::Result<Vec<MethodRef>> {
+    let mut methods = Vec::new();
+    for entry in entries {
+        let entry = entry.replace('.', "/");
+        let found: Vec<&MethodRef> = match entry.split_once('#') {
+            Some((class, name)) => graph
+                .methods
+                .iter()
+                .filter(|method| method.class == class && method.name == name)
+                .collect(),
+            None => {
+                let main = MethodRef::new(&entry, "main", "([Ljava/lang/String;)V");
+                graph.methods.get(&main).into_iter().collect()
+            }
+        };
+        if found.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No entry point {}", entry),
+            ));
+        }
+        methods.extend(found.into_iter().cloned());
+    }
+    Ok(methods)
+}
+
+fn class_error(error: ClassLoadingError) -> io::Error {
+    invalid_data(error.to_string())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod shrink_tests {
+    use std::fs;
+    use std::io::{Cursor, Read, Write};
+    use std::path::PathBuf;
+
+    use zip::write::FileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    use super::{shrink_jar, ShrinkOptions};
+    use crate::class::Class;
+    use crate::vm::strip::Stripped;
+
+    #[test]
+    fn test_shrink_jar() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("META-INF/MANIFEST.MF", FileOptions::default())
+            .unwrap();
+        writer
+            .write_all(b"Manifest-Version: 1.0\nMain-Class: Uncaught\n\n")
+            .unwrap();
+        writer
+            .start_file("META-INF/SIGNER.SF", FileOptions::default())
+            .unwrap();
+        for name in [
+            "Uncaught",
+            "Uncaught$Recorder",
+            "Uncaught$Failing",
+            "Uncaught$Thrower",
+            "Calculator",
+        ] {
+            let file = format!("{}.class", name);
+            writer.start_file(&file, FileOptions::default()).unwrap();
+            writer
+                .write_all(&fs::read(root.join(&file)).unwrap())
+                .unwrap();
+        }
+        let jar = writer.finish().unwrap().into_inner();
+
+        let options = ShrinkOptions {
+            strip: Stripped::all(),
+            ..ShrinkOptions::default()
+        };
+        let mut output = Cursor::new(Vec::new());
+        let stats = shrink_jar(Cursor::new(&jar), &mut output, &options).unwrap();
+        assert_eq!(stats.classes, 5);
+        assert_eq!(stats.removed_classes, 2);
+        assert!(stats.stripped_attributes > 0);
+        assert!(stats.output_bytes < stats.input_bytes);
+
+        let mut archive = ZipArchive::new(Cursor::new(output.into_inner())).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert!(names.contains(&"META-INF/MANIFEST.MF"));
+        assert!(names.contains(&"Uncaught$Thrower.class"));
+        assert!(!names.contains(&"META-INF/SIGNER.SF"));
+        assert!(!names.contains(&"Uncaught$Failing.class"));
+        assert!(!names.contains(&"Calculator.class"));
+
+        let mut bytes = Vec::new();
+        let mut file = archive.by_name("Uncaught.class").unwrap();
+        file.read_to_end(&mut bytes).unwrap();
+        let class = Class::parse_bytes(&bytes).unwrap();
+        let pool = &class.constant_pool;
+        let methods: Vec<&str> = class
+            .methods
+            .iter()
+            .map(|method| pool.get_utf8(method.name_index).unwrap())
+            .collect();
+        assert!(methods.contains(&"main"));
+        assert!(!methods.contains(&"handlers"));
+        assert!(!methods.contains(&"run"));
+        // The InnerClasses are kept
+        assert!(class
+            .attributes
+            .iter()
+            .all(|attribute| attribute.stored_name(pool).unwrap() == "InnerClasses"));
+    }
+
+    #[test]
+    fn test_shrink_without_entry_point() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("Empty.txt", FileOptions::default())
+            .unwrap();
+        let jar = writer.finish().unwrap().into_inner();
+
+        let error = shrink_jar(
+            Cursor::new(jar),
+            Cursor::new(Vec::new()),
+            &ShrinkOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
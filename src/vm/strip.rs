@@ -0,0 +1,140 @@
+use crate::class::attributes::Attribute;
+use crate::class::constant_pool::ConstantPool;
+use crate::class::{Class, ClassLoadingError};
+
+bitflags::bitflags! {
+    /// Attributes the code runs without, which stripping removes.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Stripped: u8 {
+        const LINE_NUMBERS = 0x01;
+        /// The `LocalVariableTable` and `LocalVariableTypeTable`.
+        const LOCAL_VARIABLES = 0x02;
+        const SOURCE_FILE = 0x04;
+        const SOURCE_DEBUG_EXTENSION = 0x08;
+        /// The annotations only retained in the class files, of the classes,
+        /// members, parameters and types.
+        const INVISIBLE_ANNOTATIONS = 0x10;
+        /// The annotations reflection reads, of the classes, members,
+        /// parameters and types.
+        const VISIBLE_ANNOTATIONS = 0x20;
+        /// The attributes debuggers and stack traces read.
+        const DEBUG = Self::LINE_NUMBERS.bits()
+            | Self::LOCAL_VARIABLES.bits()
+            | Self::SOURCE_FILE.bits()
+            | Self::SOURCE_DEBUG_EXTENSION.bits();
+    }
+}
+
+impl Stripped {
+    fn strips(self, attribute: &Attribute, pool: &ConstantPool) -> Result<bool, ClassLoadingError> {
+        let flag = match attribute.stored_name(pool)? {
+            "LineNumberTable" => Stripped::LINE_NUMBERS,
+            "LocalVariableTable" | "LocalVariableTypeTable" => Stripped::LOCAL_VARIABLES,
+            "SourceFile" => Stripped::SOURCE_FILE,
+            "SourceDebugExtension" => Stripped::SOURCE_DEBUG_EXTENSION,
+            "RuntimeInvisibleAnnotations"
+            | "RuntimeInvisibleParameterAnnotations"
+            | "RuntimeInvisibleTypeAnnotations" => Stripped::INVISIBLE_ANNOTATIONS,
+            "RuntimeVisibleAnnotations"
+            | "RuntimeVisibleParameterAnnotations"
+            | "RuntimeVisibleTypeAnnotations" => Stripped::VISIBLE_ANNOTATIONS,
+            _ => return Ok(false),
+        };
+        Ok(self.contains(flag))
+    }
+
+    /// Removes the attributes from the list and the code attributes in it,
+    /// returning how many.
+    fn strip(
+        self,
+        attributes: &mut Vec<Attribute>,
+        pool: &ConstantPool,
+    ) -> Result<usize, ClassLoadingError> {
+        let count = attributes.len();
+        let mut kept = Vec::with_capacity(count);
+        let mut removed = 0;
+        for mut attribute in attributes.drain(..) {
+            if self.strips(&attribute, pool)? {
+                continue;
+            }
+            if let Attribute::Code(code) = &mut attribute {
+                removed += self.strip(&mut code.attributes, pool)?;
+            }
+            kept.push(attribute);
+        }
+        removed += count - kept.len();
+        *attributes = kept;
+        Ok(removed)
+    }
+}
+
+/// Removes the stripped attributes from the class, its fields, its methods
+/// and their code, returning how many were removed.
+///
+/// The constant pool is left as it is, so the names and values only the
+/// removed attributes used stay in it.
+pub fn strip_class(class: &mut Class, stripped: Stripped) -> Result<usize, ClassLoadingError> {
+    let pool = &class.constant_pool;
+    let mut removed = stripped.strip(&mut class.attributes, pool)?;
+    for field in &mut class.fields {
+        removed += stripped.strip(&mut field.attributes, pool)?;
+    }
+    for method in &mut class.methods {
+        removed += stripped.strip(&mut method.attributes, pool)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod strip_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{strip_class, Stripped};
+    use crate::class::attributes::Attribute;
+    use crate::class::Class;
+
+    fn attribute_names(class: &Class) -> Vec<String> {
+        let pool = &class.constant_pool;
+        let mut names = Vec::new();
+        let mut add = |attributes: &[Attribute]| {
+            for attribute in attributes {
+                names.push(attribute.stored_name(pool).unwrap().to_string());
+                if let Attribute::Code(code) = attribute {
+                    for attribute in &code.attributes {
+                        names.push(attribute.stored_name(pool).unwrap().to_string());
+                    }
+                }
+            }
+        };
+        add(&class.attributes);
+        for method in &class.methods {
+            add(&method.attributes);
+        }
+        names
+    }
+
+    #[test]
+    fn test_strip_class() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/embedding");
+        let bytes = fs::read(root.join("Calculator.class")).unwrap();
+        let mut class = Class::parse_bytes(&bytes).unwrap();
+        assert!(attribute_names(&class).contains(&"SourceFile".to_string()));
+
+        let removed = strip_class(&mut class, Stripped::SOURCE_FILE).unwrap();
+        assert_eq!(removed, 1);
+        let names = attribute_names(&class);
+        assert!(!names.contains(&"SourceFile".to_string()));
+        assert!(names.contains(&"LineNumberTable".to_string()));
+
+        let removed = strip_class(&mut class, Stripped::DEBUG).unwrap();
+        assert!(removed > 0);
+        assert!(attribute_names(&class)
+            .iter()
+            .all(|name| name == "Code" || name == "StackMapTable"));
+
+        let stripped = Class::parse_bytes(&class.to_bytes().unwrap()).unwrap();
+        assert_eq!(attribute_names(&stripped), attribute_names(&class));
+        assert!(class.to_bytes().unwrap().len() < bytes.len());
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+// =============================================================================
+// SYMBOLS
+// =============================================================================
+
+/// A string interned in the VM's [SymbolTable], such as a class name, a
+/// member name or a descriptor. Symbols of the same table are stored once and
+/// compared by identity rather than by content.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const u8 as usize).hash(state)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+// =============================================================================
+// SYMBOL TABLE
+// =============================================================================
+
+/// The VM-wide table of [Symbol]s, holding each of the names and descriptors
+/// repeated across the constant pools of the loaded classes once.
+#[derive(Default)]
+pub struct SymbolTable {
+    symbols: HashSet<Arc<str>>,
+}
+
+impl SymbolTable {
+    /// The symbol of the string, added to the table if new.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return Symbol(symbol.clone());
+        }
+        let symbol: Arc<str> = Arc::from(string);
+        self.symbols.insert(symbol.clone());
+        Symbol(symbol)
+    }
+
+    /// The symbol of the string, if it was interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).cloned().map(Symbol)
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+// =============================================================================
+// SYMBOL TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod symbol_tests {
+    use super::SymbolTable;
+
+    #[test]
+    fn test_symbols_are_interned_once() {
+        let mut symbols = SymbolTable::default();
+        let object = symbols.intern("java/lang/Object");
+        assert_eq!(symbols.intern(&String::from("java/lang/Object")), object);
+        assert_eq!(symbols.get("java/lang/Object"), Some(object.clone()));
+        assert_eq!(symbols.get("java/lang/String"), None);
+        assert_ne!(symbols.intern("java/lang/String"), object);
+        assert_eq!(symbols.len(), 2);
+
+        // Symbols of another table are other symbols, of the same content
+        let other = SymbolTable::default().intern("java/lang/Object");
+        assert_ne!(other, object);
+        assert!(other == *object.as_str());
+    }
+}
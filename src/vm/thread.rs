@@ -0,0 +1,477 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::vm::heap::{NativeData, StackTraceElement};
+use crate::vm::natives::lang;
+use crate::vm::runtime::{ClassId, RuntimeClass, RuntimeMethod};
+use crate::vm::value::{JValue, ObjectRef, Value};
+use crate::vm::{Unwind, Vm, VmError};
+
+/// The activation of a bytecode method.
+#[derive(Debug)]
+pub struct Frame {
+    pub class: ClassId,
+    pub method: Arc<RuntimeMethod>,
+    /// Index of the instruction being executed in the decoded instructions of
+    /// the method, see [Frame::pc].
+    pub ip: usize,
+    pub locals: Vec<Value>,
+    pub stack: Vec<Value>,
+    /// Monitors held by the frame, in the order they were entered.
+    pub monitors: Vec<ObjectRef>,
+}
+
+impl Frame {
+    /// Creates the frame, spreading the arguments over the local slots with
+    /// `long` and `double` values taking two of them.
+    pub fn new(method: Arc<RuntimeMethod>, arguments: &[Value]) -> Frame {
+        let code = method
+            .code
+            .as_ref()
+            .expect("Frames can only be created for methods with code");
+
+        let mut locals = Vec::with_capacity(code.max_locals as usize);
+        for argument in arguments {
+            locals.push(*argument);
+            if argument.is_wide() {
+                locals.push(Value::Top);
+            }
+        }
+        if locals.len() < code.max_locals as usize {
+            locals.resize(code.max_locals as usize, Value::Top);
+        }
+
+        Frame {
+            class: method.class,
+            stack: Vec::with_capacity(code.max_stack as usize),
+            method,
+            ip: 0,
+            locals,
+            monitors: Vec::new(),
+        }
+    }
+
+    /// The estimated size of the frame of the method on the stack, in bytes.
+    pub fn size(method: &RuntimeMethod) -> usize {
+        let slots = method
+            .code
+            .as_ref()
+            .map_or(0, |code| code.max_locals as usize + code.max_stack as usize);
+        mem::size_of::<Frame>() + slots * mem::size_of::<Value>()
+    }
+
+    /// Offset of the instruction being executed.
+    pub fn pc(&self) -> usize {
+        self.method
+            .code
+            .as_ref()
+            .and_then(|code| code.instruction_pcs.get(self.ip))
+            .map_or(0, |&pc| pc as usize)
+    }
+}
+
+/// A Java thread of execution: the stack of its bytecode frames, the
+/// innermost being the last one.
+#[derive(Debug, Default)]
+pub struct JavaThread {
+    pub frames: Vec<Frame>,
+    /// The estimated size of the frames in bytes, bounded by the stack size
+    /// of the VM.
+    pub(crate) size: usize,
+    /// The native methods being called, innermost last, each with the number
+    /// of frames under it.
+    pub(crate) natives: Vec<(usize, Arc<RuntimeMethod>)>,
+}
+
+/// Identifies a Java thread, the value of its `Thread.getId`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ThreadId(pub i64);
+
+impl ThreadId {
+    /// The thread running the code the embedder invokes.
+    pub const MAIN: ThreadId = ThreadId(1);
+}
+
+// =============================================================================
+// STACK WALKING
+// =============================================================================
+
+/// A frame of a Java stack, see [Vm::walk_stack].
+pub struct StackFrame<'a> {
+    vm: &'a Vm,
+    frame: &'a Frame,
+}
+
+impl<'a> StackFrame<'a> {
+    /// The class declaring the method.
+    pub fn class(&self) -> &'a RuntimeClass {
+        self.vm.class(self.frame.class)
+    }
+
+    pub fn method(&self) -> &'a RuntimeMethod {
+        &self.frame.method
+    }
+
+    /// Offset of the instruction being executed in the code of the method.
+    pub fn bci(&self) -> usize {
+        self.frame.pc()
+    }
+
+    /// The source line of the instruction being executed, if the class was
+    /// compiled with line numbers.
+    pub fn line_number(&self) -> Option<u16> {
+        let code = self.frame.method.code.as_ref()?;
+        code.line_number(self.frame.pc())
+    }
+
+    /// The values of the local variables by slot, `None` for the slots not
+    /// holding a value, like the second half of a `long` or `double`. The
+    /// objects are only kept alive by the frame referencing them.
+    pub fn locals(&self) -> Vec<Option<JValue>> {
+        self.frame
+            .locals
+            .iter()
+            .map(|local| match local {
+                Value::Top | Value::ReturnAddress(_) => None,
+                value => Some(JValue::from(*value)),
+            })
+            .collect()
+    }
+
+    /// The monitors held by the frame, in the order they were entered.
+    pub fn monitors(&self) -> &'a [ObjectRef] {
+        &self.frame.monitors
+    }
+
+    pub fn stack_trace_element(&self) -> StackTraceElement {
+        self.vm.stack_trace_element(self.frame)
+    }
+}
+
+impl Vm {
+    /// The threads with a stack, in the order they started running, the
+    /// current one being the last. The other ones wait for the threads run
+    /// after them to terminate.
+    pub fn threads(&self) -> Vec<ThreadId> {
+        let mut threads = vec![ThreadId::MAIN];
+        threads.extend(
+            self.running_threads
+                .iter()
+                .map(|&thread| self.thread_id(thread)),
+        );
+        threads
+    }
+
+    /// The thread running guest code, or the one the embedder invokes it on.
+    pub fn current_thread_id(&self) -> ThreadId {
+        match self.running_threads.last() {
+            Some(&thread) => self.thread_id(thread),
+            None => ThreadId::MAIN,
+        }
+    }
+
+    /// Walks the stack of the thread from the innermost frame outwards, or
+    /// returns `None` if the thread has no stack. The frames are up to date
+    /// whenever the embedder is called back: from a native method, a
+    /// listener or a safepoint.
+    pub fn walk_stack(
+        &self,
+        thread: ThreadId,
+    ) -> Option<impl DoubleEndedIterator<Item = StackFrame<'_>>> {
+        let stack = self.java_thread(thread)?;
+        Some(
+            stack
+                .frames
+                .iter()
+                .rev()
+                .map(move |frame| StackFrame { vm: self, frame }),
+        )
+    }
+
+    /// The stack of the thread, if it has one.
+    pub(crate) fn java_thread(&self, thread: ThreadId) -> Option<&JavaThread> {
+        let index = self.threads().iter().position(|&id| id == thread)?;
+        match self.suspended_stacks.get(index) {
+            Some(stack) => Some(stack),
+            None => Some(&self.thread),
+        }
+    }
+
+    pub(crate) fn thread_id(&self, thread: ObjectRef) -> ThreadId {
+        match self.field(thread, "tid") {
+            Some(Value::Long(id)) if id != 0 => ThreadId(id),
+            _ => ThreadId::MAIN,
+        }
+    }
+
+    /// The name of the thread, if it has a `Thread` object.
+    fn thread_name(&self, thread: ThreadId) -> Option<String> {
+        let object = match thread {
+            ThreadId::MAIN => self.main_thread,
+            _ => self
+                .running_threads
+                .iter()
+                .copied()
+                .find(|&object| self.thread_id(object) == thread),
+        }?;
+        match self.field(object, "name") {
+            Some(Value::Reference(Some(name))) => self.string_value(name),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// THREAD DUMP
+// =============================================================================
+
+impl Vm {
+    /// The location of the instruction the frame is executing.
+    pub(crate) fn stack_trace_element(&self, frame: &Frame) -> StackTraceElement {
+        let class = self.class(frame.class);
+        StackTraceElement {
+            class_name: class.name.to_string(),
+            method_name: frame.method.name.to_string(),
+            file_name: class.source_file(),
+            line_number: frame
+                .method
+                .code
+                .as_ref()
+                .and_then(|code| code.line_number(frame.pc())),
+        }
+    }
+
+    /// Describes the stacks of the Java threads and the monitors they hold,
+    /// innermost frame first, in the format of `jstack`. Only the current
+    /// thread is runnable, the other ones wait for it to terminate.
+    pub fn thread_dump(&self) -> String {
+        let mut dump = format!("Full thread dump bvm {}:\n\n", env!("CARGO_PKG_VERSION"));
+        let current = self.current_thread_id();
+        for thread in self.threads().into_iter().rev() {
+            let name = self.thread_name(thread);
+            let _ = writeln!(
+                dump,
+                "\"{}\" #{} prio=5",
+                name.as_deref().unwrap_or("main"),
+                thread.0
+            );
+            let state = if thread == current {
+                "RUNNABLE"
+            } else {
+                "WAITING"
+            };
+            let _ = writeln!(dump, "   java.lang.Thread.State: {}", state);
+            for frame in self.walk_stack(thread).into_iter().flatten() {
+                self.dump_frame(&mut dump, &frame);
+            }
+            dump.push('\n');
+        }
+        dump
+    }
+
+    fn dump_frame(&self, dump: &mut String, frame: &StackFrame) {
+        let _ = writeln!(dump, "\tat {}", frame.stack_trace_element());
+        for monitor in frame.monitors().iter().rev() {
+            let mut class = self.class(self.class_of(*monitor)).java_name();
+            if let NativeData::Class(mirrored) = self.heap.get(*monitor).native {
+                class = format!("{} for {}", class, self.class(mirrored).java_name());
+            }
+            let _ = writeln!(
+                dump,
+                "\t- locked <0x{:016x}> (a {})",
+                monitor.index(),
+                class
+            );
+        }
+    }
+}
+
+// =============================================================================
+// SCHEDULING
+// =============================================================================
+
+/// The longest the guest thread sleeps before checking its limits again.
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/// The life cycle of a `Thread`, in its `status` field. Guest code runs on
+/// a single host thread, so a started thread waits until the current one
+/// blocks, joins it or terminates, and then runs to completion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ThreadStatus {
+    New = 0,
+    Started = 1,
+    Running = 2,
+    Terminated = 3,
+}
+
+impl ThreadStatus {
+    pub(crate) fn of(value: Option<Value>) -> ThreadStatus {
+        match value {
+            Some(Value::Int(1)) => ThreadStatus::Started,
+            Some(Value::Int(2)) => ThreadStatus::Running,
+            Some(Value::Int(3)) => ThreadStatus::Terminated,
+            _ => ThreadStatus::New,
+        }
+    }
+}
+
+impl Vm {
+    /// Blocks the guest thread for the timeout on the VM's clock, in slices
+    /// so that exceeding a limit aborts it even while sleeping.
+    pub(crate) fn sleep(&mut self, timeout: Duration) -> Result<(), Unwind> {
+        let mut remaining = timeout;
+        while !remaining.is_zero() {
+            let slice = remaining.min(SLEEP_SLICE);
+            self.clock.sleep(slice);
+            remaining -= slice;
+            self.check_limits()?;
+            self.take_requested_sample(None);
+        }
+        Ok(())
+    }
+
+    /// Runs the started threads, then lets the host run other work.
+    pub(crate) fn yield_now(&mut self) -> Result<(), Unwind> {
+        self.run_started_threads()?;
+        std::thread::yield_now();
+        self.check_limits()
+    }
+
+    pub(crate) fn thread_status(&self, thread: ObjectRef) -> ThreadStatus {
+        ThreadStatus::of(self.field(thread, "status"))
+    }
+
+    fn set_thread_status(&mut self, thread: ObjectRef, status: ThreadStatus) {
+        self.set_field(thread, "status", Value::Int(status as i32));
+    }
+
+    /// Queues the new thread to run once the current one lets it.
+    pub(crate) fn start_thread(&mut self, thread: ObjectRef) {
+        self.set_thread_status(thread, ThreadStatus::Started);
+        self.started_threads.push_back(thread);
+    }
+
+    /// Runs the started thread to completion as the current thread, on a
+    /// stack of its own. Its uncaught exception goes to its handler, only an
+    /// exit or a VM error ending the run of the other threads.
+    pub(crate) fn run_thread(&mut self, thread: ObjectRef) -> Result<(), Unwind> {
+        self.started_threads.retain(|started| *started != thread);
+        self.set_thread_status(thread, ThreadStatus::Running);
+        self.running_threads.push(thread);
+        let stack = mem::take(&mut self.thread);
+        self.suspended_stacks.push(stack);
+        let result = self.invoke_virtual(thread, "run", "()V", &[]);
+        if let Some(stack) = self.suspended_stacks.pop() {
+            self.thread = stack;
+        }
+        self.running_threads.pop();
+        self.set_thread_status(thread, ThreadStatus::Terminated);
+        // Like the JDK's `Thread.exit()`, letting go of its thread-locals
+        if let NativeData::ThreadLocals(_) = self.heap.get(thread).native {
+            self.heap.get_mut(thread).native = NativeData::None;
+        }
+
+        match result {
+            Err(Unwind::Throw(exception)) => self.uncaught_exception(thread, exception),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Hands the exception the main thread let escape to its uncaught
+    /// exception handler, as the `java` launcher does once `main` returns:
+    /// the one of the thread, or the default one, or else the stack trace is
+    /// printed after `Exception in thread "main"`. The launcher then exits
+    /// with status 1 once the other threads terminated.
+    pub fn dispatch_uncaught_exception(&mut self, exception: ObjectRef) -> Result<(), VmError> {
+        let result = match lang::current_thread(self) {
+            Ok(thread) => self.uncaught_exception(thread, exception),
+            Err(unwind) => Err(unwind),
+        };
+        self.complete(result)
+    }
+
+    /// Dispatches the exception the thread terminated with like the JDK's
+    /// `Thread.dispatchUncaughtException`. An exception the handler throws
+    /// is reported like HotSpot does and otherwise ignored.
+    pub(crate) fn uncaught_exception(
+        &mut self,
+        thread: ObjectRef,
+        exception: ObjectRef,
+    ) -> Result<(), Unwind> {
+        let handler = match self.field(thread, "uncaughtExceptionHandler") {
+            Some(Value::Reference(Some(handler))) => Some(handler),
+            _ => self.default_uncaught_handler,
+        };
+        let name = match self.field(thread, "name") {
+            Some(Value::Reference(Some(name))) => self.string_value(name),
+            _ => None,
+        };
+        let name = name.unwrap_or_default();
+
+        let handler = match handler {
+            Some(handler) => handler,
+            None => {
+                let prefix = format!("Exception in thread \"{}\" ", name);
+                self.write_stderr(&prefix)?;
+                self.invoke_virtual(exception, "printStackTrace", "()V", &[])?;
+                return Ok(());
+            }
+        };
+        let arguments = [
+            Value::Reference(Some(thread)),
+            Value::Reference(Some(exception)),
+        ];
+        match self.invoke_virtual(
+            handler,
+            "uncaughtException",
+            "(Ljava/lang/Thread;Ljava/lang/Throwable;)V",
+            &arguments,
+        ) {
+            Err(Unwind::Throw(thrown)) => {
+                let class = self.class(self.class_of(thrown)).java_name();
+                self.write_stderr(&format!(
+                    "\nException: {} thrown from the UncaughtExceptionHandler in thread \"{}\"\n",
+                    class, name
+                ))
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    fn write_stderr(&mut self, text: &str) -> Result<(), Unwind> {
+        self.stderr
+            .write_all(text.as_bytes())
+            .map_err(|error| Unwind::Error(VmError::Io(error)))
+    }
+
+    /// Runs the started threads, and the ones they start, in order.
+    pub(crate) fn run_started_threads(&mut self) -> Result<(), Unwind> {
+        while let Some(thread) = self.started_threads.front().copied() {
+            self.run_thread(thread)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the started non-daemon threads, in order, until none is left:
+    /// the daemon threads, which might never end, are abandoned as the VM
+    /// exits without running them.
+    pub(crate) fn run_non_daemon_threads(&mut self) -> Result<(), Unwind> {
+        loop {
+            let next = self
+                .started_threads
+                .iter()
+                .copied()
+                .find(|thread| self.field(*thread, "daemon") != Some(Value::Int(1)));
+            match next {
+                Some(thread) => self.run_thread(thread)?,
+                None => {
+                    self.started_threads.clear();
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::vm::gc::GcCause;
+use crate::vm::loader::LoadedClass;
+use crate::vm::value::Value;
+
+// =============================================================================
+// BYTECODE TRACE
+// =============================================================================
+
+/// Logs every interpreted instruction of the selected methods, in the spirit
+/// of HotSpot's `-XX:+TraceBytecodes`.
+///
+/// Methods are selected by glob patterns matched against their qualified
+/// name, e.g. `com.example.Main.main`, where `*` matches any characters.
+/// Without patterns every method is traced.
+pub struct BytecodeTrace {
+    patterns: Vec<String>,
+    output: Box<dyn Write + Send>,
+}
+
+impl BytecodeTrace {
+    /// A trace of all methods written to standard error.
+    pub fn new() -> Self {
+        BytecodeTrace {
+            patterns: Vec::new(),
+            output: Box::new(io::stderr()),
+        }
+    }
+
+    /// Restricts the trace to the methods matching the pattern, in addition
+    /// to the ones matched by the previous patterns.
+    pub fn filter(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Whether the instructions of the method are traced, with the class
+    /// given by its internal name.
+    pub fn traces(&self, class: &str, method: &str) -> bool {
+        if self.patterns.is_empty() {
+            return true;
+        }
+
+        let qualified = format!("{}.{}", class.replace('/', "."), method);
+        self.patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern.as_bytes(), qualified.as_bytes()))
+    }
+
+    /// Logs an instruction about to be executed, rendered as its mnemonic
+    /// with any operands, and the operand stack it finds.
+    pub fn log(
+        &mut self,
+        class: &str,
+        method: &str,
+        descriptor: &str,
+        pc: usize,
+        instruction: &str,
+        stack: &[Value],
+    ) -> io::Result<()> {
+        let stack: Vec<String> = stack.iter().map(Value::to_string).collect();
+        writeln!(
+            self.output,
+            "{}.{}{} {:>5}: {:<16} [{}]",
+            class.replace('/', "."),
+            method,
+            descriptor,
+            pc,
+            instruction,
+            stack.join(", ")
+        )
+    }
+}
+
+impl Default for BytecodeTrace {
+    fn default() -> Self {
+        BytecodeTrace::new()
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any characters.
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        Some((expected, rest)) => text.first() == Some(expected) && glob_matches(rest, &text[1..]),
+    }
+}
+
+// =============================================================================
+// CLASS LOADING LOG
+// =============================================================================
+
+/// Logs every class as it is defined, like `-verbose:class`, and sums up the
+/// classes read from class files when the VM exits.
+pub struct ClassLoadingLog {
+    output: Box<dyn Write + Send>,
+    classes: usize,
+    bytes: usize,
+    parse_time: Duration,
+}
+
+impl ClassLoadingLog {
+    /// A log written to standard output.
+    pub fn new() -> Self {
+        ClassLoadingLog {
+            output: Box::new(io::stdout()),
+            classes: 0,
+            bytes: 0,
+            parse_time: Duration::ZERO,
+        }
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Logs a class defined from a class file.
+    pub fn loaded(&mut self, class: &LoadedClass) -> io::Result<()> {
+        self.classes += 1;
+        self.bytes += class.size;
+        self.parse_time += class.parse_time;
+        // Like HotSpot, which maps its archives of classes from "shared
+        // objects files".
+        let source = if class.archived {
+            "shared objects file".to_string()
+        } else {
+            class.source.display().to_string()
+        };
+        writeln!(
+            self.output,
+            "[Loaded {} from {} ({} bytes, parsed in {:.3} ms)]",
+            class.name.replace('/', "."),
+            source,
+            class.size,
+            class.parse_time.as_secs_f64() * 1000.0
+        )
+    }
+
+    /// Logs a class implemented by the VM itself.
+    pub fn builtin(&mut self, name: &str) -> io::Result<()> {
+        writeln!(
+            self.output,
+            "[Loaded {} from builtin]",
+            name.replace('/', ".")
+        )
+    }
+
+    /// Writes the totals of the classes loaded from class files.
+    pub fn summary(&mut self) -> io::Result<()> {
+        writeln!(
+            self.output,
+            "[Loaded {} classes, {} bytes, parsed in {:.3} ms]",
+            self.classes,
+            self.bytes,
+            self.parse_time.as_secs_f64() * 1000.0
+        )?;
+        self.output.flush()
+    }
+}
+
+impl Default for ClassLoadingLog {
+    fn default() -> Self {
+        ClassLoadingLog::new()
+    }
+}
+
+// =============================================================================
+// GC LOG
+// =============================================================================
+
+/// Logs every garbage collection like `-verbose:gc`, with its cause, the
+/// heap occupancy before and after it, and its pause time.
+pub struct GcLog {
+    output: Box<dyn Write + Send>,
+}
+
+impl GcLog {
+    /// A log written to standard output.
+    pub fn new() -> Self {
+        GcLog {
+            output: Box::new(io::stdout()),
+        }
+    }
+
+    pub fn output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    pub(crate) fn collected(
+        &mut self,
+        cause: GcCause,
+        before: usize,
+        after: usize,
+        pause: Duration,
+    ) -> io::Result<()> {
+        writeln!(
+            self.output,
+            "[GC ({}) {}K->{}K, {}K freed, {:.3} ms]",
+            cause,
+            before / 1024,
+            after / 1024,
+            before.saturating_sub(after) / 1024,
+            pause.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+impl Default for GcLog {
+    fn default() -> Self {
+        GcLog::new()
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+mod trace_tests {
+    use super::BytecodeTrace;
+
+    #[test]
+    fn test_filters() {
+        let trace = BytecodeTrace::new()
+            .filter("com.example.*.main")
+            .filter("*.toString");
+
+        assert!(trace.traces("com/example/Main", "main"));
+        assert!(trace.traces("java/lang/Object", "toString"));
+        assert!(!trace.traces("com/example/Main", "run"));
+        assert!(BytecodeTrace::new().traces("Main", "run"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use crate::class::descriptor::FieldType;
+
+// =============================================================================
+// OBJECT REFERENCES
+// =============================================================================
+
+/// A handle to an object living on the VM heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectRef(pub(crate) u32);
+
+impl ObjectRef {
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@{}", self.0)
+    }
+}
+
+// =============================================================================
+// VALUES
+// =============================================================================
+
+/// A Java value as seen by embedders, used both for method arguments and
+/// results. `boolean`, `byte`, `char` and `short` values are represented as
+/// [JValue::Int], the same way the JVM does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JValue {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Object(ObjectRef),
+    Null,
+}
+
+impl JValue {
+    pub fn as_int(&self) -> Option<i32> {
+        match *self {
+            JValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_long(&self) -> Option<i64> {
+        match *self {
+            JValue::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match *self {
+            JValue::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_double(&self) -> Option<f64> {
+        match *self {
+            JValue::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<ObjectRef> {
+        match *self {
+            JValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Whether the value can be passed where the given type is expected.
+    pub fn matches(&self, field_type: &FieldType) -> bool {
+        match self {
+            JValue::Int(_) => matches!(
+                field_type,
+                FieldType::Int
+                    | FieldType::Short
+                    | FieldType::Char
+                    | FieldType::Byte
+                    | FieldType::Boolean
+            ),
+            JValue::Long(_) => *field_type == FieldType::Long,
+            JValue::Float(_) => *field_type == FieldType::Float,
+            JValue::Double(_) => *field_type == FieldType::Double,
+            JValue::Object(_) | JValue::Null => field_type.is_reference(),
+        }
+    }
+}
+
+/// A value in a local variable slot or on the operand stack of the
+/// interpreter. Unlike [JValue] it can represent the unusable second slot of
+/// `long` and `double` locals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Reference(Option<ObjectRef>),
+    /// The index of the instruction a `jsr` returns to, stored in a local
+    /// by the subroutine until its `ret`.
+    ReturnAddress(u32),
+    /// The second slot of a wide local, or a local not yet assigned.
+    Top,
+}
+
+impl Value {
+    pub const NULL: Value = Value::Reference(None);
+
+    /// The default value of fields and array elements of the type.
+    pub fn default_for(field_type: &FieldType) -> Value {
+        match field_type {
+            FieldType::Long => Value::Long(0),
+            FieldType::Float => Value::Float(0.0),
+            FieldType::Double => Value::Double(0.0),
+            FieldType::Object(_) | FieldType::Array(_) => Value::NULL,
+            _ => Value::Int(0),
+        }
+    }
+
+    /// Whether the value is a category 2 computational type (JVMS 2.11.1).
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+}
+
+impl fmt::Display for Value {
+    /// Formats the value with a suffix telling its type, e.g. `5L` or `@12`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Long(value) => write!(f, "{}L", value),
+            Value::Float(value) => write!(f, "{:?}f", value),
+            Value::Double(value) => write!(f, "{:?}d", value),
+            Value::Reference(Some(object)) => write!(f, "{}", object),
+            Value::Reference(None) => write!(f, "null"),
+            Value::ReturnAddress(ip) => write!(f, "ret{}", ip),
+            Value::Top => write!(f, "top"),
+        }
+    }
+}
+
+impl From<JValue> for Value {
+    fn from(value: JValue) -> Self {
+        match value {
+            JValue::Int(value) => Value::Int(value),
+            JValue::Long(value) => Value::Long(value),
+            JValue::Float(value) => Value::Float(value),
+            JValue::Double(value) => Value::Double(value),
+            JValue::Object(object) => Value::Reference(Some(object)),
+            JValue::Null => Value::NULL,
+        }
+    }
+}
+
+impl From<Value> for JValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Int(value) => JValue::Int(value),
+            Value::Long(value) => JValue::Long(value),
+            Value::Float(value) => JValue::Float(value),
+            Value::Double(value) => JValue::Double(value),
+            Value::Reference(Some(object)) => JValue::Object(object),
+            Value::Reference(None) | Value::ReturnAddress(_) | Value::Top => JValue::Null,
+        }
+    }
+}
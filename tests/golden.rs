@@ -0,0 +1,151 @@
+//! Runs the programs of `res/golden` and compares their output with the
+//! expected one, recorded from a reference JVM.
+//!
+//! Every `<Name>.java` is a program with a `main` method, whose standard
+//! output is stored in `<Name>.out` and, when not empty, its standard error
+//! in `<Name>.err`. The optional `<Name>.args` holds the arguments of
+//! `main`, one per line. The resources the programs read, like the
+//! provider-configuration files of `META-INF/services`, sit next to them.
+//! The classes are checked in, compiled by
+//!
+//! ```text
+//! javac --release 8 -g:source,lines -d res/golden res/golden/*.java
+//! ```
+//!
+//! so running the tests needs no JDK. Setting `UPDATE_GOLDEN=1` rewrites the
+//! expected output with bvm's own, to be reviewed before being committed.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bvm::packaging::classpath::ClassPath;
+use bvm::vm::value::JValue;
+use bvm::vm::{Vm, VmError};
+
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl SharedOutput {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn golden_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/golden")
+}
+
+/// Names of the programs, sorted.
+fn programs(root: &Path) -> Vec<String> {
+    let mut programs: Vec<_> = fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "java")
+        })
+        .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+        .collect();
+    programs.sort();
+    programs
+}
+
+fn read_optional(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+/// Runs the program like `bvm <name> <args>` would, returning its standard
+/// output and error.
+fn run(root: &Path, name: &str, optimize: bool) -> (String, String) {
+    let stdout = SharedOutput::default();
+    let stderr = SharedOutput::default();
+    let class_path = ClassPath::parse(root.to_str().unwrap()).unwrap();
+    let mut vm = Vm::builder()
+        .class_path(class_path)
+        .stdout(stdout.clone())
+        .stderr(stderr.clone())
+        .optimize_bytecode(optimize)
+        .build()
+        .unwrap();
+
+    let arguments = read_optional(&root.join(format!("{}.args", name)));
+    let arguments: Vec<&str> = arguments.lines().collect();
+    let arguments = vm.new_string_array(&arguments).unwrap();
+    let result = vm.invoke_static(
+        name,
+        "main",
+        "([Ljava/lang/String;)V",
+        &[JValue::Object(arguments)],
+    );
+    match result {
+        Ok(_) => vm.shutdown().unwrap(),
+        Err(VmError::Exception(exception)) => {
+            write!(stderr.clone(), "Exception in thread \"main\" ").unwrap();
+            vm.invoke_method(exception.object, "printStackTrace", "()V", &[])
+                .unwrap();
+            vm.shutdown().unwrap();
+        }
+        Err(VmError::Exit(_)) => vm.flush().unwrap(),
+        Err(error) => panic!("{} failed: {}", name, error),
+    }
+
+    (stdout.contents(), stderr.contents())
+}
+
+#[test]
+fn test_golden_output() {
+    let root = golden_root();
+    let update = env::var_os("UPDATE_GOLDEN").is_some_and(|value| value == "1");
+
+    let mut mismatches = Vec::new();
+    for name in programs(&root) {
+        let (stdout, stderr) = run(&root, &name, false);
+        for (extension, actual) in [("out", stdout), ("err", stderr)] {
+            let path = root.join(format!("{}.{}", name, extension));
+            if update {
+                if actual.is_empty() {
+                    let _ = fs::remove_file(&path);
+                } else {
+                    fs::write(&path, &actual).unwrap();
+                }
+            } else if read_optional(&path) != actual {
+                mismatches.push(format!(
+                    "{}.{}:\n--- expected\n{}--- actual\n{}",
+                    name,
+                    extension,
+                    read_optional(&path),
+                    actual
+                ));
+            }
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Output differs from the golden files, rerun with UPDATE_GOLDEN=1 to accept it\n\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn test_golden_output_optimized() {
+    let root = golden_root();
+    for name in programs(&root) {
+        let (stdout, stderr) = run(&root, &name, true);
+        assert_eq!(stdout, read_optional(&root.join(format!("{}.out", name))));
+        assert_eq!(stderr, read_optional(&root.join(format!("{}.err", name))));
+    }
+}
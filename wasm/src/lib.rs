@@ -0,0 +1,161 @@
+//! JavaScript bindings of bvm's class file parser, for inspecting class
+//! files in a browser, e.g. uploaded by the user.
+//!
+//! Build with `wasm-pack build wasm --target web`, then
+//!
+//! ```js
+//! import init, { ClassFile } from "./pkg/bvm_wasm.js";
+//!
+//! await init();
+//! const bytes = new Uint8Array(await file.arrayBuffer());
+//! const classFile = new ClassFile(bytes);
+//! for (const [index, method] of classFile.methods().entries()) {
+//!     console.log(method.name + method.descriptor, classFile.disassemble(index));
+//! }
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use bvm::class::attributes::{Attribute, CodeAttribute};
+use bvm::class::instruction;
+use bvm::class::{Class, MethodInfo};
+
+/// A parsed class file.
+#[wasm_bindgen]
+pub struct ClassFile {
+    class: Class,
+}
+
+/// A field or method of a class file.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Member {
+    #[wasm_bindgen(readonly, js_name = accessFlags)]
+    pub access_flags: u16,
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub descriptor: String,
+}
+
+#[wasm_bindgen]
+impl ClassFile {
+    /// Parses the bytes of a class file, throwing why they are not one.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<ClassFile, JsError> {
+        let class = Class::parse_bytes(bytes)?;
+        Ok(ClassFile { class })
+    }
+
+    /// The internal name of the class, e.g. `java/lang/String`.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> Result<String, JsError> {
+        Ok(self.class.name()?.to_string())
+    }
+
+    /// The internal name of the superclass, `undefined` for
+    /// `java/lang/Object`.
+    #[wasm_bindgen(getter, js_name = superName)]
+    pub fn super_name(&self) -> Result<Option<String>, JsError> {
+        Ok(self.class.super_class_name()?.map(str::to_string))
+    }
+
+    /// The internal names of the interfaces the class implements.
+    pub fn interfaces(&self) -> Result<Vec<String>, JsError> {
+        let pool = &self.class.constant_pool;
+        let mut interfaces = Vec::new();
+        for interface in &self.class.interfaces {
+            interfaces.push(pool.get_class_name(interface.interface_index)?.to_string());
+        }
+        Ok(interfaces)
+    }
+
+    /// The class file version, e.g. `61.0`.
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> String {
+        format!("{}.{}", self.class.major_version, self.class.minor_version)
+    }
+
+    #[wasm_bindgen(getter, js_name = accessFlags)]
+    pub fn access_flags(&self) -> u16 {
+        self.class.access_flags.bits()
+    }
+
+    /// The constant pool, a line per constant like `javap -v` prints it,
+    /// e.g. `#7 Methodref java/io/PrintStream.println:(I)V`.
+    pub fn constants(&self) -> Vec<String> {
+        let pool = &self.class.constant_pool;
+        pool.iter()
+            .map(|(index, constant)| {
+                let value = pool.describe_entry(index as u16);
+                format!("#{} {} {}", index, constant.kind(), value)
+            })
+            .collect()
+    }
+
+    pub fn fields(&self) -> Result<Vec<Member>, JsError> {
+        let pool = &self.class.constant_pool;
+        let mut fields = Vec::new();
+        for field in &self.class.fields {
+            fields.push(Member {
+                access_flags: field.access_flags.bits(),
+                name: pool.get_utf8(field.name_index)?.to_string(),
+                descriptor: pool.get_utf8(field.descriptor_index)?.to_string(),
+            });
+        }
+        Ok(fields)
+    }
+
+    pub fn methods(&self) -> Result<Vec<Member>, JsError> {
+        let pool = &self.class.constant_pool;
+        let mut methods = Vec::new();
+        for method in &self.class.methods {
+            methods.push(Member {
+                access_flags: method.access_flags.bits(),
+                name: pool.get_utf8(method.name_index)?.to_string(),
+                descriptor: pool.get_utf8(method.descriptor_index)?.to_string(),
+            });
+        }
+        Ok(methods)
+    }
+
+    /// The instructions of the method at the index of [ClassFile::methods],
+    /// a line per instruction prefixed by its pc, like `4: invokevirtual
+    /// java/io/PrintStream.println:(I)V`, none for an abstract or native
+    /// method.
+    pub fn disassemble(&self, method: usize) -> Result<Vec<String>, JsError> {
+        let method = self
+            .class
+            .methods
+            .get(method)
+            .ok_or_else(|| JsError::new(&format!("No method at index {}", method)))?;
+        let code = match code(method) {
+            Some(code) => code,
+            None => return Ok(Vec::new()),
+        };
+        let decoded = instruction::decode(&code.code);
+        Ok(decoded
+            .pcs
+            .iter()
+            .enumerate()
+            .map(|(index, pc)| {
+                let instruction = instruction::disassemble(
+                    &code.code,
+                    &decoded,
+                    index,
+                    &self.class.constant_pool,
+                );
+                format!("{}: {}", pc, instruction)
+            })
+            .collect())
+    }
+}
+
+fn code(method: &MethodInfo) -> Option<&CodeAttribute> {
+    method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        })
+}